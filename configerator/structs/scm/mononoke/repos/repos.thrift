@@ -529,6 +529,28 @@ struct RawFilestoreParams {
     2: i32 concurrency,
 } (rust.exhaustive)
 
+// A rule to rewrite (or drop) small repo paths matching a pattern.
+// Rules are tried in order and the first matching rule wins, taking
+// precedence over `mapping` and `default_action`.
+struct RawCommitSyncPathRule {
+    // "glob" or "regex"
+    1: string kind,
+    // Pattern matched against the whole small repo path.
+    // Globs support `*` (any characters within a path component),
+    // `**` (any number of path components) and `?` (a single character).
+    2: string pattern,
+    // Replacement for the matching small repo path. Absent means the
+    // path is not synced. For globs each wildcard in the replacement is
+    // substituted with the text matched by the corresponding wildcard in
+    // the pattern; for regexes `$N` and `${name}` refer to capture groups.
+    3: optional string replacement,
+    // Regex rules only: pattern and replacement to map large repo paths
+    // back to small repo paths. Required for regex rewrites, as they are
+    // not invertible in general. Glob rewrites are inverted automatically.
+    4: optional string reverse_pattern,
+    5: optional string reverse_replacement,
+} (rust.exhaustive)
+
 struct RawCommitSyncSmallRepoConfig {
     1: i32 repoid,
     2: string default_action,
@@ -536,6 +558,7 @@ struct RawCommitSyncSmallRepoConfig {
     4: string bookmark_prefix,
     5: map<string, string> mapping,
     6: string direction,
+    7: optional list<RawCommitSyncPathRule> path_rules,
 } (rust.exhaustive)
 
 struct RawCommitSyncConfig {
@@ -35,6 +35,7 @@ use filenodes::{
 };
 use filestore::{ArcFilestoreConfig, FilestoreConfig};
 use futures::stream::BoxStream;
+use lfs_locks::{ArcLfsLocks, LfsLocks, SqlLfsLocksStore};
 use memblob::Memblob;
 use mercurial_mutation::{ArcHgMutationStore, SqlHgMutationStoreBuilder};
 use mercurial_types::{HgChangesetId, HgFileNodeId};
@@ -248,6 +249,13 @@ impl BenchmarkRepoFactory {
         ))
     }
 
+    pub fn lfs_locks(&self, repo_identity: &ArcRepoIdentity) -> Result<ArcLfsLocks> {
+        Ok(Arc::new(LfsLocks::new(
+            repo_identity.id(),
+            SqlLfsLocksStore::with_sqlite_in_memory()?,
+        )))
+    }
+
     pub fn segmented_changelog(&self) -> ArcSegmentedChangelog {
         Arc::new(DisabledSegmentedChangelog::new())
     }
@@ -32,6 +32,7 @@ use futures::{
     stream::FuturesUnordered,
     FutureExt, Stream, TryStreamExt,
 };
+use lfs_locks::{ArcLfsLocks, LfsLocks};
 use mercurial_mutation::{ArcHgMutationStore, HgMutationStore};
 use metaconfig_types::DerivedDataConfig;
 use mononoke_types::{
@@ -118,6 +119,9 @@ pub struct BlobRepoInner {
 
     #[facet]
     pub repo_derived_data: RepoDerivedData,
+
+    #[facet]
+    pub lfs_locks: LfsLocks,
 }
 
 #[facet::container]
@@ -139,6 +143,7 @@ pub struct BlobRepo {
         dyn Filenodes,
         dyn HgMutationStore,
         RepoDerivedData,
+        LfsLocks,
     )]
     inner: Arc<BlobRepoInner>,
 }
@@ -174,6 +179,11 @@ impl BlobRepo {
         &self.inner.hg_mutation_store
     }
 
+    #[inline]
+    pub fn lfs_locks(&self) -> &ArcLfsLocks {
+        &self.inner.lfs_locks
+    }
+
     /// Get Bonsai changesets for Mercurial heads, which we approximate as Publishing Bonsai
     /// Bookmarks. Those will be served from cache, so they might be stale.
     pub fn get_bonsai_heads_maybe_stale(
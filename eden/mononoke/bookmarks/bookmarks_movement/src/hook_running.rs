@@ -35,13 +35,13 @@ pub async fn is_admin_bypass(
     }
 
     if !hook_manager
-        .get_admin_perm_checker()
-        .is_member(ctx.metadata().identities())
+        .is_bypass_permitted(ctx)
         .await
         .with_context(|| "Error when checking BYPASS_ALL_HOOKS permissions")?
     {
         return Err(anyhow!(
-            "In order to use BYPASS_ALL_HOOKS pushvar one needs to be member of the scm group."
+            "In order to use BYPASS_ALL_HOOKS pushvar one needs to be member of the scm group \
+            or have bypass_hooks permission in the repo ACL."
         ));
     }
 
@@ -0,0 +1,86 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use clap::ArgMatches;
+use context::CoreContext;
+use fbinit::FacebookInit;
+use permission_checker::{AclFile, AclFileSource, PermissionCheckerBuilder};
+use reloader::{Loader, Reloader};
+use slog::{info, Logger};
+use tokio::runtime::Runtime;
+
+use super::app::ACL_FILE_ARG;
+
+const ACL_FILE_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+struct AclFileLoader {
+    logger: Logger,
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+#[async_trait]
+impl Loader<AclFile> for AclFileLoader {
+    async fn load(&mut self) -> Result<Option<AclFile>> {
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to stat ACL file {}", self.path.display()))?;
+        if self.last_modified == Some(modified) {
+            return Ok(None);
+        }
+
+        let acl_file = AclFile::from_path(&self.path)?;
+        if self.last_modified.is_some() {
+            info!(self.logger, "Reloaded ACL file {}", self.path.display());
+        }
+        self.last_modified = Some(modified);
+        Ok(Some(acl_file))
+    }
+}
+
+struct ReloadingAclFile(Reloader<AclFile>);
+
+impl AclFileSource for ReloadingAclFile {
+    fn acl_file(&self) -> Arc<AclFile> {
+        self.0.load_full()
+    }
+}
+
+/// If an ACL file was given, load it and use it for all ACL checks in this
+/// process, reloading it whenever it changes.
+pub(crate) fn init_acl_file(
+    fb: FacebookInit,
+    matches: &ArgMatches<'_>,
+    runtime: &Runtime,
+    logger: Logger,
+) -> Result<()> {
+    let path = match matches.value_of(ACL_FILE_ARG) {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(()),
+    };
+
+    let ctx = CoreContext::new_with_logger(fb, logger.clone());
+    let loader = AclFileLoader {
+        logger,
+        path,
+        last_modified: None,
+    };
+    let reloader = runtime.block_on(Reloader::reload_periodically(
+        ctx,
+        || ACL_FILE_RELOAD_INTERVAL,
+        loader,
+    ))?;
+
+    PermissionCheckerBuilder::set_acl_file_source(Arc::new(ReloadingAclFile(reloader)))
+}
@@ -23,6 +23,7 @@ use super::cache::{add_cachelib_args, CachelibSettings};
 use super::matches::MononokeMatches;
 
 pub const CONFIG_PATH: &str = "mononoke-config-path";
+pub const ACL_FILE_ARG: &str = "acl-file";
 pub const REPO_ID: &str = "repo-id";
 pub const REPO_NAME: &str = "repo-name";
 pub const SOURCE_REPO_GROUP: &str = "source-repo";
@@ -474,6 +475,13 @@ impl MononokeAppBuilder {
                     .long(LOCAL_CONFIGERATOR_PATH_ARG)
                     .takes_value(true)
                     .help("local path to fetch configerator configs from, instead of normal configerator"),
+            )
+            .arg(
+                Arg::with_name(ACL_FILE_ARG)
+                    .long(ACL_FILE_ARG)
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help("Path to a JSON file defining repo and tier ACLs, which is reloaded when it changes"),
             );
         }
 
@@ -46,6 +46,7 @@ pub type Normal = rand_distr::Normal<f64>;
 use crate::helpers::create_runtime;
 use crate::log;
 
+use super::acl_file::init_acl_file;
 use super::parse_config_spec_to_path;
 use super::{
     app::{
@@ -120,6 +121,9 @@ impl<'a> MononokeMatches<'a> {
         init_tunables(&matches, &config_store, logger.clone())
             .context("Failed to initialize tunables")?;
 
+        init_acl_file(fb, &matches, &runtime, logger.clone())
+            .context("Failed to initialize ACL file")?;
+
         let mysql_options =
             parse_mysql_options(&matches, &app_data).context("Failed to parse MySQL options")?;
         let blobstore_options = parse_blobstore_options(&matches, &app_data, &arg_types)
@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+mod acl_file;
 mod app;
 mod cache;
 mod matches;
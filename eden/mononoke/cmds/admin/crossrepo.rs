@@ -20,12 +20,18 @@ use cmdlib::{
 use context::CoreContext;
 use cross_repo_sync::{
     create_commit_syncer_lease,
+    dry_run::{
+        dry_run_sync_commits, DryRunCommitReport, WorkingCopyComparison, WorkingCopyDifference,
+    },
     types::{Large, Small},
     validation::{self, BookmarkDiff},
     CommitSyncContext, CommitSyncRepos, CommitSyncer, CHANGE_XREPO_MAPPING_EXTRA,
 };
 use fbinit::FacebookInit;
-use futures::{compat::Future01CompatExt, stream, try_join, TryFutureExt};
+use futures::{
+    compat::{Future01CompatExt, Stream01CompatExt},
+    stream, try_join, TryFutureExt, TryStreamExt,
+};
 use itertools::Itertools;
 use live_commit_sync_config::{CfgrLiveCommitSyncConfig, LiveCommitSyncConfig};
 use maplit::{btreemap, hashmap, hashset};
@@ -39,6 +45,7 @@ use mononoke_types::{
 use mutable_counters::MutableCounters;
 use mutable_counters::SqlMutableCounters;
 use pushrebase::{do_pushrebase_bonsai, FAILUPUSHREBASE_EXTRA};
+use revset::RangeNodeStream;
 use slog::{info, warn, Logger};
 use sorted_vector_map::sorted_vector_map;
 use std::collections::BTreeMap;
@@ -56,6 +63,9 @@ const AUTHOR_ARG: &str = "author";
 const ONCALL_ARG: &str = "oncall";
 const DUMP_MAPPING_LARGE_REPO_PATH_ARG: &str = "dump-mapping-large-repo-path";
 const MAP_SUBCOMMAND: &str = "map";
+const DRY_RUN_SUBCOMMAND: &str = "dry-run";
+const FROM_ARG: &str = "from";
+const TO_ARG: &str = "to";
 const PREPARE_ROLLOUT_SUBCOMMAND: &str = "prepare-rollout";
 const PUSHREDIRECTION_SUBCOMMAND: &str = "pushredirection";
 const VERIFY_WC_SUBCOMMAND: &str = "verify-wc";
@@ -113,6 +123,28 @@ pub async fn subcommand_crossrepo<'a>(
             let hash = sub_sub_m.value_of(HASH_ARG).unwrap().to_owned();
             subcommand_map(ctx, commit_syncer, hash).await
         }
+        (DRY_RUN_SUBCOMMAND, Some(sub_sub_m)) => {
+            let (source_repo, target_repo, mapping) =
+                get_source_target_repos_and_mapping(fb, logger, matches).await?;
+
+            let common_config =
+                live_commit_sync_config.get_common_config(source_repo.get_repoid())?;
+            let commit_sync_repos = CommitSyncRepos::new(source_repo, target_repo, &common_config)?;
+            let live_commit_sync_config: Arc<dyn LiveCommitSyncConfig> =
+                Arc::new(live_commit_sync_config);
+
+            let caching = matches.caching();
+            let x_repo_syncer_lease = create_commit_syncer_lease(ctx.fb, caching)?;
+
+            let commit_syncer = CommitSyncer::new(
+                &ctx,
+                mapping,
+                commit_sync_repos,
+                live_commit_sync_config,
+                x_repo_syncer_lease,
+            );
+            subcommand_dry_run(ctx, commit_syncer, sub_sub_m).await
+        }
         (VERIFY_WC_SUBCOMMAND, Some(sub_sub_m)) => {
             let (source_repo, target_repo, mapping) =
                 get_source_target_repos_and_mapping(fb, logger, matches).await?;
@@ -966,6 +998,103 @@ async fn subcommand_map(
     Ok(())
 }
 
+async fn subcommand_dry_run(
+    ctx: CoreContext,
+    commit_syncer: CommitSyncer<SqlSyncedCommitMapping>,
+    sub_m: &ArgMatches<'_>,
+) -> Result<(), SubcommandError> {
+    let source_repo = commit_syncer.get_source_repo();
+    let from = sub_m
+        .value_of(FROM_ARG)
+        .ok_or_else(|| format_err!("{} is not specified", FROM_ARG))?;
+    let to = sub_m
+        .value_of(TO_ARG)
+        .ok_or_else(|| format_err!("{} is not specified", TO_ARG))?;
+    let version = CommitSyncConfigVersion(
+        sub_m
+            .value_of(ARG_VERSION_NAME)
+            .ok_or_else(|| format_err!("{} is not specified", ARG_VERSION_NAME))?
+            .to_string(),
+    );
+
+    let (from, to) = try_join!(
+        helpers::csid_resolve(&ctx, source_repo, from),
+        helpers::csid_resolve(&ctx, source_repo, to),
+    )?;
+    let mut cs_ids: Vec<_> =
+        RangeNodeStream::new(ctx.clone(), source_repo.get_changeset_fetcher(), from, to)
+            .compat()
+            .try_collect()
+            .await?;
+    // Report from ancestors to descendants
+    cs_ids.reverse();
+
+    let reports = dry_run_sync_commits(&ctx, &commit_syncer, cs_ids, &version).await?;
+    let mut diverged = 0;
+    for report in reports {
+        print_dry_run_report(&report);
+        if !report.is_equivalent() {
+            diverged += 1;
+        }
+    }
+    info!(
+        ctx.logger(),
+        "{} commits would not produce the same working copy as the existing synced commits",
+        diverged
+    );
+
+    Ok(())
+}
+
+fn print_dry_run_report(report: &DryRunCommitReport) {
+    println!("{}", report.source_cs_id);
+    if !report.would_sync {
+        println!("  would be rewritten into nothingness");
+    }
+    for (source_path, target_path) in &report.path_mappings {
+        println!("  {} -> {}", source_path, target_path);
+    }
+    for path in &report.dropped_paths {
+        println!("  {} dropped", path);
+    }
+    match &report.existing {
+        Some((cs_id, version)) => println!("  currently synced as {} using {}", cs_id, version),
+        None => println!("  not currently synced"),
+    }
+    match &report.working_copy {
+        WorkingCopyComparison::NotSynced => {}
+        WorkingCopyComparison::NotDerived(cs_id) => {
+            println!(
+                "  fsnodes not derived for {}, working copy not compared",
+                cs_id
+            )
+        }
+        WorkingCopyComparison::Compared(differences) if differences.is_empty() => {
+            println!("  working copy is equivalent")
+        }
+        WorkingCopyComparison::Compared(differences) => {
+            for difference in differences {
+                match difference {
+                    WorkingCopyDifference::MissingInExisting(path) => {
+                        println!("  {} missing from existing synced commit", path)
+                    }
+                    WorkingCopyDifference::ExtraInExisting(path) => {
+                        println!("  {} only in existing synced commit", path)
+                    }
+                    WorkingCopyDifference::Different {
+                        path,
+                        candidate,
+                        existing,
+                    } => println!(
+                        "  {} differs: {:?} (candidate) vs {:?} (existing)",
+                        path, candidate, existing
+                    ),
+                }
+            }
+        }
+    }
+}
+
 async fn subcommand_verify_bookmarks(
     ctx: CoreContext,
     source_repo: BlobRepo,
@@ -1166,6 +1295,34 @@ pub fn build_subcommand<'a, 'b>() -> App<'a, 'b> {
                 .help("bonsai changeset hash to map"),
         );
 
+    let dry_run_subcommand = SubCommand::with_name(DRY_RUN_SUBCOMMAND)
+        .about(
+            "preview syncing a range of commits with a given mapping version, \
+            and report how the result differs from the existing synced commits. \
+            Nothing is written to either repo",
+        )
+        .arg(
+            Arg::with_name(FROM_ARG)
+                .long(FROM_ARG)
+                .required(true)
+                .takes_value(true)
+                .help("oldest commit of the range in the source repo"),
+        )
+        .arg(
+            Arg::with_name(TO_ARG)
+                .long(TO_ARG)
+                .required(true)
+                .takes_value(true)
+                .help("newest commit of the range in the source repo"),
+        )
+        .arg(
+            Arg::with_name(ARG_VERSION_NAME)
+                .long(ARG_VERSION_NAME)
+                .required(true)
+                .takes_value(true)
+                .help("candidate mapping version to sync with"),
+        );
+
     let verify_wc_subcommand = SubCommand::with_name(VERIFY_WC_SUBCOMMAND)
         .about("verify working copy")
         .arg(
@@ -1342,6 +1499,7 @@ pub fn build_subcommand<'a, 'b>() -> App<'a, 'b> {
 
     SubCommand::with_name(CROSSREPO)
         .subcommand(map_subcommand)
+        .subcommand(dry_run_subcommand)
         .subcommand(verify_wc_subcommand)
         .subcommand(verify_bookmarks_subcommand)
         .subcommand(commit_sync_config_subcommand)
@@ -1600,6 +1758,7 @@ mod test {
                 small_repo.get_repoid() => SmallRepoCommitSyncConfig {
                     default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
                     map: hashmap! { },
+                    path_rules: vec![],
                 },
             },
             version_name: current_version.clone(),
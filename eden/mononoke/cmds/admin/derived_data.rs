@@ -9,12 +9,13 @@ use anyhow::{anyhow, Error};
 use blobrepo::BlobRepo;
 use blobrepo_hg::BlobRepoHg;
 use blobstore::Loadable;
+use bookmarks::BookmarkName;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use cmdlib::{
     args::{self, MononokeMatches},
     helpers::csid_resolve,
 };
-use context::CoreContext;
+use context::{CoreContext, SessionContainer};
 use derived_data::BonsaiDerived;
 use derived_data_manager::BonsaiDerivable;
 use derived_data_utils::{
@@ -27,8 +28,15 @@ use futures::{
     stream, StreamExt, TryStreamExt,
 };
 use manifest::ManifestOps;
+use maplit::hashmap;
 use mercurial_derived_data::MappedHgChangesetId;
+use mononoke_api::{
+    BookmarkUpdateDelay, DerivedDataLag, Mononoke, MononokeApiEnvironment,
+    WarmBookmarksCacheDerivedData,
+};
 use mononoke_types::{ChangesetId, ContentId, FileType, MPath};
+use repo_factory::RepoFactory;
+use serde_json::{json, to_string_pretty};
 use skeleton_manifest::RootSkeletonManifestId;
 use slog::{info, Logger};
 use std::{
@@ -43,11 +51,14 @@ pub const DERIVED_DATA: &str = "derived-data";
 const SUBCOMMAND_EXISTS: &str = "exists";
 const SUBCOMMAND_COUNT_UNDERIVED: &str = "count-underived";
 const SUBCOMMAND_VERIFY_MANIFESTS: &str = "verify-manifests";
+const SUBCOMMAND_LAG: &str = "lag";
 
 const ARG_HASH_OR_BOOKMARK: &str = "hash-or-bookmark";
 const ARG_TYPE: &str = "type";
 const ARG_IF_DERIVED: &str = "if-derived";
 const ARG_BACKFILL: &str = "backfill";
+const ARG_BOOKMARK: &str = "bookmark";
+const ARG_JSON: &str = "json";
 
 const MANIFEST_DERIVED_DATA_TYPES: &[&str] = &[
     RootFsnodeId::NAME,
@@ -128,6 +139,30 @@ pub fn build_subcommand<'a, 'b>() -> App<'a, 'b> {
                         .long(ARG_IF_DERIVED),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name(SUBCOMMAND_LAG)
+                .about("report how far derived data lags behind bookmarks")
+                .arg(
+                    Arg::with_name(ARG_TYPE)
+                        .help("types of derived data (defaults to all enabled types)")
+                        .long(ARG_TYPE)
+                        .takes_value(true)
+                        .multiple(true)
+                        .possible_values(POSSIBLE_DERIVED_TYPES),
+                )
+                .arg(
+                    Arg::with_name(ARG_BOOKMARK)
+                        .help("bookmarks to check (defaults to all publishing bookmarks)")
+                        .long(ARG_BOOKMARK)
+                        .takes_value(true)
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name(ARG_JSON)
+                        .help("print the report as json")
+                        .long(ARG_JSON),
+                ),
+        )
 }
 
 pub async fn subcommand_derived_data<'a>(
@@ -136,6 +171,10 @@ pub async fn subcommand_derived_data<'a>(
     matches: &'a MononokeMatches<'_>,
     sub_m: &'a ArgMatches<'_>,
 ) -> Result<(), SubcommandError> {
+    if let (SUBCOMMAND_LAG, Some(arg_matches)) = sub_m.subcommand() {
+        return derived_data_lag(fb, logger, matches, arg_matches).await;
+    }
+
     let ctx = CoreContext::new_with_logger(fb, logger.clone());
     let repo = args::open_repo(fb, &logger, &matches).await?;
 
@@ -276,6 +315,86 @@ async fn count_underived(
     Ok(())
 }
 
+async fn derived_data_lag<'a>(
+    fb: FacebookInit,
+    logger: Logger,
+    matches: &'a MononokeMatches<'_>,
+    sub_m: &'a ArgMatches<'_>,
+) -> Result<(), SubcommandError> {
+    let derived_data_types: Option<Vec<String>> = sub_m
+        .values_of(ARG_TYPE)
+        .map(|types| types.map(|ty| ty.to_string()).collect());
+    let bookmarks = sub_m
+        .values_of(ARG_BOOKMARK)
+        .map(|bookmarks| {
+            bookmarks
+                .map(BookmarkName::new)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+    let json_flag = sub_m.is_present(ARG_JSON);
+
+    let config_store = matches.config_store();
+    let (repo_name, repo_config) = args::get_config(config_store, matches)?;
+    let common_config = args::load_common_config(config_store, &matches)?;
+    let repo_configs = args::RepoConfigs {
+        repos: hashmap! {
+            repo_name.clone() => repo_config
+        },
+        common: common_config,
+    };
+    let env = MononokeApiEnvironment {
+        repo_factory: RepoFactory::new(matches.environment().clone(), &repo_configs.common),
+        disabled_hooks: Default::default(),
+        warm_bookmarks_cache_derived_data: WarmBookmarksCacheDerivedData::None,
+        warm_bookmarks_cache_delay: BookmarkUpdateDelay::Disallow,
+        skiplist_enabled: true,
+        warm_bookmarks_cache_enabled: false,
+    };
+    let mononoke = Mononoke::new(&env, repo_configs).await?;
+    let session = SessionContainer::new_with_defaults(fb);
+    let ctx = session.new_context(logger, matches.scuba_sample_builder());
+    let repo = mononoke
+        .repo_bypass_acl_check(ctx, &repo_name)
+        .await
+        .map_err(Error::from)?
+        .ok_or_else(|| anyhow!("repo {} not found", repo_name))?;
+
+    let lags = repo
+        .derived_data_lag(derived_data_types, bookmarks)
+        .await
+        .map_err(Error::from)?;
+
+    if json_flag {
+        let answer: Vec<_> = lags.iter().map(lag_to_json).collect();
+        println!("{}", to_string_pretty(&answer).map_err(Error::from)?);
+    } else {
+        for lag in lags {
+            match (lag.oldest_underived, lag.oldest_underived_date) {
+                (Some(oldest), Some(date)) => println!(
+                    "{} {}: {} generations behind, oldest underived {} ({})",
+                    lag.derived_data_type, lag.bookmark, lag.underived_generations, oldest, date,
+                ),
+                _ => println!("{} {}: derived", lag.derived_data_type, lag.bookmark),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn lag_to_json(lag: &DerivedDataLag) -> serde_json::Value {
+    json!({
+        "type": lag.derived_data_type,
+        "bookmark": lag.bookmark,
+        "head": lag.head.to_string(),
+        "derived": lag.is_derived(),
+        "underived_generations": lag.underived_generations,
+        "oldest_underived": lag.oldest_underived.map(|cs_id| cs_id.to_string()),
+        "oldest_underived_timestamp": lag.oldest_underived_date.map(|date| date.timestamp()),
+    })
+}
+
 async fn verify_manifests(
     ctx: CoreContext,
     repo: BlobRepo,
@@ -674,6 +674,7 @@ async fn backsync_change_mapping(fb: FacebookInit) -> Result<(), Error> {
                     MPath::new("current_prefix").unwrap(),
                 ),
                 map: hashmap! { },
+                path_rules: vec![],
 
             },
         },
@@ -691,6 +692,7 @@ async fn backsync_change_mapping(fb: FacebookInit) -> Result<(), Error> {
                     MPath::new("new_prefix").unwrap(),
                 ),
                 map: hashmap! { },
+                path_rules: vec![],
 
             },
         },
@@ -1193,6 +1195,7 @@ impl MoverType {
             Noop => SmallRepoCommitSyncConfig {
                 default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
                 map: hashmap! {},
+                path_rules: vec![],
             },
             Except(files) => {
                 let mut map = hashmap! {};
@@ -1205,6 +1208,7 @@ impl MoverType {
                 SmallRepoCommitSyncConfig {
                     default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
                     map,
+                    path_rules: vec![],
                 }
             }
             Only(path) => SmallRepoCommitSyncConfig {
@@ -1214,6 +1218,7 @@ impl MoverType {
                 map: hashmap! {
                     MPath::new(path).unwrap() => MPath::new(path).unwrap(),
                 },
+                path_rules: vec![],
             },
         }
     }
@@ -1567,6 +1572,7 @@ async fn init_merged_repos(
                         MPath::new(format!("smallrepo{}", small_repo.get_repoid().id())).unwrap(),
                     ),
                     map: hashmap! { },
+                    path_rules: vec![],
 
                 },
             },
@@ -0,0 +1,253 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Preview what syncing a range of commits with a given
+//! `CommitSyncConfigVersion` would do, without writing anything.
+
+use anyhow::{format_err, Error};
+use blobrepo::BlobRepo;
+use blobstore::Loadable;
+use context::CoreContext;
+use derived_data::BonsaiDerived;
+use fsnodes::RootFsnodeId;
+use futures::TryStreamExt;
+use manifest::ManifestOps;
+use mercurial_types::FileType;
+use metaconfig_types::CommitSyncConfigVersion;
+use mononoke_types::{ChangesetId, ContentId, MPath};
+use movers::Mover;
+use std::collections::{BTreeSet, HashMap};
+use synced_commit_mapping::SyncedCommitMapping;
+
+use crate::validation::{keep_movable_paths, move_all_paths};
+use crate::{rewrite_commit, CommitSyncOutcome, CommitSyncer};
+
+/// A file whose state differs between the working copy that the candidate
+/// version would produce and the working copy of the existing synced commit.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum WorkingCopyDifference {
+    /// The candidate version would produce this path, but the existing
+    /// synced commit doesn't have it.
+    MissingInExisting(MPath),
+    /// The existing synced commit has this path (and the candidate version
+    /// maps it back to the source repo), but the candidate version wouldn't
+    /// produce it.
+    ExtraInExisting(MPath),
+    /// Both have this path, but with different file types or contents.
+    Different {
+        path: MPath,
+        candidate: (FileType, ContentId),
+        existing: (FileType, ContentId),
+    },
+}
+
+/// Result of comparing working copies for a single source commit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WorkingCopyComparison {
+    /// The source commit hasn't been synced yet (or is not a sync
+    /// candidate), so there is nothing to compare against.
+    NotSynced,
+    /// Fsnodes are not derived for this commit.  Deriving them would write
+    /// to the repo, so the comparison was skipped.
+    NotDerived(ChangesetId),
+    /// The working copies were compared.  An empty list means that they
+    /// are equivalent.
+    Compared(Vec<WorkingCopyDifference>),
+}
+
+/// What syncing a single source commit with the candidate version would do.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DryRunCommitReport {
+    pub source_cs_id: ChangesetId,
+    /// `false` if the commit would be rewritten into nothingness, i.e. it
+    /// isn't a merge and all of its changes are dropped by the mover.
+    pub would_sync: bool,
+    /// Paths changed by the source commit and where they would be written
+    /// in the target repo.
+    pub path_mappings: Vec<(MPath, MPath)>,
+    /// Paths changed by the source commit that would not be synced.
+    pub dropped_paths: Vec<MPath>,
+    /// The commit this source commit is currently synced as, and the
+    /// version that was used to sync it.
+    pub existing: Option<(ChangesetId, CommitSyncConfigVersion)>,
+    pub working_copy: WorkingCopyComparison,
+}
+
+impl DryRunCommitReport {
+    /// Returns true if syncing with the candidate version would produce the
+    /// same working copy as the existing synced commit.
+    pub fn is_equivalent(&self) -> bool {
+        match &self.working_copy {
+            WorkingCopyComparison::Compared(differences) => differences.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Rewrite each of `source_cs_ids` in memory with the movers of `version`,
+/// and compare the result with the commits they are currently synced as.
+///
+/// Nothing is written to either repo or to the synced commit mapping.
+/// Parents of the rewritten commits are not remapped, as the report only
+/// concerns paths and working copies.
+pub async fn dry_run_sync_commits<M: SyncedCommitMapping + Clone + 'static>(
+    ctx: &CoreContext,
+    commit_syncer: &CommitSyncer<M>,
+    source_cs_ids: Vec<ChangesetId>,
+    version: &CommitSyncConfigVersion,
+) -> Result<Vec<DryRunCommitReport>, Error> {
+    if !commit_syncer.version_exists(version).await? {
+        return Err(format_err!("{} version does not exist", version));
+    }
+    let mover = commit_syncer.get_mover_by_version(version).await?;
+    let reverse_mover = commit_syncer.get_reverse_mover_by_version(version).await?;
+
+    let mut reports = vec![];
+    for source_cs_id in source_cs_ids {
+        let report = dry_run_sync_commit(
+            ctx,
+            commit_syncer,
+            source_cs_id,
+            mover.clone(),
+            &reverse_mover,
+        )
+        .await?;
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+async fn dry_run_sync_commit<M: SyncedCommitMapping + Clone + 'static>(
+    ctx: &CoreContext,
+    commit_syncer: &CommitSyncer<M>,
+    source_cs_id: ChangesetId,
+    mover: Mover,
+    reverse_mover: &Mover,
+) -> Result<DryRunCommitReport, Error> {
+    let source_repo = commit_syncer.get_source_repo();
+    let target_repo = commit_syncer.get_target_repo();
+    let bcs = source_cs_id.load(ctx, source_repo.blobstore()).await?;
+
+    let mut path_mappings = vec![];
+    let mut dropped_paths = vec![];
+    for (path, _) in bcs.file_changes() {
+        match mover(path)? {
+            Some(moved) => path_mappings.push((path.clone(), moved)),
+            None => dropped_paths.push(path.clone()),
+        }
+    }
+
+    let remapped_parents: HashMap<_, _> = bcs.parents().map(|p| (p, p)).collect();
+    let rewritten = rewrite_commit(
+        ctx,
+        bcs.into_mut(),
+        &remapped_parents,
+        mover.clone(),
+        source_repo.clone(),
+    )
+    .await?;
+
+    let existing = match commit_syncer
+        .get_commit_sync_outcome(ctx, source_cs_id)
+        .await?
+    {
+        Some(CommitSyncOutcome::RewrittenAs(cs_id, version))
+        | Some(CommitSyncOutcome::EquivalentWorkingCopyAncestor(cs_id, version)) => {
+            Some((cs_id, version))
+        }
+        Some(CommitSyncOutcome::NotSyncCandidate(_)) | None => None,
+    };
+
+    let working_copy = match &existing {
+        Some((target_cs_id, _)) => {
+            compare_working_copies(
+                ctx,
+                source_repo,
+                target_repo,
+                source_cs_id,
+                *target_cs_id,
+                &mover,
+                reverse_mover,
+            )
+            .await?
+        }
+        None => WorkingCopyComparison::NotSynced,
+    };
+
+    Ok(DryRunCommitReport {
+        source_cs_id,
+        would_sync: rewritten.is_some(),
+        path_mappings,
+        dropped_paths,
+        existing,
+        working_copy,
+    })
+}
+
+async fn compare_working_copies(
+    ctx: &CoreContext,
+    source_repo: &BlobRepo,
+    target_repo: &BlobRepo,
+    source_cs_id: ChangesetId,
+    target_cs_id: ChangesetId,
+    mover: &Mover,
+    reverse_mover: &Mover,
+) -> Result<WorkingCopyComparison, Error> {
+    let source_entries = match fetch_content_ids_and_types(ctx, source_repo, source_cs_id).await? {
+        Some(entries) => move_all_paths(&entries, mover)?,
+        None => return Ok(WorkingCopyComparison::NotDerived(source_cs_id)),
+    };
+    let target_entries = match fetch_content_ids_and_types(ctx, target_repo, target_cs_id).await? {
+        Some(entries) => keep_movable_paths(&entries, reverse_mover)?,
+        None => return Ok(WorkingCopyComparison::NotDerived(target_cs_id)),
+    };
+
+    let mut differences = BTreeSet::new();
+    for (path, candidate) in &source_entries {
+        match target_entries.get(path) {
+            Some(existing) if existing != candidate => {
+                differences.insert(WorkingCopyDifference::Different {
+                    path: path.clone(),
+                    candidate: *candidate,
+                    existing: *existing,
+                });
+            }
+            Some(_) => {}
+            None => {
+                differences.insert(WorkingCopyDifference::MissingInExisting(path.clone()));
+            }
+        }
+    }
+    for path in target_entries.keys() {
+        if !source_entries.contains_key(path) {
+            differences.insert(WorkingCopyDifference::ExtraInExisting(path.clone()));
+        }
+    }
+
+    Ok(WorkingCopyComparison::Compared(
+        differences.into_iter().collect(),
+    ))
+}
+
+/// Like `validation::list_content_ids_and_types`, but never derives fsnodes.
+async fn fetch_content_ids_and_types(
+    ctx: &CoreContext,
+    repo: &BlobRepo,
+    cs_id: ChangesetId,
+) -> Result<Option<HashMap<MPath, (FileType, ContentId)>>, Error> {
+    let root_fsnode_id = match RootFsnodeId::fetch_derived(ctx, repo, &cs_id).await? {
+        Some(root_fsnode_id) => root_fsnode_id,
+        None => return Ok(None),
+    };
+    let entries = root_fsnode_id
+        .fsnode_id()
+        .list_leaf_entries(ctx.clone(), repo.get_blobstore())
+        .map_ok(|(path, fsnode)| (path, (*fsnode.file_type(), *fsnode.content_id())))
+        .try_collect()
+        .await?;
+    Ok(Some(entries))
+}
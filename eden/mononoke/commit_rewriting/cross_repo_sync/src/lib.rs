@@ -61,6 +61,7 @@ use types::{Source, Target};
 
 mod commit_sync_data_provider;
 pub mod commit_sync_outcome;
+pub mod dry_run;
 mod pushrebase_hook;
 mod reporting;
 mod sync_config_version_utils;
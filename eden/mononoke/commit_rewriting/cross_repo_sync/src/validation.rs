@@ -643,7 +643,7 @@ pub fn move_all_paths<V: Clone>(
 }
 
 // Drop all paths which `mover` rewrites into `None`
-fn keep_movable_paths<V: Clone>(
+pub(crate) fn keep_movable_paths<V: Clone>(
     path_to_values: &HashMap<MPath, V>,
     mover: &Mover,
 ) -> Result<HashMap<MPath, V>, Error> {
@@ -1176,6 +1176,7 @@ mod test {
                 small_repo.get_repoid() => SmallRepoCommitSyncConfig {
                     default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
                     map: hashmap! { },
+                    path_rules: vec![],
 
                 },
             },
@@ -26,19 +26,24 @@ use cacheblob::InProcessLease;
 use cloned::cloned;
 use context::CoreContext;
 use cross_repo_sync::{
-    update_mapping_with_version, validation::verify_working_copy, CommitSyncContext,
-    CommitSyncDataProvider, CommitSyncOutcome, ErrorKind,
+    dry_run::{dry_run_sync_commits, WorkingCopyComparison, WorkingCopyDifference},
+    update_mapping_with_version,
+    validation::verify_working_copy,
+    CommitSyncContext, CommitSyncDataProvider, CommitSyncOutcome, ErrorKind,
 };
 use cross_repo_sync_test_utils::rebase_root_on_master;
+use derived_data::BonsaiDerived;
 use fixtures::{linear, many_files_dirs};
+use fsnodes::RootFsnodeId;
 use futures::{future::join_all, FutureExt, TryStreamExt};
 use live_commit_sync_config::{TestLiveCommitSyncConfig, TestLiveCommitSyncConfigSource};
 use manifest::ManifestOps;
 use maplit::{btreemap, hashmap};
 use mercurial_types::HgChangesetId;
 use metaconfig_types::{
-    CommitSyncConfig, CommitSyncConfigVersion, CommonCommitSyncConfig,
-    DefaultSmallToLargeCommitSyncPathAction, SmallRepoCommitSyncConfig, SmallRepoPermanentConfig,
+    CommitSyncConfig, CommitSyncConfigVersion, CommitSyncPathRule, CommitSyncPathRuleAction,
+    CommonCommitSyncConfig, DefaultSmallToLargeCommitSyncPathAction, SmallRepoCommitSyncConfig,
+    SmallRepoPermanentConfig,
 };
 use mononoke_types::{
     BlobstoreValue, BonsaiChangesetMut, ChangesetId, DateTime, FileChange, FileContents, FileType,
@@ -46,6 +51,7 @@ use mononoke_types::{
 };
 use pushrebase::PushrebaseError;
 use reachabilityindex::LeastCommonAncestorsHint;
+use regex::Regex;
 use skiplist::SkiplistIndex;
 use sorted_vector_map::{sorted_vector_map, SortedVectorMap};
 use sql_construct::SqlConstruct;
@@ -268,6 +274,7 @@ fn create_commit_sync_config(
     let small_repo_config = SmallRepoCommitSyncConfig {
         default_action: DefaultSmallToLargeCommitSyncPathAction::PrependPrefix(MPath::new(prefix)?),
         map: hashmap! {},
+        path_rules: vec![],
     };
 
     Ok(CommitSyncConfig {
@@ -786,6 +793,7 @@ async fn test_sync_implicit_deletes(fb: FacebookInit) -> Result<(), Error> {
             MPath::new("dir1/subdir1/subsubdir1")? => MPath::new("prefix1")?,
             MPath::new("dir1")? => MPath::new("prefix2")?,
         },
+        path_rules: vec![],
     };
 
     let commit_sync_config = CommitSyncConfig {
@@ -892,6 +900,247 @@ async fn test_sync_implicit_deletes(fb: FacebookInit) -> Result<(), Error> {
     Ok(())
 }
 
+#[fbinit::test]
+async fn test_sync_with_path_rules(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let (small_repo, megarepo, mapping) = prepare_repos_and_mapping().unwrap();
+
+    let mut commit_syncer = create_small_to_large_commit_syncer(
+        &ctx,
+        small_repo.clone(),
+        megarepo.clone(),
+        "prefix",
+        mapping.clone(),
+    )?;
+
+    // Equivalent of the config-level glob rules
+    //   "**/generated/**" => do not sync
+    //   "*/BUCK" => "build/*/BUCK"
+    let small_repo_config = SmallRepoCommitSyncConfig {
+        default_action: DefaultSmallToLargeCommitSyncPathAction::PrependPrefix(mpath("prefix")),
+        map: hashmap! {},
+        path_rules: vec![
+            CommitSyncPathRule {
+                pattern: Regex::new("^((?:[^/]+/)*)generated/(.*)$")?.into(),
+                action: CommitSyncPathRuleAction::DoNotSync,
+            },
+            CommitSyncPathRule {
+                pattern: Regex::new("^([^/]*)/BUCK$")?.into(),
+                action: CommitSyncPathRuleAction::Rewrite {
+                    replacement: "build/${1}/BUCK".to_string(),
+                    reverse_pattern: Regex::new("^build/([^/]*)/BUCK$")?.into(),
+                    reverse_replacement: "${1}/BUCK".to_string(),
+                },
+            },
+        ],
+    };
+    let commit_sync_config = CommitSyncConfig {
+        large_repo_id: megarepo.get_repoid(),
+        common_pushrebase_bookmarks: vec![],
+        small_repos: hashmap! {
+            small_repo.get_repoid() => small_repo_config,
+        },
+        version_name: version_name_with_small_repo(),
+    };
+    let common_config = CommonCommitSyncConfig {
+        common_pushrebase_bookmarks: vec![],
+        small_repos: hashmap! {
+            small_repo.get_repoid() => SmallRepoPermanentConfig {
+                bookmark_prefix: AsciiString::new(),
+            }
+        },
+        large_repo_id: megarepo.get_repoid(),
+    };
+    let (sync_config, source) = TestLiveCommitSyncConfig::new_with_source();
+    source.add_config(commit_sync_config);
+    source.add_common_config(common_config);
+    commit_syncer.commit_sync_data_provider = CommitSyncDataProvider::Live(Arc::new(sync_config));
+
+    // Insert a fake mapping entry between the root commits, so that syncs succeed
+    let megarepo_initial_bcs_id = create_initial_commit(ctx.clone(), &megarepo).await;
+    let small_repo_initial_bcs_id = create_initial_commit(ctx.clone(), &small_repo).await;
+    let entry = SyncedCommitMappingEntry::new(
+        megarepo.get_repoid(),
+        megarepo_initial_bcs_id,
+        small_repo.get_repoid(),
+        small_repo_initial_bcs_id,
+        version_name_with_small_repo(),
+        commit_syncer.get_source_repo_type(),
+    );
+    mapping.add(&ctx, entry).await?;
+
+    let small_repo_bcs_id =
+        CreateCommitContext::new(&ctx, &small_repo, vec![small_repo_initial_bcs_id])
+            .add_file("foo/BUCK", "buck")
+            .add_file("foo/bar/BUCK", "nested buck")
+            .add_file("generated/a", "generated")
+            .add_file("foo/generated/b", "generated")
+            .add_file("foo/src.rs", "source")
+            .commit()
+            .await?;
+    let megarepo_bcs_id = sync_to_master(ctx.clone(), &commit_syncer, small_repo_bcs_id)
+        .await?
+        .expect("Unexpectedly rewritten into nothingness");
+
+    let megarepo_bcs = megarepo_bcs_id.load(&ctx, megarepo.blobstore()).await?;
+    let paths: Vec<_> = megarepo_bcs
+        .file_changes()
+        .map(|(path, _)| path.clone())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            mpath("build/foo/BUCK"),
+            mpath("prefix/foo/bar/BUCK"),
+            mpath("prefix/foo/src.rs"),
+        ]
+    );
+
+    // The large repo commit maps back to the small repo paths
+    let large_to_small = commit_syncer
+        .get_reverse_mover_by_version(&version_name_with_small_repo())
+        .await?;
+    assert_eq!(
+        large_to_small(&mpath("build/foo/BUCK"))?,
+        Some(mpath("foo/BUCK"))
+    );
+    assert_eq!(
+        large_to_small(&mpath("prefix/foo/bar/BUCK"))?,
+        Some(mpath("foo/bar/BUCK"))
+    );
+    assert_eq!(large_to_small(&mpath("prefix/foo/BUCK"))?, None);
+
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_dry_run_sync_commits(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let (small_repo, megarepo, mapping) = prepare_repos_and_mapping()?;
+
+    let mut commit_syncer = create_small_to_large_commit_syncer(
+        &ctx,
+        small_repo.clone(),
+        megarepo.clone(),
+        "prefix",
+        mapping.clone(),
+    )?;
+
+    // The candidate version moves everything to "other" and drops "b"
+    let mut candidate_config =
+        create_commit_sync_config(small_repo.get_repoid(), megarepo.get_repoid(), "other")?;
+    candidate_config.version_name = CommitSyncConfigVersion("CANDIDATE".to_string());
+    let candidate_small_repo_config = candidate_config
+        .small_repos
+        .get_mut(&small_repo.get_repoid())
+        .unwrap();
+    candidate_small_repo_config.path_rules = vec![CommitSyncPathRule {
+        pattern: Regex::new("^b$")?.into(),
+        action: CommitSyncPathRuleAction::DoNotSync,
+    }];
+    let common_config = CommonCommitSyncConfig {
+        common_pushrebase_bookmarks: vec![],
+        small_repos: hashmap! {
+            small_repo.get_repoid() => SmallRepoPermanentConfig {
+                bookmark_prefix: AsciiString::new(),
+            }
+        },
+        large_repo_id: megarepo.get_repoid(),
+    };
+    let (sync_config, source) = TestLiveCommitSyncConfig::new_with_source();
+    source.add_config(create_commit_sync_config(
+        small_repo.get_repoid(),
+        megarepo.get_repoid(),
+        "prefix",
+    )?);
+    source.add_config(candidate_config);
+    source.add_common_config(common_config);
+    commit_syncer.commit_sync_data_provider = CommitSyncDataProvider::Live(Arc::new(sync_config));
+
+    create_initial_commit(ctx.clone(), &megarepo).await;
+    let small_repo_bcs_id = CreateCommitContext::new_root(&ctx, &small_repo)
+        .add_file("a", "a")
+        .add_file("b", "b")
+        .commit()
+        .await?;
+    let megarepo_bcs_id =
+        rebase_root_on_master(ctx.clone(), &commit_syncer, small_repo_bcs_id).await?;
+
+    // Nothing is derived, and the dry run must not derive anything
+    let reports = dry_run_sync_commits(
+        &ctx,
+        &commit_syncer,
+        vec![small_repo_bcs_id],
+        &version_name_with_small_repo(),
+    )
+    .await?;
+    assert_eq!(
+        reports[0].working_copy,
+        WorkingCopyComparison::NotDerived(small_repo_bcs_id)
+    );
+    assert!(!RootFsnodeId::is_derived(&ctx, &small_repo, &small_repo_bcs_id).await?);
+
+    RootFsnodeId::derive(&ctx, &small_repo, small_repo_bcs_id).await?;
+    RootFsnodeId::derive(&ctx, &megarepo, megarepo_bcs_id).await?;
+
+    // The version that was used to sync the commit reproduces it
+    let reports = dry_run_sync_commits(
+        &ctx,
+        &commit_syncer,
+        vec![small_repo_bcs_id],
+        &version_name_with_small_repo(),
+    )
+    .await?;
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].would_sync);
+    assert_eq!(
+        reports[0].path_mappings,
+        vec![
+            (mpath("a"), mpath("prefix/a")),
+            (mpath("b"), mpath("prefix/b")),
+        ]
+    );
+    assert!(reports[0].dropped_paths.is_empty());
+    assert_eq!(
+        reports[0].existing,
+        Some((megarepo_bcs_id, version_name_with_small_repo()))
+    );
+    assert!(reports[0].is_equivalent());
+
+    // The candidate version diverges from the existing synced commit
+    let reports = dry_run_sync_commits(
+        &ctx,
+        &commit_syncer,
+        vec![small_repo_bcs_id],
+        &CommitSyncConfigVersion("CANDIDATE".to_string()),
+    )
+    .await?;
+    assert!(reports[0].would_sync);
+    assert_eq!(
+        reports[0].path_mappings,
+        vec![(mpath("a"), mpath("other/a"))]
+    );
+    assert_eq!(reports[0].dropped_paths, vec![mpath("b")]);
+    assert_eq!(
+        reports[0].working_copy,
+        WorkingCopyComparison::Compared(vec![WorkingCopyDifference::MissingInExisting(mpath(
+            "other/a"
+        ))])
+    );
+
+    // Unknown versions are rejected
+    assert!(dry_run_sync_commits(
+        &ctx,
+        &commit_syncer,
+        vec![small_repo_bcs_id],
+        &CommitSyncConfigVersion("UNKNOWN".to_string()),
+    )
+    .await
+    .is_err());
+
+    Ok(())
+}
+
 async fn update_linear_1_file(ctx: CoreContext, repo: &BlobRepo) -> ChangesetId {
     let bookmark = BookmarkName::new("master").unwrap();
     let p1 = repo
@@ -1751,6 +2000,7 @@ async fn prepare_commit_syncer_with_mapping_change(
         map: hashmap! {
             MPath::new("tools")? => MPath::new("tools")?,
         },
+        path_rules: vec![],
     };
 
     let old_version = CommitSyncConfigVersion("TEST_VERSION_NAME".to_string());
@@ -1828,6 +2078,7 @@ fn get_merge_sync_data_provider(
     let small_repo_config = SmallRepoCommitSyncConfig {
         default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
         map: hashmap! {},
+        path_rules: vec![],
     };
     let commit_sync_config_v1 = CommitSyncConfig {
         large_repo_id,
@@ -2157,6 +2408,7 @@ async fn test_no_accidental_preserved_roots(
         let small_repo_config = SmallRepoCommitSyncConfig {
             default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
             map: hashmap! {},
+            path_rules: vec![],
         };
         let commit_sync_config = CommitSyncConfig {
             large_repo_id: commit_syncer.get_large_repo().get_repoid(),
@@ -2271,6 +2523,7 @@ async fn test_not_sync_candidate_if_mapping_does_not_have_small_repo(
             first_small_repo_id => SmallRepoCommitSyncConfig {
                 default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
                 map: hashmap! {},
+                path_rules: vec![],
             },
         },
         version_name: noop_version_first_small_repo.clone(),
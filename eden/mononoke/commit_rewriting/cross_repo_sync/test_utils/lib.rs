@@ -311,6 +311,7 @@ pub fn base_commit_sync_config(large_repo: &BlobRepo, small_repo: &BlobRepo) ->
             MPath::new("prefix").unwrap(),
         ),
         map: hashmap! {},
+        path_rules: vec![],
     };
     CommitSyncConfig {
         large_repo_id: large_repo.get_repoid(),
@@ -370,6 +371,7 @@ fn get_small_repo_sync_config_noop() -> SmallRepoCommitSyncConfig {
     SmallRepoCommitSyncConfig {
         default_action: DefaultSmallToLargeCommitSyncPathAction::Preserve,
         map: hashmap! {},
+        path_rules: vec![],
     }
 }
 
@@ -379,6 +381,7 @@ fn get_small_repo_sync_config_1() -> SmallRepoCommitSyncConfig {
             MPath::new("prefix").unwrap(),
         ),
         map: hashmap! {},
+        path_rules: vec![],
     }
 }
 
@@ -390,5 +393,6 @@ fn get_small_repo_sync_config_2() -> SmallRepoCommitSyncConfig {
         map: hashmap! {
             MPath::new("special").unwrap() => MPath::new("special").unwrap(),
         },
+        path_rules: vec![],
     }
 }
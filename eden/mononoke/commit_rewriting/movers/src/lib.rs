@@ -10,8 +10,8 @@
 use anyhow::{Context, Error, Result};
 use mercurial_types::{MPath, MPathElement};
 use metaconfig_types::{
-    CommitSyncConfig, CommitSyncDirection, DefaultSmallToLargeCommitSyncPathAction,
-    SmallRepoCommitSyncConfig,
+    CommitSyncConfig, CommitSyncDirection, CommitSyncPathRuleAction, ComparableRegex,
+    DefaultSmallToLargeCommitSyncPathAction, SmallRepoCommitSyncConfig,
 };
 use mononoke_types::RepositoryId;
 use std::collections::{HashMap, HashSet};
@@ -28,6 +28,8 @@ pub enum ErrorKind {
     SmallRepoNotFound(RepositoryId),
     #[error("Provided map is not prefix-free (e.g. {0:?} and {1:?})")]
     NonPrefixFreeMap(MPath, MPath),
+    #[error("Cannot apply path rule {0:?} to {1:?}")]
+    PathRuleFailure(String, MPath),
 }
 
 /// A function to modify paths during repo sync
@@ -188,6 +190,99 @@ pub fn mover_factory(
     }))
 }
 
+/// A rule, matching the whole path against a regex
+#[derive(Debug, Clone)]
+pub struct PathRule {
+    pattern: ComparableRegex,
+    // `Some(replacement)` if the path should be rewritten using this
+    // replacement template, `None` if the path should not be synced
+    replacement: Option<String>,
+}
+
+impl PathRule {
+    /// A rule rewriting matching paths with a regex replacement template
+    pub fn rewrite(pattern: ComparableRegex, replacement: String) -> Self {
+        Self {
+            pattern,
+            replacement: Some(replacement),
+        }
+    }
+
+    /// A rule preventing matching paths from being synced
+    pub fn do_not_sync(pattern: ComparableRegex) -> Self {
+        Self {
+            pattern,
+            replacement: None,
+        }
+    }
+}
+
+/// Create a `Mover`, which applies the first matching rule from an ordered
+/// list of rules, and falls back to `fallback` if none of them match
+pub fn path_rules_mover(rules: Vec<PathRule>, fallback: Mover) -> Mover {
+    if rules.is_empty() {
+        return fallback;
+    }
+
+    Arc::new(move |source_path: &MPath| {
+        // Rules are expressed in terms of strings, so they can never
+        // match a non-utf8 path
+        let path = match String::from_utf8(source_path.to_vec()) {
+            Ok(path) => path,
+            Err(_) => return fallback(source_path),
+        };
+        for rule in rules.iter() {
+            if let Some(captures) = rule.pattern.captures(&path) {
+                return match &rule.replacement {
+                    Some(replacement) => {
+                        let mut new_path = String::new();
+                        captures.expand(replacement, &mut new_path);
+                        MPath::new(new_path).map(Some).with_context(|| {
+                            ErrorKind::PathRuleFailure(
+                                rule.pattern.as_str().to_string(),
+                                source_path.clone(),
+                            )
+                        })
+                    }
+                    None => Ok(None),
+                };
+            }
+        }
+        fallback(source_path)
+    })
+}
+
+fn small_to_large_path_rules(small_repo_config: &SmallRepoCommitSyncConfig) -> Vec<PathRule> {
+    small_repo_config
+        .path_rules
+        .iter()
+        .map(|rule| match &rule.action {
+            CommitSyncPathRuleAction::Rewrite { replacement, .. } => {
+                PathRule::rewrite(rule.pattern.clone(), replacement.clone())
+            }
+            CommitSyncPathRuleAction::DoNotSync => PathRule::do_not_sync(rule.pattern.clone()),
+        })
+        .collect()
+}
+
+// Rules which don't sync anything have no reverse, as the paths they
+// match never make it into the large repo
+fn large_to_small_path_rules(
+    small_repo_config: &SmallRepoCommitSyncConfig,
+) -> impl Iterator<Item = (&ComparableRegex, &String)> {
+    small_repo_config
+        .path_rules
+        .iter()
+        .filter_map(|rule| match &rule.action {
+            CommitSyncPathRuleAction::Rewrite {
+                reverse_pattern,
+                reverse_replacement,
+                ..
+            } => Some((reverse_pattern, reverse_replacement)),
+            CommitSyncPathRuleAction::DoNotSync => None,
+        })
+}
+
 // Given a full sync config and a small repo id,
 // split it into this repo the rest
 fn get_small_repo_and_others_from_config(
@@ -222,7 +317,10 @@ pub fn get_small_to_large_mover(
         .map(|(k, v)| (k, PrefixAction::Change(v)))
         .collect();
 
-    mover_factory(prefix_map, default_action)
+    Ok(path_rules_mover(
+        small_to_large_path_rules(source_repo_config),
+        mover_factory(prefix_map, default_action)?,
+    ))
 }
 
 /// Get a mover for a large-to-small repo sync
@@ -308,7 +406,19 @@ pub fn get_large_to_small_mover(
     // If this path is equal to the original path then we consider that default_large_to_small_mover
     // returns correct path, otherwise we return None (i.e. a path from large repo doesn't remap
     // to a path from small repo).
-    let default_large_to_small_mover = mover_factory(prefix_map, default_action)?;
+    //
+    // Path rules are reversed in the same way: this repo's rules are
+    // applied in reverse, and paths produced by other repos' rules are not
+    // synced (the check above takes care of paths produced by both).
+    let path_rules = large_to_small_path_rules(target_repo_config)
+        .map(|(pattern, replacement)| PathRule::rewrite(pattern.clone(), replacement.clone()))
+        .chain(other_repo_configs.iter().flat_map(|small_repo_config| {
+            large_to_small_path_rules(small_repo_config)
+                .map(|(pattern, _)| PathRule::do_not_sync(pattern.clone()))
+        }))
+        .collect();
+    let default_large_to_small_mover =
+        path_rules_mover(path_rules, mover_factory(prefix_map, default_action)?);
 
     let small_to_large_mover = get_small_to_large_mover(commit_sync_config, small_repo_id)?;
     Ok(Arc::new(move |path: &MPath| -> Result<Option<MPath>> {
@@ -348,7 +458,8 @@ pub fn get_movers(
 mod test {
     use super::*;
     use maplit::hashmap;
-    use metaconfig_types::CommitSyncConfigVersion;
+    use metaconfig_types::{CommitSyncConfigVersion, CommitSyncPathRule};
+    use regex::Regex;
 
     fn mp(s: &'static str) -> MPath {
         MPath::new(s).unwrap()
@@ -472,6 +583,7 @@ mod test {
             map: hashmap! {
                 mp("preserved2") => mp("repo1-rest/preserved2"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -483,6 +595,7 @@ mod test {
                 mp("sub1") => mp("repo2-rest/sub1"),
                 mp("sub2") => mp("repo2-rest/sub2"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -639,6 +752,7 @@ mod test {
                     map: hashmap! {
                         mp("preserved2") => mp("preserved2"),
                     },
+                    path_rules: vec![],
                 },
                 RepositoryId::new(2) => SmallRepoCommitSyncConfig {
                     default_action: DefaultSmallToLargeCommitSyncPathAction::PrependPrefix(mp("shifted2")),
@@ -647,6 +761,7 @@ mod test {
                         mp("sub1") => mp("repo2-rest/sub1"),
                         mp("sub2") => mp("repo2-rest/sub2"),
                     },
+                    path_rules: vec![],
                 },
             },
             version_name: CommitSyncConfigVersion("TEST_VERSION_NAME".to_string()),
@@ -731,6 +846,7 @@ mod test {
                 mp("sub1") => mp("repo2-rest/sub1"),
                 mp("sub1/preserved") => mp("sub1/preserved"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -780,6 +896,7 @@ mod test {
                 mp("preserved") => mp("preserved"),
                 mp("preserved/excluded") => mp("shifted/preserved/excluded"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -815,4 +932,93 @@ mod test {
 
         Ok(())
     }
+
+    fn re(s: &'static str) -> ComparableRegex {
+        ComparableRegex::new(Regex::new(s).unwrap())
+    }
+
+    #[test]
+    fn test_path_rules_mover() -> Result<()> {
+        let rules = vec![
+            PathRule::do_not_sync(re("^((?:[^/]+/)*)generated/(.*)$")),
+            PathRule::rewrite(re("^([^/]*)/BUCK$"), "build/${1}/BUCK".to_string()),
+            PathRule::rewrite(re("^docs/(?P<name>.*)$"), "site/${name}".to_string()),
+        ];
+        let mover = path_rules_mover(rules, mover_factory(hashmap! {}, DefaultAction::Preserve)?);
+
+        assert_eq!(mover(&mp("generated/a"))?, None);
+        assert_eq!(mover(&mp("dir/generated/sub/a"))?, None);
+        assert_eq!(mover(&mp("foo/BUCK"))?, Some(mp("build/foo/BUCK")));
+        assert_eq!(mover(&mp("foo/bar/BUCK"))?, Some(mp("foo/bar/BUCK")));
+        assert_eq!(mover(&mp("docs/a/b.md"))?, Some(mp("site/a/b.md")));
+        assert_eq!(mover(&mp("other/file"))?, Some(mp("other/file")));
+
+        // Rules which produce an invalid path fail the sync
+        let rules = vec![PathRule::rewrite(re("^flatten/.*$"), "".to_string())];
+        let mover = path_rules_mover(rules, mover_factory(hashmap! {}, DefaultAction::Preserve)?);
+        assert!(mover(&mp("flatten/a")).is_err());
+
+        Ok(())
+    }
+
+    fn get_small_repo_sync_config_with_rules() -> SmallRepoCommitSyncConfig {
+        SmallRepoCommitSyncConfig {
+            default_action: DefaultSmallToLargeCommitSyncPathAction::PrependPrefix(mp("shifted")),
+            map: hashmap! {
+                mp("preserved") => mp("preserved"),
+            },
+            path_rules: vec![
+                CommitSyncPathRule {
+                    pattern: re("^((?:[^/]+/)*)generated/(.*)$"),
+                    action: CommitSyncPathRuleAction::DoNotSync,
+                },
+                CommitSyncPathRule {
+                    pattern: re("^([^/]*)/BUCK$"),
+                    action: CommitSyncPathRuleAction::Rewrite {
+                        replacement: "build/${1}/BUCK".to_string(),
+                        reverse_pattern: re("^build/([^/]*)/BUCK$"),
+                        reverse_replacement: "${1}/BUCK".to_string(),
+                    },
+                },
+            ],
+        }
+    }
+
+    fn get_large_repo_sync_config_with_rules() -> CommitSyncConfig {
+        CommitSyncConfig {
+            large_repo_id: RepositoryId::new(2),
+            common_pushrebase_bookmarks: vec![],
+            small_repos: hashmap! {
+                RepositoryId::new(1) => get_small_repo_sync_config_with_rules(),
+            },
+            version_name: CommitSyncConfigVersion("TEST_VERSION_NAME".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_get_movers_with_path_rules() -> Result<()> {
+        let config = get_large_repo_sync_config_with_rules();
+        let small_to_large = get_small_to_large_mover(&config, RepositoryId::new(1))?;
+        let large_to_small = get_large_to_small_mover(&config, RepositoryId::new(1))?;
+
+        // Rules take precedence over the prefix map and the default action
+        assert_eq!(small_to_large(&mp("preserved/generated/f"))?, None);
+        assert_eq!(small_to_large(&mp("foo/BUCK"))?, Some(mp("build/foo/BUCK")));
+        assert_eq!(small_to_large(&mp("foo/f"))?, Some(mp("shifted/foo/f")));
+        assert_eq!(
+            small_to_large(&mp("preserved/BUCK"))?,
+            Some(mp("build/preserved/BUCK"))
+        );
+
+        // Rewrites are reversed
+        assert_eq!(large_to_small(&mp("build/foo/BUCK"))?, Some(mp("foo/BUCK")));
+        assert_eq!(large_to_small(&mp("shifted/foo/f"))?, Some(mp("foo/f")));
+        // `shifted/foo/BUCK` would be remapped to `foo/BUCK`, but that path
+        // is synced to `build/foo/BUCK`, so it can't come from the small repo
+        assert_eq!(large_to_small(&mp("shifted/foo/BUCK"))?, None);
+        // Paths which are never synced are not synced back either
+        assert_eq!(large_to_small(&mp("shifted/generated/f"))?, None);
+
+        Ok(())
+    }
 }
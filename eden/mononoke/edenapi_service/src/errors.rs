@@ -10,6 +10,7 @@ use std::fmt::Display;
 use anyhow::Error;
 use thiserror::Error;
 
+use edenapi_types::CommitId;
 use gotham_ext::error::HttpError;
 use mononoke_api::{ChangesetId, MononokeError};
 use types::{HgId, Key};
@@ -64,6 +65,8 @@ pub enum ErrorKind {
     CommitRevlogDataRequestFailed,
     #[error("HgId not found: {0}")]
     HgIdNotFound(HgId),
+    #[error("Commit not found: {0:?}")]
+    CommitNotFound(CommitId),
     #[error("Failed to fetch HgId for Bonsai Changeset ID {0}")]
     BonsaiChangesetToHgIdError(ChangesetId),
     #[error(
@@ -93,6 +96,7 @@ impl MononokeErrorExt for MononokeError {
         (match self {
             InvalidRequest(_) => HttpError::e400,
             PermissionDenied { .. } => HttpError::e403,
+            PathPermissionDenied { .. } => HttpError::e403,
             ServicePermissionDenied { .. } => HttpError::e403,
             ServiceRestricted { .. } => HttpError::e403,
             NotAvailable { .. } => HttpError::e503,
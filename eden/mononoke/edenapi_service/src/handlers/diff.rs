@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::{future, stream, try_join, StreamExt};
+use maplit::btreeset;
+
+use edenapi_types::{CommitDiffEntry, CommitDiffRequest, CommitId, DiffStat, FileDiff};
+use mercurial_types::{HgChangesetId, HgNodeHash};
+use mononoke_api::{
+    unified_diff, ChangesetContext, ChangesetDiffItem, ChangesetPathDiffContext,
+    ChangesetSpecifier, CopyInfo, UnifiedDiffMode,
+};
+use mononoke_api_hg::HgRepoContext;
+use mononoke_types::ChangesetId;
+
+use crate::errors::ErrorKind;
+use crate::utils::{to_hg_path, to_mononoke_path};
+
+use super::{EdenApiHandler, EdenApiMethod, HandlerError, HandlerResult};
+
+/// XXX: This number was chosen arbitrarily.
+const MAX_CONCURRENT_FILE_DIFFS_PER_REQUEST: usize = 10;
+
+pub struct CommitDiffHandler;
+
+/// Resolve a commit from the request. Unknown commits are the client's
+/// mistake and are reported as 404 rather than as a server error.
+async fn resolve_commit(
+    repo: &HgRepoContext,
+    id: CommitId,
+) -> Result<ChangesetContext, HandlerError> {
+    let specifier = match &id {
+        CommitId::Hg(hgid) => ChangesetSpecifier::Hg(HgChangesetId::new(HgNodeHash::from(*hgid))),
+        CommitId::Bonsai(cs_id) => ChangesetSpecifier::Bonsai(ChangesetId::from(*cs_id)),
+    };
+    repo.repo()
+        .changeset(specifier)
+        .await?
+        .ok_or_else(|| HandlerError::E404(ErrorKind::CommitNotFound(id).into()))
+}
+
+async fn file_diff(path_diff: ChangesetPathDiffContext, context: usize) -> Result<FileDiff, Error> {
+    // `to.diff(from)` reports paths as (to, from) pairs.
+    let (old, new, copy_info) = match &path_diff {
+        ChangesetPathDiffContext::Added(to) => (None, Some(to), CopyInfo::None),
+        ChangesetPathDiffContext::Removed(from) => (Some(from), None, CopyInfo::None),
+        ChangesetPathDiffContext::Changed(to, from) => (Some(from), Some(to), CopyInfo::None),
+        ChangesetPathDiffContext::Copied(to, from) => (Some(from), Some(to), CopyInfo::Copy),
+        ChangesetPathDiffContext::Moved(to, from) => (Some(from), Some(to), CopyInfo::Move),
+    };
+    let diff = unified_diff(old, new, copy_info, context, UnifiedDiffMode::Inline).await?;
+    Ok(FileDiff {
+        old_path: old.map(|p| to_hg_path(p.path())).transpose()?,
+        new_path: new.map(|p| to_hg_path(p.path())).transpose()?,
+        stat: DiffStat::from_unified_diff(&diff.raw_diff),
+        raw_diff: diff.raw_diff.into(),
+        is_binary: diff.is_binary,
+    })
+}
+
+/// Diff two commits, producing a unified diff for each changed file
+/// followed by a summary of the whole diff.
+#[async_trait]
+impl EdenApiHandler for CommitDiffHandler {
+    type Request = CommitDiffRequest;
+    type Response = CommitDiffEntry;
+
+    const HTTP_METHOD: hyper::Method = hyper::Method::POST;
+    const API_METHOD: EdenApiMethod = EdenApiMethod::CommitDiff;
+    const ENDPOINT: &'static str = "/commit/diff";
+
+    async fn handler(
+        repo: HgRepoContext,
+        _path: Self::PathExtractor,
+        _query: Self::QueryStringExtractor,
+        request: Self::Request,
+    ) -> HandlerResult<'async_trait, Self::Response> {
+        let (from, to) = try_join!(
+            resolve_commit(&repo, request.from),
+            resolve_commit(&repo, request.to),
+        )?;
+        let path_restrictions = if request.paths.is_empty() {
+            None
+        } else {
+            Some(
+                request
+                    .paths
+                    .iter()
+                    .map(to_mononoke_path)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        };
+        let path_diffs = to
+            .diff(
+                &from,
+                true,
+                path_restrictions,
+                btreeset! { ChangesetDiffItem::FILES },
+            )
+            .await?;
+
+        let context = request.context as usize;
+        let file_diffs = stream::iter(path_diffs)
+            .map(move |path_diff| file_diff(path_diff, context))
+            .buffered(MAX_CONCURRENT_FILE_DIFFS_PER_REQUEST);
+
+        // Stream the file diffs as they are computed, then send the summary
+        // once all of them have been accounted for.
+        let response = file_diffs
+            .map(Some)
+            .chain(stream::once(future::ready(None)))
+            .scan(DiffStat::default(), |summary, item| {
+                let entry = match item {
+                    Some(Ok(file)) => {
+                        *summary += file.stat;
+                        Ok(CommitDiffEntry::File(file))
+                    }
+                    Some(Err(e)) => Err(e),
+                    None => Ok(CommitDiffEntry::Summary(*summary)),
+                };
+                future::ready(Some(entry))
+            });
+
+        Ok(response.boxed())
+    }
+}
@@ -33,6 +33,7 @@ impl PathExtractorWithRepo for BasicPathExtractor {
 }
 
 pub enum HandlerError {
+    E404(anyhow::Error),
     E500(anyhow::Error),
 }
 
@@ -42,6 +42,7 @@ mod bookmarks;
 mod capabilities;
 mod clone;
 mod commit;
+mod diff;
 mod files;
 mod handler;
 mod history;
@@ -81,6 +82,7 @@ pub enum EdenApiMethod {
     CommitGraph,
     DownloadFile,
     CommitMutations,
+    CommitDiff,
 }
 
 impl fmt::Display for EdenApiMethod {
@@ -110,6 +112,7 @@ impl fmt::Display for EdenApiMethod {
             Self::FetchSnapshot => "fetch_snapshot",
             Self::DownloadFile => "download_file",
             Self::CommitMutations => "commit_mutations",
+            Self::CommitDiff => "commit_diff",
         };
         write!(f, "{}", name)
     }
@@ -224,6 +227,7 @@ async fn handler_wrapper<Handler: EdenApiHandler>(
 
         match Handler::handler(repo, path, query_string, request).await {
             Ok(responses) => Ok(encode_response_stream(responses, content_encoding)),
+            Err(HandlerError::E404(err)) => Err(HttpError::e404(err)),
             Err(HandlerError::E500(err)) => Err(HttpError::e500(err)),
         }
     }
@@ -294,6 +298,7 @@ pub fn build_router(ctx: ServerContext) -> Router {
         Handlers::setup::<commit::GraphHandler>(route);
         Handlers::setup::<files::DownloadFileHandler>(route);
         Handlers::setup::<commit::CommitMutationsHandler>(route);
+        Handlers::setup::<diff::CommitDiffHandler>(route);
         route
             .get("/:repo/capabilities")
             .with_path_extractor::<capabilities::CapabilitiesParams>()
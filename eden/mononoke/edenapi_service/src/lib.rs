@@ -21,8 +21,9 @@ use gotham::router::Router;
 use gotham_ext::{
     handler::MononokeHttpHandler,
     middleware::{
-        ClientIdentityMiddleware, LoadMiddleware, LogMiddleware, PostResponseMiddleware,
-        ScubaMiddleware, ServerIdentityMiddleware, TimerMiddleware, TlsSessionDataMiddleware,
+        ClientIdentityMiddleware, LoadMiddleware, LogMiddleware, MetricsMiddleware,
+        PostResponseMiddleware, ScubaMiddleware, ServerIdentityMiddleware, TimerMiddleware,
+        TlsSessionDataMiddleware,
     },
 };
 use http::HeaderValue;
@@ -71,6 +72,7 @@ pub fn build(
             "edenapi_server",
         )))
         .add(PostResponseMiddleware::default())
+        .add(MetricsMiddleware::new())
         .add(RequestContextMiddleware::new(
             fb,
             logger,
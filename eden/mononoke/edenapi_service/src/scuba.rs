@@ -69,6 +69,7 @@ impl ScubaHandler for EdenApiScubaHandler {
 
         if let Some(ctx) = self.request_context {
             ctx.ctx.perf_counters().insert_perf_counters(scuba);
+            ctx.ctx.perf_counters().report_to_prometheus();
         }
 
         if let Some(err) = info.first_error() {
@@ -0,0 +1,231 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::Error;
+use gotham::state::{FromState, State};
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Body, Method, Response, StatusCode, Uri,
+};
+use lazy_static::lazy_static;
+use permission_checker::{ArcMembershipChecker, MononokeIdentitySet};
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+
+use super::{ClientIdentity, Middleware, PostResponseCallbacks};
+
+/// Path on which `MetricsMiddleware` exports metrics.
+pub const METRICS_PATH: &str = "/metrics";
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "mononoke_http_requests_total",
+        "Number of HTTP requests served, by method and status code",
+        &["method", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "mononoke_http_request_duration_seconds",
+        "Time taken to fully send the response to HTTP requests, by method",
+        &["method"],
+        prometheus::exponential_buckets(0.001, 2.0, 18).unwrap()
+    )
+    .unwrap();
+    static ref HTTP_REQUESTS_IN_FLIGHT: IntGauge = register_int_gauge!(
+        "mononoke_http_requests_in_flight",
+        "Number of HTTP requests currently being served"
+    )
+    .unwrap();
+}
+
+/// Render all metrics registered in the default Prometheus registry, in the Prometheus text
+/// exposition format. This includes metrics registered outside of this crate, such as the
+/// aggregated perf counters exported by the `context` crate.
+pub fn render_metrics() -> Result<Response<Body>, Error> {
+    let encoder = TextEncoder::new();
+    let mut buf = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut buf)?;
+
+    let res = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, HeaderValue::from_str(encoder.format_type())?)
+        .body(Body::from(buf))?;
+
+    Ok(res)
+}
+
+fn is_metrics_request(state: &State) -> bool {
+    Method::borrow_from(state) == Method::GET && Uri::borrow_from(state).path() == METRICS_PATH
+}
+
+fn error_response(status: StatusCode, body: Body) -> Response<Body> {
+    let mut res = Response::new(body);
+    *res.status_mut() = status;
+    res
+}
+
+#[derive(Clone)]
+pub struct MetricsMiddleware {
+    scrapers: Option<ArcMembershipChecker>,
+}
+
+impl MetricsMiddleware {
+    /// Record metrics about the requests served, without exporting them. Use this when the
+    /// metrics are exported by something else in the process.
+    pub fn new() -> Self {
+        MetricsMiddleware { scrapers: None }
+    }
+
+    /// Record metrics about the requests served, and export them on `METRICS_PATH` to clients
+    /// whose identities are members of `scrapers`. Everyone else is refused.
+    pub fn with_endpoint(scrapers: ArcMembershipChecker) -> Self {
+        MetricsMiddleware {
+            scrapers: Some(scrapers),
+        }
+    }
+
+    fn serves_metrics(&self, state: &State) -> bool {
+        self.scrapers.is_some() && is_metrics_request(state)
+    }
+
+    async fn metrics_response(
+        identities: MononokeIdentitySet,
+        scrapers: &ArcMembershipChecker,
+    ) -> Response<Body> {
+        match scrapers.is_member(&identities).await {
+            Ok(true) => render_metrics().unwrap_or_else(|e| {
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Body::from(format!("{:#}", e)),
+                )
+            }),
+            Ok(false) => error_response(StatusCode::FORBIDDEN, Body::empty()),
+            Err(e) => error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Body::from(format!("{:#}", e)),
+            ),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for MetricsMiddleware {
+    async fn inbound(&self, state: &mut State) -> Option<Response<Body>> {
+        if let Some(scrapers) = &self.scrapers {
+            if is_metrics_request(state) {
+                // Clients without identities are not members of any non-trivial set of scrapers.
+                let identities = ClientIdentity::try_borrow_from(state)
+                    .and_then(|client_identity| client_identity.identities().clone())
+                    .unwrap_or_default();
+                return Some(Self::metrics_response(identities, scrapers).await);
+            }
+        }
+
+        HTTP_REQUESTS_IN_FLIGHT.inc();
+        None
+    }
+
+    async fn outbound(&self, state: &mut State, response: &mut Response<Body>) {
+        if self.serves_metrics(state) {
+            return;
+        }
+
+        let method = Method::borrow_from(state).clone();
+        HTTP_REQUESTS
+            .with_label_values(&[method.as_str(), response.status().as_str()])
+            .inc();
+
+        // The request is only done once the body has been sent, so update the remaining metrics
+        // after that if we can.
+        match state.try_borrow_mut::<PostResponseCallbacks>() {
+            Some(callbacks) => callbacks.add(move |info| {
+                HTTP_REQUESTS_IN_FLIGHT.dec();
+                if let Some(duration) = info.duration {
+                    HTTP_REQUEST_DURATION
+                        .with_label_values(&[method.as_str()])
+                        .observe(duration.as_secs_f64());
+                }
+            }),
+            None => HTTP_REQUESTS_IN_FLIGHT.dec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::future::{self, FutureExt};
+    use gotham::handler::{Handler, HandlerFuture};
+    use gotham::test::TestServer;
+    use permission_checker::MembershipCheckerBuilder;
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use crate::handler::MononokeHttpHandler;
+
+    #[derive(Clone)]
+    struct TestHandler;
+
+    impl Handler for TestHandler {
+        fn handle(self, state: State) -> Pin<Box<HandlerFuture>> {
+            let response = Response::builder()
+                .status(StatusCode::IM_A_TEAPOT)
+                .body(Body::empty())
+                .unwrap();
+
+            future::ready(Ok((state, response))).boxed()
+        }
+    }
+
+    #[test]
+    fn test_metrics() -> Result<(), Error> {
+        let handler = MononokeHttpHandler::builder()
+            .add(MetricsMiddleware::with_endpoint(Arc::from(
+                MembershipCheckerBuilder::always_member(),
+            )))
+            .build(TestHandler);
+        let server = TestServer::new(handler)?;
+
+        let res = server.client().get("http://host/foo").perform()?;
+        assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+
+        let res = server.client().get("http://host/metrics").perform()?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8(res.read_body()?)?;
+        assert!(body.contains(r#"mononoke_http_requests_total{method="GET",status="418"}"#));
+        assert!(body.contains("mononoke_http_requests_in_flight 0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_requires_scraper() -> Result<(), Error> {
+        let handler = MononokeHttpHandler::builder()
+            .add(MetricsMiddleware::with_endpoint(Arc::from(
+                MembershipCheckerBuilder::never_member(),
+            )))
+            .build(TestHandler);
+        let server = TestServer::new(handler)?;
+
+        let res = server.client().get("http://host/metrics").perform()?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // Without an endpoint, the request is left to the handler.
+        let handler = MononokeHttpHandler::builder()
+            .add(MetricsMiddleware::new())
+            .build(TestHandler);
+        let server = TestServer::new(handler)?;
+
+        let res = server.client().get("http://host/metrics").perform()?;
+        assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+
+        Ok(())
+    }
+}
@@ -13,6 +13,7 @@ use hyper::{Body, Response};
 pub mod client_identity;
 pub mod load;
 pub mod log;
+pub mod metrics;
 pub mod post_request;
 pub mod scuba;
 pub mod server_identity;
@@ -22,6 +23,7 @@ pub mod tls_session_data;
 pub use self::client_identity::{ClientIdentity, ClientIdentityMiddleware};
 pub use self::load::{LoadMiddleware, RequestLoad};
 pub use self::log::LogMiddleware;
+pub use self::metrics::MetricsMiddleware;
 pub use self::post_request::{
     PostResponseCallbacks, PostResponseConfig, PostResponseInfo, PostResponseMiddleware,
 };
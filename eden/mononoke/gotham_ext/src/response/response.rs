@@ -88,11 +88,20 @@ impl TryIntoResponse for EmptyBody {
 pub struct BytesBody<B> {
     bytes: B,
     mime: Mime,
+    status: StatusCode,
 }
 
 impl<B> BytesBody<B> {
     pub fn new(bytes: B, mime: Mime) -> Self {
-        Self { bytes, mime }
+        Self {
+            bytes,
+            mime,
+            status: StatusCode::OK,
+        }
+    }
+
+    pub fn with_status(self, status: StatusCode) -> Self {
+        Self { status, ..self }
     }
 }
 
@@ -109,7 +118,7 @@ where
 
         Response::builder()
             .header(CONTENT_TYPE, mime_header)
-            .status(StatusCode::OK)
+            .status(self.status)
             .body(bytes.into())
             .map_err(Error::from)
     }
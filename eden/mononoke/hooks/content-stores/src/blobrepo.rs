@@ -17,6 +17,7 @@ use context::CoreContext;
 use derived_data::BonsaiDerived;
 use futures::{future, stream::TryStreamExt};
 use futures_util::future::TryFutureExt;
+use lfs_locks::LfsLock;
 use manifest::{Diff, Entry, ManifestOps};
 use mercurial_types::{FileType, HgFileNodeId, HgManifestId};
 use mononoke_types::{ChangesetId, ContentId, MPath, ManifestUnodeId};
@@ -182,6 +183,23 @@ impl FileContentManager for BlobRepoFileContentManager {
             .map_err(ErrorKind::from)
             .await
     }
+
+    async fn lfs_locks<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        paths: Vec<MPath>,
+    ) -> Result<HashMap<MPath, LfsLock>, ErrorKind> {
+        let locks = self
+            .repo
+            .lfs_locks()
+            .find_locks(ctx, &paths)
+            .await
+            .context("Error fetching LFS locks")?;
+        Ok(locks
+            .into_iter()
+            .map(|lock| (lock.path.clone(), lock))
+            .collect())
+    }
 }
 
 impl BlobRepoFileContentManager {
@@ -13,6 +13,7 @@ use bookmarks::BookmarkName;
 use bytes::Bytes;
 use changeset_info::ChangesetInfo;
 use context::CoreContext;
+use lfs_locks::LfsLock;
 use mononoke_types::{ChangesetId, ContentId, MPath};
 use std::collections::HashMap;
 
@@ -44,6 +45,7 @@ impl Into<InMemoryFileText> for u64 {
 #[derive(Clone)]
 pub struct InMemoryFileContentManager {
     id_to_text: HashMap<ContentId, InMemoryFileText>,
+    lfs_locks: HashMap<MPath, LfsLock>,
 }
 
 #[async_trait]
@@ -111,16 +113,35 @@ impl FileContentManager for InMemoryFileContentManager {
                 .into(),
         )
     }
+
+    async fn lfs_locks<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        paths: Vec<MPath>,
+    ) -> Result<HashMap<MPath, LfsLock>, ErrorKind> {
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let lock = self.lfs_locks.get(&path)?.clone();
+                Some((path, lock))
+            })
+            .collect())
+    }
 }
 
 impl InMemoryFileContentManager {
     pub fn new() -> InMemoryFileContentManager {
         InMemoryFileContentManager {
             id_to_text: HashMap::new(),
+            lfs_locks: HashMap::new(),
         }
     }
 
     pub fn insert(&mut self, key: ContentId, text: impl Into<InMemoryFileText>) {
         self.id_to_text.insert(key, text.into());
     }
+
+    pub fn insert_lfs_lock(&mut self, lock: LfsLock) {
+        self.lfs_locks.insert(lock.path.clone(), lock);
+    }
 }
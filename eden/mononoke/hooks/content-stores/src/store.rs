@@ -12,6 +12,7 @@ use bookmarks::BookmarkName;
 use bytes::Bytes;
 use changeset_info::ChangesetInfo;
 use context::CoreContext;
+use lfs_locks::LfsLock;
 use mononoke_types::{ChangesetId, ContentId, MPath};
 use std::collections::HashMap;
 
@@ -49,6 +50,12 @@ pub trait FileContentManager: Send + Sync {
         bookmark: BookmarkName,
         paths: Vec<MPath>,
     ) -> Result<HashMap<MPath, ChangesetInfo>, ErrorKind>;
+
+    async fn lfs_locks<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        paths: Vec<MPath>,
+    ) -> Result<HashMap<MPath, LfsLock>, ErrorKind>;
 }
 
 #[derive(Clone, Debug)]
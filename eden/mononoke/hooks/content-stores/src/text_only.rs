@@ -12,6 +12,7 @@ use bookmarks::BookmarkName;
 use bytes::Bytes;
 use changeset_info::ChangesetInfo;
 use context::CoreContext;
+use lfs_locks::LfsLock;
 use mononoke_types::{ChangesetId, ContentId, MPath};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -92,6 +93,14 @@ impl<T: FileContentManager + 'static> FileContentManager for TextOnlyFileContent
     ) -> Result<HashMap<MPath, ChangesetInfo>, ErrorKind> {
         self.inner.latest_changes(ctx, bookmark, paths).await
     }
+
+    async fn lfs_locks<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        paths: Vec<MPath>,
+    ) -> Result<HashMap<MPath, LfsLock>, ErrorKind> {
+        self.inner.lfs_locks(ctx, paths).await
+    }
 }
 
 fn looks_like_binary(file_bytes: &[u8]) -> bool {
@@ -14,6 +14,7 @@ use hooks::hook_loader::load_hooks;
 use hooks::HookManager;
 use hooks_content_stores::blobrepo_text_only_fetcher;
 use metaconfig_types::RepoConfig;
+use permission_checker::{ArcPermissionChecker, PermissionCheckerBuilder};
 use scuba_ext::MononokeScubaSampleBuilder;
 
 pub async fn make_hook_manager(
@@ -42,6 +43,16 @@ pub async fn make_hook_manager(
     )
     .await?;
 
+    // Only an explicit ACL file grants hook bypass, as the default repo ACL
+    // checkers may allow everything.
+    if let Some(checker) = config
+        .hipster_acl
+        .as_deref()
+        .and_then(PermissionCheckerBuilder::acl_from_file)
+    {
+        hook_manager.set_bypass_permission_checker(ArcPermissionChecker::from(checker));
+    }
+
     load_hooks(ctx.fb, &mut hook_manager, config, disabled_hooks).await?;
 
     Ok(hook_manager)
@@ -35,7 +35,7 @@ use futures_stats::TimedFutureExt;
 pub use hooks_content_stores::{FileContentManager, PathContent};
 use metaconfig_types::{BookmarkOrRegex, HookBypass, HookConfig, HookManagerParams};
 use mononoke_types::{BasicFileChange, BonsaiChangeset, ChangesetId, MPath};
-use permission_checker::{ArcMembershipChecker, MembershipCheckerBuilder};
+use permission_checker::{ArcMembershipChecker, ArcPermissionChecker, MembershipCheckerBuilder};
 use regex::Regex;
 use scuba::builder::ServerData;
 use scuba_ext::MononokeScubaSampleBuilder;
@@ -56,6 +56,7 @@ pub struct HookManager {
     content_manager: Box<dyn FileContentManager>,
     reviewers_membership: ArcMembershipChecker,
     admin_membership: ArcMembershipChecker,
+    bypass_permission_checker: Option<ArcPermissionChecker>,
     scuba: MononokeScubaSampleBuilder,
     all_hooks_bypassed: bool,
     scuba_bypassed_commits: MononokeScubaSampleBuilder,
@@ -106,6 +107,7 @@ impl HookManager {
             content_manager,
             reviewers_membership: reviewers_membership.into(),
             admin_membership: admin_membership.into(),
+            bypass_permission_checker: None,
             scuba,
             all_hooks_bypassed: hook_manager_params.all_hooks_bypassed,
             scuba_bypassed_commits,
@@ -151,6 +153,23 @@ impl HookManager {
         self.admin_membership.clone()
     }
 
+    /// Use the `bypass_hooks` action of this checker's ACL to decide who may
+    /// bypass hooks, in addition to the admin group.
+    pub fn set_bypass_permission_checker(&mut self, checker: ArcPermissionChecker) {
+        self.bypass_permission_checker = Some(checker);
+    }
+
+    pub async fn is_bypass_permitted(&self, ctx: &CoreContext) -> Result<bool> {
+        let identities = ctx.metadata().identities();
+        if self.admin_membership.is_member(identities).await? {
+            return Ok(true);
+        }
+        match &self.bypass_permission_checker {
+            Some(checker) => checker.check_set(identities, &["bypass_hooks"]).await,
+            None => Ok(false),
+        }
+    }
+
     pub fn hooks_exist_for_bookmark(&self, bookmark: &BookmarkName) -> bool {
         if self.bookmark_hooks.contains_key(bookmark) {
             return true;
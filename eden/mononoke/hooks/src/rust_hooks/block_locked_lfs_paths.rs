@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use crate::{
+    ChangesetHook, CrossRepoPushSource, FileContentManager, HookExecution, HookRejectionInfo,
+};
+use anyhow::Error;
+use async_trait::async_trait;
+use bookmarks::BookmarkName;
+use context::CoreContext;
+use itertools::Itertools;
+use mononoke_types::BonsaiChangeset;
+
+/// Reject changesets that modify a path that someone other than the pusher
+/// has locked through the Git LFS locking API.
+#[derive(Clone, Debug)]
+pub struct BlockLockedLfsPaths;
+
+impl BlockLockedLfsPaths {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl ChangesetHook for BlockLockedLfsPaths {
+    async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        _bookmark: &BookmarkName,
+        changeset: &'cs BonsaiChangeset,
+        content_manager: &'fetcher dyn FileContentManager,
+        cross_repo_push_source: CrossRepoPushSource,
+    ) -> Result<HookExecution, Error> {
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected commits, we rely on running source-repo hooks
+            return Ok(HookExecution::Accepted);
+        }
+
+        let paths = changeset
+            .file_changes()
+            .map(|(path, _)| path.clone())
+            .collect();
+        let locks = content_manager.lfs_locks(ctx, paths).await?;
+
+        let pusher = ctx.metadata().unix_name();
+        let locked_by_others = locks
+            .into_values()
+            .filter(|lock| Some(lock.owner.as_str()) != pusher)
+            .sorted_by(|a, b| a.path.cmp(&b.path))
+            .map(|lock| format!("  {} (locked by {})", lock.path, lock.owner))
+            .collect::<Vec<_>>();
+
+        if locked_by_others.is_empty() {
+            return Ok(HookExecution::Accepted);
+        }
+
+        Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+            "Commit modifies locked files",
+            format!(
+                "These files are locked by someone else:\n{}\n\
+                Ask the lock owners to release them before pushing.",
+                locked_by_others.join("\n")
+            ),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blobrepo::BlobRepo;
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use context::SessionContainer;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::InMemoryFileContentManager;
+    use lfs_locks::LfsLock;
+    use maplit::btreeset;
+    use mononoke_types::{MPath, Timestamp};
+    use permission_checker::MononokeIdentity;
+    use sshrelay::Metadata;
+    use std::sync::Arc;
+    use tests_utils::CreateCommitContext;
+
+    fn lock(path: &str, owner: &str) -> Result<LfsLock, Error> {
+        Ok(LfsLock {
+            id: 1,
+            path: MPath::new(path)?,
+            owner: owner.to_string(),
+            locked_at: Timestamp::now(),
+        })
+    }
+
+    fn user_ctx(fb: FacebookInit, user: &str) -> Result<CoreContext, Error> {
+        let metadata =
+            Metadata::default().set_identities(btreeset! { MononokeIdentity::new("USER", user)? });
+        let session = SessionContainer::builder(fb)
+            .metadata(Arc::new(metadata))
+            .build();
+        Ok(CoreContext::test_mock_session(session))
+    }
+
+    #[fbinit::test]
+    async fn test_block_locked_lfs_paths(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BlobRepo = test_repo_factory::build_empty()?;
+        borrowed!(ctx, repo);
+
+        let cs_id = CreateCommitContext::new_root(ctx, repo)
+            .add_file("art/a.psd", "a")
+            .add_file("art/b.psd", "b")
+            .commit()
+            .await?;
+        let bcs = cs_id.load(ctx, repo.blobstore()).await?;
+        let bookmark = BookmarkName::new("book")?;
+        let hook = BlockLockedLfsPaths::new();
+
+        let mut content_manager = InMemoryFileContentManager::new();
+        content_manager.insert_lfs_lock(lock("art/c.psd", "alice")?);
+        let hook_execution = hook
+            .run(
+                &user_ctx(fb, "bob")?,
+                &bookmark,
+                &bcs,
+                &content_manager,
+                CrossRepoPushSource::NativeToThisRepo,
+            )
+            .await?;
+        assert_eq!(hook_execution, HookExecution::Accepted);
+
+        content_manager.insert_lfs_lock(lock("art/b.psd", "alice")?);
+
+        // The lock owner can push changes to the locked path.
+        let hook_execution = hook
+            .run(
+                &user_ctx(fb, "alice")?,
+                &bookmark,
+                &bcs,
+                &content_manager,
+                CrossRepoPushSource::NativeToThisRepo,
+            )
+            .await?;
+        assert_eq!(hook_execution, HookExecution::Accepted);
+
+        // Anyone else can't.
+        let hook_execution = hook
+            .run(
+                &user_ctx(fb, "bob")?,
+                &bookmark,
+                &bcs,
+                &content_manager,
+                CrossRepoPushSource::NativeToThisRepo,
+            )
+            .await?;
+        match hook_execution {
+            HookExecution::Rejected(info) => {
+                assert!(info
+                    .long_description
+                    .contains("art/b.psd (locked by alice)"));
+                assert!(!info.long_description.contains("art/c.psd"));
+            }
+            HookExecution::Accepted => panic!("should be rejected"),
+        }
+
+        Ok(())
+    }
+}
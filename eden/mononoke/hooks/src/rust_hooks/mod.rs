@@ -9,6 +9,7 @@
 
 mod always_fail_changeset;
 mod block_empty_commit;
+mod block_locked_lfs_paths;
 mod check_nocommit;
 mod conflict_markers;
 pub(crate) mod deny_files;
@@ -50,6 +51,7 @@ pub fn hook_name_to_changeset_hook<'a>(
         Ok(match name {
             "always_fail_changeset" => Some(b(always_fail_changeset::AlwaysFailChangeset::new())),
             "block_empty_commit" => Some(b(block_empty_commit::BlockEmptyCommit::new())),
+            "block_locked_lfs_paths" => Some(b(block_locked_lfs_paths::BlockLockedLfsPaths::new())),
             "limit_commit_message_length" => Some(b(
                 limit_commit_message_length::LimitCommitMessageLength::new(config)?,
             )),
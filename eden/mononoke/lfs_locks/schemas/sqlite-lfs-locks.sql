@@ -0,0 +1,16 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+CREATE TABLE IF NOT EXISTS lfs_locks(
+   `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+   `repo_id` INT UNSIGNED NOT NULL,
+   `path_hash` VARBINARY(32) NOT NULL,
+   `path` VARBINARY(4096) NOT NULL,
+   `owner` VARCHAR(255) NOT NULL,
+   `locked_at` BIGINT NOT NULL,
+   UNIQUE (`repo_id`, `path_hash`)
+);
@@ -0,0 +1,290 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+#![deny(warnings)]
+
+//! Storage for Git LFS file locks.
+//!
+//! A lock gives one user exclusive permission to modify a path. Locks are
+//! advisory for LFS clients, and enforced on push by the
+//! `block_locked_lfs_paths` hook.
+
+use anyhow::Error;
+use context::{CoreContext, PerfCounterType};
+use mononoke_types::{MPath, RepositoryId, Timestamp};
+use path_hash::PathHashBytes;
+use sql::{queries, Connection};
+use sql_construct::{SqlConstruct, SqlConstructFromMetadataDatabaseConfig};
+use sql_ext::SqlConnections;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+pub struct SqlLfsLocksStore {
+    write_connection: Connection,
+    read_master_connection: Connection,
+}
+
+impl SqlConstruct for SqlLfsLocksStore {
+    const LABEL: &'static str = "lfs_locks";
+
+    const CREATION_QUERY: &'static str = include_str!("../schemas/sqlite-lfs-locks.sql");
+
+    fn from_sql_connections(connections: SqlConnections) -> Self {
+        Self {
+            write_connection: connections.write_connection,
+            read_master_connection: connections.read_master_connection,
+        }
+    }
+}
+
+impl SqlConstructFromMetadataDatabaseConfig for SqlLfsLocksStore {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LfsLock {
+    pub id: u64,
+    pub path: MPath,
+    pub owner: String,
+    pub locked_at: Timestamp,
+}
+
+impl LfsLock {
+    fn from_row(row: (u64, Vec<u8>, String, Timestamp)) -> Result<Self, Error> {
+        let (id, path, owner, locked_at) = row;
+        Ok(Self {
+            id,
+            path: MPath::new(path)?,
+            owner,
+            locked_at,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CreateLockOutcome {
+    /// The path was not locked, and is now locked by the requester.
+    Created(LfsLock),
+    /// The path was already locked. This is the existing lock, which may be
+    /// owned by the requester.
+    Conflict(LfsLock),
+}
+
+#[facet::facet]
+#[derive(Clone)]
+pub struct LfsLocks {
+    repo_id: RepositoryId,
+    store: Arc<SqlLfsLocksStore>,
+}
+
+impl LfsLocks {
+    pub fn new(repo_id: RepositoryId, store: SqlLfsLocksStore) -> Self {
+        Self {
+            repo_id,
+            store: Arc::new(store),
+        }
+    }
+
+    pub async fn create_lock(
+        &self,
+        ctx: &CoreContext,
+        path: &MPath,
+        owner: &str,
+    ) -> Result<CreateLockOutcome, Error> {
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlWrites);
+
+        let path_bytes = path.to_vec();
+        let path_hash = PathHashBytes::new(&path_bytes);
+        let res = AddLock::query(
+            &self.store.write_connection,
+            &[(
+                &self.repo_id,
+                &path_hash.0,
+                &path_bytes,
+                &owner,
+                &Timestamp::now(),
+            )],
+        )
+        .await?;
+
+        // Whether or not we won the race for this path, the lock is now in
+        // the master, so read it back from there.
+        let lock = self
+            .get_lock_by_path_hash(ctx, &path_hash)
+            .await?
+            .ok_or_else(|| Error::msg(format!("Lock for {} vanished after creation", path)))?;
+
+        if res.affected_rows() > 0 {
+            Ok(CreateLockOutcome::Created(lock))
+        } else {
+            Ok(CreateLockOutcome::Conflict(lock))
+        }
+    }
+
+    /// Look up a lock by id. This reads from the master, as the result is
+    /// used to decide whether a lock may be released.
+    pub async fn get_lock(&self, ctx: &CoreContext, id: u64) -> Result<Option<LfsLock>, Error> {
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlReadsMaster);
+
+        let mut rows =
+            GetLockById::query(&self.store.read_master_connection, &self.repo_id, &id).await?;
+        rows.pop().map(LfsLock::from_row).transpose()
+    }
+
+    pub async fn get_lock_by_path(
+        &self,
+        ctx: &CoreContext,
+        path: &MPath,
+    ) -> Result<Option<LfsLock>, Error> {
+        let path_hash = PathHashBytes::new(&path.to_vec());
+        self.get_lock_by_path_hash(ctx, &path_hash).await
+    }
+
+    async fn get_lock_by_path_hash(
+        &self,
+        ctx: &CoreContext,
+        path_hash: &PathHashBytes,
+    ) -> Result<Option<LfsLock>, Error> {
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlReadsMaster);
+
+        let mut rows = GetLocksByPathHashes::query(
+            &self.store.read_master_connection,
+            &self.repo_id,
+            &[&path_hash.0],
+        )
+        .await?;
+        rows.pop().map(LfsLock::from_row).transpose()
+    }
+
+    /// List up to `limit` locks, in the order they were created, starting
+    /// after the lock with id `after`. Clients list their locks to verify
+    /// them before pushing, so this reads from the master.
+    pub async fn list_locks(
+        &self,
+        ctx: &CoreContext,
+        after: u64,
+        limit: u64,
+    ) -> Result<Vec<LfsLock>, Error> {
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlReadsMaster);
+
+        ListLocks::query(
+            &self.store.read_master_connection,
+            &self.repo_id,
+            &after,
+            &limit,
+        )
+        .await?
+        .into_iter()
+        .map(LfsLock::from_row)
+        .collect()
+    }
+
+    /// Find the locks held on any of the given paths.
+    pub async fn find_locks(
+        &self,
+        ctx: &CoreContext,
+        paths: &[MPath],
+    ) -> Result<Vec<LfsLock>, Error> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlReadsMaster);
+
+        let path_hashes = paths
+            .iter()
+            .map(|path| PathHashBytes::new(&path.to_vec()).0)
+            .collect::<Vec<_>>();
+        let path_hashes = path_hashes.iter().collect::<Vec<_>>();
+
+        GetLocksByPathHashes::query(
+            &self.store.read_master_connection,
+            &self.repo_id,
+            &path_hashes[..],
+        )
+        .await?
+        .into_iter()
+        .map(LfsLock::from_row)
+        .collect()
+    }
+
+    /// Release the lock with id `id`. If `owner` is given, the lock is only
+    /// released if it is held by that owner, otherwise it is force-released.
+    ///
+    /// Returns whether a lock was released.
+    pub async fn unlock(
+        &self,
+        ctx: &CoreContext,
+        id: u64,
+        owner: Option<&str>,
+    ) -> Result<bool, Error> {
+        ctx.perf_counters()
+            .increment_counter(PerfCounterType::SqlWrites);
+
+        let res = match owner {
+            Some(owner) => {
+                DeleteOwnedLock::query(&self.store.write_connection, &self.repo_id, &id, &owner)
+                    .await?
+            }
+            None => DeleteLock::query(&self.store.write_connection, &self.repo_id, &id).await?,
+        };
+
+        Ok(res.affected_rows() > 0)
+    }
+}
+
+queries! {
+    write AddLock(values: (
+        repo_id: RepositoryId,
+        path_hash: Vec<u8>,
+        path: Vec<u8>,
+        owner: &str,
+        locked_at: Timestamp,
+    )) {
+        insert_or_ignore,
+        "{insert_or_ignore} INTO lfs_locks (repo_id, path_hash, path, owner, locked_at) VALUES {values}"
+    }
+
+    write DeleteLock(repo_id: RepositoryId, id: u64) {
+        none,
+        "DELETE FROM lfs_locks WHERE repo_id = {repo_id} AND id = {id}"
+    }
+
+    write DeleteOwnedLock(repo_id: RepositoryId, id: u64, owner: &str) {
+        none,
+        "DELETE FROM lfs_locks WHERE repo_id = {repo_id} AND id = {id} AND owner = {owner}"
+    }
+
+    read GetLockById(repo_id: RepositoryId, id: u64) -> (u64, Vec<u8>, String, Timestamp) {
+        "SELECT id, path, owner, locked_at
+         FROM lfs_locks
+         WHERE repo_id = {repo_id} AND id = {id}"
+    }
+
+    read GetLocksByPathHashes(repo_id: RepositoryId, >list path_hashes: Vec<u8>) -> (
+        u64, Vec<u8>, String, Timestamp
+    ) {
+        "SELECT id, path, owner, locked_at
+         FROM lfs_locks
+         WHERE repo_id = {repo_id} AND path_hash IN {path_hashes}"
+    }
+
+    read ListLocks(repo_id: RepositoryId, after: u64, limit: u64) -> (
+        u64, Vec<u8>, String, Timestamp
+    ) {
+        "SELECT id, path, owner, locked_at
+         FROM lfs_locks
+         WHERE repo_id = {repo_id} AND id > {after}
+         ORDER BY id ASC
+         LIMIT {limit}"
+    }
+}
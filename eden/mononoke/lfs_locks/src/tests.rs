@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use super::*;
+use anyhow::Error;
+use fbinit::FacebookInit;
+
+fn created(outcome: CreateLockOutcome) -> LfsLock {
+    match outcome {
+        CreateLockOutcome::Created(lock) => lock,
+        CreateLockOutcome::Conflict(lock) => panic!("unexpected conflict with {:?}", lock),
+    }
+}
+
+#[fbinit::test]
+async fn test_create_and_get(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let store = SqlLfsLocksStore::with_sqlite_in_memory()?;
+    let locks = LfsLocks::new(RepositoryId::new(0), store);
+
+    let path = MPath::new("assets/model.blend")?;
+    let lock = created(locks.create_lock(&ctx, &path, "alice").await?);
+    assert_eq!(lock.path, path);
+    assert_eq!(lock.owner, "alice");
+
+    assert_eq!(locks.get_lock(&ctx, lock.id).await?, Some(lock.clone()));
+    assert_eq!(locks.get_lock_by_path(&ctx, &path).await?, Some(lock));
+    assert_eq!(
+        locks
+            .get_lock_by_path(&ctx, &MPath::new("assets/other.blend")?)
+            .await?,
+        None
+    );
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_conflict(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let store = SqlLfsLocksStore::with_sqlite_in_memory()?;
+    let locks = LfsLocks::new(RepositoryId::new(0), store);
+
+    let path = MPath::new("assets/model.blend")?;
+    let lock = created(locks.create_lock(&ctx, &path, "alice").await?);
+
+    let res = locks.create_lock(&ctx, &path, "bob").await?;
+    assert_eq!(res, CreateLockOutcome::Conflict(lock));
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_repos_are_independent(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let store = Arc::new(SqlLfsLocksStore::with_sqlite_in_memory()?);
+    let first = LfsLocks {
+        repo_id: RepositoryId::new(0),
+        store: store.clone(),
+    };
+    let second = LfsLocks {
+        repo_id: RepositoryId::new(1),
+        store,
+    };
+
+    let path = MPath::new("assets/model.blend")?;
+    let lock = created(first.create_lock(&ctx, &path, "alice").await?);
+    created(second.create_lock(&ctx, &path, "bob").await?);
+
+    assert_eq!(first.find_locks(&ctx, &[path]).await?, vec![lock]);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_list_and_find(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let store = SqlLfsLocksStore::with_sqlite_in_memory()?;
+    let locks = LfsLocks::new(RepositoryId::new(0), store);
+
+    let mut all = Vec::new();
+    for name in &["a", "b", "c"] {
+        let path = MPath::new(name)?;
+        all.push(created(locks.create_lock(&ctx, &path, "alice").await?));
+    }
+
+    assert_eq!(locks.list_locks(&ctx, 0, 10).await?, all);
+    assert_eq!(locks.list_locks(&ctx, 0, 2).await?, all[..2].to_vec());
+    assert_eq!(
+        locks.list_locks(&ctx, all[1].id, 10).await?,
+        all[2..].to_vec()
+    );
+
+    let found = locks
+        .find_locks(&ctx, &[MPath::new("c")?, MPath::new("d")?])
+        .await?;
+    assert_eq!(found, vec![all[2].clone()]);
+    assert_eq!(locks.find_locks(&ctx, &[]).await?, vec![]);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_unlock(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let store = SqlLfsLocksStore::with_sqlite_in_memory()?;
+    let locks = LfsLocks::new(RepositoryId::new(0), store);
+
+    let path = MPath::new("assets/model.blend")?;
+    let lock = created(locks.create_lock(&ctx, &path, "alice").await?);
+
+    // Only the owner can release the lock without forcing it.
+    assert!(!locks.unlock(&ctx, lock.id, Some("bob")).await?);
+    assert!(locks.unlock(&ctx, lock.id, Some("alice")).await?);
+    assert_eq!(locks.get_lock(&ctx, lock.id).await?, None);
+    assert!(!locks.unlock(&ctx, lock.id, Some("alice")).await?);
+
+    let lock = created(locks.create_lock(&ctx, &path, "alice").await?);
+    assert!(locks.unlock(&ctx, lock.id, None).await?);
+    assert_eq!(locks.get_lock_by_path(&ctx, &path).await?, None);
+    Ok(())
+}
@@ -0,0 +1,253 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use quickcheck::{Arbitrary, Gen};
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::Ref;
+
+// This module provides types conforming to the Git-LFS locking API specification:
+// https://github.com/git-lfs/git-lfs/blob/master/docs/api/locking.md
+
+#[derive(Clone, Serialize, Debug, Deserialize, Hash, Eq, PartialEq)]
+pub struct LockOwner {
+    pub name: String,
+}
+
+impl Arbitrary for LockOwner {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            name: String::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, Hash, Eq, PartialEq)]
+pub struct Lock {
+    pub id: String,
+    pub path: String,
+    /// When the lock was created, in RFC 3339 format.
+    pub locked_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<LockOwner>,
+}
+
+impl Arbitrary for Lock {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            id: String::arbitrary(g),
+            path: String::arbitrary(g),
+            locked_at: String::arbitrary(g),
+            owner: Option::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct CreateLockRequest {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<Ref>,
+}
+
+impl Arbitrary for CreateLockRequest {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            path: String::arbitrary(g),
+            r#ref: Option::arbitrary(g),
+        }
+    }
+}
+
+/// Response to a successful lock creation, or to an unlock.
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct LockResponse {
+    pub lock: Lock,
+}
+
+impl Arbitrary for LockResponse {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            lock: Lock::arbitrary(g),
+        }
+    }
+}
+
+/// Response to a lock creation for a path that is already locked.
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct LockConflict {
+    pub lock: Lock,
+    pub message: String,
+}
+
+impl Arbitrary for LockConflict {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            lock: Lock::arbitrary(g),
+            message: String::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct ListLocksResponse {
+    pub locks: Vec<Lock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl Arbitrary for ListLocksResponse {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            locks: Vec::arbitrary(g),
+            next_cursor: Option::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct VerifyLocksRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<Ref>,
+}
+
+impl Arbitrary for VerifyLocksRequest {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            cursor: Option::arbitrary(g),
+            limit: Option::arbitrary(g),
+            r#ref: Option::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct VerifyLocksResponse {
+    /// Locks held by the requester.
+    pub ours: Vec<Lock>,
+    /// Locks held by anyone else.
+    pub theirs: Vec<Lock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl Arbitrary for VerifyLocksResponse {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            ours: Vec::arbitrary(g),
+            theirs: Vec::arbitrary(g),
+            next_cursor: Option::arbitrary(g),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct UnlockRequest {
+    /// Release the lock even if it is held by someone else.
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<Ref>,
+}
+
+impl Arbitrary for UnlockRequest {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            force: bool::arbitrary(g),
+            r#ref: Option::arbitrary(g),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use quickcheck::quickcheck;
+    use serde_json::{self, json};
+
+    #[test]
+    pub fn test_deserialize_create_lock_request() {
+        let j = json!({
+            "path": "foo/bar.zip",
+            "ref": {
+                "name": "refs/heads/my-feature"
+            }
+        });
+
+        let res = serde_json::from_str::<CreateLockRequest>(&j.to_string()).unwrap();
+        assert_eq!(
+            res,
+            CreateLockRequest {
+                path: "foo/bar.zip".to_string(),
+                r#ref: Some(Ref {
+                    name: "refs/heads/my-feature".to_string()
+                }),
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_deserialize_unlock_request() {
+        let j = json!({});
+
+        let res = serde_json::from_str::<UnlockRequest>(&j.to_string()).unwrap();
+        assert_eq!(
+            res,
+            UnlockRequest {
+                force: false,
+                r#ref: None,
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_serialize_lock_response() {
+        let res = LockResponse {
+            lock: Lock {
+                id: "1".to_string(),
+                path: "foo/bar.zip".to_string(),
+                locked_at: "2016-05-17T15:49:06+00:00".to_string(),
+                owner: Some(LockOwner {
+                    name: "Jane Doe".to_string(),
+                }),
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&res).unwrap(),
+            json!({
+                "lock": {
+                    "id": "1",
+                    "path": "foo/bar.zip",
+                    "locked_at": "2016-05-17T15:49:06+00:00",
+                    "owner": {
+                        "name": "Jane Doe"
+                    }
+                }
+            })
+        );
+    }
+
+    quickcheck! {
+        fn list_locks_response_roundtrip(res: ListLocksResponse) -> bool {
+            let json = serde_json::to_string(&res).unwrap();
+            let rt = serde_json::from_str::<ListLocksResponse>(&json).unwrap();
+            rt == res
+        }
+
+        fn verify_locks_response_roundtrip(res: VerifyLocksResponse) -> bool {
+            let json = serde_json::to_string(&res).unwrap();
+            let rt = serde_json::from_str::<VerifyLocksResponse>(&json).unwrap();
+            rt == res
+        }
+    }
+}
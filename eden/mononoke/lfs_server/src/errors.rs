@@ -66,6 +66,24 @@ pub enum ErrorKind {
     ObjectNotInternallyAvailableAndUpstreamUnavailable(lfs_protocol::Sha256),
     #[error("Object could not be synced from upstream: {0:?}")]
     ObjectCannotBeSynced(RequestObject),
+    #[error("Could not parse lock request")]
+    InvalidLockRequest,
+    #[error("Invalid lock id: {0}")]
+    InvalidLockId(String),
+    #[error("Invalid lock path: {0}")]
+    InvalidLockPath(String),
+    #[error("Lock does not exist: {0}")]
+    LockDoesNotExist(String),
+    #[error("Could not determine which user is making the request")]
+    LockOwnerUnknown,
+    #[error("Lock {0} is owned by {1}")]
+    LockOwnedByOther(String, String),
+    #[error("Only administrators can release locks owned by others")]
+    ForceUnlockForbidden,
+    #[error("Locking files requires write access to the repository")]
+    LockWriteForbidden,
+    #[error("Could not access LFS locks")]
+    LocksStoreFailure,
 
     /// A generic error occurred, and we'd like to propagate it.
     #[error(transparent)]
@@ -48,6 +48,10 @@ pub type HttpsHyperClient = Client<HttpsConnector<HttpConnector>>;
 
 // For some reason Source Control uses the read action to decide if a user can write to a repo...
 const ACL_CHECK_ACTION: &str = "read";
+// Creating or releasing LFS locks requires this action.
+const LOCK_WRITE_ACL_ACTION: &str = "write";
+// Releasing LFS locks held by someone else requires this action.
+const LOCK_ADMIN_ACL_ACTION: &str = "admin";
 // The user agent string presented to upstream
 const CLIENT_USER_AGENT: &str = "mononoke-lfs-server/0.1.0 git/2.15.1";
 
@@ -137,7 +141,7 @@ impl LfsServerContext {
         let enforce_authentication = config.enforce_authentication();
 
         acl_check(
-            aclchecker,
+            aclchecker.clone(),
             identities,
             enforce_acl_check,
             enforce_authentication,
@@ -156,6 +160,8 @@ impl LfsServerContext {
             config,
             always_wait_for_upstream,
             max_upload_size,
+            aclchecker,
+            identities: identities.cloned(),
         })
     }
 
@@ -219,6 +225,8 @@ pub struct RepositoryRequestContext {
     always_wait_for_upstream: bool,
     max_upload_size: Option<u64>,
     client: HttpClient,
+    aclchecker: ArcPermissionChecker,
+    identities: Option<MononokeIdentitySet>,
 }
 
 pub struct HttpClientResponse<S: Stream<Item = Result<Bytes, Error>> + Send + 'static> {
@@ -305,6 +313,39 @@ impl RepositoryRequestContext {
         self.max_upload_size
     }
 
+    /// The user making this request, as recorded in the LFS locks they own.
+    pub fn lock_owner(&self) -> Option<&str> {
+        self.identities
+            .as_ref()?
+            .iter()
+            .find(|id| id.id_type() == "USER")
+            .map(|id| id.id_data())
+    }
+
+    /// Whether this request may create LFS locks and release its own.
+    pub async fn can_write_locks(&self) -> Result<bool, Error> {
+        match &self.identities {
+            Some(identities) => {
+                self.aclchecker
+                    .check_set(identities, &[LOCK_WRITE_ACL_ACTION])
+                    .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Whether this request may release LFS locks owned by someone else.
+    pub async fn is_lock_admin(&self) -> Result<bool, Error> {
+        match &self.identities {
+            Some(identities) => {
+                self.aclchecker
+                    .check_set(identities, &[LOCK_ADMIN_ACL_ACTION])
+                    .await
+            }
+            None => Ok(false),
+        }
+    }
+
     pub async fn dispatch(
         &self,
         mut request: Request<Body>,
@@ -547,6 +588,8 @@ mod test {
         upstream_uri: Option<String>,
         config: ServerConfig,
         host: String,
+        aclchecker: ArcPermissionChecker,
+        identities: Option<MononokeIdentitySet>,
     }
 
     impl TestContextBuilder<'_> {
@@ -565,6 +608,16 @@ mod test {
             self
         }
 
+        pub fn aclchecker(mut self, aclchecker: ArcPermissionChecker) -> Self {
+            self.aclchecker = aclchecker;
+            self
+        }
+
+        pub fn identities(mut self, identities: MononokeIdentitySet) -> Self {
+            self.identities = Some(identities);
+            self
+        }
+
         pub fn build(self) -> Result<RepositoryRequestContext, Error> {
             let Self {
                 fb,
@@ -573,6 +626,8 @@ mod test {
                 upstream_uri,
                 config,
                 host,
+                aclchecker,
+                identities,
             } = self;
 
             let uri_builder = uri_builder(self_uris, upstream_uri.as_deref(), host)?;
@@ -585,6 +640,8 @@ mod test {
                 always_wait_for_upstream: false,
                 max_upload_size: None,
                 client: HttpClient::Disabled,
+                aclchecker,
+                identities,
             })
         }
     }
@@ -598,6 +655,8 @@ mod test {
                 upstream_uri: Some("http://bar.com".to_string()),
                 config: ServerConfig::default(),
                 host: "foo.com".to_string(),
+                aclchecker: ArcPermissionChecker::from(PermissionCheckerBuilder::always_allow()),
+                identities: None,
             })
         }
     }
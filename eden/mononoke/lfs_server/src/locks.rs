@@ -0,0 +1,590 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Git LFS file locking API:
+//! https://github.com/git-lfs/git-lfs/blob/master/docs/api/locking.md
+//!
+//! Locks are held on a path for the whole repository, so the `ref` that
+//! clients may send along with their requests is ignored.
+
+use anyhow::Context;
+use gotham::state::{FromState, State};
+use gotham_derive::{StateData, StaticResponseExtender};
+use gotham_ext::{
+    body_ext::BodyExt,
+    error::HttpError,
+    response::{BytesBody, TryIntoResponse},
+};
+use http::header::HeaderMap;
+use hyper::{Body, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use lfs_locks::{CreateLockOutcome, LfsLock};
+use lfs_protocol::{
+    git_lfs_mime, CreateLockRequest, ListLocksResponse, Lock, LockConflict, LockOwner,
+    LockResponse, UnlockRequest, VerifyLocksRequest, VerifyLocksResponse,
+};
+use mononoke_types::{DateTime, MPath};
+
+use crate::errors::ErrorKind;
+use crate::lfs_server_context::RepositoryRequestContext;
+use crate::middleware::LfsMethod;
+
+/// Number of locks returned per page when the client does not ask for a limit.
+const DEFAULT_LOCKS_LIMIT: u64 = 100;
+/// Maximum number of locks returned per page.
+const MAX_LOCKS_LIMIT: u64 = 1000;
+
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+pub struct LocksParams {
+    repository: String,
+}
+
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+pub struct ListLocksQueryString {
+    path: Option<String>,
+    id: Option<String>,
+    cursor: Option<String>,
+    limit: Option<u64>,
+}
+
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+pub struct UnlockParams {
+    repository: String,
+    id: String,
+}
+
+fn to_protocol_lock(lock: LfsLock) -> Lock {
+    Lock {
+        id: lock.id.to_string(),
+        path: lock.path.to_string(),
+        locked_at: DateTime::from(lock.locked_at).as_chrono().to_rfc3339(),
+        owner: Some(LockOwner { name: lock.owner }),
+    }
+}
+
+fn parse_lock_id(id: &str) -> Result<u64, HttpError> {
+    id.parse()
+        .map_err(|_| HttpError::e400(ErrorKind::InvalidLockId(id.to_string())))
+}
+
+fn parse_lock_path(path: &str) -> Result<MPath, HttpError> {
+    MPath::new(path).map_err(|_| HttpError::e400(ErrorKind::InvalidLockPath(path.to_string())))
+}
+
+fn lock_owner(ctx: &RepositoryRequestContext) -> Result<&str, HttpError> {
+    ctx.lock_owner()
+        .ok_or_else(|| HttpError::e403(ErrorKind::LockOwnerUnknown))
+}
+
+/// Locks are writes to the repository, so only users who may write to it
+/// can take or release them.
+async fn check_lock_write(ctx: &RepositoryRequestContext) -> Result<(), HttpError> {
+    if ctx.can_write_locks().await.map_err(HttpError::e500)? {
+        Ok(())
+    } else {
+        Err(HttpError::e403(ErrorKind::LockWriteForbidden))
+    }
+}
+
+fn json_response<T: Serialize>(
+    res: &T,
+    status: StatusCode,
+) -> Result<BytesBody<String>, HttpError> {
+    let body = serde_json::to_string(res).map_err(HttpError::e500)?;
+    Ok(BytesBody::new(body, git_lfs_mime()).with_status(status))
+}
+
+async fn read_request<T: DeserializeOwned>(state: &mut State) -> Result<T, HttpError> {
+    let body = Body::take_from(state);
+    let headers = HeaderMap::try_borrow_from(state);
+
+    let body = body
+        .try_concat_body_opt(headers)
+        .map_err(HttpError::e400)?
+        .await
+        .context(ErrorKind::ClientCancelled)
+        .map_err(HttpError::e400)?;
+
+    serde_json::from_slice::<T>(&body)
+        .context(ErrorKind::InvalidLockRequest)
+        .map_err(HttpError::e400)
+}
+
+/// Fetch one page of locks, and the cursor to fetch the next one from.
+async fn locks_page(
+    ctx: &RepositoryRequestContext,
+    cursor: Option<&str>,
+    limit: Option<u64>,
+) -> Result<(Vec<LfsLock>, Option<String>), HttpError> {
+    let after = cursor.map(parse_lock_id).transpose()?.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_LOCKS_LIMIT).min(MAX_LOCKS_LIMIT);
+
+    let locks = ctx
+        .repo
+        .lfs_locks()
+        .list_locks(&ctx.ctx, after, limit)
+        .await
+        .context(ErrorKind::LocksStoreFailure)
+        .map_err(HttpError::e500)?;
+
+    let next_cursor = if locks.len() as u64 == limit {
+        locks.last().map(|lock| lock.id.to_string())
+    } else {
+        None
+    };
+
+    Ok((locks, next_cursor))
+}
+
+async fn create_lock_inner(
+    ctx: &RepositoryRequestContext,
+    request: CreateLockRequest,
+) -> Result<CreateLockOutcome, HttpError> {
+    let owner = lock_owner(ctx)?;
+    check_lock_write(ctx).await?;
+    let path = parse_lock_path(&request.path)?;
+
+    ctx.repo
+        .lfs_locks()
+        .create_lock(&ctx.ctx, &path, owner)
+        .await
+        .context(ErrorKind::LocksStoreFailure)
+        .map_err(HttpError::e500)
+}
+
+async fn list_locks_inner(
+    ctx: &RepositoryRequestContext,
+    query: ListLocksQueryString,
+) -> Result<ListLocksResponse, HttpError> {
+    let lfs_locks = ctx.repo.lfs_locks();
+
+    // Looking up a single lock, either by id or by path, never needs more
+    // than one page.
+    let (locks, next_cursor) = match (query.id, query.path) {
+        (Some(id), path) => {
+            let lock = lfs_locks
+                .get_lock(&ctx.ctx, parse_lock_id(&id)?)
+                .await
+                .context(ErrorKind::LocksStoreFailure)
+                .map_err(HttpError::e500)?;
+            let path = path.as_deref().map(parse_lock_path).transpose()?;
+            let locks = lock
+                .into_iter()
+                .filter(|lock| path.as_ref().map_or(true, |path| &lock.path == path))
+                .collect();
+            (locks, None)
+        }
+        (None, Some(path)) => {
+            let lock = lfs_locks
+                .get_lock_by_path(&ctx.ctx, &parse_lock_path(&path)?)
+                .await
+                .context(ErrorKind::LocksStoreFailure)
+                .map_err(HttpError::e500)?;
+            (lock.into_iter().collect(), None)
+        }
+        (None, None) => locks_page(ctx, query.cursor.as_deref(), query.limit).await?,
+    };
+
+    Ok(ListLocksResponse {
+        locks: locks.into_iter().map(to_protocol_lock).collect(),
+        next_cursor,
+    })
+}
+
+async fn verify_locks_inner(
+    ctx: &RepositoryRequestContext,
+    request: VerifyLocksRequest,
+) -> Result<VerifyLocksResponse, HttpError> {
+    let owner = lock_owner(ctx)?;
+    let (locks, next_cursor) = locks_page(ctx, request.cursor.as_deref(), request.limit).await?;
+
+    let (ours, theirs): (Vec<_>, Vec<_>) = locks.into_iter().partition(|lock| lock.owner == owner);
+
+    Ok(VerifyLocksResponse {
+        ours: ours.into_iter().map(to_protocol_lock).collect(),
+        theirs: theirs.into_iter().map(to_protocol_lock).collect(),
+        next_cursor,
+    })
+}
+
+async fn unlock_inner(
+    ctx: &RepositoryRequestContext,
+    id: &str,
+    request: UnlockRequest,
+) -> Result<LockResponse, HttpError> {
+    let owner = lock_owner(ctx)?;
+    check_lock_write(ctx).await?;
+    let lfs_locks = ctx.repo.lfs_locks();
+
+    let lock = lfs_locks
+        .get_lock(&ctx.ctx, parse_lock_id(id)?)
+        .await
+        .context(ErrorKind::LocksStoreFailure)
+        .map_err(HttpError::e500)?
+        .ok_or_else(|| HttpError::e404(ErrorKind::LockDoesNotExist(id.to_string())))?;
+
+    let required_owner = if lock.owner == owner {
+        Some(owner)
+    } else if !request.force {
+        return Err(HttpError::e403(ErrorKind::LockOwnedByOther(
+            id.to_string(),
+            lock.owner,
+        )));
+    } else if ctx.is_lock_admin().await.map_err(HttpError::e500)? {
+        None
+    } else {
+        return Err(HttpError::e403(ErrorKind::ForceUnlockForbidden));
+    };
+
+    let released = lfs_locks
+        .unlock(&ctx.ctx, lock.id, required_owner)
+        .await
+        .context(ErrorKind::LocksStoreFailure)
+        .map_err(HttpError::e500)?;
+
+    if !released {
+        // Someone else released the lock while we were looking at it.
+        return Err(HttpError::e404(ErrorKind::LockDoesNotExist(id.to_string())));
+    }
+
+    Ok(LockResponse {
+        lock: to_protocol_lock(lock),
+    })
+}
+
+pub async fn create_lock(state: &mut State) -> Result<impl TryIntoResponse, HttpError> {
+    let LocksParams { repository } = state.take();
+    let ctx =
+        RepositoryRequestContext::instantiate(state, repository, LfsMethod::CreateLock).await?;
+
+    let request = read_request::<CreateLockRequest>(state).await?;
+
+    match create_lock_inner(&ctx, request).await? {
+        CreateLockOutcome::Created(lock) => json_response(
+            &LockResponse {
+                lock: to_protocol_lock(lock),
+            },
+            StatusCode::CREATED,
+        ),
+        CreateLockOutcome::Conflict(lock) => json_response(
+            &LockConflict {
+                lock: to_protocol_lock(lock),
+                message: "already created lock".to_string(),
+            },
+            StatusCode::CONFLICT,
+        ),
+    }
+}
+
+pub async fn list_locks(state: &mut State) -> Result<impl TryIntoResponse, HttpError> {
+    let LocksParams { repository } = state.take();
+    let query = state.take::<ListLocksQueryString>();
+    let ctx =
+        RepositoryRequestContext::instantiate(state, repository, LfsMethod::ListLocks).await?;
+
+    let res = list_locks_inner(&ctx, query).await?;
+    json_response(&res, StatusCode::OK)
+}
+
+pub async fn verify_locks(state: &mut State) -> Result<impl TryIntoResponse, HttpError> {
+    let LocksParams { repository } = state.take();
+    let ctx =
+        RepositoryRequestContext::instantiate(state, repository, LfsMethod::VerifyLocks).await?;
+
+    let request = read_request::<VerifyLocksRequest>(state).await?;
+    let res = verify_locks_inner(&ctx, request).await?;
+    json_response(&res, StatusCode::OK)
+}
+
+pub async fn unlock(state: &mut State) -> Result<impl TryIntoResponse, HttpError> {
+    let UnlockParams { repository, id } = state.take();
+    let ctx = RepositoryRequestContext::instantiate(state, repository, LfsMethod::Unlock).await?;
+
+    let request = read_request::<UnlockRequest>(state).await?;
+    let res = unlock_inner(&ctx, &id, request).await?;
+    json_response(&res, StatusCode::OK)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Error;
+    use fbinit::FacebookInit;
+    use maplit::btreeset;
+    use permission_checker::{
+        AclFile, ArcPermissionChecker, MononokeIdentity, PermissionCheckerBuilder,
+    };
+    use std::sync::Arc;
+
+    const ACL_FILE: &str = r#"{
+        "groups": {
+            "writers": ["USER:alice", "USER:bob"],
+            "readers": ["USER:alice", "USER:bob", "USER:carol"]
+        },
+        "acls": {
+            "repo": {
+                "actions": {
+                    "read": ["readers"],
+                    "write": ["writers"]
+                }
+            }
+        }
+    }"#;
+
+    fn user(name: &str) -> Result<MononokeIdentity, Error> {
+        MononokeIdentity::new("USER", name)
+    }
+
+    /// A checker where alice and bob may write, carol may only read and
+    /// nobody is an admin.
+    fn acl_checker() -> Result<ArcPermissionChecker, Error> {
+        let acl_file = Arc::new(AclFile::from_json(ACL_FILE.as_bytes())?);
+        Ok(ArcPermissionChecker::from(
+            PermissionCheckerBuilder::acl_file_checker(Arc::new(acl_file), "repo"),
+        ))
+    }
+
+    fn create_request(path: &str) -> CreateLockRequest {
+        CreateLockRequest {
+            path: path.to_string(),
+            r#ref: None,
+        }
+    }
+
+    fn created(outcome: CreateLockOutcome) -> LfsLock {
+        match outcome {
+            CreateLockOutcome::Created(lock) => lock,
+            CreateLockOutcome::Conflict(lock) => panic!("unexpected conflict with {:?}", lock),
+        }
+    }
+
+    #[fbinit::test]
+    async fn test_create_lock(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = RepositoryRequestContext::test_builder(fb)?
+            .identities(btreeset! { user("alice")? })
+            .build()?;
+
+        let lock = created(create_lock_inner(&ctx, create_request("a/b.psd")).await?);
+        assert_eq!(lock.owner, "alice");
+
+        let outcome = create_lock_inner(&ctx, create_request("a/b.psd")).await?;
+        assert_eq!(outcome, CreateLockOutcome::Conflict(lock));
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_create_lock_requires_user(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = RepositoryRequestContext::test_builder(fb)?.build()?;
+
+        let res = create_lock_inner(&ctx, create_request("a/b.psd")).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_list_and_verify_locks(fb: FacebookInit) -> Result<(), Error> {
+        let alice = RepositoryRequestContext::test_builder(fb)?
+            .identities(btreeset! { user("alice")? })
+            .build()?;
+        let bob = RepositoryRequestContext::test_builder(fb)?
+            .repo(alice.repo.clone())
+            .identities(btreeset! { user("bob")? })
+            .build()?;
+
+        create_lock_inner(&alice, create_request("a")).await?;
+        create_lock_inner(&bob, create_request("b")).await?;
+        create_lock_inner(&alice, create_request("c")).await?;
+
+        let all = list_locks_inner(
+            &bob,
+            ListLocksQueryString {
+                path: None,
+                id: None,
+                cursor: None,
+                limit: None,
+            },
+        )
+        .await?;
+        let paths = all
+            .locks
+            .iter()
+            .map(|l| l.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a", "b", "c"]);
+        assert_eq!(all.next_cursor, None);
+
+        let page = list_locks_inner(
+            &bob,
+            ListLocksQueryString {
+                path: None,
+                id: None,
+                cursor: None,
+                limit: Some(2),
+            },
+        )
+        .await?;
+        assert_eq!(page.locks, all.locks[..2].to_vec());
+        let next = list_locks_inner(
+            &bob,
+            ListLocksQueryString {
+                path: None,
+                id: None,
+                cursor: page.next_cursor,
+                limit: Some(2),
+            },
+        )
+        .await?;
+        assert_eq!(next.locks, all.locks[2..].to_vec());
+        assert_eq!(next.next_cursor, None);
+
+        let by_path = list_locks_inner(
+            &bob,
+            ListLocksQueryString {
+                path: Some("b".to_string()),
+                id: None,
+                cursor: None,
+                limit: None,
+            },
+        )
+        .await?;
+        assert_eq!(by_path.locks, vec![all.locks[1].clone()]);
+
+        let verify = verify_locks_inner(
+            &alice,
+            VerifyLocksRequest {
+                cursor: None,
+                limit: None,
+                r#ref: None,
+            },
+        )
+        .await?;
+        assert_eq!(
+            verify.ours,
+            vec![all.locks[0].clone(), all.locks[2].clone()]
+        );
+        assert_eq!(verify.theirs, vec![all.locks[1].clone()]);
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_unlock(fb: FacebookInit) -> Result<(), Error> {
+        let alice = RepositoryRequestContext::test_builder(fb)?
+            .identities(btreeset! { user("alice")? })
+            .build()?;
+        let bob = RepositoryRequestContext::test_builder(fb)?
+            .repo(alice.repo.clone())
+            .identities(btreeset! { user("bob")? })
+            .aclchecker(acl_checker()?)
+            .build()?;
+        let admin = RepositoryRequestContext::test_builder(fb)?
+            .repo(alice.repo.clone())
+            .identities(btreeset! { user("admin")? })
+            .build()?;
+
+        let id = created(create_lock_inner(&alice, create_request("a")).await?)
+            .id
+            .to_string();
+
+        let unlock = |force| UnlockRequest { force, r#ref: None };
+
+        // Locks owned by others can only be released by force, by admins.
+        let res = unlock_inner(&bob, &id, unlock(false)).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+        let res = unlock_inner(&bob, &id, unlock(true)).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+        let res = unlock_inner(&admin, &id, unlock(false)).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+
+        let res = unlock_inner(&admin, &id, unlock(true)).await?;
+        assert_eq!(res.lock.id, id);
+
+        let res = unlock_inner(&alice, &id, unlock(false)).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::NOT_FOUND)
+        );
+
+        // The owner can release their own lock.
+        let id = created(create_lock_inner(&alice, create_request("a")).await?)
+            .id
+            .to_string();
+        let res = unlock_inner(&alice, &id, unlock(false)).await?;
+        assert_eq!(res.lock.id, id);
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_locks_require_write(fb: FacebookInit) -> Result<(), Error> {
+        let alice = RepositoryRequestContext::test_builder(fb)?
+            .identities(btreeset! { user("alice")? })
+            .aclchecker(acl_checker()?)
+            .build()?;
+        let carol = RepositoryRequestContext::test_builder(fb)?
+            .repo(alice.repo.clone())
+            .identities(btreeset! { user("carol")? })
+            .aclchecker(acl_checker()?)
+            .build()?;
+
+        let res = create_lock_inner(&carol, create_request("a")).await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+
+        // Reading locks only needs read access.
+        let lock = created(create_lock_inner(&alice, create_request("a")).await?);
+        let by_path = list_locks_inner(
+            &carol,
+            ListLocksQueryString {
+                path: Some("a".to_string()),
+                id: None,
+                cursor: None,
+                limit: None,
+            },
+        )
+        .await?;
+        assert_eq!(by_path.locks, vec![to_protocol_lock(lock.clone())]);
+
+        let res = unlock_inner(
+            &carol,
+            &lock.id.to_string(),
+            UnlockRequest {
+                force: true,
+                r#ref: None,
+            },
+        )
+        .await;
+        assert_eq!(
+            res.err().map(|e| e.status_code),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            alice.repo.lfs_locks().get_lock(&alice.ctx, lock.id).await?,
+            Some(lock)
+        );
+
+        Ok(())
+    }
+}
@@ -23,13 +23,16 @@ use futures_util::try_join;
 use gotham_ext::{
     handler::MononokeHttpHandler,
     middleware::{
-        ClientIdentityMiddleware, LoadMiddleware, LogMiddleware, PostResponseMiddleware,
-        ScubaMiddleware, ServerIdentityMiddleware, TimerMiddleware, TlsSessionDataMiddleware,
+        ClientIdentityMiddleware, LoadMiddleware, LogMiddleware, MetricsMiddleware,
+        PostResponseMiddleware, ScubaMiddleware, ServerIdentityMiddleware, TimerMiddleware,
+        TlsSessionDataMiddleware,
     },
     serve,
 };
 use hyper::header::HeaderValue;
-use permission_checker::{ArcPermissionChecker, MononokeIdentitySet, PermissionCheckerBuilder};
+use permission_checker::{
+    ArcPermissionChecker, MembershipCheckerBuilder, MononokeIdentitySet, PermissionCheckerBuilder,
+};
 use slog::info;
 use std::collections::HashMap;
 use std::net::ToSocketAddrs;
@@ -57,6 +60,7 @@ mod config;
 mod download;
 mod errors;
 mod lfs_server_context;
+mod locks;
 mod middleware;
 mod popularity;
 mod scuba;
@@ -81,6 +85,7 @@ const ARG_TEST_FRIENDLY_LOGGING: &str = "test-friendly-logging";
 const ARG_TLS_SESSION_DATA_LOG_FILE: &str = "tls-session-data-log-file";
 const ARG_MAX_UPLOAD_SIZE: &str = "max-upload-size";
 const ARG_DISABLE_ACL_CHECKER: &str = "disable-acl-checker";
+const ARG_METRICS_SCRAPER_IDENTITY: &str = "metrics-scraper-identity";
 
 const SERVICE_NAME: &str = "mononoke_lfs_server";
 
@@ -222,6 +227,15 @@ fn main(fb: FacebookInit) -> Result<(), Error> {
                 .takes_value(false)
                 .required(false)
                 .help("Whether to disable ACL checks (only use this locally!)"),
+        )
+        .arg(
+            Arg::with_name(ARG_METRICS_SCRAPER_IDENTITY)
+                .long(ARG_METRICS_SCRAPER_IDENTITY)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Identity allowed to read Prometheus metrics (metrics are not exported if unset)"),
         );
 
     let matches = app.get_matches(fb)?;
@@ -248,6 +262,16 @@ fn main(fb: FacebookInit) -> Result<(), Error> {
     let test_idents = idents_from_values(matches.values_of(ARG_TEST_IDENTITY))?;
     let disable_acl_checker = matches.is_present(ARG_DISABLE_ACL_CHECKER);
 
+    let metrics_scraper_idents =
+        idents_from_values(matches.values_of(ARG_METRICS_SCRAPER_IDENTITY))?;
+    let metrics_middleware = if !metrics_scraper_idents.is_empty() {
+        MetricsMiddleware::with_endpoint(Arc::from(MembershipCheckerBuilder::allowlist_checker(
+            metrics_scraper_idents,
+        )))
+    } else {
+        MetricsMiddleware::new()
+    };
+
     let test_acl_checker = if !test_idents.is_empty() {
         Some(ArcPermissionChecker::from(
             PermissionCheckerBuilder::allowlist_checker(test_idents),
@@ -342,6 +366,7 @@ fn main(fb: FacebookInit) -> Result<(), Error> {
         .add(TlsSessionDataMiddleware::new(tls_session_data_log)?)
         .add(ClientIdentityMiddleware::new())
         .add(PostResponseMiddleware::with_config(config_handle))
+        .add(metrics_middleware)
         .add(RequestContextMiddleware::new(fb, logger.clone()))
         .add(LoadMiddleware::new())
         .add(log_middleware)
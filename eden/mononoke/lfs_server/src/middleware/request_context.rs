@@ -25,6 +25,10 @@ pub enum LfsMethod {
     Download,
     DownloadSha256,
     Batch,
+    CreateLock,
+    ListLocks,
+    VerifyLocks,
+    Unlock,
 }
 
 impl fmt::Display for LfsMethod {
@@ -34,6 +38,10 @@ impl fmt::Display for LfsMethod {
             Self::Download => "download",
             Self::DownloadSha256 => "download_sha256",
             Self::Batch => "batch",
+            Self::CreateLock => "create_lock",
+            Self::ListLocks => "list_locks",
+            Self::VerifyLocks => "verify_locks",
+            Self::Unlock => "unlock",
         };
         write!(f, "{}", name)
     }
@@ -149,6 +149,7 @@ impl ScubaHandler for LfsScubaHandler {
             scuba.add(LfsScubaKey::ErrorCount, info.error_count());
 
             ctx.ctx.perf_counters().insert_perf_counters(scuba);
+            ctx.ctx.perf_counters().report_to_prometheus();
         }
 
         if let Some(client_info) = self.client_info {
@@ -452,15 +452,15 @@ mod test {
     use maplit::{btreemap, hashmap, hashset};
     use metaconfig_types::{
         BlameVersion, BlobConfig, BlobstoreId, BookmarkParams, Bundle2ReplayParams,
-        CacheWarmupParams, CommitSyncConfig, CommitSyncConfigVersion, DatabaseConfig,
-        DefaultSmallToLargeCommitSyncPathAction, DerivedDataConfig, DerivedDataTypesConfig,
-        EphemeralBlobstoreConfig, FilestoreParams, HookBypass, HookConfig, HookManagerParams,
-        HookParams, InfinitepushNamespace, InfinitepushParams, LfsParams, LocalDatabaseConfig,
-        MetadataDatabaseConfig, MultiplexId, MultiplexedStoreType, PushParams, PushrebaseFlags,
-        PushrebaseParams, RemoteDatabaseConfig, RemoteMetadataDatabaseConfig, RepoClientKnobs,
-        SegmentedChangelogConfig, ShardableRemoteDatabaseConfig, ShardedRemoteDatabaseConfig,
-        SmallRepoCommitSyncConfig, SourceControlServiceMonitoring, SourceControlServiceParams,
-        UnodeVersion, WireprotoLoggingConfig,
+        CacheWarmupParams, CommitSyncConfig, CommitSyncConfigVersion, CommitSyncPathRuleAction,
+        DatabaseConfig, DefaultSmallToLargeCommitSyncPathAction, DerivedDataConfig,
+        DerivedDataTypesConfig, EphemeralBlobstoreConfig, FilestoreParams, HookBypass, HookConfig,
+        HookManagerParams, HookParams, InfinitepushNamespace, InfinitepushParams, LfsParams,
+        LocalDatabaseConfig, MetadataDatabaseConfig, MultiplexId, MultiplexedStoreType, PushParams,
+        PushrebaseFlags, PushrebaseParams, RemoteDatabaseConfig, RemoteMetadataDatabaseConfig,
+        RepoClientKnobs, SegmentedChangelogConfig, ShardableRemoteDatabaseConfig,
+        ShardedRemoteDatabaseConfig, SmallRepoCommitSyncConfig, SourceControlServiceMonitoring,
+        SourceControlServiceParams, UnodeVersion, WireprotoLoggingConfig,
     };
     use mononoke_types::MPath;
     use nonzero_ext::nonzero;
@@ -559,6 +559,7 @@ mod test {
                             MPath::new("p1").unwrap() => MPath::new(".r2-legacy/p1").unwrap(),
                             MPath::new("p5").unwrap() => MPath::new(".r2-legacy/p5").unwrap(),
                         },
+                        path_rules: vec![],
                     },
                     RepositoryId::new(3) => SmallRepoCommitSyncConfig {
                         default_action: DefaultSmallToLargeCommitSyncPathAction::PrependPrefix(MPath::new("subdir").unwrap()),
@@ -566,6 +567,7 @@ mod test {
                             MPath::new("p1").unwrap() => MPath::new("p1").unwrap(),
                             MPath::new("p4").unwrap() => MPath::new("p5/p4").unwrap(),
                         },
+                        path_rules: vec![],
                     }
                 },
                 version_name: CommitSyncConfigVersion("TEST_VERSION_NAME".to_string()),
@@ -645,6 +647,205 @@ mod test {
             assert!(msg.contains("present multiple times in the same CommitSyncConfig"));
         }
     }
+
+    #[test]
+    fn test_commit_sync_config_path_rules() {
+        let commit_sync_config = r#"
+            [mega]
+            large_repo_id = 1
+            common_pushrebase_bookmarks = ["master"]
+
+                [[mega.small_repos]]
+                repoid = 2
+                bookmark_prefix = "repo2"
+                default_action = "preserve"
+                direction = "small_to_large"
+
+                    [[mega.small_repos.path_rules]]
+                    kind = "glob"
+                    pattern = "**/generated/**"
+
+                    [[mega.small_repos.path_rules]]
+                    kind = "glob"
+                    pattern = "*/BUCK"
+                    replacement = "build/*/BUCK"
+
+                    [[mega.small_repos.path_rules]]
+                    kind = "regex"
+                    pattern = "docs/(.*)\\.md"
+                    replacement = "site/$1.html"
+                    reverse_pattern = "site/(.*)\\.html"
+                    reverse_replacement = "docs/$1.md"
+        "#;
+
+        let paths = btreemap! {
+            "common/commitsyncmap.toml" => commit_sync_config
+        };
+        let tmp_dir = write_files(&paths);
+        let config_store = ConfigStore::new(Arc::new(TestSource::new()), None, None);
+        let raw_config = crate::raw::read_raw_configs(tmp_dir.path(), &config_store)
+            .expect("expect to read configs");
+        let commit_sync = parse_commit_sync_config(raw_config.commit_sync)
+            .expect("expected to get a commit sync config");
+
+        let path_rules = &commit_sync["mega"].small_repos[&RepositoryId::new(2)].path_rules;
+        assert_eq!(path_rules.len(), 3);
+
+        assert_eq!(
+            path_rules[0].pattern.as_str(),
+            "^((?:[^/]+/)*)generated/(.*)$"
+        );
+        assert_eq!(path_rules[0].action, CommitSyncPathRuleAction::DoNotSync);
+
+        assert_eq!(path_rules[1].pattern.as_str(), "^([^/]*)/BUCK$");
+        match &path_rules[1].action {
+            CommitSyncPathRuleAction::Rewrite {
+                replacement,
+                reverse_pattern,
+                reverse_replacement,
+            } => {
+                assert_eq!(replacement, "build/${1}/BUCK");
+                assert_eq!(reverse_pattern.as_str(), "^build/([^/]*)/BUCK$");
+                assert_eq!(reverse_replacement, "${1}/BUCK");
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+
+        assert_eq!(path_rules[2].pattern.as_str(), r"^(?:docs/(.*)\.md)$");
+        match &path_rules[2].action {
+            CommitSyncPathRuleAction::Rewrite {
+                replacement,
+                reverse_pattern,
+                reverse_replacement,
+            } => {
+                assert_eq!(replacement, "site/$1.html");
+                assert_eq!(reverse_pattern.as_str(), r"^(?:site/(.*)\.html)$");
+                assert_eq!(reverse_replacement, "docs/$1.md");
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn test_commit_sync_config_non_invertible_path_rules() {
+        let rules = vec![
+            (
+                r#"
+                kind = "glob"
+                pattern = "*/BUCK"
+                replacement = "build/BUCK"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "glob"
+                pattern = "**/*.txt"
+                replacement = "*/**/x.txt"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "glob"
+                pattern = "?/*"
+                replacement = "x/*"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "regex"
+                pattern = "a/(.*)"
+                replacement = "b/$1"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "glob"
+                pattern = "*_*"
+                replacement = "*-*"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "glob"
+                pattern = "**/src/**"
+                replacement = "code/**/**"
+                "#,
+                "is not invertible",
+            ),
+            (
+                r#"
+                kind = "regex"
+                pattern = "a/(.*)"
+                replacement = "b/$1"
+                reverse_pattern = "c/(.*)"
+                reverse_replacement = "a/$1"
+                "#,
+                "does not rewrite paths back",
+            ),
+            (
+                r#"
+                kind = "regex"
+                pattern = "(a|b)/(.*)"
+                replacement = "c/$2"
+                reverse_pattern = "c/(.*)"
+                reverse_replacement = "a/$1"
+                "#,
+                "must use every capture group",
+            ),
+            (
+                r#"
+                kind = "regex"
+                pattern = "a/(.*"
+                "#,
+                "invalid path rule pattern",
+            ),
+            (
+                r#"
+                kind = "other"
+                pattern = "a"
+                "#,
+                "unknown path rule kind",
+            ),
+        ];
+
+        for (rule, expected_error) in rules {
+            let commit_sync_config = format!(
+                r#"
+                [mega]
+                large_repo_id = 1
+                common_pushrebase_bookmarks = ["master"]
+
+                    [[mega.small_repos]]
+                    repoid = 2
+                    bookmark_prefix = "repo2"
+                    default_action = "preserve"
+                    direction = "small_to_large"
+
+                        [[mega.small_repos.path_rules]]
+                        {}
+                "#,
+                rule
+            );
+            let paths = btreemap! {
+                "common/commitsyncmap.toml" => commit_sync_config.as_str()
+            };
+            let tmp_dir = write_files(&paths);
+            let config_store = ConfigStore::new(Arc::new(TestSource::new()), None, None);
+            let RawRepoConfigs { commit_sync, .. } =
+                crate::raw::read_raw_configs(tmp_dir.path().as_ref(), &config_store).unwrap();
+            for (_config_name, commit_sync_config) in commit_sync {
+                let res = commit_sync_config.convert();
+                let msg = format!("{:#?}", res);
+                assert!(res.is_err());
+                assert!(msg.contains(expected_error), "{}", msg);
+            }
+        }
+    }
     #[test]
     fn test_duplicated_repo_ids() {
         let www_content = r#"
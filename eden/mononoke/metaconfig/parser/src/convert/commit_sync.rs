@@ -14,11 +14,13 @@ use bookmarks_types::BookmarkName;
 use commitsync::types::CommonCommitSyncConfig as RawCommonCommitSyncConfig;
 use itertools::Itertools;
 use metaconfig_types::{
-    CommitSyncConfig, CommitSyncConfigVersion, CommonCommitSyncConfig,
-    DefaultSmallToLargeCommitSyncPathAction, SmallRepoCommitSyncConfig, SmallRepoPermanentConfig,
+    CommitSyncConfig, CommitSyncConfigVersion, CommitSyncPathRule, CommitSyncPathRuleAction,
+    CommonCommitSyncConfig, ComparableRegex, DefaultSmallToLargeCommitSyncPathAction,
+    SmallRepoCommitSyncConfig, SmallRepoPermanentConfig,
 };
 use mononoke_types::{MPath, RepositoryId};
-use repos::{RawCommitSyncConfig, RawCommitSyncSmallRepoConfig};
+use regex::Regex;
+use repos::{RawCommitSyncConfig, RawCommitSyncPathRule, RawCommitSyncSmallRepoConfig};
 
 use crate::convert::Convert;
 
@@ -158,6 +160,7 @@ impl Convert for RawCommitSyncSmallRepoConfig {
             default_action,
             default_prefix,
             mapping,
+            path_rules,
             ..
         } = self;
 
@@ -182,13 +185,401 @@ impl Convert for RawCommitSyncSmallRepoConfig {
             .map(|(k, v)| Ok((MPath::new(k)?, MPath::new(v)?)))
             .collect::<Result<HashMap<_, _>>>()?;
 
+        let path_rules = path_rules
+            .unwrap_or_default()
+            .into_iter()
+            .map(|rule| rule.convert())
+            .collect::<Result<Vec<_>>>()?;
+
         Ok(SmallRepoCommitSyncConfig {
             default_action,
             map,
+            path_rules,
         })
     }
 }
 
+/// A single element of a glob pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobToken {
+    Literal(char),
+    /// `?`: any single character except `/`
+    AnyChar,
+    /// `*`: any characters within a single path component
+    Star,
+    /// `**/`: any number of leading directories, including none
+    DirStars,
+    /// `**` (not followed by `/`): any characters, including `/`
+    Stars,
+}
+
+impl GlobToken {
+    fn is_wildcard(&self) -> bool {
+        match self {
+            GlobToken::Star | GlobToken::DirStars | GlobToken::Stars => true,
+            GlobToken::Literal(_) | GlobToken::AnyChar => false,
+        }
+    }
+}
+
+/// Whether the text matched by each wildcard of a glob can be recovered
+/// from a path matching it.  This is the case if there is at most one `**`,
+/// and any two other wildcards are separated by a `/`.  For example, a
+/// path matching `*_*` can be split in several ways if it contains more
+/// than one `_`.
+fn is_unambiguous_glob(tokens: &[GlobToken]) -> bool {
+    let multi_component_wildcards = tokens
+        .iter()
+        .filter(|t| matches!(t, GlobToken::DirStars | GlobToken::Stars))
+        .count();
+    if multi_component_wildcards > 1 {
+        return false;
+    }
+    let mut previous_wildcard = None;
+    let mut separated = false;
+    for token in tokens {
+        match token {
+            GlobToken::Literal('/') => separated = true,
+            GlobToken::Literal(_) | GlobToken::AnyChar => {}
+            wildcard => {
+                // `**/` ends with a separator
+                if previous_wildcard.is_some()
+                    && !separated
+                    && previous_wildcard != Some(GlobToken::DirStars)
+                {
+                    return false;
+                }
+                previous_wildcard = Some(*wildcard);
+                separated = false;
+            }
+        }
+    }
+    true
+}
+
+fn tokenize_glob(glob: &str) -> Vec<GlobToken> {
+    let mut tokens = Vec::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '?' => GlobToken::AnyChar,
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    GlobToken::DirStars
+                } else {
+                    GlobToken::Stars
+                }
+            }
+            '*' => GlobToken::Star,
+            c => GlobToken::Literal(c),
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Build an anchored regex from a glob, with a capture group per wildcard
+fn glob_to_regex(tokens: &[GlobToken]) -> String {
+    let mut regex = String::from("^");
+    for token in tokens {
+        match token {
+            GlobToken::Literal(c) => regex.push_str(&regex::escape(&c.to_string())),
+            GlobToken::AnyChar => regex.push_str("[^/]"),
+            GlobToken::Star => regex.push_str("([^/]*)"),
+            GlobToken::DirStars => regex.push_str("((?:[^/]+/)*)"),
+            GlobToken::Stars => regex.push_str("(.*)"),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Build a regex replacement template from a glob, where the N-th wildcard
+/// refers to the N-th capture group of the pattern
+fn glob_to_replacement(glob: &str, tokens: &[GlobToken]) -> Result<String> {
+    let mut replacement = String::new();
+    let mut group = 0;
+    for token in tokens {
+        match token {
+            GlobToken::Literal('$') => replacement.push_str("$$"),
+            GlobToken::Literal(c) => replacement.push(*c),
+            GlobToken::AnyChar => {
+                return Err(anyhow!(
+                    "glob {:?} can't be used as a replacement: '?' is not allowed",
+                    glob
+                ));
+            }
+            GlobToken::Star | GlobToken::DirStars | GlobToken::Stars => {
+                group += 1;
+                replacement.push_str(&format!("${{{}}}", group));
+            }
+        }
+    }
+    Ok(replacement)
+}
+
+fn compile_regex(pattern: &str) -> Result<ComparableRegex> {
+    let regex = Regex::new(pattern)
+        .map_err(|e| anyhow!("invalid path rule pattern {:?}: {}", pattern, e))?;
+    Ok(ComparableRegex::new(regex))
+}
+
+/// Convert a glob rule.  Glob rewrites are inverted automatically, which is
+/// only possible if the replacement uses the same wildcards, in the same
+/// order, as the pattern, the pattern doesn't use `?`, and the text matched
+/// by each wildcard is unambiguous.  Rules which don't sync paths have no
+/// inverse, so any glob can be used.
+fn convert_glob_path_rule(
+    pattern: String,
+    replacement: Option<String>,
+) -> Result<CommitSyncPathRule> {
+    let tokens = tokenize_glob(&pattern);
+    let regex = compile_regex(&glob_to_regex(&tokens))?;
+    let action = match replacement {
+        None => CommitSyncPathRuleAction::DoNotSync,
+        Some(replacement) => {
+            let replacement_tokens = tokenize_glob(&replacement);
+            let wildcards: Vec<_> = tokens.iter().filter(|t| t.is_wildcard()).collect();
+            let replacement_wildcards: Vec<_> = replacement_tokens
+                .iter()
+                .filter(|t| t.is_wildcard())
+                .collect();
+            if wildcards != replacement_wildcards {
+                return Err(anyhow!(
+                    "glob rule {:?} -> {:?} is not invertible: the replacement must use the same wildcards in the same order as the pattern",
+                    pattern,
+                    replacement
+                ));
+            }
+            if !is_unambiguous_glob(&tokens) || !is_unambiguous_glob(&replacement_tokens) {
+                return Err(anyhow!(
+                    "glob rule {:?} -> {:?} is not invertible: wildcards must be separated by '/', and only one '**' is allowed",
+                    pattern,
+                    replacement
+                ));
+            }
+            CommitSyncPathRuleAction::Rewrite {
+                replacement: glob_to_replacement(&replacement, &replacement_tokens)?,
+                reverse_pattern: compile_regex(&glob_to_regex(&replacement_tokens))?,
+                reverse_replacement: glob_to_replacement(&pattern, &tokens).map_err(|_| {
+                    anyhow!(
+                        "glob rule {:?} -> {:?} is not invertible: '?' is not allowed in rewritten patterns",
+                        pattern,
+                        replacement
+                    )
+                })?,
+            }
+        }
+    };
+    Ok(CommitSyncPathRule {
+        pattern: regex,
+        action,
+    })
+}
+
+/// A part of a replacement template, see `regex::Captures::expand`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    Group(String),
+}
+
+fn parse_template(template: &str) -> Vec<TemplatePart> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find('$') {
+        literal.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            literal.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        if name.is_empty() {
+            literal.push('$');
+            continue;
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+        }
+        parts.push(TemplatePart::Group(name.to_string()));
+        rest = after;
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    parts
+}
+
+/// Whether `replacement` refers to every capture group of `pattern`, so no
+/// part of the matched path is lost by the rewrite
+fn uses_all_groups(pattern: &Regex, replacement: &str) -> bool {
+    let used: Vec<String> = parse_template(replacement)
+        .into_iter()
+        .filter_map(|part| match part {
+            TemplatePart::Group(name) => Some(name),
+            TemplatePart::Literal(_) => None,
+        })
+        .collect();
+    pattern
+        .capture_names()
+        .enumerate()
+        .skip(1)
+        .all(|(index, name)| {
+            used.iter()
+                .any(|u| *u == index.to_string() || Some(u.as_str()) == name)
+        })
+}
+
+fn rewrite_path(pattern: &Regex, replacement: &str, path: &str) -> Option<String> {
+    let captures = pattern.captures(path)?;
+    let mut rewritten = String::new();
+    captures.expand(replacement, &mut rewritten);
+    Some(rewritten)
+}
+
+/// Check that two rewrites undo each other.  For each side, a path is built
+/// from the replacement template by substituting capture group references
+/// with placeholders, and must be rewritten back to itself by the two
+/// rewrites.  Placeholders made of letters and of digits are tried, as
+/// capture groups may be restricted to either.
+fn is_inverse_rewrite(
+    pattern: &Regex,
+    replacement: &str,
+    reverse_pattern: &Regex,
+    reverse_replacement: &str,
+) -> bool {
+    let round_trips = |template: &str, there: (&Regex, &str), back: (&Regex, &str), c: char| {
+        let mut groups: Vec<String> = Vec::new();
+        let path: String = parse_template(template)
+            .into_iter()
+            .map(|part| match part {
+                TemplatePart::Literal(literal) => literal,
+                TemplatePart::Group(name) => {
+                    let index = match groups.iter().position(|g| g == &name) {
+                        Some(index) => index,
+                        None => {
+                            groups.push(name);
+                            groups.len() - 1
+                        }
+                    };
+                    c.to_string().repeat(index + 1)
+                }
+            })
+            .collect();
+        rewrite_path(there.0, there.1, &path)
+            .and_then(|rewritten| rewrite_path(back.0, back.1, &rewritten))
+            == Some(path)
+    };
+    let forward = (pattern, replacement);
+    let reverse = (reverse_pattern, reverse_replacement);
+    ['x', '1'].iter().any(|c| {
+        round_trips(replacement, reverse, forward, *c)
+            && round_trips(reverse_replacement, forward, reverse, *c)
+    })
+}
+
+/// Convert a regex rule.  Regex rewrites can't be inverted automatically,
+/// so they must specify the reverse rewrite explicitly, which is checked
+/// to undo the rewrite.
+fn convert_regex_path_rule(
+    pattern: String,
+    replacement: Option<String>,
+    reverse_pattern: Option<String>,
+    reverse_replacement: Option<String>,
+) -> Result<CommitSyncPathRule> {
+    let regex = compile_regex(&format!("^(?:{})$", pattern))?;
+    let action = match (replacement, reverse_pattern, reverse_replacement) {
+        (None, None, None) => CommitSyncPathRuleAction::DoNotSync,
+        (None, _, _) => {
+            return Err(anyhow!(
+                "regex rule {:?} does not sync paths, so it must not have a reverse rewrite",
+                pattern
+            ));
+        }
+        (Some(replacement), Some(reverse_pattern), Some(reverse_replacement)) => {
+            let reverse_pattern = compile_regex(&format!("^(?:{})$", reverse_pattern))?;
+            if !uses_all_groups(&regex, &replacement) {
+                return Err(anyhow!(
+                    "regex rule {:?} -> {:?} is not invertible: the replacement must use every capture group of the pattern",
+                    pattern,
+                    replacement
+                ));
+            }
+            if !is_inverse_rewrite(&regex, &replacement, &reverse_pattern, &reverse_replacement) {
+                return Err(anyhow!(
+                    "regex rule {:?} -> {:?} is not invertible: {:?} -> {:?} does not rewrite paths back",
+                    pattern,
+                    replacement,
+                    reverse_pattern.as_str(),
+                    reverse_replacement
+                ));
+            }
+            CommitSyncPathRuleAction::Rewrite {
+                replacement,
+                reverse_pattern,
+                reverse_replacement,
+            }
+        }
+        (Some(replacement), _, _) => {
+            return Err(anyhow!(
+                "regex rule {:?} -> {:?} is not invertible: reverse_pattern and reverse_replacement must be provided",
+                pattern,
+                replacement
+            ));
+        }
+    };
+    Ok(CommitSyncPathRule {
+        pattern: regex,
+        action,
+    })
+}
+
+impl Convert for RawCommitSyncPathRule {
+    type Output = CommitSyncPathRule;
+
+    fn convert(self) -> Result<Self::Output> {
+        let RawCommitSyncPathRule {
+            kind,
+            pattern,
+            replacement,
+            reverse_pattern,
+            reverse_replacement,
+        } = self;
+
+        match kind.as_str() {
+            "glob" => {
+                if reverse_pattern.is_some() || reverse_replacement.is_some() {
+                    return Err(anyhow!(
+                        "glob rule {:?} must not specify a reverse rewrite, it is inverted automatically",
+                        pattern
+                    ));
+                }
+                convert_glob_path_rule(pattern, replacement)
+            }
+            "regex" => {
+                convert_regex_path_rule(pattern, replacement, reverse_pattern, reverse_replacement)
+            }
+            other => Err(anyhow!("unknown path rule kind: {:?}", other)),
+        }
+    }
+}
+
 impl Convert for RawCommonCommitSyncConfig {
     type Output = CommonCommitSyncConfig;
 
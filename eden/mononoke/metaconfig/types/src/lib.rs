@@ -1257,6 +1257,41 @@ pub struct SmallRepoCommitSyncConfig {
     pub default_action: DefaultSmallToLargeCommitSyncPathAction,
     /// A map of prefix replacements when syncing
     pub map: HashMap<MPath, MPath>,
+    /// An ordered list of pattern-based rules.  The first rule which
+    /// matches a path takes precedence over both `map` and `default_action`
+    pub path_rules: Vec<CommitSyncPathRule>,
+}
+
+/// A rule to rewrite small repo paths which match a pattern when syncing
+/// commits from small to large repos.
+///
+/// Glob patterns in the config are translated into regexes at config load
+/// time, so the rule is always expressed in terms of regexes here.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommitSyncPathRule {
+    /// Regex, matched against the whole small repo path
+    pub pattern: ComparableRegex,
+    /// What to do with the paths which match `pattern`
+    pub action: CommitSyncPathRuleAction,
+}
+
+/// Action to perform on a path that matches a `CommitSyncPathRule`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CommitSyncPathRuleAction {
+    /// Rewrite the path
+    Rewrite {
+        /// Replacement template for the small repo path; `$N` and `${name}`
+        /// refer to the capture groups of the rule pattern
+        replacement: String,
+        /// Regex, matched against the whole large repo path, used to
+        /// rewrite paths when syncing from large to small repos
+        reverse_pattern: ComparableRegex,
+        /// Replacement template for the large repo path; capture groups
+        /// refer to `reverse_pattern`
+        reverse_replacement: String,
+    },
+    /// Do not sync the path
+    DoNotSync,
 }
 
 /// Commit sync direction
@@ -20,6 +20,7 @@ use cloned::cloned;
 use context::{CoreContext, PerfCounterType};
 use derived_data::BonsaiDerived;
 use fsnodes::RootFsnodeId;
+use futures::compat::Stream01CompatExt;
 use futures::future::{self, try_join, try_join_all, FutureExt, Shared};
 use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use manifest::{Diff as ManifestDiff, Entry as ManifestEntry, ManifestOps, PathOrPrefix};
@@ -29,6 +30,7 @@ pub use mononoke_types::Generation;
 use mononoke_types::{BonsaiChangeset, FileChange, MPath, MPathElement, Svnrev};
 use reachabilityindex::ReachabilityIndex;
 use repo_derived_data::RepoDerivedDataRef;
+use revset::RangeNodeStream;
 use skeleton_manifest::RootSkeletonManifestId;
 use sorted_vector_map::SortedVectorMap;
 use tunables::tunables;
@@ -437,6 +439,54 @@ impl ChangesetContext {
         Ok(is_ancestor_of)
     }
 
+    /// Returns all commits that are descendants of this commit and ancestors
+    /// of `descendant`, including both of them.
+    ///
+    /// Commits are returned in reverse topological order, i.e. descendants
+    /// come before their ancestors.  The first `skip` commits are omitted
+    /// and at most `limit` commits are returned.  If this commit is not an
+    /// ancestor of `descendant`, the result is empty.
+    pub async fn range_to(
+        &self,
+        descendant: ChangesetId,
+        skip: u64,
+        limit: u64,
+    ) -> Result<Vec<ChangesetId>, MononokeError> {
+        if !tunables().get_segmented_changelog_disable_for_server_side_ops() {
+            let segmented_changelog = self.repo().segmented_changelog();
+            // If we have segmeneted changelog enabled...
+            if !segmented_changelog.disabled(&self.ctx()).await? {
+                // ... and it has the answer for us ...
+                if let Some(result) = segmented_changelog
+                    .range(&self.ctx(), self.id, descendant, skip, limit)
+                    .await?
+                {
+                    self.ctx()
+                        .perf_counters()
+                        .increment_counter(PerfCounterType::SegmentedChangelogServerSideOpsHits);
+                    // ... it's cheaper to return it.
+                    return Ok(result);
+                }
+                self.ctx()
+                    .perf_counters()
+                    .increment_counter(PerfCounterType::SegmentedChangelogServerSideOpsFallbacks);
+            }
+        }
+
+        let range = RangeNodeStream::new(
+            self.ctx().clone(),
+            self.repo().blob_repo().get_changeset_fetcher(),
+            self.id,
+            descendant,
+        )
+        .compat()
+        .skip(skip as usize)
+        .take(limit as usize)
+        .try_collect()
+        .await?;
+        Ok(range)
+    }
+
     /// Returns the lowest common ancestor of two commits.
     ///
     /// In case of ambiguity (can happen with multiple merges of the same branches) returns the
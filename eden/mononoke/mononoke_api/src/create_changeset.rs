@@ -329,6 +329,9 @@ impl RepoContext {
             )));
         }
 
+        let paths: Vec<_> = changes.keys().map(|path| path.to_string()).collect();
+        self.check_path_permissions(&paths).await?;
+
         // Obtain contexts for each of the parents (which should exist).
         let parent_ctxs: Vec<_> = parents
             .iter()
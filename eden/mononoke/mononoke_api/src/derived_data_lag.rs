@@ -0,0 +1,377 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+use blobstore::Loadable;
+use bookmarks::{BookmarkKind, BookmarkName, BookmarkPagination, BookmarkPrefix, Freshness};
+use chrono::{DateTime, FixedOffset};
+use derived_data_utils::{derived_data_utils, DerivedUtils};
+use futures::stream::TryStreamExt;
+use mononoke_types::ChangesetId;
+use segmented_changelog::{Location, SegmentedChangelog};
+
+use crate::errors::MononokeError;
+use crate::repo::RepoContext;
+
+/// How far the derived data of a single type lags behind a bookmark.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DerivedDataLag {
+    /// Name of the derived data type.
+    pub derived_data_type: String,
+
+    /// Name of the bookmark.
+    pub bookmark: String,
+
+    /// The commit the bookmark currently points to.
+    pub head: ChangesetId,
+
+    /// The oldest ancestor of the bookmark that does not have data
+    /// derived.  `None` if the data for the bookmark is fully derived.
+    pub oldest_underived: Option<ChangesetId>,
+
+    /// Number of generations between the bookmark and the derived
+    /// frontier.  Zero if the data for the bookmark is fully derived.
+    pub underived_generations: u64,
+
+    /// Author date of the oldest underived commit.
+    pub oldest_underived_date: Option<DateTime<FixedOffset>>,
+}
+
+impl DerivedDataLag {
+    /// Returns true if the data for the bookmark is fully derived.
+    pub fn is_derived(&self) -> bool {
+        self.oldest_underived.is_none()
+    }
+}
+
+impl RepoContext {
+    /// Report how far behind each bookmark the derived data frontier is.
+    ///
+    /// If `derived_data_types` is `None`, all types enabled in the repo's
+    /// derived data config are reported.  If `bookmarks` is `None`, all
+    /// publishing bookmarks are reported.
+    ///
+    /// Derived data is always derived for ancestors before descendants, so
+    /// the set of underived commits is closed under descendants.  This
+    /// allows the frontier to be found without visiting every underived
+    /// commit: the segmented changelog is used to binary search along
+    /// first-parent chains, falling back to the skiplist index (or plain
+    /// parent traversal) when the segmented changelog doesn't cover the
+    /// bookmark.
+    pub async fn derived_data_lag(
+        &self,
+        derived_data_types: Option<Vec<String>>,
+        bookmarks: Option<Vec<BookmarkName>>,
+    ) -> Result<Vec<DerivedDataLag>, MononokeError> {
+        let enabled = &self.blob_repo().get_derived_data_config().enabled.types;
+        let derived_data_types: BTreeSet<String> = match derived_data_types {
+            Some(types) => {
+                for ty in types.iter() {
+                    if !enabled.contains(ty) {
+                        return Err(MononokeError::InvalidRequest(format!(
+                            "derived data type '{}' is not enabled",
+                            ty
+                        )));
+                    }
+                }
+                types.into_iter().collect()
+            }
+            None => enabled.iter().cloned().collect(),
+        };
+
+        let bookmarks = self.derived_data_lag_bookmarks(bookmarks).await?;
+
+        let mut lags = Vec::new();
+        for ty in derived_data_types {
+            let utils = derived_data_utils(self.ctx().fb, self.blob_repo(), &ty)?;
+            for (bookmark, head) in bookmarks.iter() {
+                let oldest_underived = self.find_oldest_underived(&utils, *head).await?;
+                let lag = match oldest_underived {
+                    Some(oldest) => {
+                        let fetcher = self.blob_repo().get_changeset_fetcher();
+                        let head_gen = fetcher
+                            .get_generation_number(self.ctx().clone(), *head)
+                            .await?;
+                        let oldest_gen = fetcher
+                            .get_generation_number(self.ctx().clone(), oldest)
+                            .await?;
+                        let bonsai = oldest
+                            .load(self.ctx(), self.blob_repo().blobstore())
+                            .await?;
+                        DerivedDataLag {
+                            derived_data_type: ty.clone(),
+                            bookmark: bookmark.to_string(),
+                            head: *head,
+                            oldest_underived: Some(oldest),
+                            underived_generations: head_gen.value() - oldest_gen.value() + 1,
+                            oldest_underived_date: Some(*bonsai.author_date().as_chrono()),
+                        }
+                    }
+                    None => DerivedDataLag {
+                        derived_data_type: ty.clone(),
+                        bookmark: bookmark.to_string(),
+                        head: *head,
+                        oldest_underived: None,
+                        underived_generations: 0,
+                        oldest_underived_date: None,
+                    },
+                };
+                lags.push(lag);
+            }
+        }
+        Ok(lags)
+    }
+
+    async fn derived_data_lag_bookmarks(
+        &self,
+        bookmarks: Option<Vec<BookmarkName>>,
+    ) -> Result<Vec<(BookmarkName, ChangesetId)>, MononokeError> {
+        match bookmarks {
+            Some(bookmarks) => {
+                let mut resolved = Vec::new();
+                for bookmark in bookmarks {
+                    let cs_id = self
+                        .blob_repo()
+                        .bookmarks()
+                        .get(self.ctx().clone(), &bookmark)
+                        .await?
+                        .ok_or_else(|| {
+                            MononokeError::InvalidRequest(format!(
+                                "bookmark '{}' does not exist",
+                                bookmark
+                            ))
+                        })?;
+                    resolved.push((bookmark, cs_id));
+                }
+                Ok(resolved)
+            }
+            None => Ok(self
+                .blob_repo()
+                .bookmarks()
+                .list(
+                    self.ctx().clone(),
+                    Freshness::MostRecent,
+                    &BookmarkPrefix::empty(),
+                    BookmarkKind::ALL_PUBLISHING,
+                    &BookmarkPagination::FromStart,
+                    std::u64::MAX,
+                )
+                .map_ok(|(bookmark, cs_id)| (bookmark.into_name(), cs_id))
+                .try_collect()
+                .await?),
+        }
+    }
+
+    /// Find the oldest underived ancestor of `head`, or `None` if `head` is
+    /// derived.
+    async fn find_oldest_underived(
+        &self,
+        utils: &Arc<dyn DerivedUtils>,
+        head: ChangesetId,
+    ) -> Result<Option<ChangesetId>, MononokeError> {
+        if !self.is_underived(utils, vec![head]).await?.contains(&head) {
+            return Ok(None);
+        }
+        if !self.segmented_changelog_disabled().await? {
+            // Segmented changelog may not know about this bookmark (e.g. if
+            // it is not an ancestor of the master bookmark), in which case
+            // fall back to the skiplist.
+            let known = self
+                .segmented_changelog()
+                .is_ancestor(self.ctx(), head, head)
+                .await?;
+            if known.is_some() {
+                return Ok(Some(
+                    self.find_oldest_underived_segmented(utils, head).await?,
+                ));
+            }
+        }
+        Ok(Some(
+            self.find_oldest_underived_skiplist(utils, head).await?,
+        ))
+    }
+
+    /// Search the underived ancestors of an underived `head` using
+    /// segmented changelog locations.
+    ///
+    /// Each first-parent chain is binary searched for its oldest underived
+    /// commit.  Merges on the underived part of a chain may have more
+    /// underived ancestors through their other parents, so the chains of
+    /// those parents are searched too.  The underived commit with the
+    /// lowest generation number is returned.
+    async fn find_oldest_underived_segmented(
+        &self,
+        utils: &Arc<dyn DerivedUtils>,
+        head: ChangesetId,
+    ) -> Result<ChangesetId, MononokeError> {
+        let fetcher = self.blob_repo().get_changeset_fetcher();
+        let head_gen = fetcher
+            .get_generation_number(self.ctx().clone(), head)
+            .await?;
+        let mut oldest = (head_gen, head);
+        let mut visited = HashSet::new();
+        let mut heads = vec![head];
+        while let Some(head) = heads.pop() {
+            if visited.contains(&head) {
+                continue;
+            }
+            let (distance, chain_oldest) =
+                self.find_oldest_underived_first_parent(utils, head).await?;
+            let chain = self
+                .location_to_changeset_id(Location::new(head, 0), distance + 1)
+                .await?;
+            let mut merge_parents = Vec::new();
+            for cs_id in chain {
+                if !visited.insert(cs_id) {
+                    // The rest of the chain was searched from another head.
+                    break;
+                }
+                let parents = fetcher.get_parents(self.ctx().clone(), cs_id).await?;
+                merge_parents.extend(parents.into_iter().skip(1));
+            }
+            heads.extend(self.is_underived(utils, merge_parents).await?);
+            let chain_oldest_gen = fetcher
+                .get_generation_number(self.ctx().clone(), chain_oldest)
+                .await?;
+            if chain_oldest_gen < oldest.0 {
+                oldest = (chain_oldest_gen, chain_oldest);
+            }
+        }
+        Ok(oldest.1)
+    }
+
+    /// Binary search the first-parent chain of an underived `head` using
+    /// segmented changelog locations.  Returns the oldest underived commit
+    /// on the chain and its distance from `head`.
+    async fn find_oldest_underived_first_parent(
+        &self,
+        utils: &Arc<dyn DerivedUtils>,
+        head: ChangesetId,
+    ) -> Result<(u64, ChangesetId), MononokeError> {
+        // Invariant: `head~low` is underived, and `head~high` is either
+        // derived or past the root of the first-parent chain.
+        let mut low = 0;
+        let mut low_cs_id = head;
+        let mut high = 1;
+        loop {
+            match self
+                .first_parent_ancestor(head, high, (low, low_cs_id))
+                .await?
+            {
+                Some(cs_id) => {
+                    if self.is_underived(utils, vec![cs_id]).await?.is_empty() {
+                        break;
+                    }
+                    low = high;
+                    low_cs_id = cs_id;
+                    high *= 2;
+                }
+                None => break,
+            }
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            match self
+                .first_parent_ancestor(head, mid, (low, low_cs_id))
+                .await?
+            {
+                Some(cs_id) if !self.is_underived(utils, vec![cs_id]).await?.is_empty() => {
+                    low = mid;
+                    low_cs_id = cs_id;
+                }
+                _ => high = mid,
+            }
+        }
+        Ok((low, low_cs_id))
+    }
+
+    /// Returns `head~distance`, or `None` if the first-parent chain of `head`
+    /// is shorter than `distance`.  `known` is `(d, head~d)` for some `d`
+    /// less than `distance`.
+    async fn first_parent_ancestor(
+        &self,
+        head: ChangesetId,
+        distance: u64,
+        known: (u64, ChangesetId),
+    ) -> Result<Option<ChangesetId>, MononokeError> {
+        match self
+            .location_to_changeset_id(Location::new(head, distance), 1)
+            .await
+        {
+            Ok(cs_ids) => Ok(cs_ids.into_iter().next()),
+            Err(err) => {
+                // Segmented changelog does not tell a first-parent chain
+                // that is too short apart from other errors.  Follow the
+                // first parents from the known ancestor to find out.  This
+                // only happens if the underived commits reach the root.
+                let fetcher = self.blob_repo().get_changeset_fetcher();
+                let (known_distance, mut cs_id) = known;
+                for _ in known_distance..distance {
+                    let parents = fetcher.get_parents(self.ctx().clone(), cs_id).await?;
+                    match parents.first() {
+                        Some(parent) => cs_id = *parent,
+                        None => return Ok(None),
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Walk from an underived `head` towards the derived frontier, jumping
+    /// along skiplist edges where they are available.
+    async fn find_oldest_underived_skiplist(
+        &self,
+        utils: &Arc<dyn DerivedUtils>,
+        head: ChangesetId,
+    ) -> Result<ChangesetId, MononokeError> {
+        let fetcher = self.blob_repo().get_changeset_fetcher();
+        let mut current = head;
+        loop {
+            if let Some(edges) = self.skiplist_index().get_skip_edges(current) {
+                // Skip edges all point to ancestors of `current`.  Jump to
+                // the furthest one that is still underived.
+                let underived = self
+                    .is_underived(utils, edges.iter().map(|(cs_id, _)| *cs_id).collect())
+                    .await?;
+                let furthest = edges
+                    .iter()
+                    .filter(|(cs_id, _)| underived.contains(cs_id))
+                    .min_by_key(|(_, generation)| *generation);
+                if let Some((cs_id, _)) = furthest {
+                    if *cs_id != current {
+                        current = *cs_id;
+                        continue;
+                    }
+                }
+            }
+            let parents = fetcher.get_parents(self.ctx().clone(), current).await?;
+            let underived = self.is_underived(utils, parents.clone()).await?;
+            match parents.into_iter().find(|p| underived.contains(p)) {
+                Some(parent) => current = parent,
+                None => return Ok(current),
+            }
+        }
+    }
+
+    async fn is_underived(
+        &self,
+        utils: &Arc<dyn DerivedUtils>,
+        cs_ids: Vec<ChangesetId>,
+    ) -> Result<BTreeSet<ChangesetId>, MononokeError> {
+        if cs_ids.is_empty() {
+            return Ok(BTreeSet::new());
+        }
+        Ok(utils
+            .pending(self.ctx().clone(), self.blob_repo().clone(), cs_ids)
+            .await?
+            .into_iter()
+            .collect())
+    }
+}
@@ -53,6 +53,14 @@ pub enum MononokeError {
         identities: String,
         reponame: String,
     },
+    #[error(
+        "permission denied: modifying {paths} in repo {reponame} not permitted for {identities}"
+    )]
+    PathPermissionDenied {
+        paths: String,
+        identities: String,
+        reponame: String,
+    },
     #[error(
         "permission denied: access to repo {reponame} on behalf of {service_identity} not permitted for {identities}"
     )]
@@ -29,6 +29,7 @@ pub mod changeset;
 pub mod changeset_path;
 pub mod changeset_path_diff;
 pub mod create_changeset;
+pub mod derived_data_lag;
 pub mod errors;
 pub mod file;
 pub mod path;
@@ -50,6 +51,7 @@ pub use crate::changeset_path::{
 };
 pub use crate::changeset_path_diff::ChangesetPathDiffContext;
 pub use crate::create_changeset::{CreateChange, CreateChangeFile, CreateCopyInfo};
+pub use crate::derived_data_lag::DerivedDataLag;
 pub use crate::errors::MononokeError;
 pub use crate::file::{
     headerless_unified_diff, FileContext, FileId, FileMetadata, FileType, HeaderlessUnifiedDiff,
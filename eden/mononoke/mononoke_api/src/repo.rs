@@ -450,6 +450,11 @@ impl Repo {
         &self.readonly_fetcher
     }
 
+    /// The permission checker for the repo's ACL.
+    pub fn permission_checker(&self) -> &ArcPermissionChecker {
+        &self.repo_permission_checker
+    }
+
     /// The configuration for the referenced repository.
     pub fn config(&self) -> &RepoConfig {
         &self.config
@@ -696,6 +701,38 @@ impl Repo {
         Ok(())
     }
 
+    async fn check_path_permissions(
+        &self,
+        ctx: &CoreContext,
+        paths: &[String],
+    ) -> Result<(), MononokeError> {
+        let identities = ctx.metadata().identities();
+
+        let denied = self
+            .repo_permission_checker
+            .denied_paths(&*identities, paths)
+            .await?;
+        if !denied.is_empty() {
+            debug!(
+                ctx.logger(),
+                "Permission denied: modifying {} in {}",
+                denied.join(","),
+                self.name
+            );
+            let identities = if identities.is_empty() {
+                "<none>".to_string()
+            } else {
+                identities.iter().join(",")
+            };
+            return Err(MononokeError::PathPermissionDenied {
+                paths: denied.join(", "),
+                identities,
+                reponame: self.name.clone(),
+            });
+        }
+        Ok(())
+    }
+
     async fn check_service_permissions(
         &self,
         ctx: &CoreContext,
@@ -1465,6 +1502,15 @@ impl RepoContext {
         Ok(RepoWriteContext::new(self, PermissionsModel::AllowAnyWrite))
     }
 
+    /// Check the user is permitted to modify these paths by the path
+    /// restrictions of the repo's ACL.
+    pub(crate) async fn check_path_permissions(
+        &self,
+        paths: &[String],
+    ) -> Result<(), MononokeError> {
+        self.repo.check_path_permissions(&self.ctx, paths).await
+    }
+
     /// Get a write context to make changes to this repository on behalf of a service.
     pub async fn service_write(
         mut self,
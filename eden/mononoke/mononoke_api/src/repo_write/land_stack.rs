@@ -5,7 +5,7 @@
  * GNU General Public License version 2.
  */
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
 use blobstore::Loadable;
@@ -87,6 +87,14 @@ impl RepoWriteContext {
         .try_collect()
         .await?;
 
+        let paths: Vec<_> = changesets
+            .iter()
+            .flat_map(|bcs| bcs.file_changes().map(|(path, _)| path.to_string()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.check_path_permissions(&paths).await?;
+
         // Pushrebase these commits onto the bookmark.
         let mut op = bookmarks_movement::PushrebaseOntoBookmarkOp::new(&bookmark, changesets)
             .with_pushvars(pushvars);
@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+mod test_derived_data_lag;
 mod test_file_diff;
 mod test_history;
 mod test_repo;
@@ -0,0 +1,236 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Error;
+use derived_data::BonsaiDerived;
+use fbinit::FacebookInit;
+use fixtures::{linear, merge_uneven};
+use fsnodes::RootFsnodeId;
+use metaconfig_types::SegmentedChangelogConfig;
+use repo_identity::RepoIdentity;
+use segmented_changelog::{
+    new_server_segmented_changelog, Location, SegmentedChangelogSqlConnections,
+};
+use sql_construct::SqlConstruct;
+
+use crate::{BookmarkName, ChangesetId, CoreContext, HgChangesetId, Mononoke, Repo, RepoContext};
+
+#[fbinit::test]
+async fn derived_data_lag(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let mononoke = Mononoke::new_test(
+        ctx.clone(),
+        vec![("test".to_string(), linear::getrepo(fb).await)],
+    )
+    .await?;
+    let repo = mononoke
+        .repo(ctx.clone(), "test")
+        .await?
+        .expect("repo exists");
+    let master = BookmarkName::new("master")?;
+    let root = repo
+        .changeset(HgChangesetId::from_str(
+            "2d7d4ba9ce0a6ffd222de7785b249ead9c51c536",
+        )?)
+        .await?
+        .expect("root exists")
+        .id();
+    let head = repo
+        .resolve_bookmark("master", crate::BookmarkFreshness::MostRecent)
+        .await?
+        .expect("master exists")
+        .id();
+
+    // Nothing has been derived yet, so the whole history is lagging.
+    let lags = repo
+        .derived_data_lag(
+            Some(vec![RootFsnodeId::NAME.to_string()]),
+            Some(vec![master.clone()]),
+        )
+        .await?;
+    assert_eq!(lags.len(), 1);
+    assert_eq!(lags[0].derived_data_type, RootFsnodeId::NAME);
+    assert_eq!(lags[0].bookmark, "master");
+    assert_eq!(lags[0].head, head);
+    assert_eq!(lags[0].oldest_underived, Some(root));
+    assert_eq!(lags[0].underived_generations, 11);
+    assert!(lags[0].oldest_underived_date.is_some());
+
+    // Derive the parent of master: only master itself should lag.
+    let parent = repo
+        .changeset(head)
+        .await?
+        .expect("head exists")
+        .parents()
+        .await?[0];
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), parent).await?;
+    let lags = repo
+        .derived_data_lag(
+            Some(vec![RootFsnodeId::NAME.to_string()]),
+            Some(vec![master.clone()]),
+        )
+        .await?;
+    assert_eq!(lags[0].oldest_underived, Some(head));
+    assert_eq!(lags[0].underived_generations, 1);
+
+    // Derive master: nothing lags.
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), head).await?;
+    let lags = repo
+        .derived_data_lag(
+            Some(vec![RootFsnodeId::NAME.to_string()]),
+            Some(vec![master]),
+        )
+        .await?;
+    assert!(lags[0].is_derived());
+    assert_eq!(lags[0].underived_generations, 0);
+
+    // Types which are not enabled are rejected.
+    assert!(repo
+        .derived_data_lag(Some(vec!["not_a_type".to_string()]), None)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+async fn merge_uneven_repo(fb: FacebookInit) -> Result<(CoreContext, RepoContext), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let mononoke = Mononoke::new_test(
+        ctx.clone(),
+        vec![("test".to_string(), merge_uneven::getrepo(fb).await)],
+    )
+    .await?;
+    let repo = mononoke
+        .repo(ctx.clone(), "test")
+        .await?
+        .expect("repo exists");
+    Ok((ctx, repo))
+}
+
+async fn merge_uneven_repo_segmented(
+    fb: FacebookInit,
+) -> Result<(CoreContext, RepoContext), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let blob_repo = merge_uneven::getrepo(fb).await;
+    let mut repo = Repo::new_test(ctx.clone(), blob_repo.clone()).await?;
+    repo.inner.segmented_changelog = new_server_segmented_changelog(
+        fb,
+        &ctx,
+        &RepoIdentity::new(blob_repo.get_repoid(), "test".to_string()),
+        SegmentedChangelogConfig {
+            enabled: true,
+            skip_dag_load_at_startup: true,
+            ..Default::default()
+        },
+        SegmentedChangelogSqlConnections::with_sqlite_in_memory()?,
+        blob_repo.get_changeset_fetcher(),
+        blob_repo.bookmarks().clone(),
+        Arc::new(blob_repo.get_blobstore()),
+        None,
+    )
+    .await?;
+    let repo = RepoContext::new(ctx.clone(), Arc::new(repo)).await?;
+
+    // The dag is built on demand.  Build it up to master so that the lag
+    // search finds master in it.
+    let master = repo
+        .resolve_bookmark("master", crate::BookmarkFreshness::MostRecent)
+        .await?
+        .expect("master exists")
+        .id();
+    repo.location_to_changeset_id(Location::new(master, 0), 1)
+        .await?;
+    assert!(!repo.segmented_changelog_disabled().await?);
+    Ok((ctx, repo))
+}
+
+async fn resolve(repo: &RepoContext, hg_id: &str) -> Result<ChangesetId, Error> {
+    Ok(repo
+        .changeset(HgChangesetId::from_str(hg_id)?)
+        .await?
+        .expect("changeset exists")
+        .id())
+}
+
+async fn master_lag(repo: &RepoContext) -> Result<(Option<ChangesetId>, u64), Error> {
+    let lags = repo
+        .derived_data_lag(
+            Some(vec![RootFsnodeId::NAME.to_string()]),
+            Some(vec![BookmarkName::new("master")?]),
+        )
+        .await?;
+    Ok((lags[0].oldest_underived, lags[0].underived_generations))
+}
+
+// In the merge_uneven fixture, master is a merge whose first parent is the
+// head of "Branch 2" (8 commits) and whose second parent is the head of
+// "Branch 1" (3 commits).
+const BRANCH1_ROOT: &str = "3cda5c78aa35f0f5b09780d971197b51cad4613a";
+const BRANCH1_HEAD: &str = "16839021e338500b3cf7c9b871c8a07351697d68";
+const BRANCH2_ROOT: &str = "d7542c9db7f4c77dab4b315edd328edf1514952f";
+const BRANCH2_HEAD: &str = "264f01429683b3dd8042cb3979e8bf37007118bc";
+const MERGE: &str = "d35b1875cdd1ed2c687e86f1604b9d7e989450cb";
+
+#[fbinit::test]
+async fn derived_data_lag_first_parent(fb: FacebookInit) -> Result<(), Error> {
+    let (ctx, repo) = merge_uneven_repo(fb).await?;
+
+    // The second parent is derived: the first-parent chain lags.
+    let branch1_head = resolve(&repo, BRANCH1_HEAD).await?;
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), branch1_head).await?;
+    let branch2_root = resolve(&repo, BRANCH2_ROOT).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(branch2_root), 9));
+
+    // Both parents are derived: only the merge lags.
+    let branch2_head = resolve(&repo, BRANCH2_HEAD).await?;
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), branch2_head).await?;
+    let merge = resolve(&repo, MERGE).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(merge), 1));
+
+    Ok(())
+}
+
+#[fbinit::test]
+async fn derived_data_lag_second_parent(fb: FacebookInit) -> Result<(), Error> {
+    let (ctx, repo) = merge_uneven_repo(fb).await?;
+
+    // The first parent is derived: the second-parent chain, which is much
+    // shorter than the merge's generation number, lags.
+    let branch2_head = resolve(&repo, BRANCH2_HEAD).await?;
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), branch2_head).await?;
+    let branch1_root = resolve(&repo, BRANCH1_ROOT).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(branch1_root), 9));
+
+    Ok(())
+}
+
+#[fbinit::test]
+async fn derived_data_lag_segmented(fb: FacebookInit) -> Result<(), Error> {
+    let (ctx, repo) = merge_uneven_repo_segmented(fb).await?;
+
+    // Nothing is derived: the first-parent chain is searched first.
+    let branch2_root = resolve(&repo, BRANCH2_ROOT).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(branch2_root), 9));
+
+    // The first parent is derived: the search must continue from the merge
+    // into the second-parent chain.
+    let branch2_head = resolve(&repo, BRANCH2_HEAD).await?;
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), branch2_head).await?;
+    let branch1_root = resolve(&repo, BRANCH1_ROOT).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(branch1_root), 9));
+
+    // Both parents are derived: only the merge lags.
+    let branch1_head = resolve(&repo, BRANCH1_HEAD).await?;
+    RootFsnodeId::derive(&ctx, repo.blob_repo(), branch1_head).await?;
+    let merge = resolve(&repo, MERGE).await?;
+    assert_eq!(master_lag(&repo).await?, (Some(merge), 1));
+
+    Ok(())
+}
@@ -204,6 +204,71 @@ async fn commit_is_ancestor_of(fb: FacebookInit) -> Result<(), Error> {
     Ok(())
 }
 
+#[fbinit::test]
+async fn commit_range_to(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let mononoke = Mononoke::new_test(
+        ctx.clone(),
+        vec![("test".to_string(), branch_uneven::getrepo(fb).await)],
+    )
+    .await?;
+    let repo = mononoke.repo(ctx, "test").await?.expect("repo exists");
+    let mut changesets = Vec::new();
+    for hg_hash in [
+        "5d43888a3c972fe68c224f93d41b30e9f888df7c", // 0: branch 1 near top
+        "d7542c9db7f4c77dab4b315edd328edf1514952f", // 1: branch 1 near bottom
+        "1d8a907f7b4bf50c6a09c16361e2205047ecc5e5", // 2: branch 2
+        "15c40d0abc36d47fb51c8eaec51ac7aad31f669c", // 3: base
+    ] {
+        let changeset = repo
+            .changeset(HgChangesetId::from_str(hg_hash)?)
+            .await?
+            .expect("changeset exists");
+        changesets.push(changeset);
+    }
+
+    let range = changesets[3]
+        .range_to(changesets[0].id(), 0, u64::MAX)
+        .await?;
+    assert_eq!(range.first(), Some(&changesets[0].id()));
+    assert_eq!(range.last(), Some(&changesets[3].id()));
+    assert!(range.contains(&changesets[1].id()));
+    assert!(!range.contains(&changesets[2].id()));
+    let mut generations = Vec::new();
+    for cs_id in range.iter() {
+        let changeset = repo.changeset(*cs_id).await?.expect("changeset exists");
+        generations.push(changeset.generation().await?.value());
+    }
+    assert!(generations.windows(2).all(|w| w[0] > w[1]));
+
+    // Pages concatenate to the full range.
+    let mut paged = Vec::new();
+    for skip in (0..range.len() as u64 + 2).step_by(2) {
+        paged.extend(changesets[3].range_to(changesets[0].id(), skip, 2).await?);
+    }
+    assert_eq!(paged, range);
+
+    assert_eq!(
+        changesets[0]
+            .range_to(changesets[0].id(), 0, u64::MAX)
+            .await?,
+        vec![changesets[0].id()]
+    );
+    assert!(
+        changesets[2]
+            .range_to(changesets[0].id(), 0, u64::MAX)
+            .await?
+            .is_empty()
+    );
+    assert!(
+        changesets[0]
+            .range_to(changesets[3].id(), 0, u64::MAX)
+            .await?
+            .is_empty()
+    );
+    Ok(())
+}
+
 #[fbinit::test]
 async fn commit_find_files(fb: FacebookInit) -> Result<(), Error> {
     let ctx = CoreContext::test_mock(fb);
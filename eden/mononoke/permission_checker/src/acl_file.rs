@@ -0,0 +1,391 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! ACLs defined in a local JSON file, for deployments that don't have an
+//! external ACL service. The file looks like:
+//!
+//! ```json
+//! {
+//!   "groups": {
+//!     "engineers": ["USER:alice", "USER:bob"],
+//!     "release": ["USER:carol", "SERVICE_IDENTITY:release-bot"]
+//!   },
+//!   "acls": {
+//!     "my_repo": {
+//!       "actions": {
+//!         "read": ["engineers", "release"],
+//!         "write": ["engineers", "release"],
+//!         "bypass_hooks": ["release"]
+//!       },
+//!       "path_restrictions": [
+//!         {"path_prefix": "release/config", "writers": ["release"]}
+//!       ]
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! ACLs are looked up by name, which is the `hipster_acl` of a repo, or the
+//! tier name for tier ACLs. Path restrictions only allow the listed groups to
+//! modify files under the path prefix, even if others may write to the repo.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::checker::{BoxPermissionChecker, PermissionChecker, PermissionCheckerBuilder};
+use crate::{MononokeIdentity, MononokeIdentitySet};
+
+static ACL_FILE_SOURCE: OnceCell<Arc<dyn AclFileSource>> = OnceCell::new();
+
+/// Provides the current contents of an ACL file, which may change if the
+/// file is reloaded.
+pub trait AclFileSource: Send + Sync + 'static {
+    fn acl_file(&self) -> Arc<AclFile>;
+}
+
+impl AclFileSource for Arc<AclFile> {
+    fn acl_file(&self) -> Arc<AclFile> {
+        self.clone()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAclFile {
+    #[serde(default)]
+    groups: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    acls: HashMap<String, RawAcl>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAcl {
+    #[serde(default)]
+    actions: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    path_restrictions: Vec<RawPathRestriction>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPathRestriction {
+    path_prefix: String,
+    writers: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AclFile {
+    acls: HashMap<String, Acl>,
+}
+
+#[derive(Debug, Default)]
+struct Acl {
+    actions: HashMap<String, MononokeIdentitySet>,
+    path_restrictions: Vec<PathRestriction>,
+}
+
+#[derive(Debug)]
+struct PathRestriction {
+    /// Path components of the prefix.
+    prefix: Vec<String>,
+    writers: MononokeIdentitySet,
+}
+
+impl PathRestriction {
+    fn matches(&self, path: &str) -> bool {
+        let mut components = path.split('/').filter(|c| !c.is_empty());
+        self.prefix
+            .iter()
+            .all(|prefix| components.next() == Some(prefix.as_str()))
+    }
+}
+
+impl AclFile {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read ACL file {}", path.display()))?;
+        Self::from_json(&data)
+            .with_context(|| format!("Failed to parse ACL file {}", path.display()))
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        let raw: RawAclFile = serde_json::from_slice(data)?;
+
+        let groups = raw
+            .groups
+            .into_iter()
+            .map(|(name, members)| {
+                let members = members
+                    .iter()
+                    .map(|member| member.parse::<MononokeIdentity>())
+                    .collect::<Result<MononokeIdentitySet>>()
+                    .with_context(|| format!("Invalid member of group {}", name))?;
+                Ok((name, members))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let resolve = |group_names: &[String]| -> Result<MononokeIdentitySet> {
+            let mut identities = MononokeIdentitySet::new();
+            for name in group_names {
+                let group = groups
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Unknown group {}", name))?;
+                identities.extend(group.iter().cloned());
+            }
+            Ok(identities)
+        };
+
+        let acls = raw
+            .acls
+            .into_iter()
+            .map(|(name, raw_acl)| {
+                let actions = raw_acl
+                    .actions
+                    .iter()
+                    .map(|(action, group_names)| Ok((action.clone(), resolve(group_names)?)))
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("Invalid actions in ACL {}", name))?;
+                let path_restrictions = raw_acl
+                    .path_restrictions
+                    .iter()
+                    .map(|restriction| {
+                        Ok(PathRestriction {
+                            prefix: restriction
+                                .path_prefix
+                                .split('/')
+                                .filter(|c| !c.is_empty())
+                                .map(String::from)
+                                .collect(),
+                            writers: resolve(&restriction.writers)?,
+                        })
+                    })
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("Invalid path restrictions in ACL {}", name))?;
+                Ok((
+                    name,
+                    Acl {
+                        actions,
+                        path_restrictions,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { acls })
+    }
+
+    /// Whether any of the accessors may perform any of the actions. Unknown
+    /// ACLs and actions permit nothing.
+    pub fn check_actions(
+        &self,
+        acl: &str,
+        accessors: &MononokeIdentitySet,
+        actions: &[&str],
+    ) -> bool {
+        let acl = match self.acls.get(acl) {
+            Some(acl) => acl,
+            None => return false,
+        };
+
+        actions.iter().any(|action| match acl.actions.get(*action) {
+            Some(allowed) => !allowed.is_disjoint(accessors),
+            None => false,
+        })
+    }
+
+    /// The paths among `paths` that are covered by a path restriction that
+    /// none of the accessors are a writer for.
+    pub fn denied_paths(
+        &self,
+        acl: &str,
+        accessors: &MononokeIdentitySet,
+        paths: &[String],
+    ) -> Vec<String> {
+        let acl = match self.acls.get(acl) {
+            Some(acl) => acl,
+            None => return Vec::new(),
+        };
+
+        let denied: Vec<&PathRestriction> = acl
+            .path_restrictions
+            .iter()
+            .filter(|restriction| restriction.writers.is_disjoint(accessors))
+            .collect();
+
+        let mut seen = HashSet::new();
+        paths
+            .iter()
+            .filter(|path| denied.iter().any(|restriction| restriction.matches(path)))
+            .filter(|path| seen.insert(path.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+struct AclFileChecker {
+    // Sources only hand out immutable snapshots of the ACL file, so a panic
+    // during a check can't leave them in an inconsistent state.
+    source: AssertUnwindSafe<Arc<dyn AclFileSource>>,
+    acl: String,
+}
+
+#[async_trait]
+impl PermissionChecker for AclFileChecker {
+    async fn check_set(&self, accessors: &MononokeIdentitySet, actions: &[&str]) -> Result<bool> {
+        Ok(self
+            .source
+            .acl_file()
+            .check_actions(&self.acl, accessors, actions))
+    }
+
+    async fn denied_paths(
+        &self,
+        accessors: &MononokeIdentitySet,
+        paths: &[String],
+    ) -> Result<Vec<String>> {
+        Ok(self
+            .source
+            .acl_file()
+            .denied_paths(&self.acl, accessors, paths))
+    }
+}
+
+impl PermissionCheckerBuilder {
+    /// Check permissions using the named ACL in an ACL file.
+    pub fn acl_file_checker(
+        source: Arc<dyn AclFileSource>,
+        acl: impl Into<String>,
+    ) -> BoxPermissionChecker {
+        Box::new(AclFileChecker {
+            source: AssertUnwindSafe(source),
+            acl: acl.into(),
+        })
+    }
+
+    /// Use this ACL file for the ACLs of all repos and tiers in this process,
+    /// in place of the default ACLs. This can only be set once, and must be
+    /// set before any permission checkers are built.
+    pub fn set_acl_file_source(source: Arc<dyn AclFileSource>) -> Result<()> {
+        ACL_FILE_SOURCE
+            .set(source)
+            .map_err(|_| anyhow!("ACL file source has already been set"))
+    }
+
+    /// The named ACL from the ACL file source set by `set_acl_file_source`,
+    /// if one was set.
+    pub fn acl_from_file(acl: &str) -> Option<BoxPermissionChecker> {
+        ACL_FILE_SOURCE
+            .get()
+            .map(|source| Self::acl_file_checker(source.clone(), acl))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ACL_FILE: &str = r#"{
+        "groups": {
+            "engineers": ["USER:alice", "USER:bob"],
+            "release": ["USER:carol"]
+        },
+        "acls": {
+            "repo": {
+                "actions": {
+                    "read": ["engineers", "release"],
+                    "write": ["engineers", "release"],
+                    "bypass_hooks": ["release"]
+                },
+                "path_restrictions": [
+                    {"path_prefix": "release/config/", "writers": ["release"]}
+                ]
+            }
+        }
+    }"#;
+
+    fn identities(user: &str) -> Result<MononokeIdentitySet> {
+        let mut identities = MononokeIdentitySet::new();
+        identities.insert(MononokeIdentity::new("USER", user)?);
+        Ok(identities)
+    }
+
+    #[tokio::test]
+    async fn test_actions() -> Result<()> {
+        let acl_file = Arc::new(AclFile::from_json(ACL_FILE.as_bytes())?);
+        let checker = PermissionCheckerBuilder::acl_file_checker(Arc::new(acl_file), "repo");
+
+        assert!(checker.check_set(&identities("alice")?, &["read"]).await?);
+        assert!(checker.check_set(&identities("alice")?, &["write"]).await?);
+        assert!(
+            !checker
+                .check_set(&identities("alice")?, &["bypass_hooks"])
+                .await?
+        );
+        assert!(
+            checker
+                .check_set(&identities("carol")?, &["bypass_hooks"])
+                .await?
+        );
+        assert!(!checker.check_set(&identities("dave")?, &["read"]).await?);
+        assert!(!checker.check_set(&identities("alice")?, &["admin"]).await?);
+
+        let other = PermissionCheckerBuilder::acl_file_checker(
+            Arc::new(Arc::new(AclFile::from_json(ACL_FILE.as_bytes())?)),
+            "other_repo",
+        );
+        assert!(!other.check_set(&identities("alice")?, &["read"]).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_denied_paths() -> Result<()> {
+        let acl_file = Arc::new(AclFile::from_json(ACL_FILE.as_bytes())?);
+        let checker = PermissionCheckerBuilder::acl_file_checker(Arc::new(acl_file), "repo");
+
+        let paths = vec![
+            "release/config/prod.json".to_string(),
+            "release/configs/prod.json".to_string(),
+            "release/config".to_string(),
+            "src/main.rs".to_string(),
+        ];
+
+        assert_eq!(
+            checker.denied_paths(&identities("alice")?, &paths).await?,
+            vec![
+                "release/config/prod.json".to_string(),
+                "release/config".to_string()
+            ]
+        );
+        assert!(checker
+            .denied_paths(&identities("carol")?, &paths)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(AclFile::from_json(b"{\"groups\": {\"g\": [\"not an identity\"]}}").is_err());
+        assert!(AclFile::from_json(
+            br#"{"acls": {"repo": {"actions": {"read": ["missing_group"]}}}}"#
+        )
+        .is_err());
+        assert!(AclFile::from_json(b"{\"unknown\": {}}").is_err());
+    }
+}
@@ -18,6 +18,17 @@ pub type BoxPermissionChecker = Box<dyn PermissionChecker + Send + Sync + RefUnw
 #[async_trait]
 pub trait PermissionChecker {
     async fn check_set(&self, accessors: &MononokeIdentitySet, actions: &[&str]) -> Result<bool>;
+
+    /// Return the paths among `paths` that the accessors may not modify,
+    /// even if they are otherwise allowed to write. Checkers without
+    /// path-level rules don't deny any paths.
+    async fn denied_paths(
+        &self,
+        _accessors: &MononokeIdentitySet,
+        _paths: &[String],
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
 }
 
 pub struct PermissionCheckerBuilder {}
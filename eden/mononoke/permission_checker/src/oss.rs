@@ -65,12 +65,12 @@ impl MononokeIdentitySetExt for MononokeIdentitySet {
 }
 
 impl PermissionCheckerBuilder {
-    pub async fn acl_for_repo(_fb: FacebookInit, _name: &str) -> Result<BoxPermissionChecker> {
-        Ok(Self::always_allow())
+    pub async fn acl_for_repo(_fb: FacebookInit, name: &str) -> Result<BoxPermissionChecker> {
+        Ok(Self::acl_from_file(name).unwrap_or_else(Self::always_allow))
     }
 
-    pub async fn acl_for_tier(_fb: FacebookInit, _name: &str) -> Result<BoxPermissionChecker> {
-        Ok(Self::always_allow())
+    pub async fn acl_for_tier(_fb: FacebookInit, name: &str) -> Result<BoxPermissionChecker> {
+        Ok(Self::acl_from_file(name).unwrap_or_else(Self::always_allow))
     }
 }
 
@@ -46,7 +46,7 @@ pub trait RateLimiter {
 
     fn check_load_shed(&self, identities: &MononokeIdentitySet) -> Result<(), RateLimitReason>;
 
-    fn bump_load(&self, metric: Metric, load: LoadCost);
+    fn bump_load(&self, metric: Metric, identities: &MononokeIdentitySet, load: LoadCost);
 
     fn category(&self) -> &str;
 
@@ -108,7 +108,6 @@ pub struct RateLimit {
     metric: Metric,
 }
 
-#[cfg(fbcode_build)]
 impl RateLimit {
     fn applies_to_client(&self, identities: &MononokeIdentitySet) -> bool {
         match &self.target {
@@ -127,20 +126,26 @@ impl LoadShedLimit {
         fb: FacebookInit,
         identities: Option<&MononokeIdentitySet>,
     ) -> Result<(), RateLimitReason> {
-        let applies_to_client = match &self.target {
-            Some(t) => t.matches_client(identities),
-            None => true,
-        };
-
-        if !applies_to_client {
+        if !self.applies_to_client(identities) {
             return Ok(());
         }
 
         let metric = self.raw_config.metric.to_string();
+        self.check_value(STATS::load_shed_counter.get_value(fb, (metric,)))
+    }
+
+    fn applies_to_client(&self, identities: Option<&MononokeIdentitySet>) -> bool {
+        match &self.target {
+            Some(t) => t.matches_client(identities),
+            None => true,
+        }
+    }
 
-        match STATS::load_shed_counter.get_value(fb, (metric.clone(),)) {
+    /// Check the current value of the metric against the limit.
+    fn check_value(&self, value: Option<i64>) -> Result<(), RateLimitReason> {
+        match value {
             Some(value) if value > self.raw_config.limit => Err(RateLimitReason::LoadShedMetric(
-                metric,
+                self.raw_config.metric.to_string(),
                 value,
                 self.raw_config.limit,
             )),
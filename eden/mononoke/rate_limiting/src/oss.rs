@@ -5,19 +5,52 @@
  * GNU General Public License version 2.
  */
 
-use std::collections::BTreeMap;
-use std::sync::Arc;
+//! In-process rate limiting for open source builds.
+//!
+//! Every client (identified by its full identity set) gets a token bucket for each `RateLimit`
+//! that targets it. A bucket holds up to `limit` tokens (scaled by the region weight) and refills
+//! at a rate of `limit` tokens per `window`. Load is charged to the buckets after the fact through
+//! `bump_load`, so buckets can go into debt: a client is rate limited for as long as its bucket
+//! is empty.
+//!
+//! Limiters are created per session, so the buckets live in a process-wide registry keyed by
+//! category, and are shared by all limiters of that category. Buckets are keyed by what a limit
+//! applies to rather than by its position in the config, so they survive config reloads. Buckets
+//! that have refilled completely are indistinguishable from new ones, and are evicted
+//! periodically.
+//!
+//! Load shedding uses the load charged through `bump_load` as well, summed over all clients of
+//! the process in one bucket per second. Load shed limits name a metric as `<metric>.sum.<secs>`,
+//! e.g. `egress_bytes.sum.60` for the bytes sent in the last minute. Limits on any other metric
+//! never shed load.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Error;
 use async_trait::async_trait;
 use fbinit::FacebookInit;
+use lazy_static::lazy_static;
 use permission_checker::MononokeIdentitySet;
+use rate_limiting_config::RateLimitStatus;
 
 use crate::{
-    BoxRateLimiter, LoadCost, Metric, MononokeRateLimitConfig, RateLimitBody, RateLimitReason,
-    RateLimiter,
+    BoxRateLimiter, LoadCost, Metric, MononokeRateLimitConfig, RateLimit, RateLimitBody,
+    RateLimitReason, RateLimiter,
 };
 
+lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<String, Arc<TokenBuckets>>> = Mutex::new(HashMap::new());
+    static ref LOAD: Arc<LoadCounters> = Arc::new(LoadCounters::new(Arc::new(SystemClock)));
+}
+
+/// How often full buckets are evicted.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The longest window load shed limits can sum load over.
+const MAX_LOAD_SHED_WINDOW: u64 = 3600;
+
 pub fn get_region_capacity(_datacenter_capacity: &BTreeMap<String, i32>) -> Option<i32> {
     None
 }
@@ -25,41 +58,669 @@ pub fn get_region_capacity(_datacenter_capacity: &BTreeMap<String, i32>) -> Opti
 pub fn create_rate_limiter(
     _fb: FacebookInit,
     category: String,
-    _config: Arc<MononokeRateLimitConfig>,
+    config: Arc<MononokeRateLimitConfig>,
 ) -> BoxRateLimiter {
-    Box::new(FakeLimiter { category })
+    let buckets = BUCKETS
+        .lock()
+        .expect("lock poisoned")
+        .entry(category.clone())
+        .or_insert_with(|| Arc::new(TokenBuckets::new(Arc::new(SystemClock))))
+        .clone();
+
+    Box::new(TokenBucketLimiter::new(
+        category,
+        config,
+        buckets,
+        LOAD.clone(),
+    ))
+}
+
+/// Source of the current time, so that tests can control how buckets refill.
+pub(crate) trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Instant;
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BucketKey {
+    /// What the limit applies to, see `limit_key`.
+    limit: String,
+    identities: String,
 }
 
 #[derive(Debug)]
-struct FakeLimiter {
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+    /// When the bucket will have refilled completely, if nothing is charged to it.
+    full_at: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant, body: &RateLimitBody, region_weight: f64) {
+        let capacity = body.raw_config.limit * region_weight;
+        let window = body.window.as_secs_f64();
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+
+        // A zero window means that the limit resets continuously.
+        let refill = if window > 0.0 {
+            capacity * elapsed / window
+        } else {
+            capacity
+        };
+
+        self.tokens = (self.tokens + refill).min(capacity);
+        self.last_refill = now;
+    }
+
+    fn update_full_at(&mut self, body: &RateLimitBody, region_weight: f64) {
+        let capacity = body.raw_config.limit * region_weight;
+        let missing = capacity - self.tokens;
+        self.full_at = if missing > 0.0 && capacity > 0.0 {
+            self.last_refill + body.window.mul_f64(missing / capacity)
+        } else {
+            self.last_refill
+        };
+    }
+}
+
+struct TokenBucketsState {
+    buckets: HashMap<BucketKey, TokenBucket>,
+    last_eviction: Instant,
+}
+
+/// All token buckets for one rate limiting category.
+pub(crate) struct TokenBuckets {
+    clock: Arc<dyn Clock>,
+    state: Mutex<TokenBucketsState>,
+}
+
+impl TokenBuckets {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        Self {
+            clock,
+            state: Mutex::new(TokenBucketsState {
+                buckets: HashMap::new(),
+                last_eviction: now,
+            }),
+        }
+    }
+
+    /// Refill the bucket for this key, then apply `update` to its tokens and return the result.
+    fn update(
+        &self,
+        key: BucketKey,
+        body: &RateLimitBody,
+        region_weight: f64,
+        update: impl FnOnce(&mut f64),
+    ) -> f64 {
+        let now = self.clock.now();
+        let mut state = self.state.lock().expect("lock poisoned");
+
+        if now.saturating_duration_since(state.last_eviction) >= EVICTION_INTERVAL {
+            // A full bucket is recreated identically the next time it is needed.
+            state.buckets.retain(|_, bucket| bucket.full_at > now);
+            state.last_eviction = now;
+        }
+
+        let bucket = state.buckets.entry(key).or_insert_with(|| TokenBucket {
+            tokens: body.raw_config.limit * region_weight,
+            last_refill: now,
+            full_at: now,
+        });
+        bucket.refill(now, body, region_weight);
+        update(&mut bucket.tokens);
+        bucket.update_full_at(body, region_weight);
+        bucket.tokens
+    }
+}
+
+const METRICS: [Metric; 4] = [
+    Metric::EgressBytes,
+    Metric::TotalManifests,
+    Metric::GetpackFiles,
+    Metric::Commits,
+];
+
+fn metric_name(metric: Metric) -> &'static str {
+    match metric {
+        Metric::EgressBytes => "egress_bytes",
+        Metric::TotalManifests => "total_manifests",
+        Metric::GetpackFiles => "getpack_files",
+        Metric::Commits => "commits",
+    }
+}
+
+/// Load charged to the process for each metric, in one bucket per second, oldest first.
+pub(crate) struct LoadCounters {
+    clock: Arc<dyn Clock>,
+    origin: Instant,
+    counters: Mutex<HashMap<&'static str, VecDeque<(u64, f64)>>>,
+}
+
+impl LoadCounters {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        let origin = clock.now();
+        Self {
+            clock,
+            origin,
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Seconds since the counters were created.
+    fn now(&self) -> u64 {
+        self.clock
+            .now()
+            .saturating_duration_since(self.origin)
+            .as_secs()
+    }
+
+    fn bump(&self, metric: Metric, load: LoadCost) {
+        let now = self.now();
+        let mut counters = self.counters.lock().expect("lock poisoned");
+        let buckets = counters.entry(metric_name(metric)).or_default();
+        while let Some((second, _)) = buckets.front() {
+            if second + MAX_LOAD_SHED_WINDOW > now {
+                break;
+            }
+            buckets.pop_front();
+        }
+        match buckets.back_mut() {
+            Some((second, total)) if *second == now => *total += load,
+            _ => buckets.push_back((now, load)),
+        }
+    }
+
+    /// The value of a load shed metric, or `None` if it isn't one of ours.
+    fn value(&self, load_shed_metric: &str) -> Option<i64> {
+        let mut parts = load_shed_metric.rsplitn(3, '.');
+        let window: u64 = parts.next()?.parse().ok()?;
+        if parts.next()? != "sum" {
+            return None;
+        }
+        let name = parts.next()?;
+        let metric = METRICS
+            .iter()
+            .copied()
+            .find(|metric| metric_name(*metric) == name)?;
+        let window = window.min(MAX_LOAD_SHED_WINDOW);
+
+        let now = self.now();
+        let counters = self.counters.lock().expect("lock poisoned");
+        let total: f64 = counters.get(metric_name(metric)).map_or(0.0, |buckets| {
+            buckets
+                .iter()
+                .filter(|(second, _)| second + window > now)
+                .map(|(_, load)| load)
+                .sum()
+        });
+        Some(total as i64)
+    }
+}
+
+/// Identifies a limit by what it applies to, so that its buckets are kept when limits are added
+/// to or removed from the config, or when its threshold changes.
+fn limit_key(limit: &RateLimit) -> String {
+    format!(
+        "{:?}/{:?}/{:?}",
+        limit.metric, limit.body.window, limit.target
+    )
+}
+
+fn identities_key(identities: &MononokeIdentitySet) -> String {
+    identities
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub(crate) struct TokenBucketLimiter {
     category: String,
+    config: Arc<MononokeRateLimitConfig>,
+    buckets: Arc<TokenBuckets>,
+    load: Arc<LoadCounters>,
+}
+
+impl TokenBucketLimiter {
+    pub(crate) fn new(
+        category: String,
+        config: Arc<MononokeRateLimitConfig>,
+        buckets: Arc<TokenBuckets>,
+        load: Arc<LoadCounters>,
+    ) -> Self {
+        Self {
+            category,
+            config,
+            buckets,
+            load,
+        }
+    }
+
+    /// Rate limits for this metric that apply to this client.
+    fn applicable_limits<'a>(
+        &'a self,
+        metric: Metric,
+        identities: &'a MononokeIdentitySet,
+    ) -> impl Iterator<Item = &'a RateLimit> + 'a {
+        self.config.rate_limits.iter().filter(move |limit| {
+            limit.metric == metric
+                && limit.body.raw_config.status != RateLimitStatus::Disabled
+                && limit.applies_to_client(identities)
+        })
+    }
 }
 
 #[async_trait]
-impl RateLimiter for FakeLimiter {
+impl RateLimiter for TokenBucketLimiter {
     async fn check_rate_limit(
         &self,
-        _metric: Metric,
-        _identities: &MononokeIdentitySet,
+        metric: Metric,
+        identities: &MononokeIdentitySet,
     ) -> Result<Result<(), RateLimitReason>, Error> {
+        let key = identities_key(identities);
+        for limit in self.applicable_limits(metric, identities) {
+            let bucket_key = BucketKey {
+                limit: limit_key(limit),
+                identities: key.clone(),
+            };
+            let tokens =
+                self.buckets
+                    .update(bucket_key, &limit.body, self.config.region_weight, |_| {});
+
+            if tokens <= 0.0 && limit.body.raw_config.status == RateLimitStatus::Enforced {
+                return Ok(Err(RateLimitReason::RateLimitedMetric(
+                    metric,
+                    limit.body.window,
+                )));
+            }
+        }
+
         Ok(Ok(()))
     }
 
-    fn check_load_shed(&self, _identities: &MononokeIdentitySet) -> Result<(), RateLimitReason> {
+    fn check_load_shed(&self, identities: &MononokeIdentitySet) -> Result<(), RateLimitReason> {
+        for limit in self.config.load_shed_limits.iter() {
+            if !limit.applies_to_client(Some(identities)) {
+                continue;
+            }
+            let res = limit.check_value(self.load.value(&limit.raw_config.metric));
+            if limit.raw_config.status == RateLimitStatus::Enforced {
+                res?;
+            }
+        }
+
         Ok(())
     }
 
-    fn bump_load(&self, _metric: Metric, _load: LoadCost) {}
+    fn bump_load(&self, metric: Metric, identities: &MononokeIdentitySet, load: LoadCost) {
+        self.load.bump(metric, load);
+
+        let key = identities_key(identities);
+        for limit in self.applicable_limits(metric, identities) {
+            let bucket_key = BucketKey {
+                limit: limit_key(limit),
+                identities: key.clone(),
+            };
+            self.buckets.update(
+                bucket_key,
+                &limit.body,
+                self.config.region_weight,
+                |tokens| *tokens -= load,
+            );
+        }
+    }
 
     fn category(&self) -> &str {
         &self.category
     }
 
     fn commits_per_author_limit(&self) -> Option<RateLimitBody> {
-        None
+        Some(self.config.commits_per_author.clone())
     }
 
     fn total_file_changes_limit(&self) -> Option<RateLimitBody> {
-        None
+        self.config.total_file_changes.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use permission_checker::MononokeIdentity;
+
+    use crate::{LoadShedLimit, Target};
+
+    struct MockClock(Mutex<Instant>);
+
+    impl MockClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn body(status: RateLimitStatus, limit: f64, window: u64) -> RateLimitBody {
+        RateLimitBody {
+            raw_config: rate_limiting_config::RateLimitBody {
+                status,
+                limit,
+                window: window as i64,
+            },
+            window: Duration::from_secs(window),
+        }
+    }
+
+    fn config(rate_limits: Vec<RateLimit>) -> Arc<MononokeRateLimitConfig> {
+        Arc::new(MononokeRateLimitConfig {
+            region_weight: 1.0,
+            rate_limits,
+            load_shed_limits: vec![],
+            commits_per_author: body(RateLimitStatus::Enforced, 10.0, 60),
+            total_file_changes: None,
+        })
+    }
+
+    fn user(name: &str) -> Result<MononokeIdentity, Error> {
+        MononokeIdentity::new("USER", name)
+    }
+
+    fn identities(name: &str) -> Result<MononokeIdentitySet, Error> {
+        let mut identities = MononokeIdentitySet::new();
+        identities.insert(user(name)?);
+        Ok(identities)
+    }
+
+    fn limiter(
+        config: &Arc<MononokeRateLimitConfig>,
+        buckets: &Arc<TokenBuckets>,
+    ) -> TokenBucketLimiter {
+        let load = Arc::new(LoadCounters::new(buckets.clock.clone()));
+        TokenBucketLimiter::new("test".to_string(), config.clone(), buckets.clone(), load)
+    }
+
+    #[fbinit::test]
+    async fn test_token_bucket(_fb: FacebookInit) -> Result<(), Error> {
+        let clock = MockClock::new();
+        let buckets = Arc::new(TokenBuckets::new(clock.clone()));
+        let config = config(vec![RateLimit {
+            body: body(RateLimitStatus::Enforced, 10.0, 10),
+            target: None,
+            metric: Metric::EgressBytes,
+        }]);
+        let alice = identities("alice")?;
+        let bob = identities("bob")?;
+
+        let limiter = limiter(&config, &buckets);
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_ok());
+        limiter.bump_load(Metric::EgressBytes, &alice, 15.0);
+
+        // Alice is now in debt, but that doesn't affect other metrics or other clients.
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_err());
+        assert!(limiter
+            .check_rate_limit(Metric::Commits, &alice)
+            .await?
+            .is_ok());
+
+        // Limiters are per session, but buckets are shared between them.
+        let other = limiter(&config, &buckets);
+        assert!(other
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_err());
+        assert!(other
+            .check_rate_limit(Metric::EgressBytes, &bob)
+            .await?
+            .is_ok());
+
+        // The debt of 5 tokens takes 5 seconds to repay.
+        clock.advance(Duration::from_secs(5));
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_err());
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_ok());
+
+        // The bucket never holds more than the limit.
+        clock.advance(Duration::from_secs(3600));
+        limiter.bump_load(Metric::EgressBytes, &alice, 11.0);
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_err());
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_tracked_limits_do_not_reject(_fb: FacebookInit) -> Result<(), Error> {
+        let buckets = Arc::new(TokenBuckets::new(MockClock::new()));
+        let config = config(vec![RateLimit {
+            body: body(RateLimitStatus::Tracked, 1.0, 10),
+            target: None,
+            metric: Metric::Commits,
+        }]);
+        let alice = identities("alice")?;
+
+        let limiter = limiter(&config, &buckets);
+        assert!(limiter
+            .check_rate_limit(Metric::Commits, &alice)
+            .await?
+            .is_ok());
+        limiter.bump_load(Metric::Commits, &alice, 100.0);
+        assert!(limiter
+            .check_rate_limit(Metric::Commits, &alice)
+            .await?
+            .is_ok());
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_targets(_fb: FacebookInit) -> Result<(), Error> {
+        let buckets = Arc::new(TokenBuckets::new(MockClock::new()));
+        let target = Target::AndTarget(vec![
+            Target::OrTarget(vec![
+                Target::Identity(user("alice")?),
+                Target::Identity(user("bob")?),
+            ]),
+            Target::NotTarget(Box::new(Target::Identity(MononokeIdentity::new(
+                "GROUP", "exempt",
+            )?))),
+        ]);
+        let config = config(vec![RateLimit {
+            body: body(RateLimitStatus::Enforced, 1.0, 10),
+            target: Some(target),
+            metric: Metric::GetpackFiles,
+        }]);
+
+        let mut exempt_alice = identities("alice")?;
+        exempt_alice.insert(MononokeIdentity::new("GROUP", "exempt")?);
+
+        for (identities, limited) in [
+            (identities("alice")?, true),
+            (identities("bob")?, true),
+            (identities("carol")?, false),
+            (exempt_alice, false),
+        ] {
+            let limiter = limiter(&config, &buckets);
+            assert!(limiter
+                .check_rate_limit(Metric::GetpackFiles, &identities)
+                .await?
+                .is_ok());
+            limiter.bump_load(Metric::GetpackFiles, &identities, 2.0);
+            let res = limiter
+                .check_rate_limit(Metric::GetpackFiles, &identities)
+                .await?;
+            assert_eq!(res.is_err(), limited, "{:?}", identities);
+        }
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_buckets_survive_config_changes(_fb: FacebookInit) -> Result<(), Error> {
+        let buckets = Arc::new(TokenBuckets::new(MockClock::new()));
+        let egress = RateLimit {
+            body: body(RateLimitStatus::Enforced, 10.0, 10),
+            target: None,
+            metric: Metric::EgressBytes,
+        };
+        let commits = RateLimit {
+            body: body(RateLimitStatus::Enforced, 10.0, 10),
+            target: None,
+            metric: Metric::Commits,
+        };
+        let alice = identities("alice")?;
+
+        let before = limiter(&config(vec![egress.clone()]), &buckets);
+        before.bump_load(Metric::EgressBytes, &alice, 15.0);
+
+        // A new limit is added in front of the existing one: the debt is still there, and it
+        // isn't charged to the new limit.
+        let after = limiter(&config(vec![commits, egress]), &buckets);
+        assert!(after
+            .check_rate_limit(Metric::EgressBytes, &alice)
+            .await?
+            .is_err());
+        assert!(after
+            .check_rate_limit(Metric::Commits, &alice)
+            .await?
+            .is_ok());
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_full_buckets_are_evicted(_fb: FacebookInit) -> Result<(), Error> {
+        let clock = MockClock::new();
+        let buckets = Arc::new(TokenBuckets::new(clock.clone()));
+        let config = config(vec![RateLimit {
+            body: body(RateLimitStatus::Enforced, 10.0, 120),
+            target: None,
+            metric: Metric::EgressBytes,
+        }]);
+        let alice = identities("alice")?;
+        let bob = identities("bob")?;
+        let bucket_count = || buckets.state.lock().unwrap().buckets.len();
+
+        let limiter = limiter(&config, &buckets);
+        limiter.bump_load(Metric::EgressBytes, &alice, 5.0);
+        limiter.bump_load(Metric::EgressBytes, &bob, 10.0);
+        assert_eq!(bucket_count(), 2);
+
+        // After 60 seconds alice's bucket is full again, but bob's isn't.
+        clock.advance(Duration::from_secs(60));
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &bob)
+            .await?
+            .is_ok());
+        assert_eq!(bucket_count(), 1);
+
+        // Bob's bucket still remembers what he used.
+        limiter.bump_load(Metric::EgressBytes, &bob, 5.0);
+        assert!(limiter
+            .check_rate_limit(Metric::EgressBytes, &bob)
+            .await?
+            .is_err());
+
+        Ok(())
+    }
+
+    fn load_shed_limit(metric: &str, status: RateLimitStatus, limit: i64) -> LoadShedLimit {
+        LoadShedLimit {
+            raw_config: rate_limiting_config::LoadShedLimit {
+                metric: metric.to_string(),
+                status,
+                target: None,
+                limit,
+            },
+            target: None,
+        }
+    }
+
+    #[fbinit::test]
+    async fn test_load_shed(_fb: FacebookInit) -> Result<(), Error> {
+        let clock = MockClock::new();
+        let buckets = Arc::new(TokenBuckets::new(clock.clone()));
+        let load = Arc::new(LoadCounters::new(clock.clone()));
+        let config = Arc::new(MononokeRateLimitConfig {
+            load_shed_limits: vec![
+                load_shed_limit("egress_bytes.sum.10", RateLimitStatus::Enforced, 100),
+                load_shed_limit("commits.sum.10", RateLimitStatus::Tracked, 0),
+                load_shed_limit("mononoke.unknown.sum.10", RateLimitStatus::Enforced, 0),
+            ],
+            ..(*config(vec![])).clone()
+        });
+        let alice = identities("alice")?;
+        let bob = identities("bob")?;
+
+        let session = || {
+            TokenBucketLimiter::new(
+                "test".to_string(),
+                config.clone(),
+                buckets.clone(),
+                load.clone(),
+            )
+        };
+
+        // Load is summed over all clients and sessions, and tracked limits don't shed.
+        session().bump_load(Metric::EgressBytes, &alice, 60.0);
+        session().bump_load(Metric::Commits, &alice, 10.0);
+        assert!(session().check_load_shed(&bob).is_ok());
+        clock.advance(Duration::from_secs(5));
+        session().bump_load(Metric::EgressBytes, &bob, 60.0);
+        assert!(session().check_load_shed(&alice).is_err());
+
+        // Load falls out of the window.
+        clock.advance(Duration::from_secs(5));
+        assert!(session().check_load_shed(&alice).is_ok());
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    fn test_limits_from_config(_fb: FacebookInit) {
+        let buckets = Arc::new(TokenBuckets::new(MockClock::new()));
+        let config = config(vec![]);
+        let limiter = limiter(&config, &buckets);
+
+        let limit = limiter.commits_per_author_limit().unwrap();
+        assert_eq!(limit.raw_config.limit, 10.0);
+        assert_eq!(limit.window, Duration::from_secs(60));
+        assert!(limiter.total_file_changes_limit().is_none());
     }
 }
@@ -24,6 +24,7 @@ use metaconfig_types::{
 use mononoke_api::Repo;
 use mononoke_types::RepositoryId;
 use mutable_counters::{MutableCounters, SqlMutableCounters};
+use permission_checker::ArcPermissionChecker;
 use rand::Rng;
 use reachabilityindex::LeastCommonAncestorsHint;
 use repo_blobstore::RepoBlobstore;
@@ -224,6 +225,10 @@ impl MononokeRepo {
         &self.repo.readonly_fetcher()
     }
 
+    pub fn permission_checker(&self) -> &ArcPermissionChecker {
+        self.repo.permission_checker()
+    }
+
     pub fn infinitepush(&self) -> &InfinitepushParams {
         &self.repo.config().infinitepush
     }
@@ -8,8 +8,8 @@
 use crate::errors::ErrorKind;
 
 use unbundle::{
-    run_hooks, run_post_resolve_action, BundleResolverError, CrossRepoPushSource, PushRedirector,
-    PushRedirectorArgs,
+    enforce_path_acls, run_hooks, run_post_resolve_action, BundleResolverError,
+    CrossRepoPushSource, PushRedirector, PushRedirectorArgs,
 };
 
 use anyhow::{format_err, Context, Error, Result};
@@ -1670,6 +1670,14 @@ impl HgCommands for RepoClient {
                         )
                         .await?;
 
+                        enforce_path_acls(
+                            &ctx,
+                            &reponame,
+                            client.repo.permission_checker(),
+                            &action,
+                        )
+                        .await?;
+
                         let unbundle_future = async {
                             maybe_validate_pushed_bonsais(&ctx, blobrepo, &maybereplaydata).await?;
 
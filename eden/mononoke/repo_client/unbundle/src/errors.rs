@@ -14,4 +14,8 @@ pub(crate) enum ErrorKind {
     WhileUploadingData(Vec<HgChangesetId>),
     #[error("Repo is marked as read-only: {0}")]
     RepoReadOnly(String),
+    #[error("Write access to repo {0} is not permitted")]
+    WriteNotPermitted(String),
+    #[error("Modifying these paths is not permitted: {}", .0.join(", "))]
+    RestrictedPaths(Vec<String>),
 }
@@ -14,6 +14,7 @@
 mod changegroup;
 mod errors;
 mod hook_running;
+mod path_acls;
 mod processing;
 mod push_redirector;
 mod rate_limits;
@@ -24,6 +25,7 @@ mod upload_blobs;
 mod upload_changesets;
 
 pub use hook_running::run_hooks;
+pub use path_acls::enforce_path_acls;
 pub use hooks::CrossRepoPushSource;
 pub use processing::run_post_resolve_action;
 pub use push_redirector::{PushRedirector, PushRedirectorArgs};
@@ -0,0 +1,63 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::Error;
+use context::CoreContext;
+use itertools::Itertools;
+use permission_checker::ArcPermissionChecker;
+use slog::debug;
+
+use crate::errors::ErrorKind;
+use crate::{BundleResolverError, PostResolveAction, PostResolvePush, PostResolvePushRebase};
+
+/// Check that the pusher is allowed to write to the repo, and to all of the
+/// paths modified by the pushed commits. Infinitepush only writes to scratch
+/// bookmarks, so it isn't subject to these checks.
+pub async fn enforce_path_acls(
+    ctx: &CoreContext,
+    reponame: &str,
+    permission_checker: &ArcPermissionChecker,
+    action: &PostResolveAction,
+) -> Result<(), BundleResolverError> {
+    let uploaded_bonsais = match action {
+        PostResolveAction::Push(PostResolvePush {
+            ref uploaded_bonsais,
+            ..
+        }) => Some(uploaded_bonsais),
+        PostResolveAction::PushRebase(PostResolvePushRebase {
+            ref uploaded_bonsais,
+            ..
+        }) => Some(uploaded_bonsais),
+        PostResolveAction::BookmarkOnlyPushRebase(..) => None,
+        PostResolveAction::InfinitePush(..) => return Ok(()),
+    };
+
+    let identities = ctx.metadata().identities();
+    if !permission_checker.check_set(identities, &["write"]).await? {
+        debug!(
+            ctx.logger(),
+            "Permission denied: write access to {}", reponame
+        );
+        return Err(Error::from(ErrorKind::WriteNotPermitted(reponame.to_string())).into());
+    }
+
+    let paths = match uploaded_bonsais {
+        Some(uploaded_bonsais) => uploaded_bonsais
+            .iter()
+            .flat_map(|bcs| bcs.file_changes().map(|(path, _)| path.to_string()))
+            .unique()
+            .collect::<Vec<_>>(),
+        None => return Ok(()),
+    };
+
+    let mut denied = permission_checker.denied_paths(identities, &paths).await?;
+    if denied.is_empty() {
+        return Ok(());
+    }
+    denied.sort();
+    Err(Error::from(ErrorKind::RestrictedPaths(denied)).into())
+}
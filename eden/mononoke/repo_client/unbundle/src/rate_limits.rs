@@ -358,3 +358,110 @@ fn make_key(prefix: &str, author: &str) -> String {
     let key = format!("{}.{}", prefix, hex::encode(hasher.result()));
     return key;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Error;
+    use async_trait::async_trait;
+    use context::SessionContainer;
+    use fbinit::FacebookInit;
+    use mononoke_types::{BonsaiChangesetMut, DateTime};
+    use permission_checker::MononokeIdentitySet;
+    use rate_limiting::{BoxRateLimiter, LoadCost, Metric, RateLimitReason, RateLimiter};
+
+    struct CommitsPerAuthorLimiter {
+        category: String,
+        limit: RateLimitBody,
+    }
+
+    #[async_trait]
+    impl RateLimiter for CommitsPerAuthorLimiter {
+        async fn check_rate_limit(
+            &self,
+            _metric: Metric,
+            _identities: &MononokeIdentitySet,
+        ) -> Result<Result<(), RateLimitReason>, Error> {
+            Ok(Ok(()))
+        }
+
+        fn check_load_shed(
+            &self,
+            _identities: &MononokeIdentitySet,
+        ) -> Result<(), RateLimitReason> {
+            Ok(())
+        }
+
+        fn bump_load(&self, _metric: Metric, _identities: &MononokeIdentitySet, _load: LoadCost) {}
+
+        fn category(&self) -> &str {
+            &self.category
+        }
+
+        fn commits_per_author_limit(&self) -> Option<RateLimitBody> {
+            Some(self.limit.clone())
+        }
+
+        fn total_file_changes_limit(&self) -> Option<RateLimitBody> {
+            None
+        }
+    }
+
+    fn commit(author: &str) -> Result<BonsaiChangeset, Error> {
+        BonsaiChangesetMut {
+            parents: vec![],
+            author: author.to_string(),
+            author_date: DateTime::from_timestamp(0, 0)?,
+            committer: None,
+            committer_date: None,
+            message: "commit".to_string(),
+            extra: Default::default(),
+            file_changes: Default::default(),
+            is_snapshot: false,
+        }
+        .freeze()
+    }
+
+    #[fbinit::test]
+    async fn test_commits_per_author_limit(fb: FacebookInit) -> Result<(), Error> {
+        let limiter = CommitsPerAuthorLimiter {
+            category: "test_commits_per_author_limit".to_string(),
+            limit: RateLimitBody {
+                raw_config: rate_limiting_config::RateLimitBody {
+                    status: RateLimitStatus::Enforced,
+                    limit: 2.0,
+                    window: 60,
+                },
+                window: Duration::from_secs(60),
+            },
+        };
+        let session = SessionContainer::builder(fb)
+            .rate_limiter(Box::new(limiter) as BoxRateLimiter)
+            .build();
+        let ctx = CoreContext::test_mock_session(session);
+
+        // Each author can push two commits in the window.
+        let commits = vec![commit("alice")?, commit("alice")?, commit("bob")?];
+        assert!(enforce_commit_rate_limits_on_commits(&ctx, commits.iter())
+            .await
+            .is_ok());
+
+        // Alice's third commit is rejected, but bob can still push.
+        let commits = vec![commit("alice")?];
+        match enforce_commit_rate_limits_on_commits(&ctx, commits.iter()).await {
+            Err(BundleResolverError::RateLimitExceeded { entity, value, .. }) => {
+                assert_eq!(entity, "alice");
+                assert_eq!(value, 3.0);
+            }
+            Err(err) => panic!("unexpected error: {:?}", Error::from(err)),
+            Ok(()) => panic!("push was not rejected"),
+        }
+        let commits = vec![commit("bob")?];
+        assert!(enforce_commit_rate_limits_on_commits(&ctx, commits.iter())
+            .await
+            .is_ok());
+
+        Ok(())
+    }
+}
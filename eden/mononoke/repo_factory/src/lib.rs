@@ -56,6 +56,7 @@ use fbinit::FacebookInit;
 use filenodes::ArcFilenodes;
 use filestore::{ArcFilestoreConfig, FilestoreConfig};
 use futures_watchdog::WatchdogExt;
+use lfs_locks::{ArcLfsLocks, LfsLocks, SqlLfsLocksStore};
 use mercurial_mutation::{ArcHgMutationStore, SqlHgMutationStoreBuilder};
 use metaconfig_types::{
     ArcRepoConfig, BlobConfig, CensoredScubaParams, CommonConfig, MetadataDatabaseConfig,
@@ -473,6 +474,9 @@ pub enum RepoFactoryError {
 
     #[error("Error opening mutable renames")]
     MutableRenames,
+
+    #[error("Error opening LFS locks")]
+    LfsLocks,
 }
 
 #[facet::factory(name: String, config: RepoConfig)]
@@ -857,6 +861,14 @@ impl RepoFactory {
         Ok(Arc::new(MutableRenames::new(repo_config.repoid, sql_store)))
     }
 
+    pub async fn lfs_locks(&self, repo_config: &ArcRepoConfig) -> Result<ArcLfsLocks> {
+        let sql_store = self
+            .open::<SqlLfsLocksStore>(&repo_config.storage_config.metadata)
+            .await
+            .context(RepoFactoryError::LfsLocks)?;
+        Ok(Arc::new(LfsLocks::new(repo_config.repoid, sql_store)))
+    }
+
     pub fn derived_data_manager_set(
         &self,
         repo_identity: &ArcRepoIdentity,
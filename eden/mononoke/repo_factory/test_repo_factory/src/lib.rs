@@ -35,6 +35,7 @@ use filenodes::ArcFilenodes;
 use filestore::{ArcFilestoreConfig, FilestoreConfig};
 use fsnodes::RootFsnodeId;
 use git_types::TreeHandle;
+use lfs_locks::{ArcLfsLocks, LfsLocks, SqlLfsLocksStore};
 use maplit::hashset;
 use megarepo_mapping::MegarepoMapping;
 use memblob::Memblob;
@@ -153,6 +154,7 @@ impl TestRepoFactory {
         metadata_con.execute_batch(SqlPushrebaseMutationMappingConnection::CREATION_QUERY)?;
         metadata_con.execute_batch(SqlLongRunningRequestsQueue::CREATION_QUERY)?;
         metadata_con.execute_batch(SqlMutableRenamesStore::CREATION_QUERY)?;
+        metadata_con.execute_batch(SqlLfsLocksStore::CREATION_QUERY)?;
         metadata_con.execute_batch(SqlSyncedCommitMapping::CREATION_QUERY)?;
         let metadata_db =
             SqlConnectionsWithSchema::new_single(Connection::with_sqlite(metadata_con));
@@ -447,4 +449,10 @@ impl TestRepoFactory {
             SqlMutableRenamesStore::from_sql_connections(self.metadata_db.clone().into());
         Ok(Arc::new(MutableRenames::new(repo_identity.id(), sql_store)))
     }
+
+    /// LFS locks
+    pub fn lfs_locks(&self, repo_identity: &ArcRepoIdentity) -> Result<ArcLfsLocks> {
+        let sql_store = SqlLfsLocksStore::from_sql_connections(self.metadata_db.clone().into());
+        Ok(Arc::new(LfsLocks::new(repo_identity.id(), sql_store)))
+    }
 }
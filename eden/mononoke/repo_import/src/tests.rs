@@ -530,6 +530,7 @@ mod tests {
             map: hashmap! {
                 mp("dest_path_prefix/B") => mp("random_dir/B"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -542,6 +543,7 @@ mod tests {
                 mp("dest_path_prefix/B") => mp("random_dir/B"),
                 mp("dest_path_prefix/C") => mp("random_dir/C"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -551,6 +553,7 @@ mod tests {
             map: hashmap! {
                 mp("dest_path_prefix_2") => mp("dpp2"),
             },
+            path_rules: vec![],
         }
     }
 
@@ -815,6 +815,41 @@ struct CommitListDescendantBookmarksParams {
   5: set<CommitIdentityScheme> identity_schemes;
 }
 
+const i64 COMMIT_RANGE_MAX_LIMIT = 10000;
+
+struct CommitRangeParams {
+  /// The other end of the range.  Commits that are descendants of this
+  /// commit and ancestors of `descendant_commit_id` are returned.
+  1: CommitId descendant_commit_id;
+
+  /// Limit to the number of commits that may be returned.
+  2: i64 limit;
+
+  /// Number of commits to skip, to be used for paging.
+  3: i64 skip;
+
+  /// Commit identity schemes to return.
+  4: set<CommitIdentityScheme> identity_schemes;
+}
+
+struct CommitDescendantsParams {
+  /// Return descendants up to the commit this bookmark points to.
+  /// Exactly one of `bookmark` and `until_commit_id` must be set.
+  1: optional string bookmark;
+
+  /// Return descendants up to this commit.
+  2: optional CommitId until_commit_id;
+
+  /// Limit to the number of commits that may be returned.
+  3: i64 limit;
+
+  /// Number of commits to skip, to be used for paging.
+  4: i64 skip;
+
+  /// Commit identity schemes to return.
+  5: set<CommitIdentityScheme> identity_schemes;
+}
+
 struct CommitPathExistsParams {}
 
 struct CommitPathInfoParams {}
@@ -1187,6 +1222,26 @@ struct CommitListDescendantBookmarksResponse {
   2: optional string continue_after;
 }
 
+struct CommitRangeResponse {
+  /// The commits in the range, including both ends, with descendants
+  /// before their ancestors.
+  1: list<map<CommitIdentityScheme, CommitId>> commits;
+
+  /// If set, there are potentially more commits.  Provide this value as
+  /// the `skip` parameter in a new request to continue finding them.
+  2: optional i64 continue_skip;
+}
+
+struct CommitDescendantsResponse {
+  /// The descendants of the commit, not including the commit itself, with
+  /// descendants before their ancestors.
+  1: list<map<CommitIdentityScheme, CommitId>> commits;
+
+  /// If set, there are potentially more commits.  Provide this value as
+  /// the `skip` parameter in a new request to continue finding them.
+  2: optional i64 continue_skip;
+}
+
 struct CommitPathExistsResponse {
   /// Whether anything exists at this path.
   1: bool exists;
@@ -1556,6 +1611,19 @@ service SourceControlService extends fb303.FacebookService {
     2: CommitListDescendantBookmarksParams params,
   ) throws (1: RequestError request_error, 2: InternalError internal_error);
 
+  /// List the commits between this commit and a descendant commit.
+  CommitRangeResponse commit_range(
+    1: CommitSpecifier commit,
+    2: CommitRangeParams params,
+  ) throws (1: RequestError request_error, 2: InternalError internal_error);
+
+  /// List the descendants of this commit that are ancestors of a bookmark
+  /// or another commit.
+  CommitDescendantsResponse commit_descendants(
+    1: CommitSpecifier commit,
+    2: CommitDescendantsParams params,
+  ) throws (1: RequestError request_error, 2: InternalError internal_error);
+
   /// CommitPath methods
   /// ==============
 
@@ -130,6 +130,13 @@ impl From<MononokeError> for ServiceError {
                 reason: error.to_string(),
                 ..Default::default()
             }),
+            error @ MononokeError::PathPermissionDenied { .. } => {
+                Self::Request(thrift::RequestError {
+                    kind: thrift::RequestErrorKind::PERMISSION_DENIED,
+                    reason: error.to_string(),
+                    ..Default::default()
+                })
+            }
             error @ MononokeError::ServicePermissionDenied { .. } => {
                 Self::Request(thrift::RequestError {
                     kind: thrift::RequestErrorKind::PERMISSION_DENIED,
@@ -212,6 +219,8 @@ impl_into_thrift_error!(service::CommitIsAncestorOfExn);
 impl_into_thrift_error!(service::CommitFindFilesExn);
 impl_into_thrift_error!(service::CommitHistoryExn);
 impl_into_thrift_error!(service::CommitListDescendantBookmarksExn);
+impl_into_thrift_error!(service::CommitRangeExn);
+impl_into_thrift_error!(service::CommitDescendantsExn);
 impl_into_thrift_error!(service::CommitPathExistsExn);
 impl_into_thrift_error!(service::CommitPathInfoExn);
 impl_into_thrift_error!(service::CommitMultiplePathInfoExn);
@@ -5,7 +5,7 @@
  * GNU General Public License version 2.
  */
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
 use context::CoreContext;
@@ -14,9 +14,9 @@ use futures::{future, try_join};
 use itertools::{Either, Itertools};
 use maplit::btreeset;
 use mononoke_api::{
-    unified_diff, CandidateSelectionHintArgs, ChangesetContext, ChangesetDiffItem,
-    ChangesetHistoryOptions, ChangesetId, ChangesetPathDiffContext, ChangesetSpecifier, CopyInfo,
-    MononokeError, MononokePath, UnifiedDiffMode,
+    unified_diff, BookmarkFreshness, CandidateSelectionHintArgs, ChangesetContext,
+    ChangesetDiffItem, ChangesetHistoryOptions, ChangesetId, ChangesetPathDiffContext,
+    ChangesetSpecifier, CopyInfo, MononokeError, MononokePath, RepoContext, UnifiedDiffMode,
 };
 use source_control as thrift;
 
@@ -117,6 +117,47 @@ async fn into_compare_path(
     Err(errors::internal_error("programming error, diff is neither tree nor file").into())
 }
 
+// helper used by commit_range and commit_descendants
+//
+// Only the requested page of `changeset::descendant` is fetched and mapped.
+// One extra commit is requested to find out whether there are more pages.
+async fn paginate_range(
+    repo: &RepoContext,
+    changeset: &ChangesetContext,
+    descendant: ChangesetId,
+    include_changeset: bool,
+    skip: i64,
+    limit: i64,
+    identity_schemes: &BTreeSet<thrift::CommitIdentityScheme>,
+) -> Result<
+    (
+        Vec<BTreeMap<thrift::CommitIdentityScheme, thrift::CommitId>>,
+        Option<i64>,
+    ),
+    errors::ServiceError,
+> {
+    let skip: u64 = check_range_and_convert("skip", skip, 0..)?;
+    let limit: u64 =
+        check_range_and_convert("limit", limit, 0..=source_control::COMMIT_RANGE_MAX_LIMIT)?;
+    let mut page = changeset.range_to(descendant, skip, limit + 1).await?;
+    if !include_changeset {
+        // The range ends with the commit itself, which is not a descendant.
+        page.retain(|cs_id| *cs_id != changeset.id());
+    }
+    let continue_skip = if page.len() as u64 > limit {
+        page.truncate(limit as usize);
+        Some((skip + limit) as i64)
+    } else {
+        None
+    };
+    let id_mapping = map_commit_identities(repo, page.clone(), identity_schemes).await?;
+    let commits = page
+        .into_iter()
+        .map(|cs_id| id_mapping.get(&cs_id).cloned().unwrap_or_default())
+        .collect();
+    Ok((commits, continue_skip))
+}
+
 impl SourceControlServiceImpl {
     /// Returns the lowest common ancestor of two commits.
     ///
@@ -634,6 +675,75 @@ impl SourceControlServiceImpl {
         })
     }
 
+    /// Returns the commits between this commit and a descendant commit.
+    pub(crate) async fn commit_range(
+        &self,
+        ctx: CoreContext,
+        commit: thrift::CommitSpecifier,
+        params: thrift::CommitRangeParams,
+    ) -> Result<thrift::CommitRangeResponse, errors::ServiceError> {
+        let (repo, changeset, descendant) = self
+            .repo_changeset_pair(ctx, &commit, &params.descendant_commit_id)
+            .await?;
+        let (commits, continue_skip) = paginate_range(
+            &repo,
+            &changeset,
+            descendant.id(),
+            true,
+            params.skip,
+            params.limit,
+            &params.identity_schemes,
+        )
+        .await?;
+        Ok(thrift::CommitRangeResponse {
+            commits,
+            continue_skip,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the descendants of this commit that are ancestors of a
+    /// bookmark or another commit.
+    pub(crate) async fn commit_descendants(
+        &self,
+        ctx: CoreContext,
+        commit: thrift::CommitSpecifier,
+        params: thrift::CommitDescendantsParams,
+    ) -> Result<thrift::CommitDescendantsResponse, errors::ServiceError> {
+        let (repo, changeset) = self.repo_changeset(ctx, &commit).await?;
+        let until = match (&params.bookmark, &params.until_commit_id) {
+            (Some(bookmark), None) => repo
+                .resolve_bookmark(bookmark, BookmarkFreshness::MaybeStale)
+                .await?
+                .ok_or_else(|| {
+                    errors::commit_not_found(format!("repo={} bookmark={}", repo.name(), bookmark))
+                })?
+                .id(),
+            (None, Some(until_commit_id)) => self.changeset_id(&repo, until_commit_id).await?,
+            _ => {
+                return Err(errors::invalid_request(
+                    "exactly one of bookmark and until_commit_id must be set",
+                )
+                .into());
+            }
+        };
+        let (commits, continue_skip) = paginate_range(
+            &repo,
+            &changeset,
+            until,
+            false,
+            params.skip,
+            params.limit,
+            &params.identity_schemes,
+        )
+        .await?;
+        Ok(thrift::CommitDescendantsResponse {
+            commits,
+            continue_skip,
+            ..Default::default()
+        })
+    }
+
     /// Do a cross-repo lookup to see if a commit exists under a different hash in another repo
     pub(crate) async fn commit_lookup_xrepo(
         &self,
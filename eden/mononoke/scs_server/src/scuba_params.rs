@@ -228,6 +228,29 @@ impl AddScubaParams for thrift::CommitListDescendantBookmarksParams {
     }
 }
 
+impl AddScubaParams for thrift::CommitRangeParams {
+    fn add_scuba_params(&self, scuba: &mut MononokeScubaSampleBuilder) {
+        scuba.add("other_commit", self.descendant_commit_id.to_string());
+        scuba.add("param_skip", self.skip);
+        scuba.add("param_limit", self.limit);
+        self.identity_schemes.add_scuba_params(scuba);
+    }
+}
+
+impl AddScubaParams for thrift::CommitDescendantsParams {
+    fn add_scuba_params(&self, scuba: &mut MononokeScubaSampleBuilder) {
+        if let Some(bookmark) = &self.bookmark {
+            scuba.add("bookmark_name", bookmark.as_str());
+        }
+        if let Some(until_commit_id) = &self.until_commit_id {
+            scuba.add("other_commit", until_commit_id.to_string());
+        }
+        scuba.add("param_skip", self.skip);
+        scuba.add("param_limit", self.limit);
+        self.identity_schemes.add_scuba_params(scuba);
+    }
+}
+
 impl AddScubaParams for thrift::CommitLookupXRepoParams {
     fn add_scuba_params(&self, scuba: &mut MononokeScubaSampleBuilder) {
         scuba.add("other_repo", self.other_repo.name.as_str());
@@ -60,6 +60,10 @@ impl AddScubaResponse for thrift::CommitHistoryResponse {}
 
 impl AddScubaResponse for thrift::CommitListDescendantBookmarksResponse {}
 
+impl AddScubaResponse for thrift::CommitRangeResponse {}
+
+impl AddScubaResponse for thrift::CommitDescendantsResponse {}
+
 impl AddScubaResponse for thrift::CommitPathBlameResponse {}
 
 impl AddScubaResponse for thrift::CommitPathHistoryResponse {}
@@ -605,6 +605,16 @@ impl SourceControlService for SourceControlServiceThriftImpl {
             params: thrift::CommitListDescendantBookmarksParams,
         ) -> Result<thrift::CommitListDescendantBookmarksResponse, service::CommitListDescendantBookmarksExn>;
 
+        async fn commit_range(
+            commit: thrift::CommitSpecifier,
+            params: thrift::CommitRangeParams,
+        ) -> Result<thrift::CommitRangeResponse, service::CommitRangeExn>;
+
+        async fn commit_descendants(
+            commit: thrift::CommitSpecifier,
+            params: thrift::CommitDescendantsParams,
+        ) -> Result<thrift::CommitDescendantsResponse, service::CommitDescendantsExn>;
+
         async fn commit_lookup_xrepo(
             commit: thrift::CommitSpecifier,
             params: thrift::CommitLookupXRepoParams,
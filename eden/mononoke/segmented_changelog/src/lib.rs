@@ -118,6 +118,18 @@ impl SegmentedChangelog for DisabledSegmentedChangelog {
         // None means inconclusive result, it can be returned safely
         Ok(None)
     }
+
+    async fn range(
+        &self,
+        _ctx: &CoreContext,
+        _ancestor: ChangesetId,
+        _descendant: ChangesetId,
+        _skip: u64,
+        _limit: u64,
+    ) -> Result<Option<Vec<ChangesetId>>> {
+        // None means inconclusive result, it can be returned safely
+        Ok(None)
+    }
 }
 
 #[macro_export]
@@ -182,6 +194,18 @@ macro_rules! segmented_changelog_delegate {
                 let delegate = $delegate;
                 delegate.is_ancestor($ctx, ancestor, descendant).await
             }
+
+            async fn range(
+                &$self,
+                $ctx: &CoreContext,
+                ancestor: ChangesetId,
+                descendant: ChangesetId,
+                skip: u64,
+                limit: u64,
+            ) -> Result<Option<Vec<ChangesetId>>> {
+                let delegate = $delegate;
+                delegate.range($ctx, ancestor, descendant, skip, limit).await
+            }
         }
     };
 }
@@ -425,6 +425,19 @@ impl SegmentedChangelog for OnDemandUpdateSegmentedChangelog {
         let read_dag = ReadOnlySegmentedChangelog::new(&iddag, self.idmap.clone());
         read_dag.is_ancestor(ctx, ancestor, descendant).await
     }
+
+    async fn range(
+        &self,
+        ctx: &CoreContext,
+        ancestor: ChangesetId,
+        descendant: ChangesetId,
+        skip: u64,
+        limit: u64,
+    ) -> Result<Option<Vec<ChangesetId>>> {
+        let iddag = self.iddag.read().await;
+        let read_dag = ReadOnlySegmentedChangelog::new(&iddag, self.idmap.clone());
+        read_dag.range(ctx, ancestor, descendant, skip, limit).await
+    }
 }
 
 pub struct PeriodicUpdateSegmentedChangelog {
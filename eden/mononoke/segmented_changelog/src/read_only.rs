@@ -229,6 +229,64 @@ impl<'a> SegmentedChangelog for ReadOnlySegmentedChangelog<'a> {
         Ok(Some(self.iddag.is_ancestor(*ancestor_id, *descendant_id)?))
     }
 
+    /// Find commits in `ancestor::descendant`, descendants first, skipping
+    /// `skip` of them and returning at most `limit`.
+    /// Returns None in case segmented changelog doesn't know about either of those commit.
+    async fn range(
+        &self,
+        ctx: &CoreContext,
+        ancestor: ChangesetId,
+        descendant: ChangesetId,
+        skip: u64,
+        limit: u64,
+    ) -> Result<Option<Vec<ChangesetId>>> {
+        let request_ids = self
+            .idmap
+            .find_many_dag_ids_maybe_stale(ctx, vec![ancestor, descendant])
+            .await?;
+        let ancestor_id = if let Some(ancestor_id) = request_ids.get(&ancestor) {
+            *ancestor_id
+        } else {
+            return Ok(None);
+        };
+        let descendant_id = if let Some(descendant_id) = request_ids.get(&descendant) {
+            *descendant_id
+        } else {
+            return Ok(None);
+        };
+
+        // Even though the ids exist, our local DAG might not have them.
+        let all = self.iddag.all()?;
+        if !all.contains(descendant_id) || !all.contains(ancestor_id) {
+            return Ok(None);
+        }
+
+        // Dag ids are assigned in topological order, so iterating in
+        // descending order yields descendants before ancestors. Paging is
+        // done on the spans so that only the requested ids are translated.
+        let range_ids: Vec<DagId> = self
+            .iddag
+            .range(ancestor_id.into(), descendant_id.into())?
+            .skip(skip)
+            .take(limit)
+            .iter_desc()
+            .collect();
+        let mut cs_ids = self
+            .idmap
+            .find_many_changeset_ids(ctx, range_ids.clone())
+            .await
+            .context("error retrieving mappings for range")?;
+        let range = range_ids
+            .into_iter()
+            .map(|dag_id| {
+                cs_ids
+                    .remove(&dag_id)
+                    .ok_or_else(|| format_err!("failed to find dag_id translation for {}", dag_id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(range))
+    }
+
     async fn disabled(&self, _ctx: &CoreContext) -> Result<bool> {
         Ok(false)
     }
@@ -555,6 +555,57 @@ async fn test_changeset_id_to_location_multiple_heads(fb: FacebookInit) -> Resul
     Ok(())
 }
 
+#[fbinit::test]
+async fn test_range(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    let blobrepo = linear::getrepo(fb).await;
+    let master = resolve_cs_id(&ctx, &blobrepo, "79a13814c5ce7330173ec04d279bf95ab3f652fb").await?;
+    let conns = SegmentedChangelogSqlConnections::with_sqlite_in_memory()?;
+    seed(&ctx, &blobrepo, &conns, master).await?;
+    let sc = load_owned(&ctx, &blobrepo, &conns).await?;
+
+    let added_7 =
+        resolve_cs_id(&ctx, &blobrepo, "0ed509bf086fadcb8a8a5384dc3b550729b0fc17").await?;
+    let added_8 =
+        resolve_cs_id(&ctx, &blobrepo, "a9473beb2eb03ddb1cccc3fbaeb8a4820f9cd157").await?;
+    let added_9 =
+        resolve_cs_id(&ctx, &blobrepo, "3c15267ebf11807f3d772eb891272b911ec68759").await?;
+
+    assert_eq!(
+        sc.range(&ctx, added_7, added_9, 0, u64::MAX).await?,
+        Some(vec![added_9, added_8, added_7])
+    );
+    assert_eq!(
+        sc.range(&ctx, added_8, added_8, 0, u64::MAX).await?,
+        Some(vec![added_8])
+    );
+    // `added_9` is not an ancestor of `added_7`
+    assert_eq!(
+        sc.range(&ctx, added_9, added_7, 0, u64::MAX).await?,
+        Some(vec![])
+    );
+
+    // Paging through the range
+    assert_eq!(
+        sc.range(&ctx, added_7, added_9, 0, 2).await?,
+        Some(vec![added_9, added_8])
+    );
+    assert_eq!(
+        sc.range(&ctx, added_7, added_9, 2, 2).await?,
+        Some(vec![added_7])
+    );
+    assert_eq!(sc.range(&ctx, added_7, added_9, 3, 2).await?, Some(vec![]));
+
+    // Commits unknown to segmented changelog give an inconclusive answer
+    let unknown = CreateCommitContext::new(&ctx, &blobrepo, vec![master])
+        .add_file("unknown", "unknown")
+        .commit()
+        .await?;
+    assert_eq!(sc.range(&ctx, added_7, unknown, 0, u64::MAX).await?, None);
+
+    Ok(())
+}
+
 #[fbinit::test]
 async fn test_build_incremental_from_scratch(fb: FacebookInit) -> Result<()> {
     let ctx = CoreContext::test_mock(fb);
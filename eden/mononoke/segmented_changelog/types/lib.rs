@@ -219,6 +219,23 @@ pub trait SegmentedChangelog: Send + Sync {
         ancestor: ChangesetId,
         descendant: ChangesetId,
     ) -> Result<Option<bool>>;
+
+    /// Find all commits that are descendants of `ancestor` and ancestors of
+    /// `descendant` (`ancestor::descendant` in revset notation), including
+    /// both of them.  Commits are returned in reverse topological order,
+    /// i.e. descendants come before their ancestors.  The first `skip`
+    /// commits of that order are omitted and at most `limit` commits are
+    /// returned, so callers paging through a large range only pay for the
+    /// page they ask for.
+    /// Returns None in case segmented changelog doesn't know about either of those commit.
+    async fn range(
+        &self,
+        ctx: &CoreContext,
+        ancestor: ChangesetId,
+        descendant: ChangesetId,
+        skip: u64,
+        limit: u64,
+    ) -> Result<Option<Vec<ChangesetId>>>;
 }
 
 #[derive(Debug, Error)]
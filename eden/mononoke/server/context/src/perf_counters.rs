@@ -5,9 +5,51 @@
  * GNU General Public License version 2.
  */
 
+use lazy_static::lazy_static;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{register_int_counter_vec, IntCounterVec, IntGaugeVec, Opts};
 use scuba_ext::MononokeScubaSampleBuilder;
 use std::sync::atomic::{AtomicI64, Ordering};
 
+lazy_static! {
+    static ref PROMETHEUS_SUM_COUNTERS: IntCounterVec = register_int_counter_vec!(
+        "mononoke_perf_counters_total",
+        "Perf counters summed over all completed requests",
+        &["counter"]
+    )
+    .unwrap();
+    static ref PROMETHEUS_MAX_COUNTERS: IntGaugeVec = {
+        let gauges = IntGaugeVec::new(
+            Opts::new(
+                "mononoke_perf_counters_max",
+                "Largest value of max-type perf counters seen by a request completed since \
+                 the previous scrape",
+            ),
+            &["counter"],
+        )
+        .unwrap();
+        prometheus::register(Box::new(ResetOnCollect(gauges.clone()))).unwrap();
+        gauges
+    };
+}
+
+/// Exports gauges and resets them, so that each scrape only reports the values set since the
+/// previous one.
+struct ResetOnCollect(IntGaugeVec);
+
+impl Collector for ResetOnCollect {
+    fn desc(&self) -> Vec<&Desc> {
+        self.0.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let families = self.0.collect();
+        self.0.reset();
+        families
+    }
+}
+
 macro_rules! define_perf_counters {
     (enum $enum_name:ident {
         $($variant:ident),*,
@@ -272,6 +314,28 @@ impl PerfCounters {
             }
         }
     }
+
+    /// Aggregate these counters into the process-wide Prometheus metrics. This should be called
+    /// once per request, when the request completes.
+    pub fn report_to_prometheus(&self) {
+        for key in PERF_COUNTERS.iter() {
+            let value = self.get_counter(*key);
+            if value <= 0 {
+                continue;
+            }
+            match key.expected_update_func() {
+                PerfCounterTypeUpdateFunc::Add => PROMETHEUS_SUM_COUNTERS
+                    .with_label_values(&[key.name()])
+                    .inc_by(value as u64),
+                PerfCounterTypeUpdateFunc::Max => {
+                    let gauge = PROMETHEUS_MAX_COUNTERS.with_label_values(&[key.name()]);
+                    if value > gauge.get() {
+                        gauge.set(value);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -303,4 +367,41 @@ mod test {
         ctrs.set_max_counter(k, 2);
         assert_eq!(ctrs.get_counter(k), 3);
     }
+
+    #[test]
+    fn test_report_to_prometheus() {
+        let sum = PROMETHEUS_SUM_COUNTERS.with_label_values(&[PerfCounterType::SqlWrites.name()]);
+        let max = PROMETHEUS_MAX_COUNTERS
+            .with_label_values(&[PerfCounterType::GetpackMaxFileSize.name()]);
+        let initial_sum = sum.get();
+
+        let ctrs = PerfCounters::default();
+        ctrs.add_to_counter(PerfCounterType::SqlWrites, 2);
+        ctrs.set_max_counter(PerfCounterType::GetpackMaxFileSize, 10);
+        ctrs.report_to_prometheus();
+
+        let ctrs = PerfCounters::default();
+        ctrs.add_to_counter(PerfCounterType::SqlWrites, 3);
+        ctrs.set_max_counter(PerfCounterType::GetpackMaxFileSize, 5);
+        ctrs.report_to_prometheus();
+
+        assert_eq!(sum.get(), initial_sum + 5);
+        assert_eq!(max.get(), 10);
+
+        // Scraping reports the max, and starts a new window.
+        let scraped = prometheus::gather()
+            .into_iter()
+            .find(|family| family.get_name() == "mononoke_perf_counters_max")
+            .expect("max counters are registered");
+        assert!(scraped.get_metric().iter().any(|metric| {
+            metric.get_gauge().get_value() == 10.0
+                && metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_value() == PerfCounterType::GetpackMaxFileSize.name())
+        }));
+        let max = PROMETHEUS_MAX_COUNTERS
+            .with_label_values(&[PerfCounterType::GetpackMaxFileSize.name()]);
+        assert_eq!(max.get(), 0);
+    }
 }
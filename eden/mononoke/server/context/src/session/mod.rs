@@ -97,7 +97,7 @@ impl SessionContainer {
 
     pub fn bump_load(&self, metric: Metric, load: LoadCost) {
         if let Some(limiter) = self.rate_limiter() {
-            limiter.bump_load(metric, load)
+            limiter.bump_load(metric, self.metadata().identities(), load)
         }
     }
 
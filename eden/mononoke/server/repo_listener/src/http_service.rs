@@ -9,6 +9,7 @@ use anyhow::{anyhow, Context, Error, Result};
 #[cfg(fbcode_build)]
 use clientinfo::{ClientInfo, CLIENT_INFO_HEADER};
 use futures::future::{BoxFuture, FutureExt};
+use gotham_ext::middleware::metrics::{render_metrics, METRICS_PATH};
 use gotham_ext::socket_data::TlsSocketData;
 use http::{HeaderMap, HeaderValue, Method, Request, Response, Uri};
 use hyper::{service::Service, Body};
@@ -142,6 +143,15 @@ where
             return Ok(res);
         }
 
+        if req.method() == Method::GET && req.uri().path() == METRICS_PATH {
+            // Metrics describe all the traffic on this server, so only trusted parties may see
+            // them.
+            if !self.conn.is_trusted {
+                return Err(HttpError::Forbidden);
+            }
+            return render_metrics().map_err(HttpError::internal);
+        }
+
         let upgrade = req
             .headers()
             .get(http::header::UPGRADE)
@@ -18,7 +18,12 @@ use futures::compat::Future01CompatExt;
 use futures_old::{sync::mpsc, Future, Stream};
 use futures_stats::TimedFutureExt;
 use hgproto::{sshproto, HgProtoHandler};
+use lazy_static::lazy_static;
 use maplit::{hashmap, hashset};
+use prometheus::{
+    register_histogram, register_int_counter_vec, register_int_gauge, Histogram, IntCounterVec,
+    IntGauge,
+};
 use qps::Qps;
 use rate_limiting::Metric;
 use rate_limiting::RateLimitEnvironment;
@@ -46,6 +51,43 @@ define_stats! {
     request_outcome_permille: timeseries(Average),
 }
 
+lazy_static! {
+    static ref WIREPROTO_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "mononoke_wireproto_requests_total",
+        "Number of wireproto requests served, by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+    static ref WIREPROTO_REQUEST_DURATION: Histogram = register_histogram!(
+        "mononoke_wireproto_request_duration_seconds",
+        "Time taken to serve wireproto requests",
+        prometheus::exponential_buckets(0.001, 2.0, 20).unwrap()
+    )
+    .unwrap();
+    static ref WIREPROTO_REQUESTS_IN_FLIGHT: IntGauge = register_int_gauge!(
+        "mononoke_wireproto_requests_in_flight",
+        "Number of wireproto requests currently being served"
+    )
+    .unwrap();
+}
+
+/// Counts a request in `WIREPROTO_REQUESTS_IN_FLIGHT` until it is dropped, so that requests that
+/// are cancelled are not counted forever.
+struct InFlightRequest;
+
+impl InFlightRequest {
+    fn new() -> Self {
+        WIREPROTO_REQUESTS_IN_FLIGHT.inc();
+        Self
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        WIREPROTO_REQUESTS_IN_FLIGHT.dec();
+    }
+}
+
 pub async fn request_handler(
     fb: FacebookInit,
     reponame: String,
@@ -190,7 +232,9 @@ pub async fn request_handler(
         .map(|_| ());
 
     // If we got an error at this point, then catch it and print a message
+    let in_flight = InFlightRequest::new();
     let (stats, result) = endres.compat().timed().await;
+    drop(in_flight);
 
     let wireproto_calls = {
         let mut wireproto_calls = wireproto_calls.lock().expect("lock poisoned");
@@ -198,6 +242,7 @@ pub async fn request_handler(
     };
 
     STATS::wireproto_ms.add_value(stats.completion_time.as_millis_unchecked() as i64);
+    WIREPROTO_REQUEST_DURATION.observe(stats.completion_time.as_secs_f64());
 
     let mut scuba = scuba.clone();
 
@@ -211,15 +256,18 @@ pub async fn request_handler(
 
     // Log request level perf counters
     request_perf_counters.insert_perf_counters(&mut scuba);
+    request_perf_counters.report_to_prometheus();
 
     match &result {
         Ok(_) => {
             STATS::request_success.add_value(1);
+            WIREPROTO_REQUESTS.with_label_values(&["success"]).inc();
             STATS::request_outcome_permille.add_value(1000);
             scuba.log_with_msg("Request finished - Success", None)
         }
         Err(err) => {
             STATS::request_failure.add_value(1);
+            WIREPROTO_REQUESTS.with_label_values(&["failure"]).inc();
             STATS::request_outcome_permille.add_value(0);
             scuba.log_with_msg("Request finished - Failure", format!("{:#?}", err));
         }
@@ -5,31 +5,265 @@
  * GNU General Public License version 2.
  */
 
+//! In-process time window counters for open source builds.
+//!
+//! There is no shared counter service outside of Facebook, so counters only count what this
+//! process has bumped. Counters are kept in a process-wide registry keyed by category and key, so
+//! that all counters built for the same key see the same values. Each counter keeps one bucket
+//! per second for as long as its largest time window, and counters that have not been bumped for
+//! that long are evicted periodically.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
 use async_trait::async_trait;
 use fbinit::FacebookInit;
+use lazy_static::lazy_static;
 
 use crate::{BoxGlobalTimeWindowCounter, GlobalTimeWindowCounter, GlobalTimeWindowCounterBuilder};
 
-struct AlwaysZeroCounter {}
+lazy_static! {
+    static ref COUNTERS: Arc<Counters> = Arc::new(Counters::new(Arc::new(SystemClock)));
+}
+
+/// How often stale counters are evicted.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Source of the current time, so that tests can control how values expire.
+pub(crate) trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Instant;
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Values bumped in each second, oldest first.
+struct Buckets {
+    buckets: VecDeque<(u64, f64)>,
+    max_time_window: u32,
+}
+
+impl Buckets {
+    fn trim(&mut self, now: u64) {
+        let max_time_window = u64::from(self.max_time_window);
+        while let Some((second, _)) = self.buckets.front() {
+            if second + max_time_window > now {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+}
+
+struct CountersState {
+    counters: HashMap<(String, String), Arc<Mutex<Buckets>>>,
+    last_eviction: u64,
+}
+
+/// All counters in the process.
+pub(crate) struct Counters {
+    clock: Arc<dyn Clock>,
+    origin: Instant,
+    state: Mutex<CountersState>,
+}
+
+impl Counters {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        let origin = clock.now();
+        Self {
+            clock,
+            origin,
+            state: Mutex::new(CountersState {
+                counters: HashMap::new(),
+                last_eviction: 0,
+            }),
+        }
+    }
+
+    /// Seconds since the counters were created.
+    fn now(&self) -> u64 {
+        self.clock
+            .now()
+            .saturating_duration_since(self.origin)
+            .as_secs()
+    }
+
+    pub(crate) fn counter(
+        self: &Arc<Self>,
+        category: &str,
+        key: &str,
+        min_time_window: u32,
+        max_time_window: u32,
+    ) -> InProcessCounter {
+        let now = self.now();
+        let mut state = self.state.lock().expect("lock poisoned");
+
+        if now.saturating_sub(state.last_eviction) >= EVICTION_INTERVAL.as_secs() {
+            // A counter with no values left is recreated identically the next time it is needed.
+            state.counters.retain(|_, buckets| {
+                let mut buckets = buckets.lock().expect("lock poisoned");
+                buckets.trim(now);
+                !buckets.buckets.is_empty()
+            });
+            state.last_eviction = now;
+        }
+
+        let buckets = state
+            .counters
+            .entry((category.to_string(), key.to_string()))
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Buckets {
+                    buckets: VecDeque::new(),
+                    max_time_window,
+                }))
+            })
+            .clone();
+        {
+            let mut buckets = buckets.lock().expect("lock poisoned");
+            buckets.max_time_window = buckets.max_time_window.max(max_time_window);
+        }
+
+        InProcessCounter {
+            counters: self.clone(),
+            buckets,
+            min_time_window,
+            max_time_window,
+        }
+    }
+}
+
+pub(crate) struct InProcessCounter {
+    counters: Arc<Counters>,
+    buckets: Arc<Mutex<Buckets>>,
+    min_time_window: u32,
+    max_time_window: u32,
+}
 
 #[async_trait]
-impl GlobalTimeWindowCounter for AlwaysZeroCounter {
-    async fn get(&self, _time_window: u32) -> Result<f64> {
-        Ok(0.0)
+impl GlobalTimeWindowCounter for InProcessCounter {
+    async fn get(&self, time_window: u32) -> Result<f64> {
+        let time_window = u64::from(
+            time_window
+                .max(self.min_time_window)
+                .min(self.max_time_window),
+        );
+        let now = self.counters.now();
+        let buckets = self.buckets.lock().expect("lock poisoned");
+        Ok(buckets
+            .buckets
+            .iter()
+            .filter(|(second, _)| second + time_window > now)
+            .map(|(_, value)| value)
+            .sum())
     }
 
-    fn bump(&self, _value: f64) {}
+    fn bump(&self, value: f64) {
+        let now = self.counters.now();
+        let mut buckets = self.buckets.lock().expect("lock poisoned");
+        buckets.trim(now);
+        match buckets.buckets.back_mut() {
+            Some((second, total)) if *second == now => *total += value,
+            _ => buckets.buckets.push_back((now, value)),
+        }
+    }
 }
 
 impl GlobalTimeWindowCounterBuilder {
     pub fn build(
         _fb: FacebookInit,
-        _category: impl AsRef<str>,
-        _key: impl AsRef<str>,
-        _min_time_window: u32,
-        _max_time_window: u32,
+        category: impl AsRef<str>,
+        key: impl AsRef<str>,
+        min_time_window: u32,
+        max_time_window: u32,
     ) -> BoxGlobalTimeWindowCounter {
-        Box::new(AlwaysZeroCounter {})
+        Box::new(COUNTERS.counter(
+            category.as_ref(),
+            key.as_ref(),
+            min_time_window,
+            max_time_window,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct MockClock(Mutex<Instant>);
+
+    impl MockClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[fbinit::test]
+    async fn test_counter(_fb: FacebookInit) -> Result<()> {
+        let clock = MockClock::new();
+        let counters = Arc::new(Counters::new(clock.clone()));
+
+        let counter = counters.counter("category", "key", 1, 60);
+        counter.bump(1.0);
+        clock.advance(Duration::from_secs(5));
+        counter.bump(2.0);
+        assert_eq!(counter.get(10).await?, 3.0);
+        assert_eq!(counter.get(5).await?, 2.0);
+
+        // Counters for the same key share their values, other keys don't.
+        let same = counters.counter("category", "key", 10, 60);
+        assert_eq!(same.get(10).await?, 3.0);
+        let other = counters.counter("category", "other", 10, 60);
+        assert_eq!(other.get(10).await?, 0.0);
+        let other = counters.counter("other", "key", 10, 60);
+        assert_eq!(other.get(10).await?, 0.0);
+
+        // Windows are clamped to the range the counter was built with.
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(counter.get(3600).await?, 3.0);
+        let clamped = counters.counter("category", "key", 20, 60);
+        assert_eq!(clamped.get(1).await?, 3.0);
+
+        // Values fall out of the largest window.
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(counter.get(60).await?, 0.0);
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_stale_counters_are_evicted(_fb: FacebookInit) -> Result<()> {
+        let clock = MockClock::new();
+        let counters = Arc::new(Counters::new(clock.clone()));
+        let counter_count = || counters.state.lock().unwrap().counters.len();
+
+        counters.counter("category", "old", 10, 10).bump(1.0);
+        clock.advance(Duration::from_secs(55));
+        counters.counter("category", "new", 10, 10).bump(1.0);
+        assert_eq!(counter_count(), 2);
+
+        // After 60 seconds, the old counter is empty but the new one isn't.
+        clock.advance(Duration::from_secs(5));
+        let counter = counters.counter("category", "new", 10, 10);
+        assert_eq!(counter_count(), 1);
+        assert_eq!(counter.get(10).await?, 1.0);
+
+        Ok(())
     }
 }
@@ -587,7 +587,7 @@ def inmemorymerge(ui, repo, src, dest, base):
     src is "remote" and dest is "local".
     """
     mergeresult = nativecheckout.mergeresult(
-        src.manifest(), dest.manifest(), base.manifest()
+        repo.ui._rcfg._rcfg, src.manifest(), dest.manifest(), base.manifest()
     )
 
     manifestbuilder = mergeresult.manifestbuilder()
@@ -641,7 +641,7 @@ class rebaseruntime(object):
         p1ctx = repo[p1]
         basectx = repo[base]
         mergeresult = nativecheckout.mergeresult(
-            ctx.manifest(), p1ctx.manifest(), basectx.manifest()
+            repo.ui._rcfg._rcfg, ctx.manifest(), p1ctx.manifest(), basectx.manifest()
         )
         manifestbuilder = mergeresult.manifestbuilder()
         if manifestbuilder is None:
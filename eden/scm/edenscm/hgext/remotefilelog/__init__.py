@@ -1080,6 +1080,17 @@ def debugwaitonprefetch(ui, repo, **opts):
     return debugcommands.debugwaitonprefetch(repo)
 
 
+@command("debuglfsprune", [], _("hg debuglfsprune"))
+def debuglfsprune(ui, repo, **opts):
+    """evict least recently used blobs from the shared LFS cache
+
+    Blobs are evicted until the shared LFS cache fits in ``lfs.cachelimit``.
+    Nothing is evicted if ``lfs.cachelimit`` is not set. Blobs in the local
+    LFS store might not be uploaded yet and are never evicted.
+    """
+    return debugcommands.debuglfsprune(ui, repo)
+
+
 def resolveprefetchopts(ui, opts):
     if not opts.get("rev"):
         revset = [".", "draft()"]
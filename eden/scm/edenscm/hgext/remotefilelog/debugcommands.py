@@ -505,3 +505,11 @@ def debugwaitonprefetch(repo):
         _("prefetching in %s") % repo.origroot,
     ):
         pass
+
+
+def debuglfsprune(ui, repo):
+    filescmstore = getattr(repo.fileslog, "filescmstore", None)
+    if filescmstore is None:
+        raise error.Abort(_("debuglfsprune requires a shallow repository"))
+    blobs, size = filescmstore.prunelfscache()
+    ui.status(_("evicted %d LFS blobs (%s)\n") % (blobs, util.bytecount(size)))
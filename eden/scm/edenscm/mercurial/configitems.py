@@ -213,6 +213,7 @@ coreconfigitem("devel", "deprec-warn", default=False)
 coreconfigitem("devel", "disableloaddefaultcerts", default=False)
 coreconfigitem("devel", "legacy.exchange", default=list)
 coreconfigitem("devel", "legacy.revnum", default="accept")
+coreconfigitem("devel", "rust-log-no-fallback", default=False)
 coreconfigitem("devel", "servercafile", default="")
 coreconfigitem("devel", "serverexactprotocol", default="")
 coreconfigitem("devel", "serverrequirecert", default=False)
@@ -302,6 +303,8 @@ coreconfigitem("experimental", "revf64compat", default=True)
 
 # load Rust-based HgCommits on changelog.
 coreconfigitem("experimental", "rust-commits", default=True)
+# use the native "log" command when its options are supported.
+coreconfigitem("experimental", "rust-log", default=False)
 
 coreconfigitem("experimental", "single-head-per-branch", default=False)
 coreconfigitem("experimental", "spacemovesdown", default=False)
@@ -384,6 +387,7 @@ coreconfigitem(
     "merge-tools", r".*\.premerge$", default=dynamicdefault, generic=True, priority=-1
 )
 coreconfigitem("merge-tools", r".*\.symlink$", default=False, generic=True, priority=-1)
+coreconfigitem("metalog", "resolve-conflicts", default=False)
 coreconfigitem("metalog", "track-config", default=True)
 coreconfigitem("mononokepeer", "compression", default=False)
 coreconfigitem("mononokepeer", "sockettimeout", default=15.0)
@@ -598,6 +602,9 @@ coreconfigitem("worker", "backgroundcloseminfilecount", default=2048)
 coreconfigitem("worker", "backgroundclosethreadcount", default=4)
 coreconfigitem("worker", "enabled", default=True)
 coreconfigitem("worker", "numcpus", default=None)
+# Threads walking the working copy for rustpendingchanges. Less than 2 walks
+# on the calling thread.
+coreconfigitem("workingcopy", "walkerthreads", default=0)
 
 # Rebase related configuration moved to core because other extension are doing
 # strange things. For example, shelve import the extensions to reuse some bit
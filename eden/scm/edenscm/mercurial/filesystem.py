@@ -29,6 +29,9 @@ configtable = {}
 configitem = registrar.configitem(configtable)
 configitem("workingcopy", "enablerustwalker", default=False)
 configitem("workingcopy", "rustpendingchanges", default=False)
+# Socket of a watchman-compatible file system watcher, used by the Rust
+# pending changes to avoid walking the working copy.
+configitem("workingcopy", "watchersocket", default=None)
 
 
 class physicalfilesystem(object):
@@ -164,7 +167,11 @@ class physicalfilesystem(object):
         or not.
         """
         if self.ui.configbool("workingcopy", "rustpendingchanges", False):
-            physicalfs = workingcopy.physicalfilesystem(self.opener.join(""))
+            physicalfs = workingcopy.physicalfilesystem(
+                self.opener.join(""),
+                self.ui.config("workingcopy", "watchersocket"),
+                self.ui.configint("workingcopy", "walkerthreads"),
+            )
             pendingchanges = physicalfs.pendingchanges(
                 self.dirstate._map._tree, match, False, self.dirstate._lastnormaltime
             )
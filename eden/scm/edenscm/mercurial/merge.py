@@ -1838,6 +1838,16 @@ def applyupdates(repo, actions, wctx, mctx, overwrite, labels=None, ancestors=No
             wctx[f].write(mctx.filectx(f0).data(), flags)
             updated += 1
 
+        # merged cleanly by native merge, write the merged content
+        for f, args, msg in actions.get("gm", []):
+            repo.ui.debug(" %s: %s -> gm\n" % (f, msg))
+            z += 1
+            prog.value = (z, f)
+            flags, content = args
+            wctx[f].audit()
+            wctx[f].write(content, flags)
+            merged += 1
+
         # exec
         for f, args, msg in actions["e"]:
             repo.ui.debug(" %s: %s -> e\n" % (f, msg))
@@ -2080,6 +2090,14 @@ def recordupdates(repo, actions, branchmerge):
                     repo.dirstate.delete(f1)
             prog.value += 1
 
+        # merged cleanly by native merge
+        for f, args, msg in actions.get("gm", []):
+            if branchmerge:
+                repo.dirstate.merge(f)
+            else:
+                repo.dirstate.normallookup(f)
+            prog.value += 1
+
         # directory rename, move local
         for f, args, msg in actions.get("dm", []):
             f0, flag = args
@@ -2504,7 +2522,9 @@ def update(
                 raise error.Abort(msg.strip(), hint=hint)
 
         # Convert to dictionary-of-lists format
-        actions = dict((m, []) for m in "a am f g cd dc r rg dm dg m e k p pr".split())
+        actions = dict(
+            (m, []) for m in "a am f g gm cd dc r rg dm dg m e k p pr".split()
+        )
         for f, (m, args, msg) in pycompat.iteritems(actionbyfile):
             if m not in actions:
                 actions[m] = []
@@ -2773,11 +2793,18 @@ def donativecheckout(repo, p1, p2, xp1, xp2, matcher, force, partial, wc, prerec
 def calculateupdatesnative(repo, p1, p2, pa):
     if not repo.ui.configbool("experimental", "nativerebase"):
         return None
+    # Text files changed on both sides that merge cleanly are not conflicts.
     mergeresult = nativecheckout.mergeresult(
-        p2.manifest(), p1.manifest(), pa.manifest()
+        repo.ui._rcfg._rcfg,
+        p2.manifest(),
+        p1.manifest(),
+        pa.manifest(),
+        repo.fileslog.filescmstore,
     )
     # Returns None if mergeresult can not be converted fully into Python actions
     actions = mergeresult.pymerge_actions()
+    if actions is not None:
+        repo.ui.debug("Using native merge\n")
     return actions
 
 
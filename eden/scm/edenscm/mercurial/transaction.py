@@ -660,10 +660,18 @@ class transaction(util.transactional):
             trdesc = "Transaction: %s" % self.desc
             message = "\n".join([command, parent, trdesc])
 
+            # Merge bookmarks, remotenames and visibleheads changed by others
+            # instead of failing the transaction.
+            resolve = bool(
+                self.uiconfig
+                and self.uiconfig.configbool("metalog", "resolve-conflicts")
+            )
+
             util.failpoint("transaction-metalog-commit")
             metalog.commit(
                 message,
                 int(util.timer()),
+                resolve=resolve,
             )
             # Discard metalog state when exiting transaction.
             del svfs.__dict__["metalog"]
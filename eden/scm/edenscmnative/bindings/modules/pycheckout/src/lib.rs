@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::SystemTime;
 
+use anyhow::format_err;
 use anyhow::Result;
 use async_runtime::try_block_unless_interrupted;
 use checkout::Action;
@@ -20,6 +21,7 @@ use checkout::CheckoutPlan;
 use checkout::Conflict;
 use checkout::Merge;
 use checkout::MergeResult;
+use checkout::PyMergeAction;
 use cpython::*;
 use cpython_ext::ExtractInner;
 use cpython_ext::ExtractInnerRef;
@@ -38,10 +40,12 @@ use pyrevisionstore::contentstore;
 use pyrevisionstore::filescmstore;
 use pystatus::status as PyStatus;
 use pytreestate::treestate as PyTreeState;
+use revisionstore::scmstore::FileAttributes;
 use revisionstore::LegacyStore;
 use tracing::warn;
 use treestate::filestate::FileStateV2;
 use treestate::filestate::StateFlags;
+use types::Key;
 use types::RepoPath;
 use vfs::VFS;
 
@@ -206,13 +210,21 @@ py_class!(class mergeresult |py| {
 
     def __new__(
         _cls,
+        config: &config,
         src_manifest: &treemanifest,
         dst_manifest: &treemanifest,
         ancestor_manifest: &treemanifest,
+        // If set, text files changed on both sides are merged using contents from this store.
+        scmstore: Option<PyObject> = None,
         // matcher: Option<PyObject> = None,
         // If sparse profile changes, contains Some((old_sparse_matcher, new_sparse_matcher))
         // sparse_change: Option<(PyObject, PyObject)> = None,
     ) -> PyResult<mergeresult> {
+        let merge = Merge::from_config(&config.get_cfg(py));
+        let store = match scmstore {
+            Some(store) => Some(filescmstore::downcast_from(py, store)?.extract_inner(py)),
+            None => None,
+        };
         let src_lock = src_manifest.get_underlying(py);
         let dst_lock = dst_manifest.get_underlying(py);
         let ancestor_lock = ancestor_manifest.get_underlying(py);
@@ -220,7 +232,18 @@ py_class!(class mergeresult |py| {
             let src = src_lock.read();
             let dst = dst_lock.read();
             let ancestor = ancestor_lock.read();
-            Merge{}.merge(&*src, &*dst, &*ancestor)
+            let mut result = merge.merge(&*src, &*dst, &*ancestor)?;
+            if let Some(store) = store {
+                merge.merge_file_contents(&mut result, |path, meta| {
+                    let key = Key::new(path.to_owned(), meta.hgid);
+                    store
+                        .fetch(std::iter::once(key), FileAttributes::CONTENT)
+                        .single()?
+                        .ok_or_else(|| format_err!("file {} {} not found", path, meta.hgid))?
+                        .file_content()
+                })?;
+            }
+            Ok(result)
         }).map_pyerr(py)?;
         mergeresult::create_instance(py, merge_result)
     }
@@ -229,10 +252,19 @@ py_class!(class mergeresult |py| {
         Ok(PyString::new(py, &self.merge_result(py).to_string()))
     }
 
-    def pymerge_actions(&self) -> PyResult<Option<HashMap<String, (&'static str, (&'static str, bool), &'static str)>>> {
+    def pymerge_actions(&self) -> PyResult<Option<HashMap<String, PyObject>>> {
         let actions = self.merge_result(py).try_actions();
         if let Some(actions) = actions {
-            Ok(Some(actions.iter().map(|(k,v)|(k.to_string(), v.pymerge_action())).collect()))
+            Ok(Some(actions.iter().map(|(path, action)| {
+                let action = match action.pymerge_action() {
+                    PyMergeAction::Simple(name, args, message) => (name, args, message).to_py_object(py).into_object(),
+                    PyMergeAction::Merged(name, (flags, content), message) => {
+                        let content = PyBytes::new(py, content.as_ref());
+                        (name, (flags, content), message).to_py_object(py).into_object()
+                    }
+                };
+                (path.to_string(), action)
+            }).collect()))
         } else {
             Ok(None)
         }
@@ -16,6 +16,7 @@ pub mod commits;
 pub mod dagalgo;
 pub mod idmap;
 pub mod nameset;
+pub mod revset;
 pub mod spanset;
 
 pub use nameset::Names;
@@ -37,6 +38,9 @@ pub fn init_module(py: Python, package: &str) -> PyResult<PyModule> {
     m.add_class::<nameset::nameset>(py)?;
     m.add_class::<spanset::spans>(py)?;
 
+    // revset language
+    m.add_class::<revset::revset>(py)?;
+
     // maximum Id
     m.add(py, "MAX_ID", dag::Id::MAX.0)?;
 
@@ -0,0 +1,108 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::cell::RefCell;
+
+use ::revset::Aliases;
+use ::revset::Arg;
+use ::revset::Evaluator;
+use async_runtime::try_block_unless_interrupted as block_on;
+use cpython::*;
+use cpython_ext::AnyhowResultExt;
+use cpython_ext::PyNone;
+use cpython_ext::ResultPyErrExt;
+use cpython_ext::Str;
+
+use crate::dagalgo::dagalgo;
+use crate::Names;
+
+py_class!(pub class revset |py| {
+    data inner: RefCell<Evaluator>;
+    data errors: Vec<(String, String)>;
+
+    /// Create a revset evaluator on a dag.
+    ///
+    /// `aliases` is a list of `(declaration, definition)` pairs, like the
+    /// items in the `[revsetalias]` config section.
+    def __new__(_cls, dag: dagalgo, aliases: Vec<(String, String)> = Vec::new()) -> PyResult<Self> {
+        let mut table = Aliases::new();
+        for (decl, defn) in aliases {
+            table.insert(&decl, &defn);
+        }
+        let errors = table
+            .errors()
+            .into_iter()
+            .map(|(decl, message)| (decl.to_string(), message.to_string()))
+            .collect();
+        let evaluator = Evaluator::new(dag.dag(py).clone()).with_aliases(table);
+        Self::create_instance(py, RefCell::new(evaluator), errors)
+    }
+
+    /// Broken aliases, as a list of `(declaration, message)`.
+    def aliaserrors(&self) -> PyResult<Vec<(String, String)>> {
+        Ok(self.errors(py).clone())
+    }
+
+    /// Register a function used by revsets, like `bookmark(name)`.
+    ///
+    /// `func` is called with a list of `(text, set)` arguments and returns a
+    /// set. `text` is the name of a symbol, the content of a quoted string, or
+    /// the normalized revset form of other arguments. `set` is the evaluated
+    /// argument, or `None` if it is not a revision, like a pattern.
+    def registerfunc(&self, name: String, func: PyObject) -> PyResult<PyNone> {
+        self.inner(py).borrow_mut().register_function(name, move |args: &[Arg]| {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+            let args: Vec<(String, Option<Names>)> = args
+                .iter()
+                .map(|arg| (arg.text.clone(), arg.set.clone().map(Names)))
+                .collect();
+            let set = func.call(py, (args,), None).into_anyhow_result()?;
+            let set: Names = set.extract(py).into_anyhow_result()?;
+            Ok(set.0)
+        });
+        Ok(PyNone)
+    }
+
+    /// Set how symbols are resolved.
+    ///
+    /// `func(name)` returns a set, or `None` to look up `name` as a vertex in
+    /// the dag.
+    def setsymbolresolver(&self, func: PyObject) -> PyResult<PyNone> {
+        self.inner(py).borrow_mut().set_symbol_resolver(move |name: &str| {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+            let set = func.call(py, (name,), None).into_anyhow_result()?;
+            let set: Option<Names> = set.extract(py).into_anyhow_result()?;
+            Ok(set.map(|s| s.0))
+        });
+        Ok(PyNone)
+    }
+
+    /// Evaluate a revset string to a set.
+    def eval(&self, spec: &str) -> PyResult<Names> {
+        // Callbacks may call back into this object, for example to register
+        // functions, so don't keep it borrowed while evaluating.
+        let evaluator = self.inner(py).borrow().clone();
+        let set = block_on(async {
+            evaluator.eval_str(spec).await.map_err(|e| match e {
+                // Preserve errors (ex. Python exceptions) from callbacks.
+                ::revset::Error::Other(e) => e,
+                e => e.into(),
+            })
+        })
+        .map_pyerr(py)?;
+        Ok(Names(set))
+    }
+
+    /// Parse a revset string. Return its normalized form.
+    @staticmethod
+    def parse(spec: &str) -> PyResult<Str> {
+        let expr = ::revset::parse(spec).map_pyerr(py)?;
+        Ok(expr.to_string().into())
+    }
+});
@@ -11,6 +11,7 @@ use std::cell::RefCell;
 use std::path::Path;
 use std::time::SystemTime;
 
+use ::metalog::resolver::Resolvers;
 use ::metalog::CommitOptions;
 use ::metalog::Id20;
 use ::metalog::MetaLog;
@@ -98,9 +99,15 @@ py_class!(pub class metalog |py| {
     }
 
     /// Write pending data to disk. Raise if race condition is detected.
-    def commit(&self, message: &str, time: Option<u64> = None, pending: bool = false) -> PyResult<Bytes> {
+    ///
+    /// If `resolve` is True, changes to "bookmarks", "remotenames" and
+    /// "visibleheads" made by others are merged instead of raising.
+    def commit(&self, message: &str, time: Option<u64> = None, pending: bool = false, resolve: bool = false) -> PyResult<Bytes> {
         let mut opts = CommitOptions::default();
         opts.detached = pending;
+        if resolve {
+            opts.resolver = Some(Resolvers::with_defaults().into_resolver());
+        }
         opts.timestamp = time.unwrap_or_else(|| {
             SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
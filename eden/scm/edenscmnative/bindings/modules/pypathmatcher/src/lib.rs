@@ -7,6 +7,7 @@
 
 #![allow(non_camel_case_types)]
 
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -19,6 +20,9 @@ use cpython_ext::ExtractInnerRef;
 use cpython_ext::PyPath;
 use cpython_ext::PyPathBuf;
 use cpython_ext::Str;
+use pathmatcher::sparse::Profile;
+use pathmatcher::sparse::Root;
+use pathmatcher::sparse::SparseMatcher;
 use pathmatcher::AlwaysMatcher;
 use pathmatcher::DifferenceMatcher;
 use pathmatcher::DirectoryMatch;
@@ -34,6 +38,7 @@ pub fn init_module(py: Python, package: &str) -> PyResult<PyModule> {
     let m = PyModule::new(py, &name)?;
     m.add_class::<gitignorematcher>(py)?;
     m.add_class::<treematcher>(py)?;
+    m.add_class::<sparsematcher>(py)?;
     m.add(py, "normalizeglob", py_fn!(py, normalize_glob(path: &str)))?;
     m.add(py, "plaintoglob", py_fn!(py, plain_to_glob(path: &str)))?;
     m.add(
@@ -41,6 +46,11 @@ pub fn init_module(py: Python, package: &str) -> PyResult<PyModule> {
         "expandcurlybrackets",
         py_fn!(py, expand_curly_brackets(path: &str)),
     )?;
+    m.add(
+        py,
+        "sparseprofilemetadata",
+        py_fn!(py, sparse_profile_metadata(data: PyBytes, source: String)),
+    )?;
     Ok(m)
 }
 
@@ -91,6 +101,67 @@ impl ExtractInnerRef for treematcher {
     }
 }
 
+py_class!(pub class sparsematcher |py| {
+    data matcher: Arc<SparseMatcher>;
+
+    /// Build the matcher for a sparse config. `readprofile(path)` returns
+    /// the content of an included profile, or None if it does not exist.
+    def __new__(_cls, data: PyBytes, source: String, readprofile: PyObject) -> PyResult<Self> {
+        let root = Root::from_bytes(data.data(py), source).map_pyerr(py)?;
+        let matcher = root
+            .matcher(|path| {
+                let data = readprofile.call(py, (path,), None).into_anyhow_result()?;
+                if data.is_none(py) {
+                    return Ok(None);
+                }
+                let data = PyBytes::extract(py, &data).into_anyhow_result()?;
+                Ok(Some(data.data(py).to_vec()))
+            })
+            .map_pyerr(py)?;
+        Self::create_instance(py, Arc::new(matcher))
+    }
+
+    def matches(&self, path: &PyPath) -> PyResult<bool> {
+        let path = path.to_repo_path().map_pyerr(py)?;
+        self.matcher(py).matches_file(path).map_pyerr(py)
+    }
+
+    def match_recursive(&self, path: &PyPath) -> PyResult<Option<bool>> {
+        let path = path.to_repo_path().map_pyerr(py)?;
+        Ok(match self.matcher(py).matches_directory(path).map_pyerr(py)? {
+            DirectoryMatch::Everything => Some(true),
+            DirectoryMatch::Nothing => Some(false),
+            DirectoryMatch::ShouldTraverse => None,
+        })
+    }
+
+    def explain(&self, path: &PyPath) -> PyResult<Str> {
+        let path = path.to_repo_path().map_pyerr(py)?;
+        Ok(self.matcher(py).explain(path).map_pyerr(py)?.into())
+    }
+
+    def profiles(&self) -> PyResult<Vec<String>> {
+        Ok(self.matcher(py).profiles().to_vec())
+    }
+});
+
+impl ExtractInnerRef for sparsematcher {
+    type Inner = Arc<SparseMatcher>;
+
+    fn extract_inner_ref<'a>(&'a self, py: Python<'a>) -> &'a Self::Inner {
+        self.matcher(py)
+    }
+}
+
+fn sparse_profile_metadata(
+    py: Python,
+    data: PyBytes,
+    source: String,
+) -> PyResult<HashMap<String, String>> {
+    let profile = Profile::from_bytes(data.data(py), source).map_pyerr(py)?;
+    Ok(profile.metadata().clone())
+}
+
 fn normalize_glob(_py: Python, path: &str) -> PyResult<Str> {
     Ok(pathmatcher::normalize_glob(path).into())
 }
@@ -222,6 +293,9 @@ pub fn extract_matcher(py: Python, matcher: PyObject) -> PyResult<Arc<dyn Matche
     if let Ok(matcher) = treematcher::downcast_from(py, matcher.clone_ref(py)) {
         return Ok(matcher.extract_inner(py));
     }
+    if let Ok(matcher) = sparsematcher::downcast_from(py, matcher.clone_ref(py)) {
+        return Ok(matcher.extract_inner(py));
+    }
     let py_type = matcher.get_type(py);
     let type_name = py_type.name(py);
     if type_name.as_ref() == "treematcher" {
@@ -1413,6 +1413,14 @@ py_class!(pub class filescmstore |py| {
         let store = self.store(py);
         mutabledeltastore::create_instance(py, store.get_shared_mutable())
     }
+
+    /// Evict the least recently used blobs from the LFS cache, so it fits in `lfs.cachelimit`.
+    /// Return the number of evicted blobs, and their size in bytes.
+    def prunelfscache(&self) -> PyResult<(usize, u64)> {
+        let store = self.store(py);
+        let stats = py.allow_threads(|| store.prune_lfs_cache()).map_pyerr(py)?;
+        Ok((stats.evicted_blobs, stats.evicted_bytes))
+    }
 });
 
 impl ExtractInnerRef for filescmstore {
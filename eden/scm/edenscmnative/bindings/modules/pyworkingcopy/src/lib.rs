@@ -8,11 +8,14 @@
 #![allow(non_camel_case_types)]
 
 use std::cell::RefCell;
+use std::sync::Arc;
 
 use anyhow::Error;
 use cpython::*;
 use cpython_ext::error::ResultPyErrExt;
 use cpython_ext::PyPathBuf;
+use pathmatcher::Matcher;
+use pypathmatcher::extract_matcher;
 use pypathmatcher::UnsafePythonMatcher;
 use pytreestate::treestate;
 use workingcopy::filesystem::ChangeType;
@@ -21,6 +24,8 @@ use workingcopy::filesystem::PendingChanges;
 use workingcopy::filesystem::PhysicalFileSystem;
 use workingcopy::walker::WalkError;
 use workingcopy::walker::Walker;
+#[cfg(unix)]
+use workingcopy::watcher::SocketWatcher;
 
 pub fn init_module(py: Python, package: &str) -> PyResult<PyModule> {
     let name = [package, "workingcopy"].join(".");
@@ -34,12 +39,28 @@ pub fn init_module(py: Python, package: &str) -> PyResult<PyModule> {
 py_class!(class physicalfilesystem |py| {
     data filesystem: RefCell<PhysicalFileSystem>;
 
-    def __new__(_cls, root: PyPathBuf) -> PyResult<physicalfilesystem> {
-        physicalfilesystem::create_instance(py, RefCell::new(PhysicalFileSystem::new(root.to_path_buf()).map_pyerr(py)?))
+    def __new__(_cls, root: PyPathBuf, watchersocket: Option<PyPathBuf> = None, walkerthreads: usize = 0) -> PyResult<physicalfilesystem> {
+        let filesystem = PhysicalFileSystem::new(root.to_path_buf())
+            .map_pyerr(py)?
+            .with_walker_threads(walkerthreads);
+        // Watchers talk over unix sockets, elsewhere the working copy is always walked.
+        #[cfg(unix)]
+        let filesystem = match watchersocket {
+            Some(watchersocket) => {
+                let watcher = SocketWatcher::new(watchersocket.to_path_buf(), root.to_path_buf());
+                filesystem.with_watcher(Arc::new(watcher))
+            }
+            None => filesystem,
+        };
+        #[cfg(not(unix))]
+        let _ = watchersocket;
+        physicalfilesystem::create_instance(py, RefCell::new(filesystem))
     }
 
     def pendingchanges(&self, pytreestate: treestate, pymatcher: PyObject, include_directories: bool, last_write: u32) -> PyResult<pendingchanges> {
-        let matcher = UnsafePythonMatcher::new(pymatcher);
+        // The matcher might be called from walker threads, so Python
+        // matchers take the GIL themselves.
+        let matcher = extract_matcher(py, pymatcher)?;
         let fs = self.filesystem(py);
         let treestate = pytreestate.get_state(py);
         let last_write = last_write.into();
@@ -51,15 +72,17 @@ py_class!(class physicalfilesystem |py| {
 });
 
 py_class!(class pendingchanges |py| {
-    data inner: RefCell<PendingChanges<UnsafePythonMatcher>>;
+    data inner: RefCell<PendingChanges<Arc<dyn Matcher + Send + Sync>>>;
 
     def __iter__(&self) -> PyResult<Self> {
         Ok(self.clone_ref(py))
     }
 
     def __next__(&self) -> PyResult<Option<(PyPathBuf, bool)>> {
+        let mut inner = self.inner(py).borrow_mut();
+        let inner = &mut *inner;
         loop {
-            match self.inner(py).borrow_mut().next() {
+            match py.allow_threads(|| inner.next()) {
                 Some(Ok(change)) => {
                     if let PendingChangeResult::File(change_type) = change {
                         return Ok(Some(match change_type {
@@ -19,8 +19,11 @@ use thiserror::Error;
 use url::Url;
 use util::path::expand_path;
 
+pub mod token;
 pub mod x509;
 
+pub use token::TokenProvider;
+pub use token::TokenSource;
 pub use x509::check_certs;
 pub use x509::X509Error;
 
@@ -40,6 +43,9 @@ pub struct AuthGroup {
     pub key: Option<PathBuf>,
     pub cacerts: Option<PathBuf>,
     pub username: Option<String>,
+    pub token: Option<String>,
+    pub token_file: Option<PathBuf>,
+    pub credential_helper: Option<String>,
     pub schemes: Vec<String>,
     pub priority: i32,
     pub extras: HashMap<String, String>,
@@ -69,6 +75,19 @@ impl AuthGroup {
 
         let username = settings.remove("username").map(|s| s.to_string());
 
+        let token = settings
+            .remove("token")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+        let token_file = settings
+            .remove("tokenfile")
+            .filter(|s| !s.is_empty())
+            .map(expand_path);
+        let credential_helper = settings
+            .remove("credentialhelper")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+
         // If the URL prefix for this group has a scheme specified, use that
         // and ignore the contents of the "schemes" field for this group.
         let schemes = if let Some(i) = prefix.find("://") {
@@ -103,11 +122,27 @@ impl AuthGroup {
             key,
             cacerts,
             username,
+            token,
+            token_file,
+            credential_helper,
             schemes,
             priority,
             extras,
         })
     }
+
+    /// Where to get a bearer token for this group, if it uses one. A token
+    /// in the config takes precedence over a token file, which takes
+    /// precedence over a credential helper.
+    pub fn token_source(&self) -> Option<TokenSource> {
+        if let Some(token) = &self.token {
+            Some(TokenSource::Static(token.clone()))
+        } else if let Some(path) = &self.token_file {
+            Some(TokenSource::File(path.clone()))
+        } else {
+            self.credential_helper.clone().map(TokenSource::Helper)
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -296,6 +331,9 @@ mod test {
                 key: Some("/foo/key".into()),
                 cacerts: Some("/foo/cacerts".into()),
                 username: Some("user".into()),
+                token: None,
+                token_file: None,
+                credential_helper: None,
                 schemes: vec!["http".into(), "https".into()],
                 priority: 1,
                 extras: HashMap::new(),
@@ -310,6 +348,9 @@ mod test {
                 key: Some("/bar/key".into()),
                 cacerts: None,
                 username: None,
+                token: None,
+                token_file: None,
+                credential_helper: None,
                 schemes: vec!["https".into()],
                 priority: 0,
                 extras: HashMap::new(),
@@ -445,4 +486,43 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_token_source() -> Result<()> {
+        let mut config = ConfigSet::new();
+        let _errors = config.parse(
+            "[auth]\n\
+             a.prefix = a.com\n\
+             a.token = abc\n\
+             a.tokenfile = /a/token\n\
+             b.prefix = b.com\n\
+             b.tokenfile = /b/token\n\
+             b.credentialhelper = helper --flag\n\
+             c.prefix = c.com\n\
+             c.credentialhelper = helper --flag\n\
+             d.prefix = d.com\n\
+             ",
+            &Options::default(),
+        );
+        let auth = AuthSection::from_config(&config);
+        let source = |url: &str| -> Result<Option<TokenSource>> {
+            Ok(auth.best_match_for(&url.parse()?)?.unwrap().token_source())
+        };
+
+        assert_eq!(
+            source("https://a.com")?,
+            Some(TokenSource::Static("abc".into()))
+        );
+        assert_eq!(
+            source("https://b.com")?,
+            Some(TokenSource::File("/b/token".into()))
+        );
+        assert_eq!(
+            source("https://c.com")?,
+            Some(TokenSource::Helper("helper --flag".into()))
+        );
+        assert_eq!(source("https://d.com")?, None);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,323 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Bearer tokens for servers behind an OAuth proxy.
+//!
+//! A token may be given directly in the config, read from a file, or
+//! obtained from a credential helper. Credential helpers speak the
+//! git-credential protocol: the helper command is run through the shell
+//! with `get` (or `erase`) appended, receives `key=value` lines describing
+//! the URL on stdin, and prints `key=value` lines. The token is taken from
+//! the `password` field.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use parking_lot::Mutex;
+use url::Url;
+
+/// Where to get a bearer token from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TokenSource {
+    /// A token given in the config.
+    Static(String),
+    /// A file containing the token. It is read again when the token is
+    /// rejected, in case it was updated.
+    File(PathBuf),
+    /// A git-credential style helper command.
+    Helper(String),
+}
+
+struct CachedToken {
+    token: String,
+    username: Option<String>,
+    expiry: Option<SystemTime>,
+}
+
+/// Provides a bearer token for requests to a URL, caching it until it
+/// expires or is rejected by the server.
+pub struct TokenProvider {
+    source: TokenSource,
+    url: Url,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl fmt::Debug for TokenProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the token.
+        let source = match &self.source {
+            TokenSource::Static(_) => "static".to_string(),
+            TokenSource::File(path) => format!("file {}", path.display()),
+            TokenSource::Helper(command) => format!("helper {}", command),
+        };
+        f.debug_struct("TokenProvider")
+            .field("source", &source)
+            .field("url", &self.url.as_str())
+            .finish()
+    }
+}
+
+impl TokenProvider {
+    pub fn new(source: TokenSource, url: Url) -> Self {
+        Self {
+            source,
+            url,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// The current token, fetching a new one if there is no unexpired
+    /// token cached.
+    pub fn token(&self) -> Result<String> {
+        let mut cached = self.cached.lock();
+        if let Some(token) = cached.as_ref() {
+            let expired = match token.expiry {
+                Some(expiry) => expiry <= SystemTime::now(),
+                None => false,
+            };
+            if !expired {
+                return Ok(token.token.clone());
+            }
+        }
+        let token = self.fetch()?;
+        let value = token.token.clone();
+        *cached = Some(token);
+        Ok(value)
+    }
+
+    /// The server rejected `rejected` (e.g. with HTTP 401). Forget it, and
+    /// get a new token if possible. Returns whether the new token differs,
+    /// which is when retrying the request may help.
+    pub fn refresh(&self, rejected: &str) -> Result<bool> {
+        let mut cached = self.cached.lock();
+        if let Some(token) = cached.as_ref() {
+            if token.token != rejected {
+                // Already refreshed by someone else.
+                return Ok(true);
+            }
+        }
+        let old = cached.take();
+
+        if let (TokenSource::Helper(command), Some(old)) = (&self.source, &old) {
+            let mut fields = self.url_fields();
+            if let Some(username) = &old.username {
+                fields.push(("username", username.clone()));
+            }
+            fields.push(("password", old.token.clone()));
+            if let Err(e) = run_helper(command, "erase", &fields) {
+                tracing::warn!("credential helper failed to erase rejected token: {}", e);
+            }
+        }
+
+        let token = self.fetch()?;
+        let changed = token.token != rejected;
+        *cached = Some(token);
+        Ok(changed)
+    }
+
+    fn fetch(&self) -> Result<CachedToken> {
+        let token = match &self.source {
+            TokenSource::Static(token) => CachedToken {
+                token: token.clone(),
+                username: None,
+                expiry: None,
+            },
+            TokenSource::File(path) => {
+                let token = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read token file {}", path.display()))?;
+                CachedToken {
+                    token: token.trim().to_string(),
+                    username: None,
+                    expiry: None,
+                }
+            }
+            TokenSource::Helper(command) => {
+                let mut output = run_helper(command, "get", &self.url_fields())?;
+                let token = output
+                    .remove("password")
+                    .ok_or_else(|| anyhow!("credential helper {:?} returned no token", command))?;
+                let expiry = output
+                    .get("password_expiry_utc")
+                    .and_then(|secs| secs.parse::<u64>().ok())
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                CachedToken {
+                    token,
+                    username: output.remove("username"),
+                    expiry,
+                }
+            }
+        };
+        if token.token.is_empty() {
+            bail!("empty bearer token for {}", self.url);
+        }
+        Ok(token)
+    }
+
+    /// Describe the URL to a credential helper.
+    fn url_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![("protocol", self.url.scheme().to_string())];
+        if let Some(host) = self.url.host_str() {
+            let host = match self.url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            fields.push(("host", host));
+        }
+        let path = self.url.path().trim_start_matches('/');
+        if !path.is_empty() {
+            fields.push(("path", path.to_string()));
+        }
+        if !self.url.username().is_empty() {
+            fields.push(("username", self.url.username().to_string()));
+        }
+        fields
+    }
+}
+
+/// Run `command action`, passing `fields` on stdin, and parse its output.
+fn run_helper(
+    command: &str,
+    action: &str,
+    fields: &[(&str, String)],
+) -> Result<HashMap<String, String>> {
+    let command_line = format!("{} {}", command, action);
+    let mut child = shell_command(&command_line)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed to run credential helper {:?}", command))?;
+
+    let mut input = String::new();
+    for (key, value) in fields {
+        if value.contains('\n') {
+            bail!("invalid credential field {}", key);
+        }
+        input.push_str(&format!("{}={}\n", key, value));
+    }
+    input.push('\n');
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "credential helper {:?} failed with {}",
+            command_line,
+            output.status
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect())
+}
+
+#[cfg(unix)]
+fn shell_command(command_line: &str) -> Command {
+    let mut command = Command::new("/bin/sh");
+    command.arg("-c").arg(command_line);
+    command
+}
+
+#[cfg(windows)]
+fn shell_command(command_line: &str) -> Command {
+    let mut command = Command::new("cmd.exe");
+    command.arg("/c").arg(command_line);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_and_file_tokens() -> Result<()> {
+        let url: Url = "https://example.com/edenapi".parse()?;
+
+        let provider = TokenProvider::new(TokenSource::Static("abc".into()), url.clone());
+        assert_eq!(provider.token()?, "abc");
+        assert!(!provider.refresh("abc")?);
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("token");
+        fs::write(&path, "first\n")?;
+        let provider = TokenProvider::new(TokenSource::File(path.clone()), url);
+        assert_eq!(provider.token()?, "first");
+
+        // The cached token is used until it is rejected.
+        fs::write(&path, "second\n")?;
+        assert_eq!(provider.token()?, "first");
+        assert!(provider.refresh("first")?);
+        assert_eq!(provider.token()?, "second");
+
+        fs::remove_file(&path)?;
+        assert!(provider.refresh("second").is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_credential_helper() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("log");
+        let count = dir.path().join("count");
+        let script = dir.path().join("helper.sh");
+        fs::write(
+            &script,
+            format!(
+                r#"
+cat >> {log}
+echo "--- $1" >> {log}
+if [ "$1" = get ]; then
+    echo x >> {count}
+    echo username=bot
+    echo password=token$(wc -l < {count} | tr -d ' ')
+fi
+"#,
+                log = log.display(),
+                count = count.display()
+            ),
+        )?;
+
+        let url: Url = "https://example.com:8443/edenapi/".parse()?;
+        let command = format!("sh {}", script.display());
+        let provider = TokenProvider::new(TokenSource::Helper(command), url);
+
+        assert_eq!(provider.token()?, "token1");
+        assert_eq!(provider.token()?, "token1");
+        assert!(provider.refresh("token1")?);
+        assert_eq!(provider.token()?, "token2");
+
+        assert_eq!(
+            fs::read_to_string(&log)?,
+            "protocol=https\nhost=example.com:8443\npath=edenapi/\n\n--- get\n\
+             protocol=https\nhost=example.com:8443\npath=edenapi/\nusername=bot\npassword=token1\n\n--- erase\n\
+             protocol=https\nhost=example.com:8443\npath=edenapi/\n\n--- get\n"
+        );
+        Ok(())
+    }
+}
@@ -148,7 +148,7 @@ impl BlackboxOptions {
                     bytes.write_all(data).expect("Vec::write should not fail");
                     result.push(IndexOutput::Owned(bytes.into_boxed_slice()));
                 };
-                if let Some(entry) = Entry::from_slice(bytes) {
+                if let Some(entry) = Entry::from_slice(&bytes) {
                     match entry.data {
                         Event::Start {
                             timestamp_ms, pid, ..
@@ -357,7 +357,7 @@ impl Blackbox {
                                 for value in values {
                                     if let Ok(bytes) = value {
                                         if let Some(session_id) =
-                                            Entry::session_id_from_slice(bytes)
+                                            Entry::session_id_from_slice(&bytes)
                                         {
                                             candidate_session_ids.push(session_id)
                                         }
@@ -375,7 +375,7 @@ impl Blackbox {
                     {
                         for bytes in iter {
                             if let Ok(bytes) = bytes {
-                                if let Some(entry) = Entry::from_slice(bytes) {
+                                if let Some(entry) = Entry::from_slice(&bytes) {
                                     if entry.match_pattern(pattern) {
                                         result.insert(session_id);
                                         continue 'next_session_id;
@@ -390,7 +390,7 @@ impl Blackbox {
                 // Cannot use index. Go through every entry.
                 for next in self.log.iter() {
                     if let Ok(bytes) = next {
-                        let session_id = match Entry::session_id_from_slice(bytes) {
+                        let session_id = match Entry::session_id_from_slice(&bytes) {
                             Some(id) => id,
                             None => continue,
                         };
@@ -399,7 +399,7 @@ impl Blackbox {
                             // Skip deserializing it.
                             continue;
                         }
-                        if let Some(entry) = Entry::from_slice(bytes) {
+                        if let Some(entry) = Entry::from_slice(&bytes) {
                             if entry.match_pattern(pattern) {
                                 result.insert(session_id);
                             }
@@ -428,7 +428,7 @@ impl Blackbox {
             {
                 for bytes in iter {
                     if let Ok(bytes) = bytes {
-                        if let Some(entry) = Entry::from_slice(bytes) {
+                        if let Some(entry) = Entry::from_slice(&bytes) {
                             result.push(entry)
                         }
                     }
@@ -18,6 +18,7 @@ use manifest::DiffType;
 use manifest::FileMetadata;
 use manifest::FileType;
 use manifest::Manifest;
+use minibytes::Bytes;
 use pathmatcher::Matcher;
 use pathmatcher::XorMatcher;
 use types::RepoPathBuf;
@@ -30,11 +31,13 @@ pub struct ActionMap {
 
 /// Basic update action.
 /// Diff between regular(no conflict checkin) commit generates list of such actions.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     Update(UpdateAction),
     Remove,
     UpdateExec(bool),
+    /// Write content produced by merging both sides of a merge.
+    Merged(MergedFile),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -43,6 +46,25 @@ pub struct UpdateAction {
     pub to: FileMetadata,
 }
 
+/// Content of a file that was changed on both sides of a merge, and could be merged cleanly.
+/// Unlike `UpdateAction`, this content isn't in the store yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedFile {
+    pub content: Bytes,
+    pub file_type: FileType,
+    /// Version of the file in the merge base, None if both sides created the file.
+    pub ancestor: Option<FileMetadata>,
+}
+
+/// Action in the format returned by `calculateupdates` in merge.py.
+#[derive(Debug, PartialEq)]
+pub enum PyMergeAction {
+    /// ("g" | "r" | "e", (flags, backup), message)
+    Simple(&'static str, (&'static str, bool), &'static str),
+    /// ("gm", (flags, content), message)
+    Merged(&'static str, (&'static str, Bytes), &'static str),
+}
+
 impl ActionMap {
     // This is similar to CheckoutPlan::new
     // Eventually CheckoutPlan::new will migrate to take (Conflict)ActionMap instead of a Diff and there won't be code duplication
@@ -113,7 +135,7 @@ impl ActionMap {
                         va.insert(Action::Update(UpdateAction::new(None, file.meta)));
                     }
                     Entry::Occupied(mut oc) => match oc.get() {
-                        Action::Remove | Action::Update(_) => {}
+                        Action::Remove | Action::Update(_) | Action::Merged(_) => {}
                         Action::UpdateExec(_) => {
                             oc.insert(Action::Update(UpdateAction::new(None, file.meta)));
                         }
@@ -172,6 +194,7 @@ impl fmt::Display for ActionMap {
                 Action::Update(up) => write!(f, "up {}=>{}\n", path, up.to.hgid)?,
                 Action::UpdateExec(x) => write!(f, "{} {}\n", if *x { "+x" } else { "-x" }, path)?,
                 Action::Remove => write!(f, "rm {}\n", path)?,
+                Action::Merged(_) => write!(f, "merged {}\n", path)?,
             }
         }
         Ok(())
@@ -179,15 +202,29 @@ impl fmt::Display for ActionMap {
 }
 
 impl Action {
-    pub fn pymerge_action(&self) -> (&'static str, (&'static str, bool), &'static str) {
+    /// Convert to a merge.py action.
+    pub fn pymerge_action(&self) -> PyMergeAction {
         match self {
-            Action::Update(up) => ("g", (pyflags(&up.to.file_type), false), "created/changed"),
-            Action::Remove => ("r", ("", false), "deleted"),
-            Action::UpdateExec(x) => (
+            Action::Update(up) => {
+                PyMergeAction::Simple("g", (pyflags(&up.to.file_type), false), "created/changed")
+            }
+            Action::Remove => PyMergeAction::Simple("r", ("", false), "deleted"),
+            Action::UpdateExec(x) => PyMergeAction::Simple(
                 "e",
                 (if *x { "x" } else { "" }, false),
                 "update permissions",
             ),
+            Action::Merged(merged) => {
+                let message = match merged.ancestor {
+                    Some(_) => "versions differ",
+                    None => "both created",
+                };
+                PyMergeAction::Merged(
+                    "gm",
+                    (pyflags(&merged.file_type), merged.content.clone()),
+                    message,
+                )
+            }
         }
     }
 }
@@ -271,6 +308,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pymerge_action() {
+        let update = Action::Update(UpdateAction::new(None, FileMetadata::executable(hgid(1))));
+        assert_eq!(
+            update.pymerge_action(),
+            PyMergeAction::Simple("g", ("x", false), "created/changed")
+        );
+        assert_eq!(
+            Action::Remove.pymerge_action(),
+            PyMergeAction::Simple("r", ("", false), "deleted")
+        );
+
+        let mut merged = MergedFile {
+            content: Bytes::from("a\n"),
+            file_type: FileType::Regular,
+            ancestor: Some(FileMetadata::regular(hgid(2))),
+        };
+        assert_eq!(
+            Action::Merged(merged.clone()).pymerge_action(),
+            PyMergeAction::Merged("gm", ("", Bytes::from("a\n")), "versions differ")
+        );
+        merged.ancestor = None;
+        merged.file_type = FileType::Executable;
+        assert_eq!(
+            Action::Merged(merged).pymerge_action(),
+            PyMergeAction::Merged("gm", ("x", Bytes::from("a\n")), "both created")
+        );
+    }
+
     fn rp(p: &str) -> RepoPathBuf {
         RepoPathBuf::from_string(p.to_string()).unwrap()
     }
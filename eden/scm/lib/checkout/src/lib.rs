@@ -53,22 +53,28 @@ use vfs::AsyncVfsWriter;
 use vfs::UpdateFlag;
 use vfs::VFS;
 
-#[allow(dead_code)]
 mod actions;
-#[allow(dead_code)]
 mod conflict;
-#[allow(dead_code)]
 mod merge;
+mod sparse;
+mod text_merge;
 
 pub use actions::Action;
 pub use actions::ActionMap;
+pub use actions::MergedFile;
+pub use actions::PyMergeAction;
 use configmodel::Config;
 use configmodel::ConfigExt;
 pub use conflict::Conflict;
 pub use merge::Merge;
 pub use merge::MergeResult;
+pub use sparse::sparse_matcher;
 use status::FileStatus;
 use status::Status;
+pub use text_merge::merge_text;
+pub use text_merge::ConflictLabels;
+pub use text_merge::ConflictStyle;
+pub use text_merge::TextMergeResult;
 use tokio::runtime::Handle;
 
 const PREFETCH_CHUNK_SIZE: usize = 1000;
@@ -83,6 +89,8 @@ pub struct CheckoutPlan {
     update_content: Vec<UpdateContentAction>,
     /// Files that only need X flag updated.
     update_meta: Vec<UpdateMetaAction>,
+    /// Files whose new content is not in the store, such as merged files.
+    write_content: Vec<WriteContentAction>,
     progress: Option<Mutex<CheckoutProgress>>,
     checkout: Checkout,
 }
@@ -107,6 +115,17 @@ struct UpdateContentAction {
     new_file: bool,
 }
 
+/// Write given content to the file
+#[derive(Debug)]
+struct WriteContentAction {
+    /// Path to file.
+    path: RepoPathBuf,
+    /// New content.
+    content: Bytes,
+    /// New file type.
+    file_type: FileType,
+}
+
 /// Only update metadata on the file, do not update content
 #[derive(Debug)]
 struct UpdateMetaAction {
@@ -159,6 +178,7 @@ impl CheckoutPlan {
         let mut remove = vec![];
         let mut update_content = vec![];
         let mut update_meta = vec![];
+        let mut write_content = vec![];
         for (path, action) in map.into_iter() {
             match action {
                 Action::Remove => remove.push(path),
@@ -168,12 +188,18 @@ impl CheckoutPlan {
                 Action::Update(up) => {
                     update_content.push(UpdateContentAction::new(path, up.to, up.from.is_none()))
                 }
+                Action::Merged(merged) => write_content.push(WriteContentAction {
+                    path,
+                    content: merged.content,
+                    file_type: merged.file_type,
+                }),
             }
         }
         Self {
             remove,
             update_content,
             update_meta,
+            write_content,
             progress: None,
             checkout,
         }
@@ -227,7 +253,10 @@ impl CheckoutPlan {
             "Skipping checking out {} files since they're already written",
             self.update_content.len() - filtered_update_content.len()
         );
-        let total = filtered_update_content.len() + self.remove.len() + self.update_meta.len();
+        let total = filtered_update_content.len()
+            + self.remove.len()
+            + self.update_meta.len()
+            + self.write_content.len();
         let bar = &ProgressBar::new("Updating", total as u64, "files");
         Registry::main().register_progress_bar(bar);
         let async_vfs = &AsyncVfsWriter::spawn_new(vfs.clone(), 16);
@@ -274,10 +303,15 @@ impl CheckoutPlan {
         });
         let update_meta = update_meta.buffer_unordered(self.checkout.concurrency);
 
+        let write_content = stream::iter(self.write_content.chunks(VFS_BATCH_SIZE))
+            .map(|actions| Self::write_content(async_vfs, stats_ref, actions, bar));
+        let write_content = write_content.buffer_unordered(self.checkout.concurrency);
+
         let update_content = Self::process_work_stream(update_content);
         let update_meta = Self::process_work_stream(update_meta);
+        let write_content = Self::process_work_stream(write_content);
 
-        try_join!(update_content, update_meta)?;
+        try_join!(update_content, update_meta, write_content)?;
 
         Ok(stats)
     }
@@ -589,6 +623,27 @@ impl CheckoutPlan {
         Ok(())
     }
 
+    async fn write_content(
+        async_vfs: &AsyncVfsWriter,
+        stats: &CheckoutStats,
+        actions: &[WriteContentAction],
+        bar: &Arc<ProgressBar>,
+    ) -> Result<()> {
+        let count = actions.len();
+        let actions = actions.iter().map(|action| {
+            (
+                action.path.clone(),
+                action.content.clone(),
+                type_to_flag(&action.file_type),
+            )
+        });
+        let w = async_vfs.write_batch(actions).await?;
+        stats.updated.fetch_add(count, Ordering::Relaxed);
+        stats.written_bytes.fetch_add(w, Ordering::Relaxed);
+        bar.increase_position(count as u64);
+        Ok(())
+    }
+
     async fn remove_files(
         async_vfs: &AsyncVfsWriter,
         stats: &CheckoutStats,
@@ -623,7 +678,10 @@ impl CheckoutPlan {
     }
 
     pub fn updated_content_files(&self) -> impl Iterator<Item = &RepoPathBuf> {
-        self.update_content.iter().map(|u| &u.path)
+        self.update_content
+            .iter()
+            .map(|u| &u.path)
+            .chain(self.write_content.iter().map(|w| &w.path))
     }
 
     pub fn updated_meta_files(&self) -> impl Iterator<Item = &RepoPathBuf> {
@@ -641,12 +699,13 @@ impl CheckoutPlan {
             .map(|u| &u.path)
             .chain(self.remove.iter())
             .chain(self.update_meta.iter().map(|u| &u.path))
+            .chain(self.write_content.iter().map(|w| &w.path))
     }
 
     /// Returns (updated, removed)
     pub fn stats(&self) -> (usize, usize) {
         (
-            self.update_meta.len() + self.update_content.len(),
+            self.update_meta.len() + self.update_content.len() + self.write_content.len(),
             self.remove.len(),
         )
     }
@@ -661,6 +720,7 @@ impl CheckoutPlan {
             remove: vec![],
             update_content: vec![],
             update_meta: vec![],
+            write_content: vec![],
             progress: None,
             checkout: Checkout::default_config(vfs),
         }
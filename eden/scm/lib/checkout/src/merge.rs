@@ -10,20 +10,35 @@ use std::fmt;
 
 use anyhow::bail;
 use anyhow::Result;
+use configmodel::Config;
+use manifest::FileMetadata;
 use manifest::FileType;
 use manifest::FsNodeMetadata;
 use manifest::Manifest;
+use minibytes::Bytes;
 use pathmatcher::AlwaysMatcher;
+use types::RepoPath;
 use types::RepoPathBuf;
 
 use crate::actions::Action;
 use crate::actions::ActionMap;
+use crate::actions::MergedFile;
 use crate::actions::UpdateAction;
 use crate::conflict::Conflict;
 use crate::conflict::ConflictState;
+use crate::text_merge::merge_text;
+use crate::text_merge::ConflictLabels;
+use crate::text_merge::ConflictStyle;
+use crate::text_merge::TextMergeResult;
 
 /// Merge operation settings
-pub struct Merge {}
+#[derive(Default)]
+pub struct Merge {
+    /// Conflict marker style used when merging file contents.
+    pub conflict_style: ConflictStyle,
+    /// Labels for conflict markers.
+    pub labels: ConflictLabels,
+}
 
 /// Contains result of the mere, separated by update actions and conflicts
 pub struct MergeResult<M: Manifest> {
@@ -38,6 +53,19 @@ pub enum ActionOrConflict {
 }
 
 impl Merge {
+    /// Settings matching the merge tool configured in `ui.merge`. The
+    /// `:merge3` tool shows the base version in conflicts.
+    pub fn from_config(config: &dyn Config) -> Self {
+        let conflict_style = match config.get("ui", "merge").as_deref() {
+            Some(":merge3") | Some("internal:merge3") => ConflictStyle::Diff3,
+            _ => ConflictStyle::Merge,
+        };
+        Self {
+            conflict_style,
+            ..Default::default()
+        }
+    }
+
     // dest          result
     // |             |
     // |  src   =>   dest
@@ -64,9 +92,9 @@ impl Merge {
         for file in union {
             let ac = match (src_actions.get(*file), dest_actions.get(*file)) {
                 (None, Some(_a)) => continue, // Already in destination
-                (Some(a), None) => ActionOrConflict::Action(*a),
+                (Some(a), None) => ActionOrConflict::Action(a.clone()),
                 (Some(s), Some(d)) => {
-                    if let Some(ac) = self.resolve_conflict(*s, *d) {
+                    if let Some(ac) = self.resolve_conflict(s.clone(), d.clone())? {
                         ac
                     } else {
                         continue;
@@ -80,9 +108,9 @@ impl Merge {
         Ok(result)
     }
 
-    fn resolve_conflict(&self, src: Action, dest: Action) -> Option<ActionOrConflict> {
-        Some(match (src, dest) {
-            (Action::Remove, Action::Remove) => return None,
+    fn resolve_conflict(&self, src: Action, dest: Action) -> Result<Option<ActionOrConflict>> {
+        Ok(Some(match (src, dest) {
+            (Action::Remove, Action::Remove) => return Ok(None),
             (Action::Update(s), Action::Update(d)) => both_changed(s, d),
             (Action::UpdateExec(s), Action::UpdateExec(d)) => {
                 assert!(s == d); // Can not be otherwise
@@ -96,7 +124,7 @@ impl Merge {
 
             // mercurial handles this differently - it actually raise it as a conflict since file has "changed" and removed
             // but more logical is probably to just remove the file and ignore flag update on the other side
-            (Action::UpdateExec(_), Action::Remove) => return None,
+            (Action::UpdateExec(_), Action::Remove) => return Ok(None),
 
             // exists only in src / remote / m2
             (Action::Remove, Action::Update(up)) => {
@@ -108,10 +136,96 @@ impl Merge {
             // flag conflicts - todo - implement
             (Action::Update(_), Action::UpdateExec(_)) => unimplemented!(),
             (Action::UpdateExec(_), Action::Update(_)) => unimplemented!(),
-        })
+
+            (Action::Merged(_), _) | (_, Action::Merged(_)) => {
+                bail!("Merged content can not be merged again")
+            }
+        }))
+    }
+
+    /// Merge the contents of text files that were changed on both sides.
+    /// Files that merge cleanly are turned from conflicts into `Action::Merged`
+    /// actions, the rest are left as conflicts.
+    ///
+    /// `read` returns the content of a version of a file.
+    pub fn merge_file_contents<M, F>(&self, result: &mut MergeResult<M>, mut read: F) -> Result<()>
+    where
+        M: Manifest,
+        F: FnMut(&RepoPath, &FileMetadata) -> Result<Bytes>,
+    {
+        let paths: Vec<_> = result.conflicts.keys().cloned().collect();
+        for path in paths {
+            let (ancestor, dest, src) = match result.conflicts.get(&path) {
+                Some(Conflict::BothChanged {
+                    ancestor,
+                    dest,
+                    src,
+                }) => (*ancestor, *dest, *src),
+                _ => continue,
+            };
+            let file_type = match merged_file_type(ancestor.as_ref(), &dest, &src) {
+                Some(file_type) => file_type,
+                None => continue,
+            };
+
+            let base_content = match &ancestor {
+                Some(ancestor) => read(&path, ancestor)?,
+                // Both sides created the file.
+                None => Bytes::new(),
+            };
+            let dest_content = read(&path, &dest)?;
+            let src_content = read(&path, &src)?;
+            if is_binary(&base_content) || is_binary(&dest_content) || is_binary(&src_content) {
+                continue;
+            }
+
+            let merged = merge_text(
+                &base_content,
+                &dest_content,
+                &src_content,
+                self.conflict_style,
+                &self.labels,
+            );
+            if let TextMergeResult::Clean(content) = merged {
+                result.conflicts.remove(&path);
+                result.actions.insert(
+                    path,
+                    Action::Merged(MergedFile {
+                        content: Bytes::from(content),
+                        file_type,
+                        ancestor,
+                    }),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// File type of the merged file, or None if the file types can't be merged.
+fn merged_file_type(
+    ancestor: Option<&FileMetadata>,
+    dest: &FileMetadata,
+    src: &FileMetadata,
+) -> Option<FileType> {
+    if dest.file_type == FileType::Symlink || src.file_type == FileType::Symlink {
+        return None;
+    }
+    if dest.file_type == src.file_type {
+        return Some(dest.file_type);
+    }
+    // Only one side changed the exec flag.
+    match ancestor {
+        Some(ancestor) if ancestor.file_type == dest.file_type => Some(src.file_type),
+        Some(ancestor) if ancestor.file_type == src.file_type => Some(dest.file_type),
+        _ => None,
     }
 }
 
+fn is_binary(content: &[u8]) -> bool {
+    content.contains(&0)
+}
+
 fn both_changed(src: UpdateAction, dest: UpdateAction) -> ActionOrConflict {
     assert_eq!(dest.from, src.from);
     ActionOrConflict::Conflict(Conflict::BothChanged {
@@ -164,6 +278,10 @@ impl<M: Manifest> MergeResult<M> {
                     m.insert(file, up.to)?;
                 }
                 Action::Remove => unreachable!(),
+                Action::Merged(_) => bail!(
+                    "Failed to apply to manifest: {} has merged content that is not in the store",
+                    file
+                ),
                 Action::UpdateExec(up) => {
                     let meta = m.get(&file)?;
                     let mut meta = match meta {
@@ -216,3 +334,94 @@ impl<T: Manifest> fmt::Display for MergeResult<T> {
         write!(f, "{}\n{}", self.actions, self.conflicts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use manifest_tree::testutil::make_tree_manifest_from_meta;
+    use manifest_tree::testutil::TestStore;
+    use types::HgId;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_from_config() {
+        let mut config: BTreeMap<String, String> = BTreeMap::new();
+        assert_eq!(
+            Merge::from_config(&config).conflict_style,
+            ConflictStyle::Merge
+        );
+        config.insert("ui.merge".to_string(), ":merge3".to_string());
+        assert_eq!(
+            Merge::from_config(&config).conflict_style,
+            ConflictStyle::Diff3
+        );
+        config.insert("ui.merge".to_string(), "internal:merge".to_string());
+        assert_eq!(
+            Merge::from_config(&config).conflict_style,
+            ConflictStyle::Merge
+        );
+    }
+
+    #[test]
+    fn test_merge_file_contents() -> Result<()> {
+        let store = Arc::new(TestStore::new());
+        let mut result = MergeResult::new_empty(make_tree_manifest_from_meta(store, vec![]));
+        let contents: HashMap<HgId, &'static str> = vec![
+            (hgid(1), "a\nb\nc\n"),
+            (hgid(2), "A\nb\nc\n"),
+            (hgid(3), "a\nb\nC\n"),
+            (hgid(4), "a\nB1\nc\n"),
+            (hgid(5), "a\nB2\nc\n"),
+        ]
+        .into_iter()
+        .collect();
+
+        result.insert_new(
+            rp("clean"),
+            ActionOrConflict::Conflict(Conflict::BothChanged {
+                ancestor: Some(FileMetadata::regular(hgid(1))),
+                dest: FileMetadata::regular(hgid(2)),
+                src: FileMetadata::executable(hgid(3)),
+            }),
+        );
+        result.insert_new(
+            rp("conflict"),
+            ActionOrConflict::Conflict(Conflict::BothChanged {
+                ancestor: Some(FileMetadata::regular(hgid(1))),
+                dest: FileMetadata::regular(hgid(4)),
+                src: FileMetadata::regular(hgid(5)),
+            }),
+        );
+
+        Merge::default().merge_file_contents(&mut result, |_path, meta| {
+            Ok(Bytes::from(contents[&meta.hgid]))
+        })?;
+
+        assert_eq!(
+            result.actions().get(&rp("clean")),
+            Some(&Action::Merged(MergedFile {
+                content: Bytes::from("A\nb\nC\n"),
+                file_type: FileType::Executable,
+                ancestor: Some(FileMetadata::regular(hgid(1))),
+            }))
+        );
+        assert!(result.conflicts().contains_key(&rp("conflict")));
+        assert!(!result.conflicts().contains_key(&rp("clean")));
+
+        Ok(())
+    }
+
+    fn rp(p: &str) -> RepoPathBuf {
+        RepoPathBuf::from_string(p.to_string()).unwrap()
+    }
+
+    fn hgid(p: u8) -> HgId {
+        let mut r = HgId::default().into_byte_array();
+        r[0] = p;
+        HgId::from_byte_array(r)
+    }
+}
@@ -0,0 +1,111 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Sparse profiles of the commit being checked out.
+
+use anyhow::format_err;
+use anyhow::Result;
+use manifest::Manifest;
+use pathmatcher::sparse::Root;
+use pathmatcher::sparse::SparseMatcher;
+use revisionstore::scmstore::FileAttributes;
+use revisionstore::scmstore::FileStore;
+use types::Key;
+use types::RepoPath;
+
+/// Build the sparse matcher for a working copy at the commit of `manifest`.
+/// The profiles `root` includes, and the profiles they include, are read
+/// from that commit, with their content fetched from `store`.
+pub fn sparse_matcher(
+    root: &Root,
+    manifest: &impl Manifest,
+    store: &FileStore,
+) -> Result<SparseMatcher> {
+    sparse_matcher_with(root, manifest, |key| {
+        let path = key.path.clone();
+        let mut file = store
+            .fetch(std::iter::once(key), FileAttributes::CONTENT)
+            .single()?
+            .ok_or_else(|| format_err!("sparse profile {} is missing from the store", path))?;
+        Ok(file.file_content()?.to_vec())
+    })
+}
+
+/// Same as `sparse_matcher`, but reads file content with `read_file`.
+fn sparse_matcher_with(
+    root: &Root,
+    manifest: &impl Manifest,
+    mut read_file: impl FnMut(Key) -> Result<Vec<u8>>,
+) -> Result<SparseMatcher> {
+    root.matcher(|name| {
+        let path = RepoPath::from_str(name)?;
+        match manifest.get_file(path)? {
+            Some(meta) => Ok(Some(read_file(Key::new(path.to_owned(), meta.hgid))?)),
+            None => Ok(None),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use manifest::FileMetadata;
+    use manifest_tree::testutil::make_tree_manifest_from_meta;
+    use manifest_tree::testutil::TestStore;
+    use pathmatcher::Matcher;
+    use types::HgId;
+    use types::RepoPathBuf;
+
+    use super::*;
+
+    fn hgid(p: u8) -> HgId {
+        let mut r = HgId::default().into_byte_array();
+        r[0] = p;
+        HgId::from_byte_array(r)
+    }
+
+    #[test]
+    fn test_nested_profiles_from_manifest() -> Result<()> {
+        let files = [
+            (
+                "tools/app",
+                hgid(1),
+                "%include tools/base\n[include]\napp\n",
+            ),
+            (
+                "tools/base",
+                hgid(2),
+                "%include tools/missing\n[include]\nlib\n",
+            ),
+        ];
+        let manifest = make_tree_manifest_from_meta(
+            Arc::new(TestStore::new()),
+            files.iter().map(|(path, id, _)| {
+                (
+                    RepoPathBuf::from_string(path.to_string()).unwrap(),
+                    FileMetadata::regular(*id),
+                )
+            }),
+        );
+        let contents: HashMap<HgId, &str> =
+            files.iter().map(|(_, id, data)| (*id, *data)).collect();
+
+        let root = Root::from_bytes("%include tools/app\n%include tools\n", ".hg/sparse")?;
+        let matcher = sparse_matcher_with(&root, &manifest, |key| {
+            Ok(contents[&key.hgid].as_bytes().to_vec())
+        })?;
+
+        // Profiles that are not files in the manifest are skipped.
+        assert_eq!(matcher.profiles(), &["tools/app", "tools/base"]);
+        assert!(matcher.matches_file(RepoPath::from_str("app/main.rs")?)?);
+        assert!(matcher.matches_file(RepoPath::from_str("lib/lib.rs")?)?);
+        assert!(!matcher.matches_file(RepoPath::from_str("other/a.rs")?)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Line based three-way merge of file contents.
+
+use std::cmp::max;
+use std::cmp::min;
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Result;
+use xdiff::diff_hunks;
+
+const MARKER_LEN: usize = 7;
+
+/// How conflicting regions are presented in the merged content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStyle {
+    /// Only show the dest and src versions of conflicting lines.
+    Merge,
+    /// Also show the base version of conflicting lines.
+    Diff3,
+    /// Like diff3, but lines that both sides agree on at the start and end of
+    /// a conflict are moved out of the conflict.
+    ZDiff3,
+}
+
+impl Default for ConflictStyle {
+    fn default() -> Self {
+        ConflictStyle::Merge
+    }
+}
+
+impl FromStr for ConflictStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "merge" => ConflictStyle::Merge,
+            "diff3" => ConflictStyle::Diff3,
+            "zdiff3" => ConflictStyle::ZDiff3,
+            _ => bail!("unknown conflict style '{}'", s),
+        })
+    }
+}
+
+/// Labels written after the conflict markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictLabels {
+    pub dest: String,
+    pub base: String,
+    pub src: String,
+}
+
+impl Default for ConflictLabels {
+    fn default() -> Self {
+        Self {
+            dest: "local".to_string(),
+            base: "base".to_string(),
+            src: "other".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextMergeResult {
+    /// All changes were merged without conflicts.
+    Clean(Vec<u8>),
+    /// Some changes conflicted. The content contains conflict markers.
+    Conflicts { content: Vec<u8>, count: usize },
+}
+
+impl TextMergeResult {
+    pub fn is_clean(&self) -> bool {
+        matches!(self, TextMergeResult::Clean(_))
+    }
+
+    pub fn content(&self) -> &[u8] {
+        match self {
+            TextMergeResult::Clean(content) => content,
+            TextMergeResult::Conflicts { content, .. } => content,
+        }
+    }
+}
+
+/// A change made by one side, in terms of line ranges.
+struct Change {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+
+fn changes(base: &[u8], side: &[u8]) -> Vec<Change> {
+    diff_hunks(base, side)
+        .into_iter()
+        .map(|hunk| Change {
+            base: hunk.remove,
+            side: hunk.add,
+        })
+        .collect()
+}
+
+fn split_lines(text: &[u8]) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, byte) in text.iter().enumerate() {
+        if *byte == b'\n' {
+            lines.push(&text[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+/// The lines of a side that correspond to `base` lines, given the changes
+/// the side made within them.
+fn side_range(changes: &[Change], base: &Range<usize>) -> Range<usize> {
+    match (changes.first(), changes.last()) {
+        (Some(first), Some(last)) => {
+            (first.side.start - (first.base.start - base.start))
+                ..(last.side.end + (base.end - last.base.end))
+        }
+        _ => base.clone(),
+    }
+}
+
+/// Merge the changes made from `base` to `dest` and from `base` to `src`.
+///
+/// Changes that overlap or are adjacent to each other conflict, unless both
+/// sides made the same change.
+pub fn merge_text(
+    base: &[u8],
+    dest: &[u8],
+    src: &[u8],
+    style: ConflictStyle,
+    labels: &ConflictLabels,
+) -> TextMergeResult {
+    let dest_changes = changes(base, dest);
+    let src_changes = changes(base, src);
+    let base_lines = split_lines(base);
+    let dest_lines = split_lines(dest);
+    let src_lines = split_lines(src);
+
+    let mut out = Vec::with_capacity(max(dest.len(), src.len()));
+    let mut conflicts = 0;
+    let mut base_pos = 0;
+    let (mut d, mut s) = (0, 0);
+
+    while d < dest_changes.len() || s < src_changes.len() {
+        // Group changes from both sides that overlap or touch.
+        let start = min(
+            dest_changes.get(d).map_or(usize::MAX, |c| c.base.start),
+            src_changes.get(s).map_or(usize::MAX, |c| c.base.start),
+        );
+        let mut end = start;
+        let (group_d, group_s) = (d, s);
+        loop {
+            if let Some(change) = dest_changes.get(d).filter(|c| c.base.start <= end) {
+                end = max(end, change.base.end);
+                d += 1;
+            } else if let Some(change) = src_changes.get(s).filter(|c| c.base.start <= end) {
+                end = max(end, change.base.end);
+                s += 1;
+            } else {
+                break;
+            }
+        }
+
+        write_lines(&mut out, &base_lines[base_pos..start]);
+        base_pos = end;
+
+        let base_range = start..end;
+        let dest_group = &dest_changes[group_d..d];
+        let src_group = &src_changes[group_s..s];
+        let dest_region = &dest_lines[side_range(dest_group, &base_range)];
+        let src_region = &src_lines[side_range(src_group, &base_range)];
+
+        if src_group.is_empty() || dest_region == src_region {
+            write_lines(&mut out, dest_region);
+        } else if dest_group.is_empty() {
+            write_lines(&mut out, src_region);
+        } else {
+            conflicts += 1;
+            write_conflict(
+                &mut out,
+                style,
+                labels,
+                &base_lines[base_range],
+                dest_region,
+                src_region,
+            );
+        }
+    }
+    write_lines(&mut out, &base_lines[base_pos..]);
+
+    if conflicts == 0 {
+        TextMergeResult::Clean(out)
+    } else {
+        TextMergeResult::Conflicts {
+            content: out,
+            count: conflicts,
+        }
+    }
+}
+
+fn write_lines(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    for line in lines {
+        out.extend_from_slice(line);
+    }
+}
+
+/// Write lines followed by a marker, making sure the marker starts on a new line.
+fn write_lines_before_marker(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    write_lines(out, lines);
+    if !out.is_empty() && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+}
+
+fn write_marker(out: &mut Vec<u8>, marker: u8, label: Option<&str>) {
+    out.extend_from_slice(&[marker; MARKER_LEN]);
+    if let Some(label) = label {
+        out.push(b' ');
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(b'\n');
+}
+
+fn write_conflict(
+    out: &mut Vec<u8>,
+    style: ConflictStyle,
+    labels: &ConflictLabels,
+    base: &[&[u8]],
+    dest: &[&[u8]],
+    src: &[&[u8]],
+) {
+    let (prefix, suffix) = match style {
+        ConflictStyle::Diff3 => (0, 0),
+        ConflictStyle::Merge | ConflictStyle::ZDiff3 => {
+            let prefix = dest.iter().zip(src).take_while(|(d, s)| d == s).count();
+            let suffix = dest[prefix..]
+                .iter()
+                .rev()
+                .zip(src[prefix..].iter().rev())
+                .take_while(|(d, s)| d == s)
+                .count();
+            (prefix, suffix)
+        }
+    };
+
+    write_lines_before_marker(out, &dest[..prefix]);
+    write_marker(out, b'<', Some(&labels.dest));
+    write_lines_before_marker(out, &dest[prefix..dest.len() - suffix]);
+    if style != ConflictStyle::Merge {
+        write_marker(out, b'|', Some(&labels.base));
+        write_lines_before_marker(out, base);
+    }
+    write_marker(out, b'=', None);
+    write_lines_before_marker(out, &src[prefix..src.len() - suffix]);
+    write_marker(out, b'>', Some(&labels.src));
+    write_lines(out, &dest[dest.len() - suffix..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, dest: &str, src: &str, style: ConflictStyle) -> (String, usize) {
+        match merge_text(
+            base.as_bytes(),
+            dest.as_bytes(),
+            src.as_bytes(),
+            style,
+            &ConflictLabels::default(),
+        ) {
+            TextMergeResult::Clean(content) => (String::from_utf8(content).unwrap(), 0),
+            TextMergeResult::Conflicts { content, count } => {
+                (String::from_utf8(content).unwrap(), count)
+            }
+        }
+    }
+
+    #[test]
+    fn test_clean_merges() {
+        let base = "a\nb\nc\nd\ne\n";
+        assert_eq!(
+            merge(
+                base,
+                "A\nb\nc\nd\ne\n",
+                "a\nb\nc\nd\nE\n",
+                ConflictStyle::Merge
+            ),
+            ("A\nb\nc\nd\nE\n".to_string(), 0)
+        );
+        // Insertions and deletions on different sides.
+        assert_eq!(
+            merge(
+                base,
+                "a\nb\nc\nx\nd\ne\n",
+                "b\nc\nd\ne\n",
+                ConflictStyle::Merge
+            ),
+            ("b\nc\nx\nd\ne\n".to_string(), 0)
+        );
+        // Only one side changed.
+        assert_eq!(
+            merge(base, base, "b\nc\nz\n", ConflictStyle::Merge),
+            ("b\nc\nz\n".to_string(), 0)
+        );
+        // Both sides made the same change.
+        assert_eq!(
+            merge(
+                base,
+                "a\nB\nc\nd\ne\n",
+                "a\nB\nc\nd\nE\n",
+                ConflictStyle::Merge
+            ),
+            ("a\nB\nc\nd\nE\n".to_string(), 0)
+        );
+        // Missing newline at end of file.
+        assert_eq!(
+            merge("a\nb\nc", "A\nb\nc", "a\nb\nc\nd", ConflictStyle::Merge),
+            ("A\nb\nc\nd".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_conflict_styles() {
+        let base = "a\nb\nc\n";
+        let dest = "a\nx\nB1\ny\nc\n";
+        let src = "a\nx\nB2\ny\nc\n";
+
+        assert_eq!(
+            merge(base, dest, src, ConflictStyle::Merge),
+            (
+                "a\nx\n<<<<<<< local\nB1\n=======\nB2\n>>>>>>> other\ny\nc\n".to_string(),
+                1
+            )
+        );
+        assert_eq!(
+            merge(base, dest, src, ConflictStyle::Diff3),
+            (
+                "a\n<<<<<<< local\nx\nB1\ny\n||||||| base\nb\n=======\nx\nB2\ny\n>>>>>>> other\nc\n"
+                    .to_string(),
+                1
+            )
+        );
+        assert_eq!(
+            merge(base, dest, src, ConflictStyle::ZDiff3),
+            (
+                "a\nx\n<<<<<<< local\nB1\n||||||| base\nb\n=======\nB2\n>>>>>>> other\ny\nc\n"
+                    .to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        // Insertions at the same place conflict.
+        assert_eq!(
+            merge("a\nb\n", "a\nx\nb\n", "a\ny\nb\n", ConflictStyle::Merge),
+            (
+                "a\n<<<<<<< local\nx\n=======\ny\n>>>>>>> other\nb\n".to_string(),
+                1
+            )
+        );
+        // Adjacent changes conflict.
+        assert_eq!(
+            merge("a\nb\nc\n", "A\nb\nc\n", "a\nB\nc\n", ConflictStyle::Merge),
+            (
+                "<<<<<<< local\nA\nb\n=======\na\nB\n>>>>>>> other\nc\n".to_string(),
+                1
+            )
+        );
+        // Each conflicting region is counted, and markers start on new lines.
+        assert_eq!(
+            merge(
+                "a\nb\nc\nd\ne",
+                "A1\nb\nc\nd\nE1",
+                "A2\nb\nc\nd\nE2",
+                ConflictStyle::Merge
+            ),
+            (
+                "<<<<<<< local\nA1\n=======\nA2\n>>>>>>> other\nb\nc\nd\n\
+                <<<<<<< local\nE1\n=======\nE2\n>>>>>>> other\n"
+                    .to_string(),
+                2
+            )
+        );
+        // Files created on both sides are merged against an empty base.
+        assert_eq!(
+            merge("", "a\n", "b\n", ConflictStyle::Diff3),
+            (
+                "<<<<<<< local\na\n||||||| base\n=======\nb\n>>>>>>> other\n".to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_conflict_style_from_str() {
+        assert_eq!(
+            "zdiff3".parse::<ConflictStyle>().unwrap(),
+            ConflictStyle::ZDiff3
+        );
+        assert!("foo".parse::<ConflictStyle>().is_err());
+    }
+}
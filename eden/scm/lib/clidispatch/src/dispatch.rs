@@ -73,6 +73,20 @@ where
     Ok(())
 }
 
+/// Reflect -q, -v and --debug in the "ui" config section, like Python does.
+fn apply_output_flags(config: &mut ConfigSet, opts: &HgGlobalOpts) {
+    if opts.quiet || opts.verbose || opts.debug {
+        for (name, value) in [
+            ("quiet", opts.quiet),
+            ("verbose", opts.verbose),
+            ("debug", opts.debug),
+        ] {
+            let value = if value { "true" } else { "false" };
+            config.set("ui", name, Some(value), &format!("--{}", name).into());
+        }
+    }
+}
+
 fn last_chance_to_abort(opts: &HgGlobalOpts) -> Result<()> {
     if opts.profile {
         return Err(errors::Abort("--profile does not support Rust commands (yet)".into()).into());
@@ -226,7 +240,7 @@ impl Dispatcher {
     pub fn run_command(self, command_table: &CommandTable, io: &IO) -> Result<u8> {
         let args = &self.args;
         let early_result = &self.early_result;
-        let optional_repo = self.optional_repo;
+        let mut optional_repo = self.optional_repo;
         let config = optional_repo.config();
         let global_opts = self.global_opts;
 
@@ -304,6 +318,7 @@ impl Dispatcher {
 
         let global_opts: HgGlobalOpts = parsed.clone().try_into()?;
         last_chance_to_abort(&global_opts)?;
+        apply_output_flags(optional_repo.config_mut(), &global_opts);
 
         initialize_blackbox(&optional_repo)?;
 
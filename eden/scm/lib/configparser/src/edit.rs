@@ -0,0 +1,392 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Edit config files in place.
+//!
+//! Edits only touch the lines defining the edited config. Comments, blank
+//! lines, ordering and `%include` directives are kept as-is.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use pest::Parser;
+
+use crate::error::Error;
+use crate::parser::ConfigParser;
+use crate::parser::Rule;
+
+/// A config file being edited.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    content: String,
+}
+
+/// Locations of things in a config file, relevant for editing.
+#[derive(Default)]
+struct Layout {
+    /// Spans of `config_item`s, and of their values, for the given config.
+    items: Vec<(Range<usize>, Range<usize>)>,
+    /// End of the last line belonging to the last block of the given
+    /// section, ignoring trailing blank lines and comments.
+    section_end: Option<usize>,
+}
+
+impl ConfigFile {
+    /// Open a config file for editing. A missing file is treated as empty,
+    /// and is created by [`ConfigFile::save`].
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = match fs::read(&path) {
+            Ok(data) => {
+                String::from_utf8(data).map_err(|e| Error::Utf8(path.clone(), e.utf8_error()))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::Io(path, e)),
+        };
+        Self::from_content(path, content)
+    }
+
+    /// Edit `content`, which will be saved to `path`.
+    pub fn from_content(path: impl AsRef<Path>, content: impl Into<String>) -> crate::Result<Self> {
+        let file = Self {
+            path: path.as_ref().to_path_buf(),
+            content: content.into(),
+        };
+        // Check the syntax early, so edits can't fail to parse.
+        file.layout("", "")?;
+        Ok(file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The edited content.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Write the edited content to the file, creating it and its parent
+    /// directories if needed.
+    pub fn save(&self) -> crate::Result<()> {
+        let io_error = |e| Error::Io(self.path.clone(), e);
+        if let Some(dir) = self.path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir).map_err(io_error)?;
+            }
+        }
+        // Write to a temporary file first so readers never see a partially
+        // written config.
+        let mut temp_name = self.path.clone().into_os_string();
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+        fs::write(&temp_path, &self.content).map_err(io_error)?;
+        fs::rename(&temp_path, &self.path).map_err(io_error)
+    }
+
+    /// Set a config. If the file defines the config, its last definition is
+    /// replaced in place. Otherwise, the config is added to the end of the
+    /// last `[section]` block, or to a new block at the end of the file.
+    pub fn set(&mut self, section: &str, name: &str, value: &str) -> crate::Result<()> {
+        let layout = self.layout(section, name)?;
+        match layout.items.last() {
+            Some((_, value_span)) => {
+                let mut value = format_value(value);
+                // "name =" has no space before the (empty) value.
+                if value_span.is_empty() && !self.content[..value_span.start].ends_with(' ') {
+                    value.insert(0, ' ');
+                }
+                self.content.replace_range(value_span.clone(), &value);
+                Ok(())
+            }
+            None => self.insert(section, name, value, layout.section_end),
+        }
+    }
+
+    /// Add a definition of a config after the existing definitions in the
+    /// section, leaving existing definitions untouched.
+    pub fn append(&mut self, section: &str, name: &str, value: &str) -> crate::Result<()> {
+        let layout = self.layout(section, name)?;
+        self.insert(section, name, value, layout.section_end)
+    }
+
+    /// Remove all definitions of a config from the file. Returns whether
+    /// anything was removed.
+    ///
+    /// This does not add an `%unset` directive, so the config may still be
+    /// set by other files.
+    pub fn unset(&mut self, section: &str, name: &str) -> crate::Result<bool> {
+        let layout = self.layout(section, name)?;
+        for (item_span, _) in layout.items.iter().rev() {
+            let mut end = item_span.end;
+            if self.content[end..].starts_with("\r\n") {
+                end += 2;
+            } else if self.content[end..].starts_with('\n') {
+                end += 1;
+            }
+            self.content.replace_range(item_span.start..end, "");
+        }
+        Ok(!layout.items.is_empty())
+    }
+
+    fn insert(
+        &mut self,
+        section: &str,
+        name: &str,
+        value: &str,
+        section_end: Option<usize>,
+    ) -> crate::Result<()> {
+        if name.is_empty() || name.contains(&['=', '\n', '[', '%', '#', ';'][..]) {
+            return Err(Error::General(format!("invalid config name {:?}", name)));
+        }
+        let item = format!("{} = {}", name, format_value(value));
+        match section_end {
+            Some(end) => {
+                let newline = self.newline();
+                self.content
+                    .insert_str(end, &format!("{}{}", newline, item));
+            }
+            None => {
+                if section.is_empty() || section.contains(&[']', '\n'][..]) {
+                    return Err(Error::General(format!(
+                        "invalid config section {:?}",
+                        section
+                    )));
+                }
+                let newline = self.newline();
+                if !self.content.is_empty() {
+                    if !self.content.ends_with('\n') {
+                        self.content.push_str(newline);
+                    }
+                    self.content.push_str(newline);
+                }
+                self.content
+                    .push_str(&format!("[{}]{}{}{}", section, newline, item, newline));
+            }
+        }
+        Ok(())
+    }
+
+    /// Match the line endings of the file.
+    fn newline(&self) -> &'static str {
+        if self.content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn layout(&self, section: &str, name: &str) -> crate::Result<Layout> {
+        let pairs = ConfigParser::parse(Rule::file, &self.content)
+            .map_err(|e| Error::Parse(self.path.clone(), format!("{}", e)))?;
+
+        let mut layout = Layout::default();
+        let mut current_section = "";
+        for pair in pairs {
+            let span = pair.as_span();
+            match pair.as_rule() {
+                Rule::section => {
+                    current_section = pair
+                        .into_inner()
+                        .find(|pair| pair.as_rule() == Rule::section_name)
+                        .map(|pair| pair.as_str())
+                        .unwrap_or_default();
+                    if current_section == section {
+                        layout.section_end = Some(span.end());
+                    }
+                }
+                Rule::config_item if current_section == section => {
+                    layout.section_end = Some(span.end());
+                    let mut item_name = "";
+                    for inner in pair.into_inner() {
+                        match inner.as_rule() {
+                            Rule::config_name => item_name = inner.as_str().trim_end(),
+                            Rule::value if item_name == name => {
+                                let value = inner.as_span();
+                                layout
+                                    .items
+                                    .push((span.start()..span.end(), value.start()..value.end()));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Rule::directive if current_section == section => {
+                    layout.section_end = Some(span.end());
+                }
+                _ => {}
+            }
+        }
+        Ok(layout)
+    }
+}
+
+/// Format a value so it parses back to itself. Lines after the first are
+/// indented so they continue the value.
+fn format_value(value: &str) -> String {
+    value.trim().replace('\n', "\n  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigSet;
+    use crate::config::Options;
+
+    const CONTENT: &str = r#"# Leading comment.
+[ui]
+username = Alice <alice@example.com>
+; Keep this comment.
+merge = internal:merge
+
+%include other.rc
+
+[extensions]
+rebase =
+amend = !
+
+[ui]
+verbose = true
+# comment after ui
+"#;
+
+    fn edit(content: &str) -> ConfigFile {
+        ConfigFile::from_content("hgrc", content).unwrap()
+    }
+
+    fn get(content: &str, section: &str, name: &str) -> Option<String> {
+        let mut config = ConfigSet::new();
+        let errors = config.parse(content.to_string(), &Options::default());
+        assert!(errors.is_empty(), "{:?}", errors);
+        config.get(section, name).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut file = edit(CONTENT);
+        assert_eq!(file.content(), CONTENT);
+
+        file.set("ui", "merge", "internal:merge").unwrap();
+        assert_eq!(file.content(), CONTENT);
+
+        // Unsetting then appending a config at the end of its section is a
+        // no-op for the last item in the file.
+        assert!(file.unset("ui", "verbose").unwrap());
+        file.append("ui", "verbose", "true").unwrap();
+        assert_eq!(file.content(), CONTENT);
+    }
+
+    #[test]
+    fn test_set_existing() {
+        let mut file = edit(CONTENT);
+        file.set("ui", "username", "Bob <bob@example.com>").unwrap();
+        file.set("extensions", "rebase", "!").unwrap();
+        assert_eq!(
+            file.content(),
+            CONTENT
+                .replace("Alice <alice@example.com>", "Bob <bob@example.com>")
+                .replace("rebase =\n", "rebase = !\n")
+        );
+        assert_eq!(
+            get(file.content(), "ui", "username").unwrap(),
+            "Bob <bob@example.com>"
+        );
+        assert_eq!(get(file.content(), "extensions", "rebase").unwrap(), "!");
+    }
+
+    #[test]
+    fn test_set_new() {
+        let mut file = edit(CONTENT);
+        file.set("ui", "paginate", "false").unwrap();
+        file.set("pager", "pager", "less").unwrap();
+        assert_eq!(
+            file.content(),
+            CONTENT.replace("verbose = true\n", "verbose = true\npaginate = false\n")
+                + "\n[pager]\npager = less\n"
+        );
+
+        let mut file = edit("");
+        file.set("a", "x", "1").unwrap();
+        assert_eq!(file.content(), "[a]\nx = 1\n");
+
+        let mut file = edit("[a]\nx = 1");
+        file.set("a", "y", "2").unwrap();
+        file.set("b", "z", "3").unwrap();
+        assert_eq!(file.content(), "[a]\nx = 1\ny = 2\n\n[b]\nz = 3\n");
+
+        assert!(file.set("c", "", "1").is_err());
+        assert!(file.set("c", "a=b", "1").is_err());
+        assert!(file.set("c]", "x", "1").is_err());
+    }
+
+    #[test]
+    fn test_multiline_values() {
+        let mut file = edit("[a]\nx = 1\n  2\ny = 3\n");
+        file.set("a", "x", "4\n5\n6").unwrap();
+        assert_eq!(file.content(), "[a]\nx = 4\n  5\n  6\ny = 3\n");
+        assert_eq!(get(file.content(), "a", "x").unwrap(), "4\n5\n6");
+        assert_eq!(get(file.content(), "a", "y").unwrap(), "3");
+
+        assert!(file.unset("a", "x").unwrap());
+        assert_eq!(file.content(), "[a]\ny = 3\n");
+    }
+
+    #[test]
+    fn test_unset_and_append() {
+        let mut file = edit("[a]\nx = 1\n[b]\nx = 2\n[a]\nx = 3\n%unset y\n# end\n");
+        file.append("a", "x", "4").unwrap();
+        assert_eq!(
+            file.content(),
+            "[a]\nx = 1\n[b]\nx = 2\n[a]\nx = 3\n%unset y\nx = 4\n# end\n"
+        );
+        assert_eq!(get(file.content(), "a", "x").unwrap(), "4");
+
+        assert!(file.unset("a", "x").unwrap());
+        assert_eq!(file.content(), "[a]\n[b]\nx = 2\n[a]\n%unset y\n# end\n");
+        assert!(!file.unset("a", "x").unwrap());
+        assert_eq!(get(file.content(), "b", "x").unwrap(), "2");
+    }
+
+    #[test]
+    fn test_crlf() {
+        let mut file = edit("[a]\r\nx = 1\r\n");
+        file.set("a", "y", "2").unwrap();
+        file.set("b", "z", "3").unwrap();
+        assert_eq!(
+            file.content(),
+            "[a]\r\nx = 1\r\ny = 2\r\n\r\n[b]\r\nz = 3\r\n"
+        );
+        assert!(file.unset("a", "x").unwrap());
+        assert_eq!(file.content(), "[a]\r\ny = 2\r\n\r\n[b]\r\nz = 3\r\n");
+    }
+
+    #[test]
+    fn test_open_and_save() {
+        let dir = tempdir::TempDir::new("test_edit").unwrap();
+        let path = dir.path().join("hg").join("hgrc");
+
+        let mut file = ConfigFile::open(&path).unwrap();
+        assert_eq!(file.content(), "");
+        file.set("ui", "username", "Alice").unwrap();
+        file.save().unwrap();
+
+        let mut config = ConfigSet::new();
+        assert!(config.load_path(&path, &Options::default()).is_empty());
+        assert_eq!(config.get("ui", "username").unwrap(), "Alice");
+        let sources = config.get_sources("ui", "username");
+        let (location_path, range) = sources[0].location().unwrap();
+        assert_eq!(location_path, path);
+        assert_eq!(&file.content()[range], "Alice");
+
+        fs::write(&path, "[ui\n").unwrap();
+        assert!(ConfigFile::open(&path).is_err());
+    }
+}
@@ -30,6 +30,7 @@ use util::path::expand_path;
 use crate::config::ConfigSet;
 use crate::config::Options;
 use crate::config::SupersetVerification;
+use crate::edit::ConfigFile;
 use crate::error::Error;
 use crate::error::Errors;
 
@@ -270,25 +271,8 @@ impl ConfigSetHgExt for ConfigSet {
                 errors.append(&mut self.load_path(expand_path(path), &opts));
             }
         } else {
-            #[cfg(unix)]
-            {
-                errors.append(&mut self.load_path("/etc/mercurial/system.rc", &opts));
-                // TODO(T40519286): Remove this after the tupperware overrides move out of hgrc.d
-                errors.append(
-                    &mut self.load_path("/etc/mercurial/hgrc.d/tupperware_overrides.rc", &opts),
-                );
-                // TODO(quark): Remove this after packages using system.rc are rolled out
-                errors.append(&mut self.load_path("/etc/mercurial/hgrc.d/include.rc", &opts));
-            }
-
-            #[cfg(windows)]
-            {
-                if let Ok(program_data_path) = env::var("PROGRAMDATA") {
-                    let hgrc_dir = Path::new(&program_data_path).join("Facebook\\Mercurial");
-                    errors.append(&mut self.load_path(hgrc_dir.join("system.rc"), &opts));
-                    // TODO(quark): Remove this after packages using system.rc are rolled out
-                    errors.append(&mut self.load_path(hgrc_dir.join("hgrc"), &opts));
-                }
+            for path in system_config_paths() {
+                errors.append(&mut self.load_path(path, &opts));
             }
         }
 
@@ -433,20 +417,11 @@ impl ConfigSetHgExt for ConfigSet {
 
     fn load_user(&mut self, opts: Options) -> Vec<Error> {
         // If HGRCPATH is set, don't load user configs
-        let mut paths = Vec::new();
-        if env::var("HGRCPATH").is_err() {
-            if let Some(home_dir) = dirs::home_dir() {
-                paths.push(home_dir.join(".hgrc"));
-
-                #[cfg(windows)]
-                {
-                    paths.push(home_dir.join("mercurial.ini"));
-                }
-            }
-            if let Some(config_dir) = dirs::config_dir() {
-                paths.push(config_dir.join("hg/hgrc"))
-            }
-        }
+        let paths = if env::var("HGRCPATH").is_err() {
+            user_config_paths()
+        } else {
+            Vec::new()
+        };
         self.load_user_internal(&paths, opts)
     }
 
@@ -491,6 +466,104 @@ impl ConfigSetHgExt for ConfigSet {
     }
 }
 
+/// A config file that `hg` reads, which settings can be written to with
+/// [`ConfigLayer::edit`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigLayer {
+    /// The system config file.
+    System,
+    /// The user config file.
+    User,
+    /// The config file of a repo, given the path to its `.hg` directory.
+    Repo(PathBuf),
+}
+
+impl ConfigLayer {
+    /// The file to edit for this layer. For the user layer, this is the
+    /// first existing user config file, or `~/.hgrc` if none exists.
+    ///
+    /// Returns `None` for the system and user layers if `$HGRCPATH` is set,
+    /// since their files are not read in that case.
+    pub fn path(&self) -> Option<PathBuf> {
+        match self {
+            ConfigLayer::System | ConfigLayer::User if env::var(HGRCPATH).is_ok() => None,
+            ConfigLayer::System => system_config_paths().into_iter().next(),
+            ConfigLayer::User => {
+                let paths = user_config_paths();
+                paths
+                    .iter()
+                    .find(|path| path.exists())
+                    .or_else(|| paths.first())
+                    .cloned()
+            }
+            ConfigLayer::Repo(repo_path) => Some(repo_path.join("hgrc")),
+        }
+    }
+
+    /// Open the file of this layer for editing.
+    pub fn edit(&self) -> std::result::Result<ConfigFile, Error> {
+        if !matches!(self, ConfigLayer::Repo(_)) && env::var(HGRCPATH).is_ok() {
+            return Err(Error::General(format!(
+                "cannot edit the config file for {:?} while ${} is set",
+                self, HGRCPATH
+            )));
+        }
+        match self.path() {
+            Some(path) => ConfigFile::open(path),
+            None => Err(Error::General(format!(
+                "cannot find the config file for {:?}",
+                self
+            ))),
+        }
+    }
+}
+
+/// System config files, in the order they are loaded. The first one is the
+/// main system config file.
+fn system_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    #[cfg(unix)]
+    {
+        paths.push(PathBuf::from("/etc/mercurial/system.rc"));
+        // TODO(T40519286): Remove this after the tupperware overrides move out of hgrc.d
+        paths.push(PathBuf::from(
+            "/etc/mercurial/hgrc.d/tupperware_overrides.rc",
+        ));
+        // TODO(quark): Remove this after packages using system.rc are rolled out
+        paths.push(PathBuf::from("/etc/mercurial/hgrc.d/include.rc"));
+    }
+
+    #[cfg(windows)]
+    {
+        if let Ok(program_data_path) = env::var("PROGRAMDATA") {
+            let hgrc_dir = Path::new(&program_data_path).join("Facebook\\Mercurial");
+            paths.push(hgrc_dir.join("system.rc"));
+            // TODO(quark): Remove this after packages using system.rc are rolled out
+            paths.push(hgrc_dir.join("hgrc"));
+        }
+    }
+
+    paths
+}
+
+/// Candidate user config files, in the order they are loaded.
+fn user_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(home_dir) = dirs::home_dir() {
+        paths.push(home_dir.join(".hgrc"));
+
+        #[cfg(windows)]
+        {
+            paths.push(home_dir.join("mercurial.ini"));
+        }
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("hg/hgrc"))
+    }
+    paths
+}
+
 impl ConfigSet {
     // For easier testing.
     pub(crate) fn load_user_internal(&mut self, paths: &[PathBuf], opts: Options) -> Vec<Error> {
@@ -797,6 +870,35 @@ mod tests {
         cfg.load_system(Options::new());
         assert_eq!(cfg.get("x", "a"), Some("1".into()));
         assert_eq!(cfg.get("y", "b"), Some("2".into()));
+
+        // The system and user files are not read, so they cannot be edited.
+        assert_eq!(ConfigLayer::User.path(), None);
+        assert!(ConfigLayer::User.edit().is_err());
+        assert!(ConfigLayer::System.edit().is_err());
+    }
+
+    #[test]
+    fn test_edit_repo_layer() {
+        let dir = TempDir::new("test_edit_repo_layer").unwrap();
+        let layer = ConfigLayer::Repo(dir.path().to_path_buf());
+        write_file(
+            dir.path().join("hgrc"),
+            "# repo config\n[paths]\ndefault = foo\n",
+        );
+
+        let mut file = layer.edit().unwrap();
+        file.set("paths", "default", "bar").unwrap();
+        file.set("ui", "username", "test").unwrap();
+        file.save().unwrap();
+
+        let mut cfg = ConfigSet::new();
+        cfg.load_repo(dir.path(), Options::new());
+        assert_eq!(cfg.get("paths", "default").unwrap(), "bar");
+        assert_eq!(cfg.get("ui", "username").unwrap(), "test");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hgrc")).unwrap(),
+            "# repo config\n[paths]\ndefault = bar\n\n[ui]\nusername = test\n"
+        );
     }
 
     #[test]
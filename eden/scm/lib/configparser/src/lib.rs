@@ -65,9 +65,15 @@
 //!  line2
 //!  line3
 //! ```
+//!
+//! ## Editing
+//!
+//! [`edit::ConfigFile`] sets, appends or removes configs in a file while
+//! keeping its comments, ordering and `%include`s.
 
 pub mod c_api;
 pub mod config;
+pub mod edit;
 pub mod hg;
 pub mod parser;
 
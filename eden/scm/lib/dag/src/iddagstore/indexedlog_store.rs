@@ -125,7 +125,7 @@ impl IdDagStore for IndexedLogStore {
         let key = Self::serialize_head_level_lookup_key(head, level);
         match self.log.lookup(Self::INDEX_LEVEL_HEAD, &key)?.nth(0) {
             None => Ok(None),
-            Some(bytes) => Ok(Some(self.segment_from_slice(bytes?))),
+            Some(bytes) => Ok(Some(self.segment_from_slice(&bytes?))),
         }
     }
 
@@ -140,7 +140,7 @@ impl IdDagStore for IndexedLogStore {
             let (_, entries) = entry?;
             for entry in entries {
                 let entry = entry?;
-                let seg = self.segment_from_slice(entry);
+                let seg = self.segment_from_slice(&entry);
                 if seg.span()?.low > id {
                     return Ok(None);
                 }
@@ -210,7 +210,7 @@ impl IdDagStore for IndexedLogStore {
                 // break the logic here. If perf is really needed, we can change
                 // logic here to not checking values.
                 if let Some(bytes) = values.next() {
-                    let seg = self.segment_from_slice(bytes?);
+                    let seg = self.segment_from_slice(&bytes?);
                     Ok(seg.high()? + 1)
                 } else {
                     bug(format!("key {:?} should have values in next_free_id", key))
@@ -229,7 +229,7 @@ impl IdDagStore for IndexedLogStore {
         {
             let (_, values) = entry?;
             for value in values {
-                result.push(self.segment_from_slice(value?));
+                result.push(self.segment_from_slice(&value?));
             }
         }
         Ok(result)
@@ -252,7 +252,7 @@ impl IdDagStore for IndexedLogStore {
                     .into_iter()
                     .map(|value| {
                         let value = value?;
-                        Ok(self.segment_from_slice(value))
+                        Ok(self.segment_from_slice(&value))
                     })
                     .collect(),
                 Err(err) => vec![Err(err.into())],
@@ -276,7 +276,7 @@ impl IdDagStore for IndexedLogStore {
                 Ok((_key, values)) => values
                     .map(|value| {
                         let value = value?;
-                        Ok(self.segment_from_slice(value))
+                        Ok(self.segment_from_slice(&value))
                     })
                     .collect(),
                 Err(err) => vec![Err(err.into())],
@@ -303,7 +303,7 @@ impl IdDagStore for IndexedLogStore {
             for segment in segments {
                 result.push((
                     parent_id,
-                    SegmentWithWrongHead(self.segment_from_slice(segment?)),
+                    SegmentWithWrongHead(self.segment_from_slice(&segment?)),
                 ));
             }
         }
@@ -319,7 +319,7 @@ impl IdDagStore for IndexedLogStore {
             let iter = self.log.lookup(Self::INDEX_PARENT, &key)?;
             let iter = iter.map(move |result| {
                 match result {
-                    Ok(bytes) => Ok(SegmentWithWrongHead(self.segment_from_slice(bytes))),
+                    Ok(bytes) => Ok(SegmentWithWrongHead(self.segment_from_slice(&bytes))),
                     Err(err) => Err(err.into()),
                 }
             });
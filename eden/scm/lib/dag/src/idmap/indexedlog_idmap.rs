@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+use std::borrow::Cow;
 use std::fmt;
 use std::fs::File;
 use std::fs::{self};
@@ -134,7 +135,7 @@ impl IdMap {
     }
 
     /// Find name by a specified integer id.
-    pub fn find_name_by_id(&self, id: Id) -> Result<Option<&[u8]>> {
+    pub fn find_name_by_id(&self, id: Id) -> Result<Option<Cow<'_, [u8]>>> {
         let key = id.0.to_be_bytes();
         let key = self.log.lookup(Self::INDEX_ID_TO_NAME, &key)?.nth(0);
         match key {
@@ -142,7 +143,11 @@ impl IdMap {
                 if entry.len() < 8 {
                     return bug("index key should have 8 bytes at least");
                 }
-                Ok(Some(&entry[Self::NAME_OFFSET..]))
+                let name = match entry {
+                    Cow::Borrowed(entry) => Cow::Borrowed(&entry[Self::NAME_OFFSET..]),
+                    Cow::Owned(entry) => Cow::Owned(entry[Self::NAME_OFFSET..].to_vec()),
+                };
+                Ok(Some(name))
             }
             None => Ok(None),
             Some(Err(err)) => Err(err.into()),
@@ -152,7 +157,7 @@ impl IdMap {
     /// Find VertexName by a specified integer id.
     pub fn find_vertex_name_by_id(&self, id: Id) -> Result<Option<VertexName>> {
         self.find_name_by_id(id)
-            .map(|v| v.map(|n| VertexName(self.log.slice_to_bytes(n))))
+            .map(|v| v.map(|n| VertexName(self.log.slice_to_bytes(&n))))
     }
 
     /// Find the integer id matching the given name.
@@ -166,11 +171,11 @@ impl IdMap {
                 .lookup(Self::INDEX_GROUP_NAME_TO_ID, group_name)?
                 .nth(0);
             match key {
-                Some(Ok(mut entry)) => {
+                Some(Ok(entry)) => {
                     if entry.len() < 8 {
                         return bug("index key should have 8 bytes at least");
                     }
-                    let id = Id(entry.read_u64::<BigEndian>().unwrap());
+                    let id = Id((&entry[..]).read_u64::<BigEndian>().unwrap());
                     return Ok(Some(id));
                 }
                 None => {}
@@ -310,7 +315,8 @@ impl fmt::Debug for IdMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "IdMap {{\n")?;
         for data in self.log.iter() {
-            if let Ok(mut data) = data {
+            if let Ok(data) = data {
+                let mut data = &data[..];
                 let id = data.read_u64::<BigEndian>().unwrap();
                 let _group = data.read_u8().unwrap();
                 let mut name = Vec::with_capacity(20);
@@ -734,12 +734,12 @@ Lv0: RH0-6[] 7-10[5] H11-12[6, 10] N0-N3[1] N4-N5[N3, 9]"#
     );
     assert_eq!(
         built.name_dag.map.find_name_by_id(Id(8)).unwrap().unwrap(),
-        b"m"
+        &b"m"[..]
     );
     let id = Group::NON_MASTER.min_id() + 5;
     assert_eq!(
         built.name_dag.map.find_name_by_id(id).unwrap().unwrap(),
-        b"q"
+        &b"q"[..]
     );
 
     // Parent-child indexes work fine.
@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "eagerepo_server",
+    about = "Serve an eager repo over the EdenApi HTTP protocol"
+)]
+struct Args {
+    #[structopt(help = "Path to the eager repo. Created on demand.")]
+    repo: PathBuf,
+    #[structopt(
+        long,
+        default_value = "127.0.0.1:0",
+        help = "Address to listen on. Port 0 picks an unused port."
+    )]
+    addr: SocketAddr,
+    #[structopt(long, help = "Write the server URL to this file")]
+    url_file: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let args = Args::from_args();
+    let (addr, server) = eagerepo_server::bind(&args.repo, &args.addr)?;
+
+    // Tests read the URL to set "edenapi.url".
+    let url = format!("http://{}/", addr);
+    if let Some(path) = args.url_file {
+        std::fs::write(path, &url)?;
+    }
+    println!("{}", url);
+    std::io::stdout().flush()?;
+
+    server.await
+}
@@ -0,0 +1,763 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Serves an [`EagerRepo`] over HTTP using the EdenApi wire protocol.
+//!
+//! This exercises the real `edenapi` HTTP client, including its CBOR framing
+//! and retry logic, without depending on Mononoke. Routes follow
+//! `edenapi_service`: `/health_check`, and `/{repo}/{endpoint}` where
+//! `endpoint` is one of the `edenapi` client paths. Reads: `capabilities`,
+//! `files`, `trees`, `history`, `commit/*`, `bookmarks`, `clone`,
+//! `pull_fast_forward_master`, `snapshot` and `download/file`. Writes:
+//! `lookup`, `upload/*`, `bookmarks/set`, `land` and `ephemeral/prepare`.
+//!
+//! Requests are CBOR-encoded wire types. Responses are concatenated
+//! CBOR-encoded wire values, or JSON for `capabilities`. As with the
+//! `edenapi_service`, `commit/revlog_data` uses plain CBOR without wire types.
+//!
+//! The repo name in the URL is ignored. The repo is reopened for each
+//! request so changes written to the directory by other processes are
+//! visible without restarting the server.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use eagerepo::EagerRepo;
+use edenapi_trait::types::wire::pull::PullFastForwardRequest;
+use edenapi_trait::types::AnyFileContentId;
+use edenapi_trait::types::Batch;
+use edenapi_trait::types::BookmarkRequest;
+use edenapi_trait::types::CommitDiffRequest;
+use edenapi_trait::types::CommitGraphRequest;
+use edenapi_trait::types::CommitHashLookupRequest;
+use edenapi_trait::types::CommitHashToLocationRequestBatch;
+use edenapi_trait::types::CommitLocationToHashRequestBatch;
+use edenapi_trait::types::CommitMutationsRequest;
+use edenapi_trait::types::CommitRevlogDataRequest;
+use edenapi_trait::types::EphemeralPrepareRequest;
+use edenapi_trait::types::FetchSnapshotRequest;
+use edenapi_trait::types::FileRequest;
+use edenapi_trait::types::HistoryEntry;
+use edenapi_trait::types::HistoryRequest;
+use edenapi_trait::types::HistoryResponseChunk;
+use edenapi_trait::types::LandStackRequest;
+use edenapi_trait::types::LookupRequest;
+use edenapi_trait::types::SetBookmarkRequest;
+use edenapi_trait::types::ToApi;
+use edenapi_trait::types::ToWire;
+use edenapi_trait::types::TreeRequest;
+use edenapi_trait::types::UploadBonsaiChangesetRequest;
+use edenapi_trait::types::UploadHgChangesetsRequest;
+use edenapi_trait::types::UploadHgFilenodeRequest;
+use edenapi_trait::types::UploadToken;
+use edenapi_trait::types::UploadTreeRequest;
+use edenapi_trait::types::WireHistoryEntry;
+use edenapi_trait::EdenApi;
+use edenapi_trait::EdenApiError;
+use hyper::body::Bytes;
+use hyper::header;
+use hyper::service::make_service_fn;
+use hyper::service::service_fn;
+use hyper::Body;
+use hyper::Method;
+use hyper::Request;
+use hyper::Response;
+use hyper::Server;
+use hyper::StatusCode;
+use tracing::debug;
+
+const HEALTH_CHECK: &str = "health_check";
+const HEALTH_CHECK_RESPONSE: &str = "I_AM_ALIVE";
+
+/// Bind an HTTP server serving the repo at `dir` to `addr`.
+///
+/// Returns the bound address, which differs from `addr` if it uses port 0,
+/// and a future that runs the server. Must be called within a Tokio runtime.
+pub fn bind(
+    dir: &Path,
+    addr: &SocketAddr,
+) -> Result<(SocketAddr, impl Future<Output = Result<()>>)> {
+    let dir: Arc<PathBuf> = Arc::new(dir.to_path_buf());
+    let make_service = make_service_fn(move |_conn| {
+        let dir = dir.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let dir = dir.clone();
+                async move { Ok::<_, Infallible>(handle(&dir, req).await) }
+            }))
+        }
+    });
+    let server = Server::try_bind(addr)?.serve(make_service);
+    let addr = server.local_addr();
+    let fut = async move {
+        server.await?;
+        Ok(())
+    };
+    Ok((addr, fut))
+}
+
+async fn handle(dir: &Path, req: Request<Body>) -> Response<Body> {
+    debug!("{} {}", req.method(), req.uri());
+    match dispatch(dir, req).await {
+        Ok(response) => response,
+        Err(e) => error_response(e),
+    }
+}
+
+async fn dispatch(dir: &Path, req: Request<Body>) -> edenapi_trait::Result<Response<Body>> {
+    let method = req.method().clone();
+    let path = req.uri().path().trim_start_matches('/').to_string();
+    let query = req.uri().query().map(|q| q.to_string());
+    if path == HEALTH_CHECK {
+        return Ok(Response::new(Body::from(HEALTH_CHECK_RESPONSE)));
+    }
+
+    // Paths are "{repo}/{endpoint}". Endpoints might contain "/".
+    let (repo_name, endpoint) = match path.split_once('/') {
+        Some((repo_name, endpoint)) => (repo_name.to_string(), endpoint),
+        None => return Err(not_found_error(&path)),
+    };
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map_err(|e| bad_request_error(format!("cannot read request body: {}", e)))?;
+    let repo = EagerRepo::open(dir).map_err(|e| EdenApiError::Other(e.into()))?;
+
+    match (method, endpoint) {
+        (Method::GET, "capabilities") => {
+            let capabilities = repo.capabilities(repo_name).await?;
+            json_response(&capabilities)
+        }
+        (Method::POST, "files") => {
+            let req: FileRequest = decode(&body)?;
+            let response = if req.reqs.is_empty() {
+                repo.files(repo_name, req.keys).await?
+            } else {
+                repo.files_attrs(repo_name, req.reqs).await?
+            };
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "trees") => {
+            let req: TreeRequest = decode(&body)?;
+            let response = repo
+                .trees(repo_name, req.keys, Some(req.attributes))
+                .await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "history") => {
+            let req: HistoryRequest = decode(&body)?;
+            let response = repo.history(repo_name, req.keys, req.length).await?;
+            cbor_response(history_chunks(response.flatten().await?))
+        }
+        (Method::POST, "commit/graph") => {
+            let req: CommitGraphRequest = decode(&body)?;
+            let entries = repo.commit_graph(repo_name, req.heads, req.common).await?;
+            cbor_response(entries)
+        }
+        (Method::POST, "bookmarks") => {
+            let req: BookmarkRequest = decode(&body)?;
+            let entries = repo.bookmarks(repo_name, req.bookmarks).await?;
+            cbor_response(entries)
+        }
+        (Method::POST, "clone") => {
+            let clone_data = repo.clone_data(repo_name).await?;
+            cbor_response(vec![clone_data])
+        }
+        (Method::POST, "pull_fast_forward_master") => {
+            let req: PullFastForwardRequest = decode(&body)?;
+            let clone_data = repo
+                .pull_fast_forward_master(repo_name, req.old_master, req.new_master)
+                .await?;
+            cbor_response(vec![clone_data])
+        }
+        (Method::POST, "commit/revlog_data") => {
+            let req: CommitRevlogDataRequest = serde_cbor::from_slice(&body)
+                .map_err(|e| bad_request_error(format!("cannot decode request: {}", e)))?;
+            let response = repo.commit_revlog_data(repo_name, req.hgids).await?;
+            raw_cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "commit/location_to_hash") => {
+            let req: CommitLocationToHashRequestBatch = decode(&body)?;
+            let response = repo
+                .commit_location_to_hash(repo_name, req.requests)
+                .await?;
+            cbor_response(response)
+        }
+        (Method::POST, "commit/hash_to_location") => {
+            let req: CommitHashToLocationRequestBatch = decode(&body)?;
+            let response = repo
+                .commit_hash_to_location(repo_name, req.master_heads, req.hgids)
+                .await?;
+            cbor_response(response)
+        }
+        (Method::POST, "commit/hash_lookup") => {
+            let req: Batch<CommitHashLookupRequest> = decode(&body)?;
+            let prefixes = req.batch.iter().map(hash_lookup_prefix).collect();
+            let response = repo.hash_prefixes_lookup(repo_name, prefixes).await?;
+            cbor_response(response)
+        }
+        (Method::POST, "commit/mutations") => {
+            let req: CommitMutationsRequest = decode(&body)?;
+            let response = repo.commit_mutations(repo_name, req.commits).await?;
+            cbor_response(response)
+        }
+        (Method::POST, "commit/diff") => {
+            let req: CommitDiffRequest = decode(&body)?;
+            let response = repo.commit_diff(repo_name, req).await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "bookmarks/set") => {
+            let req: SetBookmarkRequest = decode(&body)?;
+            let pushvars = req.pushvars.into_iter().map(|v| (v.key, v.value));
+            repo.set_bookmark(
+                repo_name,
+                req.bookmark,
+                req.to,
+                req.from,
+                pushvars.collect(),
+            )
+            .await?;
+            cbor_response(vec![()])
+        }
+        (Method::POST, "land") => {
+            let req: LandStackRequest = decode(&body)?;
+            let pushvars = req.pushvars.into_iter().map(|v| (v.key, v.value));
+            let response = repo
+                .land_stack(
+                    repo_name,
+                    req.bookmark,
+                    req.head,
+                    req.base,
+                    pushvars.collect(),
+                )
+                .await?;
+            cbor_response(vec![response])
+        }
+        (Method::POST, "lookup") => {
+            let req: Batch<LookupRequest> = decode(&body)?;
+            // Items are looked up in groups sharing the same bubble.
+            let mut groups = BTreeMap::new();
+            for item in req.batch {
+                groups
+                    .entry(item.bubble_id)
+                    .or_insert_with(Vec::new)
+                    .push(item.id);
+            }
+            let mut response = Vec::new();
+            for (bubble_id, ids) in groups {
+                let values = repo.lookup_batch(repo_name.clone(), ids, bubble_id).await?;
+                response.extend(values);
+            }
+            cbor_response(response)
+        }
+        (Method::PUT, endpoint) if endpoint.starts_with("upload/file/") => {
+            let id = &endpoint["upload/file/".len()..];
+            let id = AnyFileContentId::from_str(id)
+                .map_err(|e| bad_request_error(format!("invalid content id: {}", e)))?;
+            let content_size: Option<usize> = query_param(query.as_deref(), "content_size")?;
+            if content_size.map_or(false, |size| size != body.len()) {
+                return Err(bad_request_error(format!(
+                    "content size {:?} does not match body size {}",
+                    content_size,
+                    body.len()
+                )));
+            }
+            let bubble_id = query_param(query.as_deref(), "bubble_id")?;
+            let content = minibytes::Bytes::from(body.to_vec());
+            let response = repo
+                .process_files_upload(repo_name, vec![(id, content)], bubble_id)
+                .await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "upload/filenodes") => {
+            let req: Batch<UploadHgFilenodeRequest> = decode(&body)?;
+            let items = req.batch.into_iter().map(|r| r.data).collect();
+            let response = repo.upload_filenodes_batch(repo_name, items).await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "upload/trees") => {
+            let req: Batch<UploadTreeRequest> = decode(&body)?;
+            let items = req.batch.into_iter().map(|r| r.entry).collect();
+            let response = repo.upload_trees_batch(repo_name, items).await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "upload/changesets") => {
+            let req: UploadHgChangesetsRequest = decode(&body)?;
+            let response = repo
+                .upload_changesets(repo_name, req.changesets, req.mutations)
+                .await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "upload/changeset/bonsai") => {
+            let req: UploadBonsaiChangesetRequest = decode(&body)?;
+            let bubble_id = query_param(query.as_deref(), "bubble_id")?;
+            let response = repo
+                .upload_bonsai_changeset(repo_name, req.changeset, bubble_id)
+                .await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "ephemeral/prepare") => {
+            let req: EphemeralPrepareRequest = decode(&body)?;
+            let duration = req.custom_duration_secs.map(Duration::from_secs);
+            let response = repo.ephemeral_prepare(repo_name, duration).await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "snapshot") => {
+            let req: FetchSnapshotRequest = decode(&body)?;
+            let response = repo.fetch_snapshot(repo_name, req).await?;
+            cbor_response(response.flatten().await?)
+        }
+        (Method::POST, "download/file") => {
+            let token: UploadToken = decode(&body)?;
+            let data = repo.download_file(repo_name, token).await?;
+            cbor_response(vec![Bytes::from(data.to_vec())])
+        }
+        _ => Err(not_found_error(&path)),
+    }
+}
+
+/// Decode a CBOR-encoded wire request.
+fn decode<T: ToWire>(body: &[u8]) -> edenapi_trait::Result<T> {
+    let wire: T::Wire = serde_cbor::from_slice(body)
+        .map_err(|e| bad_request_error(format!("cannot decode request: {}", e)))?;
+    wire.to_api()
+        .map_err(|e| bad_request_error(format!("invalid request: {}", e)))
+}
+
+/// Encode values as concatenated CBOR-encoded wire values.
+fn cbor_response<T: ToWire>(values: Vec<T>) -> edenapi_trait::Result<Response<Body>> {
+    let mut data = Vec::new();
+    for value in values {
+        serde_cbor::to_writer(&mut data, &value.to_wire())
+            .map_err(|e| EdenApiError::Other(e.into()))?;
+    }
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/cbor")
+        .body(Body::from(data))
+        .expect("response should be valid"))
+}
+
+/// Encode values as concatenated CBOR values, without wire type conversion.
+fn raw_cbor_response<T: serde::Serialize>(values: Vec<T>) -> edenapi_trait::Result<Response<Body>> {
+    let mut data = Vec::new();
+    for value in values {
+        serde_cbor::to_writer(&mut data, &value).map_err(|e| EdenApiError::Other(e.into()))?;
+    }
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/cbor")
+        .body(Body::from(data))
+        .expect("response should be valid"))
+}
+
+fn json_response<T: serde::Serialize>(value: &T) -> edenapi_trait::Result<Response<Body>> {
+    let data = serde_json::to_vec(value).map_err(|e| EdenApiError::Other(e.into()))?;
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(data))
+        .expect("response should be valid"))
+}
+
+fn error_response(e: EdenApiError) -> Response<Body> {
+    let (status, message) = match e {
+        EdenApiError::HttpError { status, message } => (status, message),
+        EdenApiError::NotSupported => (StatusCode::NOT_IMPLEMENTED, e.to_string()),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e)),
+    };
+    debug!("error {}: {}", status, &message);
+    Response::builder()
+        .status(status)
+        .body(Body::from(message))
+        .expect("response should be valid")
+}
+
+/// Group history entries into chunks of consecutive entries with the same path.
+fn history_chunks(entries: Vec<HistoryEntry>) -> Vec<HistoryResponseChunk> {
+    let mut chunks: Vec<HistoryResponseChunk> = Vec::new();
+    for entry in entries {
+        let path = entry.key.path.clone();
+        let entry = WireHistoryEntry::from(entry);
+        match chunks.last_mut() {
+            Some(chunk) if chunk.path == path => chunk.entries.push(entry),
+            _ => chunks.push(HistoryResponseChunk::new(path, vec![entry])),
+        }
+    }
+    chunks
+}
+
+/// Parse the value of `name` in a URL query string like `a=1&b=2`.
+fn query_param<T: FromStr>(query: Option<&str>, name: &str) -> edenapi_trait::Result<Option<T>> {
+    let value = query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value);
+    match value {
+        None => Ok(None),
+        Some(value) => match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(bad_request_error(format!(
+                "invalid {} in query: {}",
+                name, value
+            ))),
+        },
+    }
+}
+
+/// Convert a hash range back to the hex prefix the client looked up.
+fn hash_lookup_prefix(req: &CommitHashLookupRequest) -> String {
+    let CommitHashLookupRequest::InclusiveRange(low, high) = req;
+    low.to_hex()
+        .chars()
+        .zip(high.to_hex().chars())
+        .take_while(|(l, h)| l == h)
+        .map(|(l, _)| l)
+        .collect()
+}
+
+fn bad_request_error(message: String) -> EdenApiError {
+    EdenApiError::HttpError {
+        status: StatusCode::BAD_REQUEST,
+        message,
+    }
+}
+
+fn not_found_error(path: &str) -> EdenApiError {
+    EdenApiError::HttpError {
+        status: StatusCode::NOT_FOUND,
+        message: format!("unknown endpoint: {}", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use edenapi::types::CommitLocation;
+    use edenapi::types::CommitLocationToHashRequest;
+    use edenapi::types::HgChangesetContent;
+    use edenapi::types::HgFilenodeData;
+    use edenapi::types::HgId;
+    use edenapi::types::Key;
+    use edenapi::types::Parents;
+    use edenapi::types::RepoPathBuf;
+    use edenapi::types::Sha1;
+    use edenapi::types::TreeAttributes;
+    use edenapi::types::UploadHgChangeset;
+    use edenapi::types::UploadTreeEntry;
+    use tempfile::tempdir;
+    use url::Url;
+
+    use super::*;
+
+    fn add_blob(repo: &mut EagerRepo, text: &[u8]) -> HgId {
+        let mut data = HgId::null_id().as_ref().repeat(2);
+        data.extend_from_slice(text);
+        repo.add_sha1_blob(&data).unwrap()
+    }
+
+    /// SHA1 of `data`, calculated by the content store.
+    fn sha1(data: &[u8]) -> HgId {
+        let dir = tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+        repo.add_sha1_blob(data).unwrap()
+    }
+
+    fn key(path: &str, hgid: HgId) -> Key {
+        Key::new(RepoPathBuf::from_string(path.to_string()).unwrap(), hgid)
+    }
+
+    /// Start a server for `dir` and return a client talking to it.
+    fn serve(dir: &Path) -> edenapi::Client {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let (addr, fut) = bind(dir, &addr).unwrap();
+        tokio::spawn(fut);
+        let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        edenapi::HttpClientBuilder::new()
+            .repo_name("test")
+            .server_url(url)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serve_over_http() {
+        let dir = tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+        let file = add_blob(&mut repo, b"content");
+        let tree = add_blob(&mut repo, format!("a\0{}\n", file.to_hex()).as_bytes());
+        let text = format!("{}\ntest\n0 0\na\n\nmessage", tree.to_hex());
+        let commit = repo.add_commit(&[], text.as_bytes()).await.unwrap();
+        repo.set_bookmark("master", Some(commit)).unwrap();
+        repo.flush().await.unwrap();
+
+        let client = serve(dir.path());
+        let repo_name = "test".to_string();
+        client.health().await.unwrap();
+
+        let caps = client.capabilities(repo_name.clone()).await.unwrap();
+        assert_eq!(caps, vec!["segmented-changelog".to_string()]);
+
+        let files = client
+            .files(repo_name.clone(), vec![key("a", file)])
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].data().unwrap().as_ref(), b"content");
+
+        let trees = client
+            .trees(
+                repo_name.clone(),
+                vec![key("", tree)],
+                Some(TreeAttributes::default()),
+            )
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].as_ref().unwrap().key.hgid, tree);
+
+        let history = client
+            .history(repo_name.clone(), vec![key("a", file)], None)
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].key, key("a", file));
+
+        let bookmarks = client
+            .bookmarks(
+                repo_name.clone(),
+                vec!["master".to_string(), "x".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(bookmarks[0].hgid, Some(commit));
+        assert_eq!(bookmarks[1].hgid, None);
+
+        let graph = client
+            .commit_graph(repo_name.clone(), vec![commit], vec![])
+            .await
+            .unwrap();
+        assert_eq!(graph.len(), 1);
+        assert_eq!(graph[0].hgid, commit);
+
+        let clone_data = client.clone_data(repo_name.clone()).await.unwrap();
+        assert_eq!(clone_data.idmap.values().collect::<Vec<_>>(), vec![&commit]);
+    }
+
+    #[tokio::test]
+    async fn test_push_and_pull() {
+        let dir = tempdir().unwrap();
+        let client = serve(dir.path());
+        let repo_name = "test".to_string();
+        let null = *HgId::null_id();
+
+        // Push a file, a tree and two commits, then move "master".
+        let content = Bytes::from_static(b"content\n");
+        let content_id = AnyFileContentId::Sha1(Sha1::from_slice(sha1(&content).as_ref()).unwrap());
+        let tokens: Vec<UploadToken> = client
+            .process_files_upload(
+                repo_name.clone(),
+                vec![(content_id, minibytes::Bytes::from(content.to_vec()))],
+                None,
+            )
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert_eq!(tokens.len(), 1);
+
+        let file = sha1(&[null.as_ref(), null.as_ref(), &content[..]].concat());
+        let filenode = HgFilenodeData {
+            node_id: file,
+            parents: Parents::None,
+            file_content_upload_token: tokens[0].clone(),
+            metadata: Vec::new(),
+        };
+        client
+            .upload_filenodes_batch(repo_name.clone(), vec![filenode])
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+
+        let tree_text = format!("a\0{}\n", file.to_hex()).into_bytes();
+        let tree = sha1(&[null.as_ref(), null.as_ref(), &tree_text].concat());
+        let tree_entry = UploadTreeEntry {
+            node_id: tree,
+            data: tree_text,
+            parents: Parents::None,
+        };
+        client
+            .upload_trees_batch(repo_name.clone(), vec![tree_entry])
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+
+        let changeset = |parents: Parents, message: &str| {
+            let text = format!("{}\ntest\n0 0\na\n\n{}", tree.to_hex(), message);
+            let (p1, p2) = match parents {
+                Parents::One(p1) => (p1, null),
+                _ => (null, null),
+            };
+            let (p1, p2) = (p1.min(p2), p1.max(p2));
+            let node_id = sha1(&[p1.as_ref(), p2.as_ref(), text.as_bytes()].concat());
+            let changeset_content = HgChangesetContent {
+                parents,
+                manifestid: tree,
+                user: b"test".to_vec(),
+                time: 0,
+                tz: 0,
+                extras: Vec::new(),
+                files: vec![RepoPathBuf::from_string("a".to_string()).unwrap()],
+                message: message.as_bytes().to_vec(),
+            };
+            UploadHgChangeset {
+                node_id,
+                changeset_content,
+            }
+        };
+        let changeset1 = changeset(Parents::None, "A");
+        let commit1 = changeset1.node_id;
+        let changeset2 = changeset(Parents::One(commit1), "B");
+        let commit2 = changeset2.node_id;
+        client
+            .upload_changesets(repo_name.clone(), vec![changeset1, changeset2], Vec::new())
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        client
+            .set_bookmark(
+                repo_name.clone(),
+                "master".to_string(),
+                Some(commit2),
+                None,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        // Pushed items are known.
+        let unknown = HgId::from_byte_array([1; 20]);
+        let known = client
+            .commit_known(repo_name.clone(), vec![commit1, commit2, unknown])
+            .await
+            .unwrap();
+        let known: Vec<bool> = known.into_iter().map(|k| k.known.unwrap()).collect();
+        assert_eq!(known, vec![true, true, false]);
+
+        // Moving the bookmark from a stale position is rejected.
+        assert!(client
+            .set_bookmark(
+                repo_name.clone(),
+                "master".to_string(),
+                Some(commit1),
+                None,
+                HashMap::new(),
+            )
+            .await
+            .is_err());
+
+        // Pull the pushed commits and their content.
+        let bookmarks = client
+            .bookmarks(repo_name.clone(), vec!["master".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(bookmarks[0].hgid, Some(commit2));
+
+        let clone_data = client.clone_data(repo_name.clone()).await.unwrap();
+        let mut ids: Vec<HgId> = clone_data.idmap.values().cloned().collect();
+        ids.sort();
+        let mut expected = vec![commit1, commit2];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        let location = CommitLocation {
+            descendant: commit2,
+            distance: 1,
+        };
+        let hashes = client
+            .commit_location_to_hash(
+                repo_name.clone(),
+                vec![CommitLocationToHashRequest {
+                    location: location.clone(),
+                    count: 1,
+                }],
+            )
+            .await
+            .unwrap();
+        assert_eq!(hashes[0].hgids, vec![commit1]);
+
+        let locations = client
+            .commit_hash_to_location(repo_name.clone(), vec![commit2], vec![commit1])
+            .await
+            .unwrap();
+        assert_eq!(locations[0].result.as_ref().unwrap(), &Some(location));
+
+        let prefix = commit1.to_hex()[..8].to_string();
+        let lookup = client
+            .hash_prefixes_lookup(repo_name.clone(), vec![prefix])
+            .await
+            .unwrap();
+        assert_eq!(lookup[0].hgids, vec![commit1]);
+
+        let revlog_data = client
+            .commit_revlog_data(repo_name.clone(), vec![commit2])
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert!(revlog_data[0].revlog_data.ends_with(b"\n\nB"));
+
+        let files = client
+            .files(repo_name.clone(), vec![key("a", file)])
+            .await
+            .unwrap()
+            .flatten()
+            .await
+            .unwrap();
+        assert_eq!(files[0].data().unwrap().as_ref(), &content[..]);
+
+        let downloaded = client
+            .download_file(repo_name.clone(), tokens[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(&downloaded[..], &content[..]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_endpoint() {
+        let dir = tempdir().unwrap();
+        let request = Request::post("/test/commit/unknown")
+            .body(Body::empty())
+            .unwrap();
+        let response = handle(dir.path(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -5,9 +5,17 @@
  * GNU General Public License version 2.
  */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::num::NonZeroU64;
 use std::sync::Arc;
+use std::time::Duration;
 
+use blake2::digest::Update;
+use blake2::digest::VariableOutput;
+use blake2::VarBlake2b;
 use configmodel::Config;
 use configmodel::ConfigExt;
 use dag::ops::DagAlgorithm;
@@ -21,25 +29,54 @@ use dag::Vertex;
 use dag::VertexName;
 use edenapi::configmodel;
 use edenapi::types::make_hash_lookup_request;
+use edenapi::types::AnyFileContentId;
+use edenapi::types::AnyId;
+use edenapi::types::BonsaiChangesetContent;
+use edenapi::types::BonsaiChangesetId;
+use edenapi::types::BonsaiFileChange;
 use edenapi::types::BookmarkEntry;
+use edenapi::types::CommitDiffEntry;
+use edenapi::types::CommitDiffRequest;
 use edenapi::types::CommitGraphEntry;
 use edenapi::types::CommitHashLookupResponse;
 use edenapi::types::CommitHashToLocationResponse;
+use edenapi::types::CommitId;
 use edenapi::types::CommitKnownResponse;
 use edenapi::types::CommitLocationToHashRequest;
 use edenapi::types::CommitLocationToHashResponse;
+use edenapi::types::CommitMutationsResponse;
 use edenapi::types::CommitRevlogData;
+use edenapi::types::ContentId;
+use edenapi::types::DiffStat;
+use edenapi::types::EphemeralPrepareResponse;
+use edenapi::types::Extra;
+use edenapi::types::FetchSnapshotRequest;
+use edenapi::types::FetchSnapshotResponse;
 use edenapi::types::FileContent;
+use edenapi::types::FileContentTokenMetadata;
+use edenapi::types::FileDiff;
 use edenapi::types::FileEntry;
 use edenapi::types::FileSpec;
+use edenapi::types::HgChangesetContent;
+use edenapi::types::HgFilenodeData;
 use edenapi::types::HgId;
+use edenapi::types::HgMutationEntryContent;
 use edenapi::types::HistoryEntry;
+use edenapi::types::IndexableId;
 use edenapi::types::Key;
+use edenapi::types::LandStackResponse;
+use edenapi::types::LookupResponse;
+use edenapi::types::LookupResult;
 use edenapi::types::NodeInfo;
 use edenapi::types::Parents;
 use edenapi::types::RepoPathBuf;
 use edenapi::types::TreeAttributes;
 use edenapi::types::TreeEntry;
+use edenapi::types::UploadHgChangeset;
+use edenapi::types::UploadToken;
+use edenapi::types::UploadTokensResponse;
+use edenapi::types::UploadTreeEntry;
+use edenapi::types::UploadTreeResponse;
 use edenapi::EdenApi;
 use edenapi::EdenApiError;
 use edenapi::Response;
@@ -50,10 +87,13 @@ use futures::stream::TryStreamExt;
 use futures::StreamExt;
 use http::StatusCode;
 use http::Version;
+use indexedlog::lock::ScopedDirLock;
+use minibytes::Bytes;
 use nonblocking::non_blocking_result;
 use tracing::debug;
 use tracing::trace;
 
+use crate::eager_repo::hg_sha1_text;
 use crate::EagerRepo;
 
 #[async_trait::async_trait]
@@ -440,10 +480,412 @@ impl EdenApi for EagerRepo {
             )
             .collect()
     }
+
+    async fn commit_diff(
+        &self,
+        _repo: String,
+        request: CommitDiffRequest,
+    ) -> edenapi::Result<Response<CommitDiffEntry>> {
+        debug!("commit_diff {:?} {:?}", request.from, request.to);
+        let from_files = self.get_commit_files_for_api(&request.from)?;
+        let to_files = self.get_commit_files_for_api(&request.to)?;
+        let paths: BTreeSet<&RepoPathBuf> = from_files
+            .keys()
+            .chain(to_files.keys())
+            .filter(|path| path_matches(path, &request.paths))
+            .collect();
+
+        // NOTE: Copies and renames are not detected. They show up as a
+        // removed and an added file.
+        let mut values = Vec::new();
+        let mut summary = DiffStat::default();
+        for path in paths {
+            let old = from_files.get(path);
+            let new = to_files.get(path);
+            if old == new {
+                continue;
+            }
+            let old_file = old
+                .map(|&(id, file_type)| self.get_diff_file_for_api(path, id, file_type))
+                .transpose()?;
+            let new_file = new
+                .map(|&(id, file_type)| self.get_diff_file_for_api(path, id, file_type))
+                .transpose()?;
+            let is_binary = xdiff::file_is_binary(&old_file) || xdiff::file_is_binary(&new_file);
+            let opts = xdiff::DiffOpts {
+                context: request.context as usize,
+                copy_info: xdiff::CopyInfo::None,
+            };
+            let raw_diff = xdiff::diff_unified(old_file, new_file, opts);
+            let stat = DiffStat::from_unified_diff(&raw_diff);
+            summary += stat;
+            let entry = FileDiff {
+                old_path: old.map(|_| path.clone()),
+                new_path: new.map(|_| path.clone()),
+                raw_diff: raw_diff.into(),
+                is_binary,
+                stat,
+            };
+            values.push(Ok(CommitDiffEntry::File(entry)));
+        }
+        values.push(Ok(CommitDiffEntry::Summary(summary)));
+        Ok(convert_to_response(values))
+    }
+
+    // The write APIs below, and the read APIs about uploaded data, serve each
+    // request using a fresh handle of the repo, and flush writes before
+    // returning, like a server would. So requests always see what earlier
+    // requests wrote.
+    //
+    // Bubbles are not isolated. Data uploaded to a bubble is visible to all
+    // requests, and never expires.
+
+    async fn set_bookmark(
+        &self,
+        _repo: String,
+        bookmark: String,
+        to: Option<HgId>,
+        from: Option<HgId>,
+        _pushvars: HashMap<String, String>,
+    ) -> edenapi::Result<()> {
+        debug!("set_bookmark {} {:?} {:?}", bookmark, to, from);
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let current = repo
+            .get_bookmarks_map()
+            .map_err(map_crate_err)?
+            .get(&bookmark)
+            .cloned();
+        if current != from {
+            return Err(conflict_error(format!(
+                "bookmark {} is at {:?}, not {:?}",
+                bookmark, current, from
+            )));
+        }
+        if let Some(id) = to {
+            repo.get_sha1_blob_for_api(id)?;
+        }
+        EagerRepo::set_bookmark(&mut repo, &bookmark, to).map_err(map_crate_err)?;
+        repo.flush_for_api().await
+    }
+
+    async fn land_stack(
+        &self,
+        _repo: String,
+        bookmark: String,
+        head: HgId,
+        base: HgId,
+        _pushvars: HashMap<String, String>,
+    ) -> edenapi::Result<LandStackResponse> {
+        debug!(
+            "land_stack {} {} {}",
+            bookmark,
+            head.to_hex(),
+            base.to_hex()
+        );
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let response = repo.land_stack_for_api(&bookmark, head, base).await?;
+        repo.flush_for_api().await?;
+        Ok(response)
+    }
+
+    async fn lookup_batch(
+        &self,
+        _repo: String,
+        items: Vec<AnyId>,
+        bubble_id: Option<NonZeroU64>,
+    ) -> edenapi::Result<Vec<LookupResponse>> {
+        debug!("lookup_batch {} items", items.len());
+        let repo = self.reopen_for_api()?;
+        let content_ids = repo.get_id_map(CONTENT_IDS_KEY).map_err(map_crate_err)?;
+        let snapshots = repo.get_id_map(SNAPSHOTS_KEY).map_err(map_crate_err)?;
+        let mut values = Vec::with_capacity(items.len());
+        for id in items {
+            let token = match &id {
+                AnyId::AnyFileContentId(content_id) => {
+                    match content_ids.get(&content_id_name(content_id)) {
+                        Some(&blob_id) => {
+                            let size = repo.get_sha1_blob_for_api(blob_id)?.len();
+                            Some(file_content_token(*content_id, bubble_id, size))
+                        }
+                        None => None,
+                    }
+                }
+                AnyId::HgFilenodeId(hgid) | AnyId::HgTreeId(hgid) | AnyId::HgChangesetId(hgid) => {
+                    repo.get_sha1_blob(*hgid)
+                        .map_err(map_crate_err)?
+                        .map(|_| UploadToken::new_fake_token(id.clone(), bubble_id))
+                }
+                AnyId::BonsaiChangesetId(cs_id) => snapshots
+                    .get(&cs_id.to_hex())
+                    .map(|_| UploadToken::new_fake_token(id.clone(), bubble_id)),
+            };
+            let result = match token {
+                Some(token) => LookupResult::Present(token),
+                None => LookupResult::NotPresent(IndexableId { id, bubble_id }),
+            };
+            values.push(LookupResponse { result });
+        }
+        Ok(values)
+    }
+
+    async fn process_files_upload(
+        &self,
+        _repo: String,
+        data: Vec<(AnyFileContentId, Bytes)>,
+        bubble_id: Option<NonZeroU64>,
+    ) -> edenapi::Result<Response<UploadToken>> {
+        debug!("process_files_upload {} files", data.len());
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let mut content_ids = repo.get_id_map(CONTENT_IDS_KEY).map_err(map_crate_err)?;
+        let mut values = Vec::with_capacity(data.len());
+        for (content_id, content) in data {
+            // The blob id is the SHA1 of the content.
+            let blob_id = repo.add_sha1_blob(&content).map_err(map_crate_err)?;
+            check_content_id(&content_id, &content, blob_id)?;
+            content_ids.insert(content_id_name(&content_id), blob_id);
+            let token = file_content_token(content_id, bubble_id, content.len());
+            values.push(Ok(token));
+        }
+        repo.set_id_map(CONTENT_IDS_KEY, content_ids)
+            .map_err(map_crate_err)?;
+        repo.flush_for_api().await?;
+        Ok(convert_to_response(values))
+    }
+
+    async fn upload_filenodes_batch(
+        &self,
+        _repo: String,
+        items: Vec<HgFilenodeData>,
+    ) -> edenapi::Result<Response<UploadTokensResponse>> {
+        debug!("upload_filenodes_batch {} items", items.len());
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let mut values = Vec::with_capacity(items.len());
+        for (index, item) in items.into_iter().enumerate() {
+            let content = match &item.file_content_upload_token.data.id {
+                AnyId::AnyFileContentId(content_id) => repo.get_file_content_for_api(content_id)?,
+                id => {
+                    return Err(bad_request_error(format!(
+                        "{:?} is not a file content token",
+                        id
+                    )));
+                }
+            };
+            let mut text = item.metadata;
+            text.extend_from_slice(&content);
+            repo.add_sha1_blob_for_api(item.node_id, item.parents, &text)?;
+            let token = UploadToken::new_fake_token(AnyId::HgFilenodeId(item.node_id), None);
+            values.push(Ok(UploadTokensResponse { index, token }));
+        }
+        repo.flush_for_api().await?;
+        Ok(convert_to_response(values))
+    }
+
+    async fn upload_trees_batch(
+        &self,
+        _repo: String,
+        items: Vec<UploadTreeEntry>,
+    ) -> edenapi::Result<Response<UploadTreeResponse>> {
+        debug!("upload_trees_batch {} items", items.len());
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let mut values = Vec::with_capacity(items.len());
+        for (index, item) in items.into_iter().enumerate() {
+            repo.add_sha1_blob_for_api(item.node_id, item.parents, &item.data)?;
+            let token = UploadToken::new_fake_token(AnyId::HgTreeId(item.node_id), None);
+            values.push(Ok(UploadTreeResponse { index, token }));
+        }
+        repo.flush_for_api().await?;
+        Ok(convert_to_response(values))
+    }
+
+    async fn upload_changesets(
+        &self,
+        _repo: String,
+        changesets: Vec<UploadHgChangeset>,
+        mutations: Vec<HgMutationEntryContent>,
+    ) -> edenapi::Result<Response<UploadTokensResponse>> {
+        debug!(
+            "upload_changesets {} changesets {} mutations",
+            changesets.len(),
+            mutations.len()
+        );
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let mut values = Vec::with_capacity(changesets.len());
+        // Changesets are sorted topologically by the client.
+        for (index, changeset) in changesets.into_iter().enumerate() {
+            let content = changeset.changeset_content;
+            let parents: Vec<HgId> = content.parents.clone().into_iter().collect();
+            let text = hg_changeset_text(&content);
+            let id = repo
+                .add_commit(&parents, &text)
+                .await
+                .map_err(map_crate_err)?;
+            if id != changeset.node_id {
+                return Err(bad_request_error(format!(
+                    "changeset {} has hash {}",
+                    changeset.node_id.to_hex(),
+                    id.to_hex()
+                )));
+            }
+            let token = UploadToken::new_fake_token(AnyId::HgChangesetId(id), None);
+            values.push(Ok(UploadTokensResponse { index, token }));
+        }
+        if !mutations.is_empty() {
+            let mut map = repo.get_id_map(MUTATIONS_KEY).map_err(map_crate_err)?;
+            for entry in mutations {
+                let data = serde_json::to_vec(&entry).map_err(|e| EdenApiError::Other(e.into()))?;
+                let blob_id = repo.add_sha1_blob(&data).map_err(map_crate_err)?;
+                map.insert(entry.successor.to_hex(), blob_id);
+            }
+            repo.set_id_map(MUTATIONS_KEY, map).map_err(map_crate_err)?;
+        }
+        repo.flush_for_api().await?;
+        Ok(convert_to_response(values))
+    }
+
+    async fn upload_bonsai_changeset(
+        &self,
+        _repo: String,
+        changeset: BonsaiChangesetContent,
+        bubble_id: Option<NonZeroU64>,
+    ) -> edenapi::Result<Response<UploadTokensResponse>> {
+        debug!("upload_bonsai_changeset");
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let content_ids = repo.get_id_map(CONTENT_IDS_KEY).map_err(map_crate_err)?;
+        for (path, change) in &changeset.file_changes {
+            let token = match change {
+                BonsaiFileChange::Change { upload_token, .. }
+                | BonsaiFileChange::UntrackedChange { upload_token, .. } => upload_token,
+                BonsaiFileChange::Deletion | BonsaiFileChange::UntrackedDeletion => continue,
+            };
+            let uploaded = match &token.data.id {
+                AnyId::AnyFileContentId(id) => content_ids.contains_key(&content_id_name(id)),
+                _ => false,
+            };
+            if !uploaded {
+                return Err(bad_request_error(format!(
+                    "content of {} was not uploaded",
+                    path
+                )));
+            }
+        }
+        let data = serde_json::to_vec(&changeset).map_err(|e| EdenApiError::Other(e.into()))?;
+        let cs_id = calc_bonsai_changeset_id(&data);
+        let blob_id = repo.add_sha1_blob(&data).map_err(map_crate_err)?;
+        let mut snapshots = repo.get_id_map(SNAPSHOTS_KEY).map_err(map_crate_err)?;
+        snapshots.insert(cs_id.to_hex(), blob_id);
+        repo.set_id_map(SNAPSHOTS_KEY, snapshots)
+            .map_err(map_crate_err)?;
+        repo.flush_for_api().await?;
+        let token = UploadToken::new_fake_token(AnyId::BonsaiChangesetId(cs_id), bubble_id);
+        Ok(convert_to_response(vec![Ok(UploadTokensResponse {
+            index: 0,
+            token,
+        })]))
+    }
+
+    async fn ephemeral_prepare(
+        &self,
+        _repo: String,
+        _custom_duration: Option<Duration>,
+    ) -> edenapi::Result<Response<EphemeralPrepareResponse>> {
+        debug!("ephemeral_prepare");
+        let (mut repo, _lock) = self.reopen_locked_for_api()?;
+        let last_id: u64 = match repo
+            .metalog()
+            .get(LAST_BUBBLE_ID_KEY)
+            .map_err(|e| EdenApiError::Other(e.into()))?
+        {
+            Some(data) => {
+                let text = String::from_utf8_lossy(&data);
+                text.trim().parse().map_err(|_| EdenApiError::HttpError {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    message: format!("invalid {}: {:?}", LAST_BUBBLE_ID_KEY, text),
+                })?
+            }
+            None => 0,
+        };
+        let bubble_id = NonZeroU64::new(last_id + 1).unwrap(); // unwrap: not zero
+        repo.metalog_mut()
+            .set(LAST_BUBBLE_ID_KEY, bubble_id.to_string().as_bytes())
+            .map_err(|e| EdenApiError::Other(e.into()))?;
+        repo.flush_for_api().await?;
+        Ok(convert_to_response(vec![Ok(EphemeralPrepareResponse {
+            bubble_id,
+        })]))
+    }
+
+    async fn fetch_snapshot(
+        &self,
+        _repo: String,
+        request: FetchSnapshotRequest,
+    ) -> edenapi::Result<Response<FetchSnapshotResponse>> {
+        debug!("fetch_snapshot {}", request.cs_id.to_hex());
+        let repo = self.reopen_for_api()?;
+        let snapshots = repo.get_id_map(SNAPSHOTS_KEY).map_err(map_crate_err)?;
+        let blob_id = match snapshots.get(&request.cs_id.to_hex()) {
+            Some(&id) => id,
+            None => {
+                return Err(EdenApiError::HttpError {
+                    status: StatusCode::NOT_FOUND,
+                    message: format!("snapshot {} cannot be found", request.cs_id.to_hex()),
+                });
+            }
+        };
+        let data = repo.get_sha1_blob_for_api(blob_id)?;
+        let changeset: BonsaiChangesetContent =
+            serde_json::from_slice(&data).map_err(|e| EdenApiError::Other(e.into()))?;
+        let response = FetchSnapshotResponse {
+            hg_parents: changeset.hg_parents,
+            file_changes: changeset.file_changes,
+            author: changeset.author,
+            time: changeset.time,
+            tz: changeset.tz,
+        };
+        Ok(convert_to_response(vec![Ok(response)]))
+    }
+
+    async fn download_file(&self, _repo: String, token: UploadToken) -> edenapi::Result<Bytes> {
+        debug!("download_file {:?}", token.data.id);
+        let repo = self.reopen_for_api()?;
+        match &token.data.id {
+            AnyId::AnyFileContentId(content_id) => repo.get_file_content_for_api(content_id),
+            id => Err(bad_request_error(format!(
+                "{:?} is not a file content token",
+                id
+            ))),
+        }
+    }
+
+    async fn commit_mutations(
+        &self,
+        _repo: String,
+        commits: Vec<HgId>,
+    ) -> edenapi::Result<Vec<CommitMutationsResponse>> {
+        debug!("commit_mutations {}", debug_hgid_list(&commits));
+        let repo = self.reopen_for_api()?;
+        let map = repo.get_id_map(MUTATIONS_KEY).map_err(map_crate_err)?;
+        // Include the mutation history of predecessors, too.
+        let mut values = Vec::new();
+        let mut visited: HashSet<HgId> = Default::default();
+        let mut to_visit: Vec<HgId> = commits;
+        while let Some(id) = to_visit.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(&blob_id) = map.get(&id.to_hex()) {
+                let data = repo.get_sha1_blob_for_api(blob_id)?;
+                let mutation: HgMutationEntryContent =
+                    serde_json::from_slice(&data).map_err(|e| EdenApiError::Other(e.into()))?;
+                to_visit.extend(mutation.predecessors.iter().cloned());
+                values.push(CommitMutationsResponse { mutation });
+            }
+        }
+        Ok(values)
+    }
 }
 
 impl EagerRepo {
-    fn get_sha1_blob_for_api(&self, id: HgId) -> edenapi::Result<minibytes::Bytes> {
+    fn get_sha1_blob_for_api(&self, id: HgId) -> edenapi::Result<Bytes> {
         // Emulate the HTTP errors.
         match self.get_sha1_blob(id) {
             Ok(None) => {
@@ -463,8 +905,373 @@ impl EagerRepo {
             }),
         }
     }
+
+    /// Open another handle of the repo to serve a request.
+    fn reopen_for_api(&self) -> edenapi::Result<EagerRepo> {
+        self.reopen().map_err(map_crate_err)
+    }
+
+    /// Open another handle of the repo to serve a request that changes it.
+    /// The returned lock serializes such requests, and should be held until
+    /// the changes are flushed.
+    fn reopen_locked_for_api(&self) -> edenapi::Result<(EagerRepo, ScopedDirLock)> {
+        let lock = self.lock().map_err(map_crate_err)?;
+        let repo = self.reopen_for_api()?;
+        Ok((repo, lock))
+    }
+
+    async fn flush_for_api(&mut self) -> edenapi::Result<()> {
+        self.flush().await.map_err(map_crate_err)
+    }
+
+    /// Insert a file or tree with the given parents. Check its hash.
+    fn add_sha1_blob_for_api(
+        &mut self,
+        id: HgId,
+        parents: Parents,
+        text: &[u8],
+    ) -> edenapi::Result<()> {
+        let (p1, p2) = parents.into_nodes();
+        let parents = [
+            Vertex::copy_from(p1.as_ref()),
+            Vertex::copy_from(p2.as_ref()),
+        ];
+        let data = hg_sha1_text(&parents, text);
+        let actual = self.add_sha1_blob(&data).map_err(map_crate_err)?;
+        if actual != id {
+            return Err(bad_request_error(format!(
+                "{} has hash {}",
+                id.to_hex(),
+                actual.to_hex()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read file content uploaded by `process_files_upload`.
+    fn get_file_content_for_api(&self, content_id: &AnyFileContentId) -> edenapi::Result<Bytes> {
+        let name = content_id_name(content_id);
+        match self
+            .get_id_map(CONTENT_IDS_KEY)
+            .map_err(map_crate_err)?
+            .get(&name)
+        {
+            Some(&blob_id) => self.get_sha1_blob_for_api(blob_id),
+            None => Err(EdenApiError::HttpError {
+                status: StatusCode::NOT_FOUND,
+                message: format!("{} cannot be found", name),
+            }),
+        }
+    }
+
+    /// Move `bookmark` to `head`, rebasing the linear stack between `base`
+    /// and `head` if the bookmark has moved past `base`.
+    ///
+    /// Like pushrebase, this fails if both the stack and the commits landed
+    /// after `base` changed the same file.
+    async fn land_stack_for_api(
+        &mut self,
+        bookmark: &str,
+        head: HgId,
+        base: HgId,
+    ) -> edenapi::Result<LandStackResponse> {
+        let onto = match self
+            .get_bookmarks_map()
+            .map_err(map_crate_err)?
+            .get(bookmark)
+        {
+            Some(&id) => id,
+            None => {
+                return Err(bad_request_error(format!(
+                    "bookmark {} does not exist",
+                    bookmark
+                )));
+            }
+        };
+        let base_vertex = Vertex::copy_from(base.as_ref());
+        let onto_vertex = Vertex::copy_from(onto.as_ref());
+        if !self
+            .dag()
+            .is_ancestor(base_vertex.clone(), onto_vertex)
+            .await
+            .map_err(map_dag_err)?
+        {
+            return Err(bad_request_error(format!(
+                "{} is not an ancestor of bookmark {}",
+                base.to_hex(),
+                bookmark
+            )));
+        }
+
+        // The stack, from the oldest commit.
+        let stack: Vec<HgId> = {
+            let heads = dag::Set::from_static_names(vec![Vertex::copy_from(head.as_ref())]);
+            let common = dag::Set::from_static_names(vec![base_vertex.clone()]);
+            let set = self.dag().only(heads, common).await.map_err(map_dag_err)?;
+            let set = self.dag().sort(&set).await.map_err(map_dag_err)?;
+            let vertexes: Vec<Vertex> = set
+                .iter_rev()
+                .await
+                .map_err(map_dag_err)?
+                .try_collect()
+                .await
+                .map_err(map_dag_err)?;
+            check_convert_to_hgid(vertexes.iter())?;
+            vertexes
+                .iter()
+                .map(|v| HgId::from_slice(v.as_ref()).unwrap()) // unwrap: checked above
+                .collect()
+        };
+        let mut parent = base_vertex;
+        for id in &stack {
+            let vertex = Vertex::copy_from(id.as_ref());
+            let parents = self
+                .dag()
+                .parent_names(vertex.clone())
+                .await
+                .map_err(map_dag_err)?;
+            if parents != [parent] {
+                return Err(bad_request_error(format!(
+                    "cannot land {}: only linear stacks can be landed",
+                    id.to_hex()
+                )));
+            }
+            parent = vertex;
+        }
+
+        let mut old_to_new_hgids = HashMap::new();
+        let new_head = if onto == base {
+            old_to_new_hgids.extend(stack.iter().map(|&id| (id, id)));
+            head
+        } else {
+            let mut old_parent_files = self.get_commit_files_for_api(&CommitId::Hg(base))?;
+            let mut new_files = self.get_commit_files_for_api(&CommitId::Hg(onto))?;
+            let landed_paths = changed_paths(&old_parent_files, &new_files);
+            let mut new_parent = onto;
+            for old in stack {
+                let files = self.get_commit_files_for_api(&CommitId::Hg(old))?;
+                for path in changed_paths(&old_parent_files, &files) {
+                    if landed_paths.contains(&path) {
+                        return Err(conflict_error(format!(
+                            "cannot land {}: {} was changed by bookmark {}",
+                            old.to_hex(),
+                            path,
+                            bookmark
+                        )));
+                    }
+                    match files.get(&path) {
+                        Some(&file) => new_files.insert(path, file),
+                        None => new_files.remove(&path),
+                    };
+                }
+                let parent_tree = self.get_commit_tree_for_api(new_parent)?;
+                let tree = self.add_tree_for_api(&new_files, parent_tree)?;
+                let mut text = extract_body(&self.get_sha1_blob_for_api(old)?).to_vec();
+                text[..HgId::hex_len()].copy_from_slice(tree.to_hex().as_bytes());
+                let new = self
+                    .add_commit(&[new_parent], &text)
+                    .await
+                    .map_err(map_crate_err)?;
+                old_to_new_hgids.insert(old, new);
+                new_parent = new;
+                old_parent_files = files;
+            }
+            new_parent
+        };
+        self.set_bookmark(bookmark, Some(new_head))
+            .map_err(map_crate_err)?;
+
+        Ok(LandStackResponse {
+            new_head,
+            old_to_new_hgids,
+        })
+    }
+
+    /// Read the root tree id of a commit.
+    fn get_commit_tree_for_api(&self, id: HgId) -> edenapi::Result<HgId> {
+        let data = self.get_sha1_blob_for_api(id)?;
+        let text = extract_body(&data);
+        text.get(..HgId::hex_len())
+            .and_then(|hex| HgId::from_hex(hex).ok())
+            .ok_or_else(|| malformed_error(id, "commit"))
+    }
+
+    /// Read all files of a commit as `path -> (filenode, file type)`.
+    fn get_commit_files_for_api(
+        &self,
+        commit: &CommitId,
+    ) -> edenapi::Result<BTreeMap<RepoPathBuf, (HgId, xdiff::FileType)>> {
+        let id = match commit {
+            CommitId::Hg(id) => *id,
+            CommitId::Bonsai(_) => {
+                return Err(not_implemented_error(
+                    "EagerRepo does not support bonsai commit ids".to_string(),
+                ));
+            }
+        };
+        let tree_id = self.get_commit_tree_for_api(id)?;
+        let mut files = BTreeMap::new();
+        self.collect_tree_files_for_api(tree_id, "", &mut files)?;
+        Ok(files)
+    }
+
+    /// Recursively collect files of a tree into `files`.
+    fn collect_tree_files_for_api(
+        &self,
+        tree_id: HgId,
+        prefix: &str,
+        files: &mut BTreeMap<RepoPathBuf, (HgId, xdiff::FileType)>,
+    ) -> edenapi::Result<()> {
+        for (name, (id, flag)) in self.read_tree_for_api(tree_id)? {
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let file_type = match flag.as_str() {
+                "t" => {
+                    self.collect_tree_files_for_api(id, &path, files)?;
+                    continue;
+                }
+                "" => xdiff::FileType::Regular,
+                "x" => xdiff::FileType::Executable,
+                "l" => xdiff::FileType::Symlink,
+                _ => return Err(malformed_error(tree_id, "tree")),
+            };
+            let path = RepoPathBuf::from_string(path).map_err(|e| EdenApiError::Other(e.into()))?;
+            files.insert(path, (id, file_type));
+        }
+        Ok(())
+    }
+
+    /// Read entries of a tree as `name -> (id, flag)`.
+    ///
+    /// Trees use the hg manifest format, one `name\0hex[flag]` entry per line,
+    /// with the `t` flag marking sub-trees.
+    fn read_tree_for_api(&self, tree_id: HgId) -> edenapi::Result<TreeEntries> {
+        let data = self.get_sha1_blob_for_api(tree_id)?;
+        let mut entries = TreeEntries::new();
+        for line in extract_body(&data).split(|&b| b == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let (name, rest) = match line.iter().position(|&b| b == 0) {
+                Some(pos) => (String::from_utf8_lossy(&line[..pos]), &line[pos + 1..]),
+                None => return Err(malformed_error(tree_id, "tree")),
+            };
+            if rest.len() < HgId::hex_len() {
+                return Err(malformed_error(tree_id, "tree"));
+            }
+            let (hex, flag) = rest.split_at(HgId::hex_len());
+            let id = HgId::from_hex(hex).map_err(|_| malformed_error(tree_id, "tree"))?;
+            let flag = String::from_utf8_lossy(flag).to_string();
+            entries.insert(name.to_string(), (id, flag));
+        }
+        Ok(entries)
+    }
+
+    /// Write trees for `files`, a `path -> (filenode, file type)` map.
+    /// `parent` is the root tree of the parent commit. Its sub-trees are
+    /// reused if unchanged. Return the root tree id.
+    fn add_tree_for_api(
+        &mut self,
+        files: &BTreeMap<RepoPathBuf, (HgId, xdiff::FileType)>,
+        parent: HgId,
+    ) -> edenapi::Result<HgId> {
+        let files: Vec<(&str, HgId, xdiff::FileType)> = files
+            .iter()
+            .map(|(path, &(id, file_type))| (path.as_str(), id, file_type))
+            .collect();
+        self.add_subtree_for_api(&files, parent)
+    }
+
+    fn add_subtree_for_api(
+        &mut self,
+        files: &[(&str, HgId, xdiff::FileType)],
+        parent: HgId,
+    ) -> edenapi::Result<HgId> {
+        let parent_entries = if parent.is_null() {
+            TreeEntries::new()
+        } else {
+            self.read_tree_for_api(parent)?
+        };
+        let mut entries = TreeEntries::new();
+        let mut i = 0;
+        while i < files.len() {
+            let (path, id, file_type) = files[i];
+            match path.split_once('/') {
+                None => {
+                    let flag = match file_type {
+                        xdiff::FileType::Regular => "",
+                        xdiff::FileType::Executable => "x",
+                        xdiff::FileType::Symlink => "l",
+                    };
+                    entries.insert(path.to_string(), (id, flag.to_string()));
+                    i += 1;
+                }
+                Some((dir, _)) => {
+                    // Paths are sorted. Files in `dir` are adjacent.
+                    let prefix = format!("{}/", dir);
+                    let mut sub_files = Vec::new();
+                    while let Some(&(path, id, file_type)) = files.get(i) {
+                        match path.strip_prefix(&prefix) {
+                            Some(sub_path) => sub_files.push((sub_path, id, file_type)),
+                            None => break,
+                        }
+                        i += 1;
+                    }
+                    let sub_parent = match parent_entries.get(dir) {
+                        Some((id, flag)) if flag == "t" => *id,
+                        _ => *HgId::null_id(),
+                    };
+                    let sub_id = self.add_subtree_for_api(&sub_files, sub_parent)?;
+                    entries.insert(dir.to_string(), (sub_id, "t".to_string()));
+                }
+            }
+        }
+        if !parent.is_null() && entries == parent_entries {
+            return Ok(parent);
+        }
+        let text: Vec<u8> = entries
+            .iter()
+            .flat_map(|(name, (id, flag))| {
+                format!("{}\0{}{}\n", name, id.to_hex(), flag).into_bytes()
+            })
+            .collect();
+        let data = hg_sha1_text(&[Vertex::copy_from(parent.as_ref())], &text);
+        self.add_sha1_blob(&data).map_err(map_crate_err)
+    }
+
+    fn get_diff_file_for_api(
+        &self,
+        path: &RepoPathBuf,
+        id: HgId,
+        file_type: xdiff::FileType,
+    ) -> edenapi::Result<xdiff::DiffFile<String, Vec<u8>>> {
+        let data = self.get_sha1_blob_for_api(id)?;
+        let content = strip_metadata(extract_body(&data)).to_vec();
+        Ok(xdiff::DiffFile::new(
+            path.as_str().to_string(),
+            content,
+            file_type,
+        ))
+    }
 }
 
+/// Metadata keys of server-side state used by the write APIs.
+///
+/// `contentids` maps uploaded file content ids, like `sha1/<hex>`, to the
+/// blobs of their content. `mutations` maps successors to serialized
+/// [`HgMutationEntryContent`]. `snapshots` maps bonsai changeset ids to
+/// serialized [`BonsaiChangesetContent`].
+const CONTENT_IDS_KEY: &str = "contentids";
+const MUTATIONS_KEY: &str = "mutations";
+const SNAPSHOTS_KEY: &str = "snapshots";
+const LAST_BUBBLE_ID_KEY: &str = "lastbubbleid";
+
+/// Entries of a tree, as `name -> (id, flag)`.
+type TreeEntries = BTreeMap<String, (HgId, String)>;
+
 /// Optionally build `EdenApi` from config.
 ///
 /// If the config does not specify eagerepo-based `EdenApi`, return `Ok(None)`.
@@ -532,6 +1339,142 @@ fn extract_rename(data: &[u8]) -> Option<Key> {
     None
 }
 
+/// Strip the filelog metadata header (if any) from file data.
+/// data is not prefixed by hashes.
+fn strip_metadata(data: &[u8]) -> &[u8] {
+    if data.starts_with(b"\x01\n") {
+        if let Some(pos) = data[2..].windows(2).position(|needle| needle == b"\x01\n") {
+            return &data[pos + 4..];
+        }
+    }
+    data
+}
+
+/// Test if `path` is one of, or is under one of, `prefixes`.
+/// An empty `prefixes` matches everything.
+fn path_matches(path: &RepoPathBuf, prefixes: &[RepoPathBuf]) -> bool {
+    prefixes.is_empty()
+        || prefixes.iter().any(|prefix| {
+            let (path, prefix) = (path.as_str(), prefix.as_str());
+            prefix.is_empty()
+                || path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .map_or(false, |rest| rest.starts_with('/'))
+        })
+}
+
+/// Paths that differ between two `path -> (filenode, file type)` maps.
+fn changed_paths(
+    old: &BTreeMap<RepoPathBuf, (HgId, xdiff::FileType)>,
+    new: &BTreeMap<RepoPathBuf, (HgId, xdiff::FileType)>,
+) -> BTreeSet<RepoPathBuf> {
+    old.keys()
+        .chain(new.keys())
+        .filter(|path| old.get(*path) != new.get(*path))
+        .cloned()
+        .collect()
+}
+
+/// Convert a changeset to the hg commit text format.
+///
+/// See `changelog.py:changelog.add`.
+fn hg_changeset_text(content: &HgChangesetContent) -> Vec<u8> {
+    let mut text = Vec::new();
+    text.extend_from_slice(content.manifestid.to_hex().as_bytes());
+    text.push(b'\n');
+    text.extend_from_slice(&content.user);
+    text.push(b'\n');
+    text.extend_from_slice(format!("{} {}", content.time, content.tz).as_bytes());
+    if !content.extras.is_empty() {
+        let mut extras: Vec<&Extra> = content.extras.iter().collect();
+        extras.sort_by(|a, b| a.key.cmp(&b.key));
+        text.push(b' ');
+        for (i, extra) in extras.into_iter().enumerate() {
+            if i > 0 {
+                text.push(0);
+            }
+            let mut item = extra.key.clone();
+            item.push(b':');
+            item.extend_from_slice(&extra.value);
+            // See `changelog.py:_string_escape`.
+            for &b in &item {
+                match b {
+                    b'\\' => text.extend_from_slice(b"\\\\"),
+                    b'\n' => text.extend_from_slice(b"\\n"),
+                    b'\r' => text.extend_from_slice(b"\\r"),
+                    0 => text.extend_from_slice(b"\\0"),
+                    b => text.push(b),
+                }
+            }
+        }
+    }
+    let mut files: Vec<&str> = content.files.iter().map(|p| p.as_str()).collect();
+    files.sort_unstable();
+    for file in files {
+        text.push(b'\n');
+        text.extend_from_slice(file.as_bytes());
+    }
+    text.extend_from_slice(b"\n\n");
+    text.extend_from_slice(&content.message);
+    text
+}
+
+/// Name of a file content id in the `contentids` metadata.
+fn content_id_name(id: &AnyFileContentId) -> String {
+    match id {
+        AnyFileContentId::ContentId(id) => format!("content_id/{}", id.to_hex()),
+        AnyFileContentId::Sha1(id) => format!("sha1/{}", id.to_hex()),
+        AnyFileContentId::Sha256(id) => format!("sha256/{}", id.to_hex()),
+    }
+}
+
+/// Check that `content`, with SHA1 `sha1`, matches `id`.
+/// SHA256 is not checked.
+fn check_content_id(id: &AnyFileContentId, content: &[u8], sha1: HgId) -> edenapi::Result<()> {
+    let matches = match id {
+        AnyFileContentId::ContentId(id) => *id == ContentId::from(calc_blake2(b"content", content)),
+        AnyFileContentId::Sha1(id) => id.as_ref() == sha1.as_ref(),
+        AnyFileContentId::Sha256(_) => true,
+    };
+    if matches {
+        Ok(())
+    } else {
+        Err(bad_request_error(format!(
+            "content does not match {}",
+            content_id_name(id)
+        )))
+    }
+}
+
+fn calc_bonsai_changeset_id(data: &[u8]) -> BonsaiChangesetId {
+    BonsaiChangesetId::from(calc_blake2(b"changeset", data))
+}
+
+/// Keyed Blake2b hash, as used by Mononoke for content and changeset ids.
+fn calc_blake2(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut hash = VarBlake2b::new_keyed(key, 32);
+    hash.update(data);
+    let mut result = [0u8; 32];
+    hash.finalize_variable(|res| result.copy_from_slice(res));
+    result
+}
+
+fn file_content_token(
+    id: AnyFileContentId,
+    bubble_id: Option<NonZeroU64>,
+    size: usize,
+) -> UploadToken {
+    let metadata = FileContentTokenMetadata {
+        content_size: size as u64,
+    };
+    UploadToken::new_fake_token_with_metadata(
+        AnyId::AnyFileContentId(id),
+        bubble_id,
+        metadata.into(),
+    )
+}
+
 /// Convert `Vec<T>` to `Response<T>`.
 fn convert_to_response<T: Send + Sync + 'static>(values: Vec<edenapi::Result<T>>) -> Response<T> {
     Response {
@@ -548,6 +1491,24 @@ fn not_implemented_error(message: String) -> EdenApiError {
     }
 }
 
+fn bad_request_error(message: String) -> EdenApiError {
+    EdenApiError::HttpError {
+        status: StatusCode::BAD_REQUEST,
+        message,
+    }
+}
+
+fn conflict_error(message: String) -> EdenApiError {
+    EdenApiError::HttpError {
+        status: StatusCode::CONFLICT,
+        message,
+    }
+}
+
+fn malformed_error(id: HgId, kind: &str) -> EdenApiError {
+    EdenApiError::Other(anyhow::format_err!("{} {} is malformed", kind, id.to_hex()))
+}
+
 fn check_convert_to_hgid<'a>(vertexes: impl Iterator<Item = &'a Vertex>) -> edenapi::Result<()> {
     for v in vertexes {
         let _ = HgId::from_slice(v.as_ref()).map_err(|e| EdenApiError::Other(e.into()))?;
@@ -608,3 +1569,522 @@ fn debug_list<T>(keys: &[T], func: impl Fn(&T) -> String) -> String {
         msg
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_blob(repo: &mut EagerRepo, text: &[u8]) -> HgId {
+        let mut data = HgId::null_id().as_ref().repeat(2);
+        data.extend_from_slice(text);
+        repo.add_sha1_blob(&data).unwrap()
+    }
+
+    fn add_tree(repo: &mut EagerRepo, entries: &[(&str, HgId, &str)]) -> HgId {
+        let text: Vec<u8> = entries
+            .iter()
+            .flat_map(|(name, id, flag)| {
+                format!("{}\0{}{}\n", name, id.to_hex(), flag).into_bytes()
+            })
+            .collect();
+        add_blob(repo, &text)
+    }
+
+    async fn add_commit(repo: &mut EagerRepo, parents: &[HgId], tree: HgId, message: &str) -> HgId {
+        let text = format!("{}\ntest\n0 0\n\n{}", tree.to_hex(), message);
+        repo.add_commit(parents, text.as_bytes()).await.unwrap()
+    }
+
+    /// SHA1 of `data`, calculated by the content store.
+    fn sha1(data: &[u8]) -> HgId {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+        repo.add_sha1_blob(data).unwrap()
+    }
+
+    fn files_of(repo: &EagerRepo, commit: HgId) -> Vec<(String, HgId)> {
+        repo.get_commit_files_for_api(&CommitId::Hg(commit))
+            .unwrap()
+            .into_iter()
+            .map(|(path, (id, _))| (path.to_string(), id))
+            .collect()
+    }
+
+    async fn diff(repo: &EagerRepo, from: HgId, to: HgId, paths: &[&str]) -> Vec<CommitDiffEntry> {
+        let request = CommitDiffRequest {
+            from: CommitId::Hg(from),
+            to: CommitId::Hg(to),
+            paths: paths
+                .iter()
+                .map(|p| RepoPathBuf::from_string(p.to_string()).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+        repo.commit_diff("repo".to_string(), request)
+            .await
+            .unwrap()
+            .entries
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commit_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+
+        let a1 = add_blob(&mut repo, b"1\n2\n");
+        let a2 = add_blob(&mut repo, b"1\n3\n");
+        let b = add_blob(&mut repo, b"x\ny\n");
+        let dir1 = add_tree(&mut repo, &[("b", b, "")]);
+        let tree1 = add_tree(&mut repo, &[("a", a1, ""), ("dir", dir1, "t")]);
+        let tree2 = add_tree(&mut repo, &[("a", a2, ""), ("c", b, "x")]);
+        let commit1 = add_commit(&mut repo, &[], tree1, "message").await;
+        let commit2 = add_commit(&mut repo, &[], tree2, "message").await;
+
+        let entries = diff(&repo, commit1, commit2, &[]).await;
+        let files: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CommitDiffEntry::File(file) => Some(file),
+                CommitDiffEntry::Summary(_) => None,
+            })
+            .collect();
+        let paths: Vec<_> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.old_path.as_ref().map(|p| p.to_string()),
+                    file.new_path.as_ref().map(|p| p.to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (Some("a".to_string()), Some("a".to_string())),
+                (None, Some("c".to_string())),
+                (Some("dir/b".to_string()), None),
+            ]
+        );
+        let raw_diff = String::from_utf8_lossy(&files[0].raw_diff).to_string();
+        assert!(raw_diff.contains("-2\n+3\n"), "{}", raw_diff);
+        assert_eq!(
+            entries.last(),
+            Some(&CommitDiffEntry::Summary(DiffStat {
+                files_changed: 3,
+                insertions: 3,
+                deletions: 3,
+            }))
+        );
+
+        let entries = diff(&repo, commit1, commit2, &["dir"]).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries.last(),
+            Some(&CommitDiffEntry::Summary(DiffStat {
+                files_changed: 1,
+                insertions: 0,
+                deletions: 2,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_files_and_trees() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        let repo_name = "repo".to_string();
+
+        let content = Bytes::from_static(b"foo\n");
+        let content_id = AnyFileContentId::ContentId(calc_blake2(b"content", &content).into());
+        let lookup = |id: AnyId| repo.lookup_batch(repo_name.clone(), vec![id], None);
+        let result = lookup(AnyId::AnyFileContentId(content_id)).await.unwrap();
+        assert!(matches!(result[0].result, LookupResult::NotPresent(_)));
+
+        let tokens: Vec<UploadToken> = repo
+            .process_files_upload(repo_name.clone(), vec![(content_id, content.clone())], None)
+            .await
+            .unwrap()
+            .entries
+            .try_collect()
+            .await
+            .unwrap();
+        let result = lookup(AnyId::AnyFileContentId(content_id)).await.unwrap();
+        assert_eq!(result[0].result, LookupResult::Present(tokens[0].clone()));
+        let downloaded = repo
+            .download_file(repo_name.clone(), tokens[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(downloaded, content);
+
+        // Mismatched content id.
+        let wrong_id = AnyFileContentId::Sha1(Default::default());
+        assert!(repo
+            .process_files_upload(repo_name.clone(), vec![(wrong_id, content.clone())], None)
+            .await
+            .is_err());
+
+        let null = *HgId::null_id();
+        let mut text = null.as_ref().repeat(2);
+        text.extend_from_slice(&content);
+        let filenode_id = sha1(&text);
+        let filenode = HgFilenodeData {
+            node_id: filenode_id,
+            parents: Parents::None,
+            file_content_upload_token: tokens[0].clone(),
+            metadata: Vec::new(),
+        };
+        repo.upload_filenodes_batch(repo_name.clone(), vec![filenode])
+            .await
+            .unwrap();
+
+        let tree_text = format!("a\0{}\n", filenode_id.to_hex()).into_bytes();
+        let mut text = null.as_ref().repeat(2);
+        text.extend_from_slice(&tree_text);
+        let tree_id = sha1(&text);
+        let tree = UploadTreeEntry {
+            node_id: tree_id,
+            data: tree_text.clone(),
+            parents: Parents::None,
+        };
+        repo.upload_trees_batch(repo_name.clone(), vec![tree.clone()])
+            .await
+            .unwrap();
+        let result = lookup(AnyId::HgTreeId(tree_id)).await.unwrap();
+        assert!(matches!(result[0].result, LookupResult::Present(_)));
+
+        // Mismatched hash.
+        let tree = UploadTreeEntry {
+            node_id: filenode_id,
+            ..tree
+        };
+        assert!(repo
+            .upload_trees_batch(repo_name.clone(), vec![tree])
+            .await
+            .is_err());
+
+        // Uploaded data is readable by other handles.
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        assert_eq!(
+            repo.get_sha1_blob(filenode_id).unwrap().as_deref(),
+            Some(&[null.as_ref(), null.as_ref(), &b"foo\n"[..]].concat()[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_changesets_and_mutations() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        let repo_name = "repo".to_string();
+
+        let make_changeset = |parents: Parents, message: &str| {
+            let content = HgChangesetContent {
+                parents,
+                manifestid: *HgId::null_id(),
+                user: b"test".to_vec(),
+                time: 0,
+                tz: 0,
+                extras: vec![Extra {
+                    key: b"branch".to_vec(),
+                    value: b"default".to_vec(),
+                }],
+                files: vec![RepoPathBuf::from_string("b".to_string()).unwrap()],
+                message: message.as_bytes().to_vec(),
+            };
+            let parent_vertexes: Vec<Vertex> = content
+                .parents
+                .clone()
+                .into_iter()
+                .map(|p| Vertex::copy_from(p.as_ref()))
+                .collect();
+            let text = hg_changeset_text(&content);
+            assert_eq!(
+                text,
+                format!(
+                    "{}\ntest\n0 0 branch:default\nb\n\n{}",
+                    HgId::null_id().to_hex(),
+                    message
+                )
+                .into_bytes()
+            );
+            UploadHgChangeset {
+                node_id: sha1(&hg_sha1_text(&parent_vertexes, &text)),
+                changeset_content: content,
+            }
+        };
+
+        let changeset1 = make_changeset(Parents::None, "A");
+        let id1 = changeset1.node_id;
+        let changeset2 = make_changeset(Parents::One(id1), "B");
+        let id2 = changeset2.node_id;
+        let changeset3 = make_changeset(Parents::None, "C");
+        let id3 = changeset3.node_id;
+        let mutation = |successor: HgId, predecessor: HgId| HgMutationEntryContent {
+            successor,
+            predecessors: vec![predecessor],
+            op: "amend".to_string(),
+            ..Default::default()
+        };
+        repo.upload_changesets(
+            repo_name.clone(),
+            vec![changeset1, changeset2, changeset3],
+            vec![mutation(id2, id1), mutation(id3, id2)],
+        )
+        .await
+        .unwrap();
+
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        let parents = repo
+            .dag()
+            .parent_names(Vertex::copy_from(id2.as_ref()))
+            .await
+            .unwrap();
+        assert_eq!(parents, vec![Vertex::copy_from(id1.as_ref())]);
+
+        // Mutations of predecessors are included.
+        let mutations = repo
+            .commit_mutations(repo_name.clone(), vec![id3])
+            .await
+            .unwrap();
+        let successors: Vec<HgId> = mutations.iter().map(|m| m.mutation.successor).collect();
+        assert_eq!(successors, vec![id3, id2]);
+
+        // Hash mismatch.
+        let mut changeset = make_changeset(Parents::None, "D");
+        changeset.node_id = id1;
+        assert!(repo
+            .upload_changesets(repo_name.clone(), vec![changeset], Vec::new())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_bookmark_and_land_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+        let repo_name = "repo".to_string();
+
+        let a1 = add_blob(&mut repo, b"a1");
+        let a2 = add_blob(&mut repo, b"a2");
+        let b1 = add_blob(&mut repo, b"b1");
+        let b2 = add_blob(&mut repo, b"b2");
+        let c = add_blob(&mut repo, b"c");
+        let dir1 = add_tree(&mut repo, &[("c", c, "")]);
+        let tree_base = add_tree(&mut repo, &[("a", a1, ""), ("b", b1, ""), ("d", dir1, "t")]);
+        let tree_landed = add_tree(&mut repo, &[("a", a2, ""), ("b", b1, ""), ("d", dir1, "t")]);
+        let tree_stack1 = add_tree(&mut repo, &[("a", a1, ""), ("b", b2, ""), ("d", dir1, "t")]);
+        let tree_stack2 = add_tree(&mut repo, &[("a", a1, ""), ("b", b2, "")]);
+        let tree_conflict = add_tree(&mut repo, &[("a", a2, ""), ("b", b1, "")]);
+        let base = add_commit(&mut repo, &[], tree_base, "base").await;
+        let landed = add_commit(&mut repo, &[base], tree_landed, "landed").await;
+        let stack1 = add_commit(&mut repo, &[base], tree_stack1, "stack1").await;
+        let stack2 = add_commit(&mut repo, &[stack1], tree_stack2, "stack2").await;
+        let conflict = add_commit(&mut repo, &[base], tree_conflict, "conflict").await;
+        repo.flush().await.unwrap();
+
+        let set_bookmark = |to: Option<HgId>, from: Option<HgId>| {
+            repo.set_bookmark(
+                repo_name.clone(),
+                "main".to_string(),
+                to,
+                from,
+                Default::default(),
+            )
+        };
+        set_bookmark(Some(base), None).await.unwrap();
+        assert!(set_bookmark(Some(landed), None).await.is_err());
+        set_bookmark(Some(landed), Some(base)).await.unwrap();
+
+        let land = |head: HgId| {
+            repo.land_stack(
+                repo_name.clone(),
+                "main".to_string(),
+                head,
+                base,
+                Default::default(),
+            )
+        };
+        assert!(land(conflict).await.is_err());
+
+        let response = land(stack2).await.unwrap();
+        assert_eq!(response.old_to_new_hgids.len(), 2);
+        let new_stack1 = response.old_to_new_hgids[&stack1];
+        let new_stack2 = response.new_head;
+        assert_eq!(response.old_to_new_hgids[&stack2], new_stack2);
+
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        assert_eq!(repo.get_bookmarks_map().unwrap()["main"], new_stack2);
+        assert_eq!(
+            files_of(&repo, new_stack1),
+            vec![
+                ("a".to_string(), a2),
+                ("b".to_string(), b2),
+                ("d/c".to_string(), c),
+            ]
+        );
+        assert_eq!(
+            files_of(&repo, new_stack2),
+            vec![("a".to_string(), a2), ("b".to_string(), b2)]
+        );
+        // Unchanged trees are reused.
+        let tree = repo.get_commit_tree_for_api(new_stack1).unwrap();
+        assert_eq!(repo.read_tree_for_api(tree).unwrap()["d"].0, dir1);
+        let parents = repo
+            .dag()
+            .parent_names(Vertex::copy_from(new_stack1.as_ref()))
+            .await
+            .unwrap();
+        assert_eq!(parents, vec![Vertex::copy_from(landed.as_ref())]);
+
+        // Fast-forward.
+        let stack3 = {
+            let mut repo = EagerRepo::open(dir.path()).unwrap();
+            let id = add_commit(&mut repo, &[new_stack2], tree_stack2, "stack3").await;
+            repo.flush().await.unwrap();
+            id
+        };
+        let response = repo
+            .land_stack(
+                repo_name.clone(),
+                "main".to_string(),
+                stack3,
+                new_stack2,
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.new_head, stack3);
+        assert_eq!(response.old_to_new_hgids[&stack3], stack3);
+    }
+
+    #[tokio::test]
+    async fn test_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        let repo_name = "repo".to_string();
+
+        let bubble_id = |response: Response<EphemeralPrepareResponse>| async move {
+            let entries: Vec<_> = response.entries.try_collect().await.unwrap();
+            entries[0].bubble_id.get()
+        };
+        let response = repo.ephemeral_prepare(repo_name.clone(), None).await;
+        assert_eq!(bubble_id(response.unwrap()).await, 1);
+        let response = repo.ephemeral_prepare(repo_name.clone(), None).await;
+        assert_eq!(bubble_id(response.unwrap()).await, 2);
+
+        let content = Bytes::from_static(b"untracked");
+        let content_id = AnyFileContentId::ContentId(calc_blake2(b"content", &content).into());
+        let mut tokens: Vec<UploadToken> = repo
+            .process_files_upload(repo_name.clone(), vec![(content_id, content)], None)
+            .await
+            .unwrap()
+            .entries
+            .try_collect()
+            .await
+            .unwrap();
+        let mut changeset = BonsaiChangesetContent {
+            hg_parents: Parents::None,
+            author: "test".to_string(),
+            time: 1,
+            tz: 0,
+            extra: Vec::new(),
+            file_changes: vec![(
+                RepoPathBuf::from_string("x".to_string()).unwrap(),
+                BonsaiFileChange::UntrackedChange {
+                    upload_token: tokens.remove(0),
+                    file_type: Default::default(),
+                },
+            )],
+            message: String::new(),
+            is_snapshot: true,
+        };
+        let responses: Vec<UploadTokensResponse> = repo
+            .upload_bonsai_changeset(repo_name.clone(), changeset.clone(), None)
+            .await
+            .unwrap()
+            .entries
+            .try_collect()
+            .await
+            .unwrap();
+        let cs_id = match responses[0].token.data.id {
+            AnyId::BonsaiChangesetId(id) => id,
+            ref id => panic!("unexpected id {:?}", id),
+        };
+
+        let snapshots: Vec<FetchSnapshotResponse> = repo
+            .fetch_snapshot(repo_name.clone(), FetchSnapshotRequest { cs_id })
+            .await
+            .unwrap()
+            .entries
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(snapshots[0].file_changes, changeset.file_changes);
+        assert_eq!(snapshots[0].time, 1);
+
+        // Changes must refer to uploaded content.
+        changeset.file_changes[0].1 = BonsaiFileChange::Change {
+            upload_token: UploadToken::new_fake_token(
+                AnyId::AnyFileContentId(Default::default()),
+                None,
+            ),
+            file_type: Default::default(),
+        };
+        assert!(repo
+            .upload_bonsai_changeset(repo_name.clone(), changeset, None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_prepare_corrupt_counter() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = EagerRepo::open(dir.path()).unwrap();
+        repo.metalog_mut().set(LAST_BUBBLE_ID_KEY, b"x").unwrap();
+        repo.flush().await.unwrap();
+
+        let repo = EagerRepo::open(dir.path()).unwrap();
+        assert!(repo
+            .ephemeral_prepare("repo".to_string(), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Arc::new(EagerRepo::open(dir.path()).unwrap());
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let repo = repo.clone();
+                tokio::spawn(async move {
+                    let content = Bytes::from(format!("content {}", i).into_bytes());
+                    let content_id =
+                        AnyFileContentId::ContentId(calc_blake2(b"content", &content).into());
+                    repo.process_files_upload(
+                        "repo".to_string(),
+                        vec![(content_id, content)],
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                    AnyId::AnyFileContentId(content_id)
+                })
+            })
+            .collect();
+        let ids = futures::future::try_join_all(tasks).await.unwrap();
+
+        // No upload is lost.
+        let result = repo
+            .lookup_batch("repo".to_string(), ids, None)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 8);
+        for response in result {
+            assert!(matches!(response.result, LookupResult::Present(_)));
+        }
+    }
+}
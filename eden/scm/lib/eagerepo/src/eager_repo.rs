@@ -16,6 +16,7 @@ use dag::Dag;
 use dag::Group;
 use dag::Vertex;
 use dag::VertexListWithOptions;
+use indexedlog::lock::ScopedDirLock;
 use metalog::CommitOptions;
 use metalog::MetaLog;
 use minibytes::Bytes;
@@ -64,9 +65,14 @@ use crate::Result;
 /// Format is made compatible with the Python code. Only bookmarks is
 /// implemented for now to support testing use-cases.
 ///
+/// Server-side state used by the EdenApi write APIs, like uploaded file
+/// contents and commit mutations, is also kept here, as maps from names to
+/// SHA1 blobs in the content store.
+///
 /// Currently backed by [`metalog::MetaLog`]. It's a lightweight source control
 /// for atomic metadata changes.
 pub struct EagerRepo {
+    dir: PathBuf,
     dag: Dag,
     store: Zstore,
     metalog: MetaLog,
@@ -76,11 +82,12 @@ impl EagerRepo {
     /// Open an [`EagerRepo`] at the given directory. Create an empty repo on demand.
     pub fn open(dir: &Path) -> Result<Self> {
         // Attempt to match directory layout of a real client repo.
-        let dir = dir.join(".hg/store");
-        let dag = Dag::open(dir.join("segments/v1"))?;
-        let store = Zstore::open(dir.join("hgcommits/v1"))?;
-        let metalog = MetaLog::open(dir.join("metalog"), None)?;
+        let store_dir = dir.join(".hg/store");
+        let dag = Dag::open(store_dir.join("segments/v1"))?;
+        let store = Zstore::open(store_dir.join("hgcommits/v1"))?;
+        let metalog = MetaLog::open(store_dir.join("metalog"), None)?;
         let repo = Self {
+            dir: dir.to_path_buf(),
             dag,
             store,
             metalog,
@@ -88,6 +95,25 @@ impl EagerRepo {
         Ok(repo)
     }
 
+    /// Open another handle of the same repo.
+    ///
+    /// The new handle sees changes flushed to disk, including those flushed
+    /// after this handle was opened. Pending changes of this handle are not
+    /// visible.
+    pub fn reopen(&self) -> Result<Self> {
+        Self::open(&self.dir)
+    }
+
+    /// Lock the repo for writing.
+    ///
+    /// Writers that read, modify and write back metadata should take the
+    /// lock, then `reopen` to see the latest state, and flush before
+    /// releasing the lock. Otherwise concurrent writers can lose each
+    /// other's changes.
+    pub fn lock(&self) -> Result<ScopedDirLock> {
+        Ok(ScopedDirLock::new(&self.dir.join(".hg/store"))?)
+    }
+
     /// Convert an URL to a directory path that can be passed to `open`.
     ///
     /// Supported URLs:
@@ -184,8 +210,18 @@ impl EagerRepo {
     /// Get bookmarks.
     pub fn get_bookmarks_map(&self) -> Result<BTreeMap<String, Id20>> {
         // Attempt to match the format used by a real client repo.
+        self.get_id_map("bookmarks")
+    }
+
+    /// Set bookmarks.
+    pub fn set_bookmarks_map(&mut self, map: BTreeMap<String, Id20>) -> Result<()> {
+        self.set_id_map("bookmarks", map)
+    }
+
+    /// Get a `name -> id` map stored as metadata `key`.
+    pub fn get_id_map(&self, key: &str) -> Result<BTreeMap<String, Id20>> {
         let text: String = {
-            let data = self.metalog.get("bookmarks")?;
+            let data = self.metalog.get(key)?;
             let opt_text = data.map(|b| String::from_utf8_lossy(&b).to_string());
             opt_text.unwrap_or_default()
         };
@@ -205,14 +241,15 @@ impl EagerRepo {
         Ok(map)
     }
 
-    /// Set bookmarks.
-    pub fn set_bookmarks_map(&mut self, map: BTreeMap<String, Id20>) -> Result<()> {
+    /// Set a `name -> id` map stored as metadata `key`.
+    /// Names must not contain whitespace.
+    pub fn set_id_map(&mut self, key: &str, map: BTreeMap<String, Id20>) -> Result<()> {
         let text = map
             .into_iter()
             .map(|(name, id)| format!("{} {}\n", id.to_hex(), name))
             .collect::<Vec<_>>()
             .concat();
-        self.metalog.set("bookmarks", text.as_bytes())?;
+        self.metalog.set(key, text.as_bytes())?;
         Ok(())
     }
 
@@ -225,10 +262,15 @@ impl EagerRepo {
     pub fn metalog(&self) -> &MetaLog {
         &self.metalog
     }
+
+    /// Obtain a mutable reference to the metalog.
+    pub fn metalog_mut(&mut self) -> &mut MetaLog {
+        &mut self.metalog
+    }
 }
 
 /// Convert parents and raw_text to HG SHA1 text format.
-fn hg_sha1_text(parents: &[Vertex], raw_text: &[u8]) -> Vec<u8> {
+pub(crate) fn hg_sha1_text(parents: &[Vertex], raw_text: &[u8]) -> Vec<u8> {
     fn null_id() -> Vertex {
         Vertex::copy_from(Id20::null_id().as_ref())
     }
@@ -35,6 +35,12 @@ impl From<zstore::Error> for Error {
     }
 }
 
+impl From<indexedlog::Error> for Error {
+    fn from(err: indexedlog::Error) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
 impl From<metalog::Error> for Error {
     fn from(err: metalog::Error) -> Self {
         anyhow::Error::from(err).into()
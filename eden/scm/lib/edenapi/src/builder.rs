@@ -15,6 +15,7 @@ use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Error;
 use auth::AuthSection;
+use auth::TokenProvider;
 use configmodel::convert::FromConfigValue;
 use configmodel::ConfigExt;
 use http_client::Encoding;
@@ -115,6 +116,7 @@ pub struct HttpClientBuilder {
     cert: Option<PathBuf>,
     key: Option<PathBuf>,
     ca_bundle: Option<PathBuf>,
+    token_provider: Option<Arc<TokenProvider>>,
     headers: HashMap<String, String>,
     max_requests: Option<usize>,
     max_files: Option<usize>,
@@ -161,7 +163,7 @@ impl HttpClientBuilder {
 
         let validate_certs =
             get_config::<bool>(config, "edenapi", "validate-certs")?.unwrap_or_default();
-        let (cert, key, ca_bundle) = AuthSection::from_config(config)
+        let auth = AuthSection::from_config(config)
             .best_match_for(&server_url)
             .or_else(|e| {
                 // If certificate validation is disabled, ignore errors here and make it appear as
@@ -173,7 +175,12 @@ impl HttpClientBuilder {
                     tracing::warn!("Ignoring missing client certificates: {}", &e);
                     Ok(None)
                 }
-            })?
+            })?;
+        let token_provider = auth
+            .as_ref()
+            .and_then(|auth| auth.token_source())
+            .map(|source| Arc::new(TokenProvider::new(source, server_url.clone())));
+        let (cert, key, ca_bundle) = auth
             .map(|auth| (auth.cert, auth.key, auth.cacerts))
             .unwrap_or_default();
         // Normally, this setting would be set globally for Mercurial elsewhere. However, when this
@@ -234,6 +241,7 @@ impl HttpClientBuilder {
             cert,
             key,
             ca_bundle,
+            token_provider,
             headers,
             max_requests,
             max_files,
@@ -289,6 +297,14 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Authenticate with bearer tokens from this provider. If the server
+    /// rejects a token, a new one is requested from the provider and the
+    /// request is retried.
+    pub fn token_provider(mut self, provider: Arc<TokenProvider>) -> Self {
+        self.token_provider = Some(provider);
+        self
+    }
+
     /// Extra HTTP headers that should be sent with each request.
     pub fn headers<T, K, V>(mut self, headers: T) -> Self
     where
@@ -419,6 +435,7 @@ pub(crate) struct Config {
     pub(crate) cert: Option<PathBuf>,
     pub(crate) key: Option<PathBuf>,
     pub(crate) ca_bundle: Option<PathBuf>,
+    pub(crate) token_provider: Option<Arc<TokenProvider>>,
     pub(crate) headers: HashMap<String, String>,
     pub(crate) max_requests: Option<usize>,
     pub(crate) max_files: Option<usize>,
@@ -448,6 +465,7 @@ impl TryFrom<HttpClientBuilder> for Config {
             cert,
             key,
             ca_bundle,
+            token_provider,
             headers,
             max_requests,
             max_files,
@@ -489,6 +507,7 @@ impl TryFrom<HttpClientBuilder> for Config {
             cert,
             key,
             ca_bundle,
+            token_provider,
             headers,
             max_requests,
             max_files,
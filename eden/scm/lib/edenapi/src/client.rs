@@ -25,6 +25,8 @@ use edenapi_types::BonsaiChangesetContent;
 use edenapi_types::BookmarkEntry;
 use edenapi_types::BookmarkRequest;
 use edenapi_types::CloneData;
+use edenapi_types::CommitDiffEntry;
+use edenapi_types::CommitDiffRequest;
 use edenapi_types::CommitGraphEntry;
 use edenapi_types::CommitGraphRequest;
 use edenapi_types::CommitHashLookupRequest;
@@ -134,6 +136,7 @@ mod paths {
     pub const COMMIT_HASH_LOOKUP: &str = "commit/hash_lookup";
     pub const COMMIT_GRAPH: &str = "commit/graph";
     pub const COMMIT_MUTATIONS: &str = "commit/mutations";
+    pub const COMMIT_DIFF: &str = "commit/diff";
     pub const BOOKMARKS: &str = "bookmarks";
     pub const SET_BOOKMARK: &str = "bookmarks/set";
     pub const LAND_STACK: &str = "land";
@@ -208,6 +211,8 @@ impl Client {
             req.set_header(k, v);
         }
 
+        self.set_bearer_token(&mut req)?;
+
         if let Some(ref correlator) = config.correlator {
             req.set_header("X-Client-Correlator", correlator);
         }
@@ -231,6 +236,15 @@ impl Client {
         Ok(req)
     }
 
+    /// Set the current bearer token on a request, if configured. `fetch_raw`
+    /// sets it again when sending, in case the token was refreshed.
+    fn set_bearer_token(&self, req: &mut Request) -> Result<(), EdenApiError> {
+        if let Some(provider) = &self.config().token_provider {
+            req.set_bearer_token(provider.token()?);
+        }
+        Ok(())
+    }
+
     /// Prepare a collection of POST requests for the given keys.
     /// The keys will be grouped into batches of the specified size and
     /// passed to the `make_req` callback, which should insert them into
@@ -268,17 +282,52 @@ impl Client {
     /// from different HTTP responses may be arbitrarily interleaved.
     fn fetch_raw<T: DeserializeOwned + Send + 'static>(
         &self,
-        requests: Vec<Request>,
+        mut requests: Vec<Request>,
     ) -> Result<Response<T>, EdenApiError> {
+        let token_provider = self.config().token_provider.clone();
+        let sent_token = match &token_provider {
+            Some(provider) => Some(provider.token()?),
+            None => None,
+        };
+        if let Some(token) = &sent_token {
+            for req in requests.iter_mut() {
+                req.set_bearer_token(token);
+            }
+        }
+
+        // Keep a copy of each request, to resend it once if the server
+        // rejects the bearer token.
+        let resends: Vec<Option<Request>> = requests
+            .iter()
+            .map(|req| sent_token.as_ref().map(|_| req.clone()))
+            .collect();
         let (responses, stats) = self.inner.client.send_async(requests)?;
 
         // Transform each response `Future` (which resolves when all of the HTTP
         // headers for that response have been received) into a `Stream` that
         // waits until all headers have been received and then starts yielding
         // entries. This allows multiplexing the streams using `select_all`.
-        let streams = responses.into_iter().map(|fut| {
+        let streams = responses.into_iter().zip(resends).map(|(fut, resend)| {
+            let client = self.inner.client.clone();
+            let token_provider = token_provider.clone();
+            let sent_token = sent_token.clone();
             stream::once(async move {
-                let res = raise_for_status(fut.await?).await?;
+                let mut res = fut.await?;
+                if let (Some(mut req), Some(provider), Some(rejected)) =
+                    (resend, token_provider, sent_token)
+                {
+                    // Resending only helps if there is a new token to try.
+                    if res.status().as_u16() == 401 && provider.refresh(&rejected)? {
+                        tracing::warn!("Retrying with refreshed bearer token");
+                        req.set_bearer_token(provider.token()?);
+                        // The resent request is not counted in `stats`.
+                        let (responses, _) = client.send_async(vec![req])?;
+                        if let Some(fut) = responses.into_iter().next() {
+                            res = fut.await?;
+                        }
+                    }
+                }
+                let res = raise_for_status(res).await?;
                 tracing::debug!("{:?}", ResponseMeta::from(&res));
                 Ok::<_, EdenApiError>(res.into_body().cbor::<T>().err_into())
             })
@@ -1214,6 +1263,28 @@ impl EdenApi for Client {
         self.fetch_vec_with_retry::<CommitMutationsResponse>(requests)
             .await
     }
+
+    async fn commit_diff(
+        &self,
+        repo: String,
+        request: CommitDiffRequest,
+    ) -> Result<Response<CommitDiffEntry>, EdenApiError> {
+        tracing::info!(
+            "Requesting diff of {:?} and {:?} for {} paths",
+            request.from,
+            request.to,
+            request.paths.len()
+        );
+        let url = self.build_url(paths::COMMIT_DIFF, Some(&repo))?;
+        self.log_request(&request, "commit_diff");
+        let req = request.to_wire();
+        let request = self
+            .configure_request(Request::post(url.clone()))?
+            .cbor(&req)
+            .map_err(EdenApiError::RequestSerializationFailed)?;
+
+        Ok(self.fetch::<CommitDiffEntry>(vec![request])?)
+    }
 }
 
 /// Split up a collection of keys into batches of at most `batch_size`.
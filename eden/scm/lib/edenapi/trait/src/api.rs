@@ -14,6 +14,8 @@ use edenapi_types::AnyId;
 use edenapi_types::BonsaiChangesetContent;
 use edenapi_types::BookmarkEntry;
 use edenapi_types::CloneData;
+use edenapi_types::CommitDiffEntry;
+use edenapi_types::CommitDiffRequest;
 use edenapi_types::CommitGraphEntry;
 use edenapi_types::CommitHashLookupResponse;
 use edenapi_types::CommitHashToLocationResponse;
@@ -314,4 +316,15 @@ pub trait EdenApi: Send + Sync + 'static {
         let _ = (repo, commits);
         Err(EdenApiError::NotSupported)
     }
+
+    /// Diff two commits, streaming a unified diff for each changed file
+    /// followed by a summary of the whole diff
+    async fn commit_diff(
+        &self,
+        repo: String,
+        request: CommitDiffRequest,
+    ) -> Result<Response<CommitDiffEntry>, EdenApiError> {
+        let _ = (repo, request);
+        Err(EdenApiError::NotSupported)
+    }
 }
@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::ops::AddAssign;
+
+use bytes::Bytes;
+#[cfg(any(test, feature = "for-tests"))]
+use quickcheck::Arbitrary;
+#[cfg(any(test, feature = "for-tests"))]
+use quickcheck::Gen;
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use type_macros::auto_wire;
+use types::HgId;
+use types::RepoPathBuf;
+
+use crate::BonsaiChangesetId;
+
+/// Default number of context lines around each hunk of a diff.
+pub const DEFAULT_DIFF_CONTEXT_LINES: u64 = 3;
+
+/// A commit to diff, either by its Mercurial or Bonsai hash.
+#[auto_wire]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum CommitId {
+    #[id(1)]
+    Hg(HgId),
+    #[id(2)]
+    Bonsai(BonsaiChangesetId),
+}
+
+impl Default for CommitId {
+    fn default() -> Self {
+        Self::Hg(*HgId::null_id())
+    }
+}
+
+/// Request the diff that turns the `from` commit into the `to` commit.
+#[auto_wire]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CommitDiffRequest {
+    #[id(1)]
+    pub from: CommitId,
+    #[id(2)]
+    pub to: CommitId,
+    /// Only diff files at or under these paths. If empty, the whole commits
+    /// are diffed.
+    #[id(3)]
+    pub paths: Vec<RepoPathBuf>,
+    /// Number of context lines around each hunk.
+    #[id(4)]
+    pub context: u64,
+}
+
+impl Default for CommitDiffRequest {
+    fn default() -> Self {
+        Self {
+            from: CommitId::default(),
+            to: CommitId::default(),
+            paths: Vec::new(),
+            context: DEFAULT_DIFF_CONTEXT_LINES,
+        }
+    }
+}
+
+/// Number of files changed and lines inserted and deleted by a diff.
+#[auto_wire]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DiffStat {
+    #[id(1)]
+    pub files_changed: u64,
+    #[id(2)]
+    pub insertions: u64,
+    #[id(3)]
+    pub deletions: u64,
+}
+
+impl DiffStat {
+    /// Compute the stat of a single file from its unified diff.
+    ///
+    /// Only lines inside hunks are counted, so the `---`/`+++` file headers
+    /// are not mistaken for a deletion and an insertion. Binary diffs have no
+    /// hunks and therefore count as a changed file with no changed lines.
+    pub fn from_unified_diff(raw_diff: &[u8]) -> Self {
+        let mut stat = DiffStat {
+            files_changed: 1,
+            ..Default::default()
+        };
+        let mut in_hunk = false;
+        for line in raw_diff.split(|&b| b == b'\n') {
+            if line.starts_with(b"@@") {
+                in_hunk = true;
+            } else if in_hunk {
+                match line.first() {
+                    Some(b'+') => stat.insertions += 1,
+                    Some(b'-') => stat.deletions += 1,
+                    _ => {}
+                }
+            }
+        }
+        stat
+    }
+}
+
+impl AddAssign for DiffStat {
+    fn add_assign(&mut self, other: Self) {
+        self.files_changed += other.files_changed;
+        self.insertions += other.insertions;
+        self.deletions += other.deletions;
+    }
+}
+
+/// Unified diff of a single file.
+#[auto_wire]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct FileDiff {
+    /// Path of the file in the `from` commit, if it exists there.
+    #[id(1)]
+    pub old_path: Option<RepoPathBuf>,
+    /// Path of the file in the `to` commit, if it exists there.
+    #[id(2)]
+    pub new_path: Option<RepoPathBuf>,
+    /// Diff in the extended (git) unified diff format.
+    #[id(3)]
+    pub raw_diff: Bytes,
+    /// One of the diffed files is binary, `raw_diff` contains just a
+    /// placeholder.
+    #[id(4)]
+    pub is_binary: bool,
+    #[id(5)]
+    pub stat: DiffStat,
+}
+
+/// An entry of a streamed commit diff. All `File` entries are sent first,
+/// followed by a single `Summary` covering all of them.
+#[auto_wire]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum CommitDiffEntry {
+    #[id(1)]
+    File(FileDiff),
+    #[id(2)]
+    Summary(DiffStat),
+}
+
+impl Default for CommitDiffEntry {
+    fn default() -> Self {
+        Self::Summary(DiffStat::default())
+    }
+}
+
+#[cfg(any(test, feature = "for-tests"))]
+impl Arbitrary for CommitId {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if Arbitrary::arbitrary(g) {
+            Self::Hg(Arbitrary::arbitrary(g))
+        } else {
+            Self::Bonsai(Arbitrary::arbitrary(g))
+        }
+    }
+}
+
+#[cfg(any(test, feature = "for-tests"))]
+impl Arbitrary for CommitDiffRequest {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            from: Arbitrary::arbitrary(g),
+            to: Arbitrary::arbitrary(g),
+            paths: Arbitrary::arbitrary(g),
+            context: Arbitrary::arbitrary(g),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "for-tests"))]
+impl Arbitrary for DiffStat {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self {
+            files_changed: Arbitrary::arbitrary(g),
+            insertions: Arbitrary::arbitrary(g),
+            deletions: Arbitrary::arbitrary(g),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "for-tests"))]
+impl Arbitrary for FileDiff {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let raw_diff: Vec<u8> = Arbitrary::arbitrary(g);
+        Self {
+            old_path: Arbitrary::arbitrary(g),
+            new_path: Arbitrary::arbitrary(g),
+            raw_diff: raw_diff.into(),
+            is_binary: Arbitrary::arbitrary(g),
+            stat: Arbitrary::arbitrary(g),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "for-tests"))]
+impl Arbitrary for CommitDiffEntry {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if Arbitrary::arbitrary(g) {
+            Self::File(Arbitrary::arbitrary(g))
+        } else {
+            Self::Summary(Arbitrary::arbitrary(g))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_stat_from_unified_diff() {
+        let raw_diff = b"diff --git a/a b/a\n--- a/a\n+++ b/a\n@@ -1,3 +1,3 @@\n a\n-b\n+c\n+d\n--\n";
+        assert_eq!(
+            DiffStat::from_unified_diff(raw_diff),
+            DiffStat {
+                files_changed: 1,
+                insertions: 2,
+                deletions: 2,
+            }
+        );
+
+        let binary = b"diff --git a/a b/a\nBinary file a has changed\n";
+        assert_eq!(
+            DiffStat::from_unified_diff(binary),
+            DiffStat {
+                files_changed: 1,
+                insertions: 0,
+                deletions: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_commit_diff_request_default_context() {
+        assert_eq!(
+            CommitDiffRequest::default().context,
+            DEFAULT_DIFF_CONTEXT_LINES
+        );
+    }
+
+    #[test]
+    fn test_diff_stat_add_assign() {
+        let mut total = DiffStat::default();
+        total += DiffStat {
+            files_changed: 1,
+            insertions: 3,
+            deletions: 0,
+        };
+        total += DiffStat {
+            files_changed: 1,
+            insertions: 1,
+            deletions: 4,
+        };
+        assert_eq!(
+            total,
+            DiffStat {
+                files_changed: 2,
+                insertions: 4,
+                deletions: 4,
+            }
+        );
+    }
+}
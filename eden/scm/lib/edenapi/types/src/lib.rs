@@ -30,6 +30,7 @@ pub mod anyid;
 pub mod batch;
 pub mod bookmark;
 pub mod commit;
+pub mod diff;
 pub mod errors;
 pub mod file;
 pub mod history;
@@ -93,6 +94,12 @@ pub use crate::commit::UploadBonsaiChangesetRequest;
 pub use crate::commit::UploadHgChangeset;
 pub use crate::commit::UploadHgChangesetsRequest;
 pub use crate::commit::UploadSnapshotResponse;
+pub use crate::diff::CommitDiffEntry;
+pub use crate::diff::CommitDiffRequest;
+pub use crate::diff::CommitId;
+pub use crate::diff::DiffStat;
+pub use crate::diff::FileDiff;
+pub use crate::diff::DEFAULT_DIFF_CONTEXT_LINES;
 pub use crate::errors::ServerError;
 pub use crate::file::FileAttributes;
 pub use crate::file::FileAuxData;
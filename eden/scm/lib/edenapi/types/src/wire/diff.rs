@@ -0,0 +1,26 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+pub use crate::diff::WireCommitDiffEntry;
+pub use crate::diff::WireCommitDiffRequest;
+pub use crate::diff::WireCommitId;
+pub use crate::diff::WireDiffStat;
+pub use crate::diff::WireFileDiff;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::tests::auto_wire_tests;
+
+    auto_wire_tests!(
+        WireCommitId,
+        WireCommitDiffRequest,
+        WireDiffStat,
+        WireFileDiff,
+        WireCommitDiffEntry,
+    );
+}
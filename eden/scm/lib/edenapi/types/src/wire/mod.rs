@@ -53,6 +53,7 @@ pub mod batch;
 pub mod bookmark;
 pub mod clone;
 pub mod commit;
+pub mod diff;
 pub mod errors;
 pub mod file;
 pub mod history;
@@ -112,6 +113,11 @@ pub use crate::wire::commit::WireHgMutationEntryContent;
 pub use crate::wire::commit::WireUploadBonsaiChangesetRequest;
 pub use crate::wire::commit::WireUploadHgChangeset;
 pub use crate::wire::commit::WireUploadHgChangesetsRequest;
+pub use crate::wire::diff::WireCommitDiffEntry;
+pub use crate::wire::diff::WireCommitDiffRequest;
+pub use crate::wire::diff::WireCommitId;
+pub use crate::wire::diff::WireDiffStat;
+pub use crate::wire::diff::WireFileDiff;
 pub use crate::wire::errors::WireError;
 pub use crate::wire::errors::WireResult;
 pub use crate::wire::file::WireFileEntry;
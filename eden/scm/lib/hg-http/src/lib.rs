@@ -24,6 +24,8 @@ use progress_model::IoTimeSeries;
 use progress_model::ProgressBar;
 use progress_model::Registry;
 
+pub use http_client::FixtureMode;
+
 #[derive(Default)]
 struct Total {
     download_bytes: AtomicUsize,
@@ -57,15 +59,17 @@ pub struct HgHttpConfig {
     pub client_info: Option<String>,
     pub unix_socket_path: Option<String>,
     pub unix_socket_domains: HashSet<String>,
+    pub fixture_mode: Option<FixtureMode>,
 }
 
 /// Set a global configuration that will be applied to all HTTP requests in
 /// Mercurial's Rust code.
-pub fn set_global_config(config: HgHttpConfig) {
+pub fn set_global_config(mut config: HgHttpConfig) {
     if config.disable_tls_verification {
         tracing::warn!("--insecure flag specified; server TLS certificate will not be verified");
     }
 
+    http_client::set_fixture_mode(config.fixture_mode.take());
 
     Request::on_new_request(move |req| {
         if let Some(domain) = req.ctx().url().domain() {
@@ -7,8 +7,10 @@
 
 use blackbox::event::Event;
 use blackbox::json;
+use blackbox::Entry;
 use blackbox::SessionId;
 use clidispatch::errors;
+use tracing_collector::TracingData;
 
 use super::define_flags;
 use super::Repo;
@@ -25,9 +27,12 @@ define_flags! {
         #[short('s')]
         session_id: i64,
 
-        /// output path (.txt, .json, .json.gz, .spans.json)
+        /// output path (.txt, .json, .json.gz, .spans.json, .folded)
         #[short('o')]
         output_path: String,
+
+        /// blackbox session id to compare span durations against
+        compare_session_id: i64,
     }
 }
 
@@ -50,6 +55,30 @@ pub fn run(opts: DumpTraceOpts, io: &IO, _repo: Repo) -> Result<u8> {
         blackbox.entries_by_session_ids(session_ids)
     };
 
+    let merged = load_tracing_data(entries);
+
+    if opts.compare_session_id != 0 {
+        let old = {
+            let blackbox = blackbox::SINGLETON.lock();
+            let session_ids = vec![SessionId(opts.compare_session_id as u64)];
+            load_tracing_data(blackbox.entries_by_session_ids(session_ids))
+        };
+        // Hide changes less than 1 millisecond.
+        let compared = merged.compare_ascii(&old, 1000);
+        if opts.output_path.is_empty() || opts.output_path == "-" {
+            io.write(compared)?;
+        } else {
+            std::fs::write(&opts.output_path, compared)?;
+        }
+        return Ok(0);
+    }
+
+    crate::run::write_trace(io, &opts.output_path, &merged)?;
+
+    Ok(0)
+}
+
+fn load_tracing_data(entries: Vec<Entry>) -> TracingData {
     let mut tracing_data_list = Vec::new();
     for entry in entries {
         if let Event::TracingData { serialized } = entry.data {
@@ -60,11 +89,7 @@ pub fn run(opts: DumpTraceOpts, io: &IO, _repo: Repo) -> Result<u8> {
             }
         }
     }
-    let merged = tracing_collector::TracingData::merge(tracing_data_list);
-
-    crate::run::write_trace(io, &opts.output_path, &merged)?;
-
-    Ok(0)
+    TracingData::merge(tracing_data_list)
 }
 
 pub fn name() -> &'static str {
@@ -0,0 +1,1142 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Native `log` and `log -G`.
+//!
+//! Covers revsets supported by the `revset` crate, `--limit`, merge, user
+//! and keyword filters, plain path filtering, the default and `-q` output,
+//! and templates using common keywords. Anything else, including
+//! `--follow` with paths, `--patch` and `--debug`, falls back to Python.
+//! Obsolete commits are not marked with `x` in the graph.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_runtime::block_on;
+use chrono::TimeZone;
+use clidispatch::errors;
+use configparser::config::ConfigSet;
+use dag::nameset::SyncNameSetQuery;
+use dag::ops::DagAlgorithm;
+use dag::ops::IdConvert;
+use dag::ops::PrefixLookup;
+use dag::render::Ancestor;
+use dag::render::GraphRowRenderer;
+use dag::render::Renderer;
+use dag::Set;
+use dag::Vertex;
+use hgcommits::HgCommits;
+use hgcommits::ReadCommitText;
+use metalog::MetaLog;
+use revset::Aliases;
+use revset::Evaluator;
+use revset::Expr;
+
+use super::define_flags;
+use super::Repo;
+use super::Result;
+use super::IO;
+use crate::commands::FormatterOpts;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct LogOpts {
+        /// follow changeset history, or file history across copies and renames
+        #[short('f')]
+        follow: bool,
+
+        /// only follow the first parent of merge changesets (DEPRECATED)
+        follow_first: bool,
+
+        /// show revisions matching date spec
+        #[short('d')]
+        date: String,
+
+        /// show copied files
+        #[short('C')]
+        copies: bool,
+
+        /// do case-insensitive search for a given text
+        #[short('k')]
+        keyword: Vec<String>,
+
+        /// show the specified revision or revset
+        #[short('r')]
+        rev: Vec<String>,
+
+        /// follow line range of specified file (EXPERIMENTAL)
+        #[short('L')]
+        line_range: Vec<String>,
+
+        /// include revisions where files were removed
+        removed: bool,
+
+        /// show only merges (DEPRECATED)
+        #[short('m')]
+        only_merges: bool,
+
+        /// revisions committed by user
+        #[short('u')]
+        user: Vec<String>,
+
+        /// show changesets within the given named branch
+        #[short('b')]
+        branch: Vec<String>,
+
+        /// do not display revision or any of its ancestors
+        #[short('P')]
+        prune: Vec<String>,
+
+        /// show patch
+        #[short('p')]
+        patch: bool,
+
+        /// use git extended diff format
+        #[short('g')]
+        git: bool,
+
+        /// limit number of changes displayed
+        #[short('l')]
+        limit: String,
+
+        /// do not show merges
+        #[short('M')]
+        no_merges: bool,
+
+        /// output diffstat-style summary of changes
+        stat: bool,
+
+        /// show the revision DAG
+        #[short('G')]
+        graph: bool,
+
+        /// display using template map file (DEPRECATED)
+        style: String,
+
+        /// shows all changesets in the repo
+        all: bool,
+
+        /// show remote names even if hidden
+        remote: bool,
+
+        /// limit to changesets affecting the sparse checkout
+        sparse: bool,
+
+        walk_opts: WalkOpts,
+        formatter_opts: FormatterOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+pub fn run(opts: LogOpts, io: &IO, repo: Repo) -> Result<u8> {
+    let config = repo.config();
+    if !config.get_or("experimental", "rust-log", || false)? {
+        return Err(errors::FallbackToPython.into());
+    }
+    let unsupported = [
+        (config.get_or("ui", "debug", || false)?, "--debug"),
+        (opts.follow_first, "--follow-first"),
+        (!opts.date.is_empty(), "--date"),
+        (opts.copies, "--copies"),
+        (!opts.line_range.is_empty(), "--line-range"),
+        (opts.removed, "--removed"),
+        (!opts.branch.is_empty(), "--branch"),
+        (!opts.prune.is_empty(), "--prune"),
+        (opts.patch, "--patch"),
+        (opts.git, "--git"),
+        (opts.stat, "--stat"),
+        (!opts.style.is_empty(), "--style"),
+        (opts.remote, "--remote"),
+        (opts.sparse, "--sparse"),
+        (!opts.walk_opts.include.is_empty(), "--include"),
+        (!opts.walk_opts.exclude.is_empty(), "--exclude"),
+        (config.get("ui", "logtemplate").is_some(), "ui.logtemplate"),
+        (config.get("ui", "style").is_some(), "ui.style"),
+        (
+            config.get("ui", "graphnodetemplate").is_some(),
+            "ui.graphnodetemplate",
+        ),
+        (
+            !is_segmented_changelog(repo.store_path())?,
+            "non-segmented changelog",
+        ),
+    ];
+    if let Some((_, reason)) = unsupported.iter().find(|(unsupported, _)| *unsupported) {
+        return Err(fallback(config, reason));
+    }
+
+    // tweakdefaults makes "log" follow unless revisions or --all are given.
+    let follow = opts.follow
+        || (extension_enabled(config, "tweakdefaults") && opts.rev.is_empty() && !opts.all);
+    if follow && !opts.args.is_empty() {
+        // Following file history needs copy tracing.
+        return Err(fallback(config, "--follow with paths"));
+    }
+
+    let limit = match opts.limit.as_str() {
+        "" => None,
+        limit => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => Some(limit),
+            _ => return Err(fallback(config, "--limit")),
+        },
+    };
+
+    let template = match opts.formatter_opts.template.as_str() {
+        "" => None,
+        spec => match parse_template(spec) {
+            Some(template) => Some(template),
+            None => return Err(fallback(config, "template")),
+        },
+    };
+
+    let cwd = std::env::current_dir()?;
+    let mut paths = Vec::with_capacity(opts.args.len());
+    for arg in &opts.args {
+        match repo_relative_path(repo.path(), &cwd, arg) {
+            Some(path) => paths.push(path),
+            None => return Err(fallback(config, "path")),
+        }
+    }
+
+    let filter = Filter {
+        keywords: opts.keyword.iter().map(|k| k.to_lowercase()).collect(),
+        users: opts.user.iter().map(|u| u.to_lowercase()).collect(),
+        paths,
+        no_merges: opts.no_merges,
+        only_merges: opts.only_merges,
+        limit,
+    };
+    if filter
+        .users
+        .iter()
+        .any(|u| u.starts_with("re:") || u.starts_with("literal:"))
+    {
+        return Err(fallback(config, "--user pattern"));
+    }
+
+    let format = Format {
+        template,
+        quiet: config.get_or("ui", "quiet", || false)?,
+        verbose: config.get_or("ui", "verbose", || false)?,
+        graph: opts.graph,
+    };
+
+    let log = Log::open(&repo)?;
+    let revs = match block_on(log.revs(config, &opts.rev, follow))? {
+        Some(revs) => revs,
+        None => return Err(fallback(config, "revset")),
+    };
+    block_on(log.show(io, config, revs, &filter, &format))?;
+
+    Ok(0)
+}
+
+pub fn name() -> &'static str {
+    "log|history"
+}
+
+pub fn doc() -> &'static str {
+    r#"show commit history
+
+    Print the revision history of the specified files or the entire
+    project.
+
+    This is a native implementation of :hg:`log` enabled by
+    ``experimental.rust-log``. Options it does not support are handled by
+    the Python implementation.
+
+    Returns 0 on success."#
+}
+
+/// Fall back to Python for an unsupported feature, or abort if
+/// `devel.rust-log-no-fallback` is set so tests notice the fallback.
+fn fallback(config: &ConfigSet, reason: &str) -> anyhow::Error {
+    if config
+        .get_or("devel", "rust-log-no-fallback", || false)
+        .unwrap_or(false)
+    {
+        return errors::Abort(format!("{} is not supported by native log", reason).into()).into();
+    }
+    tracing::debug!("native log falls back to Python for {}", reason);
+    errors::FallbackToPython.into()
+}
+
+fn is_segmented_changelog(store_path: &Path) -> Result<bool> {
+    let requires = match fs::read_to_string(store_path.join("requires")) {
+        Ok(requires) => requires,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(requires.lines().any(|l| l.trim() == "segmentedchangelog"))
+}
+
+fn extension_enabled(config: &ConfigSet, name: &str) -> bool {
+    match config.get("extensions", name) {
+        Some(value) => !value.starts_with('!'),
+        None => false,
+    }
+}
+
+/// Whether HGPLAIN disables a feature, like `ui.plain(feature)` in Python.
+fn plain(feature: &str) -> bool {
+    if std::env::var_os("HGPLAIN").is_none() {
+        return false;
+    }
+    match std::env::var("HGPLAINEXCEPT") {
+        Ok(except) => !except.split(',').any(|e| e.trim() == feature),
+        Err(_) => true,
+    }
+}
+
+/// Convert a command line path to a repo-relative path. Return `None` for
+/// patterns and paths outside the repo.
+fn repo_relative_path(root: &Path, cwd: &Path, arg: &str) -> Option<String> {
+    if arg.contains(':') {
+        return None;
+    }
+    let path = util::path::absolute(cwd.join(arg)).ok()?;
+    let relative = path.strip_prefix(root).ok()?;
+    let components: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(components.join("/"))
+}
+
+struct Filter {
+    keywords: Vec<String>,
+    users: Vec<String>,
+    paths: Vec<String>,
+    no_merges: bool,
+    only_merges: bool,
+    limit: Option<usize>,
+}
+
+impl Filter {
+    /// Whether matching needs the commit text, not just its parents.
+    fn needs_text(&self) -> bool {
+        !self.keywords.is_empty() || !self.users.is_empty() || !self.paths.is_empty()
+    }
+
+    fn matches_parents(&self, parents: &[Vertex]) -> bool {
+        let is_merge = parents.len() > 1;
+        !(self.no_merges && is_merge) && !(self.only_merges && !is_merge)
+    }
+
+    fn matches(&self, commit: &Commit) -> bool {
+        if !self.matches_parents(&commit.parents) {
+            return false;
+        }
+        if !self.users.is_empty() {
+            let user = commit.user.to_lowercase();
+            if !self.users.iter().any(|u| user.contains(u.as_str())) {
+                return false;
+            }
+        }
+        if !self.keywords.is_empty() {
+            let mut texts: Vec<String> = commit.files.iter().map(|f| f.to_lowercase()).collect();
+            texts.push(commit.user.to_lowercase());
+            texts.push(commit.desc.to_lowercase());
+            if !self
+                .keywords
+                .iter()
+                .any(|k| texts.iter().any(|t| t.contains(k.as_str())))
+            {
+                return false;
+            }
+        }
+        if !self.paths.is_empty() {
+            let matches_path = |file: &str| {
+                self.paths.iter().any(|p| {
+                    p.is_empty()
+                        || file == p
+                        || (file.starts_with(p.as_str()) && file[p.len()..].starts_with('/'))
+                })
+            };
+            if !commit.files.iter().any(|f| matches_path(f)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+struct Format {
+    template: Option<Vec<TemplateItem>>,
+    quiet: bool,
+    verbose: bool,
+    graph: bool,
+}
+
+/// Parsed commit text.
+struct Commit {
+    node: Vertex,
+    parents: Vec<Vertex>,
+    user: String,
+    time: i64,
+    tz: i32,
+    branch: String,
+    close: bool,
+    files: Vec<String>,
+    desc: String,
+}
+
+impl Commit {
+    /// Parse the text written by `changelog.add`: manifest, user,
+    /// "time tz extras", files, a blank line, then the description.
+    fn parse(node: Vertex, parents: Vec<Vertex>, text: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(text);
+        let (header, desc) = match text.find("\n\n") {
+            Some(pos) => (&text[..pos], &text[pos + 2..]),
+            None => (&text[..], ""),
+        };
+        let mut lines = header.split('\n');
+        let _manifest = lines.next();
+        let user = lines.next().unwrap_or_default().to_string();
+        let mut date = lines.next().unwrap_or_default().splitn(3, ' ');
+        let time = date
+            .next()
+            .and_then(|t| t.parse::<f64>().ok())
+            .unwrap_or_default() as i64;
+        let tz = date
+            .next()
+            .and_then(|t| t.parse::<i32>().ok())
+            .unwrap_or_default();
+        let mut branch = "default".to_string();
+        let mut close = false;
+        for extra in date.next().unwrap_or_default().split('\0') {
+            let extra = unescape_extra(extra);
+            if let Some(value) = extra.strip_prefix("branch=") {
+                branch = value.to_string();
+            } else if extra.starts_with("close=") {
+                close = true;
+            }
+        }
+        let files = lines.map(|l| l.to_string()).collect();
+        Commit {
+            node,
+            parents,
+            user,
+            time,
+            tz,
+            branch,
+            close,
+            files,
+            desc: desc.trim().to_string(),
+        }
+    }
+
+    fn hex(&self) -> String {
+        self.node.to_hex()
+    }
+
+    fn short(&self) -> String {
+        self.hex()[..12].to_string()
+    }
+}
+
+/// Decode extras escaped by `changelog.encodeextra`.
+fn unescape_extra(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Format a date like `util.datestr`. The time zone offset is in seconds
+/// west of UTC.
+fn format_date(time: i64, tz: i32, format: &str, with_zone: bool) -> String {
+    let local = match chrono::Utc.timestamp_opt(time - tz as i64, 0).single() {
+        Some(local) => local.format(format).to_string(),
+        None => time.to_string(),
+    };
+    if !with_zone {
+        return local;
+    }
+    let sign = if tz > 0 { '-' } else { '+' };
+    let minutes = tz.abs() / 60;
+    format!("{} {}{:02}{:02}", local, sign, minutes / 60, minutes % 60)
+}
+
+/// Names pointing to commits, shown in the default output and used to
+/// resolve symbols.
+#[derive(Default)]
+struct Names {
+    bookmarks: HashMap<Vertex, Vec<String>>,
+    remote_bookmarks: HashMap<Vertex, Vec<String>>,
+    hoisted_names: HashMap<Vertex, Vec<String>>,
+    /// Name to commit, in the priority order of Python namespaces.
+    lookup: HashMap<String, Vertex>,
+}
+
+impl Names {
+    fn load(metalog: &MetaLog, hoist: &str) -> Result<Self> {
+        let mut names = Names::default();
+
+        let bookmarks = metalog.get("bookmarks")?.unwrap_or_default();
+        let mut local = Vec::new();
+        for line in String::from_utf8_lossy(&bookmarks).lines() {
+            if let Some((hex, name)) = line.split_once(' ') {
+                if let Ok(vertex) = Vertex::from_hex(hex.as_bytes()) {
+                    local.push((name.to_string(), vertex));
+                }
+            }
+        }
+        local.sort();
+        for (name, vertex) in local {
+            names.lookup.entry(name.clone()).or_insert(vertex.clone());
+            names.bookmarks.entry(vertex).or_default().push(name);
+        }
+
+        let remotenames = metalog.get("remotenames")?.unwrap_or_default();
+        let hoist_prefix = format!("{}/", hoist);
+        let mut hoisted = Vec::new();
+        for line in String::from_utf8_lossy(&remotenames).lines() {
+            let mut fields = line.splitn(3, ' ');
+            let (hex, kind, name) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(hex), Some(kind), Some(name)) => (hex, kind, name),
+                _ => continue,
+            };
+            if kind != "bookmarks" {
+                continue;
+            }
+            let vertex = match Vertex::from_hex(hex.as_bytes()) {
+                Ok(vertex) => vertex,
+                Err(_) => continue,
+            };
+            names
+                .lookup
+                .entry(name.to_string())
+                .or_insert(vertex.clone());
+            names
+                .remote_bookmarks
+                .entry(vertex.clone())
+                .or_default()
+                .push(name.to_string());
+            if let Some(name) = name.strip_prefix(hoist_prefix.as_str()) {
+                hoisted.push((name.to_string(), vertex));
+            }
+        }
+        for (name, vertex) in hoisted {
+            names.lookup.entry(name.clone()).or_insert(vertex.clone());
+            names.hoisted_names.entry(vertex).or_default().push(name);
+        }
+
+        Ok(names)
+    }
+
+    fn get<'a>(map: &'a HashMap<Vertex, Vec<String>>, vertex: &Vertex) -> &'a [String] {
+        map.get(vertex).map(|v| v.as_slice()).unwrap_or_default()
+    }
+}
+
+struct Log {
+    commits: HgCommits,
+    dag: Arc<dyn DagAlgorithm + Send + Sync>,
+    names: Names,
+    visible_heads: Vec<Vertex>,
+    /// Working copy parents.
+    wdir_parents: Vec<Vertex>,
+}
+
+impl Log {
+    fn open(repo: &Repo) -> Result<Self> {
+        let store_path = repo.store_path();
+        let commits = HgCommits::new(
+            &store_path.join("segments/v1"),
+            &store_path.join("hgcommits/v1"),
+        )?;
+        let dag = commits.dag_snapshot()?;
+
+        let metalog = MetaLog::open(store_path.join("metalog"), None)?;
+        let hoist = repo
+            .config()
+            .get("remotenames", "hoist")
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "default".to_string());
+        let names = Names::load(&metalog, &hoist)?;
+
+        let visible_heads = metalog.get("visibleheads")?.unwrap_or_default();
+        let visible_heads = String::from_utf8_lossy(&visible_heads)
+            .lines()
+            .skip(1)
+            .filter_map(|l| Vertex::from_hex(l.trim().as_bytes()).ok())
+            .collect();
+
+        let dirstate = fs::read(repo.dot_hg_path().join("dirstate")).unwrap_or_default();
+        let wdir_parents = dirstate
+            .chunks_exact(20)
+            .take(2)
+            .filter(|p| p.iter().any(|&b| b != 0))
+            .map(Vertex::copy_from)
+            .collect();
+
+        Ok(Log {
+            commits,
+            dag,
+            names,
+            visible_heads,
+            wdir_parents,
+        })
+    }
+
+    /// Commits that are not hidden: ancestors of visible heads and names.
+    async fn visible(&self) -> Result<Set> {
+        let mut heads: Vec<Vertex> = self.visible_heads.clone();
+        heads.extend(self.names.lookup.values().cloned());
+        heads.extend(self.wdir_parents.iter().cloned());
+        let mut existing = Vec::with_capacity(heads.len());
+        for head in heads {
+            if self.commits.contains_vertex_name(&head).await? {
+                existing.push(head);
+            }
+        }
+        Ok(self.dag.ancestors(Set::from_static_names(existing)).await?)
+    }
+
+    /// Resolve a revset symbol like `.`, a name, or a hex prefix.
+    async fn resolve(&self, name: &str) -> Result<Option<Vertex>> {
+        if name == "." {
+            return Ok(self.wdir_parents.first().cloned());
+        }
+        if let Some(vertex) = self.names.lookup.get(name) {
+            return Ok(Some(vertex.clone()));
+        }
+        // Numbers are revision numbers in Python.
+        if name.is_empty()
+            || name.len() > 40
+            || name.bytes().all(|b| b.is_ascii_digit())
+            || !name.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return Ok(None);
+        }
+        let prefix = name.to_ascii_lowercase();
+        let mut matches = self
+            .commits
+            .vertexes_by_hex_prefix(prefix.as_bytes(), 2)
+            .await?;
+        if matches.len() == 1 {
+            Ok(matches.pop())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Revisions to show, before filtering. `None` if the revset is not
+    /// supported natively.
+    async fn revs(
+        &self,
+        config: &ConfigSet,
+        specs: &[String],
+        follow: bool,
+    ) -> Result<Option<Vec<Vertex>>> {
+        let visible = self.visible().await?;
+        if specs.is_empty() {
+            let revs = if follow {
+                match self.wdir_parents.first() {
+                    Some(p1) => {
+                        self.dag
+                            .ancestors(Set::from_static_names(vec![p1.clone()]))
+                            .await?
+                    }
+                    None => Set::empty(),
+                }
+            } else {
+                visible
+            };
+            return Ok(Some(revs.iter()?.collect::<dag::Result<_>>()?));
+        }
+
+        let aliases = Aliases::from_config(config);
+        let lookup = |name: &str| self.names.lookup.contains_key(name);
+        let mut resolved: HashMap<String, Vertex> = HashMap::new();
+        let mut exprs = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let expr = match revset::parse_with_lookup(spec, &lookup)
+                .and_then(|expr| aliases.expand(&expr))
+            {
+                Ok(expr) => expr,
+                Err(_) => return Ok(None),
+            };
+            let mut symbols = Vec::new();
+            collect_symbols(&expr, &mut symbols);
+            for symbol in symbols {
+                if resolved.contains_key(&symbol) {
+                    continue;
+                }
+                if let Some(vertex) = self.resolve(&symbol).await? {
+                    // Hidden commits need --hidden, which is handled by Python.
+                    if !visible.contains(&vertex)? {
+                        return Ok(None);
+                    }
+                    resolved.insert(symbol, vertex);
+                }
+            }
+            exprs.push(expr);
+        }
+
+        let mut evaluator = Evaluator::new(self.dag.clone());
+        evaluator.set_symbol_resolver(move |name| {
+            Ok(resolved
+                .get(name)
+                .map(|v| Set::from_static_names(vec![v.clone()])))
+        });
+        let mut revs = Set::empty();
+        for expr in &exprs {
+            match evaluator.eval(expr).await {
+                Ok(set) => revs = revs.union(&set),
+                Err(_) => return Ok(None),
+            }
+        }
+        let revs = revs.intersection(&visible);
+
+        // "log -f -r X" shows "reverse(::X)". Otherwise revisions are shown
+        // in ascending order, like Python.
+        let revs: Vec<Vertex> = if follow {
+            self.dag
+                .ancestors(revs)
+                .await?
+                .iter()?
+                .collect::<dag::Result<_>>()?
+        } else {
+            self.dag
+                .sort(&revs)
+                .await?
+                .iter_rev()?
+                .collect::<dag::Result<_>>()?
+        };
+        Ok(Some(revs))
+    }
+
+    async fn read_commit(&self, vertex: &Vertex) -> Result<Commit> {
+        let text = match self.commits.get_commit_raw_text(vertex).await? {
+            Some(text) => text,
+            None => anyhow::bail!("commit {} is missing", vertex.to_hex()),
+        };
+        let parents = self.dag.parent_names(vertex.clone()).await?;
+        Ok(Commit::parse(vertex.clone(), parents, &text))
+    }
+
+    async fn show(
+        &self,
+        io: &IO,
+        config: &ConfigSet,
+        revs: Vec<Vertex>,
+        filter: &Filter,
+        format: &Format,
+    ) -> Result<()> {
+        if format.graph {
+            let revs = self.select(revs, filter).await?;
+            return self.show_graph(io, config, revs, format).await;
+        }
+        let limit = filter.limit.unwrap_or(usize::MAX);
+        let mut count = 0;
+        for vertex in revs {
+            if count >= limit {
+                break;
+            }
+            let commit = self.read_commit(&vertex).await?;
+            if !filter.matches(&commit) {
+                continue;
+            }
+            count += 1;
+            io.write(self.render(&commit, format))?;
+        }
+        Ok(())
+    }
+
+    /// Revisions matching the filter. The graph needs them before rendering
+    /// edges. Commit text is only read if the filter needs it.
+    async fn select(&self, revs: Vec<Vertex>, filter: &Filter) -> Result<Vec<Vertex>> {
+        let limit = filter.limit.unwrap_or(usize::MAX);
+        let mut selected = Vec::new();
+        for vertex in revs {
+            if selected.len() >= limit {
+                break;
+            }
+            let matches = if filter.needs_text() {
+                filter.matches(&self.read_commit(&vertex).await?)
+            } else {
+                filter.matches_parents(&self.dag.parent_names(vertex.clone()).await?)
+            };
+            if matches {
+                selected.push(vertex);
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Render `revs` as a graph, reading and writing one commit at a time.
+    async fn show_graph(
+        &self,
+        io: &IO,
+        config: &ConfigSet,
+        revs: Vec<Vertex>,
+        format: &Format,
+    ) -> Result<()> {
+        let renderer_name = if plain("graph") {
+            "ascii".to_string()
+        } else {
+            config
+                .get("experimental", "graph.renderer")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "lines".to_string())
+        };
+        let min_row_height = if config.get_or("experimental", "graphshorten", || false)? {
+            1
+        } else {
+            2
+        };
+        let min_row_height =
+            config.get_or("experimental", "graph.min-row-height", || min_row_height)?;
+        let builder = GraphRowRenderer::<Vertex>::new()
+            .output()
+            .with_min_row_height(min_row_height);
+        let mut renderer: Box<dyn Renderer<Vertex, Output = String>> = match renderer_name.as_str()
+        {
+            "lines" | "lines-curved" => Box::new(builder.build_box_drawing()),
+            "lines-square" => Box::new(builder.build_box_drawing().with_square_glyphs()),
+            "lines-dec" => Box::new(builder.build_box_drawing().with_dec_graphics_glyphs()),
+            "ascii-large" => Box::new(builder.build_ascii_large()),
+            _ => Box::new(builder.build_ascii()),
+        };
+
+        let simplify_grandparents = config.get_or("log", "simplify-grandparents", || true)?;
+        let rev_set = self
+            .dag
+            .sort(&Set::from_static_names(revs.iter().cloned()))
+            .await?;
+        let mut grandparents_cache: HashMap<Vertex, Vec<Vertex>> = HashMap::new();
+
+        for vertex in revs {
+            let commit = self.read_commit(&vertex).await?;
+            // Like graphmod.dagwalker: parents in the set are direct edges,
+            // other parents are replaced by their nearest ancestors in the
+            // set, or an anonymous edge if there are none.
+            let mut direct = Vec::new();
+            let mut missing = Vec::new();
+            for parent in &commit.parents {
+                if rev_set.contains(parent)? {
+                    direct.push(parent.clone());
+                } else {
+                    missing.push(parent.clone());
+                }
+            }
+            let direct = self.sort_ascending(direct).await?;
+            let mut seen: HashSet<Vertex> = direct.iter().cloned().collect();
+            let mut parents: Vec<Ancestor<Vertex>> =
+                direct.into_iter().map(Ancestor::Parent).collect();
+            for parent in missing {
+                if !grandparents_cache.contains_key(&parent) {
+                    let parent_set = Set::from_static_names(vec![parent.clone()]);
+                    let grandparents = if simplify_grandparents {
+                        let ancestors = self.dag.ancestors(parent_set).await?;
+                        self.dag
+                            .heads_ancestors(ancestors.intersection(&rev_set))
+                            .await?
+                    } else {
+                        self.dag
+                            .reachable_roots(rev_set.clone(), parent_set)
+                            .await?
+                    };
+                    let grandparents = grandparents.iter()?.collect::<dag::Result<_>>()?;
+                    let grandparents = self.sort_ascending(grandparents).await?;
+                    grandparents_cache.insert(parent.clone(), grandparents);
+                }
+                let grandparents = &grandparents_cache[&parent];
+                if grandparents.is_empty() {
+                    parents.push(Ancestor::Anonymous);
+                    seen.insert(parent);
+                } else {
+                    for grandparent in grandparents {
+                        if seen.insert(grandparent.clone()) {
+                            parents.push(Ancestor::Ancestor(grandparent.clone()));
+                        }
+                    }
+                }
+            }
+
+            let glyph = self.graph_node(&commit);
+            let message = self.render(&commit, format);
+            let row = renderer.next_row(commit.node.clone(), parents, glyph, message);
+            io.write(row)?;
+        }
+        Ok(())
+    }
+
+    async fn sort_ascending(&self, vertexes: Vec<Vertex>) -> Result<Vec<Vertex>> {
+        if vertexes.len() < 2 {
+            return Ok(vertexes);
+        }
+        let sorted = self
+            .dag
+            .sort(&Set::from_static_names(vertexes))
+            .await?
+            .iter_rev()?
+            .collect::<dag::Result<_>>()?;
+        Ok(sorted)
+    }
+
+    /// Like the `{graphnode}` template keyword.
+    fn graph_node(&self, commit: &Commit) -> String {
+        if self.wdir_parents.contains(&commit.node) {
+            "@"
+        } else if commit.close {
+            "_"
+        } else {
+            "o"
+        }
+        .to_string()
+    }
+
+    fn render(&self, commit: &Commit, format: &Format) -> String {
+        if let Some(template) = &format.template {
+            return render_template(template, commit, self);
+        }
+        if format.quiet {
+            return format!("{}\n", commit.short());
+        }
+
+        // Matches changeset_printer.
+        let mut out = format!("commit:      {}\n", commit.short());
+        if commit.branch != "default" {
+            out += &format!("branch:      {}\n", commit.branch);
+        }
+        for name in Names::get(&self.names.bookmarks, &commit.node) {
+            out += &format!("bookmark:    {}\n", name);
+        }
+        for name in Names::get(&self.names.remote_bookmarks, &commit.node) {
+            out += &format!("bookmark:    {}\n", name);
+        }
+        for name in Names::get(&self.names.hoisted_names, &commit.node) {
+            out += &format!("hoistedname: {}\n", name);
+        }
+        out += &format!("user:        {}\n", commit.user);
+        out += &format!(
+            "date:        {}\n",
+            format_date(commit.time, commit.tz, "%a %b %d %H:%M:%S %Y", true)
+        );
+        if format.verbose && !commit.files.is_empty() {
+            out += &format!("files:       {}\n", commit.files.join(" "));
+        }
+        if !commit.desc.is_empty() {
+            if format.verbose {
+                out += &format!("description:\n{}\n\n", commit.desc);
+            } else {
+                let summary = commit.desc.lines().next().unwrap_or_default();
+                out += &format!("summary:     {}\n", summary);
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Collect symbols and strings that might name commits.
+fn collect_symbols(expr: &Expr, symbols: &mut Vec<String>) {
+    match expr {
+        Expr::Symbol(s) | Expr::String(s) => symbols.push(s.clone()),
+        Expr::Func(_, args) => args.iter().for_each(|a| collect_symbols(a, symbols)),
+        Expr::KeyValue(_, x) | Expr::Not(x) | Expr::Negate(x) => collect_symbols(x, symbols),
+        Expr::And(x, y) | Expr::Or(x, y) | Expr::Minus(x, y) => {
+            collect_symbols(x, symbols);
+            collect_symbols(y, symbols);
+        }
+        Expr::Only(x, y) => {
+            collect_symbols(x, symbols);
+            if let Some(y) = y {
+                collect_symbols(y, symbols);
+            }
+        }
+        Expr::DagRange(x, y) | Expr::Range(x, y) => {
+            for e in [x, y].into_iter().flatten() {
+                collect_symbols(e, symbols);
+            }
+        }
+        Expr::Parent(x, _) | Expr::Ancestor(x, _) => collect_symbols(x, symbols),
+    }
+}
+
+/// A piece of a template: literal text, or a keyword with filters.
+enum TemplateItem {
+    Literal(String),
+    Keyword(String, Vec<String>),
+}
+
+const TEXT_KEYWORDS: &[&str] = &[
+    "author",
+    "bookmarks",
+    "branch",
+    "desc",
+    "files",
+    "graphnode",
+    "node",
+    "p1node",
+    "p2node",
+];
+const TEXT_FILTERS: &[&str] = &["email", "emailuser", "firstline", "short", "user"];
+const DATE_FILTERS: &[&str] = &["hgdate", "isodate", "isodatesec", "shortdate"];
+
+/// Parse templates like `{node|short} {desc|firstline}\n`. Return `None`
+/// for syntax or keywords that are not supported natively.
+fn parse_template(spec: &str) -> Option<Vec<TemplateItem>> {
+    // Names like "json" or "status" refer to styles.
+    if !spec.contains('{') || spec.starts_with('\'') || spec.starts_with('"') {
+        return None;
+    }
+    let mut items = Vec::new();
+    let mut literal = String::new();
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => literal.push('\n'),
+                't' => literal.push('\t'),
+                c @ ('\\' | '{' | '}') => literal.push(c),
+                _ => return None,
+            },
+            '{' => {
+                let mut body = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        '{' | '(' | '"' | '\'' | '\\' => return None,
+                        c => body.push(c),
+                    }
+                }
+                let mut parts = body.split('|').map(|s| s.trim().to_string());
+                let keyword = parts.next()?;
+                let filters: Vec<String> = parts.collect();
+                let valid = if keyword == "date" {
+                    // A bare "{date}" renders a tuple in Python.
+                    matches!(filters.split_first(), Some((first, rest))
+                        if DATE_FILTERS.contains(&first.as_str())
+                            && rest.iter().all(|f| TEXT_FILTERS.contains(&f.as_str())))
+                } else {
+                    TEXT_KEYWORDS.contains(&keyword.as_str())
+                        && filters.iter().all(|f| TEXT_FILTERS.contains(&f.as_str()))
+                };
+                if !valid {
+                    return None;
+                }
+                if !literal.is_empty() {
+                    items.push(TemplateItem::Literal(std::mem::take(&mut literal)));
+                }
+                items.push(TemplateItem::Keyword(keyword, filters));
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        items.push(TemplateItem::Literal(literal));
+    }
+    Some(items)
+}
+
+fn render_template(template: &[TemplateItem], commit: &Commit, log: &Log) -> String {
+    let null_hex = || "0".repeat(40);
+    let mut out = String::new();
+    for item in template {
+        let (keyword, filters) = match item {
+            TemplateItem::Literal(s) => {
+                out += s;
+                continue;
+            }
+            TemplateItem::Keyword(keyword, filters) => (keyword.as_str(), filters.as_slice()),
+        };
+        let (mut value, filters) = match keyword {
+            "author" => (commit.user.clone(), filters),
+            "bookmarks" => (
+                Names::get(&log.names.bookmarks, &commit.node).join(" "),
+                filters,
+            ),
+            "branch" => (commit.branch.clone(), filters),
+            "desc" => (commit.desc.clone(), filters),
+            "files" => (commit.files.join(" "), filters),
+            "graphnode" => (log.graph_node(commit), filters),
+            "node" => (commit.hex(), filters),
+            "p1node" => (
+                commit.parents.first().map_or_else(null_hex, |p| p.to_hex()),
+                filters,
+            ),
+            "p2node" => (
+                commit.parents.get(1).map_or_else(null_hex, |p| p.to_hex()),
+                filters,
+            ),
+            "date" => {
+                let (time, tz) = (commit.time, commit.tz);
+                let value = match filters[0].as_str() {
+                    "hgdate" => format!("{} {}", time, tz),
+                    "isodate" => format_date(time, tz, "%Y-%m-%d %H:%M", true),
+                    "isodatesec" => format_date(time, tz, "%Y-%m-%d %H:%M:%S", true),
+                    _ => format_date(time, tz, "%Y-%m-%d", false),
+                };
+                (value, &filters[1..])
+            }
+            _ => unreachable!("keyword checked by parse_template"),
+        };
+        for filter in filters {
+            value = match filter.as_str() {
+                "email" => email(&value).to_string(),
+                "emailuser" => email_user(&value).to_string(),
+                "firstline" => value.lines().next().unwrap_or_default().to_string(),
+                "short" => value.chars().take(12).collect(),
+                _ => short_user(&value).to_string(),
+            };
+        }
+        out += &value;
+    }
+    out
+}
+
+/// Like `util.email`.
+fn email(author: &str) -> &str {
+    let start = author.find('<').map_or(0, |i| i + 1);
+    let end = author.find('>').unwrap_or(author.len());
+    author.get(start..end).unwrap_or_default()
+}
+
+/// Like `util.emailuser`.
+fn email_user(user: &str) -> &str {
+    let mut user = user;
+    if let Some(i) = user.find('@') {
+        user = &user[..i];
+    }
+    if let Some(i) = user.find('<') {
+        user = &user[i + 1..];
+    }
+    user
+}
+
+/// Like `util.shortuser`.
+fn short_user(user: &str) -> &str {
+    let mut user = email_user(user);
+    for sep in [' ', '.'] {
+        if let Some(i) = user.find(sep) {
+            user = &user[..i];
+        }
+    }
+    user
+}
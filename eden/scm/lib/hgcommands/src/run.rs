@@ -35,6 +35,7 @@ use clientinfo::ClientInfo;
 use configparser::config::ConfigSet;
 use configparser::configmodel::ConfigExt;
 use fail::FailScenario;
+use hg_http::FixtureMode;
 use hg_http::HgHttpConfig;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
@@ -450,10 +451,13 @@ pub(crate) fn write_trace(io: &IO, path: &str, data: &TracingData) -> Result<()>
         TraceEventJSON,
         TraceEventGzip,
         SpansJSON,
+        Folded,
     }
 
     let format = if path.ends_with(".txt") {
         Format::ASCII
+    } else if path.ends_with(".folded") {
+        Format::Folded
     } else if path.ends_with("spans.json") {
         Format::SpansJSON
     } else if path.ends_with(".json") {
@@ -478,6 +482,10 @@ pub(crate) fn write_trace(io: &IO, path: &str, data: &TracingData) -> Result<()>
             out.write_all(data.ascii(&ascii_opts).as_bytes())?;
             out.flush()?;
         }
+        Format::Folded => {
+            out.write_all(data.folded_stacks().as_bytes())?;
+            out.flush()?;
+        }
         Format::SpansJSON => {
             let spans = data.tree_spans::<&str>();
             serde_json::to_writer(&mut out, &spans)?;
@@ -658,10 +666,26 @@ fn setup_http(config: &ConfigSet, global_opts: &HgGlobalOpts) {
                 .unwrap_or_else(|_| vec![])
                 .into_iter(),
         ),
+        fixture_mode: http_fixture_mode(config),
     };
     hg_http::set_global_config(http_config);
 }
 
+/// Record HTTP responses to, or replay them from, the fixture file
+/// specified by `http.record-path` or `http.replay-path`.
+fn http_fixture_mode(config: &ConfigSet) -> Option<FixtureMode> {
+    let replay_path: Option<PathBuf> = config
+        .get_nonempty_opt("http", "replay-path")
+        .unwrap_or_default();
+    if let Some(path) = replay_path {
+        return Some(FixtureMode::Replay(path));
+    }
+    let record_path: Option<PathBuf> = config
+        .get_nonempty_opt("http", "record-path")
+        .unwrap_or_default();
+    record_path.map(FixtureMode::Record)
+}
+
 fn setup_eager_repo() {
     static REGISTERED: Lazy<()> = Lazy::new(|| {
         edenapi::Builder::register_customize_build_func(eagerepo::edenapi_from_config)
@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+use std::collections::HashMap;
 use std::pin::Pin;
 
 use curl::easy::Easy2;
@@ -14,6 +15,9 @@ use crate::driver::MultiDriver;
 use crate::errors::Abort;
 use crate::errors::HttpClientError;
 use crate::event_listeners::HttpClientEventListeners;
+use crate::fixture::Intercept;
+use crate::fixture::RecordingReceiver;
+use crate::fixture::{self};
 use crate::handler::Buffered;
 use crate::handler::HandlerExt;
 use crate::handler::Streaming;
@@ -106,8 +110,21 @@ impl HttpClient {
             .set_max_total_connections(self.max_concurrent_requests)?;
         let driver = MultiDriver::new(multi.get(), progress_cb, self.verbose);
 
+        let mut recorders = HashMap::new();
         for mut request in requests {
             self.event_listeners.trigger_new_request(request.ctx_mut());
+            match fixture::intercept(&request)? {
+                Intercept::Send(recorder) => {
+                    if let Some(recorder) = recorder {
+                        recorders.insert(request.id(), recorder);
+                    }
+                }
+                Intercept::Replay(response) => {
+                    let res = response.into_response(request.ctx().info().clone());
+                    response_cb(res)?;
+                    continue;
+                }
+            }
             let handle: Easy2<Buffered> = request.try_into()?;
             driver.add(handle)?;
         }
@@ -127,6 +144,12 @@ impl HttpClient {
                     ctx.event_listeners().trigger_success(&info);
                     self.event_listeners.trigger_succeeded_request(ctx);
                     Response::try_from(easy.get_mut())
+                })
+                .and_then(|res| {
+                    if let Some(recorder) = recorders.remove(&res.head.request_info.id()) {
+                        recorder.record(&res)?;
+                    }
+                    Ok(res)
                 });
             response_cb(res)
         })?;
@@ -231,7 +254,11 @@ impl HttpClient {
         for mut request in requests {
             self.event_listeners
                 .trigger_new_request(request.request.ctx_mut());
-            let handle: Easy2<Streaming<R>> = request.try_into()?;
+            let request = match fixture::intercept_stream(request)? {
+                Some(request) => request,
+                None => continue,
+            };
+            let handle: Easy2<Streaming<RecordingReceiver<R>>> = request.try_into()?;
             driver.add(handle)?;
         }
 
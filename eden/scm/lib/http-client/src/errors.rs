@@ -31,6 +31,8 @@ pub enum HttpClientError {
     #[error("Could not decode response: {}", .0)]
     DecompressionFailed(futures::io::Error),
     #[error(transparent)]
+    Fixture(anyhow::Error),
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
@@ -0,0 +1,609 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Record HTTP responses to a fixture file and replay them later.
+//!
+//! In record mode, requests are sent as usual, and each request/response
+//! pair is appended to the fixture file as a line of JSON. In replay mode,
+//! requests are not sent at all. Instead, the response is served from the
+//! fixture file, matched by method, URL, selected headers, and body.
+//!
+//! If the same request was recorded multiple times, the responses are
+//! replayed in the order they were recorded. Once they are used up, the
+//! last one is replayed for further identical requests.
+//!
+//! Replayed requests do not go through libcurl, so they are not included
+//! in transfer `Stats`.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use http::header::HeaderMap;
+use http::header::HeaderName;
+use http::header::HeaderValue;
+use http::StatusCode;
+use http::Version;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::errors::Abort;
+use crate::errors::HttpClientError;
+use crate::header::Header;
+use crate::progress::Progress;
+use crate::receiver::Receiver;
+use crate::request::Request;
+use crate::request::RequestInfo;
+use crate::request::StreamRequest;
+use crate::response::Head;
+use crate::response::Response;
+
+/// Request headers that are recorded and used to match requests. Other
+/// headers tend to contain credentials or vary between runs.
+const MATCHED_HEADERS: &[&str] = &["accept", "content-encoding", "content-type"];
+
+/// Response headers that are not recorded, since they contain credentials.
+const UNRECORDED_RESPONSE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "set-cookie",
+    "set-cookie2",
+    "www-authenticate",
+];
+
+/// How requests should interact with fixture files.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FixtureMode {
+    /// Send requests, and append the responses to the given fixture file.
+    Record(PathBuf),
+    /// Do not send requests. Serve responses from the given fixture file.
+    Replay(PathBuf),
+}
+
+static FIXTURES: Lazy<RwLock<Option<Arc<Fixtures>>>> = Lazy::new(Default::default);
+
+/// Set the fixture mode for all HTTP requests in this process.
+///
+/// The fixture file is opened lazily, so errors (ex. the fixture file used
+/// for replaying does not exist) are reported by the requests.
+pub fn set_fixture_mode(mode: Option<FixtureMode>) {
+    *FIXTURES.write() = mode.map(|mode| Arc::new(Fixtures::new(mode)));
+}
+
+/// What to do with a request, according to the fixture mode.
+pub(crate) enum Intercept {
+    /// Send the request. Record the response if there is a `Recorder`.
+    Send(Option<Recorder>),
+    /// Do not send the request. Use the recorded response instead.
+    Replay(RecordedResponse),
+}
+
+/// Decide what to do with a request, according to the global fixture mode.
+pub(crate) fn intercept(request: &Request) -> Result<Intercept, HttpClientError> {
+    let fixtures = FIXTURES.read().clone();
+    match fixtures {
+        Some(fixtures) => fixtures.intercept(request),
+        None => Ok(Intercept::Send(None)),
+    }
+}
+
+/// Like `intercept`, but for streaming requests.
+///
+/// If the response was replayed to the receiver, return `None`. Otherwise,
+/// return a request that should be sent. Its receiver records the response
+/// if needed.
+pub(crate) fn intercept_stream<R: Receiver>(
+    request: StreamRequest<R>,
+) -> Result<Option<StreamRequest<RecordingReceiver<R>>>, HttpClientError> {
+    let StreamRequest { request, receiver } = request;
+    match intercept(&request)? {
+        Intercept::Send(recorder) => Ok(Some(
+            request.into_streaming(RecordingReceiver::new(receiver, recorder)),
+        )),
+        Intercept::Replay(response) => {
+            response.replay_to(receiver)?;
+            Ok(None)
+        }
+    }
+}
+
+enum Fixtures {
+    Record {
+        path: PathBuf,
+        file: Mutex<Option<File>>,
+    },
+    Replay {
+        path: PathBuf,
+        responses: Mutex<Option<HashMap<RecordedRequest, VecDeque<RecordedResponse>>>>,
+    },
+}
+
+/// A line in the fixture file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    #[serde(with = "hex_bytes")]
+    body: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    #[serde(with = "hex_bytes")]
+    body: Vec<u8>,
+}
+
+/// Records the response of a request to the fixture file.
+pub(crate) struct Recorder {
+    fixtures: Arc<Fixtures>,
+    request: RecordedRequest,
+}
+
+impl Fixtures {
+    fn new(mode: FixtureMode) -> Self {
+        match mode {
+            FixtureMode::Record(path) => Fixtures::Record {
+                path,
+                file: Default::default(),
+            },
+            FixtureMode::Replay(path) => Fixtures::Replay {
+                path,
+                responses: Default::default(),
+            },
+        }
+    }
+
+    fn intercept(self: &Arc<Self>, request: &Request) -> Result<Intercept, HttpClientError> {
+        let request = RecordedRequest::new(request);
+        match self.as_ref() {
+            Fixtures::Record { .. } => Ok(Intercept::Send(Some(Recorder {
+                fixtures: self.clone(),
+                request,
+            }))),
+            Fixtures::Replay { path, responses } => {
+                let mut responses = responses.lock();
+                if responses.is_none() {
+                    *responses = Some(load(path).map_err(HttpClientError::Fixture)?);
+                }
+                let queue = responses
+                    .as_mut()
+                    .unwrap()
+                    .get_mut(&request)
+                    .filter(|queue| !queue.is_empty())
+                    .ok_or_else(|| {
+                        HttpClientError::Fixture(anyhow!(
+                            "no recorded response for {} {} in {}",
+                            &request.method,
+                            &request.url,
+                            path.display()
+                        ))
+                    })?;
+                let response = if queue.len() > 1 {
+                    queue.pop_front().unwrap()
+                } else {
+                    queue[0].clone()
+                };
+                Ok(Intercept::Replay(response))
+            }
+        }
+    }
+
+    fn append(&self, entry: &Entry) -> anyhow::Result<()> {
+        let (path, file) = match self {
+            Fixtures::Record { path, file } => (path, file),
+            Fixtures::Replay { .. } => return Ok(()),
+        };
+        (|| -> anyhow::Result<()> {
+            let mut line = serde_json::to_vec(entry)?;
+            line.push(b'\n');
+            let mut file = file.lock();
+            if file.is_none() {
+                *file = Some(
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)?,
+                );
+            }
+            // Write the line at once so entries from concurrent requests
+            // do not interleave.
+            file.as_mut().unwrap().write_all(&line)?;
+            Ok(())
+        })()
+        .with_context(|| format!("cannot record HTTP fixture to {}", path.display()))
+    }
+}
+
+/// Load recorded responses from a fixture file.
+fn load(path: &Path) -> anyhow::Result<HashMap<RecordedRequest, VecDeque<RecordedResponse>>> {
+    (|| -> anyhow::Result<_> {
+        let data = fs::read(path)?;
+        let mut responses: HashMap<_, VecDeque<_>> = HashMap::new();
+        for line in data.split(|&b| b == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_slice(line)?;
+            responses
+                .entry(entry.request)
+                .or_default()
+                .push_back(entry.response);
+        }
+        Ok(responses)
+    })()
+    .with_context(|| format!("cannot load HTTP fixtures from {}", path.display()))
+}
+
+impl RecordedRequest {
+    fn new(request: &Request) -> Self {
+        let ctx = request.ctx();
+        let headers = request
+            .headers()
+            .iter()
+            .filter(|(name, _)| MATCHED_HEADERS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Self {
+            method: ctx.method().to_string(),
+            url: ctx.url().to_string(),
+            headers,
+            body: ctx.body.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn status(&self) -> Result<StatusCode, HttpClientError> {
+        StatusCode::from_u16(self.status)
+            .map_err(|e| HttpClientError::Fixture(anyhow!("invalid status code: {}", e)))
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, HttpClientError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| HttpClientError::Fixture(anyhow!("invalid header name: {}", e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| HttpClientError::Fixture(anyhow!("invalid header value: {}", e)))?;
+            headers.append(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// Convert to a `Response` of the given request.
+    pub(crate) fn into_response(
+        self,
+        request_info: RequestInfo,
+    ) -> Result<Response, HttpClientError> {
+        Ok(Response {
+            head: Head {
+                version: Version::HTTP_11,
+                status: self.status()?,
+                headers: self.header_map()?,
+                request_info,
+            },
+            body: self.body,
+        })
+    }
+
+    /// Pass the response to the receiver as if it was received from the
+    /// network.
+    pub(crate) fn replay_to<R: Receiver>(self, mut receiver: R) -> Result<(), Abort> {
+        let res = (|| -> Result<(), HttpClientError> {
+            receiver.header(Header::Status(Version::HTTP_11, self.status()?))?;
+            for (name, value) in self.header_map()?.into_iter() {
+                if let Some(name) = name {
+                    receiver.header(Header::Header(name, value))?;
+                }
+            }
+            receiver.header(Header::EndOfHeaders)?;
+            if !self.body.is_empty() {
+                receiver.chunk(self.body)?;
+            }
+            Ok(())
+        })();
+        receiver.done(res)
+    }
+}
+
+impl Recorder {
+    /// Record a buffered response.
+    pub(crate) fn record(self, response: &Response) -> Result<(), HttpClientError> {
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| is_recorded_response_header(name))
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_string(), value)
+            })
+            .collect();
+        self.finish(RecordedResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: response.body().to_vec(),
+        })
+    }
+
+    fn finish(self, response: RecordedResponse) -> Result<(), HttpClientError> {
+        let entry = Entry {
+            request: self.request,
+            response,
+        };
+        self.fixtures
+            .append(&entry)
+            .map_err(HttpClientError::Fixture)
+    }
+}
+
+fn is_recorded_response_header(name: &HeaderName) -> bool {
+    !UNRECORDED_RESPONSE_HEADERS.contains(&name.as_str())
+}
+
+/// A `Receiver` that records the response it receives, if it has a
+/// `Recorder`, before passing it to the inner `Receiver`.
+pub(crate) struct RecordingReceiver<R> {
+    inner: R,
+    recording: Option<(Recorder, RecordedResponse)>,
+}
+
+impl<R> RecordingReceiver<R> {
+    fn new(inner: R, recorder: Option<Recorder>) -> Self {
+        Self {
+            inner,
+            recording: recorder.map(|recorder| (recorder, Default::default())),
+        }
+    }
+}
+
+impl<R: Receiver> Receiver for RecordingReceiver<R> {
+    fn chunk(&mut self, chunk: Vec<u8>) -> anyhow::Result<()> {
+        if let Some((_, response)) = self.recording.as_mut() {
+            response.body.extend_from_slice(&chunk);
+        }
+        self.inner.chunk(chunk)
+    }
+
+    fn header(&mut self, header: Header) -> anyhow::Result<()> {
+        if let Some((_, response)) = self.recording.as_mut() {
+            match &header {
+                Header::Status(_, status) => {
+                    // Only keep the headers of the final response, not ones
+                    // from redirects or "100 Continue".
+                    response.status = status.as_u16();
+                    response.headers.clear();
+                }
+                Header::Header(name, _) if !is_recorded_response_header(name) => {}
+                Header::Header(name, value) => {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    response.headers.push((name.as_str().to_string(), value));
+                }
+                Header::EndOfHeaders => {}
+            }
+        }
+        self.inner.header(header)
+    }
+
+    fn progress(&mut self, progress: Progress) {
+        self.inner.progress(progress)
+    }
+
+    fn done(self, res: Result<(), HttpClientError>) -> Result<(), Abort> {
+        let res = match (res, self.recording) {
+            (Ok(()), Some((recorder, response))) => recorder.finish(response),
+            (res, _) => res,
+        };
+        self.inner.done(res)
+    }
+}
+
+/// Serialize bytes as a hex string.
+mod hex_bytes {
+    use std::fmt::Write;
+
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for b in bytes {
+            write!(hex, "{:02x}", b).unwrap();
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd length hex string"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    .ok_or_else(|| D::Error::custom("invalid hex string"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use mockito::mock;
+    use tempfile::tempdir;
+    use url::Url;
+
+    use super::*;
+    use crate::receiver::testutil::TestReceiver;
+
+    fn replay(fixtures: &Arc<Fixtures>, request: &Request) -> Result<Response> {
+        match fixtures.intercept(request)? {
+            Intercept::Replay(response) => {
+                Ok(response.into_response(request.ctx().info().clone())?)
+            }
+            Intercept::Send(_) => panic!("request should be replayed"),
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() -> Result<()> {
+        let mock = mock("POST", "/fixture")
+            .with_status(201)
+            .with_header("X-Served-By", "mock")
+            .with_header("Set-Cookie", "session=cookie-secret")
+            .with_body("response")
+            .create();
+
+        let dir = tempdir()?;
+        let path = dir.path().join("fixtures");
+        let url = Url::parse(&mockito::server_url())?.join("fixture")?;
+        let request = Request::post(url.clone())
+            .header("Content-Type", "text/plain")
+            .header("Authorization", "secret")
+            .body("request");
+
+        let recording = Arc::new(Fixtures::new(FixtureMode::Record(path.clone())));
+        let recorder = match recording.intercept(&request)? {
+            Intercept::Send(Some(recorder)) => recorder,
+            _ => panic!("request should be recorded"),
+        };
+        let response = request.clone().send()?;
+        recorder.record(&response)?;
+        mock.assert();
+
+        let content = fs::read_to_string(&path)?;
+        assert!(!content.contains("secret"));
+        assert!(!content.contains("set-cookie"));
+
+        let replaying = Arc::new(Fixtures::new(FixtureMode::Replay(path)));
+        let replayed = replay(&replaying, &request)?;
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(replayed.headers()["x-served-by"], "mock");
+        assert!(replayed.headers().get("set-cookie").is_none());
+        assert_eq!(replayed.body(), b"response");
+
+        // Requests with a different body are not matched.
+        assert!(replaying.intercept(&request.clone().body("other")).is_err());
+
+        // Headers not used for matching are ignored.
+        replay(
+            &replaying,
+            &request.clone().header("Authorization", "other"),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_in_order() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("fixtures");
+        let request = Request::get(Url::parse("http://example.com/a")?);
+
+        let recording = Arc::new(Fixtures::new(FixtureMode::Record(path.clone())));
+        for body in ["1", "2"] {
+            let recorder = match recording.intercept(&request)? {
+                Intercept::Send(Some(recorder)) => recorder,
+                _ => panic!("request should be recorded"),
+            };
+            recorder.finish(RecordedResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: body.as_bytes().to_vec(),
+            })?;
+        }
+
+        let replaying = Arc::new(Fixtures::new(FixtureMode::Replay(path)));
+        assert_eq!(replay(&replaying, &request)?.body(), b"1");
+        assert_eq!(replay(&replaying, &request)?.body(), b"2");
+        assert_eq!(replay(&replaying, &request)?.body(), b"2");
+
+        let other = Request::get(Url::parse("http://example.com/b")?);
+        assert!(replaying.intercept(&other).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_and_replay_stream() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("fixtures");
+        let request = Request::get(Url::parse("http://example.com/stream")?);
+
+        let recording = Arc::new(Fixtures::new(FixtureMode::Record(path.clone())));
+        let recorder = match recording.intercept(&request)? {
+            Intercept::Send(recorder) => recorder,
+            _ => panic!("request should be sent"),
+        };
+        let rcv = TestReceiver::new();
+        let mut receiver = RecordingReceiver::new(rcv.clone(), recorder);
+        receiver.header(Header::Status(Version::HTTP_11, StatusCode::OK))?;
+        receiver.header(Header::Header(
+            HeaderName::from_static("x-served-by"),
+            HeaderValue::from_static("mock"),
+        ))?;
+        receiver.header(Header::Header(
+            HeaderName::from_static("set-cookie"),
+            HeaderValue::from_static("session=secret"),
+        ))?;
+        receiver.header(Header::EndOfHeaders)?;
+        receiver.chunk(b"hello ".to_vec())?;
+        receiver.chunk(b"world".to_vec())?;
+        receiver.done(Ok(()))?;
+        assert_eq!(rcv.chunks().concat(), b"hello world");
+        assert!(!fs::read_to_string(&path)?.contains("set-cookie"));
+
+        let replaying = Arc::new(Fixtures::new(FixtureMode::Replay(path)));
+        let response = match replaying.intercept(&request)? {
+            Intercept::Replay(response) => response,
+            _ => panic!("request should be replayed"),
+        };
+        let rcv = TestReceiver::new();
+        response.replay_to(rcv.clone())?;
+        assert_eq!(rcv.status(), Some(StatusCode::OK));
+        assert_eq!(rcv.headers().len(), 1);
+        assert_eq!(rcv.chunks().concat(), b"hello world");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_missing_fixture_file() -> Result<()> {
+        let dir = tempdir()?;
+        let replaying = Arc::new(Fixtures::new(FixtureMode::Replay(
+            dir.path().join("missing"),
+        )));
+        let request = Request::get(Url::parse("http://example.com/a")?);
+        assert!(replaying.intercept(&request).is_err());
+        Ok(())
+    }
+}
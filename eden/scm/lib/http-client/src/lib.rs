@@ -13,6 +13,7 @@ mod client;
 mod driver;
 mod errors;
 mod event_listeners;
+mod fixture;
 mod handler;
 mod header;
 mod pool;
@@ -30,6 +31,8 @@ pub use curl::easy::HttpVersion;
 pub use errors::Abort;
 pub use errors::HttpClientError;
 pub use errors::TlsError;
+pub use fixture::set_fixture_mode;
+pub use fixture::FixtureMode;
 pub use header::Header;
 pub use progress::Progress;
 pub use receiver::Receiver;
@@ -33,6 +33,9 @@ use url::Url;
 use crate::errors::HttpClientError;
 use crate::event_listeners::RequestCreationEventListeners;
 use crate::event_listeners::RequestEventListeners;
+use crate::fixture::Intercept;
+use crate::fixture::RecordingReceiver;
+use crate::fixture::{self};
 use crate::handler::Buffered;
 use crate::handler::HandlerExt;
 use crate::handler::Streaming;
@@ -404,10 +407,25 @@ impl Request {
         self
     }
 
+    /// Authenticate with an OAuth bearer token.
+    pub fn bearer_token(mut self, token: impl AsRef<str>) -> Self {
+        self.set_bearer_token(token);
+        self
+    }
+
+    /// Authenticate with an OAuth bearer token.
+    pub fn set_bearer_token(&mut self, token: impl AsRef<str>) -> &mut Self {
+        self.set_header("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+
     pub fn get_header_mut<'a>(&'a mut self, name: impl ToString) -> Option<&'a mut String> {
         self.headers.get_mut(&name.to_string().to_lowercase())
     }
 
+    pub(crate) fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
     /// Specify a client certificate for TLS mutual authentiation.
     ///
     /// This should be a path to a base64-encoded PEM file containing the
@@ -577,6 +595,13 @@ impl Request {
     /// concurrent requests or large requests that require
     /// progress reporting.
     pub fn send(self) -> Result<Response, HttpClientError> {
+        let recorder = match fixture::intercept(&self)? {
+            Intercept::Send(recorder) => recorder,
+            Intercept::Replay(response) => {
+                return response.into_response(self.ctx.info().clone());
+            }
+        };
+
         let mut easy: Easy2<Buffered> = self.try_into()?;
         let res = easy.perform();
         let ctx = easy.get_mut().request_context_mut();
@@ -592,7 +617,11 @@ impl Request {
             }
         }
 
-        Response::try_from(easy.get_mut())
+        let response = Response::try_from(easy.get_mut())?;
+        if let Some(recorder) = recorder {
+            recorder.record(&response)?;
+        }
+        Ok(response)
     }
 
     /// Execute this request asynchronously.
@@ -793,7 +822,12 @@ pub struct StreamRequest<R> {
 
 impl<R: Receiver> StreamRequest<R> {
     pub fn send(self) -> Result<(), HttpClientError> {
-        let mut easy: Easy2<Streaming<R>> = self.try_into()?;
+        let request = match fixture::intercept_stream(self)? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+
+        let mut easy: Easy2<Streaming<RecordingReceiver<R>>> = request.try_into()?;
         let res = easy.perform().map_err(Into::into);
         let _ = easy
             .get_mut()
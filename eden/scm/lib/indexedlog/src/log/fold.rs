@@ -189,13 +189,14 @@ impl FoldState {
         }
 
         // Catch up by processing remaining entries one by one.
-        let mut iter = log.iter();
+        // Deleted entries are still included until compaction.
+        let mut iter = log.iter_including_deleted();
         if self.offset > 0 {
             iter.next_offset = self.offset;
         }
         for entry in iter {
             let entry = entry?;
-            self.fold.accumulate(entry)?;
+            self.fold.accumulate(&entry)?;
         }
 
         // Set self state as up-to-date, and write to disk.
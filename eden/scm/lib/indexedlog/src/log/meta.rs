@@ -16,6 +16,7 @@ use vlqencoding::VLQDecode;
 use vlqencoding::VLQEncode;
 
 use crate::errors::IoResultExt;
+use crate::log::CompressionType;
 use crate::utils::atomic_read;
 use crate::utils::atomic_write;
 use crate::utils::xxhash;
@@ -33,6 +34,12 @@ pub struct LogMetadata {
     /// Used to detect non-append-only changes.
     /// Conceptually similar to "create time".
     pub(crate) epoch: u64,
+
+    /// Length of the tombstone file, which records deleted entries.
+    pub(crate) tombstone_len: u64,
+
+    /// Compression type used for new entries.
+    pub(crate) compression_type: CompressionType,
 }
 
 impl LogMetadata {
@@ -78,10 +85,27 @@ impl LogMetadata {
         // format. So not being able to read it (because EOF) is not fatal.
         let epoch = reader.read_vlq().unwrap_or_default();
 
+        // 'tombstone_len' is optional for the same reason.
+        let tombstone_len = reader.read_vlq().unwrap_or_default();
+
+        // 'compression_type' is optional for the same reason.
+        let compression_type: u64 = reader.read_vlq().unwrap_or_default();
+        let compression_type = match compression_type {
+            0 => CompressionType::None,
+            1 => CompressionType::Zstd,
+            2 => CompressionType::Lz4,
+            value => {
+                let msg = format!("unsupported compression type {}", value);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+        };
+
         Ok(Self {
             primary_len,
             indexes,
             epoch,
+            tombstone_len,
+            compression_type,
         })
     }
 
@@ -97,6 +121,12 @@ impl LogMetadata {
             buf.write_vlq(*len)?;
         }
         buf.write_vlq(self.epoch)?;
+        buf.write_vlq(self.tombstone_len)?;
+        buf.write_vlq(match self.compression_type {
+            CompressionType::None => 0u64,
+            CompressionType::Zstd => 1,
+            CompressionType::Lz4 => 2,
+        })?;
         writer.write_all(Self::HEADER)?;
         writer.write_vlq(xxhash(&buf))?;
         writer.write_vlq(buf.len())?;
@@ -131,6 +161,8 @@ impl LogMetadata {
             primary_len: len,
             indexes: BTreeMap::new(),
             epoch: utils::epoch(),
+            tombstone_len: 0,
+            compression_type: CompressionType::None,
         }
     }
 
@@ -148,19 +180,50 @@ mod tests {
 
     use super::*;
 
+    fn compression_type_from_u8(value: u8) -> CompressionType {
+        match value % 3 {
+            0 => CompressionType::None,
+            1 => CompressionType::Zstd,
+            _ => CompressionType::Lz4,
+        }
+    }
+
+    #[test]
+    fn test_read_meta_without_tombstone_len() {
+        // Metadata written by an older version does not have 'tombstone_len'.
+        let meta = LogMetadata {
+            primary_len: 12,
+            indexes: Default::default(),
+            epoch: 3,
+            tombstone_len: 0,
+            compression_type: CompressionType::None,
+        };
+        let mut old_buf = Vec::new();
+        old_buf.write_vlq(12u64).unwrap();
+        old_buf.write_vlq(0usize).unwrap();
+        old_buf.write_vlq(3u64).unwrap();
+        let mut old = LogMetadata::HEADER.to_vec();
+        old.write_vlq(xxhash(&old_buf)).unwrap();
+        old.write_vlq(old_buf.len()).unwrap();
+        old.write_all(&old_buf).unwrap();
+        assert_eq!(LogMetadata::read(&old[..]).unwrap(), meta);
+    }
+
     quickcheck! {
-        fn test_roundtrip_meta(primary_len: u64, indexes: BTreeMap<String, u64>, epoch: u64) -> bool {
+        fn test_roundtrip_meta(primary_len: u64, indexes: BTreeMap<String, u64>, epoch: u64, tombstone_len: u64, compression: u8) -> bool {
             let mut buf = Vec::new();
-            let meta = LogMetadata { primary_len, indexes, epoch,  };
+            let compression_type = compression_type_from_u8(compression);
+            let meta = LogMetadata { primary_len, indexes, epoch, tombstone_len, compression_type };
             meta.write(&mut buf).expect("write");
             let mut cur = Cursor::new(buf);
             let meta_read = LogMetadata::read(&mut cur).expect("read");
             meta_read == meta
         }
 
-        fn test_roundtrip_meta_file(primary_len: u64, indexes: BTreeMap<String, u64>, epoch: u64) -> bool {
+        fn test_roundtrip_meta_file(primary_len: u64, indexes: BTreeMap<String, u64>, epoch: u64, tombstone_len: u64, compression: u8) -> bool {
             let dir = tempdir().unwrap();
-            let meta = LogMetadata { primary_len, indexes, epoch,  };
+            let compression_type = compression_type_from_u8(compression);
+            let meta = LogMetadata { primary_len, indexes, epoch, tombstone_len, compression_type };
             let path = dir.path().join("meta");
             meta.write_file(&path, false).expect("write_file");
             let meta_read = LogMetadata::read_file(&path).expect("read_file");
@@ -176,6 +239,8 @@ mod tests {
             primary_len: 1,
             indexes: Default::default(),
             epoch: 42,
+            tombstone_len: 0,
+            compression_type: CompressionType::None,
         };
         let mut buf: Vec<u8> = Vec::new();
         meta.write(&mut buf).unwrap();
@@ -19,21 +19,28 @@
 //   ENTRY_LIST := '' | ENTRY_LIST + ENTRY
 //   ENTRY := ENTRY_FLAGS + LEN(CONTENT) + CHECKSUM + CONTENT
 //   CHECKSUM := '' | XXHASH64(CONTENT) | XXHASH32(CONTENT)
+//   CONTENT := DATA | ZSTD(DATA) | LZ4(DATA)
 //
 // Metadata:
 //   META := HEADER + XXHASH64(DATA) + LEN(DATA) + DATA
 //   HEADER := 'meta\0'
-//   DATA := LEN(LOG) + LEN(INDEXES) + INDEXES
+//   DATA := LEN(LOG) + LEN(INDEXES) + INDEXES + EPOCH + LEN(TOMBSTONE) +
+//           COMPRESSION_TYPE
 //   INDEXES := '' | INDEXES + INDEX
 //   INDEX := LEN(NAME) + NAME + INDEX_LOGIC_LEN
 //
+// Tombstone:
+//   TOMBSTONE := '' | TOMBSTONE + RECORD
+//   RECORD := ENTRY_OFFSET + XXHASH32(ENTRY_OFFSET)
+//
 // Indexes:
 //   See `index.rs`.
 //
-// Integers are VLQ encoded, except for XXHASH64 and XXHASH32, which uses
-// LittleEndian encoding.
+// Integers are VLQ encoded, except for XXHASH64, XXHASH32 and ENTRY_OFFSET,
+// which uses LittleEndian encoding.
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::{self};
@@ -79,8 +86,10 @@ mod path;
 mod repair;
 #[cfg(test)]
 pub(crate) mod tests;
+mod tombstone;
 
 pub use open_options::ChecksumType;
+pub use open_options::CompressionType;
 pub use open_options::FlushFilterContext;
 pub use open_options::FlushFilterFunc;
 pub use open_options::FlushFilterOutput;
@@ -100,9 +109,13 @@ pub(crate) const PRIMARY_FILE: &str = "log";
 const PRIMARY_HEADER: &[u8] = b"indexedlog0\0";
 const PRIMARY_START_OFFSET: u64 = 12; // PRIMARY_HEADER.len() as u64;
 pub(crate) const META_FILE: &str = "meta";
+const PRIMARY_COMPACT_FILE: &str = "log.compact";
+pub(crate) const META_COMPACT_FILE: &str = "meta.compact";
 
 const ENTRY_FLAG_HAS_XXHASH64: u32 = 1;
 const ENTRY_FLAG_HAS_XXHASH32: u32 = 2;
+const ENTRY_FLAG_ZSTD: u32 = 4;
+const ENTRY_FLAG_LZ4: u32 = 8;
 
 // 1MB index checksum. This makes checksum file within one block (4KB) for 512MB index.
 const INDEX_CHECKSUM_CHUNK_SIZE_LOGARITHM: u32 = 20;
@@ -120,6 +133,9 @@ const INDEX_CHECKSUM_CHUNK_SIZE_LOGARITHM: u32 = 20;
 ///   non-append-only checksum file.
 /// - A small "metadata" file which records the logic lengths (in bytes)
 ///   for the log and index files.
+/// - An optional append-only "tombstone" file which records entries deleted
+///   by [`Log::delete`]. Deleted entries are skipped by lookups and
+///   iterators, and removed physically by [`Log::compact`].
 ///
 /// Reading is lock-free because the log and indexes are append-only.
 /// Writes are buffered in memory. Flushing in-memory parts to
@@ -141,12 +157,17 @@ pub struct Log {
     // probably fine considering index corruptions are rare.
     index_corrupted: bool,
     open_options: OpenOptions,
+    // Offsets of deleted entries, including pending deletions.
+    deleted: HashSet<u64>,
+    // Offsets of deleted entries that are not written to disk yet.
+    dirty_deleted: Vec<u64>,
 }
 
 /// Iterator over all entries in a [`Log`].
 pub struct LogIter<'a> {
     next_offset: u64,
     errored: bool,
+    skip_deleted: bool,
     log: &'a Log,
 }
 
@@ -246,6 +267,13 @@ impl Log {
         let result: crate::Result<_> = (|| {
             let data = data.as_ref();
 
+            // Keep the entry uncompressed if compression does not save space.
+            let compressed = self.compress(data)?;
+            let (compression_flag, content) = match &compressed {
+                Some((flag, content)) if content.len() < data.len() => (*flag, &content[..]),
+                _ => (0, data),
+            };
+
             let checksum_type = if self.open_options.checksum_type == ChecksumType::Auto {
                 // xxhash64 is slower for smaller data. A quick benchmark on x64 platform shows:
                 //
@@ -264,7 +292,7 @@ impl Log {
                 //  120       3000      3428
                 //  128       3459      4266
                 const XXHASH64_THRESHOLD: usize = 88;
-                if data.len() >= XXHASH64_THRESHOLD {
+                if content.len() >= XXHASH64_THRESHOLD {
                     ChecksumType::Xxhash64
                 } else {
                     ChecksumType::Xxhash32
@@ -275,14 +303,11 @@ impl Log {
 
             let offset = self.meta.primary_len + self.mem_buf.len() as u64;
 
-            // Design note: Currently checksum_type is the only thing that decides
-            // entry_flags.  Entry flags is not designed to just cover different
-            // checksum types.  For example, if we'd like to introduce transparent
-            // compression (maybe not a good idea since it can be more cleanly built
-            // at an upper layer), or some other ways to store data (ex. reference
-            // to other data, or fixed length data), they can probably be done by
-            // extending the entry type.
-            let mut entry_flags = 0;
+            // Design note: Entry flags decide the checksum type and the
+            // compression type. Entry flags can be extended to support other
+            // ways to store data (ex. reference to other data, or fixed length
+            // data).
+            let mut entry_flags = compression_flag;
             entry_flags |= match checksum_type {
                 ChecksumType::Xxhash64 => ENTRY_FLAG_HAS_XXHASH64,
                 ChecksumType::Xxhash32 => ENTRY_FLAG_HAS_XXHASH32,
@@ -290,26 +315,28 @@ impl Log {
             };
 
             self.mem_buf.write_vlq(entry_flags).infallible()?;
-            self.mem_buf.write_vlq(data.len()).infallible()?;
+            self.mem_buf.write_vlq(content.len()).infallible()?;
 
             match checksum_type {
                 ChecksumType::Xxhash64 => {
                     self.mem_buf
-                        .write_u64::<LittleEndian>(xxhash(data))
+                        .write_u64::<LittleEndian>(xxhash(content))
                         .infallible()?;
                 }
                 ChecksumType::Xxhash32 => {
                     self.mem_buf
-                        .write_u32::<LittleEndian>(xxhash32(data))
+                        .write_u32::<LittleEndian>(xxhash32(content))
                         .infallible()?;
                 }
                 ChecksumType::Auto => unreachable!(),
             };
             let data_offset = self.meta.primary_len + self.mem_buf.len() as u64;
+            let next_offset = data_offset + content.len() as u64;
 
-            self.mem_buf.write_all(data).infallible()?;
-            self.update_indexes_for_in_memory_entry(data, offset, data_offset)?;
-            self.update_fold_for_in_memory_entry(data, offset, data_offset)?;
+            self.mem_buf.write_all(content).infallible()?;
+            let is_compressed = compression_flag != 0;
+            self.update_indexes_for_in_memory_entry(data, offset, data_offset, is_compressed)?;
+            self.update_fold_for_in_memory_entry(data, offset, next_offset)?;
 
             if let Some(threshold) = self.open_options.auto_sync_threshold {
                 if self.mem_buf.len() as u64 >= threshold {
@@ -333,6 +360,40 @@ impl Log {
             .context(|| format!("  Log.dir = {:?}", self.dir))
     }
 
+    /// Delete entries matching `key` using the given index. The `index_id`
+    /// is the index of `index_defs` passed to [`Log::open`].
+    ///
+    /// Deleted entries are skipped by lookups and [`Log::iter`]. Similar to
+    /// [`Log::append`], the deletion is in-memory until [`Log::sync`].
+    /// Deleted entries still take space on disk until [`Log::compact`].
+    ///
+    /// Indexes and folds are not changed. Indexes still contain keys of
+    /// deleted entries, and folds still include deleted entries until
+    /// [`Log::compact`].
+    ///
+    /// Return the count of newly deleted entries.
+    pub fn delete<K: AsRef<[u8]>>(&mut self, index_id: usize, key: K) -> crate::Result<usize> {
+        let result: crate::Result<_> = (|| {
+            self.maybe_return_index_error()?;
+            let index = self.get_index(index_id)?;
+            let offsets = index
+                .get(&key)?
+                .values(index)
+                .collect::<crate::Result<Vec<u64>>>()?;
+            let mut count = 0;
+            for offset in offsets {
+                if self.deleted.insert(offset) {
+                    self.dirty_deleted.push(offset);
+                    count += 1;
+                }
+            }
+            Ok(count)
+        })();
+        result
+            .context(|| format!("in Log::delete({}, {:?})", index_id, key.as_ref()))
+            .context(|| format!("  Log.dir = {:?}", self.dir))
+    }
+
     /// Remove dirty (in-memory) state. Restore the [`Log`] to the state as
     /// if it's just loaded from disk without modifications.
     pub fn clear_dirty(&mut self) -> crate::Result<()> {
@@ -342,6 +403,9 @@ impl Log {
                 index.clear_dirty();
             }
             self.mem_buf.clear();
+            for offset in self.dirty_deleted.drain(..) {
+                self.deleted.remove(&offset);
+            }
             self.all_folds = self.disk_folds.clone();
             self.update_indexes_for_on_disk_entries()?;
             Ok(())
@@ -382,6 +446,15 @@ impl Log {
         } else {
             Box::pin(Vec::new())
         };
+        let mut deleted = self.deleted.clone();
+        let dirty_deleted = if copy_dirty {
+            self.dirty_deleted.clone()
+        } else {
+            for offset in self.dirty_deleted.iter() {
+                deleted.remove(offset);
+            }
+            Vec::new()
+        };
 
         {
             // Update external key buffer of indexes to point to the new mem_buf.
@@ -413,6 +486,8 @@ impl Log {
             .clone(),
             index_corrupted: false,
             open_options: self.open_options.clone(),
+            deleted,
+            dirty_deleted,
         };
 
         if !copy_dirty {
@@ -468,7 +543,7 @@ impl Log {
             }
 
             // Read-only fast path - no need to take directory lock.
-            if self.mem_buf.is_empty() {
+            if self.mem_buf.is_empty() && self.dirty_deleted.is_empty() {
                 if let Ok(meta) = Self::load_or_create_meta(&self.dir, false) {
                     let changed = self.meta != meta;
                     let truncated = self.meta.epoch != meta.epoch;
@@ -511,6 +586,30 @@ impl Log {
             if !truncated {
                 check_append_only(self, &meta)?;
             }
+            if let Some(compression_type) = self.open_options.compression_type {
+                // Record the compression type so it is used by default.
+                meta.compression_type = compression_type;
+            }
+
+            // Pending deletions of on-disk entries. Pending deletions of dirty
+            // entries are applied by skipping them in `iter_dirty`, or by
+            // writing tombstones below.
+            let disk_deleted: Vec<u64> = self
+                .dirty_deleted
+                .iter()
+                .cloned()
+                .filter(|&offset| offset < self.meta.primary_len)
+                .collect();
+            if truncated && !disk_deleted.is_empty() {
+                // Offsets no longer match entries.
+                return Err(crate::Error::path(
+                    &dir,
+                    format!(
+                        "cannot write pending deletions since epoch has changed ({} to {})",
+                        self.meta.epoch, meta.epoch
+                    ),
+                ));
+            }
 
             // Cases where Log and Indexes need to be reloaded.
             if changed && self.open_options.flush_filter.is_some() {
@@ -527,14 +626,19 @@ impl Log {
                     let content = entry?;
                     let context = FlushFilterContext { log: &log };
                     // Re-insert entries to that clean log.
-                    match filter(&context, content)
+                    match filter(&context, &content)
                         .map_err(|err| crate::Error::wrap(err, "failed to run filter function"))?
                     {
                         FlushFilterOutput::Drop => {}
-                        FlushFilterOutput::Keep => log.append(content)?,
+                        FlushFilterOutput::Keep => log.append(&content)?,
                         FlushFilterOutput::Replace(content) => log.append(content)?,
                     }
                 }
+                for offset in disk_deleted {
+                    if log.deleted.insert(offset) {
+                        log.dirty_deleted.push(offset);
+                    }
+                }
 
                 // Replace "self" so we can continue flushing the updated data.
                 *self = log;
@@ -610,9 +714,30 @@ impl Log {
                     .context(&primary_path, "cannot fsync")?;
             }
 
+            // Dirty entries were written at `meta.primary_len`, which might
+            // be different from `self.meta.primary_len`. Translate offsets.
+            let deleted: Vec<u64> = self
+                .dirty_deleted
+                .iter()
+                .map(|&offset| {
+                    if offset < self.meta.primary_len {
+                        offset
+                    } else {
+                        offset - self.meta.primary_len + meta.primary_len
+                    }
+                })
+                .collect();
+
             meta.primary_len += self.mem_buf.len() as u64;
             self.mem_buf.clear();
 
+            // Append to the tombstone file.
+            if !deleted.is_empty() {
+                meta.tombstone_len =
+                    tombstone::write(&dir, meta.tombstone_len, &deleted, self.open_options.fsync)?;
+            }
+            self.dirty_deleted.clear();
+
             // Step 3: Reload primary log and indexes to get the latest view.
             let (disk_buf, indexes) = Self::load_log_and_indexes(
                 &self.dir,
@@ -644,6 +769,10 @@ impl Log {
             self.disk_buf = disk_buf;
             self.indexes = indexes;
             self.meta = meta;
+            if changed {
+                // Other processes might have deleted entries.
+                self.deleted = Self::load_deleted(&self.dir, &self.meta)?;
+            }
 
             // Step 4: Update the indexes and folds. Optionally flush them.
             self.update_indexes_for_on_disk_entries()?;
@@ -663,6 +792,35 @@ impl Log {
             .context(|| format!("  Log.dir = {:?}", self.dir))
     }
 
+    /// Write pending deletions of on-disk entries, without writing dirty
+    /// entries.
+    ///
+    /// This is used internally by [`RotateLog`], which might write dirty
+    /// entries to a different [`Log`].
+    pub(crate) fn sync_deleted(&mut self) -> crate::Result<()> {
+        let primary_len = self.meta.primary_len;
+        let disk_deleted: Vec<u64> = self
+            .dirty_deleted
+            .iter()
+            .cloned()
+            .filter(|&offset| offset < primary_len)
+            .collect();
+        if disk_deleted.is_empty() {
+            return Ok(());
+        }
+        if self.mem_buf.is_empty() {
+            self.sync()?;
+        } else {
+            // Sync a clean clone so dirty entries are not written.
+            let mut log = self.try_clone_without_dirty()?;
+            log.deleted.extend(disk_deleted.iter().cloned());
+            log.dirty_deleted = disk_deleted;
+            log.sync()?;
+            self.dirty_deleted.retain(|&offset| offset >= primary_len);
+        }
+        Ok(())
+    }
+
     /// Write (updated) lagging indexes back to disk.
     /// Usually called after `update_indexes_for_on_disk_entries`.
     /// This function might change `self.meta`. Be sure to write `self.meta` to
@@ -729,6 +887,16 @@ impl Log {
         self.disk_buf.slice_to_bytes(slice)
     }
 
+    /// Convert an entry returned by [`Log::lookup`] or [`Log::iter`] to
+    /// [`Bytes`]. Do not copy the entry if it's from the main on-disk buffer
+    /// or if it was decompressed.
+    pub fn entry_to_bytes(&self, entry: Cow<[u8]>) -> Bytes {
+        match entry {
+            Cow::Borrowed(slice) => self.slice_to_bytes(slice),
+            Cow::Owned(data) => Bytes::from(data),
+        }
+    }
+
     /// Convert a slice to [`Bytes`].
     /// Do not copy the slice if it's from the specified index buffer.
     pub fn index_slice_to_bytes(&self, index_id: usize, slice: &[u8]) -> Bytes {
@@ -901,10 +1069,138 @@ impl Log {
         Ok(message)
     }
 
+    /// Rewrite the log without deleted entries. Rebuild indexes.
+    ///
+    /// Pending changes are written by [`Log::sync`] first. Folds are
+    /// recalculated, since deleted entries are no longer included.
+    ///
+    /// This is not an append-only change. Other [`Log`] instances will
+    /// reload on their next [`Log::sync`], and pending deletions in them
+    /// cannot be written.
+    ///
+    /// Similar to [`Log::rebuild_indexes`], the function consumes the [`Log`]
+    /// object. Return the compacted [`Log`].
+    pub fn compact(self) -> crate::Result<Log> {
+        let dir = self.dir.clone();
+        let result: crate::Result<_> = (|mut this: Log| match this.dir.clone() {
+            GenericPath::Filesystem(dir) => {
+                this.sync()?;
+                let lock = ScopedDirLock::new(&dir)?;
+                let open_options = this.open_options.clone();
+                // Drop mmaps. This is required on Windows to replace files.
+                drop(this);
+                let log = open_options.open_with_lock(&dir.clone().into(), &lock)?;
+                log.compact_with_lock(&lock)
+            }
+            GenericPath::SharedMeta { .. } => Err(crate::Error::programming(
+                "compact() does not support Log with shared metadata",
+            )),
+            GenericPath::Nothing => {
+                let mut log = this.open_options.create_in_memory(GenericPath::Nothing)?;
+                for entry in this.iter() {
+                    log.append(entry?)?;
+                }
+                Ok(log)
+            }
+        })(self);
+
+        result
+            .context("in Log::compact")
+            .context(|| format!("  Log.dir = {:?}", dir))
+    }
+
+    fn compact_with_lock(self, lock: &ScopedDirLock) -> crate::Result<Log> {
+        if self.deleted.is_empty() {
+            return Ok(self);
+        }
+
+        // Copy entries that are not deleted.
+        let mut primary_buf = PRIMARY_HEADER.to_vec();
+        let mut iter = self.iter_including_deleted();
+        loop {
+            let offset = iter.next_offset;
+            match iter.next() {
+                None => break,
+                Some(entry) => {
+                    entry?;
+                    if !self.is_deleted(offset) {
+                        let entry = &self.disk_buf[offset as usize..iter.next_offset as usize];
+                        primary_buf.extend_from_slice(entry);
+                    }
+                }
+            }
+        }
+
+        let dir = self.dir.as_opt_path().unwrap().to_path_buf();
+        let fsync = self.open_options.fsync;
+        let open_options = self.open_options.clone();
+        let tombstone_len = self.meta.tombstone_len;
+        let compression_type = self.meta.compression_type;
+        drop(self);
+
+        // Stage the new primary log and metadata. The metadata has a new
+        // epoch so other Logs will reload. A crash here leaves the old log.
+        let mut meta = LogMetadata::new_with_primary_len(primary_buf.len() as u64);
+        meta.compression_type = compression_type;
+        let staged_primary_path = dir.join(PRIMARY_COMPACT_FILE);
+        let staged_meta_path = dir.join(META_COMPACT_FILE);
+        utils::atomic_write_plain(&staged_primary_path, &primary_buf, fsync)?;
+        meta.write_file(&staged_meta_path, fsync)?;
+        fail::fail_point!("log::compact::before-swap", |_| Err(
+            crate::Error::blank().message("failpoint log::compact::before-swap")
+        ));
+
+        // Swap the primary log, then the metadata. A crash between the two
+        // renames is recovered by `recover_compact` on the next open.
+        let primary_path = dir.join(PRIMARY_FILE);
+        fs::rename(&staged_primary_path, &primary_path)
+            .context(&primary_path, "cannot replace with compacted log")?;
+        fail::fail_point!("log::compact::after-primary-swap", |_| Err(
+            crate::Error::blank().message("failpoint log::compact::after-primary-swap")
+        ));
+        Self::recover_compact(&dir, lock)?;
+
+        // Tombstones are no longer covered by the metadata. Truncate them.
+        if tombstone_len > 0 {
+            tombstone::rewrite(&dir, &[], fsync)?;
+        }
+
+        // Rebuild indexes. This is similar to `delete_content`.
+        let path = GenericPath::from(dir);
+        let log = open_options.open_with_lock(&path, lock)?;
+        log.rebuild_indexes_with_lock(true, lock)?;
+        open_options.open_with_lock(&path, lock)
+    }
+
+    /// Finish a [`Log::compact`] that was interrupted after replacing the
+    /// primary log, or discard one that was interrupted before.
+    fn recover_compact(dir: &Path, _lock: &ScopedDirLock) -> crate::Result<()> {
+        let staged_meta_path = dir.join(META_COMPACT_FILE);
+        // Metadata can be written as a symlink. Do not follow it.
+        if fs::symlink_metadata(&staged_meta_path).is_err() {
+            return Ok(());
+        }
+        let staged_primary_path = dir.join(PRIMARY_COMPACT_FILE);
+        if staged_primary_path.exists() {
+            // The primary log was not replaced. Keep the old log.
+            fs::remove_file(&staged_primary_path)
+                .context(&staged_primary_path, "cannot remove staged log")?;
+            fs::remove_file(&staged_meta_path)
+                .context(&staged_meta_path, "cannot remove staged meta")?;
+        } else {
+            let meta_path = dir.join(META_FILE);
+            fs::rename(&staged_meta_path, &meta_path)
+                .context(&meta_path, "cannot replace with compacted meta")?;
+        }
+        Ok(())
+    }
+
     /// Look up an entry using the given index. The `index_id` is the index of
     /// `index_defs` passed to [`Log::open`].
     ///
-    /// Return an iterator of `Result<&[u8]>`, in reverse insertion order.
+    /// Return an iterator of `Result<Cow<[u8]>>`, in reverse insertion order.
+    /// Entries are borrowed from the log, except for compressed entries,
+    /// which are decompressed into owned buffers.
     pub fn lookup<K: AsRef<[u8]>>(&self, index_id: usize, key: K) -> crate::Result<LogLookupIter> {
         let result: crate::Result<_> = (|| {
             self.maybe_return_index_error()?;
@@ -1022,11 +1318,26 @@ impl Log {
     }
 
     /// Return an iterator for all entries.
+    ///
+    /// Like [`Log::lookup`], compressed entries are decompressed into owned
+    /// buffers.
     pub fn iter(&self) -> LogIter {
         LogIter {
             log: self,
             next_offset: PRIMARY_START_OFFSET,
             errored: false,
+            skip_deleted: true,
+        }
+    }
+
+    /// Return an iterator for all entries, including deleted ones.
+    ///
+    /// Used internally by folds, `repair` and `compact`, which care about
+    /// the physical layout.
+    pub(crate) fn iter_including_deleted(&self) -> LogIter<'_> {
+        LogIter {
+            skip_deleted: false,
+            ..self.iter()
         }
     }
 
@@ -1038,9 +1349,16 @@ impl Log {
             log: self,
             next_offset: self.meta.primary_len,
             errored: false,
+            skip_deleted: true,
         }
     }
 
+    /// Test if the entry at the given offset is deleted.
+    #[inline]
+    fn is_deleted(&self, offset: u64) -> bool {
+        !self.deleted.is_empty() && self.deleted.contains(&offset)
+    }
+
     /// Applies the given index function to the entry data and returns the index keys.
     pub fn index_func<'a>(
         &self,
@@ -1087,13 +1405,21 @@ impl Log {
     /// `offset` is the logical start offset of the entry.
     /// `data_offset` is the logical start offset of the real data (skips
     /// length, and checksum header in the entry).
+    /// `is_compressed` is `true` if the stored data is compressed. In that
+    /// case, `data` is the uncompressed data.
     fn update_indexes_for_in_memory_entry(
         &mut self,
         data: &[u8],
         offset: u64,
         data_offset: u64,
+        is_compressed: bool,
     ) -> crate::Result<()> {
-        let result = self.update_indexes_for_in_memory_entry_unchecked(data, offset, data_offset);
+        let result = self.update_indexes_for_in_memory_entry_unchecked(
+            data,
+            offset,
+            data_offset,
+            is_compressed,
+        );
         self.maybe_set_index_error(result)
     }
 
@@ -1102,10 +1428,10 @@ impl Log {
         &mut self,
         data: &[u8],
         offset: u64,
-        data_offset: u64,
+        next_offset: u64,
     ) -> crate::Result<()> {
         for fold_state in self.all_folds.iter_mut() {
-            fold_state.process_entry(data, offset, data_offset + data.len() as u64)?;
+            fold_state.process_entry(data, offset, next_offset)?;
         }
         Ok(())
     }
@@ -1115,10 +1441,17 @@ impl Log {
         data: &[u8],
         offset: u64,
         data_offset: u64,
+        is_compressed: bool,
     ) -> crate::Result<()> {
         for (index, def) in self.indexes.iter_mut().zip(&self.open_options.index_defs) {
             for index_output in (def.func)(data) {
                 match index_output {
+                    IndexOutput::Reference(range) if is_compressed => {
+                        // The key is not in the stored data. Embed it.
+                        assert!(range.start <= range.end && range.end <= data.len() as u64);
+                        let key = InsertKey::Embed(&data[range.start as usize..range.end as usize]);
+                        index.insert_advanced(key, InsertValue::Prepend(offset))?;
+                    }
                     IndexOutput::Reference(range) => {
                         assert!(range.start <= range.end && range.end <= data.len() as u64);
                         let start = range.start + data_offset;
@@ -1204,9 +1537,22 @@ impl Log {
             })?
         {
             count += 1;
-            let data = entry_result.data;
+            let decompressed;
+            let data = if entry_result.compression_flag != 0 {
+                decompressed = Self::decompress_entry(path, &entry_result, offset)?;
+                &decompressed[..]
+            } else {
+                &entry_result.data[..]
+            };
+            let is_compressed = entry_result.compression_flag != 0;
             for index_output in (def.func)(data) {
                 match index_output {
+                    IndexOutput::Reference(range) if is_compressed => {
+                        // The key is not in the stored data. Embed it.
+                        assert!(range.start <= range.end && range.end <= data.len() as u64);
+                        let key = InsertKey::Embed(&data[range.start as usize..range.end as usize]);
+                        index.insert_advanced(key, InsertValue::Prepend(offset))?;
+                    }
                     IndexOutput::Reference(range) => {
                         assert!(range.start <= range.end && range.end <= data.len() as u64);
                         let start = range.start + entry_result.data_offset;
@@ -1343,6 +1689,14 @@ impl Log {
         Ok((primary_buf, indexes))
     }
 
+    /// Read offsets of deleted entries from the tombstone file.
+    fn load_deleted(dir: &GenericPath, meta: &LogMetadata) -> crate::Result<HashSet<u64>> {
+        match dir.as_opt_path() {
+            Some(dir) if meta.tombstone_len > 0 => tombstone::read(dir, meta.tombstone_len),
+            _ => Ok(HashSet::new()),
+        }
+    }
+
     /// Return the reference to the [`GenericPath`] used to crate the [`Log`].
     pub fn path(&self) -> &GenericPath {
         &self.dir
@@ -1377,6 +1731,9 @@ impl Log {
     /// Read the entry at the given offset. Return `None` if offset is out of bound, or the content
     /// of the data, the real offset of the data, and the next offset. Raise errors if
     /// integrity-check failed.
+    ///
+    /// Compressed entries are decompressed to owned data. Their `data_offset`
+    /// is not meaningful.
     fn read_entry(&self, offset: u64) -> crate::Result<Option<EntryResult>> {
         let result = if offset < self.meta.primary_len {
             Self::read_entry_from_buf(&self.dir, &self.disk_buf, offset)?
@@ -1388,7 +1745,67 @@ impl Log {
             Self::read_entry_from_buf(&self.dir, &self.mem_buf, offset)?
                 .map(|entry_result| entry_result.offset(self.meta.primary_len))
         };
-        Ok(result)
+        match result {
+            Some(entry_result) if entry_result.compression_flag != 0 => {
+                let data = Self::decompress_entry(&self.dir, &entry_result, offset)?;
+                Ok(Some(EntryResult {
+                    data: Cow::Owned(data),
+                    ..entry_result
+                }))
+            }
+            _ => Ok(result),
+        }
+    }
+
+    /// Decompress the data of a compressed entry.
+    fn decompress_entry(
+        path: &GenericPath,
+        entry_result: &EntryResult,
+        offset: u64,
+    ) -> crate::Result<Vec<u8>> {
+        let data = &entry_result.data[..];
+        let result = match entry_result.compression_flag {
+            ENTRY_FLAG_ZSTD => zstdelta::apply(b"", data)
+                .map_err(|e| crate::Error::wrap(Box::new(e), "cannot decompress with zstd")),
+            ENTRY_FLAG_LZ4 => lz4_pyframe::decompress(data)
+                .map_err(|e| crate::Error::wrap(Box::new(e), "cannot decompress with lz4")),
+            flag => Err(crate::Error::programming(format!(
+                "unexpected compression flag {}",
+                flag
+            ))),
+        };
+        result.map_err(|e| {
+            let path = path.as_opt_path().unwrap_or_else(|| Path::new("<memory>"));
+            e.mark_corruption()
+                .message(format!("in entry at {} of {:?}", offset, path))
+        })
+    }
+
+    /// Compress data using the effective compression type.
+    ///
+    /// Return `None` if compression is disabled. Otherwise, return the
+    /// compression flag and the compressed data.
+    fn compress(&self, data: &[u8]) -> crate::Result<Option<(u32, Vec<u8>)>> {
+        let result = match self.compression_type() {
+            CompressionType::None => return Ok(None),
+            CompressionType::Zstd => zstdelta::diff(b"", data)
+                .map(|compressed| (ENTRY_FLAG_ZSTD, compressed))
+                .map_err(|e| crate::Error::wrap(Box::new(e), "cannot compress with zstd")),
+            CompressionType::Lz4 => lz4_pyframe::compress(data)
+                .map(|compressed| (ENTRY_FLAG_LZ4, compressed))
+                .map_err(|e| crate::Error::wrap(Box::new(e), "cannot compress with lz4")),
+        };
+        result.map(Some)
+    }
+
+    /// Compression type used for new entries.
+    ///
+    /// Decided by [`OpenOptions`], or the metadata if [`OpenOptions`] does
+    /// not specify it.
+    pub fn compression_type(&self) -> CompressionType {
+        self.open_options
+            .compression_type
+            .unwrap_or(self.meta.compression_type)
     }
 
     /// Read an entry at the given offset of the given buffer. Verify its integrity. Return the
@@ -1479,9 +1896,10 @@ impl Log {
         };
         if verified {
             Ok(Some(EntryResult {
-                data,
+                data: Cow::Borrowed(data),
                 data_offset: offset,
                 next_offset: end,
+                compression_flag: entry_flags & (ENTRY_FLAG_ZSTD | ENTRY_FLAG_LZ4),
             }))
         } else {
             Err(data_error(format!("integrity check failed at {}", offset)))
@@ -1593,9 +2011,12 @@ impl Log {
 
 /// "Pointer" to an entry. Used internally.
 struct EntryResult<'a> {
-    data: &'a [u8],
+    // Borrowed from the buffer, or owned if the entry is compressed.
+    data: Cow<'a, [u8]>,
     data_offset: u64,
     next_offset: u64,
+    // ENTRY_FLAG_ZSTD, ENTRY_FLAG_LZ4, or 0 if `data` is not compressed.
+    compression_flag: u32,
 }
 
 impl<'a> EntryResult<'a> {
@@ -1607,74 +2028,82 @@ impl<'a> EntryResult<'a> {
             // So it does not need to be changed.
             data_offset: self.data_offset,
             next_offset: self.next_offset + offset,
+            compression_flag: self.compression_flag,
         }
     }
 }
 
 impl<'a> Iterator for LogLookupIter<'a> {
-    type Item = crate::Result<&'a [u8]>;
+    type Item = crate::Result<Cow<'a, [u8]>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.errored {
             return None;
         }
-        match self.inner_iter.next() {
-            None => None,
-            Some(Err(err)) => {
-                self.errored = true;
+        let offset = loop {
+            match self.inner_iter.next() {
+                None => return None,
+                Some(Err(err)) => {
+                    self.errored = true;
+                    return Some(Err(err));
+                }
+                // Skip deleted entries.
+                Some(Ok(offset)) if self.log.is_deleted(offset) => continue,
+                Some(Ok(offset)) => break offset,
+            }
+        };
+        match self
+            .log
+            .read_entry(offset)
+            .context("in LogLookupIter::next")
+        {
+            Ok(Some(entry)) => Some(Ok(entry.data)),
+            Ok(None) => None,
+            Err(err) => {
+                // Do not set this iterator to an error state. It's possible
+                // that the index iterator still provides valid data, and
+                // only the "log" portion is corrupted.
+                //
+                // The index iterator is finite if integrity check is turned
+                // on. So trust it and don't worry about infinite iteration
+                // here.
                 Some(Err(err))
             }
-            Some(Ok(offset)) => match self
-                .log
-                .read_entry(offset)
-                .context("in LogLookupIter::next")
-            {
-                Ok(Some(entry)) => Some(Ok(entry.data)),
-                Ok(None) => None,
-                Err(err) => {
-                    // Do not set this iterator to an error state. It's possible
-                    // that the index iterator still provides valid data, and
-                    // only the "log" portion is corrupted.
-                    //
-                    // The index iterator is finite if integrity check is turned
-                    // on. So trust it and don't worry about infinite iteration
-                    // here.
-                    Some(Err(err))
-                }
-            },
         }
     }
 }
 
 impl<'a> LogLookupIter<'a> {
     /// A convenient way to get data.
-    pub fn into_vec(self) -> crate::Result<Vec<&'a [u8]>> {
+    pub fn into_vec(self) -> crate::Result<Vec<Cow<'a, [u8]>>> {
         self.collect()
     }
 }
 
 impl<'a> Iterator for LogIter<'a> {
-    type Item = crate::Result<&'a [u8]>;
+    type Item = crate::Result<Cow<'a, [u8]>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.errored {
-            return None;
-        }
-        match self
-            .log
-            .read_entry(self.next_offset)
-            .context("in LogIter::next")
-        {
-            Err(e) => {
-                self.errored = true;
-                Some(Err(e))
+        loop {
+            if self.errored {
+                return None;
             }
-            Ok(Some(entry_result)) => {
-                assert!(entry_result.next_offset > self.next_offset);
-                self.next_offset = entry_result.next_offset;
-                Some(Ok(entry_result.data))
+            let offset = self.next_offset;
+            match self.log.read_entry(offset).context("in LogIter::next") {
+                Err(e) => {
+                    self.errored = true;
+                    return Some(Err(e));
+                }
+                Ok(Some(entry_result)) => {
+                    assert!(entry_result.next_offset > offset);
+                    self.next_offset = entry_result.next_offset;
+                    if self.skip_deleted && self.log.is_deleted(offset) {
+                        continue;
+                    }
+                    return Some(Ok(entry_result.data));
+                }
+                Ok(None) => return None,
             }
-            Ok(None) => None,
         }
     }
 }
@@ -1728,7 +2157,7 @@ impl<'a> DoubleEndedIterator for LogRangeIter<'a> {
 impl Debug for Log {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         let mut count = 0;
-        let mut iter = self.iter();
+        let mut iter = self.iter_including_deleted();
         let bytes_per_line = 16;
         loop {
             let offset = iter.next_offset;
@@ -21,6 +21,7 @@ use crate::lock::ScopedDirLock;
 use crate::log::GenericPath;
 use crate::log::Log;
 use crate::log::LogMetadata;
+use crate::log::META_COMPACT_FILE;
 use crate::log::PRIMARY_START_OFFSET;
 
 const INDEX_FILE_PREFIX: &str = "index2-";
@@ -114,6 +115,19 @@ pub enum ChecksumType {
     Xxhash32,
 }
 
+/// What compression algorithm to use for entries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompressionType {
+    /// Do not compress entries.
+    None,
+
+    /// Use zstd. Usually compresses better.
+    Zstd,
+
+    /// Use lz4. Usually decompresses faster.
+    Lz4,
+}
+
 /// Options used to configured how an [`Log`] is opened.
 #[derive(Clone)]
 pub struct OpenOptions {
@@ -121,6 +135,7 @@ pub struct OpenOptions {
     pub(crate) fold_defs: Vec<FoldDef>,
     pub(crate) create: bool,
     pub(crate) checksum_type: ChecksumType,
+    pub(crate) compression_type: Option<CompressionType>,
     pub(crate) flush_filter: Option<FlushFilterFunc>,
     pub(crate) fsync: bool,
     pub(crate) auto_sync_threshold: Option<u64>,
@@ -237,6 +252,7 @@ impl OpenOptions {
             index_defs: Vec::new(),
             fold_defs: Vec::new(),
             checksum_type: ChecksumType::Auto,
+            compression_type: None,
             flush_filter: None,
             fsync: false,
             auto_sync_threshold: None,
@@ -303,6 +319,21 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the compression type for new entries.
+    ///
+    /// Entries are compressed by [`Log::append`] and decompressed when being
+    /// read. Index and fold functions see uncompressed data.
+    ///
+    /// The compression type is recorded in the metadata by [`Log::sync`].
+    /// If not set, the recorded compression type is used. Logs written
+    /// without compression can always be read.
+    ///
+    /// See [`CompressionType`] for details.
+    pub fn compression_type(mut self, compression_type: CompressionType) -> Self {
+        self.compression_type = Some(compression_type);
+        self
+    }
+
     /// Sets the flush filter function.
     ///
     /// The function will be called at [`Log::sync`] time, if there are
@@ -387,6 +418,8 @@ impl OpenOptions {
                 all_folds,
                 index_corrupted: false,
                 open_options: self.clone(),
+                deleted: Default::default(),
+                dirty_deleted: Vec::new(),
             })
         })();
 
@@ -431,14 +464,31 @@ impl OpenOptions {
         })?;
 
         let mem_buf = Box::pin(Vec::new());
-        let (disk_buf, indexes) = Log::load_log_and_indexes(
+        let (disk_buf, indexes) = match Log::load_log_and_indexes(
             dir,
             &meta,
             &self.index_defs,
             &mem_buf,
             reuse_indexes,
             self.fsync,
-        )?;
+        ) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                // The primary log might have been replaced by a concurrent or
+                // interrupted Log::compact. Wait for, or finish it and retry.
+                if let Some(fs_dir) = dir.as_opt_path() {
+                    if std::fs::symlink_metadata(fs_dir.join(META_COMPACT_FILE)).is_ok() {
+                        match lock {
+                            Some(lock) => Log::recover_compact(fs_dir, lock)?,
+                            None => Log::recover_compact(fs_dir, &dir.lock()?)?,
+                        }
+                        return self.open_internal(dir, None, lock);
+                    }
+                }
+                return Err(err);
+            }
+        };
+        let deleted = Log::load_deleted(dir, &meta)?;
         let disk_folds = self.empty_folds();
         let all_folds = disk_folds.clone();
         let mut log = Log {
@@ -451,6 +501,8 @@ impl OpenOptions {
             all_folds,
             index_corrupted: false,
             open_options: self.clone(),
+            deleted,
+            dirty_deleted: Vec::new(),
         };
         log.update_indexes_for_on_disk_entries()?;
         log.update_and_flush_disk_folds()?;
@@ -523,6 +575,7 @@ impl fmt::Debug for OpenOptions {
         write!(f, "fsync: {}, ", self.fsync)?;
         write!(f, "create: {}, ", self.create)?;
         write!(f, "checksum_type: {:?}, ", self.checksum_type)?;
+        write!(f, "compression_type: {:?}, ", self.compression_type)?;
         write!(f, "auto_sync_threshold: {:?}, ", self.auto_sync_threshold)?;
         let flush_filter_desc = match self.flush_filter {
             Some(ref _buf) => "Some(_)",
@@ -17,6 +17,8 @@ use std::path::Path;
 use crate::errors::IoResultExt;
 use crate::errors::ResultExt;
 use crate::lock::ScopedDirLock;
+use crate::log::tombstone;
+use crate::log::tombstone::TOMBSTONE_FILE;
 use crate::log::GenericPath;
 use crate::log::LogMetadata;
 use crate::log::OpenOptions;
@@ -133,6 +135,35 @@ impl OpenOptions {
             })()
             .context("while making sure log.length >= meta.log_length")?;
 
+            // Make sure tombstones can be read. Drop broken records.
+            (|| -> crate::Result<()> {
+                let mut meta = LogMetadata::read_file(&meta_path)?;
+                if meta.tombstone_len == 0 {
+                    return Ok(());
+                }
+                let tombstone_path = dir.join(TOMBSTONE_FILE);
+                let buf = match fs::read(&tombstone_path) {
+                    Ok(buf) => buf,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                    Err(e) => return Err(e).context(&tombstone_path, "cannot read"),
+                };
+                let len = buf.len().min(meta.tombstone_len as usize);
+                let (offsets, valid_len) = tombstone::decode(&buf[..len]);
+                if valid_len as u64 != meta.tombstone_len {
+                    // Valid records are a prefix of the file. No need to
+                    // rewrite the file.
+                    meta.tombstone_len = valid_len as u64;
+                    meta.write_file(&meta_path, self.fsync)?;
+                    message += &format!(
+                        "Reset tombstone size to {} ({} entries are deleted)\n",
+                        valid_len,
+                        offsets.len()
+                    );
+                }
+                Ok(())
+            })()
+            .context("while making sure tombstones are valid")?;
+
             // Reload the latest log without indexes.
             //
             // At this time log is likely open-able.
@@ -148,7 +179,7 @@ impl OpenOptions {
                 })
                 .context("cannot open log for repair")?;
 
-            let mut iter = log.iter();
+            let mut iter = log.iter_including_deleted();
 
             // Read entries until hitting a checksum error.
             let mut entry_count = 0;
@@ -218,6 +249,17 @@ impl OpenOptions {
                 log.meta.epoch = log.meta.epoch.wrapping_add(1);
                 log.disk_buf = mmap_path(&primary_path, valid_len)?;
 
+                // Drop tombstones of truncated entries. Their offsets can be
+                // reused by new entries.
+                if log.deleted.iter().any(|&offset| offset >= valid_len) {
+                    log.deleted.retain(|&offset| offset < valid_len);
+                    let mut offsets: Vec<u64> = log.deleted.iter().cloned().collect();
+                    offsets.sort_unstable();
+                    log.meta.tombstone_len =
+                        tombstone::rewrite(dir, &offsets, log.open_options.fsync)?;
+                    message += &format!("Rewrote tombstones for {} entries\n", offsets.len());
+                }
+
                 log.meta
                     .write_file(&meta_path, log.open_options.fsync)
                     .context("while trying to update metadata with verified log length")?;
@@ -162,7 +162,7 @@ fn test_iter_and_iter_dirty() {
 
     assert_eq!(
         log.iter().collect::<crate::Result<Vec<_>>>().unwrap(),
-        vec![b"2", b"4", b"3"]
+        vec![&b"2"[..], b"4", b"3"]
     );
     assert_eq!(
         log.iter().collect::<crate::Result<Vec<_>>>().unwrap(),
@@ -179,18 +179,18 @@ fn test_iter_and_iter_dirty() {
     );
     assert_eq!(
         log.iter().collect::<crate::Result<Vec<_>>>().unwrap(),
-        vec![b"2", b"4", b"3"]
+        vec![&b"2"[..], b"4", b"3"]
     );
 
     log.append(b"5").unwrap();
     log.append(b"1").unwrap();
     assert_eq!(
         log.iter_dirty().collect::<crate::Result<Vec<_>>>().unwrap(),
-        vec![b"5", b"1"]
+        vec![&b"5"[..], b"1"]
     );
     assert_eq!(
         log.iter().collect::<crate::Result<Vec<_>>>().unwrap(),
-        vec![b"2", b"4", b"3", b"5", b"1"]
+        vec![&b"2"[..], b"4", b"3", b"5", b"1"]
     );
 }
 
@@ -250,7 +250,8 @@ fn test_slice_to_bytes() {
     log.append(b"1231516").unwrap();
     log.sync().unwrap();
 
-    let slice = log.lookup(0, b"23").unwrap().into_vec().unwrap()[0];
+    let values = log.lookup(0, b"23").unwrap().into_vec().unwrap();
+    let slice = &values[0][..];
     assert_eq!(slice, b"1231516");
 
     // The bytes are zero-copy from the Log buffer.
@@ -344,16 +345,22 @@ fn test_index_manual() {
         // Lookups via index 0
         assert_eq!(
             log.lookup(0, b"34").unwrap().into_vec().unwrap(),
-            [b"3456", b"2345"]
+            [&b"3456"[..], b"2345"]
+        );
+        assert_eq!(
+            log.lookup(0, b"56").unwrap().into_vec().unwrap(),
+            [&b"3456"[..]]
+        );
+        assert_eq!(
+            log.lookup(0, b"78").unwrap().into_vec().unwrap(),
+            [&b"78"[..]]
         );
-        assert_eq!(log.lookup(0, b"56").unwrap().into_vec().unwrap(), [b"3456"]);
-        assert_eq!(log.lookup(0, b"78").unwrap().into_vec().unwrap(), [b"78"]);
         assert!(log.lookup(0, b"89").unwrap().into_vec().unwrap().is_empty());
 
         // Lookups via index 1
         assert_eq!(
             log.lookup(1, b"345").unwrap().into_vec().unwrap(),
-            [b"3456", b"2345"]
+            [&b"3456"[..], b"2345"]
         );
 
         log.sync().unwrap();
@@ -363,7 +370,10 @@ fn test_index_manual() {
         for key in [b"34", b"35"] {
             assert!(log.lookup(0, key).unwrap().into_vec().unwrap().is_empty());
         }
-        assert_eq!(log.lookup(0, b"56").unwrap().into_vec().unwrap(), [b"3456"]);
+        assert_eq!(
+            log.lookup(0, b"56").unwrap().into_vec().unwrap(),
+            [&b"3456"[..]]
+        );
 
         // Delete keys.
         let mut log = Log::open(dir.path(), get_index_defs(lag)).unwrap();
@@ -399,7 +409,7 @@ fn test_index_reorder() {
     log = Log::open(dir.path(), indexes).unwrap();
     assert_eq!(
         log.lookup(1, b"23").unwrap().into_vec().unwrap(),
-        [b"234", b"123"]
+        [&b"234"[..], b"123"]
     );
 }
 
@@ -466,7 +476,7 @@ fn test_lookup_prefix_and_range() {
             .1
             .collect::<Result<Vec<_>, _>>()
             .unwrap(),
-        vec![b"bb", b"bb"]
+        vec![&b"bb"[..], b"bb"]
     );
     assert_eq!(iter.next().unwrap().unwrap().0.as_ref(), b"aa");
     assert!(iter.next().is_none());
@@ -688,7 +698,7 @@ fn test_flush_filter() {
         .create(true)
         .flush_filter(Some(|ctx: &FlushFilterContext, bytes: &[u8]| {
             // "new" changes by log2 are visible.
-            assert_eq!(ctx.log.iter().nth(0).unwrap().unwrap(), b"log2");
+            assert_eq!(ctx.log.iter().nth(0).unwrap().unwrap(), &b"log2"[..]);
             Ok(match bytes.len() {
                 1 => FlushFilterOutput::Drop,
                 2 => FlushFilterOutput::Replace(b"cc".to_vec()),
@@ -947,7 +957,7 @@ fn test_repair() {
     let mut log = Log::open(dir.path(), Vec::new()).unwrap();
     assert_eq!(
         log.iter().collect::<Result<Vec<_>, _>>().unwrap(),
-        vec![b"abc", b"def"]
+        vec![&b"abc"[..], b"def"]
     );
 
     // Writing is recovered.
@@ -957,7 +967,7 @@ fn test_repair() {
     let log = Log::open(dir.path(), Vec::new()).unwrap();
     assert_eq!(
         log.iter().collect::<Result<Vec<_>, _>>().unwrap(),
-        vec![b"abc", b"def", b"pqr"]
+        vec![&b"abc"[..], b"def", b"pqr"]
     );
 }
 
@@ -1410,7 +1420,7 @@ fn test_clear_dirty() {
         log.clear_dirty().unwrap();
         assert_eq!(
             log.iter().collect::<Result<Vec<_>, _>>().unwrap(),
-            vec![[b'a'; 10]],
+            vec![&[b'a'; 10][..]],
         );
         assert_eq!(log.lookup_range(0, ..).unwrap().count(), 1);
     }
@@ -1527,6 +1537,394 @@ fn test_multithread_sync() {
     assert_eq!(count, THREAD_COUNT as u64 * WRITE_COUNT_PER_THREAD as u64);
 }
 
+/// Get `OpenOptions` with an index on the first byte.
+fn open_opts_first_byte_index(lag: u64) -> OpenOptions {
+    let index_func = |_data: &[u8]| vec![IndexOutput::Reference(0..1)];
+    OpenOptions::new()
+        .create(true)
+        .index_defs(vec![IndexDef::new("first", index_func).lag_threshold(lag)])
+}
+
+fn entries(log: &Log) -> Vec<Vec<u8>> {
+    log.iter().map(|e| e.unwrap().to_vec()).collect()
+}
+
+fn lookup(log: &Log, key: &[u8]) -> Vec<Vec<u8>> {
+    let iter = log.lookup(0, key).unwrap();
+    iter.map(|v| v.unwrap().to_vec()).collect()
+}
+
+#[test]
+fn test_delete() {
+    for lag in [0, 1000] {
+        let dir = tempdir().unwrap();
+        let opts = open_opts_first_byte_index(lag);
+        let mut log = opts.open(dir.path()).unwrap();
+        log.append(b"a1").unwrap();
+        log.append(b"b1").unwrap();
+        log.append(b"a2").unwrap();
+        log.sync().unwrap();
+        log.append(b"a3").unwrap();
+
+        // Delete both on-disk and in-memory entries.
+        assert_eq!(log.delete(0, b"a").unwrap(), 3);
+        assert_eq!(log.delete(0, b"a").unwrap(), 0);
+        assert_eq!(log.delete(0, b"c").unwrap(), 0);
+        assert_eq!(entries(&log), vec![b"b1"]);
+        assert!(log.iter_dirty().next().is_none());
+        assert!(lookup(&log, b"a").is_empty());
+        assert_eq!(lookup(&log, b"b"), vec![b"b1"]);
+        assert_eq!(log.lookup_prefix(0, b"").unwrap().count(), 2);
+
+        // Deletion is not visible to other instances before sync.
+        let mut log2 = opts.open(dir.path()).unwrap();
+        assert_eq!(entries(&log2), vec![b"a1", b"b1", b"a2"]);
+
+        log.sync().unwrap();
+        assert_eq!(entries(&log), vec![b"b1"]);
+        log2.sync().unwrap();
+        assert_eq!(entries(&log2), vec![b"b1"]);
+        assert!(lookup(&log2, b"a").is_empty());
+
+        let log3 = opts.open(dir.path()).unwrap();
+        assert_eq!(entries(&log3), vec![b"b1"]);
+        assert!(lookup(&log3, b"a").is_empty());
+
+        // Deleted keys can be appended again.
+        log2.append(b"a4").unwrap();
+        log2.sync().unwrap();
+        assert_eq!(lookup(&log2, b"a"), vec![b"a4"]);
+    }
+}
+
+#[test]
+fn test_delete_sync_with_changed_log() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0);
+    let mut log1 = opts.open(dir.path()).unwrap();
+    let mut log2 = opts.open(dir.path()).unwrap();
+
+    log1.append(b"x1").unwrap();
+    log1.sync().unwrap();
+    log2.sync().unwrap();
+
+    // log2 deletes an on-disk entry and an in-memory entry.
+    log2.append(b"y1").unwrap();
+    log2.append(b"w1").unwrap();
+    log2.delete(0, b"x").unwrap();
+    log2.delete(0, b"y").unwrap();
+
+    // log1 writes more entries. In-memory entries in log2 will be moved.
+    log1.append(b"z1").unwrap();
+    log1.append(b"y2").unwrap();
+    log1.sync().unwrap();
+
+    log2.sync().unwrap();
+    assert_eq!(entries(&log2), vec![b"z1", b"y2", b"w1"]);
+    assert_eq!(lookup(&log2, b"y"), vec![b"y2"]);
+
+    let log3 = opts.open(dir.path()).unwrap();
+    assert_eq!(entries(&log3), vec![b"z1", b"y2", b"w1"]);
+    assert_eq!(lookup(&log3, b"y"), vec![b"y2"]);
+}
+
+#[test]
+fn test_delete_clear_dirty_and_clone() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0);
+    let mut log = opts.open(dir.path()).unwrap();
+    log.append(b"a1").unwrap();
+    log.append(b"b1").unwrap();
+    log.sync().unwrap();
+    log.delete(0, b"a").unwrap();
+
+    let log2 = log.try_clone().unwrap();
+    assert_eq!(entries(&log2), vec![b"b1"]);
+    let log2 = log.try_clone_without_dirty().unwrap();
+    assert_eq!(entries(&log2), vec![b"a1", b"b1"]);
+
+    log.clear_dirty().unwrap();
+    assert_eq!(entries(&log), vec![b"a1", b"b1"]);
+    assert_eq!(lookup(&log, b"a"), vec![b"a1"]);
+    log.sync().unwrap();
+    let log = opts.open(dir.path()).unwrap();
+    assert_eq!(entries(&log), vec![b"a1", b"b1"]);
+}
+
+#[test]
+fn test_compact() {
+    for lag in [0, 1000] {
+        let dir = tempdir().unwrap();
+        let opts = open_opts_first_byte_index(lag);
+        let mut log = opts.open(dir.path()).unwrap();
+        for i in 0..100u8 {
+            log.append(&[b'a' + i % 4, i][..]).unwrap();
+        }
+        log.sync().unwrap();
+        let mut old_log = opts.open(dir.path()).unwrap();
+
+        log.delete(0, b"a").unwrap();
+        log.delete(0, b"c").unwrap();
+        log.append(b"c").unwrap();
+        let expected: Vec<Vec<u8>> = entries(&log).iter().map(|e| e.to_vec()).collect();
+        let len_before = log.sync().unwrap();
+
+        let log = log.compact().unwrap();
+        let len_after = log.meta.primary_len;
+        assert!(len_after < len_before);
+        assert_eq!(
+            fs::metadata(dir.path().join(PRIMARY_FILE)).unwrap().len(),
+            len_after
+        );
+        assert_eq!(log.meta.tombstone_len, 0);
+        assert!(log.deleted.is_empty());
+        assert_eq!(entries(&log), expected);
+        assert!(lookup(&log, b"a").is_empty());
+        assert_eq!(lookup(&log, b"b").len(), 25);
+        assert_eq!(lookup(&log, b"c"), vec![b"c"]);
+
+        // Other instances notice the change.
+        old_log.sync().unwrap();
+        assert_eq!(entries(&old_log), expected);
+        assert_eq!(lookup(&old_log, b"b").len(), 25);
+
+        let log = opts.open(dir.path()).unwrap();
+        assert_eq!(entries(&log), expected);
+        assert_eq!(lookup(&log, b"d").len(), 25);
+        assert_eq!(log.lookup_prefix(0, b"").unwrap().count(), 3);
+
+        // Compacting again is a no-op.
+        let log = log.compact().unwrap();
+        assert_eq!(log.meta.primary_len, len_after);
+    }
+}
+
+#[test]
+fn test_compact_with_pending_deletion_elsewhere() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0);
+    let mut log1 = opts.open(dir.path()).unwrap();
+    log1.append(b"a1").unwrap();
+    log1.append(b"b1").unwrap();
+    log1.sync().unwrap();
+    let mut log2 = opts.open(dir.path()).unwrap();
+
+    log1.delete(0, b"a").unwrap();
+    let log1 = log1.compact().unwrap();
+    assert_eq!(entries(&log1), vec![b"b1"]);
+
+    // Offsets in log2 no longer match entries. Pending deletions cannot be
+    // written.
+    log2.delete(0, b"b").unwrap();
+    assert!(log2.sync().is_err());
+    log2.clear_dirty().unwrap();
+    log2.sync().unwrap();
+    assert_eq!(entries(&log2), vec![b"b1"]);
+}
+
+#[test]
+fn test_compact_in_memory() {
+    let mut log = open_opts_first_byte_index(0)
+        .create_in_memory(GenericPath::Nothing)
+        .unwrap();
+    log.append(b"a1").unwrap();
+    log.append(b"b1").unwrap();
+    log.delete(0, b"a").unwrap();
+    let log = log.compact().unwrap();
+    assert!(log.deleted.is_empty());
+    assert_eq!(entries(&log), vec![b"b1"]);
+    assert_eq!(lookup(&log, b"b"), vec![b"b1"]);
+}
+
+#[test]
+fn test_repair_tombstone() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0);
+    let mut log = opts.open(dir.path()).unwrap();
+    log.append(b"a1").unwrap();
+    log.append(b"b1").unwrap();
+    log.append(b"c1").unwrap();
+    log.sync().unwrap();
+    log.delete(0, b"a").unwrap();
+    log.sync().unwrap();
+    log.delete(0, b"b").unwrap();
+    log.sync().unwrap();
+
+    // Corrupt the last tombstone record.
+    let tombstone_path = dir.path().join(tombstone::TOMBSTONE_FILE);
+    let mut buf = fs::read(&tombstone_path).unwrap();
+    *buf.last_mut().unwrap() ^= 1;
+    fs::write(&tombstone_path, &buf).unwrap();
+    assert!(opts.open(dir.path()).is_err());
+
+    let message = opts.repair(dir.path()).unwrap();
+    assert!(message.contains("Reset tombstone size"), "{}", message);
+    let log = opts.open(dir.path()).unwrap();
+    assert_eq!(entries(&log), vec![b"b1", b"c1"]);
+
+    // Truncating the log also drops related tombstones.
+    let mut log = opts.open(dir.path()).unwrap();
+    log.delete(0, b"c").unwrap();
+    log.sync().unwrap();
+    let primary_path = dir.path().join(PRIMARY_FILE);
+    let mut buf = fs::read(&primary_path).unwrap();
+    *buf.last_mut().unwrap() ^= 1;
+    fs::write(&primary_path, &buf).unwrap();
+
+    let message = opts.repair(dir.path()).unwrap();
+    assert!(message.contains("Rewrote tombstones"), "{}", message);
+    let mut log = opts.open(dir.path()).unwrap();
+    assert_eq!(entries(&log), vec![b"b1"]);
+    log.append(b"c2").unwrap();
+    log.sync().unwrap();
+    assert_eq!(entries(&log), vec![b"b1", b"c2"]);
+}
+
+fn compressible(prefix: u8, i: usize) -> Vec<u8> {
+    let mut data = vec![prefix];
+    data.extend(format!("{:0100}", i).bytes());
+    data
+}
+
+#[test]
+fn test_compression() {
+    for compression_type in [CompressionType::Zstd, CompressionType::Lz4] {
+        for lag in [0, 1000] {
+            let dir = tempdir().unwrap();
+            let opts = open_opts_first_byte_index(lag).compression_type(compression_type);
+            let mut log = opts.clone().open(dir.path()).unwrap();
+            assert_eq!(log.compression_type(), compression_type);
+            log.append(compressible(b'a', 1)).unwrap();
+            log.append(compressible(b'b', 2)).unwrap();
+            log.sync().unwrap();
+            log.append(compressible(b'a', 3)).unwrap();
+
+            // Entries are compressed on disk.
+            let size = fs::metadata(dir.path().join(PRIMARY_FILE)).unwrap().len();
+            assert!(size < 200, "log size {} is too large", size);
+
+            // Both on-disk and in-memory entries are decompressed on read.
+            let expected = vec![compressible(b'a', 3), compressible(b'a', 1)];
+            assert_eq!(lookup(&log, b"a"), expected);
+            assert_eq!(entries(&log).len(), 3);
+            assert_eq!(entries(&log)[1], &compressible(b'b', 2)[..]);
+            assert!(log.iter().all(|e| matches!(e.unwrap(), Cow::Owned(_))));
+
+            // Index functions see uncompressed data after reopen.
+            log.sync().unwrap();
+            let log = opts.open(dir.path()).unwrap();
+            assert_eq!(lookup(&log, b"a"), expected);
+            assert_eq!(lookup(&log, b"b"), vec![compressible(b'b', 2)]);
+            let message = opts.repair(dir.path()).unwrap();
+            assert!(message.contains("Verified 3 entries"), "{}", message);
+        }
+    }
+}
+
+#[test]
+fn test_compression_type_recorded_in_meta() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0);
+
+    // Existing uncompressed entries.
+    let mut log = opts.clone().open(dir.path()).unwrap();
+    log.append(compressible(b'a', 1)).unwrap();
+    log.sync().unwrap();
+    assert_eq!(log.compression_type(), CompressionType::None);
+
+    // Enable compression. Old entries are still readable.
+    let mut log = opts
+        .clone()
+        .compression_type(CompressionType::Zstd)
+        .open(dir.path())
+        .unwrap();
+    log.append(compressible(b'a', 2)).unwrap();
+    log.sync().unwrap();
+    let expected = vec![compressible(b'a', 2), compressible(b'a', 1)];
+    assert_eq!(lookup(&log, b"a"), expected);
+    let values = log.lookup(0, b"a").unwrap().into_vec().unwrap();
+    assert!(matches!(values[0], Cow::Owned(_)));
+    assert!(matches!(values[1], Cow::Borrowed(_)));
+
+    // Without the option, the recorded compression type is used.
+    let mut log = opts.clone().open(dir.path()).unwrap();
+    assert_eq!(log.compression_type(), CompressionType::Zstd);
+    assert_eq!(log.meta.compression_type, CompressionType::Zstd);
+    let size = log.meta.primary_len;
+    log.append(compressible(b'a', 3)).unwrap();
+    log.sync().unwrap();
+    assert!(log.meta.primary_len - size < 50);
+
+    // Disable compression explicitly.
+    let mut log = opts
+        .clone()
+        .compression_type(CompressionType::None)
+        .open(dir.path())
+        .unwrap();
+    let size = log.meta.primary_len;
+    log.append(compressible(b'a', 4)).unwrap();
+    log.sync().unwrap();
+    assert!(log.meta.primary_len - size > 100);
+
+    let log = opts.open(dir.path()).unwrap();
+    assert_eq!(log.compression_type(), CompressionType::None);
+    assert_eq!(lookup(&log, b"a").len(), 4);
+}
+
+#[test]
+fn test_compression_skipped_for_incompressible_data() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0).compression_type(CompressionType::Lz4);
+    let mut log = opts.clone().open(dir.path()).unwrap();
+    log.append(b"a").unwrap();
+    log.append(b"bc").unwrap();
+    log.sync().unwrap();
+    // Entries are stored as-is. 1 byte entry flag, 1 byte length,
+    // 4 bytes xxhash32.
+    assert_eq!(log.meta.primary_len, PRIMARY_START_OFFSET + 7 + 8);
+    assert_eq!(entries(&log), vec![&b"a"[..], b"bc"]);
+    assert_eq!(lookup(&log, b"b"), vec![b"bc"]);
+}
+
+#[test]
+fn test_compression_entry_to_bytes() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0).compression_type(CompressionType::Zstd);
+    let mut log = opts.open(dir.path()).unwrap();
+    log.append(compressible(b'a', 1)).unwrap();
+    log.append(b"b").unwrap();
+    log.sync().unwrap();
+
+    let entry = log.lookup(0, b"a").unwrap().next().unwrap().unwrap();
+    assert!(matches!(entry, Cow::Owned(_)));
+    let bytes = log.entry_to_bytes(entry);
+    assert_eq!(bytes.as_ref(), &compressible(b'a', 1)[..]);
+
+    let entry = log.lookup(0, b"b").unwrap().next().unwrap().unwrap();
+    assert!(matches!(entry, Cow::Borrowed(_)));
+    let bytes = log.entry_to_bytes(entry);
+    assert_eq!(bytes.as_ref(), b"b");
+    assert!(log.disk_buf.range_of_slice(bytes.as_ref()).is_some());
+}
+
+#[test]
+fn test_compression_with_delete_and_compact() {
+    let dir = tempdir().unwrap();
+    let opts = open_opts_first_byte_index(0).compression_type(CompressionType::Zstd);
+    let mut log = opts.clone().open(dir.path()).unwrap();
+    log.append(compressible(b'a', 1)).unwrap();
+    log.append(compressible(b'b', 2)).unwrap();
+    log.sync().unwrap();
+    log.delete(0, b"a").unwrap();
+    log.sync().unwrap();
+    let log = log.compact().unwrap();
+    assert_eq!(entries(&log), vec![&compressible(b'b', 2)[..]]);
+    assert_eq!(log.meta.compression_type, CompressionType::Zstd);
+    let log = opts.open(dir.path()).unwrap();
+    assert_eq!(lookup(&log, b"b"), vec![compressible(b'b', 2)]);
+    assert!(log.meta.primary_len < 100);
+}
+
 fn index_ref(data: &[u8]) -> Vec<IndexOutput> {
     vec![IndexOutput::Reference(0..data.len() as u64)]
 }
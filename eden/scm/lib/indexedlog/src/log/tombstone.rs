@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Tombstones that mark [`Log`](super::Log) entries as deleted.
+//!
+//! The tombstone file is append-only, like the primary log. Its logical
+//! length is tracked by [`LogMetadata`](super::LogMetadata).
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+
+use byteorder::ByteOrder;
+use byteorder::LittleEndian;
+
+use crate::errors::IoResultExt;
+use crate::utils;
+use crate::utils::mmap_path;
+use crate::utils::xxhash32;
+
+pub(crate) const TOMBSTONE_FILE: &str = "tombstone";
+
+// Offset of the deleted entry (u64), and XXHASH32 of the offset (u32).
+const RECORD_SIZE: usize = 12;
+
+/// Encode offsets of deleted entries as tombstone records.
+pub(crate) fn encode(offsets: &[u64]) -> Vec<u8> {
+    let mut buf = vec![0; offsets.len() * RECORD_SIZE];
+    for (record, &offset) in buf.chunks_exact_mut(RECORD_SIZE).zip(offsets) {
+        LittleEndian::write_u64(&mut record[..8], offset);
+        let checksum = xxhash32(&record[..8]);
+        LittleEndian::write_u32(&mut record[8..], checksum);
+    }
+    buf
+}
+
+/// Decode tombstone records. Stop at the first incomplete or broken record.
+///
+/// Return the offsets of deleted entries, and the length of valid records
+/// in bytes.
+pub(crate) fn decode(buf: &[u8]) -> (Vec<u64>, usize) {
+    let mut offsets = Vec::with_capacity(buf.len() / RECORD_SIZE);
+    for record in buf.chunks_exact(RECORD_SIZE) {
+        if xxhash32(&record[..8]) != LittleEndian::read_u32(&record[8..]) {
+            break;
+        }
+        offsets.push(LittleEndian::read_u64(&record[..8]));
+    }
+    let valid_len = offsets.len() * RECORD_SIZE;
+    (offsets, valid_len)
+}
+
+/// Read offsets of deleted entries from the tombstone file in `dir`.
+pub(crate) fn read(dir: &Path, len: u64) -> crate::Result<HashSet<u64>> {
+    let path = dir.join(TOMBSTONE_FILE);
+    let buf = mmap_path(&path, len)?;
+    let (offsets, valid_len) = decode(&buf);
+    if valid_len as u64 != len {
+        let msg = format!(
+            "tombstone records are broken at {} (expect {} bytes)",
+            valid_len, len
+        );
+        return Err(crate::Error::corruption(&path, msg));
+    }
+    Ok(offsets.into_iter().collect())
+}
+
+/// Write offsets of deleted entries to the tombstone file in `dir`, starting
+/// at `pos`. Return the new logical length of the tombstone file.
+///
+/// Like the primary log, bytes after `pos` are overwritten. They are not
+/// visible to readers since they are not covered by the metadata.
+///
+/// The caller should take the directory lock.
+pub(crate) fn write(dir: &Path, pos: u64, offsets: &[u64], fsync: bool) -> crate::Result<u64> {
+    let path = dir.join(TOMBSTONE_FILE);
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .context(&path, "cannot open for read-write")?;
+    let _ = utils::fix_perm_file(&file, false);
+    let actual_pos = file
+        .seek(SeekFrom::Start(pos))
+        .context(&path, || format!("cannot seek to {}", pos))?;
+    if actual_pos != pos {
+        let msg = format!("cannot seek to {} (got {})", pos, actual_pos);
+        return Err(crate::Error::path(&path, msg));
+    }
+    let buf = encode(offsets);
+    file.write_all(&buf)
+        .context(&path, || format!("cannot write data ({} bytes)", buf.len()))?;
+    if fsync || utils::get_global_fsync() {
+        file.sync_all().context(&path, "cannot fsync")?;
+    }
+    Ok(pos + buf.len() as u64)
+}
+
+/// Atomically replace the tombstone file in `dir` so it only contains the
+/// given offsets. Return the new logical length of the tombstone file.
+///
+/// Unlike [`write`], this does not break readers that have the old file
+/// mmapped. The caller should take the directory lock.
+pub(crate) fn rewrite(dir: &Path, offsets: &[u64], fsync: bool) -> crate::Result<u64> {
+    let path = dir.join(TOMBSTONE_FILE);
+    let buf = encode(offsets);
+    utils::atomic_write_plain(&path, &buf, fsync)?;
+    Ok(buf.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::quickcheck;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    quickcheck! {
+        fn test_roundtrip_tombstone(offsets: Vec<u64>) -> bool {
+            let buf = encode(&offsets);
+            decode(&buf) == (offsets, buf.len())
+        }
+    }
+
+    #[test]
+    fn test_decode_stops_at_broken_record() {
+        let mut buf = encode(&[12, 34, 56]);
+        buf[RECORD_SIZE + 1] ^= 1;
+        assert_eq!(decode(&buf), (vec![12], RECORD_SIZE));
+        assert_eq!(decode(&buf[..RECORD_SIZE + 3]), (vec![12], RECORD_SIZE));
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempdir().unwrap();
+        let len = write(dir.path(), 0, &[12, 34], false).unwrap();
+        // Overwrite data after the logical length.
+        write(dir.path(), len, &[99], false).unwrap();
+        let len = write(dir.path(), len, &[56], false).unwrap();
+        let offsets = read(dir.path(), len).unwrap();
+        assert_eq!(offsets, [12, 34, 56].iter().cloned().collect());
+        assert!(read(dir.path(), len + 1).is_err());
+    }
+}
@@ -389,7 +389,7 @@ impl OpenOptionsRepair for OpenOptions {
             // So the first entry contains the last root id.
             if let Ok(data) = entry {
                 let mut mmeta = MultiMeta::default();
-                if mmeta.read(data).is_ok() {
+                if mmeta.read(&data[..]).is_ok() {
                     // Check if everything is okay.
                     if mmeta.metas.iter().all(|(name, meta)| {
                         let len_required = meta.lock().unwrap().primary_len;
@@ -557,7 +557,7 @@ impl MultiMeta {
     fn read_log(&mut self, log: &log::Log) -> crate::Result<()> {
         if let Some(last_entry) = log.lookup(INDEX_REVERSE, INDEX_REVERSE_KEY)?.next() {
             let data = last_entry?;
-            self.read(data).context(
+            self.read(&data[..]).context(
                 log.path().as_opt_path().unwrap_or_else(|| Path::new("")),
                 "when decoding MutltiMeta",
             )?;
@@ -573,7 +573,7 @@ impl MultiMeta {
         log.clear_dirty()?;
         log.sync()?;
         if let Some(Ok(last_data)) = log.lookup(INDEX_REVERSE, INDEX_REVERSE_KEY)?.next() {
-            if last_data == &data {
+            if last_data[..] == data[..] {
                 // log does not change. Do not write redundant data.
                 return Ok(());
             }
@@ -904,7 +904,10 @@ MultiMeta is valid"#
         // Reading the log. It should contain N * 2 entries.
         let mlog = simple_open_opts().open(&path).unwrap();
         assert_eq!(
-            mlog.logs[0].iter().map(|e| e.unwrap()).collect::<Vec<_>>(),
+            mlog.logs[0]
+                .iter()
+                .map(|e| e.unwrap().to_vec())
+                .collect::<Vec<_>>(),
             [[0, 0], [0, 1], [1, 0], [1, 1]],
         );
     }
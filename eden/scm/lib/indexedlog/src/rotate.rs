@@ -7,6 +7,7 @@
 
 //! Rotation support for a set of [`Log`]s.
 
+use std::borrow::Cow;
 use std::fmt;
 use std::fs;
 use std::io;
@@ -108,6 +109,14 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the compression type.
+    ///
+    /// See [log::CompressionType] for details.
+    pub fn compression_type(mut self, compression_type: log::CompressionType) -> Self {
+        self.log_open_options = self.log_open_options.compression_type(compression_type);
+        self
+    }
+
     /// Set whether create the [`RotateLog`] structure if it does not exist.
     pub fn create(mut self, create: bool) -> Self {
         self.log_open_options = self.log_open_options.create(create);
@@ -344,8 +353,89 @@ impl RotateLog {
         .context("in RotateLog::append")
     }
 
+    /// Delete entries matching `key` using the given index from all [`Log`]s.
+    /// The `index_id` is the index of `index_defs` stored in [`OpenOptions`].
+    ///
+    /// See [`Log::delete`] for details. Deletions are written by
+    /// [`RotateLog::sync`]. Since non-latest logs are not reloaded
+    /// automatically, other [`RotateLog`] instances might not see deletions
+    /// in non-latest logs until they are re-opened.
+    ///
+    /// Return the count of newly deleted entries.
+    pub fn delete(&mut self, index_id: usize, key: impl AsRef<[u8]>) -> crate::Result<usize> {
+        let key = key.as_ref();
+        let result: crate::Result<_> = (|| {
+            // Load all logs so entries in older logs are also deleted.
+            let mut len = 0;
+            while self.load_log(len)?.is_some() {
+                len += 1;
+            }
+            let mut count = 0;
+            for cell in self.logs.iter_mut().take(len) {
+                if let Some(log) = cell.get_mut() {
+                    count += log.delete(index_id, key)?;
+                }
+            }
+            Ok(count)
+        })();
+        result
+            .context(|| format!("in RotateLog::delete({}, {:?})", index_id, key))
+            .context(|| format!("  RotateLog.dir = {:?}", self.dir))
+    }
+
+    /// Write pending changes, then rewrite [`Log`]s that have deleted
+    /// entries so the space used by deleted entries is reclaimed.
+    ///
+    /// See [`Log::compact`] for details.
+    pub fn compact(&mut self) -> crate::Result<()> {
+        let result: crate::Result<_> = (|| {
+            self.sync()?;
+            if self.dir.is_none() {
+                return Ok(());
+            }
+            let mut len = 0;
+            while self.load_log(len)?.is_some() {
+                len += 1;
+            }
+            for index in 0..len {
+                let cell = &mut self.logs[index];
+                let has_deleted = match cell.get() {
+                    Some(log) => log.meta.tombstone_len > 0,
+                    None => false,
+                };
+                if !has_deleted {
+                    continue;
+                }
+                let log = cell.take().unwrap();
+                match log.compact() {
+                    Ok(log) => {
+                        let _ = cell.set(log);
+                    }
+                    Err(err) => {
+                        // Non-latest logs are loaded lazily. The latest log
+                        // is expected to be always loaded.
+                        if index == 0 {
+                            let dir = self.dir.as_ref().unwrap();
+                            let open_options = self.open_options.log_open_options.clone();
+                            let log = load_log(dir, self.latest, open_options)?;
+                            let _ = self.logs[0].set(log);
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+            Ok(())
+        })();
+        result
+            .context("in RotateLog::compact")
+            .context(|| format!("  RotateLog.dir = {:?}", self.dir))
+    }
+
     /// Look up an entry using the given index. The `index_id` is the index of
     /// `index_defs` stored in [`OpenOptions`].
+    ///
+    /// Like [`Log::lookup`], compressed entries are decompressed into owned
+    /// buffers.
     pub fn lookup(
         &self,
         index_id: usize,
@@ -382,6 +472,18 @@ impl RotateLog {
         Bytes::copy_from_slice(slice)
     }
 
+    /// Convert an entry returned by [`RotateLog::lookup`] or
+    /// [`RotateLog::iter`] to [`Bytes`].
+    ///
+    /// Do not copy the entry if it's from the main on-disk buffer of one of
+    /// the loaded logs, or if it was decompressed.
+    pub fn entry_to_bytes(&self, entry: Cow<[u8]>) -> Bytes {
+        match entry {
+            Cow::Borrowed(slice) => self.slice_to_bytes(slice),
+            Cow::Owned(data) => Bytes::from(data),
+        }
+    }
+
     /// Look up an entry using the given index. The `index_id` is the index of
     /// `index_defs` stored in [`OpenOptions`].
     ///
@@ -428,7 +530,15 @@ impl RotateLog {
                 return Ok(0);
             }
 
-            if self.writable_log().iter_dirty().next().is_none() {
+            // Write pending deletions to the logs they were made in, even if
+            // the latest log has changed.
+            for cell in self.logs.iter_mut() {
+                if let Some(log) = cell.get_mut() {
+                    log.sync_deleted()?;
+                }
+            }
+
+            if self.writable_log().mem_buf.is_empty() {
                 // Read-only path, no need to take directory lock.
                 if let Ok(latest) = read_latest(self.dir.as_ref().unwrap()) {
                     if latest != self.latest {
@@ -468,11 +578,11 @@ impl RotateLog {
                         for entry in self.writable_log().iter_dirty() {
                             let content = entry?;
                             let context = FlushFilterContext { log };
-                            match filter(&context, content).map_err(|err| {
+                            match filter(&context, &content).map_err(|err| {
                                 crate::Error::wrap(err, "failed to run filter function")
                             })? {
                                 FlushFilterOutput::Drop => {}
-                                FlushFilterOutput::Keep => log.append(content)?,
+                                FlushFilterOutput::Keep => log.append(&content)?,
                                 FlushFilterOutput::Replace(content) => log.append(content)?,
                             }
                         }
@@ -666,14 +776,15 @@ impl RotateLog {
 
     /// Iterate over all the entries.
     ///
-    /// The entries are returned in FIFO order.
-    pub fn iter(&self) -> impl Iterator<Item = crate::Result<&[u8]>> {
+    /// The entries are returned in FIFO order. Like [`Log::iter`], compressed
+    /// entries are decompressed into owned buffers.
+    pub fn iter(&self) -> impl Iterator<Item = crate::Result<Cow<'_, [u8]>>> {
         let logs = self.logs();
         logs.into_iter().rev().flat_map(|log| log.iter())
     }
 
     /// Iterate over all dirty entries.
-    pub fn iter_dirty(&self) -> impl Iterator<Item = crate::Result<&[u8]>> {
+    pub fn iter_dirty(&self) -> impl Iterator<Item = crate::Result<Cow<'_, [u8]>>> {
         self.logs[0].get().unwrap().iter_dirty()
     }
 }
@@ -751,7 +862,7 @@ pub struct RotateLogLookupIter<'a> {
 }
 
 impl<'a> Iterator for RotateLogLookupIter<'a> {
-    type Item = crate::Result<&'a [u8]>;
+    type Item = crate::Result<Cow<'a, [u8]>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.end {
@@ -941,11 +1052,11 @@ mod tests {
     }
 
     // lookup via index 0
-    fn lookup<'a>(rotate: &'a RotateLog, key: &[u8]) -> Vec<&'a [u8]> {
+    fn lookup(rotate: &RotateLog, key: &[u8]) -> Vec<Vec<u8>> {
         let values = rotate
             .lookup(0, key.to_vec())
             .unwrap()
-            .collect::<crate::Result<Vec<&[u8]>>>()
+            .collect::<crate::Result<Vec<_>>>()
             .unwrap();
         for value in &values {
             let b1 = rotate.slice_to_bytes(value);
@@ -963,14 +1074,11 @@ mod tests {
                 "slice_to_bytes should return zero-copy"
             );
         }
-        values
+        values.into_iter().map(|v| v.into_owned()).collect()
     }
 
-    fn iter(rotate: &RotateLog) -> Vec<&[u8]> {
-        rotate
-            .iter()
-            .collect::<crate::Result<Vec<&[u8]>>>()
-            .unwrap()
+    fn iter(rotate: &RotateLog) -> Vec<Vec<u8>> {
+        rotate.iter().map(|v| v.unwrap().into_owned()).collect()
     }
 
     #[test]
@@ -1182,6 +1290,126 @@ mod tests {
         assert_eq!(iter(&rotate2), vec![b"a2"]);
     }
 
+    #[test]
+    fn test_delete() {
+        let dir = tempdir().unwrap();
+        let open_opts = OpenOptions::new()
+            .create(true)
+            .max_bytes_per_log(1)
+            .max_log_count(5)
+            .index("first-byte", |_| vec![IndexOutput::Reference(0..1)]);
+
+        let mut rotate1 = open_opts.open(&dir).unwrap();
+        rotate1.append(b"a1").unwrap();
+        assert_eq!(rotate1.sync().unwrap(), 1);
+        rotate1.append(b"a2").unwrap();
+        assert_eq!(rotate1.sync().unwrap(), 2);
+        rotate1.append(b"b1").unwrap();
+        rotate1.append(b"a3").unwrap();
+
+        // Entries in rotated logs, and in-memory entries are deleted.
+        let mut rotate2 = open_opts.open(&dir).unwrap();
+        assert_eq!(rotate1.delete(0, b"a").unwrap(), 3);
+        assert_eq!(lookup(&rotate1, b"a"), Vec::<&[u8]>::new());
+        assert_eq!(iter(&rotate1), vec![b"b1"]);
+        assert_eq!(rotate1.sync().unwrap(), 3);
+        assert_eq!(iter(&rotate1), vec![b"b1"]);
+
+        // Latest changed. Deletions are still written to the right log.
+        let mut rotate3 = open_opts.open(&dir).unwrap();
+        rotate3.append(b"c1").unwrap();
+        assert_eq!(rotate3.delete(0, b"b").unwrap(), 1);
+        rotate2.append(b"d1").unwrap();
+        assert_eq!(rotate2.sync().unwrap(), 4);
+        assert_eq!(rotate3.sync().unwrap(), 5);
+        assert_eq!(iter(&rotate3), vec![b"d1", b"c1"]);
+
+        let rotate4 = open_opts.open(&dir).unwrap();
+        assert_eq!(iter(&rotate4), vec![b"d1", b"c1"]);
+        assert_eq!(lookup(&rotate4, b"b"), Vec::<&[u8]>::new());
+
+        // In-memory RotateLog.
+        let mut rotate_mem = open_opts.create_in_memory().unwrap();
+        rotate_mem.append(b"a1").unwrap();
+        rotate_mem.append(b"b1").unwrap();
+        assert_eq!(rotate_mem.delete(0, b"a").unwrap(), 1);
+        assert_eq!(iter(&rotate_mem), vec![b"b1"]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = tempdir().unwrap();
+        let open_opts = OpenOptions::new()
+            .create(true)
+            .max_bytes_per_log(100)
+            .max_log_count(5)
+            .index("first-byte", |_| vec![IndexOutput::Reference(0..1)]);
+
+        let mut rotate1 = open_opts.open(&dir).unwrap();
+        let entry = |i: u8| -> Vec<u8> { vec![i; 60] };
+        rotate1.append(entry(b'a')).unwrap();
+        rotate1.append(entry(b'b')).unwrap();
+        assert_eq!(rotate1.sync().unwrap(), 1);
+        rotate1.append(entry(b'a')).unwrap();
+        rotate1.append(entry(b'c')).unwrap();
+        assert_eq!(rotate1.sync().unwrap(), 2);
+
+        let size = |rotate: &RotateLog| -> u64 {
+            rotate.logs().iter().map(|log| log.meta.primary_len).sum()
+        };
+        let old_size = size(&rotate1);
+        assert_eq!(rotate1.delete(0, b"a").unwrap(), 2);
+        rotate1.compact().unwrap();
+        assert!(size(&rotate1) < old_size - 100);
+        assert_eq!(iter(&rotate1), vec![&entry(b'b')[..], &entry(b'c')[..]]);
+
+        // Compacted logs can be read and written.
+        let mut rotate2 = open_opts.open(&dir).unwrap();
+        assert_eq!(lookup(&rotate2, b"b"), vec![&entry(b'b')[..]]);
+        rotate2.append(entry(b'd')).unwrap();
+        rotate2.sync().unwrap();
+        rotate1.sync().unwrap();
+        assert_eq!(lookup(&rotate1, b"d"), vec![&entry(b'd')[..]]);
+        assert_eq!(lookup(&rotate1, b"a"), Vec::<&[u8]>::new());
+    }
+
+    #[test]
+    fn test_compression() {
+        let dir = tempdir().unwrap();
+        let open_opts = OpenOptions::new()
+            .create(true)
+            .max_bytes_per_log(100)
+            .max_log_count(3)
+            .compression_type(log::CompressionType::Zstd)
+            .index("first-byte", |_| vec![IndexOutput::Reference(0..1)]);
+
+        let entry = |i: u8| -> Vec<u8> {
+            let mut data = vec![i];
+            data.extend_from_slice(&[b'x'; 200]);
+            data
+        };
+        let mut rotate1 = open_opts.open(&dir).unwrap();
+        rotate1.append(entry(b'a')).unwrap();
+        rotate1.append(entry(b'b')).unwrap();
+        // Compressed entries are small. No need to rotate.
+        assert_eq!(rotate1.sync().unwrap(), 0);
+        rotate1.append(entry(b'a')).unwrap();
+
+        // Decompressed entries are not zero-copy. Avoid `lookup`.
+        let lookup = |rotate: &RotateLog, key: &[u8]| -> Vec<Vec<u8>> {
+            let iter = rotate.lookup(0, key.to_vec()).unwrap();
+            iter.map(|v| v.unwrap().to_vec()).collect()
+        };
+        let a = entry(b'a');
+        assert_eq!(lookup(&rotate1, b"a"), vec![a.clone(), a.clone()]);
+        assert_eq!(iter(&rotate1).len(), 3);
+
+        rotate1.sync().unwrap();
+        let rotate2 = open_opts.open(&dir).unwrap();
+        assert_eq!(lookup(&rotate2, b"a"), vec![a.clone(), a.clone()]);
+        assert_eq!(iter(&rotate2)[1], &entry(b'b')[..]);
+    }
+
     #[test]
     fn test_lookup_truncated_meta() {
         // Look up or iteration should work with rotated logs.
@@ -1275,7 +1503,7 @@ mod tests {
             .max_bytes_per_log(100)
             .flush_filter(Some(|ctx, bytes| {
                 // 'aa' is not inserted yet. It should not exist in the log.
-                assert!(!ctx.log.iter().any(|x| x.unwrap() == b"aa"));
+                assert!(!ctx.log.iter().any(|x| x.unwrap() == &b"aa"[..]));
                 Ok(match bytes.len() {
                     1 => FlushFilterOutput::Replace(b"xx".to_vec()),
                     _ => FlushFilterOutput::Keep,
@@ -1381,13 +1609,13 @@ mod tests {
         );
 
         assert_eq!(
-            rotate.iter().map(|e| e.unwrap()).collect::<Vec<&[u8]>>(),
+            rotate.iter().map(|e| e.unwrap()).collect::<Vec<_>>(),
             vec![&a[..], &b, &a, &a],
         );
 
         rotate.sync().unwrap(); // trigger rotate
         assert_eq!(
-            rotate.iter().map(|e| e.unwrap()).collect::<Vec<&[u8]>>(),
+            rotate.iter().map(|e| e.unwrap()).collect::<Vec<_>>(),
             vec![&b[..], &a, &a],
         );
     }
@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+// Test Log::compact interrupted at different points. Failpoints are global to
+// the process, so this is not a unit test.
+
+use indexedlog::log::IndexDef;
+use indexedlog::log::IndexOutput;
+use indexedlog::log::Log;
+use indexedlog::log::OpenOptions;
+use tempfile::tempdir;
+
+fn open_opts() -> OpenOptions {
+    let index_func = |_data: &[u8]| vec![IndexOutput::Reference(0..1)];
+    OpenOptions::new()
+        .create(true)
+        .index_defs(vec![IndexDef::new("first", index_func).lag_threshold(0)])
+}
+
+fn entries(log: &Log) -> Vec<Vec<u8>> {
+    log.iter().map(|e| e.unwrap().to_vec()).collect()
+}
+
+fn lookup(log: &Log, key: &[u8]) -> Vec<Vec<u8>> {
+    let iter = log.lookup(0, key).unwrap();
+    iter.map(|v| v.unwrap().to_vec()).collect()
+}
+
+#[test]
+fn test_compact_interrupted() {
+    let _scenario = fail::FailScenario::setup();
+
+    for (name, compacted) in [
+        ("log::compact::before-swap", false),
+        ("log::compact::after-primary-swap", true),
+    ] {
+        let dir = tempdir().unwrap();
+        let opts = open_opts();
+        let mut log = opts.open(dir.path()).unwrap();
+        for i in 0..10u8 {
+            log.append(&[b'a' + i % 2, i][..]).unwrap();
+        }
+        log.sync().unwrap();
+        log.delete(0, b"a").unwrap();
+        let expected = entries(&log);
+        let len_before = log.sync().unwrap();
+
+        fail::cfg(name, "return").unwrap();
+        assert!(log.compact().is_err());
+        fail::remove(name);
+
+        // Either the old or the compacted log is visible.
+        let log = opts.open(dir.path()).unwrap();
+        assert_eq!(entries(&log), expected);
+        assert!(lookup(&log, b"a").is_empty());
+        assert_eq!(lookup(&log, b"b").len(), 5);
+        let mut log = log;
+        let len = log.sync().unwrap();
+        assert_eq!(len < len_before, compacted, "{}", name);
+
+        // Compacting again works.
+        let mut log = log.compact().unwrap();
+        assert_eq!(entries(&log), expected);
+        assert!(log.sync().unwrap() < len_before);
+    }
+}
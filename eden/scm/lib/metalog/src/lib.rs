@@ -21,3 +21,4 @@ pub use crate::metalog::resolver;
 pub use crate::metalog::CommitOptions;
 pub use crate::metalog::Id20;
 pub use crate::metalog::MetaLog;
+pub use crate::metalog::Resolver;
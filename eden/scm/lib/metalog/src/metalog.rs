@@ -68,6 +68,9 @@ pub struct MetaLog {
     root: Root,
 }
 
+/// Resolves conflicts when committing. See [`CommitOptions::resolver`].
+pub type Resolver = Box<dyn FnMut(&mut MetaLog, &MetaLog, &MetaLog) -> Result<()>>;
+
 /// Options used by the `commit` API.
 #[derive(Default)]
 pub struct CommitOptions<'a> {
@@ -85,7 +88,10 @@ pub struct CommitOptions<'a> {
     /// before the current pending changes.
     /// The function should try to set resolved contents on the current MetaLog and
     /// return `Ok(())` if it is able to resolve everything cleanly.
-    pub resolver: Option<Box<dyn FnMut(&mut MetaLog, &MetaLog, &MetaLog) -> Result<()>>>,
+    ///
+    /// See [`resolver::Resolvers`] to merge known keys by their content.
+    /// Defaults to [`resolver::fail`].
+    pub resolver: Option<Resolver>,
 
     /// Prevent constructing via fields.
     _private: (),
@@ -198,7 +204,7 @@ impl MetaLog {
         let result = std::iter::once(EMPTY_ROOT_ID.clone())
             .chain(
                 log.iter()
-                    .map(|e| e.ok().and_then(|e| Id20::from_slice(e).ok()))
+                    .map(|e| e.ok().and_then(|e| Id20::from_slice(&e).ok()))
                     .take_while(|s| s.is_some())
                     .map(|s| s.unwrap()),
             )
@@ -446,7 +452,7 @@ fn find_last_root_id(log: &ilog::Log) -> Result<Id20> {
     for entry in log.lookup(INDEX_REVERSE, INDEX_REVERSE_KEY)? {
         // The linked list in the index is in the reversed order.
         // So the first entry contains the last root id.
-        return Ok(Id20::from_slice(entry?)?);
+        return Ok(Id20::from_slice(&entry?)?);
     }
     Ok(EMPTY_ROOT_ID.clone())
 }
@@ -507,9 +513,13 @@ pub(crate) struct SerId20(#[serde(with = "types::serde_with::hgid::tuple")] pub(
 
 /// Predefined conflict resolutions.
 pub mod resolver {
+    use std::collections::BTreeMap;
     use std::collections::BTreeSet;
+    use std::collections::HashSet;
 
     use super::MetaLog;
+    use super::Resolver;
+    use crate::Error;
     use crate::Result;
 
     /// Fail the merge unconditionally on any kind of conflicts.
@@ -542,6 +552,256 @@ pub mod resolver {
         };
         Err(this.error(message))
     }
+
+    /// Result of merging the values of a key.
+    #[derive(Debug, Default, PartialEq)]
+    pub struct KeyMerge {
+        /// The merged value. `None` removes the key.
+        pub value: Option<Vec<u8>>,
+        /// Conflicts that were resolved automatically, but might have lost
+        /// changes, for example a bookmark moved by both sides.
+        pub conflicts: Vec<String>,
+    }
+
+    /// Merges values of a key changed by both sides. Takes `this`, `other`
+    /// and `ancestor` values, which are `None` if the key is missing.
+    pub type KeyResolver =
+        Box<dyn Fn(Option<&[u8]>, Option<&[u8]>, Option<&[u8]>) -> Result<KeyMerge> + Send + Sync>;
+
+    /// Three-way merge using resolvers registered per key.
+    ///
+    /// Keys changed by only one side take that side. Keys changed by both
+    /// sides to different values are merged by their resolvers. If a key
+    /// without a resolver is changed by both sides, the merge fails.
+    #[derive(Default)]
+    pub struct Resolvers {
+        resolvers: BTreeMap<String, KeyResolver>,
+    }
+
+    impl Resolvers {
+        /// No key-specific resolvers.
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Resolvers for keys used by Mercurial: "bookmarks", "remotenames"
+        /// and "visibleheads".
+        pub fn with_defaults() -> Self {
+            Self::new()
+                .register("bookmarks", Box::new(bookmarks))
+                .register("remotenames", Box::new(remotenames))
+                .register("visibleheads", Box::new(visibleheads))
+        }
+
+        /// Use `resolver` for `key`, replacing any previous resolver.
+        pub fn register(mut self, key: &str, resolver: KeyResolver) -> Self {
+            self.resolvers.insert(key.to_string(), resolver);
+            self
+        }
+
+        /// Merge changes from `other` into `this`. Returns the conflicts that
+        /// were resolved automatically.
+        pub fn resolve(
+            &self,
+            this: &mut MetaLog,
+            other: &MetaLog,
+            ancestor: &MetaLog,
+        ) -> Result<Vec<String>> {
+            let keys: BTreeSet<String> = other
+                .keys()
+                .into_iter()
+                .chain(this.keys())
+                .map(|key| key.to_string())
+                .collect();
+            let mut resolved_conflicts = Vec::new();
+            let mut unresolved = Vec::new();
+            for key in keys {
+                let ancestor_id = ancestor.root.map.get(&key).map(|t| t.0);
+                let other_id = other.root.map.get(&key).map(|t| t.0);
+                let this_id = this.root.map.get(&key).map(|t| t.0);
+                if other_id == ancestor_id || other_id == this_id {
+                    continue;
+                }
+                if this_id == ancestor_id {
+                    match other.get(&key)? {
+                        Some(value) => {
+                            this.set(&key, &value)?;
+                        }
+                        None => this.remove(&key)?,
+                    }
+                    continue;
+                }
+                let resolver = match self.resolvers.get(&key) {
+                    Some(resolver) => resolver,
+                    None => {
+                        unresolved.push(format!("  {}: both changed, diverged", key));
+                        continue;
+                    }
+                };
+                let merged = resolver(
+                    this.get(&key)?.as_deref(),
+                    other.get(&key)?.as_deref(),
+                    ancestor.get(&key)?.as_deref(),
+                )
+                .map_err(|e| this.error(format!("cannot merge {}: {}", key, e)))?;
+                match merged.value {
+                    Some(value) => {
+                        this.set(&key, &value)?;
+                    }
+                    None => this.remove(&key)?,
+                }
+                resolved_conflicts.extend(
+                    merged
+                        .conflicts
+                        .into_iter()
+                        .map(|conflict| format!("{}: {}", key, conflict)),
+                );
+            }
+            if !unresolved.is_empty() {
+                return Err(this.error(format!("conflict detected:\n{}", unresolved.join("\n"))));
+            }
+            Ok(resolved_conflicts)
+        }
+
+        /// Convert to a resolver for [`CommitOptions`](super::CommitOptions).
+        /// Automatically resolved conflicts are logged as warnings.
+        pub fn into_resolver(self) -> Resolver {
+            Box::new(move |this, other, ancestor| {
+                for conflict in self.resolve(this, other, ancestor)? {
+                    tracing::warn!("metalog conflict resolved: {}", conflict);
+                }
+                Ok(())
+            })
+        }
+    }
+
+    /// Merge "bookmarks", with lines like "<hex> <name>". If both sides
+    /// changed a bookmark differently, this side (the last writer) wins.
+    pub fn bookmarks(
+        this: Option<&[u8]>,
+        other: Option<&[u8]>,
+        ancestor: Option<&[u8]>,
+    ) -> Result<KeyMerge> {
+        merge_lines(this, other, ancestor, 2)
+    }
+
+    /// Merge "remotenames", with lines like "<hex> <type> <remote>/<name>".
+    /// If both sides changed a name differently, this side (the last writer)
+    /// wins.
+    pub fn remotenames(
+        this: Option<&[u8]>,
+        other: Option<&[u8]>,
+        ancestor: Option<&[u8]>,
+    ) -> Result<KeyMerge> {
+        merge_lines(this, other, ancestor, 3)
+    }
+
+    /// Merge "visibleheads", a "v1" line followed by one head per line.
+    /// Heads added by either side are kept. Heads in the ancestor are kept
+    /// only if neither side removed them.
+    pub fn visibleheads(
+        this: Option<&[u8]>,
+        other: Option<&[u8]>,
+        ancestor: Option<&[u8]>,
+    ) -> Result<KeyMerge> {
+        fn parse(data: Option<&[u8]>) -> Result<Vec<&str>> {
+            let text = std::str::from_utf8(data.unwrap_or_default())?;
+            let mut lines = text.lines();
+            match lines.next() {
+                None => Ok(Vec::new()),
+                Some(version) if version.trim() == "v1" => {
+                    Ok(lines.map(|line| line.trim()).collect())
+                }
+                Some(version) => Err(Error(format!(
+                    "unsupported visibleheads format {:?}",
+                    version
+                ))),
+            }
+        }
+        let this = parse(this)?;
+        let other = parse(other)?;
+        let ancestor: HashSet<&str> = parse(ancestor)?.into_iter().collect();
+        let this_set: HashSet<&str> = this.iter().cloned().collect();
+        let other_set: HashSet<&str> = other.iter().cloned().collect();
+
+        let mut seen = HashSet::new();
+        let mut value = "v1\n".to_string();
+        for head in this.into_iter().chain(other) {
+            let removed =
+                ancestor.contains(head) && !(this_set.contains(head) && other_set.contains(head));
+            if !removed && seen.insert(head) {
+                value.push_str(head);
+                value.push('\n');
+            }
+        }
+        Ok(KeyMerge {
+            value: Some(value.into_bytes()),
+            conflicts: Vec::new(),
+        })
+    }
+
+    /// Merge lines of `count` space-separated fields, where the last field
+    /// is a name and the other fields are its value, name by name. If both
+    /// sides changed a name differently, this side wins and the conflict is
+    /// reported. Lines are sorted by name in the result.
+    fn merge_lines(
+        this: Option<&[u8]>,
+        other: Option<&[u8]>,
+        ancestor: Option<&[u8]>,
+        count: usize,
+    ) -> Result<KeyMerge> {
+        fn parse(data: Option<&[u8]>, count: usize) -> Result<BTreeMap<&str, &str>> {
+            let text = std::str::from_utf8(data.unwrap_or_default())?;
+            let mut map = BTreeMap::new();
+            for line in text.lines() {
+                match line.match_indices(' ').nth(count - 2) {
+                    Some((index, _)) => map.insert(&line[index + 1..], &line[..index]),
+                    None => return Err(Error(format!("invalid line {:?}", line))),
+                };
+            }
+            Ok(map)
+        }
+        let this = parse(this, count)?;
+        let other = parse(other, count)?;
+        let ancestor = parse(ancestor, count)?;
+
+        let names: BTreeSet<&str> = this
+            .keys()
+            .chain(other.keys())
+            .chain(ancestor.keys())
+            .cloned()
+            .collect();
+        let describe = |value: Option<&&str>| match value {
+            Some(value) => value.to_string(),
+            None => "(removed)".to_string(),
+        };
+        let mut value = String::new();
+        let mut conflicts = Vec::new();
+        for name in names {
+            let (this_value, other_value) = (this.get(name), other.get(name));
+            let ancestor_value = ancestor.get(name);
+            let merged = if this_value == ancestor_value {
+                other_value
+            } else {
+                if other_value != ancestor_value && other_value != this_value {
+                    conflicts.push(format!(
+                        "{}: kept {} over {}",
+                        name,
+                        describe(this_value),
+                        describe(other_value)
+                    ));
+                }
+                this_value
+            };
+            if let Some(merged) = merged {
+                value.push_str(&format!("{} {}\n", merged, name));
+            }
+        }
+        Ok(KeyMerge {
+            value: Some(value.into_bytes()),
+            conflicts,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -672,6 +932,101 @@ mod tests {
         assert_eq!(metalog3.get("c").unwrap().unwrap(), b"c");
     }
 
+    #[test]
+    fn test_key_resolvers() {
+        let dir = TempDir::new().unwrap();
+        let mut metalog = MetaLog::open(&dir, None).unwrap();
+        metalog
+            .set("bookmarks", b"1111 a\n2222 b\n3333 c\n")
+            .unwrap();
+        metalog.set("visibleheads", b"v1\naaaa\nbbbb\n").unwrap();
+        metalog.set("x", b"0").unwrap();
+        metalog.commit(commit_opt("commit 0", 0)).unwrap();
+
+        let mut metalog1 = MetaLog::open(&dir, None).unwrap();
+        let mut metalog2 = MetaLog::open(&dir, None).unwrap();
+        metalog1
+            .set("bookmarks", b"1111 a\n4444 b\n3333 c\n5555 d\n")
+            .unwrap();
+        metalog1.set("visibleheads", b"v1\nbbbb\ncccc\n").unwrap();
+        metalog1
+            .set("remotenames", b"1111 bookmarks remote/main\n")
+            .unwrap();
+        metalog2.set("bookmarks", b"1111 a\n6666 b\n").unwrap();
+        metalog2
+            .set("visibleheads", b"v1\naaaa\nbbbb\ndddd\n")
+            .unwrap();
+        metalog2.set("x", b"2").unwrap();
+        metalog1.commit(commit_opt("commit 1", 1)).unwrap();
+
+        let mut opts = commit_opt("commit 2", 2);
+        opts.resolver = Some(resolver::Resolvers::with_defaults().into_resolver());
+        metalog2.commit(opts).unwrap();
+
+        let metalog3 = MetaLog::open(&dir, None).unwrap();
+        assert_eq!(
+            metalog3.get("bookmarks").unwrap().unwrap(),
+            b"1111 a\n6666 b\n5555 d\n"
+        );
+        assert_eq!(
+            metalog3.get("visibleheads").unwrap().unwrap(),
+            b"v1\nbbbb\ndddd\ncccc\n"
+        );
+        assert_eq!(
+            metalog3.get("remotenames").unwrap().unwrap(),
+            b"1111 bookmarks remote/main\n"
+        );
+        assert_eq!(metalog3.get("x").unwrap().unwrap(), b"2");
+
+        // Keys without resolvers changed by both sides still conflict.
+        let mut metalog4 = MetaLog::open(&dir, None).unwrap();
+        let mut metalog5 = MetaLog::open(&dir, None).unwrap();
+        metalog4.set("x", b"4").unwrap();
+        metalog5.set("x", b"5").unwrap();
+        metalog4.commit(commit_opt("commit 4", 4)).unwrap();
+        let mut opts = commit_opt("commit 5", 5);
+        opts.resolver = Some(resolver::Resolvers::with_defaults().into_resolver());
+        let err = metalog5
+            .commit(opts)
+            .unwrap_err()
+            .to_string()
+            .replace(&format!("{:?}", dir.path()), "<path>");
+        assert_eq!(
+            err,
+            "<path>: conflict detected:\n  x: both changed, diverged"
+        );
+    }
+
+    #[test]
+    fn test_line_resolvers() {
+        let merged = resolver::bookmarks(
+            Some(b"1111 a\n4444 b\n"),
+            Some(b"2222 a\n5555 b\n6666 c\n"),
+            Some(b"1111 a\n3333 b\n"),
+        )
+        .unwrap();
+        assert_eq!(merged.value.unwrap(), b"2222 a\n4444 b\n6666 c\n");
+        assert_eq!(merged.conflicts, vec!["b: kept 4444 over 5555"]);
+
+        let merged = resolver::bookmarks(None, Some(b"2222 a\n"), Some(b"1111 a\n")).unwrap();
+        assert_eq!(merged.value.unwrap(), b"");
+        assert_eq!(merged.conflicts, vec!["a: kept (removed) over 2222"]);
+
+        let merged = resolver::remotenames(
+            Some(b"1111 bookmarks remote/a b\n"),
+            Some(b"2222 bookmarks remote/c\n"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            merged.value.unwrap(),
+            b"1111 bookmarks remote/a b\n2222 bookmarks remote/c\n"
+        );
+        assert!(resolver::remotenames(Some(b"1111 remote/a\n"), None, None).is_err());
+
+        assert!(resolver::visibleheads(Some(b"v2\naaaa\n"), None, None).is_err());
+    }
+
     #[test]
     fn test_compaction() {
         let dir = TempDir::new().unwrap();
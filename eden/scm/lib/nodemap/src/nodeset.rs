@@ -72,7 +72,7 @@ impl NodeSet {
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = Result<Node>> + 'a {
         self.log
             .iter()
-            .map(|slice| Node::from_slice(slice?).map_err(Into::into))
+            .map(|slice| Node::from_slice(&slice?).map_err(Into::into))
     }
 }
 
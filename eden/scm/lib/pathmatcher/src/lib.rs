@@ -6,6 +6,7 @@
  */
 
 mod gitignore_matcher;
+pub mod sparse;
 mod tree_matcher;
 mod utils;
 
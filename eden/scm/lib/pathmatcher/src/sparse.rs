@@ -0,0 +1,646 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Sparse profiles
+//!
+//! A sparse profile limits the files present in a working copy. Profiles
+//! live in the repo and look like:
+//!
+//! ```text
+//! # Comments start with '#' or ';'.
+//! %include some/base/profile
+//!
+//! [metadata]
+//! title: An example profile
+//! description: Values can extend over
+//!   multiple indented lines.
+//!
+//! [include]
+//! foo/bar
+//! glob:bar/**/*.py
+//!
+//! [exclude]
+//! foo/bar/*.ignore
+//! ```
+//!
+//! Lines before the first section are include rules. Rules are `glob:`
+//! (the default) or `path:` patterns, and match recursively.
+//!
+//! The working copy's own config (`.hg/sparse`) is read as a [`Root`],
+//! which composes the profiles it includes into a [`SparseMatcher`]. Rules
+//! of version 1 profiles are merged with the root's rules, with excludes
+//! overriding includes. Version 2 profiles (`version = 2` in `[metadata]`)
+//! each get their own matcher, so excludes in one profile don't remove
+//! files included by another.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use types::RepoPath;
+
+use crate::expand_curly_brackets;
+use crate::normalize_glob;
+use crate::plain_to_glob;
+use crate::DirectoryMatch;
+use crate::Matcher;
+use crate::TreeMatcher;
+
+/// Pattern kinds understood by Mercurial. Only `glob` and `path` can be
+/// used in sparse profiles.
+const PATTERN_KINDS: &[&str] = &[
+    "re",
+    "glob",
+    "path",
+    "relglob",
+    "relpath",
+    "relre",
+    "listfile",
+    "listfile0",
+    "set",
+    "include",
+    "subinclude",
+    "rootfilesin",
+];
+
+/// Where rules that are always part of a sparse checkout come from.
+const DEFAULT_ORIGIN: &str = "(default)";
+
+/// A line of a sparse profile that affects which files are included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProfileEntry {
+    /// `%include` of another profile, by its path in the repo.
+    Profile(String),
+    /// A pattern in the `[include]` section.
+    Include(String),
+    /// A pattern in the `[exclude]` section.
+    Exclude(String),
+}
+
+/// A parsed sparse profile. Included profiles are not loaded.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    source: String,
+    entries: Vec<ProfileEntry>,
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Include,
+    Exclude,
+    Metadata,
+}
+
+impl Profile {
+    /// Parse a profile. `source` names the profile in errors and
+    /// explanations, and is usually its path in the repo.
+    ///
+    /// Malformed metadata and rules starting with `/` are ignored, like
+    /// the Python implementation does.
+    pub fn from_bytes(data: impl AsRef<[u8]>, source: impl Into<String>) -> Result<Self> {
+        let source = source.into();
+        let text = std::str::from_utf8(data.as_ref())
+            .with_context(|| format!("sparse profile {} is not valid UTF-8", source))?;
+
+        let mut entries = Vec::new();
+        let mut metadata: HashMap<String, Vec<&str>> = HashMap::new();
+        let mut last_key: Option<&str> = None;
+        let mut section = Section::Include;
+
+        for line in text.lines() {
+            let stripped = line.trim();
+            if stripped.is_empty() || stripped.starts_with('#') || stripped.starts_with(';') {
+                continue;
+            }
+
+            if let Some(name) = stripped.strip_prefix("%include ") {
+                let name = name.trim();
+                if !name.is_empty() {
+                    entries.push(ProfileEntry::Profile(name.to_string()));
+                }
+                continue;
+            }
+
+            match stripped {
+                "[include]" => section = Section::Include,
+                "[exclude]" => section = Section::Exclude,
+                "[metadata]" => section = Section::Metadata,
+                _ => match section {
+                    Section::Metadata => {
+                        if line.starts_with(' ') || line.starts_with('\t') {
+                            // Continuation of a multi-line value.
+                            if let Some(key) = last_key {
+                                metadata.entry(key.to_string()).or_default().push(stripped);
+                            }
+                        } else if let Some(index) = stripped.find([':', '=']) {
+                            let key = stripped[..index].trim();
+                            let value = stripped[index + 1..].trim();
+                            metadata.insert(key.to_string(), vec![value]);
+                            last_key = Some(key);
+                        } else {
+                            last_key = None;
+                        }
+                    }
+                    // Rules are relative to the repo root.
+                    _ if stripped.starts_with('/') => {}
+                    Section::Include => entries.push(ProfileEntry::Include(stripped.to_string())),
+                    Section::Exclude => entries.push(ProfileEntry::Exclude(stripped.to_string())),
+                },
+            }
+        }
+
+        let metadata = metadata
+            .into_iter()
+            .map(|(key, values)| (key, values.join("\n").trim().to_string()))
+            .collect();
+
+        Ok(Self {
+            source,
+            entries,
+            metadata,
+        })
+    }
+
+    /// The name given when parsing the profile.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// `%include`s and rules, in the order they appear in the profile.
+    pub fn entries(&self) -> &[ProfileEntry] {
+        &self.entries
+    }
+
+    /// Key-value pairs from the `[metadata]` section.
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// The profile version, which decides how it is combined with other
+    /// profiles. Defaults to "1".
+    pub fn version(&self) -> &str {
+        self.metadata
+            .get("version")
+            .map(String::as_str)
+            .unwrap_or("1")
+    }
+}
+
+/// The sparse config of a working copy.
+#[derive(Clone, Debug)]
+pub struct Root {
+    profile: Profile,
+}
+
+impl Root {
+    pub fn from_bytes(data: impl AsRef<[u8]>, source: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            profile: Profile::from_bytes(data, source)?,
+        })
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Build the matcher for the working copy, loading included profiles
+    /// (transitively) with `read_profile`.
+    ///
+    /// `read_profile` takes a profile path and returns its content at the
+    /// commit being checked out, or `None` if there is no such file.
+    /// Missing profiles are skipped.
+    pub fn matcher(
+        &self,
+        mut read_profile: impl FnMut(&str) -> Result<Option<Vec<u8>>>,
+    ) -> Result<SparseMatcher> {
+        let origin = self.profile.source.clone();
+        let mut includes = Vec::new();
+        let mut excludes = Vec::new();
+        let mut groups = Vec::new();
+        let mut profiles = Vec::new();
+        let mut only_v1 = true;
+
+        for entry in self.profile.entries.iter() {
+            match entry {
+                ProfileEntry::Profile(name) => {
+                    let mut stack = vec![origin.clone()];
+                    let loaded =
+                        match load_profile(name, &mut read_profile, &mut stack, &mut profiles)? {
+                            Some(loaded) => loaded,
+                            None => continue,
+                        };
+                    match loaded.version.as_str() {
+                        "1" => {
+                            for rule in loaded.rules {
+                                if rule.exclude {
+                                    excludes.push(rule);
+                                } else {
+                                    includes.push(rule);
+                                }
+                            }
+                        }
+                        "2" => {
+                            only_v1 = false;
+                            groups.push(RuleGroup::new(loaded.rules)?);
+                        }
+                        version => bail!(
+                            "unsupported version '{}' of sparse profile {}",
+                            version,
+                            name
+                        ),
+                    }
+                }
+                ProfileEntry::Include(pattern) => {
+                    includes.push(Rule::new(pattern, false, origin.clone())?)
+                }
+                ProfileEntry::Exclude(pattern) => {
+                    excludes.push(Rule::new(pattern, true, origin.clone())?)
+                }
+            }
+        }
+
+        let mut main_rules = Vec::with_capacity(includes.len() + excludes.len() + 2);
+        // If there are only excludes, start by including everything.
+        if includes.is_empty() && only_v1 {
+            main_rules.push(Rule::new("**", false, DEFAULT_ORIGIN.to_string())?);
+        }
+        main_rules.push(Rule::new("glob:.hg*", false, DEFAULT_ORIGIN.to_string())?);
+        main_rules.extend(includes);
+        main_rules.extend(excludes);
+        groups.insert(0, RuleGroup::new(main_rules)?);
+
+        Ok(SparseMatcher { groups, profiles })
+    }
+}
+
+struct LoadedProfile {
+    version: String,
+    rules: Vec<Rule>,
+}
+
+/// Load a profile and the profiles it includes, flattening their rules.
+/// `stack` holds the profiles including this one, and `profiles` collects
+/// the names of all loaded profiles.
+fn load_profile(
+    name: &str,
+    read_profile: &mut impl FnMut(&str) -> Result<Option<Vec<u8>>>,
+    stack: &mut Vec<String>,
+    profiles: &mut Vec<String>,
+) -> Result<Option<LoadedProfile>> {
+    if stack.iter().any(|s| s == name) {
+        bail!(
+            "sparse profile {} includes itself: {} -> {}",
+            name,
+            stack.join(" -> "),
+            name
+        );
+    }
+    let data = match read_profile(name)
+        .with_context(|| format!("failed to read sparse profile {}", name))?
+    {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let profile = Profile::from_bytes(data, name)?;
+    if !profiles.iter().any(|p| p == name) {
+        profiles.push(name.to_string());
+    }
+
+    stack.push(name.to_string());
+    let origin = stack.join(" -> ");
+    let mut rules = Vec::new();
+    for entry in profile.entries.iter() {
+        match entry {
+            ProfileEntry::Profile(child) => {
+                if let Some(loaded) = load_profile(child, read_profile, stack, profiles)? {
+                    rules.extend(loaded.rules);
+                }
+            }
+            ProfileEntry::Include(pattern) => {
+                rules.push(Rule::new(pattern, false, origin.clone())?)
+            }
+            ProfileEntry::Exclude(pattern) => rules.push(Rule::new(pattern, true, origin.clone())?),
+        }
+    }
+    stack.pop();
+
+    Ok(Some(LoadedProfile {
+        version: profile.version().to_string(),
+        rules,
+    }))
+}
+
+/// A pattern from a profile, converted to [`TreeMatcher`] globs.
+#[derive(Clone, Debug)]
+struct Rule {
+    pattern: String,
+    exclude: bool,
+    globs: Vec<String>,
+    /// The chain of profiles this rule was included through.
+    origin: String,
+}
+
+impl Rule {
+    fn new(pattern: &str, exclude: bool, origin: String) -> Result<Self> {
+        let globs = pattern_to_globs(pattern)
+            .with_context(|| format!("invalid sparse rule {} in {}", pattern, origin))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            exclude,
+            globs,
+            origin,
+        })
+    }
+
+    fn tree_rules(&self) -> impl Iterator<Item = String> + '_ {
+        self.globs.iter().map(move |glob| {
+            if self.exclude {
+                format!("!{}", glob)
+            } else {
+                glob.clone()
+            }
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} from {}", self.pattern, self.origin)
+    }
+}
+
+/// Convert a sparse rule to recursive globs.
+fn pattern_to_globs(pattern: &str) -> Result<Vec<String>> {
+    let (kind, pat) = match pattern.split_once(':') {
+        Some((kind, pat)) if PATTERN_KINDS.contains(&kind) => (kind, pat),
+        _ => ("glob", pattern),
+    };
+    let globs = match kind {
+        "glob" => expand_curly_brackets(pat)
+            .iter()
+            .map(|pat| make_glob_recursive(normalize_glob(pat)))
+            .collect(),
+        "path" => {
+            let pat = if pat == "." {
+                String::new()
+            } else {
+                plain_to_glob(pat)
+            };
+            vec![make_glob_recursive(pat)]
+        }
+        _ => bail!("sparse profiles do not support {}: patterns", kind),
+    };
+    Ok(globs)
+}
+
+fn make_glob_recursive(mut glob: String) -> String {
+    if !glob.is_empty() && !glob.ends_with('/') {
+        glob.push('/');
+    }
+    glob.push_str("**");
+    glob
+}
+
+/// Rules that are matched in order, with later rules taking precedence.
+struct RuleGroup {
+    matcher: TreeMatcher,
+    rules: Vec<Rule>,
+    /// For each rule, a matcher for whether the rule applies to a path,
+    /// ignoring whether it is an include or an exclude.
+    rule_matchers: Vec<TreeMatcher>,
+}
+
+impl RuleGroup {
+    fn new(rules: Vec<Rule>) -> Result<Self> {
+        let matcher = TreeMatcher::from_rules(rules.iter().flat_map(Rule::tree_rules))?;
+        let rule_matchers = rules
+            .iter()
+            .map(|rule| TreeMatcher::from_rules(rule.globs.iter()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            matcher,
+            rules,
+            rule_matchers,
+        })
+    }
+
+    /// The rule that decides whether `path` is included.
+    fn matching_rule(&self, path: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .zip(self.rule_matchers.iter())
+            .rev()
+            .find(|(_, matcher)| matcher.matches(path))
+            .map(|(rule, _)| rule)
+    }
+}
+
+/// Matches the files in a sparse working copy. A file is included if the
+/// working copy's rules, or any version 2 profile, include it.
+pub struct SparseMatcher {
+    groups: Vec<RuleGroup>,
+    profiles: Vec<String>,
+}
+
+impl SparseMatcher {
+    /// All profiles that were loaded, including nested ones.
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    /// Explain why a file is included or not, listing the rule that
+    /// decides it for the working copy and for each version 2 profile.
+    ///
+    /// Return human-readable text.
+    pub fn explain(&self, path: &RepoPath) -> Result<String> {
+        let path = path.as_str();
+        let mut text = String::new();
+        for group in self.groups.iter() {
+            if let Some(rule) = group.matching_rule(path) {
+                let action = if rule.exclude { "excluded" } else { "included" };
+                text.push_str(&format!("{}: {} by rule {}\n", path, action, rule));
+            }
+        }
+        if text.is_empty() {
+            text.push_str(&format!("{}: not included by any rule\n", path));
+        }
+        Ok(text)
+    }
+}
+
+impl Matcher for SparseMatcher {
+    fn matches_directory(&self, path: &RepoPath) -> Result<DirectoryMatch> {
+        let mut current = DirectoryMatch::Nothing;
+        for group in self.groups.iter() {
+            match group.matcher.matches_directory(path)? {
+                DirectoryMatch::Nothing => {}
+                DirectoryMatch::Everything => return Ok(DirectoryMatch::Everything),
+                DirectoryMatch::ShouldTraverse => current = DirectoryMatch::ShouldTraverse,
+            }
+        }
+        Ok(current)
+    }
+
+    fn matches_file(&self, path: &RepoPath) -> Result<bool> {
+        for group in self.groups.iter() {
+            if group.matcher.matches_file(path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_path(path: &str) -> &RepoPath {
+        RepoPath::from_str(path).unwrap()
+    }
+
+    fn reader<'a>(
+        files: &'a [(&'a str, &'a str)],
+    ) -> impl FnMut(&str) -> Result<Option<Vec<u8>>> + 'a {
+        move |name| {
+            Ok(files
+                .iter()
+                .find(|(path, _)| *path == name)
+                .map(|(_, data)| data.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let profile = Profile::from_bytes(
+            r#"# comment
+%include base/profile
+top/level
+
+[metadata]
+title: Example
+description: first line
+  second line
+version = 2
+bad line
+
+[include]
+foo/bar
+/absolute
+
+[exclude]
+; comment
+foo/bar/*.ignore
+"#,
+            "example",
+        )?;
+
+        assert_eq!(
+            profile.entries(),
+            &[
+                ProfileEntry::Profile("base/profile".to_string()),
+                ProfileEntry::Include("top/level".to_string()),
+                ProfileEntry::Include("foo/bar".to_string()),
+                ProfileEntry::Exclude("foo/bar/*.ignore".to_string()),
+            ]
+        );
+        assert_eq!(profile.metadata()["title"], "Example");
+        assert_eq!(profile.metadata()["description"], "first line\nsecond line");
+        assert_eq!(profile.version(), "2");
+        assert_eq!(profile.metadata().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_profiles() -> Result<()> {
+        let files = [
+            ("tools/base", "[include]\nlib\n[exclude]\nlib/tests\n"),
+            ("tools/app", "%include tools/base\n[include]\napp\n"),
+        ];
+        let root = Root::from_bytes("%include tools/app\n%include missing\n", ".hg/sparse")?;
+        let matcher = root.matcher(reader(&files))?;
+
+        assert!(matcher.matches_file(repo_path("lib/a.rs"))?);
+        assert!(matcher.matches_file(repo_path("app/main.rs"))?);
+        assert!(matcher.matches_file(repo_path(".hgignore"))?);
+        assert!(!matcher.matches_file(repo_path("lib/tests/a.rs"))?);
+        assert!(!matcher.matches_file(repo_path("other/a.rs"))?);
+        assert_eq!(
+            matcher.matches_directory(repo_path("app"))?,
+            DirectoryMatch::Everything
+        );
+        assert_eq!(
+            matcher.matches_directory(repo_path("other"))?,
+            DirectoryMatch::Nothing
+        );
+        assert_eq!(matcher.profiles(), &["tools/app", "tools/base"]);
+
+        assert_eq!(
+            matcher.explain(repo_path("lib/tests/a.rs"))?,
+            "lib/tests/a.rs: excluded by rule lib/tests from .hg/sparse -> tools/app -> tools/base\n"
+        );
+        assert_eq!(
+            matcher.explain(repo_path("other/a.rs"))?,
+            "other/a.rs: not included by any rule\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_profiles() -> Result<()> {
+        let files = [
+            (
+                "a",
+                "[metadata]\nversion: 2\n[include]\nfoo\n[exclude]\nfoo/bar\n",
+            ),
+            ("b", "[metadata]\nversion: 2\n[include]\npath:foo/bar\n"),
+        ];
+        let root = Root::from_bytes("%include a\n%include b\n", ".hg/sparse")?;
+        let matcher = root.matcher(reader(&files))?;
+
+        // Excludes of one v2 profile don't affect other profiles.
+        assert!(matcher.matches_file(repo_path("foo/a"))?);
+        assert!(matcher.matches_file(repo_path("foo/bar/a"))?);
+        assert!(!matcher.matches_file(repo_path("baz/a"))?);
+        assert_eq!(
+            matcher.explain(repo_path("foo/bar/a"))?,
+            "foo/bar/a: excluded by rule foo/bar from .hg/sparse -> a\n\
+             foo/bar/a: included by rule path:foo/bar from .hg/sparse -> b\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_excludes() -> Result<()> {
+        let root = Root::from_bytes("[exclude]\nglob:{a,b}/**/*.o\n", ".hg/sparse")?;
+        let matcher = root.matcher(reader(&[]))?;
+
+        assert!(matcher.matches_file(repo_path("a/x.c"))?);
+        assert!(!matcher.matches_file(repo_path("a/b/x.o"))?);
+        assert!(!matcher.matches_file(repo_path("b/x.o"))?);
+        assert!(matcher.matches_file(repo_path("c/x.o"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() -> Result<()> {
+        let files = [("a", "%include b\n"), ("b", "%include a\n")];
+        let root = Root::from_bytes("%include a\n", ".hg/sparse")?;
+        assert!(root.matcher(reader(&files)).is_err());
+
+        let root = Root::from_bytes("re:foo.*\n", ".hg/sparse")?;
+        assert!(root.matcher(reader(&[])).is_err());
+
+        let files = [("a", "[metadata]\nversion: 3\n")];
+        let root = Root::from_bytes("%include a\n", ".hg/sparse")?;
+        assert!(root.matcher(reader(&files)).is_err());
+        Ok(())
+    }
+}
@@ -175,7 +175,7 @@ impl AuxStore {
             None => return Ok(None),
             Some(slice) => slice?,
         };
-        let bytes = log.slice_to_bytes(slice);
+        let bytes = log.entry_to_bytes(slice);
         drop(log);
 
         Entry::deserialize(bytes).map(|(_hgid, entry)| Some(entry))
@@ -195,7 +195,7 @@ impl AuxStore {
         let log = self.0.read();
         log.iter()
             .map(|slice| {
-                let bytes = log.slice_to_bytes(slice?);
+                let bytes = log.entry_to_bytes(slice?);
                 Entry::deserialize(bytes).map(|(hgid, _entry)| hgid)
             })
             .collect()
@@ -133,7 +133,7 @@ impl Entry {
             Some(buf) => buf?,
         };
 
-        let bytes = locked_log.slice_to_bytes(buf);
+        let bytes = locked_log.entry_to_bytes(buf);
         drop(locked_log);
         Entry::from_bytes(bytes).map(Some)
     }
@@ -412,7 +412,7 @@ impl ToKeys for IndexedLogHgIdDataStore {
         let log = &self.store.read();
         log.iter()
             .map(|entry| {
-                let bytes = log.slice_to_bytes(entry?);
+                let bytes = log.entry_to_bytes(entry?);
                 Entry::from_bytes(bytes)
             })
             .map(|entry| Ok(entry?.key))
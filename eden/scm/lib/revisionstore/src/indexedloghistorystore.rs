@@ -148,7 +148,7 @@ impl Entry {
             None => return Ok(None),
             Some(buf) => buf?,
         };
-        let buf = log.slice_to_bytes(buf);
+        let buf = log.entry_to_bytes(buf);
         drop(log);
         Self::from_slice(buf).map(Some)
     }
@@ -284,7 +284,7 @@ impl ToKeys for IndexedLogHgIdHistoryStore {
         let log = &self.log.read();
         log.iter()
             .map(|entry| {
-                let bytes = log.slice_to_bytes(entry?);
+                let bytes = log.entry_to_bytes(entry?);
                 Entry::from_slice(bytes)
             })
             .map(|entry| Ok(entry?.key))
@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+use std::borrow::Cow;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -25,7 +26,8 @@ use tracing::debug;
 /// is local (`IndexedLog`) or shared (`RotateLog`) so that higher level stores don't have to deal
 /// with the subtle differences.
 pub enum Store {
-    Local(Log),
+    /// The `OpenOptions` are kept to reopen the `Log` when `compact` fails.
+    Local(Log, log::OpenOptions),
     Shared(RotateLog),
 }
 
@@ -38,7 +40,7 @@ pub enum StoreType {
 impl Store {
     pub fn is_local(&self) -> bool {
         match self {
-            Store::Local(_) => true,
+            Store::Local(..) => true,
             _ => false,
         }
     }
@@ -48,7 +50,7 @@ impl Store {
     pub fn lookup(&self, index_id: usize, key: impl AsRef<[u8]>) -> Result<LookupIter> {
         let key = key.as_ref();
         match self {
-            Store::Local(log) => Ok(LookupIter::Local(log.lookup(index_id, key)?)),
+            Store::Local(log, _) => Ok(LookupIter::Local(log.lookup(index_id, key)?)),
             Store::Shared(log) => Ok(LookupIter::Shared(
                 log.lookup(index_id, Bytes::copy_from_slice(key))?,
             )),
@@ -58,14 +60,14 @@ impl Store {
     /// Add the buffer to the store.
     pub fn append(&mut self, buf: impl AsRef<[u8]>) -> Result<()> {
         match self {
-            Store::Local(log) => Ok(log.append(buf)?),
+            Store::Local(log, _) => Ok(log.append(buf)?),
             Store::Shared(log) => Ok(log.append(buf)?),
         }
     }
 
-    pub fn iter(&self) -> Box<dyn Iterator<Item = IndexedlogResult<&[u8]>> + '_> {
+    pub fn iter(&self) -> Box<dyn Iterator<Item = IndexedlogResult<Cow<'_, [u8]>>> + '_> {
         match self {
-            Store::Local(log) => Box::new(log.iter()),
+            Store::Local(log, _) => Box::new(log.iter()),
             Store::Shared(log) => Box::new(log.iter()),
         }
     }
@@ -73,14 +75,23 @@ impl Store {
     /// Attempt to make slice backed by the mmap buffer to avoid heap allocation.
     pub fn slice_to_bytes(&self, slice: &[u8]) -> Bytes {
         match self {
-            Store::Local(log) => log.slice_to_bytes(slice),
+            Store::Local(log, _) => log.slice_to_bytes(slice),
             Store::Shared(log) => log.slice_to_bytes(slice),
         }
     }
 
+    /// Convert an entry returned by `lookup` or `iter` to `Bytes`, avoiding a copy if it's
+    /// backed by the mmap buffer or was decompressed.
+    pub fn entry_to_bytes(&self, entry: Cow<[u8]>) -> Bytes {
+        match self {
+            Store::Local(log, _) => log.entry_to_bytes(entry),
+            Store::Shared(log) => log.entry_to_bytes(entry),
+        }
+    }
+
     pub fn flush(&mut self) -> Result<()> {
         match self {
-            Store::Local(log) => {
+            Store::Local(log, _) => {
                 log.flush()?;
             }
             Store::Shared(log) => {
@@ -89,6 +100,43 @@ impl Store {
         };
         Ok(())
     }
+
+    /// Delete all the entries matching the key. Returns the number of deleted entries.
+    ///
+    /// Deleted entries are no longer visible, but still use disk space until `compact` is called.
+    pub fn delete(&mut self, index_id: usize, key: impl AsRef<[u8]>) -> Result<usize> {
+        match self {
+            Store::Local(log, _) => Ok(log.delete(index_id, key)?),
+            Store::Shared(log) => Ok(log.delete(index_id, key)?),
+        }
+    }
+
+    /// Write pending changes, then reclaim the disk space used by deleted entries.
+    pub fn compact(&mut self) -> Result<()> {
+        match self {
+            Store::Local(log, open_options) => {
+                // `Log::compact` consumes the log. Temporarily replace it with an in-memory one.
+                let in_memory = log::OpenOptions::new().open(())?;
+                let old_log = std::mem::replace(log, in_memory);
+                let path = old_log.path().as_opt_path().map(|path| path.to_path_buf());
+                match old_log.compact() {
+                    Ok(compacted) => *log = compacted,
+                    Err(err) => {
+                        // The on-disk log is left either uncompacted or compacted. Reopen it so
+                        // the store stays usable.
+                        if let Some(path) = path {
+                            *log = open_options.open(path)?;
+                        }
+                        return Err(err.into());
+                    }
+                }
+            }
+            Store::Shared(log) => {
+                log.compact()?;
+            }
+        };
+        Ok(())
+    }
 }
 
 /// Iterator returned from `Store::lookup`.
@@ -98,7 +146,7 @@ pub enum LookupIter<'a> {
 }
 
 impl<'a> Iterator for LookupIter<'a> {
-    type Item = Result<&'a [u8]>;
+    type Item = Result<Cow<'a, [u8]>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
@@ -169,9 +217,9 @@ impl StoreOpenOptions {
     /// Data added to a local store will never be rotated out, and `fsync(2)` is used to guarantee
     /// data consistency.
     pub fn local(self, path: impl AsRef<Path>) -> Result<Store> {
-        Ok(Store::Local(
-            self.into_local_open_options().open(path.as_ref())?,
-        ))
+        let open_options = self.into_local_open_options();
+        let log = open_options.open(path.as_ref())?;
+        Ok(Store::Local(log, open_options))
     }
 
     /// Convert a `StoreOpenOptions` to a `rotate::OpenOptions`.
@@ -250,7 +298,7 @@ mod tests {
 
         assert_eq!(
             store.lookup(0, b"aa")?.collect::<Result<Vec<_>>>()?,
-            vec![b"aabcd"]
+            vec![&b"aabcd"[..]]
         );
         Ok(())
     }
@@ -267,7 +315,7 @@ mod tests {
 
         assert_eq!(
             store.lookup(0, b"aa")?.collect::<Result<Vec<_>>>()?,
-            vec![b"aabcd"]
+            vec![&b"aabcd"[..]]
         );
         Ok(())
     }
@@ -5,10 +5,12 @@
  * GNU General Public License version 2.
  */
 
+use std::cmp::max;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::File;
+use std::fs::{self};
 use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
@@ -25,6 +27,7 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use anyhow::anyhow;
 use anyhow::bail;
@@ -109,6 +112,7 @@ use crate::remotestore::HgIdRemoteStore;
 use crate::types::ContentHash;
 use crate::types::StoreKey;
 use crate::uniondatastore::UnionHgIdDataStore;
+use crate::util::get_lfs_accesses_path;
 use crate::util::get_lfs_blobs_path;
 use crate::util::get_lfs_objects_path;
 use crate::util::get_lfs_pointers_path;
@@ -122,6 +126,38 @@ pub(crate) struct LfsIndexedLogBlobsStore {
     chunk_size: usize,
 }
 
+/// The `LfsAccessStore` records when blobs of a shared `LfsBlobsStore` were last accessed. It is
+/// used to evict the least recently used blobs when the shared store exceeds its size limit.
+struct LfsAccessStore {
+    inner: RwLock<Store>,
+
+    /// Blobs whose access was already recorded by this process. Recording one access per process
+    /// is precise enough for eviction, and avoids writing to the store on every read.
+    recorded: Mutex<HashSet<Sha256>>,
+}
+
+/// A blob of a shared `LfsBlobsStore` that can be evicted.
+struct LfsEvictableBlob {
+    sha256: Sha256,
+    /// Size of the blob on disk, in bytes.
+    size: u64,
+    /// Modification time of loose blobs, in seconds since the UNIX epoch.
+    mtime: Option<u64>,
+}
+
+/// Statistics returned by `LfsStore::prune`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LfsPruneStats {
+    /// Number of blobs in the store before pruning.
+    pub blobs: usize,
+    /// Size of the blobs in the store before pruning, in bytes.
+    pub bytes: u64,
+    /// Number of evicted blobs.
+    pub evicted_blobs: usize,
+    /// Size of the evicted blobs, in bytes.
+    pub evicted_bytes: u64,
+}
+
 /// The `LfsBlobsStore` holds the actual blobs. Lookup is done via the content hash (sha256) of the
 /// blob.
 pub(crate) enum LfsBlobsStore {
@@ -180,6 +216,11 @@ pub struct LfsRemote {
 pub struct LfsStore {
     pointers: RwLock<LfsPointersStore>,
     blobs: LfsBlobsStore,
+    /// Only shared stores with `lfs.cachelimit` set track accesses, since local blobs are never
+    /// evicted.
+    accesses: Option<LfsAccessStore>,
+    /// Size limit of the blobs in bytes, from `lfs.cachelimit`.
+    cache_limit: Option<u64>,
 }
 
 /// When a blob is added to the `LfsMultiplexer`, is will either be written to an `LfsStore`, or to
@@ -291,7 +332,7 @@ impl LfsPointersStore {
             Some(buf) => buf?,
         };
 
-        Self::get_from_slice(buf).map(Some)
+        Self::get_from_slice(&buf).map(Some)
     }
 
     /// Find the pointer corresponding to the passed in `Key`.
@@ -312,6 +353,15 @@ struct LfsIndexedLogBlobsEntry {
     data: Bytes,
 }
 
+/// The leading fields of a `LfsIndexedLogBlobsEntry`. Deserializing it skips reading the chunk
+/// data.
+#[derive(Deserialize)]
+struct LfsIndexedLogBlobsEntryHeader {
+    #[serde(with = "types::serde_with::sha256::tuple")]
+    sha256: Sha256,
+    range: Range<usize>,
+}
+
 impl DefaultOpenOptions<rotate::OpenOptions> for LfsIndexedLogBlobsStore {
     fn default_open_options() -> rotate::OpenOptions {
         Self::default_store_open_options().into_shared_open_options()
@@ -360,7 +410,7 @@ impl LfsIndexedLogBlobsStore {
         let store = self.inner.read();
         let chunks_iter = store
             .lookup(0, hash)?
-            .map(|data| Ok(deserialize::<LfsIndexedLogBlobsEntry>(data?)?));
+            .map(|data| Ok(deserialize::<LfsIndexedLogBlobsEntry>(&data?)?));
 
         // Filter errors. It's possible that one entry is corrupted, or for whatever reason can't
         // be deserialized, whenever this blob/entry is refetched, the corrupted entry will still be
@@ -458,6 +508,115 @@ impl LfsIndexedLogBlobsStore {
     pub fn flush(&self) -> Result<()> {
         self.inner.write().flush()
     }
+
+    /// Compute the size of all the blobs in the store, from the end of their last chunk. Only the
+    /// chunk headers are deserialized. Chunks that cannot be deserialized are ignored.
+    fn sizes(&self) -> Result<HashMap<Sha256, u64>> {
+        let store = self.inner.read();
+        let mut sizes = HashMap::new();
+        for data in store.iter() {
+            if let Ok(header) = deserialize::<LfsIndexedLogBlobsEntryHeader>(&data?) {
+                let size = sizes.entry(header.sha256).or_insert(0);
+                *size = max(*size, header.range.end as u64);
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// Remove all the chunks of the blob. The disk space is only reclaimed by `compact`.
+    pub fn remove(&self, hash: &Sha256) -> Result<()> {
+        self.inner.write().delete(0, hash)?;
+        Ok(())
+    }
+
+    /// Reclaim the disk space used by removed blobs.
+    pub fn compact(&self) -> Result<()> {
+        self.inner.write().compact()
+    }
+}
+
+/// On-disk format of an access record in the `LfsAccessStore`.
+#[derive(Serialize, Deserialize)]
+struct LfsAccessEntry {
+    #[serde(with = "types::serde_with::sha256::tuple")]
+    sha256: Sha256,
+    /// Seconds since the UNIX epoch.
+    time: u64,
+}
+
+impl DefaultOpenOptions<rotate::OpenOptions> for LfsAccessStore {
+    fn default_open_options() -> rotate::OpenOptions {
+        Self::default_store_open_options().into_shared_open_options()
+    }
+}
+
+impl LfsAccessStore {
+    fn default_store_open_options() -> StoreOpenOptions {
+        StoreOpenOptions::new()
+            .max_log_count(4)
+            .max_bytes_per_log(100_000_000 / 4)
+            .index("sha256", |_| {
+                vec![IndexOutput::Reference(0..Sha256::len() as u64)]
+            })
+    }
+
+    fn shared(path: &Path) -> Result<Self> {
+        let path = get_lfs_accesses_path(path)?;
+        Ok(Self {
+            inner: RwLock::new(LfsAccessStore::default_store_open_options().shared(path)?),
+            recorded: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Record that the blob is accessed now.
+    fn record(&self, hash: &Sha256) -> Result<()> {
+        if !self.recorded.lock().insert(*hash) {
+            return Ok(());
+        }
+
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        self.record_at(hash, time)
+    }
+
+    /// Record that the blob was accessed at `time`, in seconds since the UNIX epoch.
+    fn record_at(&self, hash: &Sha256, time: u64) -> Result<()> {
+        let entry = LfsAccessEntry {
+            sha256: *hash,
+            time,
+        };
+        self.inner.write().append(serialize(&entry)?)
+    }
+
+    /// Read the last access time of the blobs that have access records.
+    fn last_accesses(&self) -> Result<HashMap<Sha256, u64>> {
+        let store = self.inner.read();
+        let mut accesses = HashMap::new();
+        for data in store.iter() {
+            // Like `LfsIndexedLogBlobsStore::get`, ignore entries that cannot be deserialized.
+            if let Ok(entry) = deserialize::<LfsAccessEntry>(&data?) {
+                let time = accesses.entry(entry.sha256).or_insert(entry.time);
+                *time = max(*time, entry.time);
+            }
+        }
+        Ok(accesses)
+    }
+
+    /// Remove the access records of the blob.
+    fn remove(&self, hash: &Sha256) -> Result<()> {
+        self.inner.write().delete(0, hash)?;
+        self.recorded.lock().remove(hash);
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.inner.write().compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.inner.write().flush()
+    }
 }
 
 impl LfsBlobsStore {
@@ -600,6 +759,113 @@ impl LfsBlobsStore {
             _ => Ok(()),
         }
     }
+
+    /// List the blobs that can be evicted to reclaim disk space. A blob present in several stores
+    /// is listed once per store.
+    ///
+    /// Local blobs might not be uploaded yet, thus they are never evicted.
+    fn evictable(&self) -> Result<Vec<LfsEvictableBlob>> {
+        let blobs = match self {
+            LfsBlobsStore::Loose(_, true) => Vec::new(),
+
+            LfsBlobsStore::Loose(path, false) => {
+                let mut blobs = Vec::new();
+                // Loose blobs are stored as <2-digits hex>/<62-digits hex>.
+                for dir in fs::read_dir(path)? {
+                    let dir = dir?;
+                    if !dir.file_type()?.is_dir() {
+                        continue;
+                    }
+                    let prefix = dir.file_name();
+                    for file in fs::read_dir(dir.path())? {
+                        let file = file?;
+                        let metadata = file.metadata()?;
+                        if !metadata.is_file() {
+                            continue;
+                        }
+                        let hex = format!(
+                            "{}{}",
+                            prefix.to_string_lossy(),
+                            file.file_name().to_string_lossy()
+                        );
+                        let sha256 = match Sha256::from_str(&hex) {
+                            Ok(sha256) => sha256,
+                            Err(_) => continue,
+                        };
+                        let mtime = metadata
+                            .modified()
+                            .ok()
+                            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                            .map(|duration| duration.as_secs());
+                        blobs.push(LfsEvictableBlob {
+                            sha256,
+                            size: metadata.len(),
+                            mtime,
+                        });
+                    }
+                }
+                blobs
+            }
+
+            LfsBlobsStore::IndexedLog(log) => log
+                .sizes()?
+                .into_iter()
+                .map(|(sha256, size)| LfsEvictableBlob {
+                    sha256,
+                    size,
+                    mtime: None,
+                })
+                .collect(),
+
+            LfsBlobsStore::Union(first, second) => {
+                let mut blobs = first.evictable()?;
+                blobs.extend(second.evictable()?);
+                blobs
+            }
+        };
+
+        Ok(blobs)
+    }
+
+    /// Evict the blob from all the shared stores. Local blobs are never evicted.
+    ///
+    /// The disk space used by evicted blobs might only be reclaimed by `compact`.
+    fn evict(&self, hash: &Sha256) -> Result<()> {
+        match self {
+            LfsBlobsStore::Loose(_, true) => {}
+
+            LfsBlobsStore::Loose(path, false) => {
+                let path = LfsBlobsStore::path(&path, hash);
+                // Another process might have evicted the blob already.
+                if let Err(e) = fs::remove_file(&path) {
+                    if e.kind() != ErrorKind::NotFound {
+                        return Err(e).with_context(|| format!("Cannot evict LFS blob {}", hash));
+                    }
+                }
+            }
+
+            LfsBlobsStore::IndexedLog(log) => log.remove(hash)?,
+
+            LfsBlobsStore::Union(first, second) => {
+                first.evict(hash)?;
+                second.evict(hash)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reclaim the disk space used by evicted blobs.
+    fn compact(&self) -> Result<()> {
+        match self {
+            LfsBlobsStore::IndexedLog(log) => log.compact(),
+            LfsBlobsStore::Union(first, second) => {
+                first.compact()?;
+                second.compact()
+            }
+            LfsBlobsStore::Loose(..) => Ok(()),
+        }
+    }
 }
 
 pub(crate) enum LfsStoreEntry {
@@ -608,10 +874,17 @@ pub(crate) enum LfsStoreEntry {
 }
 
 impl LfsStore {
-    fn new(pointers: LfsPointersStore, blobs: LfsBlobsStore) -> Result<Self> {
+    fn new(
+        pointers: LfsPointersStore,
+        blobs: LfsBlobsStore,
+        accesses: Option<LfsAccessStore>,
+        cache_limit: Option<u64>,
+    ) -> Result<Self> {
         Ok(Self {
             pointers: RwLock::new(pointers),
             blobs,
+            accesses,
+            cache_limit,
         })
     }
 
@@ -622,15 +895,25 @@ impl LfsStore {
         let path = path.as_ref();
         let pointers = LfsPointersStore::local(path, config)?;
         let blobs = LfsBlobsStore::local(path)?;
-        LfsStore::new(pointers, blobs)
+        LfsStore::new(pointers, blobs, None, None)
     }
 
     /// Create a new shared `LfsStore`.
+    ///
+    /// When `lfs.cachelimit` is set, accesses to blobs are recorded, so `LfsStore::prune` can
+    /// evict the least recently used blobs when they use more than `lfs.cachelimit` bytes.
     pub fn shared(path: impl AsRef<Path>, config: &ConfigSet) -> Result<Self> {
         let path = path.as_ref();
         let pointers = LfsPointersStore::shared(path, config)?;
         let blobs = LfsBlobsStore::shared(path, config)?;
-        LfsStore::new(pointers, blobs)
+        let cache_limit = config
+            .get_opt::<ByteCount>("lfs", "cachelimit")?
+            .map(|limit| limit.value());
+        let accesses = match cache_limit {
+            Some(_) => Some(LfsAccessStore::shared(path)?),
+            None => None,
+        };
+        LfsStore::new(pointers, blobs, accesses, cache_limit)
     }
 
     pub fn repair(path: impl AsRef<Path>) -> Result<String> {
@@ -639,10 +922,94 @@ impl LfsStore {
 
         repair_str += &LfsPointersStore::repair(get_lfs_pointers_path(path)?)?;
         repair_str += &LfsIndexedLogBlobsStore::repair(get_lfs_blobs_path(path)?)?;
+        repair_str += &LfsAccessStore::repair(get_lfs_accesses_path(path)?)?;
 
         Ok(repair_str)
     }
 
+    /// Record that the blob was accessed, for shared stores with `lfs.cachelimit` set.
+    ///
+    /// Access records are only used to decide which blobs to evict. Failing to write them should
+    /// not fail reads or writes, thus errors are ignored.
+    fn record_access(&self, hash: &Sha256) {
+        if let Some(accesses) = &self.accesses {
+            let _ = accesses.record(hash);
+        }
+    }
+
+    /// Evict the least recently used blobs until the blobs of the shared store use at most
+    /// `lfs.cachelimit` bytes.
+    ///
+    /// Does nothing for local stores, or if `lfs.cachelimit` is not set.
+    pub fn prune(&self) -> Result<LfsPruneStats> {
+        match self.cache_limit {
+            Some(limit) => self.prune_to(limit),
+            None => Ok(LfsPruneStats::default()),
+        }
+    }
+
+    /// Evict the least recently used blobs until the blobs of the shared store use at most
+    /// `limit` bytes. Blobs without access records are considered the least recently used.
+    ///
+    /// Pointers are kept, so evicted blobs are fetched again from the remote store when needed.
+    /// Local stores are never pruned, since their blobs might not be uploaded yet.
+    pub fn prune_to(&self, limit: u64) -> Result<LfsPruneStats> {
+        (|| -> Result<LfsPruneStats> {
+            // Make sure recently added blobs and accesses are considered.
+            self.blobs.flush()?;
+            let last_accesses = match &self.accesses {
+                Some(accesses) => {
+                    accesses.flush()?;
+                    accesses.last_accesses()?
+                }
+                None => HashMap::new(),
+            };
+
+            // Blobs can be both in the IndexedLog and loose stores. Evict them as a whole.
+            let mut blobs: HashMap<Sha256, (u64, Option<u64>)> = HashMap::new();
+            for blob in self.blobs.evictable()? {
+                let (size, last_access) = blobs
+                    .entry(blob.sha256)
+                    .or_insert((0, last_accesses.get(&blob.sha256).copied()));
+                *size += blob.size;
+                *last_access = max(*last_access, blob.mtime);
+            }
+
+            let mut stats = LfsPruneStats {
+                blobs: blobs.len(),
+                bytes: blobs.values().map(|(size, _)| size).sum(),
+                ..Default::default()
+            };
+
+            let mut blobs = blobs.into_iter().collect::<Vec<_>>();
+            blobs.sort_unstable_by_key(|(_, (_, last_access))| *last_access);
+
+            let mut remaining = stats.bytes;
+            for (sha256, (size, _)) in blobs {
+                if remaining <= limit {
+                    break;
+                }
+                self.blobs.evict(&sha256)?;
+                if let Some(accesses) = &self.accesses {
+                    accesses.remove(&sha256)?;
+                }
+                remaining -= size;
+                stats.evicted_blobs += 1;
+                stats.evicted_bytes += size;
+            }
+
+            if stats.evicted_blobs > 0 {
+                self.blobs.compact()?;
+                if let Some(accesses) = &self.accesses {
+                    accesses.compact()?;
+                }
+            }
+
+            Ok(stats)
+        })()
+        .context("Cannot prune the LFS store")
+    }
+
     fn blob_impl(&self, key: StoreKey) -> Result<StoreResult<(LfsPointersEntry, Bytes)>> {
         let pointer = self.pointers.read().entry(&key)?;
 
@@ -663,7 +1030,10 @@ impl LfsStore {
                                 hgid,
                             )))
                         }
-                        Some(blob) => Ok(StoreResult::Found((entry, blob))),
+                        Some(blob) => {
+                            self.record_access(&content_hash.clone().unwrap_sha256());
+                            Ok(StoreResult::Found((entry, blob)))
+                        }
                     }
                 }
             },
@@ -686,7 +1056,10 @@ impl LfsStore {
                 Some(content_hash) => {
                     match self.blobs.get(&content_hash.clone().unwrap_sha256())? {
                         None => Ok(Some(LfsStoreEntry::PointerOnly(entry))),
-                        Some(blob) => Ok(Some(LfsStoreEntry::PointerAndBlob(entry, blob))),
+                        Some(blob) => {
+                            self.record_access(&content_hash.clone().unwrap_sha256());
+                            Ok(Some(LfsStoreEntry::PointerAndBlob(entry, blob)))
+                        }
                     }
                 }
             },
@@ -694,7 +1067,9 @@ impl LfsStore {
     }
 
     pub fn add_blob(&self, hash: &Sha256, blob: Bytes) -> Result<()> {
-        self.blobs.add(hash, blob)
+        self.blobs.add(hash, blob)?;
+        self.record_access(hash);
+        Ok(())
     }
 
     pub(crate) fn add_pointer(&self, pointer_entry: LfsPointersEntry) -> Result<()> {
@@ -810,13 +1185,16 @@ impl HgIdMutableDeltaStore for LfsStore {
     fn add(&self, delta: &Delta, _metadata: &Metadata) -> Result<()> {
         ensure!(delta.base.is_none(), "Deltas aren't supported.");
         let (lfs_pointer_entry, blob) = lfs_from_hg_file_blob(delta.key.hgid, &delta.data)?;
-        self.blobs.add(&lfs_pointer_entry.sha256(), blob)?;
+        self.add_blob(&lfs_pointer_entry.sha256(), blob)?;
         self.pointers.write().add(lfs_pointer_entry)
     }
 
     fn flush(&self) -> Result<Option<Vec<PathBuf>>> {
         self.blobs.flush()?;
         self.pointers.write().0.flush()?;
+        if let Some(accesses) = &self.accesses {
+            accesses.flush()?;
+        }
         Ok(None)
     }
 }
@@ -2103,6 +2481,105 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_prune_least_recently_used() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut config = make_lfs_config(&dir, "test_prune_least_recently_used");
+        config.set("lfs", "cachelimit", Some("100"), &Default::default());
+        let store = LfsStore::shared(&dir, &config)?;
+
+        let blobs = (0..3u8)
+            .map(|i| {
+                let data = Bytes::from(vec![i; 10]);
+                (ContentHash::sha256(&data).unwrap_sha256(), data)
+            })
+            .collect::<Vec<_>>();
+        for (hash, data) in &blobs {
+            store.add_blob(hash, data.clone())?;
+        }
+        let accesses = store.accesses.as_ref().unwrap();
+        accesses.record_at(&blobs[0].0, u64::MAX - 1)?;
+        accesses.record_at(&blobs[1].0, 1)?;
+        accesses.record_at(&blobs[2].0, u64::MAX)?;
+
+        // The blob accessed the least recently is evicted.
+        let stats = store.prune_to(25)?;
+        assert_eq!(
+            stats,
+            LfsPruneStats {
+                blobs: 3,
+                bytes: 30,
+                evicted_blobs: 1,
+                evicted_bytes: 10,
+            }
+        );
+        assert!(store.blobs.contains(&blobs[0].0)?);
+        assert!(!store.blobs.contains(&blobs[1].0)?);
+        assert!(store.blobs.contains(&blobs[2].0)?);
+
+        // The store is within the limit.
+        assert_eq!(store.prune_to(25)?.evicted_blobs, 0);
+
+        // Evictions are visible to other stores.
+        let config = make_lfs_config(&dir, "test_prune_least_recently_used");
+        let store = LfsStore::shared(&dir, &config)?;
+        assert_eq!(store.blobs.get(&blobs[0].0)?, Some(blobs[0].1.clone()));
+        assert_eq!(store.blobs.get(&blobs[1].0)?, None);
+
+        // Without `lfs.cachelimit`, accesses are not recorded, and nothing is evicted.
+        assert!(store.accesses.is_none());
+        assert_eq!(store.prune()?, LfsPruneStats::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_cache_limit() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut config = make_lfs_config(&dir, "test_prune_cache_limit");
+        config.set("lfs", "cachelimit", Some("15"), &Default::default());
+        let store = LfsStore::shared(&dir, &config)?;
+
+        // Loose blobs in the shared store can be evicted too.
+        let loose_store = LfsBlobsStore::loose(get_lfs_objects_path(dir.path())?);
+        let loose_data = Bytes::from(vec![1; 10]);
+        let loose_hash = ContentHash::sha256(&loose_data).unwrap_sha256();
+        loose_store.add(&loose_hash, loose_data)?;
+
+        let data = Bytes::from(vec![2; 10]);
+        let hash = ContentHash::sha256(&data).unwrap_sha256();
+        store.add_blob(&hash, data)?;
+        store
+            .accesses
+            .as_ref()
+            .unwrap()
+            .record_at(&hash, u64::MAX)?;
+
+        let stats = store.prune()?;
+        assert_eq!(stats.evicted_blobs, 1);
+        assert!(!store.blobs.contains(&loose_hash)?);
+        assert!(store.blobs.contains(&hash)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_local() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config = make_lfs_config(&dir, "test_prune_local");
+        let store = LfsStore::local(&dir, &config)?;
+
+        let data = Bytes::from(vec![1; 10]);
+        let hash = ContentHash::sha256(&data).unwrap_sha256();
+        store.add_blob(&hash, data)?;
+
+        // Local blobs might not be uploaded yet. They are never evicted.
+        assert_eq!(store.prune_to(0)?, LfsPruneStats::default());
+        assert!(store.blobs.contains(&hash)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_get_missing() -> Result<()> {
         let dir = TempDir::new()?;
@@ -2241,6 +2718,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sizes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut config = make_lfs_config(&dir, "test_sizes");
+        config.set("lfs", "blobschunksize", Some("3"), &Default::default());
+
+        let store = LfsIndexedLogBlobsStore::shared(dir.path(), &config)?;
+
+        let data = Bytes::from(&[1, 2, 3, 4, 5, 6, 7][..]);
+        let sha256 = ContentHash::sha256(&data).unwrap_sha256();
+
+        // A blob added twice is counted once.
+        store.add(&sha256, data.clone())?;
+        store.add(&sha256, data)?;
+        store.flush()?;
+
+        let sizes = store.sizes()?;
+        assert_eq!(sizes.len(), 1);
+        assert_eq!(sizes.get(&sha256), Some(&7));
+
+        Ok(())
+    }
+
     #[test]
     fn test_overlapped_chunked() -> Result<()> {
         let dir = TempDir::new()?;
@@ -190,6 +190,7 @@ pub use crate::indexedlogauxstore::AuxStore;
 pub use crate::indexedlogdatastore::IndexedLogHgIdDataStore;
 pub use crate::indexedloghistorystore::IndexedLogHgIdHistoryStore;
 pub use crate::indexedlogutil::StoreType;
+pub use crate::lfs::LfsPruneStats;
 pub use crate::localstore::ExtStoredPolicy;
 pub use crate::localstore::LocalStore;
 pub use crate::memcache::MemcacheStore;
@@ -10,6 +10,7 @@ use std::sync::Arc;
 
 use parking_lot::RwLock;
 
+use crate::lfs::LfsPruneStats;
 use crate::scmstore::metrics::namespaced;
 use crate::scmstore::metrics::ApiMetrics;
 use crate::scmstore::metrics::FetchMetrics;
@@ -175,11 +176,51 @@ impl FileStoreApiMetrics {
     }
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct LfsCacheMetrics {
+    /// Number of times the LFS cache was pruned
+    prunes: usize,
+
+    /// Number of blobs in the LFS cache, as of the last prune
+    blobs: usize,
+
+    /// Size of the blobs in the LFS cache in bytes, as of the last prune
+    bytes: usize,
+
+    /// Number of blobs evicted from the LFS cache
+    evicted_blobs: usize,
+
+    /// Size of the blobs evicted from the LFS cache in bytes
+    evicted_bytes: usize,
+}
+
+impl LfsCacheMetrics {
+    pub(crate) fn prune(&mut self, stats: &LfsPruneStats) {
+        self.prunes += 1;
+        self.blobs = stats.blobs - stats.evicted_blobs;
+        self.bytes = (stats.bytes - stats.evicted_bytes) as usize;
+        self.evicted_blobs += stats.evicted_blobs;
+        self.evicted_bytes += stats.evicted_bytes as usize;
+    }
+
+    fn metrics(&self) -> impl Iterator<Item = (&'static str, usize)> {
+        std::array::IntoIter::new([
+            ("prunes", self.prunes),
+            ("blobs", self.blobs),
+            ("bytes", self.bytes),
+            ("evicted_blobs", self.evicted_blobs),
+            ("evicted_bytes", self.evicted_bytes),
+        ])
+        .filter(|&(_, v)| v != 0)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct FileStoreMetrics {
     pub(crate) fetch: FileStoreFetchMetrics,
     pub(crate) write: FileStoreWriteMetrics,
     pub(crate) api: FileStoreApiMetrics,
+    pub(crate) lfs_cache: LfsCacheMetrics,
 }
 
 impl FileStoreMetrics {
@@ -192,7 +233,8 @@ impl FileStoreMetrics {
             "scmstore.file",
             namespaced("fetch", self.fetch.metrics())
                 .chain(namespaced("write", self.write.metrics()))
-                .chain(namespaced("api", self.api.metrics())),
+                .chain(namespaced("api", self.api.metrics()))
+                .chain(namespaced("lfs_cache", self.lfs_cache.metrics())),
         )
     }
 }
@@ -43,6 +43,7 @@ use crate::indexedlogdatastore::Entry;
 use crate::indexedlogdatastore::IndexedLogHgIdDataStore;
 use crate::indexedlogutil::StoreType;
 use crate::lfs::lfs_from_hg_file_blob;
+use crate::lfs::LfsPruneStats;
 use crate::lfs::LfsRemote;
 use crate::lfs::LfsStore;
 use crate::memcache::MEMCACHE_DELAY;
@@ -414,6 +415,19 @@ impl FileStore {
         self.metrics.read().metrics().collect()
     }
 
+    /// Evict the least recently used blobs from the LFS cache, so it fits in `lfs.cachelimit`.
+    ///
+    /// Blobs in the local LFS store are not uploaded yet, and are never evicted.
+    #[instrument(skip(self))]
+    pub fn prune_lfs_cache(&self) -> Result<LfsPruneStats> {
+        let stats = match self.lfs_cache {
+            Some(ref lfs_cache) => lfs_cache.prune()?,
+            None => return Ok(LfsPruneStats::default()),
+        };
+        self.metrics.write().lfs_cache.prune(&stats);
+        Ok(stats)
+    }
+
     pub fn empty() -> Self {
         FileStore {
             extstored_policy: ExtStoredPolicy::Ignore,
@@ -144,6 +144,14 @@ pub fn get_lfs_blobs_path(store_path: impl AsRef<Path>) -> Result<PathBuf> {
     Ok(path)
 }
 
+pub fn get_lfs_accesses_path(store_path: impl AsRef<Path>) -> Result<PathBuf> {
+    let mut path = get_lfs_path(store_path)?;
+    path.push("accesses");
+    create_shared_dir(&path)?;
+
+    Ok(path)
+}
+
 pub const RUN_ONCE_FILENAME: &str = "runoncemarker";
 pub fn check_run_once(store_path: impl AsRef<Path>, key: &str, cutoff: HgTime) -> bool {
     if HgTime::now() > Some(cutoff) {
@@ -0,0 +1,351 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Revset aliases. This is a port of `basealiasrules` in `parser.py`.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use configmodel::Config;
+
+use crate::parser::parse_alias;
+use crate::Error;
+use crate::Expr;
+use crate::Result;
+
+const SECTION: &str = "revsetalias";
+
+/// Function name used to mark alias arguments in a definition, so they are
+/// not confused with other aliases during expansion.
+const ARG: &str = "_aliasarg";
+
+/// Revset aliases, like `[revsetalias]` entries in config.
+#[derive(Clone, Debug, Default)]
+pub struct Aliases {
+    aliases: HashMap<String, Alias>,
+}
+
+#[derive(Clone, Debug)]
+struct Alias {
+    /// Declaration, as written in config.
+    decl: String,
+
+    /// `None` for symbol aliases like `x = ...`.
+    args: Option<Vec<String>>,
+
+    /// Replacement, or the reason the alias is broken.
+    body: std::result::Result<Expr, String>,
+}
+
+impl Aliases {
+    /// Create an empty alias table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load aliases from the `revsetalias` config section.
+    pub fn from_config(config: &dyn Config) -> Self {
+        let mut aliases = Self::new();
+        for decl in config.keys(SECTION) {
+            if let Some(defn) = config.get(SECTION, &decl) {
+                aliases.insert(&decl, &defn);
+            }
+        }
+        aliases
+    }
+
+    /// Add an alias, like `insert("f($1)", "ancestors($1)")`.
+    ///
+    /// Broken aliases are kept so using them reports the error. See also
+    /// [`Aliases::errors`].
+    pub fn insert(&mut self, decl: &str, defn: &str) {
+        let (name, args, body) = match parse_decl(decl) {
+            Ok((name, args)) => {
+                let body = parse_defn(defn, args.as_deref().unwrap_or_default())
+                    .map_err(|e| format!("bad definition of revset alias \"{}\": {}", name, e));
+                (name, args, body)
+            }
+            Err(e) => {
+                let body = Err(format!(
+                    "bad declaration of revset alias \"{}\": {}",
+                    decl, e
+                ));
+                (decl.to_string(), None, body)
+            }
+        };
+        let alias = Alias {
+            decl: decl.to_string(),
+            args,
+            body,
+        };
+        self.aliases.insert(name, alias);
+    }
+
+    /// Error messages of broken aliases, keyed by their declarations.
+    pub fn errors(&self) -> BTreeMap<&str, &str> {
+        self.aliases
+            .values()
+            .filter_map(|a| match &a.body {
+                Ok(_) => None,
+                Err(e) => Some((a.decl.as_str(), e.as_str())),
+            })
+            .collect()
+    }
+
+    /// Replace aliases in `expr` recursively.
+    pub fn expand(&self, expr: &Expr) -> Result<Expr> {
+        self.expand_with_stack(expr, &mut Vec::new())
+    }
+
+    fn expand_with_stack(&self, expr: &Expr, expanding: &mut Vec<String>) -> Result<Expr> {
+        let (name, alias, args) = match self.lookup(expr) {
+            Some(found) => found,
+            None => return self.expand_children(expr, expanding),
+        };
+        let body = alias.body.as_ref().map_err(|e| Error::Parse(e.clone()))?;
+        if expanding.iter().any(|n| n == name) {
+            return Err(Error::Parse(format!(
+                "infinite expansion of revset alias \"{}\" detected",
+                name
+            )));
+        }
+        expanding.push(name.to_string());
+        let replacement = self.expand_with_stack(body, expanding)?;
+        expanding.pop();
+
+        let (names, args) = match (&alias.args, args) {
+            (Some(names), Some(args)) => (names, args),
+            _ => return Ok(replacement),
+        };
+        if names.len() != args.len() {
+            return Err(Error::Parse(format!(
+                "invalid number of arguments: {}",
+                args.len()
+            )));
+        }
+        let mut values = HashMap::with_capacity(args.len());
+        for (name, arg) in names.iter().zip(args) {
+            values.insert(name.as_str(), self.expand_with_stack(arg, &mut Vec::new())?);
+        }
+        Ok(substitute_args(&replacement, &values))
+    }
+
+    /// Find the alias matching `expr`. Symbol aliases only match symbols,
+    /// and function aliases only match function calls.
+    fn lookup<'a>(&'a self, expr: &'a Expr) -> Option<(&'a str, &'a Alias, Option<&'a [Expr]>)> {
+        let (name, args) = match expr {
+            Expr::Symbol(name) => (name, None),
+            Expr::Func(name, args) if name != ARG => (name, Some(args.as_slice())),
+            _ => return None,
+        };
+        let alias = self.aliases.get(name)?;
+        if alias.args.is_some() != args.is_some() {
+            return None;
+        }
+        Some((name, alias, args))
+    }
+
+    fn expand_children(&self, expr: &Expr, expanding: &mut Vec<String>) -> Result<Expr> {
+        map_children(expr, &mut |e| self.expand_with_stack(e, expanding))
+    }
+}
+
+/// Parse a declaration into its name and argument names.
+fn parse_decl(decl: &str) -> Result<(String, Option<Vec<String>>)> {
+    match parse_alias(decl)? {
+        Expr::Symbol(name) => {
+            if name.starts_with('$') {
+                return Err(Error::Parse(format!("invalid symbol '{}'", name)));
+            }
+            Ok((name, None))
+        }
+        Expr::Func(name, args) => {
+            if name.starts_with('$') {
+                return Err(Error::Parse(format!("invalid function '{}'", name)));
+            }
+            let mut names = Vec::with_capacity(args.len());
+            for arg in args {
+                match arg {
+                    Expr::Symbol(arg) => names.push(arg),
+                    _ => return Err(Error::Parse("invalid argument list".to_string())),
+                }
+            }
+            if names.iter().collect::<HashSet<_>>().len() != names.len() {
+                return Err(Error::Parse(
+                    "argument names collide with each other".to_string(),
+                ));
+            }
+            Ok((name, Some(names)))
+        }
+        _ => Err(Error::Parse("invalid format".to_string())),
+    }
+}
+
+/// Parse a definition. Argument names are replaced by placeholders.
+fn parse_defn(defn: &str, args: &[String]) -> Result<Expr> {
+    fn relabel(expr: &Expr, args: &[String]) -> Result<Expr> {
+        match expr {
+            Expr::Symbol(name) if args.contains(name) => Ok(Expr::Func(
+                ARG.to_string(),
+                vec![Expr::String(name.clone())],
+            )),
+            Expr::Symbol(name) if name.starts_with('$') => {
+                Err(Error::Parse(format!("invalid symbol '{}'", name)))
+            }
+            _ => map_children(expr, &mut |e| relabel(e, args)),
+        }
+    }
+    relabel(&parse_alias(defn)?, args)
+}
+
+/// Replace argument placeholders with their values.
+fn substitute_args(expr: &Expr, values: &HashMap<&str, Expr>) -> Expr {
+    if let Expr::Func(name, args) = expr {
+        if name == ARG {
+            if let [Expr::String(arg)] = args.as_slice() {
+                if let Some(value) = values.get(arg.as_str()) {
+                    return value.clone();
+                }
+            }
+        }
+    }
+    let result: Result<Expr> = map_children(expr, &mut |e| Ok(substitute_args(e, values)));
+    result.expect("substitute_args does not fail")
+}
+
+/// Rebuild `expr` with `f` applied to its direct children.
+fn map_children(expr: &Expr, f: &mut dyn FnMut(&Expr) -> Result<Expr>) -> Result<Expr> {
+    fn opt(
+        e: &Option<Box<Expr>>,
+        f: &mut dyn FnMut(&Expr) -> Result<Expr>,
+    ) -> Result<Option<Box<Expr>>> {
+        Ok(match e {
+            Some(e) => Some(Box::new(f(e)?)),
+            None => None,
+        })
+    }
+    let expr = match expr {
+        Expr::Symbol(_) | Expr::String(_) => expr.clone(),
+        Expr::Func(name, args) => {
+            let args = args.iter().map(&mut *f).collect::<Result<Vec<_>>>()?;
+            Expr::Func(name.clone(), args)
+        }
+        Expr::KeyValue(key, value) => Expr::KeyValue(key.clone(), Box::new(f(value)?)),
+        Expr::And(x, y) => Expr::And(Box::new(f(x)?), Box::new(f(y)?)),
+        Expr::Or(x, y) => Expr::Or(Box::new(f(x)?), Box::new(f(y)?)),
+        Expr::Minus(x, y) => Expr::Minus(Box::new(f(x)?), Box::new(f(y)?)),
+        Expr::Not(x) => Expr::Not(Box::new(f(x)?)),
+        Expr::Negate(x) => Expr::Negate(Box::new(f(x)?)),
+        Expr::Only(x, y) => Expr::Only(Box::new(f(x)?), opt(y, f)?),
+        Expr::DagRange(x, y) => Expr::DagRange(opt(x, f)?, opt(y, f)?),
+        Expr::Range(x, y) => Expr::Range(opt(x, f)?, opt(y, f)?),
+        Expr::Parent(x, n) => Expr::Parent(Box::new(f(x)?), opt(n, f)?),
+        Expr::Ancestor(x, n) => Expr::Ancestor(Box::new(f(x)?), Box::new(f(n)?)),
+    };
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn aliases(items: &[(&str, &str)]) -> Aliases {
+        let mut aliases = Aliases::new();
+        for (decl, defn) in items {
+            aliases.insert(decl, defn);
+        }
+        aliases
+    }
+
+    /// Expand aliases in `program` and format the result, or the error.
+    fn expand(aliases: &Aliases, program: &str) -> String {
+        match aliases.expand(&parse(program).unwrap()) {
+            Ok(expr) => expr.to_string(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_expand_symbol_and_function() {
+        let a = aliases(&[
+            ("mine", "author(me)"),
+            ("f($1, $2)", "$1::$2 - mine"),
+            ("g(x)", "f(x, x)"),
+        ]);
+        assert_eq!(expand(&a, "mine"), "author(me)");
+        assert_eq!(expand(&a, "mine()"), "mine()");
+        assert_eq!(expand(&a, "f(a, b)"), "((a::b) - author(me))");
+        assert_eq!(
+            expand(&a, "g(mine)"),
+            "((author(me)::author(me)) - author(me))"
+        );
+        assert_eq!(expand(&a, "f"), "f");
+        assert_eq!(expand(&a, "f(a)"), "error: invalid number of arguments: 1");
+    }
+
+    #[test]
+    fn test_argument_names_are_not_expanded() {
+        // `x` in the body of `g` is the argument, not the `x` alias.
+        let a = aliases(&[("x", "bad"), ("g(x)", "x | y")]);
+        assert_eq!(expand(&a, "g(a)"), "(a | y)");
+        assert_eq!(expand(&a, "g(x)"), "(bad | y)");
+    }
+
+    #[test]
+    fn test_infinite_expansion() {
+        let a = aliases(&[("a", "b"), ("b", "c + a"), ("f($1)", "f($1)")]);
+        assert_eq!(
+            expand(&a, "a"),
+            "error: infinite expansion of revset alias \"a\" detected"
+        );
+        assert_eq!(
+            expand(&a, "f(x)"),
+            "error: infinite expansion of revset alias \"f\" detected"
+        );
+    }
+
+    #[test]
+    fn test_bad_aliases() {
+        let a = aliases(&[
+            ("$x", "a"),
+            ("$f()", "a"),
+            ("f(a::b)", "a"),
+            ("g(a, a)", "a"),
+            ("a + b", "a"),
+            ("h($1)", "$2"),
+            ("i", "("),
+        ]);
+        let errors: Vec<String> = a.errors().values().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "bad declaration of revset alias \"$f()\": invalid function '$f'",
+                "bad declaration of revset alias \"$x\": invalid symbol '$x'",
+                "bad declaration of revset alias \"a + b\": invalid format",
+                "bad declaration of revset alias \"f(a::b)\": invalid argument list",
+                "bad declaration of revset alias \"g(a, a)\": argument names collide with each other",
+                "bad definition of revset alias \"h\": invalid symbol '$2'",
+                "bad definition of revset alias \"i\": at 1: not a prefix: end",
+            ]
+        );
+        assert_eq!(
+            expand(&a, "i"),
+            "error: bad definition of revset alias \"i\": at 1: not a prefix: end"
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = BTreeMap::new();
+        config.insert("revsetalias.top".to_string(), "heads(all())".to_string());
+        config.insert("revsetalias.p($1)".to_string(), "parents($1)".to_string());
+        let a = Aliases::from_config(&config);
+        assert_eq!(expand(&a, "p(top)"), "parents(heads(all()))");
+    }
+}
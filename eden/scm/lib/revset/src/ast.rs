@@ -0,0 +1,93 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::fmt;
+
+/// Parsed revset expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    /// Unquoted name, like a hash, a bookmark, or `.`.
+    Symbol(String),
+
+    /// Quoted string.
+    String(String),
+
+    /// `name(args)`.
+    Func(String, Vec<Expr>),
+
+    /// `name=value`. Only meaningful as a function argument.
+    KeyValue(String, Box<Expr>),
+
+    /// `x and y`, `x & y`.
+    And(Box<Expr>, Box<Expr>),
+
+    /// `x or y`, `x | y`, `x + y`.
+    Or(Box<Expr>, Box<Expr>),
+
+    /// `x - y`.
+    Minus(Box<Expr>, Box<Expr>),
+
+    /// `not x`, `!x`.
+    Not(Box<Expr>),
+
+    /// `-x`.
+    Negate(Box<Expr>),
+
+    /// `x % y`, or `x%` if the second item is `None`.
+    Only(Box<Expr>, Option<Box<Expr>>),
+
+    /// `x::y`, `::y`, `x::`. `..` is the same as `::`.
+    DagRange(Option<Box<Expr>>, Option<Box<Expr>>),
+
+    /// `x:y`, `:y`, `x:`, `:`. Revision number ranges.
+    Range(Option<Box<Expr>>, Option<Box<Expr>>),
+
+    /// `x^`, or `x^n`.
+    Parent(Box<Expr>, Option<Box<Expr>>),
+
+    /// `x~n`.
+    Ancestor(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Get the name of a symbol or the content of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Expr::Symbol(s) | Expr::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Format as a revset string that parses back to the same expression.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let opt = |e: &Option<Box<Expr>>| match e {
+            Some(e) => e.to_string(),
+            None => String::new(),
+        };
+        match self {
+            Expr::Symbol(s) => write!(f, "{}", s),
+            Expr::String(s) => write!(f, "{:?}", s),
+            Expr::Func(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Expr::KeyValue(key, value) => write!(f, "{}={}", key, value),
+            Expr::And(x, y) => write!(f, "({} & {})", x, y),
+            Expr::Or(x, y) => write!(f, "({} | {})", x, y),
+            Expr::Minus(x, y) => write!(f, "({} - {})", x, y),
+            Expr::Not(x) => write!(f, "(not {})", x),
+            Expr::Negate(x) => write!(f, "(-{})", x),
+            Expr::Only(x, y) => write!(f, "({} % {})", x, opt(y)),
+            Expr::DagRange(x, y) => write!(f, "({}::{})", opt(x), opt(y)),
+            Expr::Range(x, y) => write!(f, "({}:{})", opt(x), opt(y)),
+            Expr::Parent(x, n) => write!(f, "({}^{})", x, opt(n)),
+            Expr::Ancestor(x, n) => write!(f, "({}~{})", x, n),
+        }
+    }
+}
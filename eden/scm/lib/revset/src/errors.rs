@@ -0,0 +1,35 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Syntax error at the given byte offset.
+    #[error("at {0}: {1}")]
+    ParseAt(usize, String),
+
+    /// The expression is syntactically valid but cannot be used. For example,
+    /// a function got the wrong number of arguments, or an alias is broken.
+    #[error("{0}")]
+    Parse(String),
+
+    /// A symbol cannot be resolved to commits.
+    #[error("unknown revision '{0}'")]
+    UnknownRevision(String),
+
+    /// A function is neither built-in nor registered.
+    #[error("unknown identifier: {0}")]
+    UnknownFunction(String),
+
+    #[error(transparent)]
+    Dag(#[from] dag::Error),
+
+    /// Errors from registered functions or symbol resolvers.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
@@ -0,0 +1,403 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Evaluate revset expressions on top of [`DagAlgorithm`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use dag::DagAlgorithm;
+use dag::Set;
+use dag::Vertex;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures::TryStreamExt;
+
+use crate::parser;
+use crate::Aliases;
+use crate::Error;
+use crate::Expr;
+use crate::Result;
+
+type Function = Arc<dyn Fn(&[Arg]) -> anyhow::Result<Set> + Send + Sync>;
+type SymbolResolver = Arc<dyn Fn(&str) -> anyhow::Result<Option<Set>> + Send + Sync>;
+
+/// An argument of a registered function.
+#[derive(Clone, Debug)]
+pub struct Arg {
+    /// The name of a symbol, the content of a quoted string, or the
+    /// normalized form of other expressions.
+    pub text: String,
+
+    /// The evaluated argument. `None` for `key=value` arguments, and for
+    /// symbols and strings that are not revisions, like patterns.
+    pub set: Option<Set>,
+}
+
+/// Evaluates revset expressions to [`Set`]s.
+///
+/// Operators and DAG functions like `ancestors` or `heads` are built-in.
+/// Other functions, like `bookmark()` or `draft()`, can be provided via
+/// [`Evaluator::register_function`].
+#[derive(Clone)]
+pub struct Evaluator {
+    dag: Arc<dyn DagAlgorithm + Send + Sync>,
+    aliases: Aliases,
+    functions: HashMap<String, Function>,
+    resolver: Option<SymbolResolver>,
+}
+
+impl Evaluator {
+    /// Create an evaluator on a DAG, typically from `dag_snapshot()`.
+    pub fn new(dag: Arc<dyn DagAlgorithm + Send + Sync>) -> Self {
+        Self {
+            dag,
+            aliases: Aliases::new(),
+            functions: HashMap::new(),
+            resolver: None,
+        }
+    }
+
+    /// Expand the given aliases in [`Evaluator::eval_str`].
+    pub fn with_aliases(mut self, aliases: Aliases) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Register a function. Its arguments are evaluated before it is
+    /// called.
+    ///
+    /// Registered functions take precedence over built-in ones.
+    pub fn register_function(
+        &mut self,
+        name: impl ToString,
+        func: impl Fn(&[Arg]) -> anyhow::Result<Set> + Send + Sync + 'static,
+    ) {
+        self.functions.insert(name.to_string(), Arc::new(func));
+    }
+
+    /// Set how symbols like `.` or bookmark names are resolved.
+    ///
+    /// Symbols that the resolver returns `None` for are looked up as vertex
+    /// names in the DAG.
+    pub fn set_symbol_resolver(
+        &mut self,
+        resolver: impl Fn(&str) -> anyhow::Result<Option<Set>> + Send + Sync + 'static,
+    ) {
+        self.resolver = Some(Arc::new(resolver));
+    }
+
+    /// Parse `program`, expand aliases, then evaluate it.
+    pub async fn eval_str(&self, program: &str) -> Result<Set> {
+        let expr = match &self.resolver {
+            Some(resolver) => {
+                let lookup = |name: &str| matches!(resolver(name), Ok(Some(_)));
+                parser::parse_with_lookup(program, &lookup)?
+            }
+            None => parser::parse(program)?,
+        };
+        let expr = self.aliases.expand(&expr)?;
+        self.eval(&expr).await
+    }
+
+    /// Evaluate an expression. Aliases are not expanded.
+    pub fn eval<'a>(&'a self, expr: &'a Expr) -> BoxFuture<'a, Result<Set>> {
+        async move {
+            let dag = &self.dag;
+            let set = match expr {
+                Expr::Symbol(name) | Expr::String(name) => self.resolve(name).await?,
+                Expr::Func(name, args) => self.eval_func(name, args).await?,
+                Expr::And(x, y) => self.eval(x).await?.intersection(&self.eval(y).await?),
+                Expr::Or(x, y) => self.eval(x).await?.union(&self.eval(y).await?),
+                Expr::Minus(x, y) => self.eval(x).await?.difference(&self.eval(y).await?),
+                Expr::Not(x) => dag.all().await?.difference(&self.eval(x).await?),
+                Expr::Only(x, None) => self.only(self.eval(x).await?).await?,
+                Expr::Only(x, Some(y)) => {
+                    dag.only(self.eval(x).await?, self.eval(y).await?).await?
+                }
+                Expr::DagRange(x, y) => match (x, y) {
+                    (Some(x), Some(y)) => {
+                        dag.range(self.eval(x).await?, self.eval(y).await?).await?
+                    }
+                    (None, Some(y)) => dag.ancestors(self.eval(y).await?).await?,
+                    (Some(x), None) => dag.descendants(self.eval(x).await?).await?,
+                    (None, None) => dag.all().await?,
+                },
+                Expr::Parent(x, n) => {
+                    let n = match n {
+                        Some(n) => parse_number(n, "^")?,
+                        None => 1,
+                    };
+                    self.parent(self.eval(x).await?, n).await?
+                }
+                Expr::Ancestor(x, n) => {
+                    let n = parse_number(n, "~")?;
+                    let mut result = Vec::new();
+                    let mut iter = self.eval(x).await?.iter().await?;
+                    while let Some(vertex) = iter.try_next().await? {
+                        if let Some(ancestor) = dag.first_ancestor_nth(vertex, n).await? {
+                            result.push(ancestor);
+                        }
+                    }
+                    Set::from_static_names(result)
+                }
+                Expr::KeyValue(..) | Expr::Negate(_) | Expr::Range(..) => {
+                    return Err(Error::Parse(format!("'{}' is not supported", expr)));
+                }
+            };
+            Ok(set)
+        }
+        .boxed()
+    }
+
+    async fn resolve(&self, name: &str) -> Result<Set> {
+        if let Some(resolver) = &self.resolver {
+            if let Some(set) = resolver(name)? {
+                return Ok(set);
+            }
+        }
+        let vertex = Vertex::copy_from(name.as_bytes());
+        if self.dag.all().await?.contains(&vertex).await? {
+            Ok(Set::from_static_names(vec![vertex]))
+        } else {
+            Err(Error::UnknownRevision(name.to_string()))
+        }
+    }
+
+    async fn eval_func(&self, name: &str, args: &[Expr]) -> Result<Set> {
+        if let Some(func) = self.functions.get(name) {
+            let mut evaluated = Vec::with_capacity(args.len());
+            for arg in args {
+                evaluated.push(self.eval_arg(arg).await?);
+            }
+            return Ok(func(&evaluated)?);
+        }
+        let dag = &self.dag;
+        let set = match (name, args) {
+            ("all", []) => dag.all().await?,
+            ("none", []) => Set::empty(),
+            ("ancestors", [x]) => dag.ancestors(self.eval(x).await?).await?,
+            ("descendants", [x]) => dag.descendants(self.eval(x).await?).await?,
+            ("parents", [x]) => dag.parents(self.eval(x).await?).await?,
+            ("children", [x]) => dag.children(self.eval(x).await?).await?,
+            ("heads", [x]) => dag.heads(self.eval(x).await?).await?,
+            ("roots", [x]) => dag.roots(self.eval(x).await?).await?,
+            ("merge", []) => dag.merges(dag.all().await?).await?,
+            ("only", [x]) => self.only(self.eval(x).await?).await?,
+            ("only", [x, y]) => dag.only(self.eval(x).await?, self.eval(y).await?).await?,
+            ("gca", _) | ("ancestor", _) => {
+                let mut set = Set::empty();
+                for arg in args {
+                    set = set.union(&self.eval(arg).await?);
+                }
+                match name {
+                    "gca" => dag.gca_all(set).await?,
+                    _ => Set::from_static_names(dag.gca_one(set).await?),
+                }
+            }
+            ("first", [x]) => self.eval(x).await?.take(1),
+            ("first", [x, n]) => self.eval(x).await?.take(parse_number(n, name)?),
+            ("p1", [x]) => self.parent(self.eval(x).await?, 1).await?,
+            ("p2", [x]) => self.parent(self.eval(x).await?, 2).await?,
+            (
+                "all" | "none" | "ancestors" | "descendants" | "parents" | "children" | "heads"
+                | "roots" | "merge" | "only" | "first" | "p1" | "p2",
+                _,
+            ) => {
+                return Err(Error::Parse(format!(
+                    "invalid number of arguments for {}: {}",
+                    name,
+                    args.len()
+                )));
+            }
+            _ => return Err(Error::UnknownFunction(name.to_string())),
+        };
+        Ok(set)
+    }
+
+    /// Evaluate an argument of a registered function.
+    async fn eval_arg(&self, arg: &Expr) -> Result<Arg> {
+        let set = match arg {
+            Expr::Symbol(name) | Expr::String(name) => match self.resolve(name).await {
+                Ok(set) => Some(set),
+                Err(Error::UnknownRevision(_)) => None,
+                Err(e) => return Err(e),
+            },
+            Expr::KeyValue(..) => None,
+            _ => Some(self.eval(arg).await?),
+        };
+        let text = match arg.as_str() {
+            Some(s) => s.to_string(),
+            None => arg.to_string(),
+        };
+        Ok(Arg { text, set })
+    }
+
+    /// `x%` or `only(x)`: ancestors of `x` that are not ancestors of other heads.
+    async fn only(&self, set: Set) -> Result<Set> {
+        let dag = &self.dag;
+        let others = dag
+            .heads(dag.all().await?)
+            .await?
+            .difference(&dag.descendants(set.clone()).await?);
+        Ok(dag.only(set, others).await?)
+    }
+
+    /// `x^n`: the n-th parent of each vertex in `x`. `x^0` is `x`.
+    async fn parent(&self, set: Set, n: u64) -> Result<Set> {
+        if n == 0 {
+            return Ok(set);
+        }
+        let mut result = Vec::new();
+        let mut iter = set.iter().await?;
+        while let Some(vertex) = iter.try_next().await? {
+            let parents = self.dag.parent_names(vertex).await?;
+            if let Some(parent) = parents.into_iter().nth(n as usize - 1) {
+                result.push(parent);
+            }
+        }
+        Ok(Set::from_static_names(result))
+    }
+}
+
+fn parse_number(expr: &Expr, context: &str) -> Result<u64> {
+    match expr.as_str().and_then(|s| s.parse().ok()) {
+        Some(n) => Ok(n),
+        None => Err(Error::Parse(format!("{} expects a number", context))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dag::nonblocking::non_blocking_result as r;
+    use dag::ops::ImportAscii;
+    use dag::MemDag;
+
+    use super::*;
+
+    /// Test DAG:
+    ///
+    /// ```plain
+    ///     A---B---C---D---G
+    ///          \         /
+    ///           E-------F---H
+    /// ```
+    fn evaluator() -> Evaluator {
+        let mut dag = MemDag::new();
+        dag.import_ascii(
+            r#"
+            A-B-C-D-G
+               \   /
+                E-F-H"#,
+        )
+        .unwrap();
+        Evaluator::new(dag.dag_snapshot().unwrap())
+    }
+
+    /// Evaluate `program` and render the result as sorted names, or the error.
+    fn eval(evaluator: &Evaluator, program: &str) -> String {
+        let set = match r(evaluator.eval_str(program)) {
+            Ok(set) => set,
+            Err(e) => return format!("error: {}", e),
+        };
+        let stream = r(set.iter()).unwrap();
+        let mut names: Vec<String> = r(stream.try_collect::<Vec<_>>())
+            .unwrap()
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_ref()).to_string())
+            .collect();
+        names.sort();
+        names.join(" ")
+    }
+
+    #[test]
+    fn test_operators() {
+        let e = evaluator();
+        assert_eq!(eval(&e, "B::D"), "B C D");
+        assert_eq!(eval(&e, "::C"), "A B C");
+        assert_eq!(eval(&e, "F::"), "F G H");
+        assert_eq!(eval(&e, "::G - ::C"), "D E F G");
+        assert_eq!(eval(&e, "(A | B | H) & ::D"), "A B");
+        assert_eq!(eval(&e, "not ::D"), "E F G H");
+        assert_eq!(eval(&e, "G % C"), "D E F G");
+        assert_eq!(eval(&e, "H%"), "H");
+        assert_eq!(eval(&e, "D%"), "C D");
+        assert_eq!(eval(&e, "G^ | G^2 | G^0"), "D F G");
+        assert_eq!(eval(&e, "D^2"), "");
+        assert_eq!(eval(&e, "D~2 | H~2"), "B E");
+        assert_eq!(eval(&e, "D^::F"), "");
+        assert_eq!(eval(&e, "A:B"), "error: '(A:B)' is not supported");
+        assert_eq!(eval(&e, "A~x"), "error: ~ expects a number");
+    }
+
+    #[test]
+    fn test_functions() {
+        let e = evaluator();
+        assert_eq!(eval(&e, "heads(all())"), "G H");
+        assert_eq!(eval(&e, "roots(D::)"), "D");
+        assert_eq!(eval(&e, "merge()"), "G");
+        assert_eq!(eval(&e, "parents(G)"), "D F");
+        assert_eq!(eval(&e, "children(B)"), "C E");
+        assert_eq!(eval(&e, "only(H, G)"), "H");
+        assert_eq!(eval(&e, "only(D)"), "C D");
+        assert_eq!(eval(&e, "ancestor(G, H)"), "F");
+        assert_eq!(eval(&e, "gca(C, F)"), "B");
+        assert_eq!(eval(&e, "p1(D) | p2(D)"), "C");
+        assert_eq!(eval(&e, "p1(G) | p2(G)"), "D F");
+        assert_eq!(eval(&e, "first(::A, 3)"), "A");
+        assert_eq!(eval(&e, "none()"), "");
+        assert_eq!(eval(&e, "X"), "error: unknown revision 'X'");
+        assert_eq!(eval(&e, "foo()"), "error: unknown identifier: foo");
+        assert_eq!(
+            eval(&e, "heads()"),
+            "error: invalid number of arguments for heads: 0"
+        );
+    }
+
+    #[test]
+    fn test_registered_functions_and_symbols() {
+        let mut e = evaluator();
+        e.register_function("bookmark", |args| match args {
+            [arg] if arg.text == "main" => Ok(Set::from_static_names(vec!["H".into()])),
+            _ => anyhow::bail!("bookmark expects a known bookmark name"),
+        });
+        e.register_function("evaluated", |args| match args {
+            [Arg { set: Some(set), .. }] => Ok(set.clone()),
+            _ => anyhow::bail!("evaluated expects a revision"),
+        });
+        e.set_symbol_resolver(|name| match name {
+            "." | "tip-1" => Ok(Some(Set::from_static_names(vec!["H".into()]))),
+            _ => Ok(None),
+        });
+        assert_eq!(eval(&e, "bookmark(main)^"), "F");
+        assert_eq!(
+            eval(&e, "bookmark(x)"),
+            "error: bookmark expects a known bookmark name"
+        );
+        assert_eq!(eval(&e, ".^ + A"), "A F");
+        assert_eq!(eval(&e, "tip-1"), "H");
+
+        // Arguments are evaluated before registered functions are called.
+        assert_eq!(eval(&e, "evaluated(B::C)"), "B C");
+        assert_eq!(eval(&e, "evaluated(.)"), "H");
+        assert_eq!(eval(&e, "evaluated(bookmark(main)^)"), "F");
+        assert_eq!(
+            eval(&e, "evaluated(x)"),
+            "error: evaluated expects a revision"
+        );
+        assert_eq!(eval(&e, "evaluated(X::)"), "error: unknown revision 'X'");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut aliases = Aliases::new();
+        aliases.insert("top", "heads(all())");
+        aliases.insert("between($1, $2)", "$1::$2 - $1");
+        let e = evaluator().with_aliases(aliases);
+        assert_eq!(eval(&e, "between(B, top)"), "C D E F G H");
+    }
+}
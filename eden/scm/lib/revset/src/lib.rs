@@ -0,0 +1,34 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! # revset
+//!
+//! Revset expression language.
+//!
+//! - [`parse`] turns a revset string into an [`Expr`]. The grammar matches
+//!   `revsetlang.py`.
+//! - [`Aliases`] expands aliases from the `[revsetalias]` config section.
+//! - [`Evaluator`] evaluates the DAG-only subset (`::`, `%`, `&`, `|`, `-`,
+//!   `ancestors`, `heads`, ...) on top of [`dag::DagAlgorithm`] and
+//!   [`dag::Set`]. Callers can register functions for revsets that need
+//!   non-DAG information, like bookmarks, phases or authors.
+
+mod alias;
+mod ast;
+mod errors;
+mod eval;
+mod parser;
+
+pub use alias::Aliases;
+pub use ast::Expr;
+pub use errors::Error;
+pub use eval::Arg;
+pub use eval::Evaluator;
+pub use parser::parse;
+pub use parser::parse_with_lookup;
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,651 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Tokenizer and parser for revset expressions.
+//!
+//! This is a port of `tokenize` in `revsetlang.py`, and the generic
+//! precedence parser in `parser.py`, driven by the same `elements` table.
+
+use std::fmt;
+
+use crate::Error;
+use crate::Expr;
+use crate::Result;
+
+/// Parse a revset expression.
+pub fn parse(program: &str) -> Result<Expr> {
+    parse_internal(program, None, false)
+}
+
+/// Parse a revset expression, using `lookup` to test whether a name exists.
+///
+/// This affects names with `-` or `:`. For example, `foo-bar` is parsed as
+/// `foo - bar` unless `lookup("foo-bar")` returns true.
+pub fn parse_with_lookup(program: &str, lookup: &dyn Fn(&str) -> bool) -> Result<Expr> {
+    parse_internal(program, Some(lookup), false)
+}
+
+/// Parse a revset alias declaration or definition. `$` is allowed in names.
+pub(crate) fn parse_alias(program: &str) -> Result<Expr> {
+    parse_internal(program, None, true)
+}
+
+fn parse_internal(
+    program: &str,
+    lookup: Option<&dyn Fn(&str) -> bool>,
+    allow_dollar: bool,
+) -> Result<Expr> {
+    if program.is_empty() {
+        return Err(Error::Parse("empty query".to_string()));
+    }
+    let tokens = tokenize(program, lookup, allow_dollar)?;
+    let mut parser = Parser { tokens, index: 0 };
+    let node = parser.parse(0)?;
+    let (token, pos) = parser.current();
+    if token != &Token::End {
+        return Err(Error::ParseAt(*pos, "invalid token".to_string()));
+    }
+    node.into_expr(0)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// Operators and keywords, like `::` or `and`.
+    Op(&'static str),
+    Symbol(String),
+    String(String),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Token::Op(op) => op,
+            Token::Symbol(_) => "symbol",
+            Token::String(_) => "string",
+            Token::End => "end",
+        };
+        f.write_str(name)
+    }
+}
+
+const KEYWORDS: [&str; 3] = ["and", "or", "not"];
+
+fn simple_op(c: char) -> Option<&'static str> {
+    let op = match c {
+        '(' => "(",
+        ')' => ")",
+        '[' => "[",
+        ']' => "]",
+        '#' => "#",
+        ':' => ":",
+        '=' => "=",
+        ',' => ",",
+        '-' => "-",
+        '|' => "|",
+        '&' => "&",
+        '+' => "+",
+        '!' => "!",
+        '~' => "~",
+        '^' => "^",
+        '%' => "%",
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn is_symbol_initial(c: char, allow_dollar: bool) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(c, '.' | '_' | '@')
+        || c as u32 >= 128
+        || (allow_dollar && c == '$')
+}
+
+fn is_symbol_letter(c: char, allow_dollar: bool) -> bool {
+    is_symbol_initial(c, allow_dollar) || matches!(c, '-' | '/')
+}
+
+fn keyword(symbol: &str) -> Option<&'static str> {
+    KEYWORDS.iter().find(|k| **k == symbol).copied()
+}
+
+/// Split a revset string into tokens with their byte offsets.
+fn tokenize(
+    program: &str,
+    lookup: Option<&dyn Fn(&str) -> bool>,
+    allow_dollar: bool,
+) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+
+    if let Some(lookup) = lookup {
+        // Attempt to parse old-style ranges first to deal with things like
+        // old-tag which contain query metacharacters.
+        let parts: Vec<&str> = program.splitn(2, ':').collect();
+        if parts.iter().all(|p| p.is_empty() || lookup(p)) {
+            if !parts[0].is_empty() {
+                tokens.push((Token::Symbol(parts[0].to_string()), 0));
+            }
+            if parts.len() > 1 {
+                let pos = parts[0].len();
+                tokens.push((Token::Op(":"), pos));
+                if !parts[1].is_empty() {
+                    tokens.push((Token::Symbol(parts[1].to_string()), pos + 1));
+                }
+            }
+            tokens.push((Token::End, program.len()));
+            return Ok(tokens);
+        }
+    }
+
+    let chars: Vec<(usize, char)> = program.char_indices().collect();
+    let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let pos_at = |i: usize| chars.get(i).map_or(program.len(), |(p, _)| *p);
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        let next = char_at(i + 1);
+        if c.is_whitespace() {
+            // Skip inter-token whitespace.
+        } else if c == ':' && next == Some(':') {
+            tokens.push((Token::Op("::"), pos));
+            i += 1;
+        } else if c == '.' && next == Some('.') {
+            tokens.push((Token::Op(".."), pos));
+            i += 1;
+        } else if c == '#' && next == Some('#') {
+            tokens.push((Token::Op("##"), pos));
+            i += 1;
+        } else if let Some(op) = simple_op(c) {
+            tokens.push((Token::Op(op), pos));
+        } else if c == '"' || c == '\'' || (c == 'r' && matches!(next, Some('"' | '\''))) {
+            let raw = c == 'r';
+            if raw {
+                i += 1;
+            }
+            let quote = chars[i].1;
+            i += 1;
+            let start = i;
+            loop {
+                match char_at(i) {
+                    None => {
+                        return Err(Error::ParseAt(
+                            pos_at(start),
+                            "unterminated string".to_string(),
+                        ));
+                    }
+                    Some('\\') => i += 2,
+                    Some(d) if d == quote => {
+                        let s = &program[pos_at(start)..pos_at(i)];
+                        let s = if raw { s.to_string() } else { unescape(s) };
+                        tokens.push((Token::String(s), pos_at(start)));
+                        break;
+                    }
+                    Some(_) => i += 1,
+                }
+            }
+        } else if is_symbol_initial(c, allow_dollar) {
+            i += 1;
+            while let Some(d) = char_at(i) {
+                if !is_symbol_letter(d, allow_dollar) {
+                    break;
+                }
+                if d == '.' && char_at(i - 1) == Some('.') {
+                    // Special case for "..".
+                    i -= 1;
+                    break;
+                }
+                i += 1;
+            }
+            let symbol = &program[pos..pos_at(i)];
+            if let Some(keyword) = keyword(symbol) {
+                tokens.push((Token::Op(keyword), pos));
+            } else if symbol.contains('-') && !matches!(lookup, Some(l) if l(symbol)) {
+                // "foo-bar" looks like an expression, not a name.
+                let parts: Vec<&str> = symbol.split('-').collect();
+                let mut part_pos = pos;
+                for (j, part) in parts.iter().enumerate() {
+                    if !part.is_empty() {
+                        tokens.push((Token::Symbol(part.to_string()), part_pos));
+                    }
+                    part_pos += part.len();
+                    if j + 1 < parts.len() {
+                        tokens.push((Token::Op("-"), part_pos));
+                        part_pos += 1;
+                    }
+                }
+            } else {
+                tokens.push((Token::Symbol(symbol.to_string()), pos));
+            }
+            i -= 1;
+        } else {
+            return Err(Error::ParseAt(
+                pos,
+                format!("syntax error in revset '{}'", program),
+            ));
+        }
+        i += 1;
+    }
+    tokens.push((Token::End, program.len()));
+    Ok(tokens)
+}
+
+/// Decode backslash escapes in a quoted string.
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(c @ ('\\' | '\'' | '"')) => result.push(c),
+            Some('x') => {
+                let hex: String = chars.clone().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(v) if hex.len() == 2 => {
+                        result.push(v as char);
+                        chars.nth(1);
+                    }
+                    _ => result.push_str("\\x"),
+                }
+            }
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// `(name, binding strength, closing token)` of a prefix or infix rule.
+type Rule = (&'static str, u8, Option<&'static str>);
+
+/// An entry in the `elements` table of `revsetlang.py`.
+struct Element {
+    strength: u8,
+    primary: Option<&'static str>,
+    prefix: Option<Rule>,
+    infix: Option<Rule>,
+    suffix: Option<&'static str>,
+}
+
+fn element(token: &Token) -> Element {
+    let (strength, primary, prefix, infix, suffix): (u8, _, Option<Rule>, Option<Rule>, _) =
+        match token {
+            Token::Symbol(_) => (0, Some("symbol"), None, None, None),
+            Token::String(_) => (0, Some("string"), None, None, None),
+            Token::End => (0, None, None, None, None),
+            Token::Op(op) => match *op {
+                "(" => (
+                    21,
+                    None,
+                    Some(("group", 1, Some(")"))),
+                    Some(("func", 1, Some(")"))),
+                    None,
+                ),
+                // Subscripts, relations and concatenation are not supported.
+                "[" | "#" => (21, None, None, None, None),
+                "##" => (20, None, None, None, None),
+                "~" => (18, None, None, Some(("ancestor", 18, None)), None),
+                "^" => (
+                    18,
+                    None,
+                    None,
+                    Some(("parent", 18, None)),
+                    Some("parentpost"),
+                ),
+                "-" => (
+                    5,
+                    None,
+                    Some(("negate", 19, None)),
+                    Some(("minus", 5, None)),
+                    None,
+                ),
+                "::" | ".." => (
+                    17,
+                    None,
+                    Some(("dagrangepre", 17, None)),
+                    Some(("dagrange", 17, None)),
+                    Some("dagrangepost"),
+                ),
+                ":" => (
+                    15,
+                    Some("rangeall"),
+                    Some(("rangepre", 15, None)),
+                    Some(("range", 15, None)),
+                    Some("rangepost"),
+                ),
+                "not" | "!" => (10, None, Some(("not", 10, None)), None, None),
+                "and" | "&" => (5, None, None, Some(("and", 5, None)), None),
+                "%" => (5, None, None, Some(("only", 5, None)), Some("onlypost")),
+                "or" | "|" | "+" => (4, None, None, Some(("or", 4, None)), None),
+                "=" => (3, None, None, Some(("keyvalue", 3, None)), None),
+                "," => (2, None, None, Some(("list", 2, None)), None),
+                _ => (0, None, None, None, None),
+            },
+        };
+    Element {
+        strength,
+        primary,
+        prefix,
+        infix,
+        suffix,
+    }
+}
+
+/// Intermediate parse result. Lists are only valid as function arguments.
+enum Node {
+    Expr(Expr),
+    List(Vec<Expr>),
+}
+
+impl Node {
+    fn into_expr(self, pos: usize) -> Result<Expr> {
+        match self {
+            Node::Expr(e) => Ok(e),
+            Node::List(_) => Err(Error::ParseAt(
+                pos,
+                "can't use a list in this context".to_string(),
+            )),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+}
+
+impl Parser {
+    fn current(&self) -> &(Token, usize) {
+        // The last token is always `End`.
+        &self.tokens[self.index.min(self.tokens.len() - 1)]
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let current = self.current().clone();
+        self.index += 1;
+        current
+    }
+
+    /// Test if the current token can start a new term.
+    fn has_new_term(&self) -> bool {
+        let element = element(&self.current().0);
+        element.primary.is_some() || element.prefix.is_some()
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<()> {
+        let (token, pos) = self.advance();
+        if token != Token::Op(op) {
+            return Err(Error::ParseAt(pos, format!("unexpected token: {}", token)));
+        }
+        Ok(())
+    }
+
+    fn parse(&mut self, bind: u8) -> Result<Node> {
+        let (token, pos) = self.advance();
+        let elem = element(&token);
+        let mut node = match (elem.primary, elem.prefix) {
+            (Some(_), prefix) if !(prefix.is_some() && self.has_new_term()) => {
+                Node::Expr(primary(token))
+            }
+            (_, Some((name, bind, end))) => {
+                let operand = self.parse_operand(bind, end)?;
+                build_prefix(name, operand, pos)?
+            }
+            _ => return Err(Error::ParseAt(pos, format!("not a prefix: {}", token))),
+        };
+        while bind < element(&self.current().0).strength {
+            let (token, pos) = self.advance();
+            let elem = element(&token);
+            node = match (elem.suffix, elem.infix) {
+                (Some(name), infix) if !(infix.is_some() && self.has_new_term()) => {
+                    build_suffix(name, node, pos)?
+                }
+                (_, Some((name, bind, end))) => {
+                    let operand = self.parse_operand(bind, end)?;
+                    build_infix(name, node, operand, pos)?
+                }
+                _ => return Err(Error::ParseAt(pos, format!("not an infix: {}", token))),
+            };
+        }
+        Ok(node)
+    }
+
+    /// Parse the right-hand side operand until `end` or binding strength met.
+    fn parse_operand(&mut self, bind: u8, end: Option<&'static str>) -> Result<Option<Node>> {
+        let node = match end {
+            Some(end) if self.current().0 == Token::Op(end) => None,
+            _ => Some(self.parse(bind)?),
+        };
+        if let Some(end) = end {
+            self.expect(end)?;
+        }
+        Ok(node)
+    }
+}
+
+fn primary(token: Token) -> Expr {
+    match token {
+        Token::Symbol(s) => Expr::Symbol(s),
+        Token::String(s) => Expr::String(s),
+        // ":" is the only operator with a primary rule.
+        _ => Expr::Range(None, None),
+    }
+}
+
+fn build_prefix(name: &str, operand: Option<Node>, pos: usize) -> Result<Node> {
+    let operand = match operand {
+        Some(node) => Box::new(node.into_expr(pos)?),
+        None => return Err(Error::ParseAt(pos, "missing argument".to_string())),
+    };
+    let expr = match name {
+        "group" => *operand,
+        "negate" => Expr::Negate(operand),
+        "dagrangepre" => Expr::DagRange(None, Some(operand)),
+        "rangepre" => Expr::Range(None, Some(operand)),
+        "not" => Expr::Not(operand),
+        _ => unreachable!("unknown prefix rule {}", name),
+    };
+    Ok(Node::Expr(expr))
+}
+
+fn build_suffix(name: &str, node: Node, pos: usize) -> Result<Node> {
+    let operand = Box::new(node.into_expr(pos)?);
+    let expr = match name {
+        "parentpost" => Expr::Parent(operand, None),
+        "dagrangepost" => Expr::DagRange(Some(operand), None),
+        "rangepost" => Expr::Range(Some(operand), None),
+        "onlypost" => Expr::Only(operand, None),
+        _ => unreachable!("unknown suffix rule {}", name),
+    };
+    Ok(Node::Expr(expr))
+}
+
+fn build_infix(name: &str, lhs: Node, rhs: Option<Node>, pos: usize) -> Result<Node> {
+    match name {
+        "func" => {
+            let name = match lhs {
+                Node::Expr(Expr::Symbol(name)) => name,
+                _ => return Err(Error::ParseAt(pos, "not a symbol".to_string())),
+            };
+            let args = match rhs {
+                None => Vec::new(),
+                Some(Node::List(args)) => args,
+                Some(Node::Expr(arg)) => vec![arg],
+            };
+            return Ok(Node::Expr(Expr::Func(name, args)));
+        }
+        "list" => {
+            // `rhs` binds tighter than "," so it is never a list.
+            let rhs = match rhs {
+                Some(node) => node.into_expr(pos)?,
+                None => unreachable!("list has no closing token"),
+            };
+            let items = match lhs {
+                Node::List(mut items) => {
+                    items.push(rhs);
+                    items
+                }
+                Node::Expr(lhs) => vec![lhs, rhs],
+            };
+            return Ok(Node::List(items));
+        }
+        _ => {}
+    }
+
+    let rhs = match rhs {
+        Some(node) => node.into_expr(pos)?,
+        None => unreachable!("only 'func' has a closing token"),
+    };
+    let expr = match name {
+        "keyvalue" => match lhs {
+            Node::Expr(Expr::Symbol(key)) => Expr::KeyValue(key, Box::new(rhs)),
+            _ => return Err(Error::ParseAt(pos, "not a symbol".to_string())),
+        },
+        _ => {
+            let lhs = Box::new(lhs.into_expr(pos)?);
+            let rhs = Box::new(rhs);
+            match name {
+                "ancestor" => Expr::Ancestor(lhs, rhs),
+                "parent" => {
+                    // `x^::y` means `(x^)::y`, not `x^(::y)`. Same for `:`.
+                    let post = Some(Box::new(Expr::Parent(lhs.clone(), None)));
+                    match *rhs {
+                        Expr::DagRange(None, y) => Expr::DagRange(post, y),
+                        Expr::Range(None, y) => Expr::Range(post, y),
+                        _ => Expr::Parent(lhs, Some(rhs)),
+                    }
+                }
+                "minus" => Expr::Minus(lhs, rhs),
+                "dagrange" => Expr::DagRange(Some(lhs), Some(rhs)),
+                "range" => Expr::Range(Some(lhs), Some(rhs)),
+                "and" => Expr::And(lhs, rhs),
+                "only" => Expr::Only(lhs, Some(rhs)),
+                "or" => Expr::Or(lhs, rhs),
+                _ => unreachable!("unknown infix rule {}", name),
+            }
+        }
+    };
+    Ok(Node::Expr(expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse and format the expression, or the error.
+    fn p(program: &str) -> String {
+        match parse(program) {
+            Ok(expr) => expr.to_string(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let t = |program: &str| -> String {
+            let tokens = tokenize(program, None, false).unwrap();
+            let tokens: Vec<String> = tokens
+                .into_iter()
+                .map(|(token, pos)| match token {
+                    Token::Symbol(s) | Token::String(s) => format!("{}@{}", s, pos),
+                    token => format!("{}@{}", token, pos),
+                })
+                .collect();
+            tokens.join(" ")
+        };
+        assert_eq!(t("@::"), "@@0 ::@1 end@3");
+        assert_eq!(t("a..b"), "a@0 ..@1 b@3 end@4");
+        assert_eq!(t("a-b-"), "a@0 -@1 b@2 -@3 end@4");
+        assert_eq!(t("x and not 'y z'"), "x@0 and@2 not@6 y z@11 end@15");
+        assert_eq!(t(r#"r'a\n' "a\n""#), "a\\n@2 a\n@8 end@12");
+        assert_eq!(t("releases/1.0"), "releases/1.0@0 end@12");
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        assert_eq!(p("a::b"), "(a::b)");
+        assert_eq!(p("::b"), "(::b)");
+        assert_eq!(p("a::"), "(a::)");
+        assert_eq!(p("a..b"), "(a::b)");
+        assert_eq!(p("a % b"), "(a % b)");
+        assert_eq!(p("a%"), "(a % )");
+        assert_eq!(p("a^"), "(a^)");
+        assert_eq!(p("a^2"), "(a^2)");
+        assert_eq!(p("a^::b"), "((a^)::b)");
+        assert_eq!(p("a^:"), "((a^):)");
+        assert_eq!(p("a~3"), "(a~3)");
+        assert_eq!(p("a:b"), "(a:b)");
+        assert_eq!(p(":"), "(:)");
+        assert_eq!(p("-a"), "(-a)");
+        assert_eq!(p("!a"), "(not a)");
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(p("a | b & c"), "(a | (b & c))");
+        assert_eq!(p("a & b | c"), "((a & b) | c)");
+        assert_eq!(p("a - b - c"), "((a - b) - c)");
+        assert_eq!(p("a::b & c"), "((a::b) & c)");
+        assert_eq!(p("not a & b"), "((not a) & b)");
+        assert_eq!(p("(a | b) & c"), "((a | b) & c)");
+        assert_eq!(p("a^::b~1"), "((a^)::(b~1))");
+        assert_eq!(p("a + b or c"), "((a | b) | c)");
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        assert_eq!(p("all()"), "all()");
+        assert_eq!(p("only(a, b::c)"), "only(a, (b::c))");
+        assert_eq!(p("f(x=1, 'y')"), "f(x=1, \"y\")");
+        assert_eq!(p("ancestors(heads(a | b))"), "ancestors(heads((a | b)))");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(p(""), "error: empty query");
+        assert_eq!(p("a b"), "error: at 2: invalid token");
+        assert_eq!(p("f(a"), "error: at 3: unexpected token: end");
+        assert_eq!(p("'abc"), "error: at 1: unterminated string");
+        assert_eq!(p("a & "), "error: at 4: not a prefix: end");
+        assert_eq!(p("a, b"), "error: at 0: can't use a list in this context");
+        assert_eq!(p("()"), "error: at 0: missing argument");
+        assert_eq!(p("a$"), "error: at 1: syntax error in revset 'a$'");
+        assert_eq!(p("'a'(b)"), "error: at 3: not a symbol");
+    }
+
+    #[test]
+    fn test_parse_with_lookup() {
+        let lookup = |name: &str| ["foo-bar", "a-1", "a-2"].contains(&name);
+        let p = |program: &str| parse_with_lookup(program, &lookup).unwrap().to_string();
+        assert_eq!(p("foo-bar"), "foo-bar");
+        assert_eq!(p("foo-bar::x-y"), "((foo-bar::x) - y)");
+        assert_eq!(p("a-1:a-2"), "(a-1:a-2)");
+        assert_eq!(p("a-1:"), "(a-1:)");
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for program in [
+            "a::b & not c",
+            "(a % b) | c^ | d~2",
+            "f('x\\ny', k=v, ::a)",
+            "a% - :b",
+        ] {
+            let expr = parse(program).unwrap();
+            assert_eq!(parse(&expr.to_string()).unwrap(), expr);
+        }
+    }
+}
@@ -1736,6 +1736,187 @@ impl<S: Eq + Hash> TreeSpan<S> {
     }
 }
 
+// -------- Folded stacks and comparison --------
+
+/// Aggregated durations of spans sharing the same call path.
+#[derive(Default, Clone, Copy)]
+struct PathStat {
+    /// Sum of span durations, including children.
+    total: u64,
+    /// Sum of span durations, excluding children.
+    self_time: u64,
+    /// Number of spans.
+    count: usize,
+}
+
+/// Duration change of a call path between two [`TracingData`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpanDelta {
+    /// Span names from the outermost to the innermost, separated by `;`.
+    pub path: String,
+    /// Total duration in the old trace, in microseconds.
+    pub old_micros: u64,
+    /// Total duration in the new trace, in microseconds.
+    pub new_micros: u64,
+    /// Number of spans in the old trace.
+    pub old_count: usize,
+    /// Number of spans in the new trace.
+    pub new_count: usize,
+}
+
+impl SpanDelta {
+    /// Duration change in microseconds. Positive means slower.
+    pub fn delta_micros(&self) -> i64 {
+        self.new_micros as i64 - self.old_micros as i64
+    }
+}
+
+impl TracingData {
+    /// Generate "folded stacks" output that can be consumed by flamegraph
+    /// tools. Each line is `name;name;...;name self_time_micros`.
+    ///
+    /// Events and incomplete spans are skipped.
+    pub fn folded_stacks(&self) -> String {
+        let mut out = String::new();
+        for (path, stat) in self.path_stats() {
+            if stat.self_time > 0 {
+                out += &format!("{} {}\n", path, stat.self_time);
+            }
+        }
+        out
+    }
+
+    /// Compare spans with an older trace. Spans are aligned by their call
+    /// paths (span names from the root). Durations of spans with the same
+    /// path are summed up.
+    ///
+    /// Rows are sorted by the absolute duration change, largest first.
+    pub fn compare(&self, old: &TracingData) -> Vec<SpanDelta> {
+        let old_stats = old.path_stats();
+        let new_stats = self.path_stats();
+        let mut deltas: Vec<SpanDelta> = old_stats
+            .keys()
+            .chain(new_stats.keys())
+            .collect::<IndexSet<_>>()
+            .into_iter()
+            .map(|path| {
+                let old = old_stats.get(path).copied().unwrap_or_default();
+                let new = new_stats.get(path).copied().unwrap_or_default();
+                SpanDelta {
+                    path: path.clone(),
+                    old_micros: old.total,
+                    new_micros: new.total,
+                    old_count: old.count,
+                    new_count: new.count,
+                }
+            })
+            .collect();
+        deltas.sort_by_key(|d| std::cmp::Reverse(d.delta_micros().unsigned_abs()));
+        deltas
+    }
+
+    /// Render [`TracingData::compare`] result as an ASCII table.
+    ///
+    /// Rows with an absolute duration change less than `min_delta_micros`
+    /// are hidden.
+    pub fn compare_ascii(&self, old: &TracingData, min_delta_micros: u64) -> String {
+        let mut rows = Rows {
+            rows: vec![Row {
+                columns: ["Old.ms", "New.ms", "Delta.ms", "Calls", "Path"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            }],
+            column_alignments: vec![
+                Alignment::Right,
+                Alignment::Right,
+                Alignment::Right,
+                Alignment::Right,
+                Alignment::Left,
+            ],
+            column_min_widths: vec![0; 5],
+            column_max_widths: Vec::new(),
+        };
+        for delta in self.compare(old) {
+            if delta.delta_micros().unsigned_abs() < min_delta_micros {
+                continue;
+            }
+            let calls = if delta.old_count == delta.new_count {
+                delta.new_count.to_string()
+            } else {
+                format!("{}->{}", delta.old_count, delta.new_count)
+            };
+            rows.rows.push(Row {
+                columns: vec![
+                    (delta.old_micros / 1000).to_string(),
+                    (delta.new_micros / 1000).to_string(),
+                    format!("{:+}", delta.delta_micros() / 1000),
+                    calls,
+                    delta.path,
+                ],
+            });
+        }
+        rows.to_string()
+    }
+
+    /// Aggregate complete spans by their call paths across all threads.
+    fn path_stats(&self) -> IndexMap<String, PathStat> {
+        let mut stats = IndexMap::new();
+        for eventus_list in self.eventus_group_by_pid_tid().values() {
+            let tree_spans = self.build_tree_spans(eventus_list);
+            let mut stack: Vec<(RawTreeSpanId, String)> = vec![(0, String::new())];
+            while let Some((id, path)) = stack.pop() {
+                let span = &tree_spans[id];
+                for &child_id in span.children.iter().rev() {
+                    let child = &tree_spans[child_id];
+                    if child.is_event {
+                        continue;
+                    }
+                    let name = self.span_frame_name(child);
+                    let child_path = if path.is_empty() {
+                        name
+                    } else {
+                        format!("{};{}", path, name)
+                    };
+                    stack.push((child_id, child_path));
+                }
+                if span.espan_id.is_none() || span.is_incomplete() {
+                    continue;
+                }
+                let children_time: u64 = span
+                    .children
+                    .iter()
+                    .map(|&i| &tree_spans[i])
+                    .filter(|c| !c.is_event && !c.is_incomplete())
+                    .map(|c| c.duration)
+                    .sum();
+                let stat: &mut PathStat = stats.entry(path).or_default();
+                stat.total += span.duration;
+                stat.self_time += span.duration.saturating_sub(children_time);
+                stat.count += span.call_count;
+            }
+        }
+        stats
+    }
+
+    /// Name of a span used in folded stacks. `;` and whitespace are
+    /// replaced since they are separators in the folded format.
+    fn span_frame_name(&self, span: &RawTreeSpan) -> String {
+        let name = span
+            .espan_id
+            .and_then(|id| self.get_espan(id))
+            .and_then(|espan| {
+                espan
+                    .meta
+                    .iter()
+                    .find(|(k, _)| self.strings.get(**k) == "name")
+                    .map(|(_, v)| self.strings.get(*v))
+            })
+            .unwrap_or("?");
+        name.replace(';', ":").replace(char::is_whitespace, "_")
+    }
+}
+
 // -------- Tests --------
 
 #[cfg(test)]
@@ -2106,6 +2287,71 @@ Start Dur.ms | Name               Source
         );
     }
 
+    #[test]
+    fn test_folded_stacks() {
+        let mut data = TracingData::new_for_test();
+        let span_id1 = data.add_espan(&meta("foo", "a.py", "10"), None);
+        let span_id2 = data.add_espan(&meta("bar baz;", "a.py", "20"), None);
+        data.add_action(span_id1, Action::EnterSpan);
+        data.add_action(span_id2, Action::EnterSpan);
+        data.add_action(span_id2, Action::ExitSpan);
+        data.add_action(span_id2, Action::EnterSpan);
+        data.add_action(span_id2, Action::ExitSpan);
+        data.add_action(span_id1, Action::ExitSpan);
+        data.add_action(span_id2, Action::EnterSpan);
+
+        // The last "bar" is incomplete and skipped.
+        assert_eq!(data.folded_stacks(), "foo 6000\nfoo;bar_baz: 4000\n");
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut old = TracingData::new_for_test();
+        let span_id1 = old.add_espan(&meta("foo", "a.py", "10"), None);
+        let span_id2 = old.add_espan(&meta("bar", "a.py", "20"), None);
+        old.add_action(span_id1, Action::EnterSpan);
+        old.add_action(span_id2, Action::EnterSpan);
+        old.add_action(span_id2, Action::ExitSpan);
+        old.add_action(span_id1, Action::ExitSpan);
+
+        let mut new = TracingData::new_for_test();
+        let span_id1 = new.add_espan(&meta("foo", "a.py", "10"), None);
+        let span_id2 = new.add_espan(&meta("bar", "a.py", "20"), None);
+        let span_id3 = new.add_espan(&meta("baz", "a.py", "30"), None);
+        new.add_action(span_id1, Action::EnterSpan);
+        new.add_action(span_id2, Action::EnterSpan);
+        new.add_action(span_id2, Action::ExitSpan);
+        new.add_action(span_id2, Action::EnterSpan);
+        new.add_action(span_id3, Action::EnterSpan);
+        new.add_action(span_id3, Action::ExitSpan);
+        new.add_action(span_id2, Action::ExitSpan);
+        new.add_action(span_id1, Action::ExitSpan);
+
+        let deltas = new.compare(&old);
+        assert_eq!(
+            deltas
+                .iter()
+                .map(|d| format!("{} {}", d.path, d.delta_micros()))
+                .collect::<Vec<_>>(),
+            ["foo 8000", "foo;bar 6000", "foo;bar;baz 2000"]
+        );
+
+        assert_eq!(
+            new.compare_ascii(&old, 0),
+            r#"Old.ms New.ms Delta.ms Calls Path
+     6     14       +8     1 foo
+     2      8       +6  1->2 foo;bar
+     0      2       +2  0->1 foo;bar;baz
+"#
+        );
+        assert_eq!(
+            new.compare_ascii(&old, 7000),
+            r#"Old.ms New.ms Delta.ms Calls Path
+     6     14       +8     1 foo
+"#
+        );
+    }
+
     #[test]
     fn test_column_widths() {
         let mut data = TracingData::new_for_test();
@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::fs;
+use std::path::Path;
+
+use minibench::bench;
+use minibench::elapsed;
+use pathmatcher::AlwaysMatcher;
+use pathmatcher::TreeMatcher;
+use tempfile::tempdir;
+use workingcopy::walker::ParallelWalker;
+use workingcopy::walker::Walker;
+
+const TOP_DIRS: usize = 100;
+const SUB_DIRS: usize = 10;
+const FILES: usize = 20;
+const THREADS: usize = 8;
+
+/// Create TOP_DIRS * SUB_DIRS * FILES files.
+fn create_working_copy(root: &Path) {
+    for i in 0..TOP_DIRS {
+        for j in 0..SUB_DIRS {
+            let dir = root.join(format!("d{}/s{}", i, j));
+            fs::create_dir_all(&dir).unwrap();
+            for k in 0..FILES {
+                fs::write(dir.join(format!("f{}", k)), b"").unwrap();
+            }
+        }
+    }
+}
+
+fn main() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    create_working_copy(&root);
+
+    bench("walker (everything)", || {
+        elapsed(|| {
+            let walker = Walker::new(root.clone(), AlwaysMatcher::new(), false).unwrap();
+            assert_eq!(walker.count(), TOP_DIRS * SUB_DIRS * FILES);
+        })
+    });
+
+    bench("parallel walker (everything)", || {
+        elapsed(|| {
+            let walker =
+                ParallelWalker::new(root.clone(), AlwaysMatcher::new(), false, THREADS).unwrap();
+            assert_eq!(walker.count(), TOP_DIRS * SUB_DIRS * FILES);
+        })
+    });
+
+    // Only a tenth of the top-level directories are visited.
+    let rules = ["d1*/**", "!d1*/s1/**"];
+
+    bench("walker (pruned)", || {
+        elapsed(|| {
+            let matcher = TreeMatcher::from_rules(rules.iter()).unwrap();
+            let walker = Walker::new(root.clone(), matcher, false).unwrap();
+            walker.for_each(|entry| {
+                entry.unwrap();
+            });
+        })
+    });
+
+    bench("parallel walker (pruned)", || {
+        elapsed(|| {
+            let matcher = TreeMatcher::from_rules(rules.iter()).unwrap();
+            let walker = ParallelWalker::new(root.clone(), matcher, false, THREADS).unwrap();
+            walker.for_each(|entry| {
+                entry.unwrap();
+            });
+        })
+    });
+}
@@ -15,6 +15,7 @@ use anyhow::Error;
 use anyhow::Result;
 use parking_lot::Mutex;
 use pathmatcher::Matcher;
+use treestate::filestate::FileStateV2;
 use treestate::filestate::StateFlags;
 use treestate::tree::VisitorResult;
 use treestate::treestate::TreeState;
@@ -24,9 +25,16 @@ use vfs::is_executable;
 use vfs::is_symlink;
 use vfs::VFS;
 
+use crate::walker::ParallelWalker;
 use crate::walker::WalkEntry;
 use crate::walker::WalkError;
 use crate::walker::Walker;
+use crate::watcher::FileSystemWatcher;
+use crate::watcher::WatcherChanges;
+
+/// Treestate metadata key of the file system watcher clock. This is shared
+/// with the Python fsmonitor extension.
+const CLOCK_KEY: &str = "clock";
 
 /// Represents a file modification time in Mercurial, in seconds since the unix epoch.
 #[derive(PartialEq)]
@@ -64,42 +72,114 @@ impl TryFrom<i32> for HgModifiedTime {
 pub struct PhysicalFileSystem {
     // TODO: Make this an Arc<Mutex<VFS>> so we can persist the vfs pathauditor cache
     vfs: VFS,
+    watcher: Option<Arc<dyn FileSystemWatcher>>,
+    walker_threads: usize,
 }
 
 impl PhysicalFileSystem {
     pub fn new(root: PathBuf) -> Result<Self> {
         Ok(PhysicalFileSystem {
             vfs: VFS::new(root)?,
+            watcher: None,
+            walker_threads: 0,
         })
     }
 
-    pub fn pending_changes<M: Matcher + Clone>(
+    /// Use a file system watcher to only check files that may have changed
+    /// since the last `pending_changes`, instead of walking the working copy.
+    ///
+    /// The watcher clock is kept in the treestate metadata. Files that are
+    /// found to be changed are marked `NEED_CHECK` in the treestate, so they
+    /// are checked again even if the watcher doesn't report them next time.
+    /// If there is no clock, or the watcher can't tell what changed since
+    /// it, the working copy is walked.
+    pub fn with_watcher(mut self, watcher: Arc<dyn FileSystemWatcher>) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    /// Walk the working copy on `num_threads` threads with a
+    /// [`ParallelWalker`]. With fewer than 2 threads, the serial [`Walker`]
+    /// is used.
+    ///
+    /// The matcher is called from the walker threads, so it must not rely
+    /// on state of the calling thread, like a held Python GIL.
+    pub fn with_walker_threads(mut self, num_threads: usize) -> Self {
+        self.walker_threads = num_threads;
+        self
+    }
+
+    pub fn pending_changes<M: Matcher + Clone + Send + Sync + 'static>(
         &self,
         treestate: Arc<Mutex<TreeState>>,
         matcher: M,
         include_directories: bool,
         last_write: HgModifiedTime,
     ) -> Result<PendingChanges<M>> {
-        let walker = Walker::new(self.vfs.root().to_path_buf(), matcher.clone(), false)?;
+        let root = self.vfs.root().to_path_buf();
+        let walker: Box<dyn Iterator<Item = Result<WalkEntry>> + Send> = if self.walker_threads > 1
+        {
+            Box::new(ParallelWalker::new(
+                root,
+                matcher.clone(),
+                false,
+                self.walker_threads,
+            )?)
+        } else {
+            Box::new(Walker::new(root, matcher.clone(), false)?)
+        };
+        let (next_clock, candidates) = self.watcher_changes(&treestate);
+        let stage = match candidates {
+            Some(_) => PendingChangesStage::IterateTree,
+            None => PendingChangesStage::Walk,
+        };
         let pending_changes = PendingChanges {
             vfs: self.vfs.clone(),
             walker,
             matcher,
             treestate,
-            stage: PendingChangesStage::Walk,
+            stage,
             include_directories,
             seen: HashSet::new(),
             lookups: vec![],
             tree_iter: None,
             last_write,
+            candidates,
+            next_clock,
+            need_check: vec![],
         };
         Ok(pending_changes)
     }
+
+    /// Ask the watcher what changed since the clock in the treestate.
+    /// Returns the clock to store once pending changes are computed, and
+    /// the paths that may have changed, if a walk can be avoided.
+    fn watcher_changes(
+        &self,
+        treestate: &Mutex<TreeState>,
+    ) -> (Option<String>, Option<Vec<RepoPathBuf>>) {
+        let watcher = match &self.watcher {
+            Some(watcher) => watcher,
+            None => return (None, None),
+        };
+        let clock = get_metadata_value(treestate.lock().get_metadata(), CLOCK_KEY);
+        match watcher.changes_since(clock.as_deref()) {
+            Ok(result) => match result.changes {
+                WatcherChanges::Changed(paths) if clock.is_some() => {
+                    (Some(result.clock), Some(paths))
+                }
+                _ => (Some(result.clock), None),
+            },
+            // Without a watcher we can only walk. The old clock is kept,
+            // since it might still be valid once the watcher is back.
+            Err(_) => (None, None),
+        }
+    }
 }
 
 pub struct PendingChanges<M: Matcher + Clone> {
     vfs: VFS,
-    walker: Walker<M>,
+    walker: Box<dyn Iterator<Item = Result<WalkEntry>> + Send>,
     matcher: M,
     treestate: Arc<Mutex<TreeState>>,
     stage: PendingChangesStage,
@@ -108,6 +188,12 @@ pub struct PendingChanges<M: Matcher + Clone> {
     lookups: Vec<RepoPathBuf>,
     tree_iter: Option<Box<dyn Iterator<Item = Result<PendingChangeResult>> + Send>>,
     last_write: HgModifiedTime,
+    // Paths reported by the watcher, when not walking the working copy.
+    candidates: Option<Vec<RepoPathBuf>>,
+    // Watcher clock to store in the treestate once finished.
+    next_clock: Option<String>,
+    // Changed files to mark NEED_CHECK once finished, if using a watcher.
+    need_check: Vec<RepoPathBuf>,
 }
 
 #[derive(PartialEq)]
@@ -129,6 +215,14 @@ impl PendingChangesStage {
     }
 }
 
+#[derive(PartialEq)]
+enum StatComparison {
+    Changed,
+    /// The contents need to be compared to tell whether the file changed.
+    Lookup,
+    Clean,
+}
+
 pub enum ChangeType {
     Changed(RepoPathBuf),
     Deleted(RepoPathBuf),
@@ -157,6 +251,27 @@ impl<M: Matcher + Clone> PendingChanges<M> {
             return Ok(true);
         }
 
+        match self.compare_stat(path, state, metadata)? {
+            StatComparison::Changed => return Ok(true),
+            StatComparison::Lookup => self.lookups.push(path.to_owned()),
+            // If it's marked NEED_CHECK, we always need to do a lookup, regardless of the mtime.
+            StatComparison::Clean if flags.intersects(StateFlags::NEED_CHECK) => {
+                self.lookups.push(path.to_owned())
+            }
+            StatComparison::Clean => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Compare the stat of a file in the working copy to its treestate
+    /// entry, ignoring the NEED_CHECK flag.
+    fn compare_stat(
+        &self,
+        path: &RepoPath,
+        state: &FileStateV2,
+        metadata: &Metadata,
+    ) -> Result<StatComparison> {
         // If working copy file size or flags are different from what is in treestate, it has changed.
         // Note: state.size is i32 since Mercurial uses negative numbers to indicate special files.
         // A -1 indicates the file is either in a merge state or a lookup state.
@@ -167,23 +282,17 @@ impl<M: Matcher + Clone> PendingChanges<M> {
         // determine if the file has changed relative to p1. This logic is a mess and we should get
         // rid of all these negative numbers.
         let valid_size = state.size >= 0;
-        if valid_size {
-            let size_different = metadata.len() != state.size.try_into().unwrap_or(std::u64::MAX);
-            let exec_different =
-                self.vfs.supports_executables() && is_executable(metadata) != state.is_executable();
-            let symlink_different =
-                self.vfs.supports_symlinks() && is_symlink(metadata) != state.is_symlink();
-
-            if size_different || exec_different || symlink_different {
-                return Ok(true);
-            }
+        if !valid_size {
+            return Ok(StatComparison::Lookup);
         }
-
-        // If it's marked NEED_CHECK, we always need to do a lookup, regardless of the mtime.
-        let needs_check = flags.intersects(StateFlags::NEED_CHECK) || !valid_size;
-        if needs_check {
-            self.lookups.push(path.to_owned());
-            return Ok(false);
+        let size_different = metadata.len() != state.size.try_into().unwrap_or(std::u64::MAX);
+        let exec_different =
+            self.vfs.supports_executables() && is_executable(metadata) != state.is_executable();
+        let symlink_different =
+            self.vfs.supports_symlinks() && is_symlink(metadata) != state.is_symlink();
+
+        if size_different || exec_different || symlink_different {
+            return Ok(StatComparison::Changed);
         }
 
         // If the mtime has changed or matches the last normal() write time, we need to compare the
@@ -192,19 +301,16 @@ impl<M: Matcher + Clone> PendingChanges<M> {
         // below to fail and force a lookup, the -1 is handled correctly without special casing. In
         // theory all -1 files should be marked NEED_CHECK above (I think).
         if state.mtime < 0 {
-            self.lookups.push(path.to_owned());
-        } else {
-            let state_mtime: Result<HgModifiedTime> = state.mtime.try_into();
-            let state_mtime =
-                state_mtime.map_err(|e| WalkError::InvalidMTime(path.to_owned(), e))?;
-            let mtime: HgModifiedTime = metadata.modified()?.try_into()?;
-
-            if mtime != state_mtime || mtime == self.last_write {
-                self.lookups.push(path.to_owned());
-            }
+            return Ok(StatComparison::Lookup);
         }
+        let state_mtime: Result<HgModifiedTime> = state.mtime.try_into();
+        let state_mtime = state_mtime.map_err(|e| WalkError::InvalidMTime(path.to_owned(), e))?;
+        let mtime: HgModifiedTime = metadata.modified()?.try_into()?;
 
-        Ok(false)
+        if mtime != state_mtime || mtime == self.last_write {
+            return Ok(StatComparison::Lookup);
+        }
+        Ok(StatComparison::Clean)
     }
 
     fn next_walk(&mut self) -> Option<Result<PendingChangeResult>> {
@@ -247,6 +353,10 @@ impl<M: Matcher + Clone> PendingChanges<M> {
     }
 
     fn get_tree_entries(&mut self) -> Vec<Result<PendingChangeResult>> {
+        if let Some(candidates) = self.candidates.take() {
+            return self.get_candidate_entries(candidates);
+        }
+
         let mut results = vec![];
         let tracked = self.get_tracked_from_p1();
         if let Err(e) = tracked {
@@ -307,6 +417,142 @@ impl<M: Matcher + Clone> PendingChanges<M> {
         results
     }
 
+    /// Check the files reported by the watcher, and the files marked
+    /// NEED_CHECK by earlier runs.
+    fn get_candidate_entries(
+        &mut self,
+        candidates: Vec<RepoPathBuf>,
+    ) -> Vec<Result<PendingChangeResult>> {
+        let mut results = vec![];
+        let need_check = match self.get_need_check() {
+            Ok(need_check) => need_check,
+            Err(e) => {
+                results.push(Err(e));
+                return results;
+            }
+        };
+        let mut candidates = candidates;
+        candidates.extend(need_check);
+        candidates.sort();
+        candidates.dedup();
+
+        for path in candidates {
+            if path.as_str().split('/').any(|component| component == ".hg") {
+                continue;
+            }
+            match self.check_candidate(path) {
+                Ok(Some(result)) => results.push(Ok(result)),
+                Ok(None) => {}
+                Err(e) => results.push(Err(e)),
+            }
+        }
+        results
+    }
+
+    fn check_candidate(&mut self, path: RepoPathBuf) -> Result<Option<PendingChangeResult>> {
+        let state = self.treestate.lock().get(&path)?.cloned();
+        let in_parent =
+            matches!(&state, Some(state) if state.state.intersects(StateFlags::EXIST_P1));
+
+        let metadata = match self.vfs.metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_file() => metadata,
+            // Missing, or not a regular file (like in the walk).
+            _ => {
+                if in_parent {
+                    return Ok(Some(PendingChangeResult::File(ChangeType::Deleted(path))));
+                }
+                // An untracked file that was marked NEED_CHECK is gone, so
+                // there is nothing to check anymore.
+                if let Some(state) = state {
+                    if state.state == StateFlags::NEED_CHECK {
+                        self.treestate.lock().remove(&path)?;
+                    }
+                }
+                return Ok(None);
+            }
+        };
+
+        // Untracked files are only reported if matched, like in the walk.
+        if !in_parent && !self.matcher.matches_file(&path)? {
+            return Ok(None);
+        }
+
+        // A file marked NEED_CHECK by an earlier run that matches its
+        // treestate entry again is clean, so it doesn't need to be checked
+        // anymore.
+        if let Some(mut state) = state {
+            if in_parent
+                && state.state.contains(StateFlags::NEED_CHECK)
+                && self.compare_stat(&path, &state, &metadata)? == StatComparison::Clean
+            {
+                state.state.remove(StateFlags::NEED_CHECK);
+                self.treestate.lock().insert(&path, &state)?;
+                return Ok(None);
+            }
+        }
+        if self.is_changed(&path, &metadata)? {
+            return Ok(Some(PendingChangeResult::File(ChangeType::Changed(path))));
+        }
+        Ok(None)
+    }
+
+    /// Returns the files in the treestate that are marked NEED_CHECK.
+    fn get_need_check(&mut self) -> Result<Vec<RepoPathBuf>> {
+        let mut treestate = self.treestate.lock();
+
+        let mut result = Vec::new();
+        let mask = StateFlags::NEED_CHECK;
+
+        treestate.visit(
+            &mut |components, _| {
+                let path = components.concat();
+                let path = RepoPathBuf::from_utf8(path)?;
+                result.push(path);
+                Ok(VisitorResult::NotChanged)
+            },
+            &|_path, dir| match dir.get_aggregated_state() {
+                None => true,
+                Some(state) => state.union.intersects(mask),
+            },
+            &|_path, file| file.state.intersects(mask),
+        )?;
+        Ok(result)
+    }
+
+    /// Store the watcher clock, and mark the files found to be changed as
+    /// NEED_CHECK, so later runs check them even if the watcher doesn't
+    /// report them again.
+    fn finish(&mut self) -> Result<()> {
+        let clock = match self.next_clock.take() {
+            Some(clock) => clock,
+            None => return Ok(()),
+        };
+
+        let mut treestate = self.treestate.lock();
+        for path in self.need_check.drain(..).chain(self.lookups.drain(..)) {
+            let state = match treestate.get(&path)? {
+                Some(state) if state.state.contains(StateFlags::NEED_CHECK) => continue,
+                Some(state) => {
+                    let mut state = state.clone();
+                    state.state |= StateFlags::NEED_CHECK;
+                    state
+                }
+                None => FileStateV2 {
+                    mode: 0,
+                    size: 0,
+                    mtime: 0,
+                    state: StateFlags::NEED_CHECK,
+                    copied: None,
+                },
+            };
+            treestate.insert(&path, &state)?;
+        }
+
+        let metadata = set_metadata_value(treestate.get_metadata(), CLOCK_KEY, &clock);
+        treestate.set_metadata(metadata);
+        Ok(())
+    }
+
     /// Returns the files in the treestate that are from p1.
     /// We only care about files from p1 because pending_changes is relative to p1.
     fn get_tracked_from_p1(&mut self) -> Result<Vec<RepoPathBuf>> {
@@ -349,13 +595,22 @@ impl<M: Matcher + Clone> Iterator for PendingChanges<M> {
                 PendingChangesStage::Finished => None,
             };
 
+            if let Some(Ok(PendingChangeResult::File(
+                ChangeType::Changed(path) | ChangeType::Deleted(path),
+            ))) = &change
+            {
+                if self.next_clock.is_some() {
+                    self.need_check.push(path.clone());
+                }
+            }
+
             if change.is_some() {
                 return change;
             }
 
             self.stage = self.stage.next();
             if self.stage == PendingChangesStage::Finished {
-                return None;
+                return self.finish().err().map(Err);
             }
         }
     }
@@ -365,3 +620,271 @@ fn normalize(path: RepoPathBuf) -> RepoPathBuf {
     // TODO: Support path normalization on case insensitive file systems
     path
 }
+
+/// Get a value from treestate metadata, which is a list of `key=value`
+/// entries separated by NUL bytes.
+fn get_metadata_value(metadata: &[u8], key: &str) -> Option<String> {
+    String::from_utf8_lossy(metadata)
+        .split('\0')
+        .find_map(|entry| {
+            let (k, v) = entry.split_once('=')?;
+            (k == key).then(|| v.to_string())
+        })
+}
+
+/// Set a value in treestate metadata, keeping other entries.
+fn set_metadata_value(metadata: &[u8], key: &str, value: &str) -> Vec<u8> {
+    let metadata = String::from_utf8_lossy(metadata);
+    let mut entries: Vec<&str> = metadata
+        .split('\0')
+        .filter(|entry| match entry.split_once('=') {
+            Some((k, _)) => k != key,
+            None => false,
+        })
+        .collect();
+    let entry = format!("{}={}", key, value);
+    entries.push(&entry);
+    entries.join("\0").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pathmatcher::AlwaysMatcher;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::watcher::WatcherResult;
+
+    /// Returns canned results, and records the clocks it was asked about.
+    struct FakeWatcher {
+        results: Mutex<Vec<WatcherResult>>,
+        clocks: Mutex<Vec<Option<String>>>,
+    }
+
+    impl FakeWatcher {
+        fn push(&self, clock: &str, changes: WatcherChanges) {
+            self.results.lock().push(WatcherResult {
+                clock: clock.to_string(),
+                changes,
+            });
+        }
+    }
+
+    impl FileSystemWatcher for FakeWatcher {
+        fn changes_since(&self, clock: Option<&str>) -> Result<WatcherResult> {
+            self.clocks.lock().push(clock.map(String::from));
+            Ok(self.results.lock().remove(0))
+        }
+    }
+
+    fn changed(paths: &[&str]) -> WatcherChanges {
+        WatcherChanges::Changed(
+            paths
+                .iter()
+                .map(|p| RepoPathBuf::from_string(p.to_string()).unwrap())
+                .collect(),
+        )
+    }
+
+    fn pending_changes(
+        fs: &PhysicalFileSystem,
+        treestate: &Arc<Mutex<TreeState>>,
+    ) -> Result<Vec<String>> {
+        let mut changes = fs
+            .pending_changes(
+                treestate.clone(),
+                Arc::new(AlwaysMatcher::new()),
+                false,
+                0u64.into(),
+            )?
+            .map(|change| {
+                Ok(match change? {
+                    PendingChangeResult::File(ChangeType::Changed(path)) => format!("M {}", path),
+                    PendingChangeResult::File(ChangeType::Deleted(path)) => format!("R {}", path),
+                    PendingChangeResult::SeenDirectory(path) => format!("D {}", path),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        changes.sort();
+        Ok(changes)
+    }
+
+    #[test]
+    fn test_metadata_values() {
+        let metadata = b"p1=abc\0clock=c:1";
+        assert_eq!(
+            get_metadata_value(metadata, "clock"),
+            Some("c:1".to_string())
+        );
+        assert_eq!(get_metadata_value(metadata, "p2"), None);
+        assert_eq!(
+            set_metadata_value(metadata, "clock", "c:2"),
+            b"p1=abc\0clock=c:2".to_vec()
+        );
+        assert_eq!(
+            set_metadata_value(b"", "clock", "c:1"),
+            b"clock=c:1".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_pending_changes_with_watcher() -> Result<()> {
+        let root = tempdir()?;
+        fs::write(root.path().join("tracked"), b"a")?;
+        fs::write(root.path().join("untracked"), b"b")?;
+        let mtime: HgModifiedTime = fs::metadata(root.path().join("tracked"))?
+            .modified()?
+            .try_into()?;
+
+        let treestate_dir = tempdir()?;
+        let mut treestate = TreeState::open(treestate_dir.path().join("treestate"), None)?;
+        treestate.insert(
+            "tracked",
+            &FileStateV2 {
+                mode: 0o644,
+                size: 1,
+                mtime: mtime.0.try_into()?,
+                state: StateFlags::EXIST_P1 | StateFlags::EXIST_NEXT,
+                copied: None,
+            },
+        )?;
+        treestate.set_metadata(b"p1=abc");
+        let treestate = Arc::new(Mutex::new(treestate));
+
+        let watcher = Arc::new(FakeWatcher {
+            results: Mutex::new(vec![]),
+            clocks: Mutex::new(vec![]),
+        });
+        let fs = PhysicalFileSystem::new(root.path().to_path_buf())?.with_watcher(watcher.clone());
+
+        // No clock yet, so the working copy is walked.
+        watcher.push("c:1", WatcherChanges::Fresh);
+        assert_eq!(pending_changes(&fs, &treestate)?, vec!["M untracked"]);
+        assert_eq!(
+            treestate.lock().get_metadata(),
+            b"p1=abc\0clock=c:1".as_ref()
+        );
+
+        // Changes the watcher doesn't report aren't noticed, but files found
+        // to be changed before are checked again.
+        fs::write(root.path().join("tracked"), b"aaa")?;
+        watcher.push("c:2", changed(&[]));
+        assert_eq!(pending_changes(&fs, &treestate)?, vec!["M untracked"]);
+
+        watcher.push("c:3", changed(&["tracked"]));
+        assert_eq!(
+            pending_changes(&fs, &treestate)?,
+            vec!["M tracked", "M untracked"]
+        );
+
+        fs::remove_file(root.path().join("tracked"))?;
+        fs::remove_file(root.path().join("untracked"))?;
+        watcher.push("c:4", changed(&["untracked"]));
+        assert_eq!(pending_changes(&fs, &treestate)?, vec!["R tracked"]);
+        assert!(treestate.lock().get("untracked")?.is_none());
+
+        // The watcher lost track of changes, so walk again.
+        watcher.push("c:5", WatcherChanges::Fresh);
+        assert_eq!(pending_changes(&fs, &treestate)?, vec!["R tracked"]);
+
+        assert_eq!(
+            *watcher.clocks.lock(),
+            vec![
+                None,
+                Some("c:1".to_string()),
+                Some("c:2".to_string()),
+                Some("c:3".to_string()),
+                Some("c:4".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_need_check_cleared_when_clean() -> Result<()> {
+        let root = tempdir()?;
+        fs::write(root.path().join("clean"), b"a")?;
+        fs::write(root.path().join("modified"), b"aaa")?;
+        let mtime: HgModifiedTime = fs::metadata(root.path().join("clean"))?
+            .modified()?
+            .try_into()?;
+
+        let treestate_dir = tempdir()?;
+        let mut treestate = TreeState::open(treestate_dir.path().join("treestate"), None)?;
+        for path in ["clean", "modified"] {
+            treestate.insert(
+                path,
+                &FileStateV2 {
+                    mode: 0o644,
+                    size: 1,
+                    mtime: mtime.0.try_into()?,
+                    state: StateFlags::EXIST_P1 | StateFlags::EXIST_NEXT | StateFlags::NEED_CHECK,
+                    copied: None,
+                },
+            )?;
+        }
+        treestate.set_metadata(b"clock=c:1");
+        let treestate = Arc::new(Mutex::new(treestate));
+
+        let watcher = Arc::new(FakeWatcher {
+            results: Mutex::new(vec![]),
+            clocks: Mutex::new(vec![]),
+        });
+        let fs = PhysicalFileSystem::new(root.path().to_path_buf())?.with_watcher(watcher.clone());
+
+        watcher.push("c:2", changed(&[]));
+        assert_eq!(pending_changes(&fs, &treestate)?, vec!["M modified"]);
+
+        let need_check = |path: &str| -> Result<bool> {
+            Ok(treestate
+                .lock()
+                .get(path)?
+                .unwrap()
+                .state
+                .contains(StateFlags::NEED_CHECK))
+        };
+        assert!(!need_check("clean")?);
+        assert!(need_check("modified")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pending_changes_parallel_walker() -> Result<()> {
+        let root = tempdir()?;
+        for dir in ["dir1/dir2", "dir3", "sub/.hg"] {
+            fs::create_dir_all(root.path().join(dir))?;
+        }
+        for path in ["a", "dir1/b", "dir1/dir2/c", "dir3/d", "sub/s"] {
+            fs::write(root.path().join(path), b"a")?;
+        }
+        let mtime: HgModifiedTime = fs::metadata(root.path().join("a"))?
+            .modified()?
+            .try_into()?;
+
+        let treestate_dir = tempdir()?;
+        let mut treestate = TreeState::open(treestate_dir.path().join("treestate"), None)?;
+        for (path, size) in [("a", 1), ("dir1/b", 2), ("dir1/dir2/c", 1), ("gone", 1)] {
+            treestate.insert(
+                path,
+                &FileStateV2 {
+                    mode: 0o644,
+                    size,
+                    mtime: mtime.0.try_into()?,
+                    state: StateFlags::EXIST_P1 | StateFlags::EXIST_NEXT,
+                    copied: None,
+                },
+            )?;
+        }
+        let treestate = Arc::new(Mutex::new(treestate));
+
+        let serial = PhysicalFileSystem::new(root.path().to_path_buf())?;
+        let expected = pending_changes(&serial, &treestate)?;
+        assert_eq!(expected, vec!["M dir1/b", "M dir3/d", "R gone"]);
+
+        let parallel = PhysicalFileSystem::new(root.path().to_path_buf())?.with_walker_threads(4);
+        assert_eq!(pending_changes(&parallel, &treestate)?, expected);
+        Ok(())
+    }
+}
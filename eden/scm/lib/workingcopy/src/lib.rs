@@ -7,3 +7,4 @@
 
 pub mod filesystem;
 pub mod walker;
+pub mod watcher;
@@ -11,8 +11,16 @@ use std::fs::{self};
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
 
+use anyhow::anyhow;
 use anyhow::Result;
+use crossbeam::channel::Receiver;
+use crossbeam::channel::Sender;
+use crossbeam::channel::{self};
 use pathmatcher::DirectoryMatch;
 use pathmatcher::Matcher;
 use thiserror::Error;
@@ -99,41 +107,6 @@ where
         Ok(walker)
     }
 
-    fn match_entry(&mut self, next_dir: &RepoPathBuf, entry: DirEntry) -> Result<()> {
-        // It'd be nice to move all this conversion noise to a function, but having it here saves
-        // us from allocating filename repeatedly.
-        let filename = entry.file_name();
-        let filename = filename.to_str().ok_or(WalkError::FsUtf8Error(
-            filename.to_string_lossy().into_owned(),
-        ))?;
-        let filename = RepoPath::from_str(filename)
-            .map_err(|e| WalkError::RepoPathError(filename.to_owned(), e))?;
-        let filetype = entry
-            .file_type()
-            .map_err(|e| WalkError::IOError(filename.to_owned(), e))?;
-
-        let mut candidate_path = next_dir.clone();
-        candidate_path.push(filename);
-        if filetype.is_file() || filetype.is_symlink() {
-            if self.matcher.matches_file(candidate_path.as_repo_path())? {
-                self.results
-                    .push(Ok(WalkEntry::File(candidate_path, entry.metadata()?)));
-            }
-        } else if filetype.is_dir() {
-            if filename.as_str() != ".hg"
-                && self
-                    .matcher
-                    .matches_directory(candidate_path.as_repo_path())?
-                    != DirectoryMatch::Nothing
-            {
-                self.dir_matches.push(candidate_path);
-            }
-        } else if self.matcher.matches_file(candidate_path.as_repo_path())? {
-            return Err(WalkError::InvalidFileType(filename.to_owned()).into());
-        }
-        Ok(())
-    }
-
     /// Lazy traversal to find matching files
     fn walk(&mut self) -> Result<()> {
         while self.results.is_empty() && !self.dir_matches.is_empty() {
@@ -142,23 +115,77 @@ where
                 self.results
                     .push(Ok(WalkEntry::Directory(next_dir.clone())));
             }
-            let abs_next_dir = self.root.join(next_dir.as_str());
-            // Don't process the directory if it contains a .hg directory, unless it's the root.
-            if next_dir.is_empty() || !Path::exists(&abs_next_dir.join(".hg")) {
-                for entry in fs::read_dir(abs_next_dir)
-                    .map_err(|e| WalkError::IOError(next_dir.clone(), e))?
-                {
-                    let entry = entry.map_err(|e| WalkError::IOError(next_dir.clone(), e))?;
-                    if let Err(e) = self.match_entry(&next_dir, entry) {
-                        self.results.push(Err(e));
-                    }
-                }
-            }
+            walk_dir(
+                &self.root,
+                next_dir,
+                &self.matcher,
+                &mut self.results,
+                &mut self.dir_matches,
+            )?;
         }
         Ok(())
     }
 }
 
+/// Read the entries of `dir`, adding matching files to `results` and
+/// directories that need to be visited to `dir_matches`.
+fn walk_dir(
+    root: &Path,
+    dir: RepoPathBuf,
+    matcher: &impl Matcher,
+    results: &mut Vec<Result<WalkEntry>>,
+    dir_matches: &mut Vec<RepoPathBuf>,
+) -> Result<()> {
+    let abs_dir = root.join(dir.as_str());
+    // Don't process the directory if it contains a .hg directory, unless it's the root.
+    if dir.is_empty() || !Path::exists(&abs_dir.join(".hg")) {
+        for entry in fs::read_dir(abs_dir).map_err(|e| WalkError::IOError(dir.clone(), e))? {
+            let entry = entry.map_err(|e| WalkError::IOError(dir.clone(), e))?;
+            if let Err(e) = match_entry(&dir, entry, matcher, results, dir_matches) {
+                results.push(Err(e));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn match_entry(
+    dir: &RepoPathBuf,
+    entry: DirEntry,
+    matcher: &impl Matcher,
+    results: &mut Vec<Result<WalkEntry>>,
+    dir_matches: &mut Vec<RepoPathBuf>,
+) -> Result<()> {
+    // It'd be nice to move all this conversion noise to a function, but having it here saves
+    // us from allocating filename repeatedly.
+    let filename = entry.file_name();
+    let filename = filename.to_str().ok_or(WalkError::FsUtf8Error(
+        filename.to_string_lossy().into_owned(),
+    ))?;
+    let filename = RepoPath::from_str(filename)
+        .map_err(|e| WalkError::RepoPathError(filename.to_owned(), e))?;
+    let filetype = entry
+        .file_type()
+        .map_err(|e| WalkError::IOError(filename.to_owned(), e))?;
+
+    let mut candidate_path = dir.clone();
+    candidate_path.push(filename);
+    if filetype.is_file() || filetype.is_symlink() {
+        if matcher.matches_file(candidate_path.as_repo_path())? {
+            results.push(Ok(WalkEntry::File(candidate_path, entry.metadata()?)));
+        }
+    } else if filetype.is_dir() {
+        if filename.as_str() != ".hg"
+            && matcher.matches_directory(candidate_path.as_repo_path())? != DirectoryMatch::Nothing
+        {
+            dir_matches.push(candidate_path);
+        }
+    } else if matcher.matches_file(candidate_path.as_repo_path())? {
+        return Err(WalkError::InvalidFileType(filename.to_owned()).into());
+    }
+    Ok(())
+}
+
 impl<M> Iterator for Walker<M>
 where
     M: Matcher,
@@ -172,6 +199,164 @@ where
     }
 }
 
+/// ParallelWalker finds the same files as [`Walker`], but lists directories
+/// concurrently on a pool of `num_threads` threads. The whole working copy
+/// is walked on the first call to `next`, and entries are returned sorted
+/// by path, followed by any errors.
+pub struct ParallelWalker<M> {
+    root: PathBuf,
+    matcher: Arc<M>,
+    include_directories: bool,
+    num_threads: usize,
+    results: Option<std::vec::IntoIter<Result<WalkEntry>>>,
+}
+
+impl<M> ParallelWalker<M>
+where
+    M: Matcher + Send + Sync + 'static,
+{
+    pub fn new(
+        root: PathBuf,
+        matcher: M,
+        include_directories: bool,
+        num_threads: usize,
+    ) -> Result<Self> {
+        Ok(ParallelWalker {
+            root,
+            matcher: Arc::new(matcher),
+            include_directories,
+            num_threads: num_threads.max(1),
+            results: None,
+        })
+    }
+
+    fn walk(&self) -> Result<Vec<Result<WalkEntry>>> {
+        if self.matcher.matches_directory(&RepoPathBuf::new())? == DirectoryMatch::Nothing {
+            return Ok(Vec::new());
+        }
+
+        // Directories left to walk, including those being walked. Workers
+        // stop when a `None` is received, which is sent once this hits zero.
+        let pending = Arc::new(AtomicUsize::new(1));
+        let (sender, receiver) = channel::unbounded();
+        sender.send(Some(RepoPathBuf::new()))?;
+
+        let mut handles = Vec::with_capacity(self.num_threads);
+        for _ in 0..self.num_threads {
+            let worker = WalkWorker {
+                root: self.root.clone(),
+                matcher: self.matcher.clone(),
+                include_directories: self.include_directories,
+                num_threads: self.num_threads,
+                pending: pending.clone(),
+                sender: sender.clone(),
+            };
+            let receiver = receiver.clone();
+            handles.push(thread::spawn(move || worker.run(receiver)));
+        }
+
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for handle in handles {
+            let results = handle
+                .join()
+                .map_err(|_| anyhow!("working copy walker thread panicked"))?;
+            for result in results {
+                match result {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+
+        entries.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        errors.sort_by_cached_key(|e| e.to_string());
+        Ok(entries
+            .into_iter()
+            .map(Ok)
+            .chain(errors.into_iter().map(Err))
+            .collect())
+    }
+}
+
+impl<M> Iterator for ParallelWalker<M>
+where
+    M: Matcher + Send + Sync + 'static,
+{
+    type Item = Result<WalkEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.results.is_none() {
+            match self.walk() {
+                Ok(results) => self.results = Some(results.into_iter()),
+                Err(e) => {
+                    self.results = Some(Vec::new().into_iter());
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.results.as_mut()?.next()
+    }
+}
+
+struct WalkWorker<M> {
+    root: PathBuf,
+    matcher: Arc<M>,
+    include_directories: bool,
+    num_threads: usize,
+    pending: Arc<AtomicUsize>,
+    sender: Sender<Option<RepoPathBuf>>,
+}
+
+impl<M: Matcher> WalkWorker<M> {
+    fn run(self, receiver: Receiver<Option<RepoPathBuf>>) -> Vec<Result<WalkEntry>> {
+        let mut results = Vec::new();
+        let mut dir_matches = Vec::new();
+        while let Ok(Some(dir)) = receiver.recv() {
+            if self.include_directories {
+                results.push(Ok(WalkEntry::Directory(dir.clone())));
+            }
+            if let Err(e) = walk_dir(
+                &self.root,
+                dir,
+                &self.matcher,
+                &mut results,
+                &mut dir_matches,
+            ) {
+                results.push(Err(e));
+            }
+
+            // Count subdirectories before finishing this one, so the count
+            // can't drop to zero while there is still work.
+            self.pending.fetch_add(dir_matches.len(), Ordering::SeqCst);
+            for dir in dir_matches.drain(..) {
+                let _ = self.sender.send(Some(dir));
+            }
+            if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                self.stop_all();
+            }
+        }
+        results
+    }
+}
+
+impl<M> WalkWorker<M> {
+    fn stop_all(&self) {
+        for _ in 0..self.num_threads {
+            let _ = self.sender.send(None);
+        }
+    }
+}
+
+impl<M> Drop for WalkWorker<M> {
+    fn drop(&mut self) {
+        // The directory being walked will never finish, so nothing else
+        // would stop the other workers.
+        if thread::panicking() {
+            self.stop_all();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::create_dir_all;
@@ -180,6 +365,7 @@ mod tests {
 
     use pathmatcher::AlwaysMatcher;
     use pathmatcher::NeverMatcher;
+    use pathmatcher::TreeMatcher;
     use tempfile::tempdir;
 
     use super::*;
@@ -229,4 +415,46 @@ mod tests {
         assert!(walked_files.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_parallel_walker() -> Result<()> {
+        let directories = vec!["dirA/dirE", "dirB/dirC/dirD", "dirF", "sub/.hg"];
+        let files = vec![
+            "a.txt",
+            "dirA/a.txt",
+            "dirA/b.txt",
+            "dirA/dirE/e.txt",
+            "dirB/dirC/dirD/c.txt",
+            "dirF/f.txt",
+            "sub/s.txt",
+        ];
+        let root_dir = create_directory(&directories, &files)?;
+        let root_path = PathBuf::from(root_dir.path());
+
+        let matcher = TreeMatcher::from_rules(["dirA/**", "!dirA/dirE/**", "dirB/**"].iter())?;
+        let walker = ParallelWalker::new(root_path.clone(), matcher, false, 4)?;
+        let walked_files = walker
+            .map(|entry| entry.map(|entry| entry.as_ref().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            walked_files,
+            vec!["dirA/a.txt", "dirA/b.txt", "dirB/dirC/dirD/c.txt"]
+        );
+
+        // Same results as the sequential walker, in a stable order.
+        let mut expected = Walker::new(root_path.clone(), AlwaysMatcher::new(), true)?
+            .map(|entry| entry.map(|entry| entry.as_ref().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        expected.sort();
+        for _ in 0..3 {
+            let walked = ParallelWalker::new(root_path.clone(), AlwaysMatcher::new(), true, 3)?
+                .map(|entry| entry.map(|entry| entry.as_ref().to_string()))
+                .collect::<Result<Vec<_>>>()?;
+            assert_eq!(walked, expected);
+        }
+
+        let walker = ParallelWalker::new(root_path, NeverMatcher::new(), true, 2)?;
+        assert_eq!(walker.count(), 0);
+        Ok(())
+    }
 }
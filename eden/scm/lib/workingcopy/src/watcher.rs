@@ -0,0 +1,259 @@
+/*
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! File system watchers report which files may have changed since a point
+//! in time (a "clock"), so `pending_changes` only needs to check those
+//! files instead of walking the whole working copy.
+
+use anyhow::Result;
+use types::RepoPathBuf;
+
+/// What a watcher knows about changes since a clock.
+#[derive(Debug, PartialEq)]
+pub enum WatcherChanges {
+    /// The watcher cannot tell what changed, for example because it was
+    /// restarted or the clock is unknown to it. All files must be checked.
+    Fresh,
+    /// Paths, relative to the working copy root, that may have changed.
+    Changed(Vec<RepoPathBuf>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WatcherResult {
+    /// Clock to pass to the next query.
+    pub clock: String,
+    pub changes: WatcherChanges,
+}
+
+pub trait FileSystemWatcher: Send + Sync {
+    /// Changes since `clock`, which was returned by an earlier query. With
+    /// no clock, returns a clock to start from and [`WatcherChanges::Fresh`].
+    fn changes_since(&self, clock: Option<&str>) -> Result<WatcherResult>;
+}
+
+#[cfg(unix)]
+pub use self::socket::SocketWatcher;
+
+#[cfg(unix)]
+mod socket {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use anyhow::bail;
+    use anyhow::Context;
+    use anyhow::Result;
+    use serde::de::DeserializeOwned;
+    use serde::Deserialize;
+    use serde_json::json;
+    use serde_json::Value;
+    use types::RepoPathBuf;
+
+    use super::FileSystemWatcher;
+    use super::WatcherChanges;
+    use super::WatcherResult;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Talks to a watcher daemon using the JSON protocol of watchman, over
+    /// a unix socket. This works with watchman itself, and with other
+    /// (e.g. inotify-based) daemons implementing the `watch-project`,
+    /// `clock` and `query` commands.
+    pub struct SocketWatcher {
+        sock_path: PathBuf,
+        root: PathBuf,
+    }
+
+    #[derive(Deserialize)]
+    struct WatchProjectResponse {
+        watch: String,
+        relative_path: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct ClockResponse {
+        clock: String,
+    }
+
+    #[derive(Deserialize)]
+    struct QueryResponse {
+        clock: String,
+        #[serde(default)]
+        is_fresh_instance: bool,
+        #[serde(default)]
+        files: Vec<String>,
+    }
+
+    impl SocketWatcher {
+        pub fn new(sock_path: PathBuf, root: PathBuf) -> Self {
+            SocketWatcher { sock_path, root }
+        }
+
+        /// Send a command and read its response, which is one line of JSON.
+        fn command<T: DeserializeOwned>(&self, command: Value) -> Result<T> {
+            let stream = UnixStream::connect(&self.sock_path)
+                .with_context(|| format!("failed to connect to {}", self.sock_path.display()))?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+
+            let mut request = serde_json::to_vec(&command)?;
+            request.push(b'\n');
+            (&stream).write_all(&request)?;
+
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line)?;
+            let response: Value = serde_json::from_str(&line)
+                .with_context(|| format!("invalid response to {}", command))?;
+            if let Some(error) = response.get("error") {
+                bail!("watcher error for {}: {}", command, error);
+            }
+            Ok(serde_json::from_value(response)?)
+        }
+    }
+
+    impl FileSystemWatcher for SocketWatcher {
+        fn changes_since(&self, clock: Option<&str>) -> Result<WatcherResult> {
+            let watch: WatchProjectResponse = self.command(json!(["watch-project", self.root]))?;
+
+            let clock = match clock {
+                Some(clock) => clock,
+                None => {
+                    let response: ClockResponse = self.command(json!(["clock", watch.watch]))?;
+                    return Ok(WatcherResult {
+                        clock: response.clock,
+                        changes: WatcherChanges::Fresh,
+                    });
+                }
+            };
+
+            let mut query = json!({
+                "since": clock,
+                "fields": ["name"],
+                "empty_on_fresh_instance": true,
+            });
+            if let Some(relative_path) = watch.relative_path {
+                query["relative_root"] = json!(relative_path);
+            }
+            let response: QueryResponse = self.command(json!(["query", watch.watch, query]))?;
+
+            let changes = if response.is_fresh_instance {
+                WatcherChanges::Fresh
+            } else {
+                let paths = response
+                    .files
+                    .into_iter()
+                    .map(RepoPathBuf::from_string)
+                    .collect::<Result<_, _>>()?;
+                WatcherChanges::Changed(paths)
+            };
+            Ok(WatcherResult {
+                clock: response.clock,
+                changes,
+            })
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    use serde_json::json;
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Answer one request per connection with `responses`, in order.
+    /// Returns the requests.
+    fn serve(listener: UnixListener, responses: Vec<Value>) -> thread::JoinHandle<Vec<Value>> {
+        thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut line = String::new();
+                    BufReader::new(&stream).read_line(&mut line).unwrap();
+                    (&stream)
+                        .write_all(format!("{}\n", response).as_bytes())
+                        .unwrap();
+                    serde_json::from_str(&line).unwrap()
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_socket_watcher() -> Result<()> {
+        let dir = tempdir()?;
+        let sock_path = dir.path().join("sock");
+        let server = serve(
+            UnixListener::bind(&sock_path)?,
+            vec![
+                json!({"watch": "/repo"}),
+                json!({"clock": "c:1"}),
+                json!({"watch": "/repo", "relative_path": "sub"}),
+                json!({"clock": "c:2", "files": ["a", "b/c"]}),
+                json!({"watch": "/repo"}),
+                json!({"clock": "c:3", "is_fresh_instance": true}),
+                json!({"error": "unable to resolve root"}),
+            ],
+        );
+        let watcher = SocketWatcher::new(sock_path, PathBuf::from("/repo/sub"));
+
+        assert_eq!(
+            watcher.changes_since(None)?,
+            WatcherResult {
+                clock: "c:1".to_string(),
+                changes: WatcherChanges::Fresh,
+            }
+        );
+        assert_eq!(
+            watcher.changes_since(Some("c:1"))?,
+            WatcherResult {
+                clock: "c:2".to_string(),
+                changes: WatcherChanges::Changed(vec![
+                    RepoPathBuf::from_string("a".to_string())?,
+                    RepoPathBuf::from_string("b/c".to_string())?,
+                ]),
+            }
+        );
+        assert_eq!(
+            watcher.changes_since(Some("c:2"))?,
+            WatcherResult {
+                clock: "c:3".to_string(),
+                changes: WatcherChanges::Fresh,
+            }
+        );
+        assert!(watcher.changes_since(Some("c:3")).is_err());
+
+        let query = |since: &str| json!({"since": since, "fields": ["name"], "empty_on_fresh_instance": true});
+        let mut relative_query = query("c:1");
+        relative_query["relative_root"] = json!("sub");
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                json!(["watch-project", "/repo/sub"]),
+                json!(["clock", "/repo"]),
+                json!(["watch-project", "/repo/sub"]),
+                json!(["query", "/repo", relative_query]),
+                json!(["watch-project", "/repo/sub"]),
+                json!(["query", "/repo", query("c:2")]),
+                json!(["watch-project", "/repo/sub"]),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -290,10 +290,14 @@ impl Zstore {
         let mut results = self.log.lookup(0, id)?;
         match results.next() {
             None => Ok(None),
-            Some(Ok(bytes)) => {
+            Some(Ok(Cow::Borrowed(bytes))) => {
                 let result = mincode::deserialize(bytes)?;
                 Ok(Some(result))
             }
+            Some(Ok(Cow::Owned(bytes))) => {
+                let result: Delta = mincode::deserialize(&bytes)?;
+                Ok(Some(result.into_owned()))
+            }
             Some(Err(err)) => Err(err.into()),
         }
     }
@@ -391,7 +395,8 @@ impl Zstore {
         }
 
         for entry in self.log.iter() {
-            let id = &self.log.index_func(Self::ID20_INDEX, entry?)?[0];
+            let entry = entry?;
+            let id = &self.log.index_func(Self::ID20_INDEX, &entry)?[0];
             let mut id = Id20::from_slice(id).unwrap();
             let mut chain: Vec<Delta> = Vec::new();
             while id != *EMPTY_ID20 {
@@ -607,6 +612,20 @@ struct Delta<'a> {
     data: Cow<'a, [u8]>,
 }
 
+impl<'a> Delta<'a> {
+    /// Detach from the buffer it was deserialized from.
+    fn into_owned(self) -> Delta<'static> {
+        Delta {
+            id: self.id,
+            base_id: self.base_id,
+            depth: self.depth,
+            subchain_len: self.subchain_len,
+            chain_bytes: self.chain_bytes,
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
+
 // -------- Tests --------
 
 #[cfg(test)]
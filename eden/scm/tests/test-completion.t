@@ -146,6 +146,7 @@ Show debug commands if there are no other candidates
   debuginternals
   debugknown
   debuglabelcomplete
+  debuglfsprune
   debuglocks
   debugmakepublic
   debugmanifestdirs
@@ -437,7 +438,7 @@ Show all commands + options
   debugdryup: 
   debugdumpdynamicconfig: reponame, username, canary
   debugdumpindexedlog: 
-  debugdumptrace: time-range, session-id, output-path
+  debugdumptrace: time-range, session-id, output-path, compare-session-id
   debugdynamicconfig: canary
   debugedenimporthelper: in-fd, out-fd, manifest, get-manifest-node, cat-file, cat-tree, get-file-size, fetch-tree
   debugedenrunpostupdatehook: 
@@ -461,6 +462,7 @@ Show all commands + options
   debuginternals: output
   debugknown: 
   debuglabelcomplete: 
+  debuglfsprune: 
   debuglocks: force-lock, force-wlock, force-undolog-lock, set-lock, set-wlock, wait
   debugmakepublic: rev, delete
   debugmanifestdirs: rev
@@ -0,0 +1,47 @@
+#chg-compatible
+
+  $ configure modern
+  $ setconfig paths.default=test:e1 ui.ssh=false commitcloud.usehttpupload=true
+
+Prepare Repo:
+
+  $ newremoterepo
+  $ setconfig paths.default=test:e1
+  $ drawdag << 'EOS'
+  > B
+  > |
+  > A
+  > EOS
+
+  $ hg push -r $A --to master --create -q
+
+Upload the draft commit using the EdenApi upload endpoints of the eager repo:
+
+  $ hg cloud check -r $B
+  112478962961147124edd43549aedd1a335e44bf not uploaded
+
+  $ hg cloud upload
+  commitcloud: head '112478962961' hasn't been uploaded yet
+  edenapi: queue 1 commit for upload
+  edenapi: queue 1 file for upload
+  edenapi: uploaded 1 file
+  edenapi: queue 1 tree for upload
+  edenapi: uploaded 1 tree
+  edenapi: uploading commit '112478962961147124edd43549aedd1a335e44bf'...
+  edenapi: uploaded 1 changeset
+
+  $ hg cloud check -r $B
+  112478962961147124edd43549aedd1a335e44bf uploaded
+
+  $ hg cloud upload
+  commitcloud: nothing to upload
+
+Pull the uploaded commit into another repo:
+
+  $ newremoterepo
+  $ setconfig paths.default=test:e1
+  $ hg pull -q -r $B
+  $ hg log -r $B -T '{desc}\n'
+  B
+  $ hg cat -r $B A B
+  AB (no-eol)
@@ -0,0 +1,41 @@
+#chg-compatible
+  $ configure modernclient
+
+  $ newserver master
+  $ setconfig extensions.lfs= lfs.url=file:$TESTTMP/lfs-server remotefilelog.lfs=True
+
+  $ clone master shallow --noupdate
+  $ switchrepo shallow
+  $ setconfig extensions.lfs= lfs.url=file:$TESTTMP/lfs-server lfs.threshold=10B remotefilelog.lfs=True
+
+  $ echo "THIS IS AN LFS BLOB" > x
+  $ echo "THIS IS ANOTHER LFS BLOB" > y
+  $ hg commit -qAm xy
+  $ hg push -q --to master --create
+
+  $ clone master shallow2 --noupdate
+  $ switchrepo shallow2
+  $ setconfig lfs.url=file:$TESTTMP/lfs-server lfs.threshold=10B remotefilelog.lfs=True
+  $ hg up -q master
+  $ cat x y
+  THIS IS AN LFS BLOB
+  THIS IS ANOTHER LFS BLOB
+
+# Without lfs.cachelimit, nothing is evicted.
+  $ hg debuglfsprune
+  evicted 0 LFS blobs (0 bytes)
+
+# The shared LFS cache is within its limit.
+  $ setconfig lfs.cachelimit=1GB
+  $ hg debuglfsprune
+  evicted 0 LFS blobs (0 bytes)
+
+  $ hg debuglfsprune --config lfs.cachelimit=0
+  evicted 2 LFS blobs (45 bytes)
+
+# Evicted blobs are fetched again when needed.
+  $ hg up -q null
+  $ hg up -q master
+  $ cat x y
+  THIS IS AN LFS BLOB
+  THIS IS ANOTHER LFS BLOB
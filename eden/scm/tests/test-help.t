@@ -1009,6 +1009,7 @@ Test list of internal help commands
    debuginternals
                  list or export internal files
    debugknown    test whether node ids are known to a repo
+   debuglfsprune evict least recently used blobs from the shared LFS cache
    debuglocks    show or modify state of locks
    debugmakepublic
                  make revisions public
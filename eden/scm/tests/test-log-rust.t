@@ -0,0 +1,83 @@
+#chg-compatible
+
+The native log command should print the same output as the Python one.
+
+  $ setconfig format.use-segmented-changelog=1
+  $ newrepo
+  $ drawdag << 'EOS'
+  > E
+  > |
+  > D
+  > |\
+  > B C
+  > |/
+  > A
+  > EOS
+  $ hg bookmark -r $B v1
+  $ hg bookmark -r $E v2
+  $ hg up -q $D
+
+devel.rust-log-no-fallback makes the native log abort instead of falling back
+to Python, so the comparisons below exercise the native implementation:
+
+  $ compare() {
+  >   hg log "$@" > $TESTTMP/python.out
+  >   hg --config experimental.rust-log=true --config devel.rust-log-no-fallback=true log "$@" > $TESTTMP/rust.out
+  >   cmp $TESTTMP/python.out $TESTTMP/rust.out && echo same
+  > }
+
+  $ hg --config experimental.rust-log=true --config devel.rust-log-no-fallback=true log -p
+  abort: --patch is not supported by native log
+  [255]
+  $ hg --config experimental.rust-log=true log --removed -r $A -T '{desc}\n'
+  A
+
+Default output:
+
+  $ compare
+  same
+  $ compare -q
+  same
+  $ compare -v
+  same
+
+Revisions, limits and filters:
+
+  $ compare -r $B
+  same
+  $ compare -r "$A::$D"
+  same
+  $ compare -r . -f
+  same
+  $ compare -l 2
+  same
+  $ compare -M
+  same
+  $ compare -m
+  same
+  $ compare -k c
+  same
+  $ compare -u test
+  same
+  $ compare B
+  same
+
+Templates:
+
+  $ compare -T '{node|short} {desc|firstline} {bookmarks}\n'
+  same
+  $ compare -T '{author|user} {date|isodate} {files}\n'
+  same
+
+Graphs:
+
+  $ compare -G
+  same
+  $ compare -G -T '{desc} {bookmarks}'
+  same
+  $ compare -G -r 'v1 + v2' -T '{desc}'
+  same
+  $ compare -G -r 'v1 + v2' -T '{desc}' --config log.simplify-grandparents=0
+  same
+  $ compare -G -T '{desc}' --config experimental.graph.renderer=ascii
+  same
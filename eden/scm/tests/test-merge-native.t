@@ -0,0 +1,38 @@
+#chg-compatible
+  $ setconfig experimental.nativerebase=true
+  $ newserver server
+
+  $ newremoterepo repo
+
+  $ printf 'a\nb\nc\n' > a
+  $ hg commit -Aqm base
+  $ printf 'A\nb\nc\n' > a
+  $ hg commit -m local
+  $ hg up -q 'desc(base)'
+  $ printf 'a\nb\nC\n' > a
+  $ hg commit -m other
+
+Changes to different lines of a file are merged natively, and Python writes
+the merged content without merging the file again:
+
+  $ hg up -q 'desc(local)'
+  $ hg graft -r 'desc(other)' --debug 2>&1 | grep -E 'native merge|-> gm|\(premerge\)'
+  Using native merge
+   a: versions differ -> gm
+  $ cat a
+  A
+  b
+  C
+  $ hg log -r . -T '{desc}\n'
+  other
+
+Conflicting changes can't be merged natively, so Python computes the actions:
+
+  $ hg up -q 'desc(base)'
+  $ printf 'a\nB\nc\n' > a
+  $ hg commit -m conflict
+  $ hg up -q 'desc(local)'
+  $ printf 'a\nb2\nc\n' > a
+  $ hg commit -m local2
+  $ hg graft -r 'desc(conflict)' --debug 2>&1 | grep -E 'native merge|-> gm|\(premerge\)'
+   a: versions differ -> m (premerge)
@@ -0,0 +1,49 @@
+#chg-compatible
+
+Check bookmark changes committed to metalog by another process while a
+transaction is running are kept if metalog.resolve-conflicts is set.
+
+The `racebookmark` command starts a transaction and sets a bookmark. Before
+the transaction closes, another metalog instance commits a different bookmark,
+like a concurrent `hg` process would.
+
+  $ configure modern
+
+  $ cat > ext.py << 'EOF'
+  > import bindings
+  > from edenscm.mercurial import registrar
+  > from edenscm.mercurial.node import hex
+  > cmdtable = {}
+  > command = registrar.command(cmdtable)
+  > @command('racebookmark', [], 'NAME OTHER')
+  > def racebookmark(ui, repo, name, other):
+  >     node = repo["."].node()
+  >     with repo.lock(), repo.transaction("racebookmark") as tr:
+  >         repo._bookmarks.applychanges(repo, tr, [(name, node)])
+  >         ml = bindings.metalog.metalog(repo.svfs.join("metalog"))
+  >         line = "%s %s\n" % (hex(node), other)
+  >         ml["bookmarks"] = (ml["bookmarks"] or b"") + line.encode("utf-8")
+  >         ml.commit("other")
+  > EOF
+
+  $ setconfig extensions.ext="$TESTTMP/ext.py"
+
+Without resolving, the transaction fails:
+
+  $ newrepo
+  $ touch a
+  $ hg commit -Aqm A
+  $ hg racebookmark this other 2>&1 | grep -o 'conflict detected'
+  conflict detected
+  $ hg bookmarks -T '{bookmark}\n'
+  other
+
+With resolving, both bookmarks are kept:
+
+  $ newrepo
+  $ touch a
+  $ hg commit -Aqm A
+  $ hg racebookmark this other --config metalog.resolve-conflicts=true
+  $ hg bookmarks -T '{bookmark}\n'
+  other
+  this